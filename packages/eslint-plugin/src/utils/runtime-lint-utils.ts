@@ -21,6 +21,12 @@ import {
   ProjectRootMappings,
 } from 'nx/src/project-graph/utils/find-project-for-path';
 import { readFileIfExisting } from 'nx/src/utils/fileutils';
+import {
+  BoundaryNode,
+  BoundaryViolation,
+  checkModuleBoundaries,
+  ProjectImport,
+} from 'nx/src/native';
 import { getPath, pathExists } from './graph-utils';
 
 export type Deps = { [projectName: string]: ProjectGraphDependency[] };
@@ -422,6 +428,35 @@ function mapGlobToRegExp(importDefinition: string): RegExp {
   return new RegExp(`^${new RegExp(mappedWildcards).source}$`);
 }
 
+/**
+ * Checks the imports between projects against the dependency constraints in
+ * one native call, instead of checking the constraints of each import while
+ * its file is linted
+ * @param projectGraph
+ * @param imports the imports of the files of the workspace
+ * @param depConstraints
+ * @returns the violations of the constraints, in the order of the imports
+ */
+export function findModuleBoundaryViolations(
+  projectGraph: ProjectGraph,
+  imports: ProjectImport[],
+  depConstraints: DepConstraint[]
+): BoundaryViolation[] {
+  const nodes: Record<string, BoundaryNode> = {};
+  for (const [name, node] of Object.entries(projectGraph.nodes)) {
+    nodes[name] = { tags: node.data.tags };
+  }
+  const externalNodes = projectGraph.externalNodes ?? {};
+  for (const [name, node] of Object.entries(externalNodes)) {
+    nodes[name] = { packageName: node.data.packageName };
+  }
+  const dependencies: Record<string, string[]> = {};
+  for (const [source, deps] of Object.entries(projectGraph.dependencies)) {
+    dependencies[source] = deps.map((dep) => dep.target);
+  }
+  return checkModuleBoundaries(nodes, dependencies, imports, depConstraints);
+}
+
 /**
  * Verifies whether the given node has a builder target
  * @param projectGraph the node to verify
@@ -0,0 +1,66 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use nx::native::glob::{build_glob_matcher, build_glob_set, clear_glob_cache, GlobOptions};
+
+/// Globs like the ones of the default named inputs of a project
+const GLOBS: &[&str] = &[
+    "libs/project/**/*",
+    "!libs/project/**/?(*.)+(spec|test).[jt]s?(x)?(.snap)",
+    "!libs/project/tsconfig.spec.json",
+    "!libs/project/jest.config.[jt]s",
+    "!libs/project/src/test-setup.[jt]s",
+    "!libs/project/.eslintrc.json",
+];
+
+fn paths() -> Vec<String> {
+    (0..10_000)
+        .map(|i| match i % 4 {
+            0 => format!("libs/project/src/lib/file-{i}.ts"),
+            1 => format!("libs/project/src/lib/file-{i}.spec.ts"),
+            2 => format!("libs/other-{i}/src/index.ts"),
+            _ => format!("apps/app/src/app/component-{i}.tsx"),
+        })
+        .collect()
+}
+
+fn compile(c: &mut Criterion) {
+    c.bench_function("compile globs", |b| {
+        b.iter(|| {
+            clear_glob_cache();
+            build_glob_set(black_box(GLOBS)).unwrap()
+        })
+    });
+    c.bench_function("compile cached globs", |b| {
+        b.iter(|| build_glob_set(black_box(GLOBS)).unwrap())
+    });
+    c.bench_function("compile ordered globs", |b| {
+        b.iter(|| {
+            clear_glob_cache();
+            build_glob_matcher(black_box(GLOBS), true, GlobOptions::default()).unwrap()
+        })
+    });
+}
+
+fn matching(c: &mut Criterion) {
+    let paths = paths();
+    let glob_set = build_glob_set(GLOBS).unwrap();
+    c.bench_function("match globs", |b| {
+        b.iter(|| {
+            paths
+                .iter()
+                .filter(|path| glob_set.is_match(black_box(path.as_str())))
+                .count()
+        })
+    });
+    let ordered = build_glob_matcher(GLOBS, true, GlobOptions::default()).unwrap();
+    c.bench_function("match ordered globs", |b| {
+        b.iter(|| {
+            paths
+                .iter()
+                .filter(|path| ordered.is_match(black_box(path.as_str())))
+                .count()
+        })
+    });
+}
+
+criterion_group!(benches, compile, matching);
+criterion_main!(benches);
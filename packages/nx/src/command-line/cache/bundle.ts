@@ -0,0 +1,86 @@
+import { resolve } from 'path';
+import { IS_WASM, NxCache } from '../../native';
+import { cacheDir } from '../../utils/cache-directory';
+import { getDbConnection } from '../../utils/db-connection';
+import { output } from '../../utils/output';
+import { workspaceRoot } from '../../utils/workspace-root';
+import {
+  CacheExportCommandOptions,
+  CacheImportCommandOptions,
+} from './command-object';
+
+export async function cacheExportHandler(
+  args: CacheExportCommandOptions
+): Promise<number> {
+  if (!isCacheDbEnabled('exported')) {
+    return 1;
+  }
+
+  const file = resolve(process.cwd(), args.file);
+  try {
+    const cache = new NxCache(workspaceRoot, cacheDir, getDbConnection());
+    const exported = cache.exportCache(args.hashes ?? null, file);
+    const missing = (args.hashes ?? []).filter(
+      (hash) => !exported.includes(hash)
+    );
+    output.success({
+      title: `Exported ${exported.length} cache entries to ${args.file}`,
+      bodyLines: missing.length
+        ? [`These entries are not in the cache: ${missing.join(', ')}`]
+        : [],
+    });
+    return 0;
+  } catch (e) {
+    output.error({
+      title: 'Unable to export the cache',
+      bodyLines: [e.message],
+    });
+    return 1;
+  }
+}
+
+export async function cacheImportHandler(
+  args: CacheImportCommandOptions
+): Promise<number> {
+  if (!isCacheDbEnabled('imported')) {
+    return 1;
+  }
+
+  const file = resolve(process.cwd(), args.file);
+  try {
+    const cache = new NxCache(workspaceRoot, cacheDir, getDbConnection());
+    const result = cache.importCache(file);
+    const bodyLines = [];
+    if (result.skipped.length) {
+      bodyLines.push(`${result.skipped.length} entries were already cached.`);
+    }
+    if (result.corrupted.length) {
+      bodyLines.push(
+        `These entries were corrupted and were not imported: ${result.corrupted.join(
+          ', '
+        )}`
+      );
+    }
+    output.success({
+      title: `Imported ${result.imported.length} cache entries from ${args.file}`,
+      bodyLines,
+    });
+    return 0;
+  } catch (e) {
+    output.error({
+      title: 'Unable to import the cache',
+      bodyLines: [e.message],
+    });
+    return 1;
+  }
+}
+
+function isCacheDbEnabled(action: string) {
+  if (IS_WASM || process.env.NX_DISABLE_DB === 'true') {
+    output.error({
+      title: `The cache can only be ${action} when the Nx database is enabled`,
+    });
+    return false;
+  }
+  return true;
+}
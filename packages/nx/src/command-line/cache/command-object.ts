@@ -0,0 +1,108 @@
+import { CommandModule, showHelp } from 'yargs';
+
+export type CacheGcCommandOptions = {
+  maxSize?: string;
+  maxAge?: string;
+};
+
+export type CacheExportCommandOptions = {
+  file: string;
+  hashes?: string[];
+};
+
+export type CacheImportCommandOptions = {
+  file: string;
+};
+
+export const yargsCacheCommand: CommandModule = {
+  command: 'cache',
+  describe: 'Manage the local cache of task outputs.',
+  builder: (yargs) =>
+    yargs
+      .command(cacheGcCommand)
+      .command(cacheExportCommand)
+      .command(cacheImportCommand)
+      .demandCommand()
+      .example(
+        '$0 cache gc --max-size 10GB',
+        'Remove the least recently used cache entries until the cache is smaller than 10GB'
+      )
+      .example(
+        '$0 cache export cache.tar.zst',
+        'Bundle every entry of the local cache into cache.tar.zst, which can be imported on an other machine'
+      )
+      .example(
+        '$0 cache import cache.tar.zst',
+        'Add the entries of cache.tar.zst to the local cache'
+      ),
+  handler: async () => {
+    showHelp();
+    process.exit(1);
+  },
+};
+
+const cacheGcCommand: CommandModule<
+  Record<string, unknown>,
+  CacheGcCommandOptions
+> = {
+  command: 'gc',
+  describe:
+    'Removes the entries of the local cache that were not used for longer than the max age, and the least recently used entries until the cache is smaller than the max size.',
+  builder: (yargs) =>
+    yargs
+      .option('maxSize', {
+        type: 'string',
+        description:
+          'The max size of the cache (e.g. 10GB). Defaults to NX_CACHE_MAX_SIZE or to maxCacheSize in nx.json.',
+      })
+      .option('maxAge', {
+        type: 'string',
+        description:
+          'The max age of the cache entries (e.g. 7d). Defaults to NX_CACHE_MAX_AGE, to maxCacheAge in nx.json or to 7 days.',
+      }),
+  handler: async (args) =>
+    process.exit(await (await import('./gc')).cacheGcHandler(args)),
+};
+
+const cacheExportCommand: CommandModule<
+  Record<string, unknown>,
+  CacheExportCommandOptions
+> = {
+  command: 'export <file>',
+  describe:
+    'Bundles entries of the local cache and their metadata into a single archive, e.g. to seed the cache of CI agents that can not reach a remote cache.',
+  builder: (yargs) =>
+    yargs
+      .positional('file', {
+        type: 'string',
+        description: 'The archive to create (e.g. cache.tar.zst).',
+        demandOption: true,
+      })
+      .option('hashes', {
+        type: 'string',
+        array: true,
+        description:
+          'The hashes of the entries to export. Every entry of the cache is exported by default.',
+        coerce: (hashes: string[]) =>
+          hashes.flatMap((hash) => hash.split(',')).filter(Boolean),
+      }),
+  handler: async (args) =>
+    process.exit(await (await import('./bundle')).cacheExportHandler(args)),
+};
+
+const cacheImportCommand: CommandModule<
+  Record<string, unknown>,
+  CacheImportCommandOptions
+> = {
+  command: 'import <file>',
+  describe:
+    'Adds the entries of an archive created by nx cache export to the local cache. Entries that are already cached are skipped.',
+  builder: (yargs) =>
+    yargs.positional('file', {
+      type: 'string',
+      description: 'The archive to import.',
+      demandOption: true,
+    }),
+  handler: async (args) =>
+    process.exit(await (await import('./bundle')).cacheImportHandler(args)),
+};
@@ -0,0 +1,52 @@
+import { IS_WASM, NxCache } from '../../native';
+import { cacheDir } from '../../utils/cache-directory';
+import { getCacheGcOptions } from '../../utils/cache-gc';
+import { getDbConnection } from '../../utils/db-connection';
+import { output } from '../../utils/output';
+import { workspaceRoot } from '../../utils/workspace-root';
+import { CacheGcCommandOptions } from './command-object';
+
+export async function cacheGcHandler(
+  args: CacheGcCommandOptions
+): Promise<number> {
+  if (IS_WASM || process.env.NX_DISABLE_DB === 'true') {
+    output.error({
+      title: 'The cache can only be cleaned up when the Nx database is enabled',
+    });
+    return 1;
+  }
+
+  const defaults = getCacheGcOptions();
+  try {
+    const cache = new NxCache(workspaceRoot, cacheDir, getDbConnection());
+    const result = cache.collectGarbage({
+      maxSize: args.maxSize ?? defaults.maxSize,
+      maxAge: args.maxAge ?? defaults.maxAge,
+    });
+    output.success({
+      title: `Removed ${result.removedEntries} cache entries`,
+      bodyLines: [
+        `Freed ${formatBytes(result.freedBytes)}, ${formatBytes(
+          result.remainingBytes
+        )} remain in the cache.`,
+      ],
+    });
+    return 0;
+  } catch (e) {
+    output.error({
+      title: 'Unable to clean up the cache',
+      bodyLines: [e.message],
+    });
+    return 1;
+  }
+}
+
+function formatBytes(bytes: number) {
+  const units = ['B', 'KB', 'MB', 'GB', 'TB'];
+  let unit = 0;
+  while (bytes >= 1024 && unit < units.length - 1) {
+    bytes /= 1024;
+    unit++;
+  }
+  return `${unit === 0 ? bytes : bytes.toFixed(1)}${units[unit]}`;
+}
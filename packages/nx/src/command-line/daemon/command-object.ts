@@ -19,5 +19,17 @@ function withDaemonOptions(yargs: Argv): Argv {
     .option('stop', {
       type: 'boolean',
       default: false,
+    })
+    .option('health', {
+      type: 'boolean',
+      describe:
+        'Prints a health report of the running daemon, and restarts it when it has degraded.',
+      default: false,
+    })
+    .option('set-log-level', {
+      type: 'string',
+      describe:
+        'Changes the level of the native logs of a module in the running daemon, e.g. nx::native::watch=trace.',
+      requiresArg: true,
     });
 }
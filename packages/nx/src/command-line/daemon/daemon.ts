@@ -1,5 +1,8 @@
+import { join } from 'path';
 import type { Arguments } from 'yargs';
+import type { DaemonDiagnostics } from '../../native';
 import { DAEMON_OUTPUT_LOG_FILE } from '../../daemon/tmp-dir';
+import { workspaceDataDirectory } from '../../utils/cache-directory';
 import { output } from '../../utils/output';
 import { generateDaemonHelpOutput } from '../../daemon/client/generate-help-output';
 
@@ -19,7 +22,125 @@ export async function daemonHandler(args: Arguments) {
     const { daemonClient } = await import('../../daemon/client/client');
     await daemonClient.stop();
     output.log({ title: 'Daemon Server - Stopped' });
+  } else if (args.health) {
+    await printDaemonHealth();
+  } else if (args.setLogLevel) {
+    await setDaemonLogLevel(args.setLogLevel as string);
   } else {
     console.log(generateDaemonHelpOutput());
   }
 }
+
+async function setDaemonLogLevel(directive: string) {
+  const [module, level] = directive.split('=');
+  if (!module || !level) {
+    output.error({
+      title: `Invalid log level: ${directive}`,
+      bodyLines: [
+        'Pass the module and its level, e.g. --set-log-level nx::native::watch=trace',
+      ],
+    });
+    process.exit(1);
+  }
+
+  const { daemonClient } = await import('../../daemon/client/client');
+  if (!(await daemonClient.isServerAvailable())) {
+    output.error({
+      title: 'Daemon Server - Not running',
+      bodyLines: ['Start it with nx daemon --start'],
+    });
+    process.exit(1);
+  }
+
+  const filter = await daemonClient.setLogLevel(module, level);
+  output.log({
+    title: `Daemon Server - Logging ${module} at ${level}`,
+    bodyLines: [
+      `${output.dim('Native logs are filtered with')} ${filter}`,
+      `${output.dim('and can be found here:')} ${join(
+        workspaceDataDirectory,
+        'logs',
+        'daemon.log'
+      )}`,
+    ],
+  });
+}
+
+async function printDaemonHealth() {
+  const { daemonClient } = await import('../../daemon/client/client');
+  if (!(await daemonClient.isServerAvailable())) {
+    output.error({
+      title: 'Daemon Server - Not running',
+      bodyLines: ['Start it with nx daemon --start'],
+    });
+    process.exit(1);
+  }
+
+  let degradations: string[];
+  try {
+    const diagnostics = await daemonClient.getDiagnostics();
+    output.log({
+      title: 'Daemon Server - Health',
+      bodyLines: formatDiagnostics(diagnostics),
+    });
+    degradations = diagnostics.degradations;
+  } catch (e) {
+    degradations = [`The daemon could not be diagnosed: ${e.message}`];
+  }
+  if (degradations.length === 0) {
+    return;
+  }
+
+  output.warn({
+    title: 'Daemon Server - Degraded, restarting it',
+    bodyLines: degradations,
+  });
+  await daemonClient.stop();
+  const pid = await daemonClient.startInBackground();
+  output.log({
+    title: `Daemon Server - Restarted in a background process (ID: ${pid})`,
+  });
+}
+
+function formatDiagnostics(diagnostics: DaemonDiagnostics): string[] {
+  const { watcher, db, cache, memory, recentErrors } = diagnostics;
+  const lines = [
+    `${output.dim('File watcher:')} ${
+      watcher.watching ? 'watching' : 'not watching'
+    } ${watcher.watchedRoots} roots (${watcher.polledRoots} polled), ${
+      watcher.events
+    } events in ${watcher.eventBatches} batches, ${
+      watcher.droppedEvents
+    } dropped, ${watcher.errors} errors`,
+    `${output.dim('Database:')} ${
+      db.connected
+        ? `connected, ${megabytes(db.size ?? 0)}`
+        : db.error ?? 'not connected'
+    }`,
+  ];
+  if (cache) {
+    lines.push(
+      `${output.dim('Cache:')} ${cache.entries} entries, ${megabytes(
+        cache.size
+      )}`
+    );
+  }
+  if (memory !== undefined && memory !== null) {
+    lines.push(`${output.dim('Memory:')} ${megabytes(memory)}`);
+  }
+  if (recentErrors.length > 0) {
+    lines.push(output.dim('Recent errors:'));
+    for (const error of recentErrors) {
+      lines.push(
+        `  ${new Date(error.timestamp).toISOString()} ${error.level} ${
+          error.target
+        }: ${error.message}`
+      );
+    }
+  }
+  return lines;
+}
+
+function megabytes(bytes: number) {
+  return `${(bytes / 1024 / 1024).toFixed(1)} MB`;
+}
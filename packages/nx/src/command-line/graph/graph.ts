@@ -45,7 +45,14 @@ import {
 import { allFileData } from '../../utils/all-file-data';
 import { splitArgsIntoNxArgsAndOverrides } from '../../utils/command-line-utils';
 import { NxJsonConfiguration } from '../../config/nx-json';
-import { HashPlanner, transferProjectGraph } from '../../native';
+import {
+  HashPlanner,
+  IS_WASM,
+  TaskGraphRunResult,
+  TaskGraphVisualization,
+  transferProjectGraph,
+  visualizeTaskGraph,
+} from '../../native';
 import { transformProjectGraphForRust } from '../../native/transform-objects';
 import { getAffectedGraphNodes } from '../affected/affected';
 import { readFileMapCache } from '../../project-graph/nx-deps-cache';
@@ -55,6 +62,7 @@ import { ConfigurationSourceMaps } from '../../project-graph/utils/project-confi
 import { createTaskHasher } from '../../hasher/create-task-hasher';
 import { ProjectGraphError } from '../../project-graph/error-types';
 import { isNxCloudUsed } from '../../utils/nx-cloud-utils';
+import { getTaskHistory } from '../../utils/task-history';
 
 export interface GraphError {
   message: string;
@@ -610,6 +618,15 @@ async function startServer(
       return;
     }
 
+    if (sanitizePath === 'task-graph-visualization.json') {
+      const taskId = parsedUrl.searchParams.get('taskId');
+      res.writeHead(200, { 'Content-Type': 'application/json' });
+      res.end(
+        JSON.stringify({ [taskId]: await getTaskGraphVisualization(taskId) })
+      );
+      return;
+    }
+
     if (sanitizePath === 'source-maps.json') {
       res.writeHead(200, { 'Content-Type': 'application/json' });
       res.end(JSON.stringify(currentSourceMapsClientResponse));
@@ -1029,6 +1046,45 @@ function createTaskId(
   }
 }
 
+/**
+ * The nodes, edges and critical path of the task graph of a task, weighted by the durations of previous runs
+ */
+async function getTaskGraphVisualization(
+  taskId: string
+): Promise<TaskGraphVisualization | null> {
+  const { taskGraphs } = await createTaskGraphClientResponse(false);
+  const taskGraph = taskGraphs[taskId];
+  if (!taskGraph) {
+    return null;
+  }
+  return visualizeTaskGraph(
+    taskGraph,
+    await getEstimatedRunResults(taskGraph)
+  );
+}
+
+/**
+ * The average durations of the tasks in the task history, when the Nx database is enabled
+ */
+async function getEstimatedRunResults(
+  taskGraph: TaskGraph
+): Promise<Record<string, TaskGraphRunResult>> {
+  if (IS_WASM || process.env.NX_DISABLE_DB === 'true') {
+    return {};
+  }
+  const tasks = Object.values(taskGraph.tasks);
+  const timings = await getTaskHistory().getEstimatedTaskTimings(
+    tasks.map((task) => task.target)
+  );
+  const results: Record<string, TaskGraphRunResult> = {};
+  for (const task of tasks) {
+    if (timings[task.id] !== undefined) {
+      results[task.id] = { duration: timings[task.id] };
+    }
+  }
+  return results;
+}
+
 async function getExpandedTaskInputs(
   taskId: string
 ): Promise<Record<string, string[]>> {
@@ -1064,6 +1120,10 @@ function expandInputs(
   const externalInputs: string[] = [];
   const otherInputs: string[] = [];
   inputs.forEach((input) => {
+    // file sets that are matched case insensitively are prefixed with caseInsensitive:
+    if (input.startsWith('caseInsensitive:')) {
+      input = input.substring('caseInsensitive:'.length);
+    }
     // grouped workspace inputs look like workspace:[pattern,otherPattern]
     if (input.startsWith('workspace:[')) {
       const inputs = input.substring(11, input.length - 1).split(',');
@@ -1154,6 +1214,10 @@ export interface GraphJson {
    * The plans for hashing a task in the task graph
    */
   taskPlans?: Record<string, string[]>;
+  /**
+   * The critical path of the graph of tasks, weighted by the durations of previous runs
+   */
+  taskGraphVisualization?: TaskGraphVisualization;
   /**
    * The project graph
    */
@@ -1227,6 +1291,10 @@ async function createJsonOutput(
     let tasks = Object.values(taskGraph.tasks);
     const hashes = await hasher.hashTasks(tasks, taskGraph);
     response.tasks = taskGraph;
+    response.taskGraphVisualization = visualizeTaskGraph(
+      taskGraph,
+      await getEstimatedRunResults(taskGraph)
+    );
     response.taskPlans = tasks.reduce((acc, task, index) => {
       acc[task.id] = Object.keys(hashes[index].details.nodes).sort();
       return acc;
@@ -12,6 +12,7 @@ import {
   yargsConnectCommand,
   yargsViewLogsCommand,
 } from './connect/command-object';
+import { yargsCacheCommand } from './cache/command-object';
 import { yargsDaemonCommand } from './daemon/command-object';
 import { yargsGraphCommand } from './graph/command-object';
 import { yargsExecCommand } from './exec/command-object';
@@ -70,6 +71,7 @@ export const commandsObject = yargs
   .command(yargsAffectedLintCommand)
   .command(yargsAffectedTestCommand)
   .command(yargsAffectedGraphCommand)
+  .command(yargsCacheCommand)
   .command(yargsConnectCommand)
   .command(yargsDaemonCommand)
   .command(yargsGraphCommand)
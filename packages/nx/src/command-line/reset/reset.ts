@@ -1,8 +1,10 @@
-import { rmSync } from 'fs-extra';
+import { readdirSync, rmSync } from 'fs-extra';
+import { join } from 'path';
 import { daemonClient } from '../../daemon/client/client';
 import { cacheDir, workspaceDataDirectory } from '../../utils/cache-directory';
 import { output } from '../../utils/output';
 import { getNativeFileCacheLocation } from '../../native/native-file-cache-location';
+import { FileLock, IS_WASM } from '../../native';
 import { ResetCommandOptions } from './command-object';
 
 import { getCloudClient } from '../../nx-cloud/utilities/client';
@@ -14,6 +16,9 @@ const INCREMENTAL_BACKOFF_MAX_DURATION = 5000;
 // If an operation fails, wait 100ms before first retry.
 const INCREMENTAL_BACKOFF_FIRST_DELAY = 100;
 
+// Held by the native cache of every Nx process while it writes or restores entries.
+const CACHE_LOCK_FILE = 'nx-cache.lock';
+
 export async function resetHandler(args: ResetCommandOptions) {
   let errors = [];
 
@@ -49,8 +54,11 @@ export async function resetHandler(args: ResetCommandOptions) {
   if (all || args.onlyCache) {
     try {
       await cleanupCacheEntries();
-    } catch {
+    } catch (e) {
       errors.push('Failed to clean up the cache directory.');
+      if (e?.message) {
+        errors.push(e.message);
+      }
     }
   }
   if (all || args.onlyWorkspaceData) {
@@ -93,14 +101,44 @@ async function resetCloudClient() {
   } catch {}
 }
 
-function cleanupCacheEntries() {
-  return incrementalBackoff(
-    INCREMENTAL_BACKOFF_FIRST_DELAY,
-    INCREMENTAL_BACKOFF_MAX_DURATION,
-    () => {
-      rmSync(cacheDir, { recursive: true, force: true });
-    }
+async function cleanupCacheEntries() {
+  if (IS_WASM) {
+    return incrementalBackoff(
+      INCREMENTAL_BACKOFF_FIRST_DELAY,
+      INCREMENTAL_BACKOFF_MAX_DURATION,
+      () => {
+        rmSync(cacheDir, { recursive: true, force: true });
+      }
+    );
+  }
+
+  // waits for the other Nx processes to finish writing and restoring cache entries,
+  // the lock file itself is removed once it is released, since it can't be removed while it is open on Windows
+  const lock = FileLock.acquire(
+    join(cacheDir, CACHE_LOCK_FILE),
+    true,
+    INCREMENTAL_BACKOFF_MAX_DURATION
   );
+  try {
+    await incrementalBackoff(
+      INCREMENTAL_BACKOFF_FIRST_DELAY,
+      INCREMENTAL_BACKOFF_MAX_DURATION,
+      () => {
+        for (const entry of readdirSync(cacheDir)) {
+          if (entry !== CACHE_LOCK_FILE) {
+            rmSync(join(cacheDir, entry), { recursive: true, force: true });
+          }
+        }
+      }
+    );
+  } finally {
+    lock.release();
+  }
+  try {
+    rmSync(cacheDir, { recursive: true, force: true });
+  } catch {
+    // another process started using the cache again
+  }
 }
 
 function cleanupNativeFileCache() {
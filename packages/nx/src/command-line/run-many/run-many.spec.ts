@@ -97,6 +97,18 @@ describe('run-many', () => {
       expect(projects).toContain('proj2');
     });
 
+    it('should filter projects with expressions', () => {
+      const projects = projectsToRun(
+        {
+          targets: ['test'],
+          projects: ['tag:theme* && !tag:api'],
+        },
+        projectGraph
+      ).map(({ name }) => name);
+      expect(projects).not.toContain('proj1');
+      expect(projects).toContain('proj2');
+    });
+
     it('should filter projects by name and tag', () => {
       let projects = projectsToRun(
         {
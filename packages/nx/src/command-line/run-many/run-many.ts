@@ -15,7 +15,7 @@ import { createProjectGraphAsync } from '../../project-graph/project-graph';
 import { TargetDependencyConfig } from '../../config/workspace-json-project-json';
 import { readNxJson } from '../../config/configuration';
 import { output } from '../../utils/output';
-import { findMatchingProjects } from '../../utils/find-matching-projects';
+import { findProjectsMatchingExpressions } from '../../utils/find-matching-projects';
 import { workspaceConfigurationCheck } from '../../utils/workspace-configuration-check';
 import { generateGraph } from '../graph/graph';
 
@@ -96,7 +96,7 @@ export function projectsToRun(
       selectedProjects[projectName] = projectGraph.nodes[projectName];
     }
   } else {
-    const matchingProjects = findMatchingProjects(
+    const matchingProjects = findProjectsMatchingExpressions(
       nxArgs.projects,
       projectGraph.nodes
     );
@@ -118,7 +118,7 @@ export function projectsToRun(
     }
   }
 
-  const excludedProjects = findMatchingProjects(
+  const excludedProjects = findProjectsMatchingExpressions(
     nxArgs.exclude,
     selectedProjects
   );
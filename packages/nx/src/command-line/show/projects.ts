@@ -17,7 +17,7 @@ import {
   parseFiles,
   splitArgsIntoNxArgsAndOverrides,
 } from '../../utils/command-line-utils';
-import { findMatchingProjects } from '../../utils/find-matching-projects';
+import { findProjectsMatchingExpressions } from '../../utils/find-matching-projects';
 import { ShowProjectsOptions } from './command-object';
 
 export async function showProjectsHandler(
@@ -66,7 +66,10 @@ export async function showProjectsHandler(
   const selectedProjects = new Set(Object.keys(graph.nodes));
 
   if (args.exclude) {
-    const excludedProjects = findMatchingProjects(nxArgs.exclude, graph.nodes);
+    const excludedProjects = findProjectsMatchingExpressions(
+      nxArgs.exclude,
+      graph.nodes
+    );
     for (const excludedProject of excludedProjects) {
       selectedProjects.delete(excludedProject);
     }
@@ -90,7 +93,7 @@ function getGraphNodesMatchingPatterns(
   patterns: string[]
 ): ProjectGraph['nodes'] {
   const nodes: Record<string, ProjectGraphProjectNode> = {};
-  const matches = findMatchingProjects(patterns, graph.nodes);
+  const matches = findProjectsMatchingExpressions(patterns, graph.nodes);
   for (const match of matches) {
     nodes[match] = graph.nodes[match];
   }
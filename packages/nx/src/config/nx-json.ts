@@ -485,6 +485,21 @@ export interface NxJsonConfiguration<T = '*' | string[]> {
    */
   cacheDirectory?: string;
 
+  /**
+   * The least recently used entries of the local cache are removed when it is bigger than this (e.g. `10GB`).
+   */
+  maxCacheSize?: string;
+
+  /**
+   * Entries of the local cache that were not used for longer than this (e.g. `7d`) are removed.
+   */
+  maxCacheAge?: string;
+
+  /**
+   * Mixed into the hash of every task. Changing it invalidates every cached result, locally and in remote caches.
+   */
+  hashSalt?: string;
+
   /**
    * Set this to false to disable the daemon.
    */
@@ -81,6 +81,14 @@ export interface Task {
    * Determines if a given task should be parallelizable.
    */
   parallelism: boolean;
+
+  /**
+   * Limits on the memory (in MiB) and CPUs that the task can use
+   */
+  resourceLimits?: {
+    memory?: number;
+    cpus?: number;
+  };
 }
 
 /**
@@ -168,7 +168,7 @@ export type InputDefinition =
   | { input: string; projects: string | string[] }
   | { input: string; dependencies: true }
   | { input: string }
-  | { fileset: string }
+  | { fileset: string; caseInsensitive?: boolean }
   | { runtime: string }
   | { externalDependencies: string[] }
   | { dependentTasksOutputFiles: string; transitive?: boolean }
@@ -237,6 +237,16 @@ export interface TargetConfiguration<T = any> {
    */
   parallelism?: boolean;
 
+  /**
+   * Limits on the memory (in MiB) and CPUs that the task can use, so that it
+   * can't starve the other tasks that run in parallel.
+   * Enforced on Linux (cgroups v2) and Windows
+   */
+  resourceLimits?: {
+    memory?: number;
+    cpus?: number;
+  };
+
   /**
    * List of generators to run before the target to ensure the workspace
    * is up to date.
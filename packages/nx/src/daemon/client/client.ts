@@ -28,11 +28,22 @@ import {
   DaemonProjectGraphError,
   ProjectGraphError,
 } from '../../project-graph/error-types';
-import { IS_WASM, NxWorkspaceFiles, TaskRun, TaskTarget } from '../../native';
+import {
+  DaemonDiagnostics,
+  FlakinessWindow,
+  FlakyTarget,
+  IS_WASM,
+  NxWorkspaceFiles,
+  readSharedWorkspaceFiles,
+  TaskRun,
+  TaskTarget,
+} from '../../native';
 import { HandleGlobMessage } from '../message-types/glob';
 import {
   GET_NX_WORKSPACE_FILES,
+  GET_SHARED_NX_WORKSPACE_FILES,
   HandleNxWorkspaceFilesMessage,
+  HandleSharedNxWorkspaceFilesMessage,
 } from '../message-types/get-nx-workspace-files';
 import {
   GET_CONTEXT_FILE_DATA,
@@ -45,8 +56,10 @@ import {
 import { HASH_GLOB, HandleHashGlobMessage } from '../message-types/hash-glob';
 import {
   GET_ESTIMATED_TASK_TIMINGS,
+  GET_FLAKY_TARGETS,
   GET_FLAKY_TASKS,
   HandleGetEstimatedTaskTimings,
+  HandleGetFlakyTargets,
   HandleGetFlakyTasks,
   HandleRecordTaskRunsMessage,
   RECORD_TASK_RUNS,
@@ -72,6 +85,24 @@ import {
   FLUSH_SYNC_GENERATOR_CHANGES_TO_DISK,
   type HandleFlushSyncGeneratorChangesToDiskMessage,
 } from '../message-types/flush-sync-generator-changes-to-disk';
+import {
+  SET_LOG_LEVEL,
+  type HandleSetLogLevelMessage,
+} from '../message-types/set-log-level';
+import {
+  DAEMON_DIAGNOSTICS,
+  type HandleDaemonDiagnosticsMessage,
+} from '../message-types/daemon-diagnostics';
+import {
+  HandleNegotiateProtocolMessage,
+  NEGOTIATE_PROTOCOL,
+} from '../message-types/negotiate-protocol';
+import {
+  DAEMON_PROTOCOLS,
+  DaemonProtocol,
+  isBinaryProtocolEnabled,
+  parseMessage,
+} from '../message-protocol';
 
 const DAEMON_ENV_SETTINGS = {
   NX_PROJECT_GLOB_CACHE: 'false',
@@ -260,7 +291,7 @@ export class DaemonClient {
       ).listen(
         (message) => {
           try {
-            const parsedMessage = JSON.parse(message);
+            const parsedMessage = parseMessage(message);
             callback(null, parsedMessage);
           } catch (e) {
             callback(e, null);
@@ -307,6 +338,15 @@ export class DaemonClient {
     });
   }
 
+  outputsChangedWhileRecording(outputs: string[]): Promise<any> {
+    return this.sendToDaemonViaQueue({
+      type: 'OUTPUTS_CHANGED_WHILE_RECORDING',
+      data: {
+        outputs,
+      },
+    });
+  }
+
   glob(globs: string[], exclude?: string[]): Promise<string[]> {
     const message: HandleGlobMessage = {
       type: 'GLOB',
@@ -323,9 +363,15 @@ export class DaemonClient {
     return this.sendToDaemonViaQueue(message);
   }
 
-  getWorkspaceFiles(
+  async getWorkspaceFiles(
     projectRootMap: Record<string, string>
   ): Promise<NxWorkspaceFiles> {
+    if (isSharedFileMapEnabled()) {
+      const files = await this.getSharedWorkspaceFiles(projectRootMap);
+      if (files) {
+        return files;
+      }
+    }
     const message: HandleNxWorkspaceFilesMessage = {
       type: GET_NX_WORKSPACE_FILES,
       projectRootMap,
@@ -333,6 +379,33 @@ export class DaemonClient {
     return this.sendToDaemonViaQueue(message);
   }
 
+  /**
+   * Reads the workspace files from the file map the daemon publishes in shared memory, instead of receiving them
+   * over the socket. There are no files when the daemon could not publish them, or changed them since
+   */
+  private async getSharedWorkspaceFiles(
+    projectRootMap: Record<string, string>
+  ): Promise<NxWorkspaceFiles | null> {
+    const message: HandleSharedNxWorkspaceFilesMessage = {
+      type: GET_SHARED_NX_WORKSPACE_FILES,
+      projectRootMap,
+    };
+    try {
+      const { generation } = await this.sendToDaemonViaQueue(message);
+      if (typeof generation !== 'number') {
+        return null;
+      }
+      return readSharedWorkspaceFiles(
+        DAEMON_DIR_FOR_CURRENT_WORKSPACE,
+        projectRootMap,
+        generation
+      );
+    } catch {
+      // daemons of older versions do not publish the files
+      return null;
+    }
+  }
+
   getFilesInDirectory(dir: string): Promise<string[]> {
     const message: HandleGetFilesInDirectoryMessage = {
       type: GET_FILES_IN_DIRECTORY,
@@ -359,6 +432,15 @@ export class DaemonClient {
     return this.sendToDaemonViaQueue(message);
   }
 
+  getFlakyTargets(window: FlakinessWindow): Promise<FlakyTarget[]> {
+    const message: HandleGetFlakyTargets = {
+      type: GET_FLAKY_TARGETS,
+      window,
+    };
+
+    return this.sendToDaemonViaQueue(message);
+  }
+
   async getEstimatedTaskTimings(
     targets: TaskTarget[]
   ): Promise<Record<string, number>> {
@@ -419,6 +501,30 @@ export class DaemonClient {
     return this.sendToDaemonViaQueue(message);
   }
 
+  /**
+   * Changes the level of the native logs of a module in the running daemon.
+   * Resolves with the filter of the native log file of the daemon
+   */
+  setLogLevel(module: string, level: string): Promise<string> {
+    const message: HandleSetLogLevelMessage = {
+      type: SET_LOG_LEVEL,
+      module,
+      level,
+    };
+    return this.sendToDaemonViaQueue(message);
+  }
+
+  /**
+   * Reports the health of the file watcher, the database and the cache of the running daemon,
+   * with its memory, its last native errors and what has degraded
+   */
+  getDiagnostics(): Promise<DaemonDiagnostics> {
+    const message: HandleDaemonDiagnosticsMessage = {
+      type: DAEMON_DIAGNOSTICS,
+    };
+    return this.sendToDaemonViaQueue(message);
+  }
+
   async isServerAvailable(): Promise<boolean> {
     return new Promise((resolve) => {
       try {
@@ -511,6 +617,7 @@ export class DaemonClient {
         await this.startInBackground();
       }
       this.setUpConnection();
+      await this.negotiateProtocol();
       this._daemonStatus = DaemonStatus.CONNECTED;
       this._daemonReady();
     } else if (this._daemonStatus == DaemonStatus.CONNECTING) {
@@ -533,10 +640,34 @@ export class DaemonClient {
     });
   }
 
-  private handleMessage(serializedResult: string) {
+  /**
+   * Agrees with the daemon on the protocol of the connection. Daemons that do not support the negotiation
+   * answer it with an error, and the connection keeps the JSON protocol
+   */
+  private async negotiateProtocol() {
+    if (!isBinaryProtocolEnabled()) {
+      return;
+    }
+    try {
+      const { protocol } = await new Promise<{ protocol: DaemonProtocol }>(
+        (resolve, reject) => {
+          this.currentResolve = resolve;
+          this.currentReject = reject;
+          const message: HandleNegotiateProtocolMessage = {
+            type: NEGOTIATE_PROTOCOL,
+            protocols: DAEMON_PROTOCOLS,
+          };
+          this.socketMessenger.sendMessage(message);
+        }
+      );
+      this.socketMessenger.useProtocol(protocol);
+    } catch {}
+  }
+
+  private handleMessage(serializedResult: string | object) {
     try {
       performance.mark('json-parse-start');
-      const parsedResult = JSON.parse(serializedResult);
+      const parsedResult = parseMessage(serializedResult);
       performance.mark('json-parse-end');
       performance.measure(
         'deserialize daemon response',
@@ -554,6 +685,13 @@ export class DaemonClient {
         return this.currentResolve(parsedResult);
       }
     } catch (e) {
+      if (typeof serializedResult !== 'string') {
+        return this.currentReject(
+          daemonProcessException(
+            `Could not handle response from Nx daemon: ${e.message}`
+          )
+        );
+      }
       const endOfResponse =
         serializedResult.length > 300
           ? serializedResult.substring(serializedResult.length - 300)
@@ -643,6 +781,13 @@ export function isDaemonEnabled() {
   return daemonClient.enabled();
 }
 
+/**
+ * Large workspaces can read the file map from the daemon through shared memory with `NX_DAEMON_SHARED_FILE_MAP=true`
+ */
+function isSharedFileMapEnabled() {
+  return process.env.NX_DAEMON_SHARED_FILE_MAP === 'true' && !IS_WASM;
+}
+
 function isDocker() {
   try {
     statSync('/.dockerenv');
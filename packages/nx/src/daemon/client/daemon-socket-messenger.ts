@@ -1,7 +1,12 @@
 import { randomUUID } from 'crypto';
 import { Socket } from 'net';
 import { performance } from 'perf_hooks';
-import { consumeMessagesFromSocket } from '../../utils/consume-messages-from-socket';
+import {
+  consumeMessagesWithProtocol,
+  DaemonProtocol,
+  useProtocol,
+  writeMessage,
+} from '../message-protocol';
 
 export interface Message extends Record<string, any> {
   type: string;
@@ -12,19 +17,24 @@ export class DaemonSocketMessenger {
   constructor(private socket: Socket) {}
 
   async sendMessage(messageToDaemon: Message) {
-    this.socket.write(JSON.stringify(messageToDaemon));
-    // send EOT to indicate that the message has been fully written
-    this.socket.write(String.fromCodePoint(4));
+    writeMessage(this.socket, JSON.stringify(messageToDaemon));
+  }
+
+  /**
+   * Switches to the protocol the daemon agreed on, for the messages sent and received after its answer
+   */
+  useProtocol(protocol: DaemonProtocol) {
+    useProtocol(this.socket, protocol);
   }
 
   listen(
-    onData: (message: string) => void,
+    onData: (message: string | object) => void,
     onClose: () => void = () => {},
     onError: (err: Error) => void = (err) => {}
   ) {
     this.socket.on(
       'data',
-      consumeMessagesFromSocket(async (message) => {
+      consumeMessagesWithProtocol(this.socket, async (message) => {
         onData(message);
       })
     );
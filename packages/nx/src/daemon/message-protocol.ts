@@ -0,0 +1,105 @@
+import type { Socket } from 'net';
+import { encodeMessage, IS_WASM, MessageDecoder } from '../native';
+import { consumeMessagesFromSocket } from '../utils/consume-messages-from-socket';
+
+/**
+ * - `json`: JSON messages ending with an EOT character. Every connection starts with it
+ * - `msgpack`: frames made of the length of a message followed by the message as MessagePack,
+ *   encoded and decoded natively so large responses (like the project graph) are not parsed in JS
+ * - `compressed-msgpack`: the frames of `msgpack`, with large messages compressed natively
+ */
+export type DaemonProtocol = 'json' | 'msgpack' | 'compressed-msgpack';
+
+/**
+ * The protocols of the daemon, the preferred one first
+ */
+export const DAEMON_PROTOCOLS: DaemonProtocol[] = IS_WASM
+  ? ['msgpack', 'json']
+  : ['compressed-msgpack', 'msgpack', 'json'];
+
+const msgpackSockets = new WeakSet<Socket>();
+const compressedSockets = new WeakSet<Socket>();
+
+/**
+ * The binary protocol can be turned off with `NX_DAEMON_BINARY_PROTOCOL=false`
+ */
+export function isBinaryProtocolEnabled(): boolean {
+  return process.env.NX_DAEMON_BINARY_PROTOCOL !== 'false';
+}
+
+/**
+ * The protocol the daemon uses with the client, the first of the client's protocols it supports
+ */
+export function negotiateProtocol(protocols: unknown): DaemonProtocol {
+  return (
+    DAEMON_PROTOCOLS.find(
+      (protocol) =>
+        Array.isArray(protocols) &&
+        protocols.includes(protocol) &&
+        (protocol === 'json' || isBinaryProtocolEnabled())
+    ) ?? 'json'
+  );
+}
+
+/**
+ * Switches the messages written to and read from the socket to the protocol
+ */
+export function useProtocol(socket: Socket, protocol: DaemonProtocol) {
+  if (protocol === 'json') {
+    msgpackSockets.delete(socket);
+  } else {
+    msgpackSockets.add(socket);
+  }
+  if (protocol === 'compressed-msgpack') {
+    compressedSockets.add(socket);
+  } else {
+    compressedSockets.delete(socket);
+  }
+}
+
+/**
+ * Writes a message that is already serialized as JSON with the protocol of the socket
+ */
+export function writeMessage(
+  socket: Socket,
+  serializedMessage: string,
+  callback?: (err?: Error) => void
+) {
+  if (msgpackSockets.has(socket)) {
+    socket.write(
+      encodeMessage(serializedMessage, compressedSockets.has(socket)),
+      callback
+    );
+  } else {
+    // EOT indicates that the message has been fully written
+    socket.write(`${serializedMessage}${String.fromCodePoint(4)}`, callback);
+  }
+}
+
+/**
+ * Reads the messages of a socket with its current protocol.
+ * JSON messages are passed as they were received, MessagePack messages are passed decoded and decompressed
+ */
+export function consumeMessagesWithProtocol(
+  socket: Socket,
+  callback: (message: string | object) => void
+) {
+  const consumeJsonMessages = consumeMessagesFromSocket(callback);
+  let decoder: MessageDecoder | undefined;
+  return (data: Buffer) => {
+    if (!msgpackSockets.has(socket)) {
+      return consumeJsonMessages(data);
+    }
+    decoder ??= new MessageDecoder();
+    for (const message of decoder.push(data)) {
+      callback(message);
+    }
+  };
+}
+
+/**
+ * Parses the messages passed by {@link consumeMessagesWithProtocol}
+ */
+export function parseMessage(message: string | object): any {
+  return typeof message === 'string' ? JSON.parse(message) : message;
+}
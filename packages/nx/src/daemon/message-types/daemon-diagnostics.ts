@@ -0,0 +1,16 @@
+export const DAEMON_DIAGNOSTICS = 'DAEMON_DIAGNOSTICS' as const;
+
+export type HandleDaemonDiagnosticsMessage = {
+  type: typeof DAEMON_DIAGNOSTICS;
+};
+
+export function isHandleDaemonDiagnosticsMessage(
+  message: unknown
+): message is HandleDaemonDiagnosticsMessage {
+  return (
+    typeof message === 'object' &&
+    message !== null &&
+    'type' in message &&
+    message['type'] === DAEMON_DIAGNOSTICS
+  );
+}
@@ -15,3 +15,22 @@ export function isHandleNxWorkspaceFilesMessage(
     message['type'] === GET_NX_WORKSPACE_FILES
   );
 }
+
+export const GET_SHARED_NX_WORKSPACE_FILES =
+  'GET_SHARED_NX_WORKSPACE_FILES' as const;
+
+export type HandleSharedNxWorkspaceFilesMessage = {
+  type: typeof GET_SHARED_NX_WORKSPACE_FILES;
+  projectRootMap: Record<string, string>;
+};
+
+export function isHandleSharedNxWorkspaceFilesMessage(
+  message: unknown
+): message is HandleSharedNxWorkspaceFilesMessage {
+  return (
+    typeof message === 'object' &&
+    message !== null &&
+    'type' in message &&
+    message['type'] === GET_SHARED_NX_WORKSPACE_FILES
+  );
+}
@@ -0,0 +1,22 @@
+import type { DaemonProtocol } from '../message-protocol';
+
+export const NEGOTIATE_PROTOCOL = 'NEGOTIATE_PROTOCOL' as const;
+
+export type HandleNegotiateProtocolMessage = {
+  type: typeof NEGOTIATE_PROTOCOL;
+  /**
+   * The protocols the client supports, the preferred one first
+   */
+  protocols: DaemonProtocol[];
+};
+
+export function isHandleNegotiateProtocolMessage(
+  message: unknown
+): message is HandleNegotiateProtocolMessage {
+  return (
+    typeof message === 'object' &&
+    message !== null &&
+    'type' in message &&
+    message['type'] === NEGOTIATE_PROTOCOL
+  );
+}
@@ -0,0 +1,18 @@
+export const SET_LOG_LEVEL = 'SET_LOG_LEVEL' as const;
+
+export type HandleSetLogLevelMessage = {
+  type: typeof SET_LOG_LEVEL;
+  module: string;
+  level: string;
+};
+
+export function isHandleSetLogLevelMessage(
+  message: unknown
+): message is HandleSetLogLevelMessage {
+  return (
+    typeof message === 'object' &&
+    message !== null &&
+    'type' in message &&
+    message['type'] === SET_LOG_LEVEL
+  );
+}
@@ -1,6 +1,7 @@
-import type { TaskRun, TaskTarget } from '../../native';
+import type { FlakinessWindow, TaskRun, TaskTarget } from '../../native';
 
 export const GET_FLAKY_TASKS = 'GET_FLAKY_TASKS' as const;
+export const GET_FLAKY_TARGETS = 'GET_FLAKY_TARGETS' as const;
 export const GET_ESTIMATED_TASK_TIMINGS = 'GET_ESTIMATED_TASK_TIMINGS' as const;
 export const RECORD_TASK_RUNS = 'RECORD_TASK_RUNS' as const;
 
@@ -9,6 +10,11 @@ export type HandleGetFlakyTasks = {
   hashes: string[];
 };
 
+export type HandleGetFlakyTargets = {
+  type: typeof GET_FLAKY_TARGETS;
+  window: FlakinessWindow;
+};
+
 export type HandleGetEstimatedTaskTimings = {
   type: typeof GET_ESTIMATED_TASK_TIMINGS;
   targets: TaskTarget[];
@@ -30,6 +36,17 @@ export function isHandleGetFlakyTasksMessage(
   );
 }
 
+export function isHandleGetFlakyTargetsMessage(
+  message: unknown
+): message is HandleGetFlakyTargets {
+  return (
+    typeof message === 'object' &&
+    message !== null &&
+    'type' in message &&
+    message['type'] === GET_FLAKY_TARGETS
+  );
+}
+
 export function isHandleGetEstimatedTaskTimings(
   message: unknown
 ): message is HandleGetEstimatedTaskTimings {
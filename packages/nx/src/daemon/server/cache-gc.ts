@@ -0,0 +1,47 @@
+import { IS_WASM, NxCache } from '../../native';
+import { cacheDir } from '../../utils/cache-directory';
+import { getCacheGcOptions } from '../../utils/cache-gc';
+import { getDbConnection } from '../../utils/db-connection';
+import { workspaceRoot } from '../../utils/workspace-root';
+import { serverLogger } from './logger';
+
+/**
+ * The cache is cleaned up after the daemon has not received a request for this long
+ */
+const CACHE_GC_IDLE_TIMEOUT_MS = 300000 as const; // 300000 ms = 5 minutes
+
+let cacheGcTimerId: NodeJS.Timeout | undefined;
+let cache: NxCache | undefined;
+
+/**
+ * Schedules a garbage collection of the local cache for when the daemon is idle.
+ * Every request postpones it, so it does not compete with running tasks.
+ */
+export function scheduleCacheGc(): void {
+  if (
+    IS_WASM ||
+    process.env.NX_DISABLE_DB === 'true' ||
+    process.env.NX_DB_CACHE !== 'true'
+  ) {
+    return;
+  }
+  if (cacheGcTimerId) {
+    clearTimeout(cacheGcTimerId);
+  }
+  cacheGcTimerId = setTimeout(collectGarbage, CACHE_GC_IDLE_TIMEOUT_MS);
+  // the daemon does not stay alive only to clean the cache
+  cacheGcTimerId.unref();
+}
+
+function collectGarbage() {
+  cacheGcTimerId = undefined;
+  try {
+    cache ??= new NxCache(workspaceRoot, cacheDir, getDbConnection());
+    const result = cache.collectGarbage(getCacheGcOptions());
+    serverLogger.log(
+      `Removed ${result.removedEntries} cache entries (${result.freedBytes} bytes), ${result.remainingBytes} bytes remain`
+    );
+  } catch (e) {
+    serverLogger.log(`Unable to clean up the cache: ${e.message}`);
+  }
+}
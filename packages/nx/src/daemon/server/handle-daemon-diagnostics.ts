@@ -0,0 +1,33 @@
+import { daemonDiagnostics } from '../../native';
+import { getDbConnection } from '../../utils/db-connection';
+import { HandlerResult } from './server';
+
+export async function handleDaemonDiagnostics(
+  outputsWatcherError: Error | undefined
+): Promise<HandlerResult> {
+  try {
+    const diagnostics = daemonDiagnostics(getDbConnection(), maxMemory());
+    if (outputsWatcherError) {
+      diagnostics.degradations.push(
+        `The outputs watcher stopped: ${outputsWatcherError.message}`
+      );
+    }
+    return {
+      response: JSON.stringify(diagnostics),
+      description: 'handleDaemonDiagnostics',
+    };
+  } catch (e) {
+    return {
+      description: 'Unable to diagnose the daemon',
+      error: e,
+    };
+  }
+}
+
+/**
+ * The memory the daemon can use before it is reported as degraded, in bytes
+ */
+function maxMemory(): number | undefined {
+  const megabytes = Number(process.env.NX_DAEMON_MAX_MEMORY);
+  return megabytes > 0 ? megabytes * 1024 * 1024 : undefined;
+}
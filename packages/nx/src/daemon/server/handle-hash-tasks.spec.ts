@@ -0,0 +1,94 @@
+import { ProjectGraph } from '../../config/project-graph';
+import { InProcessTaskHasher } from '../../hasher/task-hasher';
+import {
+  getCachedSerializedProjectGraphPromise,
+  getFilesChangedBetweenProjectGraphs,
+} from './project-graph-incremental-recomputation';
+import { handleHashTasks } from './handle-hash-tasks';
+
+jest.mock('./project-graph-incremental-recomputation');
+jest.mock('../../hasher/task-hasher');
+jest.mock('../../config/configuration', () => ({
+  readNxJson: () => ({}),
+}));
+
+describe('handleHashTasks', () => {
+  const payload = { runnerOptions: {}, env: {}, tasks: [], taskGraph: null };
+  const rustReferences = {} as any;
+
+  function recomputeProjectGraph(): ProjectGraph {
+    const projectGraph = { nodes: {}, dependencies: {} };
+    (getCachedSerializedProjectGraphPromise as jest.Mock).mockResolvedValue({
+      error: null,
+      projectGraph,
+      allWorkspaceFiles: [],
+      fileMap: { projectFileMap: {}, nonProjectFiles: [] },
+      rustReferences,
+    });
+    return projectGraph;
+  }
+
+  beforeEach(() => {
+    (InProcessTaskHasher as jest.Mock).mockImplementation(() => ({
+      hashTasks: jest.fn().mockResolvedValue([]),
+      updateFiles: jest.fn().mockReturnValue(true),
+      persistCache: jest.fn(),
+    }));
+  });
+
+  it('should update the hasher with the watched changes', async () => {
+    const previousProjectGraph = recomputeProjectGraph();
+    await handleHashTasks(payload);
+    const hasher = (InProcessTaskHasher as jest.Mock).mock.results.at(-1)
+      .value;
+
+    const projectGraph = recomputeProjectGraph();
+    (getFilesChangedBetweenProjectGraphs as jest.Mock).mockReturnValue([
+      'libs/ui/index.ts',
+    ]);
+    await handleHashTasks(payload);
+
+    expect(getFilesChangedBetweenProjectGraphs).toHaveBeenCalledWith(
+      previousProjectGraph,
+      projectGraph
+    );
+    expect(hasher.updateFiles).toHaveBeenCalledWith(
+      projectGraph,
+      rustReferences,
+      ['libs/ui/index.ts']
+    );
+    expect(hasher.hashTasks).toHaveBeenCalledTimes(2);
+  });
+
+  it('should recreate the hasher when the changes are not known', async () => {
+    recomputeProjectGraph();
+    await handleHashTasks(payload);
+    const createdHashers = (InProcessTaskHasher as jest.Mock).mock.calls
+      .length;
+
+    recomputeProjectGraph();
+    (getFilesChangedBetweenProjectGraphs as jest.Mock).mockReturnValue(null);
+    await handleHashTasks(payload);
+
+    expect((InProcessTaskHasher as jest.Mock).mock.calls.length).toEqual(
+      createdHashers + 1
+    );
+  });
+
+  it('should persist the hashes once the hashing is done', async () => {
+    jest.useFakeTimers();
+    try {
+      recomputeProjectGraph();
+      await handleHashTasks(payload);
+      const hasher = (InProcessTaskHasher as jest.Mock).mock.results.at(-1)
+        .value;
+      expect(hasher.persistCache).not.toHaveBeenCalled();
+
+      jest.runOnlyPendingTimers();
+
+      expect(hasher.persistCache).toHaveBeenCalled();
+    } finally {
+      jest.useRealTimers();
+    }
+  });
+});
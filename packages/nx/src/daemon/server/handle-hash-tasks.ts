@@ -1,5 +1,8 @@
 import { Task, TaskGraph } from '../../config/task-graph';
-import { getCachedSerializedProjectGraphPromise } from './project-graph-incremental-recomputation';
+import {
+  getCachedSerializedProjectGraphPromise,
+  getFilesChangedBetweenProjectGraphs,
+} from './project-graph-incremental-recomputation';
 import { InProcessTaskHasher } from '../../hasher/task-hasher';
 import { readNxJson } from '../../config/configuration';
 import { DaemonProjectGraphError } from '../../project-graph/error-types';
@@ -10,6 +13,11 @@ import { DaemonProjectGraphError } from '../../project-graph/error-types';
  */
 let storedProjectGraph: any = null;
 let storedHasher: InProcessTaskHasher | null = null;
+// the configuration the stored hasher was created with
+let storedHasherConfiguration: string | null = null;
+let persistCacheTimeout: NodeJS.Timeout | undefined;
+// the hashes are persisted once the daemon stops hashing for a while
+const PERSIST_CACHE_DELAY = 5000;
 
 export async function handleHashTasks(payload: {
   runnerOptions: any;
@@ -36,22 +44,44 @@ export async function handleHashTasks(payload: {
 
   const nxJson = readNxJson();
 
+  const hasherConfiguration = JSON.stringify([nxJson, payload.runnerOptions]);
+
   if (projectGraph !== storedProjectGraph) {
+    // the hasher keeps the hashes of the files that did not change
+    const changedFiles =
+      storedHasher && hasherConfiguration === storedHasherConfiguration
+        ? getFilesChangedBetweenProjectGraphs(storedProjectGraph, projectGraph)
+        : null;
+    if (
+      !changedFiles ||
+      !storedHasher.updateFiles(projectGraph, rustReferences, changedFiles)
+    ) {
+      storedHasher = new InProcessTaskHasher(
+        fileMap?.projectFileMap,
+        allWorkspaceFiles,
+        projectGraph,
+        nxJson,
+        rustReferences,
+        payload.runnerOptions
+      );
+    }
     storedProjectGraph = projectGraph;
-    storedHasher = new InProcessTaskHasher(
-      fileMap?.projectFileMap,
-      allWorkspaceFiles,
-      projectGraph,
-      nxJson,
-      rustReferences,
-      payload.runnerOptions
-    );
+    storedHasherConfiguration = hasherConfiguration;
   }
   const response = JSON.stringify(
     await storedHasher.hashTasks(payload.tasks, payload.taskGraph, payload.env)
   );
+  schedulePersistingCache();
   return {
     response,
     description: 'handleHashTasks',
   };
 }
+
+function schedulePersistingCache() {
+  clearTimeout(persistCacheTimeout);
+  persistCacheTimeout = setTimeout(() => {
+    persistCacheTimeout = undefined;
+    storedHasher?.persistCache();
+  }, PERSIST_CACHE_DELAY).unref();
+}
@@ -1,5 +1,10 @@
-import { getNxWorkspaceFilesFromContext } from '../../utils/workspace-context';
+import {
+  getNxWorkspaceFilesFromContext,
+  publishWorkspaceFilesInContext,
+} from '../../utils/workspace-context';
 import { workspaceRoot } from '../../utils/workspace-root';
+import { DAEMON_DIR_FOR_CURRENT_WORKSPACE } from '../tmp-dir';
+import { serverLogger } from './logger';
 import { HandlerResult } from './server';
 
 export async function handleNxWorkspaceFiles(
@@ -14,3 +19,23 @@ export async function handleNxWorkspaceFiles(
     description: 'handleNxWorkspaceFiles',
   };
 }
+
+export async function handleSharedNxWorkspaceFiles(
+  projectRootMap: Record<string, string>
+): Promise<HandlerResult> {
+  let generation: number | null = null;
+  try {
+    generation = publishWorkspaceFilesInContext(
+      workspaceRoot,
+      DAEMON_DIR_FOR_CURRENT_WORKSPACE,
+      projectRootMap
+    );
+  } catch (e) {
+    // the client receives the files over the socket instead
+    serverLogger.log(`Unable to publish the workspace files: ${e.message}`);
+  }
+  return {
+    response: JSON.stringify({ generation }),
+    description: 'handleSharedNxWorkspaceFiles',
+  };
+}
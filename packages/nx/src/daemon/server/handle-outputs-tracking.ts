@@ -1,5 +1,9 @@
 import { HandlerResult } from './server';
-import { outputsHashesMatch, recordOutputsHash } from './outputs-tracking';
+import {
+  outputsChangedWhileRecording,
+  outputsHashesMatch,
+  recordOutputsHash,
+} from './outputs-tracking';
 
 export async function handleRecordOutputsHash(payload: {
   type: string;
@@ -43,3 +47,23 @@ export async function handleOutputsHashesMatch(payload: {
     };
   }
 }
+
+export async function handleOutputsChangedWhileRecording(payload: {
+  type: string;
+  data: { outputs: string[] };
+}): Promise<HandlerResult> {
+  try {
+    const res = await outputsChangedWhileRecording(payload.data.outputs);
+    return {
+      response: JSON.stringify(res),
+      description: 'outputsChangedWhileRecording',
+    };
+  } catch (e) {
+    return {
+      description: 'outputsChangedWhileRecording failed',
+      error: new Error(
+        `Critical error when checking whether the outputs changed: '${e.message}'.`
+      ),
+    };
+  }
+}
@@ -0,0 +1,22 @@
+import { setLogLevel } from '../../native';
+import { HandlerResult } from './server';
+import { serverLogger } from './logger';
+
+export async function handleSetLogLevel(
+  module: string,
+  level: string
+): Promise<HandlerResult> {
+  try {
+    const filter = setLogLevel(module, level);
+    serverLogger.log(`Native logs are now filtered with ${filter}`);
+    return {
+      response: JSON.stringify(filter),
+      description: 'handleSetLogLevel',
+    };
+  } catch (e) {
+    return {
+      description: `Unable to set the log level of ${module}`,
+      error: e,
+    };
+  }
+}
@@ -1,5 +1,5 @@
 import { getTaskHistory } from '../../utils/task-history';
-import type { TaskRun, TaskTarget } from '../../native';
+import type { FlakinessWindow, TaskRun, TaskTarget } from '../../native';
 
 export async function handleRecordTaskRuns(taskRuns: TaskRun[]) {
   const taskHistory = getTaskHistory();
@@ -19,6 +19,15 @@ export async function handleGetFlakyTasks(hashes: string[]) {
   };
 }
 
+export async function handleGetFlakyTargets(window: FlakinessWindow) {
+  const taskHistory = getTaskHistory();
+  const flakyTargets = await taskHistory.getFlakyTargets(window);
+  return {
+    response: JSON.stringify(flakyTargets),
+    description: 'handleGetFlakyTargets',
+  };
+}
+
 export async function handleGetEstimatedTaskTimings(targets: TaskTarget[]) {
   const taskHistory = getTaskHistory();
   const history = await taskHistory.getEstimatedTaskTimings(targets);
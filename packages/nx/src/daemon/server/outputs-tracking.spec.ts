@@ -1,5 +1,6 @@
 import { EventType } from '../../native';
 import {
+  _outputsChangedWhileRecording,
   _outputsHashesMatch,
   _recordOutputsHash,
   processFileChangesInOutputs,
@@ -52,4 +53,18 @@ describe('outputs tracking', () => {
     );
     expect(recordedHash('dist/app/app1')).toEqual('123');
   });
+
+  it('should keep the outputs that changed while they were recorded', () => {
+    _recordOutputsHash(['dist/app/app1'], '123');
+    expect(_outputsChangedWhileRecording(['dist/app/app1'])).toBeFalsy();
+
+    processFileChangesInOutputs([
+      { path: 'dist/app/app1/child', type: EventType.update },
+    ]);
+    expect(recordedHash('dist/app/app1')).toEqual('123');
+    expect(_outputsChangedWhileRecording(['dist/app/app1'])).toBeTruthy();
+
+    _recordOutputsHash(['dist/app/app1'], '123');
+    expect(_outputsChangedWhileRecording(['dist/app/app1'])).toBeFalsy();
+  });
 });
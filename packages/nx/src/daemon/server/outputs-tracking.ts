@@ -8,6 +8,9 @@ let disabled = false;
 const dirsContainingOutputs = {} as { [dir: string]: Set<string> };
 const recordedHashes = {} as { [output: string]: string };
 const timestamps = {} as { [output: string]: number };
+// outputs that changed right after their hash was recorded, which cannot be
+// told apart from the writes of the task or of the restore from the cache
+const changedWhileRecording = {} as { [output: string]: boolean };
 const numberOfExpandedOutputs = {} as { [hash: string]: number };
 
 export function _recordOutputsHash(outputs: string[], hash: string) {
@@ -15,6 +18,7 @@ export function _recordOutputsHash(outputs: string[], hash: string) {
   for (const output of outputs) {
     recordedHashes[output] = hash;
     timestamps[output] = new Date().getTime();
+    changedWhileRecording[output] = false;
 
     let current = output;
     while (current != dirname(current)) {
@@ -40,6 +44,10 @@ export function _outputsHashesMatch(outputs: string[], hash: string) {
   return true;
 }
 
+export function _outputsChangedWhileRecording(outputs: string[]) {
+  return outputs.some((output) => changedWhileRecording[output]);
+}
+
 export function recordedHash(output: string) {
   return recordedHashes[output];
 }
@@ -56,6 +64,16 @@ export async function outputsHashesMatch(_outputs: string[], hash: string) {
   return _outputsHashesMatch(outputs, hash);
 }
 
+/**
+ * Whether the outputs changed while their hash was recorded, so the changes
+ * may not have been made by the task and the outputs have to be verified
+ */
+export async function outputsChangedWhileRecording(_outputs: string[]) {
+  const outputs = await normalizeOutputs(_outputs);
+  if (disabled) return true;
+  return _outputsChangedWhileRecording(outputs);
+}
+
 async function normalizeOutputs(outputs: string[]) {
   let expandedOutputs = collapseExpandedOutputs(
     getFilesForOutputs(workspaceRoot, outputs)
@@ -70,14 +88,19 @@ export function processFileChangesInOutputs(
   if (!now) {
     now = new Date().getTime();
   }
-  for (let e of changeEvents) {
-    let current = e.path;
+  // moved files change the outputs containing their previous path as well
+  const changedPaths = changeEvents.flatMap((e) =>
+    e.from ? [e.path, e.from] : [e.path]
+  );
+  for (let current of changedPaths) {
 
     // the path is either an output itself or a parent
     if (dirsContainingOutputs[current]) {
       dirsContainingOutputs[current].forEach((output) => {
         if (now - timestamps[output] > 2000) {
           recordedHashes[output] = undefined;
+        } else {
+          changedWhileRecording[output] = true;
         }
       });
       continue;
@@ -85,9 +108,12 @@ export function processFileChangesInOutputs(
 
     // the path is a child of some output or unrelated
     while (current != dirname(current)) {
-      if (recordedHashes[current] && now - timestamps[current] > 2000) {
-        recordedHashes[current] = undefined;
-        break;
+      if (recordedHashes[current]) {
+        if (now - timestamps[current] > 2000) {
+          recordedHashes[current] = undefined;
+          break;
+        }
+        changedWhileRecording[current] = true;
       }
       current = dirname(current);
     }
@@ -62,9 +62,18 @@ export let currentProjectGraph: ProjectGraph | undefined;
 
 const collectedUpdatedFiles = new Set<string>();
 const collectedDeletedFiles = new Set<string>();
+// previous paths of moved files mapped to their new paths
+const collectedMovedFiles = new Map<string, string>();
 const projectGraphRecomputationListeners = new Set<
   (projectGraph: ProjectGraph) => void
 >();
+// the project graphs recomputed since the internal state was reset, along
+// with the files that changed since the previous one
+const recomputedProjectGraphs: {
+  projectGraph: ProjectGraph;
+  changedFiles: string[];
+}[] = [];
+const MAX_RECOMPUTED_PROJECT_GRAPHS = 20;
 let storedWorkspaceConfigHash: string | undefined;
 let waitPeriod = 100;
 let scheduledTimeoutId;
@@ -84,7 +93,11 @@ export async function getCachedSerializedProjectGraphPromise(): Promise<Serializ
     waitPeriod = 100;
     await resetInternalStateIfNxDepsMissing();
     const plugins = await getPlugins();
-    if (collectedUpdatedFiles.size == 0 && collectedDeletedFiles.size == 0) {
+    if (
+      collectedUpdatedFiles.size == 0 &&
+      collectedDeletedFiles.size == 0 &&
+      collectedMovedFiles.size == 0
+    ) {
       if (!cachedSerializedProjectGraphPromise) {
         cachedSerializedProjectGraphPromise =
           processFilesAndCreateAndSerializeProjectGraph(plugins);
@@ -117,7 +130,8 @@ export async function getCachedSerializedProjectGraphPromise(): Promise<Serializ
 export function addUpdatedAndDeletedFiles(
   createdFiles: string[],
   updatedFiles: string[],
-  deletedFiles: string[]
+  deletedFiles: string[],
+  movedFiles: Record<string, string> = {}
 ) {
   for (let f of [...createdFiles, ...updatedFiles]) {
     collectedDeletedFiles.delete(f);
@@ -129,11 +143,33 @@ export function addUpdatedAndDeletedFiles(
     collectedDeletedFiles.add(f);
   }
 
-  if (updatedFiles.length > 0 || deletedFiles.length > 0) {
-    notifyFileWatcherSockets(null, updatedFiles, deletedFiles);
+  const movedFromFiles = Object.keys(movedFiles);
+  // moved files are created at their new path
+  const createdAndMovedFiles = [
+    ...createdFiles,
+    ...Object.values(movedFiles),
+  ];
+  for (let [from, to] of Object.entries(movedFiles)) {
+    // the previous path is removed from the project file map,
+    // the new path gets the hash of the previous path in the workspace context
+    collectedUpdatedFiles.delete(from);
+    collectedDeletedFiles.add(from);
+    collectedDeletedFiles.delete(to);
+    collectedMovedFiles.set(from, to);
+  }
+
+  if (
+    updatedFiles.length > 0 ||
+    deletedFiles.length > 0 ||
+    movedFromFiles.length > 0
+  ) {
+    notifyFileWatcherSockets(null, updatedFiles, [
+      ...deletedFiles,
+      ...movedFromFiles,
+    ]);
   }
 
-  if (createdFiles.length > 0) {
+  if (createdAndMovedFiles.length > 0) {
     waitPeriod = 100; // reset it to process the graph faster
   }
 
@@ -148,8 +184,8 @@ export function addUpdatedAndDeletedFiles(
         processFilesAndCreateAndSerializeProjectGraph(await getPlugins());
       const { projectGraph } = await cachedSerializedProjectGraphPromise;
 
-      if (createdFiles.length > 0) {
-        notifyFileWatcherSockets(createdFiles, null, null);
+      if (createdAndMovedFiles.length > 0) {
+        notifyFileWatcherSockets(createdAndMovedFiles, null, null);
       }
 
       notifyProjectGraphRecomputationListeners(projectGraph);
@@ -157,6 +193,45 @@ export function addUpdatedAndDeletedFiles(
   }
 }
 
+/**
+ * Returns the files that changed between two recomputed project graphs, or
+ * null when they are not known (e.g. because the internal state was reset)
+ */
+export function getFilesChangedBetweenProjectGraphs(
+  from: ProjectGraph,
+  to: ProjectGraph
+): string[] | null {
+  const fromIndex = recomputedProjectGraphs.findIndex(
+    (r) => r.projectGraph === from
+  );
+  const toIndex = recomputedProjectGraphs.findIndex(
+    (r) => r.projectGraph === to
+  );
+  if (fromIndex === -1 || toIndex < fromIndex) {
+    return null;
+  }
+
+  const changedFiles = new Set<string>();
+  for (let i = fromIndex + 1; i <= toIndex; i++) {
+    for (const f of recomputedProjectGraphs[i].changedFiles) {
+      changedFiles.add(f);
+    }
+  }
+  // the graphs before are not compared anymore
+  recomputedProjectGraphs.splice(0, toIndex);
+  return [...changedFiles];
+}
+
+function recordRecomputedProjectGraph(
+  projectGraph: ProjectGraph,
+  changedFiles: string[]
+) {
+  recomputedProjectGraphs.push({ projectGraph, changedFiles });
+  if (recomputedProjectGraphs.length > MAX_RECOMPUTED_PROJECT_GRAPHS) {
+    recomputedProjectGraphs.shift();
+  }
+}
+
 export function registerProjectGraphRecomputationListener(
   listener: (projectGraph: ProjectGraph) => void
 ) {
@@ -213,6 +288,7 @@ async function processCollectedUpdatedAndDeletedFiles(
 
     collectedUpdatedFiles.clear();
     collectedDeletedFiles.clear();
+    collectedMovedFiles.clear();
   } catch (e) {
     // this is expected
     // for instance, project.json can be incorrect or a file we are trying to has
@@ -236,10 +312,12 @@ async function processFilesAndCreateAndSerializeProjectGraph(
     performance.mark('hash-watched-changes-start');
     const updatedFiles = [...collectedUpdatedFiles.values()];
     const deletedFiles = [...collectedDeletedFiles.values()];
+    const movedFiles = Object.fromEntries(collectedMovedFiles);
     let updatedFileHashes = updateFilesInContext(
       workspaceRoot,
       updatedFiles,
-      deletedFiles
+      deletedFiles,
+      movedFiles
     );
     performance.mark('hash-watched-changes-end');
     performance.measure(
@@ -278,6 +356,13 @@ async function processFilesAndCreateAndSerializeProjectGraph(
       deletedFiles
     );
     const g = await createAndSerializeProjectGraph(projectConfigurationsResult);
+    if (g.projectGraph) {
+      recordRecomputedProjectGraph(g.projectGraph, [
+        ...updatedFiles,
+        ...deletedFiles,
+        ...Object.values(movedFiles),
+      ]);
+    }
 
     delete global.NX_GRAPH_CREATION;
 
@@ -423,6 +508,8 @@ async function resetInternalState() {
   currentProjectGraph = undefined;
   collectedUpdatedFiles.clear();
   collectedDeletedFiles.clear();
+  collectedMovedFiles.clear();
+  recomputedProjectGraphs.length = 0;
   resetWorkspaceContext();
   waitPeriod = 100;
 }
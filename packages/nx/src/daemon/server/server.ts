@@ -3,14 +3,24 @@ import { createServer, Server, Socket } from 'net';
 import { join } from 'path';
 import { PerformanceObserver } from 'perf_hooks';
 import { hashArray } from '../../hasher/file-hasher';
-import { hashFile } from '../../native';
-import { consumeMessagesFromSocket } from '../../utils/consume-messages-from-socket';
+import { enableFileLogging, hashFile } from '../../native';
+import { workspaceDataDirectory } from '../../utils/cache-directory';
 import { readJsonFile } from '../../utils/fileutils';
 import { PackageJson } from '../../utils/package-json';
 import { nxVersion } from '../../utils/versions';
 import { setupWorkspaceContext } from '../../utils/workspace-context';
 import { workspaceRoot } from '../../utils/workspace-root';
 import { writeDaemonJsonProcessCache } from '../cache';
+import {
+  consumeMessagesWithProtocol,
+  negotiateProtocol,
+  parseMessage,
+  useProtocol,
+} from '../message-protocol';
+import {
+  isHandleNegotiateProtocolMessage,
+  NEGOTIATE_PROTOCOL,
+} from '../message-types/negotiate-protocol';
 import {
   getFullOsSocketPath,
   isWindows,
@@ -22,6 +32,7 @@ import {
 } from './file-watching/file-watcher-sockets';
 import { handleHashTasks } from './handle-hash-tasks';
 import {
+  handleOutputsChangedWhileRecording,
   handleOutputsHashesMatch,
   handleRecordOutputsHash,
 } from './handle-outputs-tracking';
@@ -58,9 +69,14 @@ import { handleGlob } from './handle-glob';
 import { GLOB, isHandleGlobMessage } from '../message-types/glob';
 import {
   GET_NX_WORKSPACE_FILES,
+  GET_SHARED_NX_WORKSPACE_FILES,
   isHandleNxWorkspaceFilesMessage,
+  isHandleSharedNxWorkspaceFilesMessage,
 } from '../message-types/get-nx-workspace-files';
-import { handleNxWorkspaceFiles } from './handle-nx-workspace-files';
+import {
+  handleNxWorkspaceFiles,
+  handleSharedNxWorkspaceFiles,
+} from './handle-nx-workspace-files';
 import {
   GET_CONTEXT_FILE_DATA,
   isHandleContextFileDataMessage,
@@ -75,8 +91,10 @@ import { HASH_GLOB, isHandleHashGlobMessage } from '../message-types/hash-glob';
 import { handleHashGlob } from './handle-hash-glob';
 import {
   GET_ESTIMATED_TASK_TIMINGS,
+  GET_FLAKY_TARGETS,
   GET_FLAKY_TASKS,
   isHandleGetEstimatedTaskTimings,
+  isHandleGetFlakyTargetsMessage,
   isHandleGetFlakyTasksMessage,
   isHandleWriteTaskRunsToHistoryMessage,
   RECORD_TASK_RUNS,
@@ -84,6 +102,7 @@ import {
 import {
   handleRecordTaskRuns,
   handleGetFlakyTasks,
+  handleGetFlakyTargets,
   handleGetEstimatedTaskTimings,
 } from './handle-task-history';
 import { isHandleForceShutdownMessage } from '../message-types/force-shutdown';
@@ -109,6 +128,18 @@ import {
   isHandleFlushSyncGeneratorChangesToDiskMessage,
 } from '../message-types/flush-sync-generator-changes-to-disk';
 import { handleFlushSyncGeneratorChangesToDisk } from './handle-flush-sync-generator-changes-to-disk';
+import {
+  SET_LOG_LEVEL,
+  isHandleSetLogLevelMessage,
+} from '../message-types/set-log-level';
+import { handleSetLogLevel } from './handle-set-log-level';
+import {
+  DAEMON_DIAGNOSTICS,
+  isHandleDaemonDiagnosticsMessage,
+} from '../message-types/daemon-diagnostics';
+import { handleDaemonDiagnostics } from './handle-daemon-diagnostics';
+import { scheduleCacheGc } from './cache-gc';
+import { validateConfigFiles } from './validate-config';
 
 let performanceObserver: PerformanceObserver | undefined;
 let workspaceWatcherError: Error | undefined;
@@ -142,7 +173,7 @@ const server = createServer(async (socket) => {
 
   socket.on(
     'data',
-    consumeMessagesFromSocket(async (message) => {
+    consumeMessagesWithProtocol(socket, async (message) => {
       await handleMessage(socket, message);
     })
   );
@@ -164,7 +195,7 @@ const server = createServer(async (socket) => {
 });
 registerProcessTerminationListeners();
 
-async function handleMessage(socket, data: string) {
+async function handleMessage(socket, data: string | object) {
   if (workspaceWatcherError) {
     await respondWithErrorAndExit(
       socket,
@@ -183,11 +214,12 @@ async function handleMessage(socket, data: string) {
   }
 
   resetInactivityTimeout(handleInactivityTimeout);
+  scheduleCacheGc();
 
   const unparsedPayload = data;
   let payload;
   try {
-    payload = JSON.parse(unparsedPayload);
+    payload = parseMessage(unparsedPayload);
   } catch (e) {
     await respondWithErrorAndExit(
       socket,
@@ -196,7 +228,17 @@ async function handleMessage(socket, data: string) {
     );
   }
 
-  if (payload.type === 'PING') {
+  if (isHandleNegotiateProtocolMessage(payload)) {
+    const protocol = negotiateProtocol(payload.protocols);
+    await handleResult(socket, NEGOTIATE_PROTOCOL, () =>
+      Promise.resolve({
+        response: JSON.stringify({ protocol }),
+        description: 'negotiateProtocol',
+      })
+    );
+    // the answer is written with the protocol the client negotiated with
+    useProtocol(socket, protocol);
+  } else if (payload.type === 'PING') {
     await handleResult(socket, 'PING', () =>
       Promise.resolve({ response: JSON.stringify(true), description: 'ping' })
     );
@@ -218,6 +260,10 @@ async function handleMessage(socket, data: string) {
     await handleResult(socket, 'OUTPUTS_HASHES_MATCH', () =>
       handleOutputsHashesMatch(payload)
     );
+  } else if (payload.type === 'OUTPUTS_CHANGED_WHILE_RECORDING') {
+    await handleResult(socket, 'OUTPUTS_CHANGED_WHILE_RECORDING', () =>
+      handleOutputsChangedWhileRecording(payload)
+    );
   } else if (payload.type === 'REQUEST_SHUTDOWN') {
     await handleResult(socket, 'REQUEST_SHUTDOWN', () =>
       handleRequestShutdown(server, numberOfOpenConnections)
@@ -232,6 +278,10 @@ async function handleMessage(socket, data: string) {
     await handleResult(socket, GET_NX_WORKSPACE_FILES, () =>
       handleNxWorkspaceFiles(payload.projectRootMap)
     );
+  } else if (isHandleSharedNxWorkspaceFilesMessage(payload)) {
+    await handleResult(socket, GET_SHARED_NX_WORKSPACE_FILES, () =>
+      handleSharedNxWorkspaceFiles(payload.projectRootMap)
+    );
   } else if (isHandleGetFilesInDirectoryMessage(payload)) {
     await handleResult(socket, GET_FILES_IN_DIRECTORY, () =>
       handleGetFilesInDirectory(payload.dir)
@@ -248,6 +298,10 @@ async function handleMessage(socket, data: string) {
     await handleResult(socket, GET_FLAKY_TASKS, () =>
       handleGetFlakyTasks(payload.hashes)
     );
+  } else if (isHandleGetFlakyTargetsMessage(payload)) {
+    await handleResult(socket, GET_FLAKY_TARGETS, () =>
+      handleGetFlakyTargets(payload.window)
+    );
   } else if (isHandleGetEstimatedTaskTimings(payload)) {
     await handleResult(socket, GET_ESTIMATED_TASK_TIMINGS, () =>
       handleGetEstimatedTaskTimings(payload.targets)
@@ -280,11 +334,19 @@ async function handleMessage(socket, data: string) {
         payload.deletedFiles
       )
     );
+  } else if (isHandleSetLogLevelMessage(payload)) {
+    await handleResult(socket, SET_LOG_LEVEL, () =>
+      handleSetLogLevel(payload.module, payload.level)
+    );
+  } else if (isHandleDaemonDiagnosticsMessage(payload)) {
+    await handleResult(socket, DAEMON_DIAGNOSTICS, () =>
+      handleDaemonDiagnostics(outputsWatcherError)
+    );
   } else {
     await respondWithErrorAndExit(
       socket,
       `Invalid payload from the client`,
-      new Error(`Unsupported payload sent to daemon server: ${unparsedPayload}`)
+      new Error(`Unsupported payload sent to daemon server: ${JSON.stringify(payload)}`)
     );
   }
 }
@@ -441,10 +503,14 @@ const handleWorkspaceChanges: FileWatcherCallback = async (
     const updatedFilesToHash = [];
     const createdFilesToHash = [];
     const deletedFiles = [];
+    const movedFiles: Record<string, string> = {};
 
     for (const event of changeEvents) {
       if (event.type === 'delete') {
         deletedFiles.push(event.path);
+      } else if (event.type === 'moved') {
+        // moved files keep their hash, so they don't need to be hashed again
+        movedFiles[event.from] = event.to;
       } else {
         try {
           const s = statSync(join(workspaceRoot, event.path));
@@ -464,7 +530,8 @@ const handleWorkspaceChanges: FileWatcherCallback = async (
     addUpdatedAndDeletedFiles(
       createdFilesToHash,
       updatedFilesToHash,
-      deletedFiles
+      deletedFiles,
+      movedFiles
     );
   } catch (err) {
     serverLogger.watcherLog(`Unexpected workspace error`, err.message);
@@ -501,6 +568,9 @@ const handleOutputsChanges: FileWatcherCallback = async (err, changeEvents) => {
 };
 
 export async function startServer(): Promise<Server> {
+  enableFileLogging(workspaceDataDirectory, {
+    filter: process.env.NX_DAEMON_NATIVE_LOGGING,
+  });
   setupWorkspaceContext(workspaceRoot);
 
   // Persist metadata about the background process so that it can be cleaned up later if needed
@@ -520,6 +590,8 @@ export async function startServer(): Promise<Server> {
           serverLogger.log(`Started listening on: ${getFullOsSocketPath()}`);
           // this triggers the storage of the lock file hash
           daemonIsOutdated();
+          // misconfigurations are reported before a task fails because of them
+          validateConfigFiles();
 
           if (!getWatcherInstance()) {
             storeWatcherInstance(
@@ -2,9 +2,11 @@ import { workspaceRoot } from '../../utils/workspace-root';
 import type { Server, Socket } from 'net';
 import { serverLogger } from './logger';
 import { serializeResult } from '../socket-utils';
+import { writeMessage } from '../message-protocol';
 import { deleteDaemonJsonProcessCache } from '../cache';
 import type { Watcher } from '../../native';
 import { cleanupPlugins } from './plugins';
+import { shutdownNativeWork } from '../../utils/db-connection';
 import {
   DaemonProjectGraphError,
   ProjectGraphError,
@@ -68,6 +70,15 @@ export async function handleServerProcessTermination({
       );
     }
 
+    // the events the watchers flushed when they stopped get a chance to be handled before the native work is drained
+    await new Promise((res) => setImmediate(res));
+    const { abandonedWork, checkpointed } = shutdownNativeWork();
+    serverLogger.log(
+      `Native work drained (${abandonedWork} abandoned), database ${
+        checkpointed ? 'checkpointed' : 'not checkpointed'
+      }`
+    );
+
     deleteDaemonJsonProcessCache();
     cleanupPlugins();
 
@@ -95,7 +106,7 @@ export function respondToClient(
     if (description) {
       serverLogger.requestLog(`Responding to the client.`, description);
     }
-    socket.write(`${response}${String.fromCodePoint(4)}`, (err) => {
+    writeMessage(socket, response, (err) => {
       if (err) {
         console.error(err);
       }
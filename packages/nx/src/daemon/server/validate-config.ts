@@ -0,0 +1,52 @@
+import { join } from 'path';
+import { ConfigDiagnostic, IS_WASM, validateConfig } from '../../native';
+import { globWithWorkspaceContextSync } from '../../utils/workspace-context';
+import { workspaceRoot } from '../../utils/workspace-root';
+import { serverLogger } from './logger';
+
+/**
+ * Validates nx.json and the project.json files of the workspace against
+ * their schemas when the daemon starts, and logs their problems, so
+ * misconfigurations show up in the daemon log before tasks run.
+ */
+export function validateConfigFiles(): ConfigDiagnostic[] {
+  if (IS_WASM) {
+    return [];
+  }
+
+  const files = globWithWorkspaceContextSync(workspaceRoot, [
+    'nx.json',
+    '**/project.json',
+  ]);
+  const diagnostics: ConfigDiagnostic[] = [];
+  for (const file of files) {
+    try {
+      for (const diagnostic of validateConfig(join(workspaceRoot, file))) {
+        diagnostics.push({ ...diagnostic, file });
+      }
+    } catch (e) {
+      serverLogger.log(`Unable to validate ${file}: ${e.message}`);
+    }
+  }
+
+  for (const diagnostic of diagnostics) {
+    serverLogger.log(
+      `Invalid configuration: ${formatConfigDiagnostic(diagnostic)}`
+    );
+  }
+  return diagnostics;
+}
+
+export function formatConfigDiagnostic({
+  file,
+  pointer,
+  message,
+  position,
+}: ConfigDiagnostic): string {
+  const location = position
+    ? `${file}:${position.line}:${position.column}`
+    : file;
+  return pointer
+    ? `${location}: ${pointer}: ${message}`
+    : `${location}: ${message}`;
+}
@@ -13,6 +13,7 @@ import { platform } from 'os';
 import { getDaemonProcessIdSync, serverProcessJsonPath } from '../cache';
 import type { WatchEvent } from '../../native';
 import { openSockets } from './server';
+import { serverLogger } from './logger';
 
 const ALWAYS_IGNORE = [
   ...getAlwaysIgnore(workspaceRoot),
@@ -28,25 +29,29 @@ export async function watchWorkspace(server: Server, cb: FileWatcherCallback) {
   const { Watcher } = await import('../../native');
 
   const watcher = new Watcher(workspaceRoot);
-  watcher.watch((err, events) => {
-    if (err) {
-      return cb(err, null);
-    }
+  watcher.watch(
+    (err, events) => {
+      if (err) {
+        return cb(err, null);
+      }
 
-    for (const event of events) {
-      if (event.path.endsWith('.gitignore') || event.path === '.nxignore') {
-        // If the ignore files themselves have changed we need to dynamically update our cached ignoreGlobs
-        handleServerProcessTermination({
-          server,
-          reason:
-            'Stopping the daemon the set of ignored files changed (native)',
-          sockets: openSockets,
-        });
+      for (const event of events) {
+        if (event.path.endsWith('.gitignore') || event.path === '.nxignore') {
+          // If the ignore files themselves have changed we need to dynamically update our cached ignoreGlobs
+          handleServerProcessTermination({
+            server,
+            reason:
+              'Stopping the daemon the set of ignored files changed (native)',
+            sockets: openSockets,
+          });
+        }
       }
-    }
 
-    cb(null, events);
-  });
+      cb(null, events);
+    },
+    undefined,
+    (warning) => serverLogger.log(warning.message)
+  );
 
   return watcher;
 }
@@ -100,7 +105,7 @@ export function convertChangeEventsToLogMessage(
 ): string {
   // If only a single file was changed, show the information inline
   if (changeEvents.length === 1) {
-    const { path, type } = changeEvents[0];
+    const { path, type, from } = changeEvents[0];
     let typeLog = 'updated';
     switch (type) {
       case 'create':
@@ -112,6 +117,9 @@ export function convertChangeEventsToLogMessage(
       case 'delete':
         typeLog = 'deleted';
         break;
+      case 'moved':
+        typeLog = `moved from ${from}`;
+        break;
     }
     return `${path} was ${typeLog}`;
   }
@@ -119,6 +127,7 @@ export function convertChangeEventsToLogMessage(
   let numCreatedOrRestoredFiles = 0;
   let numModifiedFiles = 0;
   let numDeletedFiles = 0;
+  let numMovedFiles = 0;
   for (const event of changeEvents) {
     switch (event.type) {
       case 'create':
@@ -130,8 +139,11 @@ export function convertChangeEventsToLogMessage(
       case 'delete':
         numDeletedFiles++;
         break;
+      case 'moved':
+        numMovedFiles++;
+        break;
     }
   }
 
-  return `${numCreatedOrRestoredFiles} file(s) created or restored, ${numModifiedFiles} file(s) modified, ${numDeletedFiles} file(s) deleted`;
+  return `${numCreatedOrRestoredFiles} file(s) created or restored, ${numModifiedFiles} file(s) modified, ${numDeletedFiles} file(s) deleted, ${numMovedFiles} file(s) moved`;
 }
@@ -12,6 +12,7 @@ import { logger } from '../utils/logger';
 import { output } from '../utils/output';
 import { dirname, join, relative, sep } from 'path';
 import * as chalk from 'chalk';
+import { IS_WASM, writeFilesAtomic } from '../native';
 
 /**
  * Options to set when writing a file in the Virtual file system tree.
@@ -434,6 +435,18 @@ export class FsTree implements Tree {
 }
 
 export function flushChanges(root: string, fileChanges: FileChange[]): void {
+  if (!IS_WASM) {
+    // the files are written in parallel, each one atomically
+    writeFilesAtomic(
+      fileChanges.map((f) => ({
+        path: join(root, f.path),
+        content: f.type === 'DELETE' ? undefined : f.content,
+        mode: f.options?.mode ? parseMode(f.options.mode) : undefined,
+      }))
+    );
+    return;
+  }
+
   fileChanges.forEach((f) => {
     const fpath = join(root, f.path);
     if (f.type === 'CREATE') {
@@ -449,6 +462,13 @@ export function flushChanges(root: string, fileChanges: FileChange[]): void {
   });
 }
 
+/**
+ * Modes given as strings are octal, like `fs.chmod` parses them
+ */
+function parseMode(mode: Mode): number {
+  return typeof mode === 'string' ? parseInt(mode, 8) : mode;
+}
+
 export function printChanges(
   fileChanges: FileChange[],
   indent: string = ''
@@ -4,8 +4,11 @@ import { Task, TaskGraph } from '../config/task-graph';
 import {
   ExternalObject,
   FileData,
+  HashAlgorithm,
   HasherOptions,
   HashPlanner,
+  IS_WASM,
+  NxHashCache,
   NxWorkspaceFilesExternals,
   ProjectGraph as NativeProjectGraph,
   TaskHasher,
@@ -15,6 +18,7 @@ import { transformProjectGraphForRust } from '../native/transform-objects';
 import { PartialHash, TaskHasherImpl } from './task-hasher';
 import { readJsonFile } from '../utils/fileutils';
 import { getRootTsConfigPath } from '../plugins/js/utils/typescript';
+import { getDbConnection } from '../utils/db-connection';
 
 export class NativeTaskHasherImpl implements TaskHasherImpl {
   hasher: TaskHasher;
@@ -23,13 +27,23 @@ export class NativeTaskHasherImpl implements TaskHasherImpl {
   allWorkspaceFilesRef: ExternalObject<FileData[]>;
   projectFileMapRef: ExternalObject<Record<string, FileData[]>>;
   options: HasherOptions | undefined;
+  hashCache: NxHashCache | undefined;
 
   constructor(
     workspaceRoot: string,
-    nxJson: NxJsonConfiguration,
+    private readonly nxJson: NxJsonConfiguration,
     projectGraph: ProjectGraph,
     externals: NxWorkspaceFilesExternals,
-    options: { selectivelyHashTsConfig: boolean }
+    options: {
+      selectivelyHashTsConfig: boolean;
+      hashSalt?: string;
+      hashAlgorithm?: HashAlgorithm;
+      includePlatform?: boolean;
+      hashExternalsFromLockFile?: boolean;
+      runtimeTimeout?: number;
+      orderedNegatedGlobs?: boolean;
+      hashMissingFiles?: boolean;
+    }
   ) {
     this.projectGraphRef = transferProjectGraph(
       transformProjectGraphForRust(projectGraph)
@@ -38,17 +52,7 @@ export class NativeTaskHasherImpl implements TaskHasherImpl {
     this.allWorkspaceFilesRef = externals.allWorkspaceFiles;
     this.projectFileMapRef = externals.projectFiles;
 
-    let tsconfig: { compilerOptions?: import('typescript').CompilerOptions } =
-      {};
-    let paths = {};
-    let rootTsConfigPath = getRootTsConfigPath();
-    if (rootTsConfigPath) {
-      tsconfig = readJsonFile(getRootTsConfigPath());
-      paths = tsconfig.compilerOptions?.paths ?? {};
-      if (tsconfig.compilerOptions?.paths) {
-        delete tsconfig.compilerOptions.paths;
-      }
-    }
+    const { tsconfig, paths } = readRootTsConfig();
 
     this.planner = new HashPlanner(nxJson, this.projectGraphRef);
     this.hasher = new TaskHasher(
@@ -60,6 +64,49 @@ export class NativeTaskHasherImpl implements TaskHasherImpl {
       paths,
       options
     );
+
+    if (!IS_WASM && process.env.NX_DISABLE_DB !== 'true') {
+      try {
+        this.hashCache = new NxHashCache(getDbConnection());
+        this.hasher.importCache(this.hashCache.getHashes());
+      } catch {
+        // the persisted hashes only save time, so they can be ignored
+        this.hashCache = undefined;
+      }
+    }
+  }
+
+  persistCache(): void {
+    try {
+      this.hashCache?.saveHashes(this.hasher.exportCache());
+    } catch {
+      // the hashes are computed again by the next process
+    }
+  }
+
+  updateFiles(
+    projectGraph: ProjectGraph,
+    externals: NxWorkspaceFilesExternals,
+    changedFiles: string[]
+  ): void {
+    this.projectGraphRef = transferProjectGraph(
+      transformProjectGraphForRust(projectGraph)
+    );
+
+    this.allWorkspaceFilesRef = externals.allWorkspaceFiles;
+    this.projectFileMapRef = externals.projectFiles;
+
+    const { tsconfig, paths } = readRootTsConfig();
+
+    this.planner = new HashPlanner(this.nxJson, this.projectGraphRef);
+    this.hasher.updateFiles(
+      this.projectGraphRef,
+      this.projectFileMapRef,
+      this.allWorkspaceFilesRef,
+      Buffer.from(JSON.stringify(tsconfig)),
+      paths,
+      changedFiles
+    );
   }
 
   async hashTask(
@@ -86,3 +133,18 @@ export class NativeTaskHasherImpl implements TaskHasherImpl {
     return tasks.map((t) => hashes[t.id]);
   }
 }
+
+function readRootTsConfig() {
+  let tsconfig: { compilerOptions?: import('typescript').CompilerOptions } =
+    {};
+  let paths = {};
+  let rootTsConfigPath = getRootTsConfigPath();
+  if (rootTsConfigPath) {
+    tsconfig = readJsonFile(getRootTsConfigPath());
+    paths = tsconfig.compilerOptions?.paths ?? {};
+    if (tsconfig.compilerOptions?.paths) {
+      delete tsconfig.compilerOptions.paths;
+    }
+  }
+  return { tsconfig, paths };
+}
@@ -87,6 +87,14 @@ export interface TaskHasherImpl {
     env: NodeJS.ProcessEnv,
     visited?: string[]
   ): Promise<PartialHash>;
+
+  updateFiles?(
+    projectGraph: ProjectGraph,
+    externals: NxWorkspaceFilesExternals,
+    changedFiles: string[]
+  ): void;
+
+  persistCache?(): void;
 }
 
 export type Hasher = TaskHasher;
@@ -179,6 +187,13 @@ export class InProcessTaskHasher implements TaskHasher {
           {
             selectivelyHashTsConfig:
               this.options?.selectivelyHashTsConfig ?? false,
+            hashSalt: nxJson.hashSalt,
+            hashAlgorithm: this.options?.hashAlgorithm,
+            includePlatform: this.options?.includePlatform,
+            hashExternalsFromLockFile: this.options?.hashExternalsFromLockFile,
+            runtimeTimeout: this.options?.runtimeTimeout,
+            orderedNegatedGlobs: this.options?.orderedNegatedGlobs,
+            hashMissingFiles: this.options?.hashMissingFiles,
           }
         );
   }
@@ -204,6 +219,35 @@ export class InProcessTaskHasher implements TaskHasher {
     }
   }
 
+  /**
+   * Updates the hasher after the workspace has changed, keeping the cached
+   * hashes of the files that did not change. Returns false when the hasher
+   * cannot be updated and has to be recreated instead
+   */
+  updateFiles(
+    projectGraph: ProjectGraph,
+    externalRustReferences: NxWorkspaceFilesExternals | null,
+    changedFiles: string[]
+  ): boolean {
+    if (!this.taskHasher.updateFiles || !externalRustReferences) {
+      return false;
+    }
+    this.taskHasher.updateFiles(
+      projectGraph,
+      externalRustReferences,
+      changedFiles
+    );
+    return true;
+  }
+
+  /**
+   * Saves the cached hashes of the files that did not change, so the next
+   * process does not have to compute them again
+   */
+  persistCache(): void {
+    this.taskHasher.persistCache?.();
+  }
+
   async hashTask(
     task: Task,
     taskGraph?: TaskGraph,
@@ -248,7 +292,7 @@ export class InProcessTaskHasher implements TaskHasher {
 }
 
 export type ExpandedSelfInput =
-  | { fileset: string }
+  | { fileset: string; caseInsensitive?: boolean }
   | { runtime: string }
   | { env: string }
   | { externalDependencies: string[] };
@@ -0,0 +1,473 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use flate2::read::GzDecoder;
+use flate2::{Compression, GzBuilder};
+use tracing::trace;
+use walkdir::WalkDir;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::native::cache::expand_outputs::_expand_outputs;
+
+/// 1985-10-26T08:15:00Z, the modification time of the entries of npm tarballs
+const DEFAULT_MTIME: u64 = 499_162_500;
+/// 1980-01-01T00:00:00Z, the earliest modification time of zip entries
+const MIN_ZIP_MTIME: u64 = 315_532_800;
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+#[napi(string_enum)]
+#[derive(Debug, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    #[allow(non_camel_case_types)]
+    tarGz,
+    #[allow(non_camel_case_types)]
+    zip,
+}
+
+#[napi(object)]
+#[derive(Debug, Default)]
+pub struct ArchiveOptions {
+    /// Inferred from the extension of the archive (.tar.gz, .tgz or .zip) by default
+    pub format: Option<ArchiveFormat>,
+    /// The directory that the entries are archived under, e.g. `package` for npm tarballs
+    pub prefix: Option<String>,
+    /// The modification time of every entry, in seconds since the unix epoch.
+    /// Defaults to SOURCE_DATE_EPOCH, or to 1985-10-26T08:15:00Z like npm
+    pub mtime: Option<i64>,
+}
+
+enum EntryKind {
+    Directory,
+    File { executable: bool },
+    Symlink(String),
+}
+
+struct Entry {
+    name: String,
+    path: PathBuf,
+    kind: EntryKind,
+}
+
+impl Entry {
+    fn mode(&self) -> u32 {
+        match self.kind {
+            EntryKind::Directory | EntryKind::File { executable: true } => 0o755,
+            EntryKind::File { executable: false } => 0o644,
+            EntryKind::Symlink(_) => 0o777,
+        }
+    }
+}
+
+/// Archives the outputs of `directory` into a tar.gz or a zip archive at `destination`, which is the same for the same
+/// outputs on every machine: the entries are sorted, and their modification times, owners and permissions are normalized.
+/// Returns the names of the entries that were archived
+#[napi]
+pub fn create_archive(
+    directory: String,
+    outputs: Vec<String>,
+    destination: String,
+    options: Option<ArchiveOptions>,
+) -> anyhow::Result<Vec<String>> {
+    let options = options.unwrap_or_default();
+    let destination = PathBuf::from(destination);
+    let format = archive_format(&destination, options.format)?;
+    let mtime = options
+        .mtime
+        .map(|mtime| mtime.max(0) as u64)
+        .unwrap_or_else(default_mtime);
+    let entries = collect_entries(Path::new(&directory), outputs, options.prefix.as_deref())?;
+    trace!(
+        "archiving {} entries into {:?} ({:?})",
+        entries.len(),
+        destination,
+        format
+    );
+
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // the archive is renamed into place when complete, so a partial archive is never read
+    let temp = destination.with_extension("tmp");
+    let file = BufWriter::new(File::create(&temp)?);
+    match format {
+        ArchiveFormat::tarGz => write_tar_gz(&entries, file, mtime)?,
+        ArchiveFormat::zip => write_zip(&entries, file, mtime)?,
+    }
+    fs::rename(&temp, &destination)?;
+
+    Ok(entries.into_iter().map(|entry| entry.name).collect())
+}
+
+/// Extracts an archive created by `create_archive` into `destination`. Entries that would be outside of it are skipped.
+/// Returns the names of the entries that were extracted
+#[napi]
+pub fn extract_archive(
+    source: String,
+    destination: String,
+    format: Option<ArchiveFormat>,
+) -> anyhow::Result<Vec<String>> {
+    let source = PathBuf::from(source);
+    let destination = PathBuf::from(destination);
+    let format = archive_format(&source, format)?;
+    let file = File::open(&source).with_context(|| format!("Unable to open {:?}", source))?;
+    fs::create_dir_all(&destination)?;
+    match format {
+        ArchiveFormat::tarGz => extract_tar_gz(file, &destination),
+        ArchiveFormat::zip => extract_zip(file, &destination),
+    }
+}
+
+fn archive_format(path: &Path, format: Option<ArchiveFormat>) -> anyhow::Result<ArchiveFormat> {
+    if let Some(format) = format {
+        return Ok(format);
+    }
+    let name = path.to_string_lossy();
+    if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Ok(ArchiveFormat::tarGz)
+    } else if name.ends_with(".zip") {
+        Ok(ArchiveFormat::zip)
+    } else {
+        bail!(
+            "The format of the archive {:?} can not be inferred, its extension should be .tar.gz, .tgz or .zip",
+            path
+        )
+    }
+}
+
+/// See https://reproducible-builds.org/specs/source-date-epoch/
+fn default_mtime() -> u64 {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse().ok())
+        .unwrap_or(DEFAULT_MTIME)
+}
+
+/// The files, directories and symlinks of the outputs, sorted by name. Directories are sorted before their contents
+fn collect_entries(
+    directory: &Path,
+    outputs: Vec<String>,
+    prefix: Option<&str>,
+) -> anyhow::Result<Vec<Entry>> {
+    let prefix = prefix
+        .map(|prefix| prefix.trim_matches('/'))
+        .filter(|prefix| !prefix.is_empty());
+    let mut entries = BTreeMap::new();
+    for output in _expand_outputs(directory, outputs)? {
+        let root = directory.join(&output);
+        // symlinks to directories are archived as symlinks, not followed
+        let walker = if root.is_symlink() {
+            WalkDir::new(&root).max_depth(0)
+        } else {
+            WalkDir::new(&root)
+        };
+        for walked in walker {
+            let walked = walked?;
+            let relative = walked
+                .path()
+                .strip_prefix(directory)?
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let name = match prefix {
+                Some(prefix) => format!("{}/{}", prefix, relative),
+                None => relative,
+            };
+            if name.is_empty() || entries.contains_key(&name) {
+                continue;
+            }
+
+            let path = walked.path();
+            let kind = if path.is_symlink() {
+                let target = fs::read_link(path)?;
+                EntryKind::Symlink(target.to_string_lossy().replace('\\', "/"))
+            } else if walked.file_type().is_dir() {
+                EntryKind::Directory
+            } else {
+                EntryKind::File {
+                    executable: is_executable(&fs::metadata(path)?),
+                }
+            };
+            entries.insert(
+                name.clone(),
+                Entry {
+                    name,
+                    path: path.to_path_buf(),
+                    kind,
+                },
+            );
+        }
+    }
+    Ok(entries.into_values().collect())
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_: &fs::Metadata) -> bool {
+    false
+}
+
+fn write_tar_gz(entries: &[Entry], writer: impl Write, mtime: u64) -> anyhow::Result<()> {
+    // the gzip header records neither the name of the archive nor when it was created
+    let encoder = GzBuilder::new()
+        .mtime(0)
+        .write(writer, Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+    for entry in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_mtime(mtime);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_mode(entry.mode());
+        match &entry.kind {
+            EntryKind::Directory => {
+                header.set_entry_type(tar::EntryType::Directory);
+                header.set_size(0);
+                builder.append_data(&mut header, format!("{}/", entry.name), io::empty())?;
+            }
+            EntryKind::File { .. } => {
+                let file = File::open(&entry.path)?;
+                header.set_entry_type(tar::EntryType::Regular);
+                header.set_size(file.metadata()?.len());
+                builder.append_data(&mut header, &entry.name, file)?;
+            }
+            EntryKind::Symlink(target) => {
+                header.set_entry_type(tar::EntryType::Symlink);
+                header.set_size(0);
+                builder.append_link(&mut header, &entry.name, target)?;
+            }
+        }
+    }
+    builder.into_inner()?.finish()?.flush()?;
+    Ok(())
+}
+
+fn write_zip(entries: &[Entry], writer: impl Write + Seek, mtime: u64) -> anyhow::Result<()> {
+    let modified = zip_date_time(mtime)?;
+    let mut zip = ZipWriter::new(writer);
+    for entry in entries {
+        let options = FileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .last_modified_time(modified)
+            .unix_permissions(entry.mode());
+        match &entry.kind {
+            EntryKind::Directory => zip.add_directory(entry.name.as_str(), options)?,
+            EntryKind::File { .. } => {
+                let mut file = File::open(&entry.path)?;
+                let large_file = file.metadata()?.len() >= u32::MAX as u64;
+                zip.start_file(entry.name.as_str(), options.large_file(large_file))?;
+                io::copy(&mut file, &mut zip)?;
+            }
+            EntryKind::Symlink(target) => {
+                zip.add_symlink(entry.name.as_str(), target.as_str(), options)?
+            }
+        }
+    }
+    zip.finish()?.flush()?;
+    Ok(())
+}
+
+/// Zip entries record their modification time as a date and a time, without a time zone
+fn zip_date_time(mtime: u64) -> anyhow::Result<zip::DateTime> {
+    let mtime = mtime.max(MIN_ZIP_MTIME);
+    let (days, seconds) = (mtime / 86_400, mtime % 86_400);
+    // the civil date of a number of days since the unix epoch, from http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    zip::DateTime::from_date_and_time(
+        year as u16,
+        month as u8,
+        day as u8,
+        (seconds / 3_600) as u8,
+        (seconds % 3_600 / 60) as u8,
+        (seconds % 60) as u8,
+    )
+    .map_err(|_| anyhow::anyhow!("{} can not be the modification time of a zip entry", mtime))
+}
+
+fn extract_tar_gz(file: File, destination: &Path) -> anyhow::Result<Vec<String>> {
+    let mut archive = tar::Archive::new(GzDecoder::new(BufReader::new(file)));
+    archive.set_preserve_permissions(true);
+    // the extracted files are new to the tools that read them
+    archive.set_preserve_mtime(false);
+    archive.set_overwrite(true);
+    let mut names = vec![];
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        if entry.unpack_in(destination)? {
+            names.push(name);
+        }
+    }
+    Ok(names)
+}
+
+fn extract_zip(file: File, destination: &Path) -> anyhow::Result<Vec<String>> {
+    let mut archive = ZipArchive::new(BufReader::new(file))?;
+    let mut names = vec![];
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        let Some(path) = entry.enclosed_name().map(|name| destination.join(name)) else {
+            continue;
+        };
+        if entry.is_dir() {
+            fs::create_dir_all(&path)?;
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            if path
+                .symlink_metadata()
+                .is_ok_and(|metadata| !metadata.is_dir())
+            {
+                fs::remove_file(&path)?;
+            }
+            let mode = entry.unix_mode();
+            if mode.is_some_and(|mode| mode & S_IFMT == S_IFLNK) {
+                let mut target = String::new();
+                entry.read_to_string(&mut target)?;
+                symlink(&target, &path)?;
+            } else {
+                io::copy(&mut entry, &mut File::create(&path)?)?;
+                #[cfg(unix)]
+                if let Some(mode) = mode {
+                    use std::os::unix::fs::PermissionsExt;
+                    fs::set_permissions(&path, fs::Permissions::from_mode(mode & 0o777))?;
+                }
+            }
+        }
+        names.push(entry.name().to_string());
+    }
+    Ok(names)
+}
+
+#[cfg(unix)]
+fn symlink(target: &str, path: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+#[cfg(windows)]
+fn symlink(target: &str, path: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(target, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    fn setup_fs(modified: SystemTime) -> TempDir {
+        let temp = TempDir::new().unwrap();
+        temp.child("dist/libs/ui/index.js")
+            .write_str("index")
+            .unwrap();
+        temp.child("dist/libs/ui/styles/theme.css")
+            .write_str("theme")
+            .unwrap();
+        temp.child("dist/libs/ui/index.js.map")
+            .write_str("map")
+            .unwrap();
+        for file in ["index.js", "styles/theme.css", "index.js.map"] {
+            File::options()
+                .write(true)
+                .open(temp.join("dist/libs/ui").join(file))
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        }
+        temp
+    }
+
+    fn outputs() -> Vec<String> {
+        vec!["dist/libs/ui/**/*.js".into(), "dist/libs/ui/styles".into()]
+    }
+
+    #[test]
+    fn should_create_identical_archives_of_identical_outputs() {
+        let first = setup_fs(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000));
+        let second = setup_fs(SystemTime::now());
+
+        for extension in ["tar.gz", "zip"] {
+            let archives = [&first, &second].map(|temp| {
+                let archive = temp.join(format!("ui.{}", extension));
+                let entries = create_archive(
+                    temp.display().to_string(),
+                    outputs(),
+                    archive.display().to_string(),
+                    None,
+                )
+                .unwrap();
+                assert_eq!(
+                    entries,
+                    // the globs expand to the files of the outputs
+                    vec!["dist/libs/ui/index.js", "dist/libs/ui/styles/theme.css"]
+                );
+                fs::read(archive).unwrap()
+            });
+            assert_eq!(
+                archives[0], archives[1],
+                "the {} archives differ",
+                extension
+            );
+        }
+    }
+
+    #[test]
+    fn should_extract_archives() {
+        let temp = setup_fs(SystemTime::now());
+        for extension in ["tgz", "zip"] {
+            let archive = temp.join(format!("ui.{}", extension));
+            create_archive(
+                temp.display().to_string(),
+                outputs(),
+                archive.display().to_string(),
+                Some(ArchiveOptions {
+                    prefix: Some("package/".into()),
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+
+            let destination = temp.child(format!("extracted-{}", extension));
+            let extracted = extract_archive(
+                archive.display().to_string(),
+                destination.display().to_string(),
+                None,
+            )
+            .unwrap();
+            assert_eq!(extracted.len(), 2);
+            destination
+                .child("package/dist/libs/ui/index.js")
+                .assert("index");
+            destination
+                .child("package/dist/libs/ui/styles/theme.css")
+                .assert("theme");
+            assert!(!destination
+                .join("package/dist/libs/ui/index.js.map")
+                .exists());
+        }
+    }
+}
@@ -0,0 +1,297 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tracing::trace;
+
+use crate::native::cache::expand_outputs::_expand_outputs;
+use crate::native::cache::file_ops::_copy;
+use crate::native::glob::contains_glob_pattern;
+use crate::native::utils::parallel::prelude::*;
+
+const ARCHIVE_EXTENSION: &str = ".tar.zst";
+
+/// Resolves the outputs of a task against the workspace and copies them to `destination` in parallel.
+/// When `compress` is true, the outputs are packed into a compressed tarball at `destination` instead.
+/// Returns the outputs that were found
+#[napi]
+pub fn pack_outputs(
+    workspace_root: String,
+    outputs: Vec<String>,
+    destination: String,
+    compress: Option<bool>,
+) -> anyhow::Result<Vec<String>> {
+    let workspace_root = PathBuf::from(workspace_root);
+    let destination = PathBuf::from(destination);
+    let expanded_outputs = top_level_outputs(_expand_outputs(&workspace_root, outputs)?);
+    trace!(
+        "packing {} outputs into {:?}",
+        expanded_outputs.len(),
+        destination
+    );
+
+    if compress.unwrap_or(false) {
+        os::write_archive(&workspace_root, &expanded_outputs, &destination)?;
+    } else {
+        fs::create_dir_all(&destination)?;
+        expanded_outputs.par_iter().try_for_each(|output| {
+            let src = workspace_root.join(output);
+            if src.exists() {
+                _copy(src, destination.join(output))
+            } else {
+                Ok(())
+            }
+        })?;
+    }
+
+    Ok(expanded_outputs)
+}
+
+/// Restores outputs packed by `pack_outputs` from `source` (a directory or a tarball) into the workspace.
+/// Existing outputs are removed first, so stale files do not survive the restore
+#[napi]
+pub fn unpack_outputs(
+    source: String,
+    workspace_root: String,
+    outputs: Vec<String>,
+) -> anyhow::Result<()> {
+    let workspace_root = PathBuf::from(workspace_root);
+    let source = PathBuf::from(source);
+
+    if is_archive(&source) {
+        return os::extract_archive(&source, &workspace_root, &outputs);
+    }
+
+    let expanded_outputs = top_level_outputs(_expand_outputs(&source, outputs)?);
+    expanded_outputs.par_iter().try_for_each(|output| {
+        let dest = workspace_root.join(output);
+        fs_extra::remove_items(&[&dest])?;
+        _copy(source.join(output), dest)
+    })
+}
+
+/// Globs expand to directories and to the files inside of them, which only need to be copied once
+fn top_level_outputs(mut outputs: Vec<String>) -> Vec<String> {
+    // parents are sorted before their children
+    outputs.sort();
+    let mut parents = HashSet::new();
+    outputs
+        .into_iter()
+        .filter(|output| {
+            let path = Path::new(output.trim_end_matches('/'));
+            let is_nested = path.ancestors().skip(1).any(|a| parents.contains(a));
+            if !is_nested {
+                parents.insert(path.to_path_buf());
+            }
+            !is_nested
+        })
+        .collect()
+}
+
+fn is_archive(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(ARCHIVE_EXTENSION)
+}
+
+/// The outputs without glob patterns, which are removed as a whole before an archive is extracted
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+fn literal_outputs(outputs: &[String]) -> impl Iterator<Item = &str> {
+    outputs
+        .iter()
+        .filter(|output| !output.starts_with('!') && !contains_glob_pattern(output))
+        .map(|output| output.trim_end_matches('/'))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod os {
+    use std::fs;
+    use std::io::{BufReader, BufWriter};
+    use std::path::Path;
+
+    use anyhow::Context;
+
+    use super::literal_outputs;
+    use crate::native::cache::encryption::{decrypted, encrypt_in_place};
+    use crate::native::compression::{decoder, encoder, CompressionTarget};
+
+    pub fn write_archive(
+        workspace_root: &Path,
+        outputs: &[String],
+        destination: &Path,
+    ) -> anyhow::Result<()> {
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // the archive is renamed into place when complete, so a partial archive is never restored
+        let temp = destination.with_extension("tmp");
+        let file = BufWriter::new(fs::File::create(&temp)?);
+        let mut builder = tar::Builder::new(encoder(file, CompressionTarget::Local)?);
+        builder.follow_symlinks(false);
+        for output in outputs {
+            let path = workspace_root.join(output);
+            if path.is_dir() && !path.is_symlink() {
+                builder.append_dir_all(output, &path)?;
+            } else if path.symlink_metadata().is_ok() {
+                builder.append_path_with_name(&path, output)?;
+            }
+        }
+        builder.into_inner()?.finish()?;
+        encrypt_in_place(&temp)?;
+        fs::rename(&temp, destination)?;
+        Ok(())
+    }
+
+    pub fn extract_archive(
+        source: &Path,
+        workspace_root: &Path,
+        outputs: &[String],
+    ) -> anyhow::Result<()> {
+        let source = decrypted(source)?;
+        let open = || -> anyhow::Result<_> {
+            let file = BufReader::new(
+                fs::File::open(&*source)
+                    .with_context(|| format!("Unable to open {:?}", &*source))?,
+            );
+            Ok(tar::Archive::new(decoder(file)?))
+        };
+
+        let mut entries = vec![];
+        for entry in open()?.entries()? {
+            entries.push(entry?.path()?.into_owned());
+        }
+        let to_remove = literal_outputs(outputs)
+            .filter(|output| entries.iter().any(|entry| entry.starts_with(output)))
+            .map(|output| workspace_root.join(output))
+            .collect::<Vec<_>>();
+        fs_extra::remove_items(&to_remove)?;
+
+        let mut archive = open()?;
+        archive.set_preserve_permissions(true);
+        archive.set_overwrite(true);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            // files are replaced instead of written to, as they may be hard links to the cache
+            let dest = workspace_root.join(entry.path()?);
+            if dest
+                .symlink_metadata()
+                .is_ok_and(|metadata| !metadata.is_dir())
+            {
+                fs::remove_file(&dest)?;
+            }
+            entry.unpack_in(workspace_root)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod os {
+    use std::path::Path;
+
+    pub fn write_archive(_: &Path, _: &[String], _: &Path) -> anyhow::Result<()> {
+        anyhow::bail!("Compressed cache outputs are not supported in WASM")
+    }
+
+    pub fn extract_archive(_: &Path, _: &Path, _: &[String]) -> anyhow::Result<()> {
+        anyhow::bail!("Compressed cache outputs are not supported in WASM")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    fn setup_fs() -> TempDir {
+        let temp = TempDir::new().unwrap();
+        temp.child("dist/apps/web/main.js")
+            .write_str("main")
+            .unwrap();
+        temp.child("dist/apps/web/assets/logo.svg")
+            .write_str("logo")
+            .unwrap();
+        temp.child("dist/apps/web/main.js.map")
+            .write_str("map")
+            .unwrap();
+        temp.child("coverage/index.html").write_str("html").unwrap();
+        temp
+    }
+
+    fn outputs() -> Vec<String> {
+        vec![
+            "dist/apps/web/**/*.js".into(),
+            "dist/apps/web/assets".into(),
+        ]
+    }
+
+    #[test]
+    fn should_skip_outputs_inside_of_other_outputs() {
+        let outputs = vec![
+            "apps/web/.next/static/contents".to_string(),
+            "apps/web/.next/static".to_string(),
+            "apps/web/.next/static-file".to_string(),
+        ];
+        assert_eq!(
+            top_level_outputs(outputs),
+            vec!["apps/web/.next/static", "apps/web/.next/static-file"]
+        );
+    }
+
+    #[test]
+    fn should_pack_outputs_into_a_directory() {
+        let temp = setup_fs();
+        let cache = temp.child("cache/hash/outputs");
+        let packed = pack_outputs(
+            temp.display().to_string(),
+            outputs(),
+            cache.display().to_string(),
+            None,
+        )
+        .unwrap();
+        assert!(packed.contains(&"dist/apps/web/main.js".to_string()));
+        cache.child("dist/apps/web/main.js").assert("main");
+        cache.child("dist/apps/web/assets/logo.svg").assert("logo");
+        assert!(!cache.join("dist/apps/web/main.js.map").exists());
+
+        temp.child("dist/apps/web/main.js")
+            .write_str("stale")
+            .unwrap();
+        unpack_outputs(
+            cache.display().to_string(),
+            temp.display().to_string(),
+            outputs(),
+        )
+        .unwrap();
+        temp.child("dist/apps/web/main.js").assert("main");
+    }
+
+    #[test]
+    fn should_pack_outputs_into_an_archive() {
+        let temp = setup_fs();
+        let archive = temp.child("cache/hash/outputs.tar.zst");
+        pack_outputs(
+            temp.display().to_string(),
+            outputs(),
+            archive.display().to_string(),
+            Some(true),
+        )
+        .unwrap();
+        assert!(archive.is_file());
+
+        temp.child("dist/apps/web/assets/stale.svg")
+            .touch()
+            .unwrap();
+        temp.child("dist/apps/web/main.js")
+            .write_str("stale")
+            .unwrap();
+        unpack_outputs(
+            archive.display().to_string(),
+            temp.display().to_string(),
+            outputs(),
+        )
+        .unwrap();
+        temp.child("dist/apps/web/main.js").assert("main");
+        temp.child("dist/apps/web/assets/logo.svg").assert("logo");
+        assert!(!temp.join("dist/apps/web/assets/stale.svg").exists());
+    }
+}
@@ -0,0 +1,289 @@
+// the errors of ureq are large, and are matched on their status where the requests are sent
+#![allow(clippy::result_large_err)]
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+use base64::Engine;
+
+use super::{hmac_sha256, split_url, upload_parts, uri_encode, BlobStore, UtcTime, PART_SIZE};
+use crate::native::cache::remote_cache::with_retries;
+
+const API_VERSION: &str = "2021-08-06";
+
+pub enum Auth {
+    /// The decoded access key of the storage account
+    SharedKey(Vec<u8>),
+    /// A shared access signature, the query string that grants access to the container
+    Sas(String),
+}
+
+/// A container of Azure Blob Storage. Big artifacts are uploaded as blocks that are committed with a block list
+pub struct AzureStore {
+    agent: ureq::Agent,
+    account: String,
+    origin: String,
+    /// The path of the container, which starts with the account for the storage emulator
+    base_path: String,
+    auth: Auth,
+    retries: u32,
+    concurrency: usize,
+}
+
+impl AzureStore {
+    /// `base_url` is the url of the container, e.g. `https://<account>.blob.core.windows.net/<container>`
+    pub fn new(
+        agent: ureq::Agent,
+        account: String,
+        base_url: &str,
+        auth: Auth,
+        retries: u32,
+        concurrency: usize,
+    ) -> anyhow::Result<Self> {
+        let (origin, _, base_path) = split_url(base_url)?;
+        Ok(Self {
+            agent,
+            account,
+            origin,
+            base_path,
+            auth,
+            retries,
+            concurrency,
+        })
+    }
+
+    fn send(
+        &self,
+        method: &str,
+        key: &str,
+        query: &[(&str, &str)],
+        headers: &[(&str, &str)],
+        body: Option<&[u8]>,
+    ) -> Result<ureq::Response, ureq::Error> {
+        let path = format!("{}/{}", self.base_path, uri_encode(key, true));
+        let mut query_string = query
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, uri_encode(value, false)))
+            .collect::<Vec<_>>();
+        if let Auth::Sas(token) = &self.auth {
+            query_string.push(token.trim_start_matches('?').to_string());
+        }
+        let url = if query_string.is_empty() {
+            format!("{}{}", self.origin, path)
+        } else {
+            format!("{}{}?{}", self.origin, path, query_string.join("&"))
+        };
+
+        let mut ms_headers = vec![
+            (
+                "x-ms-date".to_string(),
+                UtcTime::from(SystemTime::now()).to_rfc1123(),
+            ),
+            ("x-ms-version".to_string(), API_VERSION.to_string()),
+        ];
+        ms_headers.extend(
+            headers
+                .iter()
+                .map(|(name, value)| (name.to_lowercase(), value.to_string())),
+        );
+
+        let mut request = self.agent.request(method, &url);
+        if let Auth::SharedKey(key) = &self.auth {
+            let string_to_sign = string_to_sign(
+                method,
+                body.map_or(0, <[u8]>::len),
+                &mut ms_headers,
+                &self.account,
+                &path,
+                query,
+            );
+            let signature = base64::engine::general_purpose::STANDARD
+                .encode(hmac_sha256(key, string_to_sign.as_bytes()));
+            request = request.set(
+                "Authorization",
+                &format!("SharedKey {}:{}", self.account, signature),
+            );
+        }
+        for (name, value) in &ms_headers {
+            request = request.set(name, value);
+        }
+
+        match body {
+            Some(body) => request.send_bytes(body),
+            None => request.call(),
+        }
+    }
+
+    fn send_with_retries(
+        &self,
+        method: &str,
+        key: &str,
+        query: &[(&str, &str)],
+        headers: &[(&str, &str)],
+        body: Option<&[u8]>,
+    ) -> Result<ureq::Response, ureq::Error> {
+        with_retries(self.retries, || {
+            self.send(method, key, query, headers, body)
+        })
+    }
+
+    fn upload_blocks(&self, key: &str, file: &Path, len: u64) -> anyhow::Result<()> {
+        let block_ids = upload_parts(file, len, self.concurrency, |index, data| {
+            let block_id = block_id(index);
+            self.send_with_retries(
+                "PUT",
+                key,
+                &[("comp", "block"), ("blockid", &block_id)],
+                &[],
+                Some(data.as_slice()),
+            )?;
+            Ok(block_id)
+        })?;
+        // blocks that are never committed are removed by Azure after a week
+        self.send_with_retries(
+            "PUT",
+            key,
+            &[("comp", "blocklist")],
+            &[],
+            Some(block_list(&block_ids).as_bytes()),
+        )?;
+        Ok(())
+    }
+}
+
+impl BlobStore for AzureStore {
+    fn exists(&self, key: &str) -> anyhow::Result<bool> {
+        match self.send_with_retries("HEAD", key, &[], &[], None) {
+            Ok(_) => Ok(true),
+            Err(ureq::Error::Status(404, _)) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn download(&self, key: &str, dest: &Path) -> anyhow::Result<bool> {
+        let response = match self.send_with_retries("GET", key, &[], &[], None) {
+            Err(ureq::Error::Status(404, _)) => return Ok(false),
+            response => response?,
+        };
+        let mut file = BufWriter::new(File::create(dest)?);
+        io::copy(&mut response.into_reader(), &mut file)?;
+        file.flush()?;
+        Ok(true)
+    }
+
+    fn upload(&self, key: &str, file: &Path) -> anyhow::Result<()> {
+        let len = fs::metadata(file)?.len();
+        if len > PART_SIZE {
+            return self.upload_blocks(key, file, len);
+        }
+        let body = fs::read(file)?;
+        self.send_with_retries(
+            "PUT",
+            key,
+            &[],
+            &[("x-ms-blob-type", "BlockBlob")],
+            Some(body.as_slice()),
+        )?;
+        Ok(())
+    }
+}
+
+/// The string that is signed with a Shared Key, see
+/// https://learn.microsoft.com/en-us/rest/api/storageservices/authorize-with-shared-key.
+/// None of the standard headers but the content length are sent, so they are empty
+fn string_to_sign(
+    method: &str,
+    content_length: usize,
+    ms_headers: &mut [(String, String)],
+    account: &str,
+    path: &str,
+    query: &[(&str, &str)],
+) -> String {
+    ms_headers.sort();
+    let canonical_headers = ms_headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect::<String>();
+
+    let mut query = query
+        .iter()
+        .map(|(name, value)| (name.to_lowercase(), *value))
+        .collect::<Vec<_>>();
+    query.sort();
+    let mut canonical_resource = format!("/{}{}", account, path);
+    for (name, value) in query {
+        canonical_resource.push_str(&format!("\n{}:{}", name, value));
+    }
+
+    let content_length = if content_length == 0 {
+        String::new()
+    } else {
+        content_length.to_string()
+    };
+    format!(
+        "{}\n\n\n{}\n\n\n\n\n\n\n\n\n{}{}",
+        method, content_length, canonical_headers, canonical_resource
+    )
+}
+
+/// The ids of the blocks of a blob have to be base64 and have the same length
+fn block_id(index: usize) -> String {
+    base64::engine::general_purpose::STANDARD.encode(format!("nx-{:08}", index))
+}
+
+fn block_list(block_ids: &[String]) -> String {
+    let blocks = block_ids
+        .iter()
+        .map(|id| format!("<Latest>{}</Latest>", id))
+        .collect::<String>();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?><BlockList>{}</BlockList>",
+        blocks
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_build_the_string_to_sign() {
+        let mut headers = vec![
+            ("x-ms-version".to_string(), API_VERSION.to_string()),
+            (
+                "x-ms-date".to_string(),
+                "Fri, 24 May 2013 00:00:00 GMT".to_string(),
+            ),
+        ];
+        assert_eq!(
+            string_to_sign(
+                "PUT",
+                5,
+                &mut headers,
+                "account",
+                "/cache/ci/123.tar.zst",
+                &[("comp", "block"), ("blockid", "bngtMDAwMDAwMDA=")]
+            ),
+            "PUT\n\n\n5\n\n\n\n\n\n\n\n\n\
+             x-ms-date:Fri, 24 May 2013 00:00:00 GMT\n\
+             x-ms-version:2021-08-06\n\
+             /account/cache/ci/123.tar.zst\n\
+             blockid:bngtMDAwMDAwMDA=\n\
+             comp:block"
+        );
+    }
+
+    #[test]
+    fn should_list_blocks() {
+        assert_eq!(block_id(0), "bngtMDAwMDAwMDA=");
+        assert_eq!(block_id(0).len(), block_id(12345).len());
+        assert_eq!(
+            block_list(&[block_id(0), block_id(1)]),
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?><BlockList>\
+             <Latest>bngtMDAwMDAwMDA=</Latest><Latest>bngtMDAwMDAwMDE=</Latest>\
+             </BlockList>"
+        );
+    }
+}
@@ -0,0 +1,610 @@
+//! Remote cache backends that store artifacts directly in cloud blob storage (S3, GCS or Azure Blob Storage),
+//! for teams that do not run a cache server. Artifacts have the same format as with the `RemoteCacheClient`
+
+mod azure;
+mod s3;
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use napi::bindgen_prelude::AsyncTask;
+use napi::{Env, Task};
+use parking_lot::Mutex;
+use sha2::Sha256;
+use tracing::trace;
+
+use crate::native::cache::cache::CachedResult;
+use crate::native::cache::remote_cache::{in_parallel, pack_artifact, unpack_artifact};
+use crate::native::compression::{record_throughput, CompressionTarget};
+use crate::native::utils::Normalize;
+
+use azure::AzureStore;
+use s3::{Credentials, S3Store};
+
+const DEFAULT_RETRIES: u32 = 3;
+const DEFAULT_CONCURRENCY: u32 = 4;
+const TIMEOUT: Duration = Duration::from_secs(60);
+/// Artifacts bigger than this are uploaded in parts of this size. S3 requires parts of at least 5 MiB
+const PART_SIZE: u64 = 8 * 1024 * 1024;
+
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct BlobStorageCacheOptions {
+    /// Where artifacts are stored: `s3://<bucket>/<prefix>`, `gs://<bucket>/<prefix>` or `azure://<container>/<prefix>`
+    pub url: String,
+    /// The region of an S3 bucket, `AWS_REGION` by default
+    pub region: Option<String>,
+    /// A custom endpoint, e.g. of S3 compatible storage like MinIO or of an Azure storage emulator
+    pub endpoint: Option<String>,
+    /// How many parts of an artifact are uploaded at the same time, 4 by default
+    pub concurrency: Option<u32>,
+    /// How many times failed requests are retried, 3 by default
+    pub retries: Option<u32>,
+}
+
+/// A bucket or container that artifacts are stored in
+trait BlobStore: Send + Sync {
+    fn exists(&self, key: &str) -> anyhow::Result<bool>;
+    /// Downloads a blob to `dest`. Returns false when the blob does not exist
+    fn download(&self, key: &str, dest: &Path) -> anyhow::Result<bool>;
+    /// Uploads a file as a blob, in concurrent parts when it is bigger than `PART_SIZE`
+    fn upload(&self, key: &str, file: &Path) -> anyhow::Result<()>;
+}
+
+/// A remote cache that reads and writes artifacts in a bucket of S3 (or S3 compatible storage),
+/// of Google Cloud Storage, or in a container of Azure Blob Storage.
+///
+/// Credentials are read from the environment:
+/// - S3: `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`
+/// - GCS: `GOOGLE_OAUTH_ACCESS_TOKEN`, or the HMAC keys `GCS_HMAC_ACCESS_KEY_ID` and `GCS_HMAC_SECRET`
+/// - Azure: `AZURE_STORAGE_ACCOUNT` with `AZURE_STORAGE_KEY` or `AZURE_STORAGE_SAS_TOKEN`
+#[napi]
+pub struct BlobStorageCacheClient {
+    store: Arc<dyn BlobStore>,
+    prefix: String,
+}
+
+#[napi]
+impl BlobStorageCacheClient {
+    #[napi(constructor)]
+    pub fn new(options: BlobStorageCacheOptions) -> anyhow::Result<Self> {
+        let location = parse_url(&options.url)?;
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(TIMEOUT)
+            .timeout_read(TIMEOUT)
+            .build();
+        let retries = options.retries.unwrap_or(DEFAULT_RETRIES);
+        let concurrency = options.concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1) as usize;
+
+        let store: Arc<dyn BlobStore> = match location.provider {
+            Provider::S3 => {
+                let region = options
+                    .region
+                    .or_else(|| env("AWS_REGION"))
+                    .or_else(|| env("AWS_DEFAULT_REGION"))
+                    .unwrap_or_else(|| "us-east-1".into());
+                let base_url = match &options.endpoint {
+                    Some(endpoint) => {
+                        format!("{}/{}", endpoint.trim_end_matches('/'), location.bucket)
+                    }
+                    None => format!("https://{}.s3.{}.amazonaws.com", location.bucket, region),
+                };
+                let credentials = Credentials {
+                    access_key_id: required_env("AWS_ACCESS_KEY_ID")?,
+                    secret_access_key: required_env("AWS_SECRET_ACCESS_KEY")?,
+                    session_token: env("AWS_SESSION_TOKEN"),
+                };
+                Arc::new(S3Store::new(
+                    agent,
+                    &base_url,
+                    s3::Auth::SigV4 {
+                        credentials,
+                        region,
+                    },
+                    retries,
+                    concurrency,
+                )?)
+            }
+            Provider::Gcs => {
+                let endpoint = options
+                    .endpoint
+                    .unwrap_or_else(|| "https://storage.googleapis.com".into());
+                let base_url = format!("{}/{}", endpoint.trim_end_matches('/'), location.bucket);
+                let auth = match env("GOOGLE_OAUTH_ACCESS_TOKEN") {
+                    Some(token) => s3::Auth::Bearer(token),
+                    None => s3::Auth::SigV4 {
+                        credentials: Credentials {
+                            access_key_id: required_env("GCS_HMAC_ACCESS_KEY_ID")?,
+                            secret_access_key: required_env("GCS_HMAC_SECRET")?,
+                            session_token: None,
+                        },
+                        region: "auto".into(),
+                    },
+                };
+                Arc::new(S3Store::new(agent, &base_url, auth, retries, concurrency)?)
+            }
+            Provider::Azure => {
+                let account = required_env("AZURE_STORAGE_ACCOUNT")?;
+                let endpoint = options
+                    .endpoint
+                    .unwrap_or_else(|| format!("https://{}.blob.core.windows.net", account));
+                let base_url = format!("{}/{}", endpoint.trim_end_matches('/'), location.bucket);
+                let auth = match (env("AZURE_STORAGE_SAS_TOKEN"), env("AZURE_STORAGE_KEY")) {
+                    (Some(token), _) => azure::Auth::Sas(token),
+                    (None, Some(key)) => azure::Auth::SharedKey(
+                        base64::engine::general_purpose::STANDARD
+                            .decode(key.trim())
+                            .context("AZURE_STORAGE_KEY is not valid base64")?,
+                    ),
+                    (None, None) => bail!(
+                        "AZURE_STORAGE_KEY or AZURE_STORAGE_SAS_TOKEN has to be set to use an Azure Blob Storage cache"
+                    ),
+                };
+                Arc::new(AzureStore::new(
+                    agent,
+                    account,
+                    &base_url,
+                    auth,
+                    retries,
+                    concurrency,
+                )?)
+            }
+        };
+
+        Ok(Self {
+            store,
+            prefix: location.prefix,
+        })
+    }
+
+    /// Downloads the artifact of a task into `<cache_directory>/<hash>`.
+    /// Resolves with null when the bucket does not have it
+    #[napi(ts_return_type = "Promise<CachedResult | null>")]
+    pub fn retrieve(&self, hash: String, cache_directory: String) -> AsyncTask<RetrieveBlob> {
+        AsyncTask::new(RetrieveBlob {
+            store: self.store.clone(),
+            key: artifact_key(&self.prefix, &hash),
+            hash,
+            cache_directory: PathBuf::from(cache_directory),
+        })
+    }
+
+    /// Uploads the outputs in `<cache_directory>/<hash>` with the terminal output and exit code of the task.
+    /// Resolves with false when the bucket already has the artifact
+    #[napi(ts_return_type = "Promise<boolean>")]
+    pub fn store(
+        &self,
+        hash: String,
+        cache_directory: String,
+        terminal_output: String,
+        code: i16,
+    ) -> AsyncTask<StoreBlob> {
+        AsyncTask::new(StoreBlob {
+            store: self.store.clone(),
+            key: artifact_key(&self.prefix, &hash),
+            hash,
+            cache_directory: PathBuf::from(cache_directory),
+            terminal_output,
+            code,
+        })
+    }
+
+    /// Whether the bucket has the artifact of each hash, without downloading them.
+    /// Buckets can not be asked for several objects at once, so the objects are checked in parallel
+    #[napi(ts_return_type = "Promise<Record<string, boolean>>")]
+    pub fn check_cache_hits(&self, hashes: Vec<String>) -> AsyncTask<CheckBlobs> {
+        AsyncTask::new(CheckBlobs {
+            store: self.store.clone(),
+            prefix: self.prefix.clone(),
+            hashes,
+        })
+    }
+}
+
+pub struct CheckBlobs {
+    store: Arc<dyn BlobStore>,
+    prefix: String,
+    hashes: Vec<String>,
+}
+
+impl Task for CheckBlobs {
+    type Output = HashMap<String, bool>;
+    type JsValue = HashMap<String, bool>;
+
+    fn compute(&mut self) -> napi::Result<Self::Output> {
+        let found = Mutex::new(HashSet::new());
+        in_parallel(&self.hashes, |hash| {
+            if self.store.exists(&artifact_key(&self.prefix, hash))? {
+                found.lock().insert(hash.clone());
+            }
+            Ok(())
+        })
+        .map_err(|e| napi::Error::from_reason(format!("{:#}", e)))?;
+        let found = found.into_inner();
+        Ok(self
+            .hashes
+            .iter()
+            .map(|hash| (hash.clone(), found.contains(hash)))
+            .collect())
+    }
+
+    fn resolve(&mut self, _: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+pub struct RetrieveBlob {
+    store: Arc<dyn BlobStore>,
+    key: String,
+    hash: String,
+    cache_directory: PathBuf,
+}
+
+impl Task for RetrieveBlob {
+    type Output = Option<CachedResult>;
+    type JsValue = Option<CachedResult>;
+
+    fn compute(&mut self) -> napi::Result<Self::Output> {
+        let artifact = self
+            .cache_directory
+            .join(format!("{}.download.tar.zst", self.hash));
+        let result = self.store.download(&self.key, &artifact).and_then(|found| {
+            if !found {
+                return Ok(None);
+            }
+            let task_dir = self.cache_directory.join(&self.hash);
+            let (code, terminal_output) = unpack_artifact(&artifact, &task_dir)?;
+            Ok(Some(CachedResult {
+                code,
+                terminal_output,
+                outputs_path: task_dir.to_normalized_string(),
+                hash_version: None,
+            }))
+        });
+        fs::remove_file(&artifact).ok();
+        result.map_err(|e| napi::Error::from_reason(format!("{:#}", e)))
+    }
+
+    fn resolve(&mut self, _: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+pub struct StoreBlob {
+    store: Arc<dyn BlobStore>,
+    key: String,
+    hash: String,
+    cache_directory: PathBuf,
+    terminal_output: String,
+    code: i16,
+}
+
+impl Task for StoreBlob {
+    type Output = bool;
+    type JsValue = bool;
+
+    fn compute(&mut self) -> napi::Result<Self::Output> {
+        let artifact = self
+            .cache_directory
+            .join(format!("{}.upload.tar.zst", self.hash));
+        let result = self.store.exists(&self.key).and_then(|exists| {
+            if exists {
+                trace!("{} is already in the bucket", self.key);
+                return Ok(false);
+            }
+            let packing = pack_artifact(
+                &self.cache_directory.join(&self.hash),
+                &self.terminal_output,
+                self.code,
+                &artifact,
+            )?;
+            let started = Instant::now();
+            self.store.upload(&self.key, &artifact)?;
+            record_throughput(CompressionTarget::Remote, packing, started.elapsed());
+            Ok(true)
+        });
+        fs::remove_file(&artifact).ok();
+        result.map_err(|e| napi::Error::from_reason(format!("{:#}", e)))
+    }
+
+    fn resolve(&mut self, _: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Provider {
+    S3,
+    Gcs,
+    Azure,
+}
+
+#[derive(Debug, PartialEq)]
+struct Location {
+    provider: Provider,
+    bucket: String,
+    /// Empty, or ends with a `/`
+    prefix: String,
+}
+
+fn parse_url(url: &str) -> anyhow::Result<Location> {
+    let (scheme, path) = url
+        .split_once("://")
+        .with_context(|| format!("The url of a blob storage cache is invalid: {}", url))?;
+    let provider = match scheme {
+        "s3" => Provider::S3,
+        "gs" | "gcs" => Provider::Gcs,
+        "azure" | "az" => Provider::Azure,
+        _ => bail!(
+            "The url of a blob storage cache has to start with s3://, gs:// or azure://: {}",
+            url
+        ),
+    };
+    let (bucket, prefix) = path.split_once('/').unwrap_or((path, ""));
+    if bucket.is_empty() {
+        bail!(
+            "The url of a blob storage cache does not name a bucket: {}",
+            url
+        );
+    }
+    let prefix = prefix.trim_matches('/');
+    Ok(Location {
+        provider,
+        bucket: bucket.to_string(),
+        prefix: if prefix.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", prefix)
+        },
+    })
+}
+
+fn artifact_key(prefix: &str, hash: &str) -> String {
+    format!("{}{}.tar.zst", prefix, hash)
+}
+
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+fn required_env(name: &str) -> anyhow::Result<String> {
+    env(name).with_context(|| format!("{} has to be set to use a blob storage cache", name))
+}
+
+/// Uploads a file in parts of `PART_SIZE` bytes on `concurrency` threads.
+/// `upload_part` is called with the index and the content of each part, and the values it returns are
+/// returned in the order of the parts
+fn upload_parts(
+    path: &Path,
+    len: u64,
+    concurrency: usize,
+    upload_part: impl Fn(usize, Vec<u8>) -> anyhow::Result<String> + Sync,
+) -> anyhow::Result<Vec<String>> {
+    let parts = len.div_ceil(PART_SIZE) as usize;
+    let next = AtomicUsize::new(0);
+    trace!("uploading {:?} in {} parts", path, parts);
+
+    thread::scope(|scope| {
+        let workers = (0..concurrency.min(parts))
+            .map(|_| {
+                scope.spawn(|| -> anyhow::Result<Vec<(usize, String)>> {
+                    let mut file = File::open(path)?;
+                    let mut uploaded = vec![];
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        if index >= parts {
+                            return Ok(uploaded);
+                        }
+                        let offset = index as u64 * PART_SIZE;
+                        let mut data = vec![0; PART_SIZE.min(len - offset) as usize];
+                        file.seek(SeekFrom::Start(offset))?;
+                        file.read_exact(&mut data)?;
+                        match upload_part(index, data) {
+                            Ok(id) => uploaded.push((index, id)),
+                            Err(e) => {
+                                // the other threads stop after their current part
+                                next.store(parts, Ordering::Relaxed);
+                                return Err(e);
+                            }
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut uploaded = vec![];
+        for worker in workers {
+            uploaded.extend(
+                worker
+                    .join()
+                    .map_err(|_| anyhow!("An upload thread panicked"))??,
+            );
+        }
+        uploaded.sort_by_key(|(index, _)| *index);
+        Ok(uploaded.into_iter().map(|(_, id)| id).collect())
+    })
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Percent-encodes everything but the unreserved characters of RFC 3986, and `/` when `keep_slashes` is true
+fn uri_encode(value: &str, keep_slashes: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if keep_slashes => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Splits `<scheme>://<host>/<path>` into the origin (`<scheme>://<host>`), the host and the path
+fn split_url(url: &str) -> anyhow::Result<(String, String, String)> {
+    let url = url.trim_end_matches('/');
+    let (scheme, rest) = url
+        .split_once("://")
+        .with_context(|| format!("The endpoint of a blob storage cache is invalid: {}", url))?;
+    let (host, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, ""),
+    };
+    Ok((
+        format!("{}://{}", scheme, host),
+        host.to_string(),
+        path.to_string(),
+    ))
+}
+
+/// A point in time in UTC, for the date headers of signed requests
+struct UtcTime {
+    year: i64,
+    month: u32,
+    day: u32,
+    hours: u32,
+    minutes: u32,
+    seconds: u32,
+    /// 0 is Sunday
+    weekday: u32,
+}
+
+impl UtcTime {
+    fn from(time: SystemTime) -> Self {
+        let seconds = time
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() as i64)
+            .unwrap_or_default();
+        let days = seconds.div_euclid(86_400);
+        let time_of_day = seconds.rem_euclid(86_400) as u32;
+
+        // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let day_of_era = z.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+        Self {
+            year,
+            month,
+            day,
+            hours: time_of_day / 3600,
+            minutes: time_of_day % 3600 / 60,
+            seconds: time_of_day % 60,
+            // the epoch was a Thursday
+            weekday: (days + 4).rem_euclid(7) as u32,
+        }
+    }
+
+    /// `20130524T000000Z`
+    fn to_iso8601_basic(&self) -> String {
+        format!(
+            "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+            self.year, self.month, self.day, self.hours, self.minutes, self.seconds
+        )
+    }
+
+    /// `Fri, 24 May 2013 00:00:00 GMT`
+    fn to_rfc1123(&self) -> String {
+        const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+        const MONTHS: [&str; 12] = [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ];
+        format!(
+            "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+            WEEKDAYS[self.weekday as usize],
+            self.day,
+            MONTHS[self.month as usize - 1],
+            self.year,
+            self.hours,
+            self.minutes,
+            self.seconds
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_urls() {
+        assert_eq!(
+            parse_url("s3://nx-cache/ci/main/").unwrap(),
+            Location {
+                provider: Provider::S3,
+                bucket: "nx-cache".into(),
+                prefix: "ci/main/".into(),
+            }
+        );
+        assert_eq!(
+            parse_url("gs://nx-cache").unwrap(),
+            Location {
+                provider: Provider::Gcs,
+                bucket: "nx-cache".into(),
+                prefix: "".into(),
+            }
+        );
+        assert_eq!(
+            parse_url("azure://cache/nx").unwrap().provider,
+            Provider::Azure
+        );
+        assert!(parse_url("https://nx-cache").is_err());
+        assert!(parse_url("s3:///prefix").is_err());
+
+        assert_eq!(artifact_key("ci/", "123"), "ci/123.tar.zst");
+    }
+
+    #[test]
+    fn should_format_dates() {
+        let time = UtcTime::from(UNIX_EPOCH + Duration::from_secs(1_369_353_600));
+        assert_eq!(time.to_iso8601_basic(), "20130524T000000Z");
+        assert_eq!(time.to_rfc1123(), "Fri, 24 May 2013 00:00:00 GMT");
+
+        let time = UtcTime::from(UNIX_EPOCH + Duration::from_secs(1_709_210_096));
+        assert_eq!(time.to_iso8601_basic(), "20240229T123456Z");
+        assert_eq!(time.to_rfc1123(), "Thu, 29 Feb 2024 12:34:56 GMT");
+    }
+
+    #[test]
+    fn should_encode_uris() {
+        assert_eq!(
+            uri_encode("ci/main/1 2+3.tar.zst", true),
+            "ci/main/1%202%2B3.tar.zst"
+        );
+        assert_eq!(uri_encode("a/b=", false), "a%2Fb%3D");
+        assert_eq!(
+            split_url("http://127.0.0.1:10000/devstoreaccount1/cache").unwrap(),
+            (
+                "http://127.0.0.1:10000".into(),
+                "127.0.0.1:10000".into(),
+                "/devstoreaccount1/cache".into()
+            )
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use serde_json::{json, Value};
+
+use crate::native::cache::encryption::{decrypted, encrypt_in_place};
+use crate::native::compression::{decoder, encoder, CompressionTarget};
+
+/// The file of a bundle that describes its entries
+const MANIFEST_FILE: &str = "manifest.json";
+const BUNDLE_VERSION: u64 = 1;
+
+/// The task that a cache entry was cached for, as recorded in the Nx database
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundledTask {
+    pub project: String,
+    pub target: String,
+    pub configuration: Option<String>,
+}
+
+/// The metadata of a cache entry in a bundle, which is recorded in the Nx database when it is imported
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundledEntry {
+    pub hash: String,
+    pub code: i16,
+    pub digest: Option<String>,
+    pub hash_version: Option<u32>,
+    pub task: Option<BundledTask>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct CacheImportResult {
+    pub imported: Vec<String>,
+    /// The entries that were already in the cache
+    pub skipped: Vec<String>,
+    /// The entries whose outputs did not match their digest, which were not imported
+    pub corrupted: Vec<String>,
+}
+
+/// Bundles cache entries into a compressed tarball at `destination`: the manifest first,
+/// then the outputs of each entry in `{hash}/` and its terminal output in `terminalOutputs/{hash}`.
+/// The bundle is encrypted when an encryption key is configured, like the other cache artifacts
+pub fn write_bundle(
+    cache_path: &Path,
+    entries: &[BundledEntry],
+    destination: &Path,
+) -> anyhow::Result<()> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // the bundle is renamed into place when complete, so a partial bundle is never imported
+    let temp = destination.with_extension("tmp");
+    let file = BufWriter::new(File::create(&temp)?);
+    let mut builder = tar::Builder::new(encoder(file, CompressionTarget::Local)?);
+    builder.follow_symlinks(false);
+
+    let manifest = serde_json::to_vec_pretty(&manifest(entries))?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest.len() as u64);
+    header.set_mode(0o644);
+    builder.append_data(&mut header, MANIFEST_FILE, manifest.as_slice())?;
+
+    for entry in entries {
+        let task_dir = cache_path.join(&entry.hash);
+        if task_dir.is_dir() {
+            builder.append_dir_all(&entry.hash, &task_dir)?;
+        }
+        let terminal_output = cache_path.join("terminalOutputs").join(&entry.hash);
+        if terminal_output.is_file() {
+            builder.append_path_with_name(
+                &terminal_output,
+                format!("terminalOutputs/{}", entry.hash),
+            )?;
+        }
+    }
+    builder.into_inner()?.finish()?;
+    encrypt_in_place(&temp)?;
+    fs::rename(&temp, destination)?;
+    Ok(())
+}
+
+/// Unpacks a bundle written by [write_bundle] into `staging`, and returns the entries of its manifest
+pub fn read_bundle(source: &Path, staging: &Path) -> anyhow::Result<Vec<BundledEntry>> {
+    let source = decrypted(source)?;
+    let file = BufReader::new(
+        File::open(&*source).with_context(|| format!("Unable to open {:?}", &*source))?,
+    );
+    let mut archive = tar::Archive::new(decoder(file)?);
+    archive.set_preserve_permissions(true);
+    fs::create_dir_all(staging)?;
+    archive.unpack(staging)?;
+
+    let manifest_path = staging.join(MANIFEST_FILE);
+    let manifest: Value = serde_json::from_reader(BufReader::new(
+        File::open(&manifest_path)
+            .context("The bundle has no manifest, it was not exported by Nx")?,
+    ))?;
+    parse_manifest(&manifest)
+}
+
+fn manifest(entries: &[BundledEntry]) -> Value {
+    let entries = entries
+        .iter()
+        .map(|entry| {
+            json!({
+                "hash": entry.hash,
+                "code": entry.code,
+                "digest": entry.digest,
+                "hashVersion": entry.hash_version,
+                "task": entry.task.as_ref().map(|task| json!({
+                    "project": task.project,
+                    "target": task.target,
+                    "configuration": task.configuration,
+                })),
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({ "version": BUNDLE_VERSION, "entries": entries })
+}
+
+fn parse_manifest(manifest: &Value) -> anyhow::Result<Vec<BundledEntry>> {
+    let version = manifest["version"].as_u64();
+    if version != Some(BUNDLE_VERSION) {
+        bail!(
+            "The bundle was exported by an other version of Nx (version {:?} of the format, {} is supported)",
+            version,
+            BUNDLE_VERSION
+        );
+    }
+    let string = |value: &Value| value.as_str().map(String::from);
+    manifest["entries"]
+        .as_array()
+        .context("The manifest of the bundle has no entries")?
+        .iter()
+        .map(|entry| {
+            let hash = string(&entry["hash"]).context("An entry of the bundle has no hash")?;
+            // hashes are paths in the cache directory
+            if hash.is_empty() || !hash.chars().all(|c| c.is_ascii_alphanumeric()) {
+                bail!("The bundle has an invalid hash: {:?}", hash);
+            }
+            let task = &entry["task"];
+            Ok(BundledEntry {
+                code: entry["code"]
+                    .as_i64()
+                    .and_then(|code| i16::try_from(code).ok())
+                    .with_context(|| format!("The entry {} of the bundle has no code", hash))?,
+                digest: string(&entry["digest"]),
+                hash_version: entry["hashVersion"]
+                    .as_u64()
+                    .and_then(|version| u32::try_from(version).ok()),
+                task: string(&task["project"]).zip(string(&task["target"])).map(
+                    |(project, target)| BundledTask {
+                        project,
+                        target,
+                        configuration: string(&task["configuration"]),
+                    },
+                ),
+                hash,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn should_read_the_entries_it_wrote() {
+        let temp = TempDir::new().unwrap();
+        let cache = temp.child("cache");
+        cache.child("123/dist/main.js").write_str("main").unwrap();
+        cache
+            .child("terminalOutputs/123")
+            .write_str("built")
+            .unwrap();
+        let entries = vec![
+            BundledEntry {
+                hash: "123".into(),
+                code: 0,
+                digest: Some("digest".into()),
+                hash_version: Some(2),
+                task: Some(BundledTask {
+                    project: "app".into(),
+                    target: "build".into(),
+                    configuration: None,
+                }),
+            },
+            BundledEntry {
+                hash: "456".into(),
+                code: 1,
+                digest: None,
+                hash_version: None,
+                task: None,
+            },
+        ];
+        let bundle = temp.join("bundle.tar.zst");
+        write_bundle(&cache, &entries, &bundle).unwrap();
+
+        let staging = temp.child("staging");
+        assert_eq!(read_bundle(&bundle, &staging).unwrap(), entries);
+        staging.child("123/dist/main.js").assert("main");
+        staging.child("terminalOutputs/123").assert("built");
+    }
+
+    #[test]
+    fn should_reject_hashes_outside_of_the_cache() {
+        let manifest = json!({
+            "version": BUNDLE_VERSION,
+            "entries": [{ "hash": "../123", "code": 0 }],
+        });
+        assert!(parse_manifest(&manifest).is_err());
+    }
+}
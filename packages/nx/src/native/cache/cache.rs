@@ -1,23 +1,52 @@
+use std::collections::{HashMap, HashSet};
 use std::fs::{create_dir_all, read_to_string, write};
 use std::path::{Path, PathBuf};
-use std::time::Instant;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 use fs_extra::remove_items;
 use napi::bindgen_prelude::*;
-use rusqlite::{params, Connection, OptionalExtension};
-use tracing::trace;
-
+use rusqlite::{params, types::Value, OptionalExtension};
+use tracing::{trace, trace_span};
+
+use crate::native::cache::bundle::{
+    read_bundle, write_bundle, BundledEntry, BundledTask, CacheImportResult,
+};
+use crate::native::cache::content_store::{
+    atomic_restore_enabled, hard_links_enabled, restore_tree, restore_tree_atomically,
+    ContentStore,
+};
 use crate::native::cache::expand_outputs::_expand_outputs;
-use crate::native::cache::file_ops::_copy;
+use crate::native::cache::gc::{
+    entries_over_size, entry_size, CacheGcOptions, CacheGcResult, GcPolicy,
+};
+use crate::native::cache::integrity::{
+    digest_entry, integrity_checks_enabled, quarantine_entry, remove_quarantined_entries,
+    CorruptedCacheEntry,
+};
+use crate::native::cache::output_search::{
+    record_terminal_output, search_terminal_outputs, TaskOutputMatch, TaskOutputSearchRange,
+};
+use crate::native::db::connection::NxDbConnection;
+use crate::native::file_lock::{lock, LockMode, DEFAULT_LOCK_TIMEOUT};
 use crate::native::machine_id::get_machine_id;
+use crate::native::pseudo_terminal::redaction::{command_redactor, Redactor};
+use crate::native::tasks::task_hasher::HASH_VERSION;
 use crate::native::utils::Normalize;
 
+/// The lock of the cache directory, in the directory itself, so `nx reset` can wait for the processes that use the cache
+const CACHE_LOCK_FILE: &str = "nx-cache.lock";
+
 #[napi(object)]
 #[derive(Default, Clone, Debug)]
 pub struct CachedResult {
     pub code: i16,
     pub terminal_output: String,
     pub outputs_path: String,
+    /// The version of the hashing algorithm of the hash the entry was cached with.
+    /// Unknown for entries that were cached before it was recorded, and for remote entries
+    pub hash_version: Option<u32>,
 }
 
 #[napi]
@@ -25,7 +54,13 @@ pub struct NxCache {
     pub cache_directory: String,
     workspace_root: PathBuf,
     cache_path: PathBuf,
-    db: External<Connection>,
+    store: ContentStore,
+    db: External<NxDbConnection>,
+    corrupted_entries: Vec<CorruptedCacheEntry>,
+    /// Held shared while entries are written or restored, and exclusively while they are removed
+    lock_path: PathBuf,
+    /// Masks the secrets of the env of Nx in the terminal output of the tasks before it is stored
+    redactor: Option<Arc<Redactor>>,
 }
 
 #[napi]
@@ -34,45 +69,30 @@ impl NxCache {
     pub fn new(
         workspace_root: String,
         cache_path: String,
-        db_connection: External<Connection>,
+        db_connection: External<NxDbConnection>,
     ) -> anyhow::Result<Self> {
         let machine_id = get_machine_id();
+        let lock_path = PathBuf::from(&cache_path).join(CACHE_LOCK_FILE);
         let cache_path = PathBuf::from(&cache_path).join(machine_id);
 
         create_dir_all(&cache_path)?;
         create_dir_all(cache_path.join("terminalOutputs"))?;
 
-        let r = Self {
+        Ok(Self {
             db: db_connection,
             workspace_root: PathBuf::from(workspace_root),
             cache_directory: cache_path.to_normalized_string(),
+            store: ContentStore::new(&cache_path)?,
             cache_path,
-        };
-
-        r.setup()?;
-
-        Ok(r)
-    }
-
-    fn setup(&self) -> anyhow::Result<()> {
-        self.db
-            .execute_batch(
-                "BEGIN;
-                CREATE TABLE IF NOT EXISTS cache_outputs (
-                    hash    TEXT PRIMARY KEY NOT NULL,
-                    code   INTEGER NOT NULL,
-                    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-                    accessed_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-                    FOREIGN KEY (hash) REFERENCES task_details (hash)
-                );
-            COMMIT;
-            ",
-            )
-            .map_err(anyhow::Error::from)
+            corrupted_entries: vec![],
+            lock_path,
+            redactor: command_redactor(None)?,
+        })
     }
 
     #[napi]
     pub fn get(&mut self, hash: String) -> anyhow::Result<Option<CachedResult>> {
+        let _span = trace_span!("cache_get", hash).entered();
         let start = Instant::now();
         trace!("GET {}", &hash);
         let task_dir = self.cache_path.join(&hash);
@@ -81,33 +101,70 @@ impl NxCache {
 
         let r = self
             .db
+            .connection()?
             .query_row(
                 "UPDATE cache_outputs
                     SET accessed_at = CURRENT_TIMESTAMP
                     WHERE hash = ?1
-                    RETURNING code",
+                    RETURNING code, digest, hash_version",
                 params![hash],
                 |row| {
                     let code: i16 = row.get(0)?;
+                    let digest: Option<String> = row.get(1)?;
+                    let hash_version: Option<u32> = row.get(2)?;
 
                     let start = Instant::now();
                     let terminal_output =
                         read_to_string(terminal_output_path).unwrap_or(String::from(""));
                     trace!("TIME reading terminal outputs {:?}", start.elapsed());
 
-                    Ok(CachedResult {
-                        code,
-                        terminal_output,
-                        outputs_path: task_dir.to_normalized_string(),
-                    })
+                    Ok((
+                        CachedResult {
+                            code,
+                            terminal_output,
+                            outputs_path: task_dir.to_normalized_string(),
+                            hash_version,
+                        },
+                        digest,
+                    ))
                 },
             )
             .optional()
             .map_err(anyhow::Error::new)?;
+
+        // entries cached without a digest can't be verified
+        let r = match r {
+            Some((result, Some(digest))) => self
+                .verify_entry(&hash, &result.terminal_output, digest)?
+                .then_some(result),
+            r => r.map(|(result, _)| result),
+        };
         trace!("GET {} {:?}", &hash, start.elapsed());
         Ok(r)
     }
 
+    /// Whether the cache has an entry for each hash, in a single query. Entries are neither restored nor verified,
+    /// and their access times are not updated, so this can run before a run starts to report what will be replayed
+    #[napi]
+    pub fn check_cache_hits(&self, hashes: Vec<String>) -> anyhow::Result<HashMap<String, bool>> {
+        let values = Rc::new(hashes.iter().cloned().map(Value::from).collect::<Vec<Value>>());
+        let cached = self
+            .db
+            .connection()?
+            .prepare("SELECT hash FROM cache_outputs WHERE hash IN rarray(?1)")?
+            .query_map([values], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<HashSet<_>>>()?;
+        Ok(hashes
+            .into_iter()
+            .map(|hash| {
+                let hit = cached.contains(&hash);
+                (hash, hit)
+            })
+            .collect())
+    }
+
+    /// Stores the outputs and the terminal output of a task. The terminal output is indexed by `task_id` when it is
+    /// given, to search it later. Returns the terminal output as it was stored, with the secrets of the env of Nx masked
     #[napi]
     pub fn put(
         &mut self,
@@ -115,7 +172,10 @@ impl NxCache {
         terminal_output: String,
         outputs: Vec<String>,
         code: i16,
-    ) -> anyhow::Result<()> {
+        task_id: Option<String>,
+    ) -> anyhow::Result<String> {
+        let _span = trace_span!("cache_put", hash).entered();
+        let _lock = lock(&self.lock_path, LockMode::Shared, DEFAULT_LOCK_TIMEOUT)?;
         let task_dir = self.cache_path.join(&hash);
 
         // Remove the task directory
@@ -123,32 +183,51 @@ impl NxCache {
         // Create the task directory again
         create_dir_all(&task_dir)?;
 
-        // Write the terminal outputs into a file
-        write(self.get_task_outputs_path_internal(&hash), terminal_output)?;
+        // Write the terminal outputs into a file, without the secrets that were printed
+        let terminal_output = match &self.redactor {
+            Some(redactor) => redactor.redact(&terminal_output).into_owned(),
+            None => terminal_output,
+        };
+        write(self.get_task_outputs_path_internal(&hash), &terminal_output)?;
 
         // Expand the outputs
         let expanded_outputs = _expand_outputs(&self.workspace_root, outputs)?;
 
-        // Copy the outputs to the cache
+        // Store the outputs in the cache
         for expanded_output in expanded_outputs.iter() {
             let p = self.workspace_root.join(expanded_output);
             if p.exists() {
                 let cached_outputs_dir = task_dir.join(expanded_output);
-                _copy(p, cached_outputs_dir)?;
+                self.store.store_tree(&p, &cached_outputs_dir)?;
             }
         }
 
-        self.record_to_cache(hash, code)?;
-        Ok(())
+        let digest = digest_entry(&task_dir, &terminal_output)?;
+        let size = entry_size(&task_dir, &terminal_output);
+        if let Some(task_id) = task_id {
+            let recorded_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+            record_terminal_output(&self.db, task_id, hash.clone(), recorded_at)?;
+        }
+        self.record_to_cache(hash, code, digest, size, Some(HASH_VERSION))?;
+        Ok(terminal_output)
     }
 
     #[napi]
     pub fn apply_remote_cache_results(&self, hash: String, result: CachedResult) -> anyhow::Result<()> {
+        let _lock = lock(&self.lock_path, LockMode::Shared, DEFAULT_LOCK_TIMEOUT)?;
         let terminal_output = result.terminal_output;
-        write(self.get_task_outputs_path(hash.clone()), terminal_output)?;
+        write(self.get_task_outputs_path(hash.clone()), &terminal_output)?;
+
+        // Deduplicate the downloaded outputs with the local ones
+        let task_dir = self.cache_path.join(&hash);
+        if task_dir.exists() {
+            self.store.adopt_tree(&task_dir)?;
+        }
 
         let code: i16 = result.code;
-        self.record_to_cache(hash, code)?;
+        let digest = digest_entry(&task_dir, &terminal_output)?;
+        let size = entry_size(&task_dir, &terminal_output);
+        self.record_to_cache(hash, code, digest, size, Some(HASH_VERSION))?;
         Ok(())
     }
 
@@ -158,19 +237,138 @@ impl NxCache {
             .join(hash)
     }
 
+    /// Searches the terminal outputs that were stored with a task id for the lines that match the regular expression
+    /// `pattern`, e.g. to find when a warning first appeared. Matches are sorted by when their output was stored
+    #[napi]
+    pub fn search_task_output(
+        &self,
+        pattern: String,
+        range: Option<TaskOutputSearchRange>,
+    ) -> anyhow::Result<Vec<TaskOutputMatch>> {
+        search_terminal_outputs(
+            &self.db,
+            &self.cache_path.join("terminalOutputs"),
+            &pattern,
+            range.unwrap_or_default(),
+        )
+    }
+
     #[napi]
     pub fn get_task_outputs_path(&self, hash: String) -> String {
         self.get_task_outputs_path_internal(&hash).to_normalized_string()
     }
 
-    fn record_to_cache(&self, hash: String, code: i16) -> anyhow::Result<()> {
-        self.db.execute(
-            "INSERT INTO cache_outputs
-                (hash, code)
-                VALUES (?1, ?2)",
-            params![hash, code],
+    fn record_to_cache(
+        &self,
+        hash: String,
+        code: i16,
+        digest: String,
+        size: u64,
+        hash_version: Option<u32>,
+    ) -> anyhow::Result<()> {
+        self.db.write(move |db| {
+            db.execute(
+                "INSERT INTO cache_outputs
+                    (hash, code, digest, size, hash_version)
+                    VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![hash, code, digest, size as i64, hash_version],
+            )?;
+            Ok(())
+        })
+    }
+
+    /// Compares the digest of a cache entry with the one that was recorded when it was cached.
+    /// A corrupted entry is quarantined and removed from the cache, so its task runs again
+    fn verify_entry(
+        &mut self,
+        hash: &str,
+        terminal_output: &str,
+        expected_digest: String,
+    ) -> anyhow::Result<bool> {
+        if !integrity_checks_enabled() {
+            return Ok(true);
+        }
+        let task_dir = self.cache_path.join(hash);
+        let actual_digest = digest_entry(&task_dir, terminal_output)?;
+        if actual_digest == expected_digest {
+            return Ok(true);
+        }
+
+        let quarantine_path = quarantine_entry(
+            &self.cache_path,
+            &task_dir,
+            &self.get_task_outputs_path_internal(hash),
+            hash,
         )?;
-        Ok(())
+        let corrupted_hash = hash.to_string();
+        self.db.write(move |db| {
+            db.execute(
+                "DELETE FROM cache_outputs WHERE hash = ?1",
+                params![corrupted_hash],
+            )?;
+            Ok(())
+        })?;
+        self.corrupted_entries.push(CorruptedCacheEntry {
+            hash: hash.to_string(),
+            expected_digest,
+            actual_digest,
+            quarantine_path: quarantine_path.to_normalized_string(),
+        });
+        Ok(false)
+    }
+
+    /// The cache entries that were found to be corrupted since this was last called.
+    /// They were quarantined, and treated as if they were not in the cache
+    #[napi]
+    pub fn take_corrupted_entries(&mut self) -> Vec<CorruptedCacheEntry> {
+        std::mem::take(&mut self.corrupted_entries)
+    }
+
+    /// Restores the outputs of a cached task into the workspace.
+    /// Files are hard linked from the cache when `hard_links` is true (`NX_CACHE_HARD_LINKS=true` by default),
+    /// and copied otherwise. Directories are restored atomically when `atomic` is true (`NX_CACHE_ATOMIC_RESTORE=true` by default):
+    /// they are restored next to the previous outputs, which they then replace at once, so an interrupted restore does not
+    /// leave a mix of previous and restored files. Returns false when the task is not in the cache, or when its entry is corrupted
+    #[napi]
+    pub fn restore(
+        &mut self,
+        hash: String,
+        outputs: Vec<String>,
+        hard_links: Option<bool>,
+        atomic: Option<bool>,
+    ) -> anyhow::Result<bool> {
+        let _lock = lock(&self.lock_path, LockMode::Shared, DEFAULT_LOCK_TIMEOUT)?;
+        let task_dir = self.cache_path.join(&hash);
+        if !task_dir.exists() {
+            return Ok(false);
+        }
+        let digest: Option<String> = self
+            .db
+            .connection()?
+            .query_row(
+                "UPDATE cache_outputs
+                    SET accessed_at = CURRENT_TIMESTAMP
+                    WHERE hash = ?1
+                    RETURNING digest",
+                params![hash],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+        if let Some(digest) = digest {
+            let terminal_output =
+                read_to_string(self.get_task_outputs_path_internal(&hash)).unwrap_or_default();
+            if !self.verify_entry(&hash, &terminal_output, digest)? {
+                return Ok(false);
+            }
+        }
+        self.restore_outputs(
+            &task_dir,
+            outputs,
+            hard_links.unwrap_or_else(hard_links_enabled),
+            atomic.unwrap_or_else(atomic_restore_enabled),
+        )?;
+        Ok(true)
     }
 
     #[napi]
@@ -179,9 +377,45 @@ impl NxCache {
         cached_result: CachedResult,
         outputs: Vec<String>,
     ) -> anyhow::Result<()> {
-        let outputs_path = Path::new(&cached_result.outputs_path);
+        let _lock = lock(&self.lock_path, LockMode::Shared, DEFAULT_LOCK_TIMEOUT)?;
+        self.restore_outputs(
+            Path::new(&cached_result.outputs_path),
+            outputs,
+            hard_links_enabled(),
+            atomic_restore_enabled(),
+        )
+    }
 
-        let expanded_outputs = _expand_outputs(outputs_path, outputs)?;
+    fn restore_outputs(
+        &self,
+        outputs_path: &Path,
+        outputs: Vec<String>,
+        hard_links: bool,
+        atomic: bool,
+    ) -> anyhow::Result<()> {
+        let _span = trace_span!("cache_restore", outputs_path = ?outputs_path).entered();
+        let mut expanded_outputs = _expand_outputs(outputs_path, outputs)?;
+
+        if atomic {
+            // outputs nested in an other output are restored with it
+            expanded_outputs.sort();
+            let mut restored: Vec<&String> = vec![];
+            for output in expanded_outputs.iter() {
+                if restored.iter().any(|restored| Path::new(output).starts_with(restored)) {
+                    continue;
+                }
+                let src = outputs_path.join(output);
+                let dest = self.workspace_root.join(output);
+                if src.symlink_metadata().is_ok() {
+                    trace!("Restoring {:?} atomically -> {:?}", &src, &dest);
+                    restore_tree_atomically(&src, &dest, hard_links)?;
+                } else {
+                    remove_items(&[&dest])?;
+                }
+                restored.push(output);
+            }
+            return Ok(());
+        }
 
         trace!("Removing expanded outputs: {:?}", &expanded_outputs);
         remove_items(
@@ -192,36 +426,226 @@ impl NxCache {
                 .as_slice(),
         )?;
 
-        trace!("Copying Files from Cache {:?} -> {:?}", &outputs_path, &self.workspace_root);
-        _copy(
-            outputs_path,
-            &self.workspace_root,
+        trace!("Restoring Files from Cache {:?} -> {:?}", &outputs_path, &self.workspace_root);
+        restore_tree(outputs_path, &self.workspace_root, hard_links)?;
+
+        Ok(())
+    }
+
+    /// Bundles the entries of `hashes` (every entry by default) with their metadata from the Nx database into a single
+    /// archive at `destination` (e.g. `cache.tar.zst`), which `import_cache` can seed the cache of an other machine with.
+    /// Returns the hashes that were exported, the ones that are not in the cache are skipped
+    #[napi]
+    pub fn export_cache(
+        &self,
+        hashes: Option<Vec<String>>,
+        destination: String,
+    ) -> anyhow::Result<Vec<String>> {
+        let _lock = lock(&self.lock_path, LockMode::Shared, DEFAULT_LOCK_TIMEOUT)?;
+        let connection = self.db.connection()?;
+        let hashes = match hashes {
+            Some(hashes) => hashes,
+            None => connection
+                .prepare("SELECT hash FROM cache_outputs ORDER BY hash")?
+                .query_map(params![], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<String>>>()?,
+        };
+        let mut query = connection.prepare(
+            "SELECT cache_outputs.code, cache_outputs.digest, cache_outputs.hash_version,
+                task_details.project, task_details.target, task_details.configuration
+                FROM cache_outputs
+                LEFT JOIN task_details ON task_details.hash = cache_outputs.hash
+                WHERE cache_outputs.hash = ?1",
         )?;
+        let mut entries = vec![];
+        for hash in hashes {
+            let entry = query
+                .query_row(params![hash], |row| {
+                    let project: Option<String> = row.get(3)?;
+                    let target: Option<String> = row.get(4)?;
+                    Ok(BundledEntry {
+                        hash: hash.clone(),
+                        code: row.get(0)?,
+                        digest: row.get(1)?,
+                        hash_version: row.get(2)?,
+                        task: project.zip(target).map(|(project, target)| BundledTask {
+                            project,
+                            target,
+                            configuration: row.get(5).ok().flatten(),
+                        }),
+                    })
+                })
+                .optional()?;
+            match entry {
+                Some(entry) => entries.push(entry),
+                None => trace!("{} is not in the cache, it is not exported", hash),
+            }
+        }
+
+        write_bundle(&self.cache_path, &entries, Path::new(&destination))?;
+        Ok(entries.into_iter().map(|entry| entry.hash).collect())
+    }
 
+    /// Adds the entries of a bundle created by `export_cache` to the cache, with their metadata.
+    /// Entries that are already in the cache are skipped, and entries whose outputs do not match their digest are not imported
+    #[napi]
+    pub fn import_cache(&mut self, archive: String) -> anyhow::Result<CacheImportResult> {
+        let _lock = lock(&self.lock_path, LockMode::Shared, DEFAULT_LOCK_TIMEOUT)?;
+        // the bundle is unpacked into the cache directory, so its entries can be moved into place
+        let staging = self
+            .cache_path
+            .join(format!(".import-{}", std::process::id()));
+        remove_items(&[&staging])?;
+        let result = self.import_bundle(Path::new(&archive), &staging);
+        remove_items(&[&staging])?;
+        result
+    }
+
+    fn import_bundle(&self, archive: &Path, staging: &Path) -> anyhow::Result<CacheImportResult> {
+        let mut result = CacheImportResult::default();
+        for entry in read_bundle(archive, staging)? {
+            let cached = self
+                .db
+                .connection()?
+                .query_row(
+                    "SELECT 1 FROM cache_outputs WHERE hash = ?1",
+                    params![entry.hash],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some();
+            if cached {
+                result.skipped.push(entry.hash);
+                continue;
+            }
+
+            let task_dir = self.cache_path.join(&entry.hash);
+            let terminal_output_path = self.get_task_outputs_path_internal(&entry.hash);
+            remove_items(&[&task_dir, &terminal_output_path])?;
+            let staged_dir = staging.join(&entry.hash);
+            if staged_dir.is_dir() {
+                std::fs::rename(&staged_dir, &task_dir)?;
+            } else {
+                create_dir_all(&task_dir)?;
+            }
+            let staged_terminal_output = staging.join("terminalOutputs").join(&entry.hash);
+            if staged_terminal_output.is_file() {
+                std::fs::rename(&staged_terminal_output, &terminal_output_path)?;
+            } else {
+                write(&terminal_output_path, "")?;
+            }
+
+            let terminal_output = read_to_string(&terminal_output_path)?;
+            let digest = digest_entry(&task_dir, &terminal_output)?;
+            if entry.digest.as_ref().is_some_and(|expected| *expected != digest) {
+                remove_items(&[&task_dir, &terminal_output_path])?;
+                result.corrupted.push(entry.hash);
+                continue;
+            }
+            self.store.adopt_tree(&task_dir)?;
+            let size = entry_size(&task_dir, &terminal_output);
+            if let Some(task) = entry.task {
+                let hash = entry.hash.clone();
+                self.db.write(move |db| {
+                    db.execute(
+                        "INSERT OR IGNORE INTO task_details (hash, project, target, configuration)
+                            VALUES (?1, ?2, ?3, ?4)",
+                        params![hash, task.project, task.target, task.configuration],
+                    )?;
+                    Ok(())
+                })?;
+            }
+            self.record_to_cache(entry.hash.clone(), entry.code, digest, size, entry.hash_version)?;
+            result.imported.push(entry.hash);
+        }
+        trace!(
+            "imported {} cache entries, {} were already cached and {} were corrupted",
+            result.imported.len(),
+            result.skipped.len(),
+            result.corrupted.len()
+        );
+        Ok(result)
+    }
+
+    #[napi]
+    pub fn remove_old_cache_records(&mut self) -> anyhow::Result<()> {
+        self.collect_garbage(None)?;
         Ok(())
     }
 
+    /// Removes the entries that were not used for longer than the max age, and then the least recently used
+    /// entries until the cache is smaller than the max size.
+    /// Waits until no other process is writing or restoring entries, and keeps them from doing so while entries are removed
     #[napi]
-    pub fn remove_old_cache_records(&self) -> anyhow::Result<()> {
-        let outdated_cache = self
-            .db
+    pub fn collect_garbage(
+        &mut self,
+        options: Option<CacheGcOptions>,
+    ) -> anyhow::Result<CacheGcResult> {
+        let policy = GcPolicy::from_options(options.unwrap_or_default())?;
+        let _lock = lock(&self.lock_path, LockMode::Exclusive, DEFAULT_LOCK_TIMEOUT)?;
+        let mut connection = self.db.connection()?;
+        let transaction = connection.transaction()?;
+        let mut removed = transaction
             .prepare(
-                "DELETE FROM cache_outputs WHERE accessed_at < datetime('now', '-7 days') RETURNING hash",
+                "DELETE FROM cache_outputs WHERE accessed_at < datetime('now', ?1) RETURNING hash, size",
             )?
-            .query_map(params![], |row| {
-                let hash: String = row.get(0)?;
-
-                Ok(vec![
-                    self.cache_path.join(&hash),
-                    self.get_task_outputs_path_internal(&hash).into(),
-                ])
-            })?
-            .filter_map(anyhow::Result::ok)
-            .flatten()
+            .query_map(
+                params![format!("-{} seconds", policy.max_age.as_secs())],
+                read_entry,
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        if let Some(max_size) = policy.max_size {
+            let entries = transaction
+                .prepare("SELECT hash, size FROM cache_outputs ORDER BY accessed_at DESC")?
+                .query_map(params![], read_entry)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            for entry in entries_over_size(entries, max_size) {
+                transaction.execute(
+                    "DELETE FROM cache_outputs WHERE hash = ?1",
+                    params![entry.0],
+                )?;
+                removed.push(entry);
+            }
+        }
+        let remaining_bytes: i64 = transaction.query_row(
+            "SELECT COALESCE(SUM(size), 0) FROM cache_outputs",
+            params![],
+            |row| row.get(0),
+        )?;
+        transaction.commit()?;
+
+        let outdated_cache = removed
+            .iter()
+            .flat_map(|(hash, _)| {
+                [
+                    self.cache_path.join(hash),
+                    self.get_task_outputs_path_internal(hash),
+                ]
+            })
             .collect::<Vec<_>>();
-
         remove_items(&outdated_cache)?;
+        self.store.prune()?;
+        remove_quarantined_entries(
+            &self.cache_path,
+            SystemTime::now()
+                .checked_sub(policy.max_age)
+                .unwrap_or(UNIX_EPOCH),
+        )?;
 
-        Ok(())
+        trace!(
+            "removed {} cache entries, {} bytes remain",
+            removed.len(),
+            remaining_bytes
+        );
+        Ok(CacheGcResult {
+            removed_entries: removed.len() as u32,
+            freed_bytes: removed.iter().map(|(_, size)| size).sum(),
+            remaining_bytes,
+        })
     }
 }
+
+/// Reads the hash and the size of a cache entry
+fn read_entry(row: &rusqlite::Row) -> rusqlite::Result<(String, i64)> {
+    Ok((row.get(0)?, row.get(1)?))
+}
@@ -0,0 +1,176 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context};
+use fastcdc::v2020::StreamCDC;
+use serde_json::{json, Value};
+
+/// Chunks are cut where the contents of an artifact match a pattern, instead of at fixed offsets,
+/// so the artifacts of a task that changed slightly between runs share most of their chunks
+const MIN_CHUNK_SIZE: u32 = 256 * 1024;
+const AVG_CHUNK_SIZE: u32 = 1024 * 1024;
+const MAX_CHUNK_SIZE: u32 = 4 * 1024 * 1024;
+const MANIFEST_VERSION: u64 = 1;
+
+/// The content type of the manifests that are stored in place of chunked artifacts
+pub const CHUNKED_ARTIFACT_TYPE: &str = "application/vnd.nx.chunked-artifact+json";
+
+/// A chunk of an artifact, which is stored in the content addressable storage of the remote cache by its digest
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    /// The blake3 digest of the uncompressed chunk
+    pub digest: String,
+    pub offset: u64,
+    pub len: u64,
+}
+
+/// Splits a file into content-defined chunks with FastCDC
+pub fn chunk_file(path: &Path) -> anyhow::Result<Vec<Chunk>> {
+    let file = BufReader::new(File::open(path)?);
+    StreamCDC::new(file, MIN_CHUNK_SIZE, AVG_CHUNK_SIZE, MAX_CHUNK_SIZE)
+        .map(|chunk| {
+            let chunk =
+                chunk.map_err(|e| anyhow!("Unable to split {:?} into chunks: {:?}", path, e))?;
+            Ok(Chunk {
+                digest: chunk_digest(&chunk.data),
+                offset: chunk.offset,
+                len: chunk.length as u64,
+            })
+        })
+        .collect()
+}
+
+pub fn chunk_digest(data: &[u8]) -> String {
+    blake3::hash(data).to_hex().to_string()
+}
+
+/// The digests of the chunks, without the chunks that are repeated
+pub fn unique_digests(chunks: &[Chunk]) -> Vec<&str> {
+    let mut seen = HashSet::new();
+    chunks
+        .iter()
+        .map(|chunk| chunk.digest.as_str())
+        .filter(|digest| seen.insert(*digest))
+        .collect()
+}
+
+/// The manifest of a chunked artifact lists its chunks in order, the artifact is their concatenation
+pub fn manifest(chunks: &[Chunk]) -> Value {
+    json!({
+        "version": MANIFEST_VERSION,
+        "size": chunks.iter().map(|chunk| chunk.len).sum::<u64>(),
+        "chunks": chunks
+            .iter()
+            .map(|chunk| json!({ "digest": chunk.digest, "size": chunk.len }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+pub fn parse_manifest(manifest: &[u8]) -> anyhow::Result<Vec<Chunk>> {
+    let manifest: Value =
+        serde_json::from_slice(manifest).context("The manifest of the artifact is invalid")?;
+    let version = manifest["version"].as_u64();
+    if version != Some(MANIFEST_VERSION) {
+        bail!(
+            "The artifact was chunked by an other version of Nx (version {:?} of the manifest, {} is supported)",
+            version,
+            MANIFEST_VERSION
+        );
+    }
+    let mut offset = 0;
+    let chunks = manifest["chunks"]
+        .as_array()
+        .context("The manifest of the artifact has no chunks")?
+        .iter()
+        .map(|chunk| {
+            let digest = chunk["digest"]
+                .as_str()
+                .filter(|digest| {
+                    digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit())
+                })
+                .context("A chunk of the artifact has an invalid digest")?;
+            let len = chunk["size"]
+                .as_u64()
+                .context("A chunk of the artifact has no size")?;
+            let chunk = Chunk {
+                digest: digest.to_string(),
+                offset,
+                len,
+            };
+            offset += len;
+            Ok(chunk)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if manifest["size"].as_u64() != Some(offset) {
+        bail!("The size of the artifact does not match the size of its chunks");
+    }
+    Ok(chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    /// Bytes that do not repeat, so chunks are cut where their contents match
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (state >> 33) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_share_the_chunks_of_similar_artifacts() {
+        let temp = TempDir::new().unwrap();
+        let data = noise(16 * 1024 * 1024, 1);
+        let mut changed = data.clone();
+        changed.splice(8 * 1024 * 1024..8 * 1024 * 1024, noise(1000, 2));
+        temp.child("before.tar").write_binary(&data).unwrap();
+        temp.child("after.tar").write_binary(&changed).unwrap();
+
+        let before = chunk_file(&temp.join("before.tar")).unwrap();
+        let after = chunk_file(&temp.join("after.tar")).unwrap();
+        assert_eq!(
+            before.iter().map(|chunk| chunk.len).sum::<u64>(),
+            data.len() as u64
+        );
+        let shared = unique_digests(&before);
+        let changed_chunks = after
+            .iter()
+            .filter(|chunk| !shared.contains(&chunk.digest.as_str()))
+            .count();
+        assert!(changed_chunks <= 3, "{} chunks changed", changed_chunks);
+    }
+
+    #[test]
+    fn should_parse_the_manifests_it_writes() {
+        let chunks = vec![
+            Chunk {
+                digest: chunk_digest(b"first"),
+                offset: 0,
+                len: 5,
+            },
+            Chunk {
+                digest: chunk_digest(b"second"),
+                offset: 5,
+                len: 6,
+            },
+        ];
+        let manifest = serde_json::to_vec(&manifest(&chunks)).unwrap();
+        assert_eq!(parse_manifest(&manifest).unwrap(), chunks);
+
+        let invalid =
+            json!({ "version": 1, "size": 1, "chunks": [{ "digest": "../x", "size": 1 }] });
+        assert!(parse_manifest(&serde_json::to_vec(&invalid).unwrap()).is_err());
+    }
+}
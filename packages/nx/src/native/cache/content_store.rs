@@ -0,0 +1,444 @@
+use std::fs;
+use std::io::{self, ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use tracing::{trace, warn};
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::native::cache::file_ops::{clone_or_copy, symlink};
+
+const HARD_LINKS_ENV: &str = "NX_CACHE_HARD_LINKS";
+const ATOMIC_RESTORE_ENV: &str = "NX_CACHE_ATOMIC_RESTORE";
+
+/// A counter for the names of the directories swapped in and out by the restores of this process
+static NEXT_SWAP: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether cached outputs should be restored as hard links instead of copies.
+/// Hard links are much faster for large outputs, but a restored file shares its content with the cache,
+/// so tools that write to their outputs in place (instead of replacing them) must not be used with it
+pub fn hard_links_enabled() -> bool {
+    std::env::var(HARD_LINKS_ENV).is_ok_and(|value| value == "true")
+}
+
+/// Whether cached output directories should be restored atomically, see [restore_tree_atomically]
+pub fn atomic_restore_enabled() -> bool {
+    std::env::var(ATOMIC_RESTORE_ENV).is_ok_and(|value| value == "true")
+}
+
+/// Stores the files of cached outputs by the hash of their content, so identical files
+/// of different tasks (or of different runs of a task) are only stored once.
+/// The outputs of a task in the cache are hard links to the objects of the store.
+///
+/// Objects are read-only on unix, so a restored hard link can be replaced but writing to it fails
+pub struct ContentStore {
+    objects: PathBuf,
+}
+
+impl ContentStore {
+    pub fn new(cache_path: &Path) -> anyhow::Result<Self> {
+        let objects = cache_path.join("objects");
+        fs::create_dir_all(&objects)?;
+        Ok(Self { objects })
+    }
+
+    /// Stores a file or a directory, and links its files at `dest`
+    pub fn store_tree(&self, src: &Path, dest: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file_type = fs::symlink_metadata(src)?.file_type();
+        if file_type.is_symlink() {
+            symlink(fs::read_link(src)?, dest)?;
+        } else if file_type.is_dir() {
+            fs::create_dir_all(dest)?;
+            for entry in fs::read_dir(src)? {
+                let entry = entry?;
+                self.store_tree(&entry.path(), &dest.join(entry.file_name()))?;
+            }
+        } else {
+            let object = self.object_path(src)?;
+            if !object.exists() {
+                // copies are renamed into place, so a concurrent reader never sees half of an object
+                let temp = temp_path(&object);
+                clone_or_copy(src, &temp)?;
+                set_readonly(&temp)?;
+                if let Err(e) = fs::rename(&temp, &object) {
+                    fs::remove_file(&temp).ok();
+                    if !object.exists() {
+                        return Err(e.into());
+                    }
+                }
+            }
+            link_or_copy(&object, dest)?;
+        }
+        Ok(())
+    }
+
+    /// Moves the files of a directory that was written outside of the store (e.g. by a remote cache) into the store
+    pub fn adopt_tree(&self, dir: &Path) -> anyhow::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                self.adopt_tree(&path)?;
+            } else if file_type.is_file() && link_count(&path) == Some(1) {
+                let object = self.object_path(&path)?;
+                if object.exists() {
+                    fs::remove_file(&path)?;
+                } else {
+                    fs::rename(&path, &object)?;
+                    set_readonly(&object)?;
+                }
+                link_or_copy(&object, &path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the objects that are not part of any cached outputs anymore.
+    /// Link counts are not available on Windows, where objects are never removed
+    pub fn prune(&self) -> anyhow::Result<usize> {
+        let mut removed = 0;
+        for prefix in fs::read_dir(&self.objects)? {
+            for object in fs::read_dir(prefix?.path())? {
+                let object = object?.path();
+                if link_count(&object) == Some(1) {
+                    set_writable(&object)?;
+                    fs::remove_file(&object)?;
+                    removed += 1;
+                }
+            }
+        }
+        trace!("removed {} unused objects", removed);
+        Ok(removed)
+    }
+
+    fn object_path(&self, file: &Path) -> anyhow::Result<PathBuf> {
+        let hash = hash_content(file)?;
+        // the mode is part of the file that is linked, so executables are separate objects
+        let name = if is_executable(file)? {
+            format!("{}-x", hash)
+        } else {
+            hash
+        };
+        let dir = self.objects.join(&name[..2]);
+        fs::create_dir_all(&dir)?;
+        Ok(dir.join(name))
+    }
+}
+
+/// Restores the cached outputs in `src` to `dest`, with hard links or with writable copies
+pub fn restore_tree(src: &Path, dest: &Path, hard_links: bool) -> anyhow::Result<()> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file_type = fs::symlink_metadata(src)?.file_type();
+    // existing files are replaced instead of written to, as they may be links to the cache
+    if !file_type.is_dir() {
+        remove_file(dest)?;
+    }
+    if file_type.is_symlink() {
+        symlink(fs::read_link(src)?, dest)?;
+    } else if file_type.is_dir() {
+        fs::create_dir_all(dest)?;
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            restore_tree(&entry.path(), &dest.join(entry.file_name()), hard_links)?;
+        }
+    } else if hard_links && cfg!(unix) {
+        link_or_copy(src, dest)?;
+    } else {
+        // clones of the cached files share their blocks until they are written to, like hard links,
+        // but writing to them does not change the cache
+        clone_or_copy(src, dest)?;
+        set_writable(dest)?;
+    }
+    Ok(())
+}
+
+/// Restores a cached directory to `dest` so that an interrupted restore never leaves a mix of previous and restored files.
+/// The outputs are restored into a sibling of `dest`, which then replaces it with two renames.
+/// The previous directory is removed in the background, with the ones left behind by restores that were interrupted before
+/// removing them. Outputs that are not directories are restored with [restore_tree]
+pub fn restore_tree_atomically(src: &Path, dest: &Path, hard_links: bool) -> anyhow::Result<()> {
+    if !fs::symlink_metadata(src)?.is_dir() {
+        return restore_tree(src, dest, hard_links);
+    }
+    let parent = dest.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(parent)?;
+
+    let staging = swap_path(dest, "restoring");
+    if let Err(e) = restore_tree(src, &staging, hard_links) {
+        fs::remove_dir_all(&staging).ok();
+        return Err(e);
+    }
+
+    let previous = swap_path(dest, "previous");
+    let has_previous = match fs::rename(dest, &previous) {
+        Ok(_) => true,
+        Err(e) if e.kind() == ErrorKind::NotFound => false,
+        Err(e) => {
+            fs::remove_dir_all(&staging).ok();
+            return Err(e.into());
+        }
+    };
+    if let Err(e) = fs::rename(&staging, dest) {
+        // puts the previous outputs back, so they are left as they were
+        if has_previous {
+            fs::rename(&previous, dest).ok();
+        }
+        fs::remove_dir_all(&staging).ok();
+        return Err(e.into());
+    }
+    trace!("swapped the restored {:?} in", dest);
+
+    let leftovers = swap_prefix(dest, "previous");
+    let previous_dirs = fs::read_dir(parent)?
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&leftovers))
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    if !previous_dirs.is_empty() {
+        thread::spawn(move || {
+            for previous in previous_dirs {
+                if let Err(e) = fs_extra::remove_items(&[&previous]) {
+                    warn!(
+                        "unable to remove the previous outputs at {:?}: {}",
+                        previous, e
+                    );
+                }
+            }
+        });
+    }
+    Ok(())
+}
+
+/// The path of a hidden sibling of `dest` to swap it with, unique to this process
+fn swap_path(dest: &Path, kind: &str) -> PathBuf {
+    let id = NEXT_SWAP.fetch_add(1, Ordering::Relaxed);
+    dest.with_file_name(format!(
+        "{}{}-{}",
+        swap_prefix(dest, kind),
+        std::process::id(),
+        id
+    ))
+}
+
+fn swap_prefix(dest: &Path, kind: &str) -> String {
+    format!(
+        ".{}.nx-{}-",
+        dest.file_name().unwrap_or_default().to_string_lossy(),
+        kind
+    )
+}
+
+/// Hard links `src` at `dest`, or copies it when it can't be linked (e.g. on another device)
+fn link_or_copy(src: &Path, dest: &Path) -> io::Result<()> {
+    if let Err(e) = fs::hard_link(src, dest) {
+        trace!("unable to link {:?} to {:?}, copying it: {}", dest, src, e);
+        clone_or_copy(src, dest)?;
+    }
+    Ok(())
+}
+
+fn remove_file(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if !metadata.is_dir() => fs::remove_file(path),
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+fn hash_content(path: &Path) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Xxh3::new();
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(len) => hasher.update(&buffer[..len]),
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(format!("{:032x}", hasher.digest128()))
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(format!(".tmp-{}", std::process::id()));
+    path.with_file_name(name)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> io::Result<bool> {
+    use std::os::unix::fs::PermissionsExt;
+    Ok(fs::metadata(path)?.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
+#[cfg(unix)]
+fn link_count(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|metadata| metadata.nlink())
+}
+
+#[cfg(not(unix))]
+fn link_count(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Read-only files can't be removed by most tools on Windows, so objects are only read-only on unix
+#[cfg(unix)]
+fn set_readonly(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = fs::metadata(path)?.permissions().mode();
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & !0o222))
+}
+
+#[cfg(not(unix))]
+fn set_readonly(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_writable(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = fs::metadata(path)?.permissions().mode();
+    fs::set_permissions(path, fs::Permissions::from_mode(mode | 0o200))
+}
+
+#[cfg(not(unix))]
+fn set_writable(path: &Path) -> io::Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    #[allow(clippy::permissions_set_readonly_false)]
+    permissions.set_readonly(false);
+    fs::set_permissions(path, permissions)
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn should_store_identical_files_once() {
+        let temp = TempDir::new().unwrap();
+        temp.child("dist/a.js").write_str("same").unwrap();
+        temp.child("dist/nested/b.js").write_str("same").unwrap();
+        temp.child("dist/c.js").write_str("other").unwrap();
+
+        let store = ContentStore::new(&temp.join("cache")).unwrap();
+        store
+            .store_tree(&temp.join("dist"), &temp.join("cache/hash/dist"))
+            .unwrap();
+
+        temp.child("cache/hash/dist/nested/b.js").assert("same");
+        let objects = fs::read_dir(temp.join("cache/objects"))
+            .unwrap()
+            .flat_map(|prefix| fs::read_dir(prefix.unwrap().path()).unwrap())
+            .count();
+        assert_eq!(objects, 2);
+
+        #[cfg(unix)]
+        assert_eq!(link_count(&temp.join("cache/hash/dist/a.js")), Some(3));
+    }
+
+    #[test]
+    fn should_restore_outputs() {
+        let temp = TempDir::new().unwrap();
+        temp.child("dist/a.js").write_str("a").unwrap();
+        let store = ContentStore::new(&temp.join("cache")).unwrap();
+        store
+            .store_tree(&temp.join("dist"), &temp.join("cache/hash/dist"))
+            .unwrap();
+
+        restore_tree(&temp.join("cache/hash/dist"), &temp.join("copied"), false).unwrap();
+        temp.child("copied/a.js").assert("a");
+        fs::write(temp.join("copied/a.js"), "changed").unwrap();
+        temp.child("cache/hash/dist/a.js").assert("a");
+
+        restore_tree(&temp.join("cache/hash/dist"), &temp.join("copied"), true).unwrap();
+        temp.child("copied/a.js").assert("a");
+        temp.child("cache/hash/dist/a.js").assert("a");
+        // the hard links share the read-only permissions of the stored files
+        #[cfg(unix)]
+        assert!(fs::metadata(temp.join("copied/a.js"))
+            .unwrap()
+            .permissions()
+            .readonly());
+    }
+
+    #[test]
+    fn should_swap_restored_directories_in() {
+        let temp = TempDir::new().unwrap();
+        temp.child("cache/hash/dist/a.js").write_str("a").unwrap();
+        temp.child("cache/hash/dist/nested/b.js")
+            .write_str("b")
+            .unwrap();
+        temp.child("workspace/dist/a.js")
+            .write_str("previous")
+            .unwrap();
+        temp.child("workspace/dist/stale.js")
+            .write_str("stale")
+            .unwrap();
+        temp.child("workspace/.dist.nx-previous-1-0/interrupted.js")
+            .write_str("interrupted")
+            .unwrap();
+
+        let dest = temp.join("workspace/dist");
+        restore_tree_atomically(&temp.join("cache/hash/dist"), &dest, false).unwrap();
+        temp.child("workspace/dist/a.js").assert("a");
+        temp.child("workspace/dist/nested/b.js").assert("b");
+        assert!(!dest.join("stale.js").exists());
+
+        // the previous directories are removed in the background
+        let siblings = || {
+            fs::read_dir(temp.join("workspace"))
+                .unwrap()
+                .flatten()
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+        for _ in 0..100 {
+            if siblings().len() == 1 {
+                break;
+            }
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(siblings(), ["dist"]);
+
+        // outputs that did not exist are restored too
+        fs::remove_dir_all(&dest).unwrap();
+        restore_tree_atomically(&temp.join("cache/hash/dist"), &dest, false).unwrap();
+        temp.child("workspace/dist/a.js").assert("a");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn should_prune_unused_objects() {
+        let temp = TempDir::new().unwrap();
+        temp.child("dist/a.js").write_str("a").unwrap();
+        temp.child("remote/b.js").write_str("b").unwrap();
+        let store = ContentStore::new(&temp.join("cache")).unwrap();
+        store
+            .store_tree(&temp.join("dist"), &temp.join("cache/hash/dist"))
+            .unwrap();
+        store.adopt_tree(&temp.join("remote")).unwrap();
+        assert_eq!(link_count(&temp.join("remote/b.js")), Some(2));
+
+        assert_eq!(store.prune().unwrap(), 0);
+        fs::remove_dir_all(temp.join("cache/hash")).unwrap();
+        assert_eq!(store.prune().unwrap(), 1);
+    }
+}
@@ -0,0 +1,318 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, Context};
+use sha2::{Digest, Sha256};
+
+const KEY_ENV: &str = "NX_CACHE_ENCRYPTION_KEY";
+const KEYCHAIN_ENV: &str = "NX_CACHE_ENCRYPTION_KEYCHAIN";
+
+/// Encrypted files start with this, followed by a random nonce prefix and the encrypted chunks
+const MAGIC: &[u8; 6] = b"NXENC1";
+/// The nonce of a chunk is the prefix, the index of the chunk (4 bytes) and whether it is the last chunk (1 byte)
+const NONCE_PREFIX_LEN: usize = 7;
+const CHUNK_SIZE: usize = 1024 * 1024;
+const TAG_LEN: usize = 16;
+
+/// The cipher of cache artifacts, when `NX_CACHE_ENCRYPTION_KEY` or `NX_CACHE_ENCRYPTION_KEYCHAIN` is set.
+/// Keys of any length are accepted, they are normalized with SHA-256
+pub fn configured_cipher() -> anyhow::Result<Option<Aes256Gcm>> {
+    if let Some(key) = std::env::var(KEY_ENV).ok().filter(|key| !key.is_empty()) {
+        return Ok(Some(cipher(&key)));
+    }
+    match std::env::var(KEYCHAIN_ENV)
+        .ok()
+        .filter(|name| !name.is_empty())
+    {
+        Some(name) => Ok(Some(cipher(&keychain::read_key(&name)?))),
+        None => Ok(None),
+    }
+}
+
+fn cipher(key: &str) -> Aes256Gcm {
+    Aes256Gcm::new_from_slice(&Sha256::digest(key.as_bytes()))
+        .expect("SHA-256 digests are valid AES-256 keys")
+}
+
+/// Encrypts a file in place when an encryption key is configured
+pub fn encrypt_in_place(path: &Path) -> anyhow::Result<()> {
+    let Some(cipher) = configured_cipher()? else {
+        return Ok(());
+    };
+    let temp = sibling_path(path, "encrypting");
+    let result = (|| -> anyhow::Result<()> {
+        let mut writer = BufWriter::new(File::create(&temp)?);
+        encrypt(BufReader::new(File::open(path)?), &mut writer, &cipher)?;
+        writer.flush()?;
+        Ok(())
+    })();
+    match result {
+        Ok(()) => fs::rename(&temp, path).map_err(anyhow::Error::from),
+        Err(e) => {
+            fs::remove_file(&temp).ok();
+            Err(e)
+        }
+    }
+}
+
+/// Encrypts bytes when an encryption key is configured, like [encrypt_in_place]
+pub fn encrypt_bytes(data: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    let Some(cipher) = configured_cipher()? else {
+        return Ok(data);
+    };
+    let mut encrypted = vec![];
+    encrypt(data.as_slice(), &mut encrypted, &cipher)?;
+    Ok(encrypted)
+}
+
+/// The plaintext of bytes encrypted by [encrypt_bytes]: the bytes themselves when they are not encrypted
+pub fn decrypt_bytes(data: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    if !data.starts_with(MAGIC) {
+        return Ok(data);
+    }
+    let cipher = configured_cipher()?.with_context(|| {
+        format!(
+            "A cache artifact is encrypted, set {} or {} to decrypt it",
+            KEY_ENV, KEYCHAIN_ENV
+        )
+    })?;
+    let mut plaintext = vec![];
+    decrypt(data.as_slice(), &mut plaintext, &cipher)
+        .context("Unable to decrypt a cache artifact")?;
+    Ok(plaintext)
+}
+
+/// A file that can be read in plaintext, which is removed when dropped if it is a decrypted copy
+pub struct PlaintextFile {
+    path: PathBuf,
+    temporary: bool,
+}
+
+impl Deref for PlaintextFile {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for PlaintextFile {
+    fn drop(&mut self) {
+        if self.temporary {
+            fs::remove_file(&self.path).ok();
+        }
+    }
+}
+
+/// The plaintext of a cache artifact: the artifact itself when it is not encrypted, and a decrypted copy when it is
+pub fn decrypted(path: &Path) -> anyhow::Result<PlaintextFile> {
+    if !is_encrypted(path)? {
+        return Ok(PlaintextFile {
+            path: path.to_path_buf(),
+            temporary: false,
+        });
+    }
+    let cipher = configured_cipher()?.with_context(|| {
+        format!(
+            "{:?} is encrypted, set {} or {} to decrypt it",
+            path, KEY_ENV, KEYCHAIN_ENV
+        )
+    })?;
+
+    let plaintext = PlaintextFile {
+        path: sibling_path(path, "decrypted"),
+        temporary: true,
+    };
+    let mut writer = BufWriter::new(File::create(&*plaintext)?);
+    decrypt(BufReader::new(File::open(path)?), &mut writer, &cipher)
+        .with_context(|| format!("Unable to decrypt {:?}", path))?;
+    writer.flush()?;
+    Ok(plaintext)
+}
+
+fn is_encrypted(path: &Path) -> io::Result<bool> {
+    let mut magic = [0; MAGIC.len()];
+    let len = read_full(&mut File::open(path)?, &mut magic)?;
+    Ok(len == magic.len() && &magic == MAGIC)
+}
+
+fn encrypt(
+    mut reader: impl Read,
+    mut writer: impl Write,
+    cipher: &Aes256Gcm,
+) -> anyhow::Result<()> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let prefix = &nonce[..NONCE_PREFIX_LEN];
+    writer.write_all(MAGIC)?;
+    writer.write_all(prefix)?;
+
+    for_each_chunk(&mut reader, CHUNK_SIZE, |index, chunk, last| {
+        let ciphertext = cipher
+            .encrypt(&chunk_nonce(prefix, index, last), chunk)
+            .map_err(|_| anyhow!("Unable to encrypt a chunk of a cache artifact"))?;
+        writer.write_all(&ciphertext)?;
+        Ok(())
+    })
+}
+
+fn decrypt(
+    mut reader: impl Read,
+    mut writer: impl Write,
+    cipher: &Aes256Gcm,
+) -> anyhow::Result<()> {
+    let mut header = [0; MAGIC.len() + NONCE_PREFIX_LEN];
+    if read_full(&mut reader, &mut header)? != header.len() || &header[..MAGIC.len()] != MAGIC {
+        anyhow::bail!("The file is not an encrypted cache artifact");
+    }
+    let prefix = &header[MAGIC.len()..];
+
+    for_each_chunk(&mut reader, CHUNK_SIZE + TAG_LEN, |index, chunk, last| {
+        // the nonce of the last chunk is different, so a truncated artifact does not decrypt
+        let plaintext = cipher
+            .decrypt(&chunk_nonce(prefix, index, last), chunk)
+            .map_err(|_| {
+                anyhow!("The artifact was encrypted with another key, or it is corrupted")
+            })?;
+        writer.write_all(&plaintext)?;
+        Ok(())
+    })
+}
+
+/// Calls `f` for each chunk of `size` bytes of `reader`, with its index and whether it is the last chunk.
+/// An empty reader has one empty chunk
+fn for_each_chunk(
+    reader: &mut impl Read,
+    size: usize,
+    mut f: impl FnMut(u32, &[u8], bool) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut current = vec![0; size];
+    let mut next = vec![0; size];
+    let mut current_len = read_full(reader, &mut current)?;
+    let mut index = 0u32;
+    loop {
+        let next_len = if current_len == size {
+            read_full(reader, &mut next)?
+        } else {
+            0
+        };
+        let last = next_len == 0;
+        f(index, &current[..current_len], last)?;
+        if last {
+            return Ok(());
+        }
+        std::mem::swap(&mut current, &mut next);
+        current_len = next_len;
+        index = index
+            .checked_add(1)
+            .context("The cache artifact is too big to be encrypted")?;
+    }
+}
+
+fn chunk_nonce(prefix: &[u8], index: u32, last: bool) -> Nonce<aes_gcm::aead::consts::U12> {
+    let mut nonce = [0; 12];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_LEN..11].copy_from_slice(&index.to_be_bytes());
+    nonce[11] = last as u8;
+    nonce.into()
+}
+
+/// Reads until `buffer` is full or the reader ends, and returns how many bytes were read
+fn read_full(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buffer.len() {
+        match reader.read(&mut buffer[len..]) {
+            Ok(0) => break,
+            Ok(read) => len += read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(len)
+}
+
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(format!(".{}-{}", suffix, std::process::id()));
+    path.with_file_name(name)
+}
+
+mod keychain {
+    use anyhow::Context;
+
+    /// The account of the keychain entries of Nx, their service is the name given in `NX_CACHE_ENCRYPTION_KEYCHAIN`
+    const USER: &str = "nx";
+
+    /// Reads a key from the keychain of macOS, the credential manager of Windows,
+    /// or the kernel keyring of Linux
+    pub fn read_key(name: &str) -> anyhow::Result<String> {
+        keyring::Entry::new(name, USER)
+            .and_then(|entry| entry.get_password())
+            .with_context(|| {
+                format!(
+                    "Unable to read the cache encryption key {:?} from the keychain",
+                    name
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn roundtrip(
+        data: &[u8],
+        encryption_key: &str,
+        decryption_key: &str,
+    ) -> anyhow::Result<Vec<u8>> {
+        let mut encrypted = vec![];
+        encrypt(Cursor::new(data), &mut encrypted, &cipher(encryption_key))?;
+        assert!(encrypted.starts_with(MAGIC));
+        let mut decrypted = vec![];
+        decrypt(
+            Cursor::new(encrypted),
+            &mut decrypted,
+            &cipher(decryption_key),
+        )?;
+        Ok(decrypted)
+    }
+
+    #[test]
+    fn should_encrypt_and_decrypt_artifacts() {
+        assert_eq!(roundtrip(b"", "key", "key").unwrap(), b"");
+        assert_eq!(roundtrip(b"outputs", "key", "key").unwrap(), b"outputs");
+
+        let big = (0..CHUNK_SIZE * 2 + 100)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        assert_eq!(roundtrip(&big, "key", "key").unwrap(), big);
+        let exact = vec![7; CHUNK_SIZE];
+        assert_eq!(roundtrip(&exact, "key", "key").unwrap(), exact);
+
+        assert!(roundtrip(b"outputs", "key", "another key").is_err());
+    }
+
+    #[test]
+    fn should_reject_truncated_and_modified_artifacts() {
+        let key = cipher("key");
+        let data = vec![1; CHUNK_SIZE + 10];
+        let mut encrypted = vec![];
+        encrypt(Cursor::new(&data), &mut encrypted, &key).unwrap();
+
+        let truncated = &encrypted[..MAGIC.len() + NONCE_PREFIX_LEN + CHUNK_SIZE + TAG_LEN];
+        assert!(decrypt(Cursor::new(truncated), io::sink(), &key).is_err());
+
+        let mut modified = encrypted.clone();
+        let last = modified.len() - 1;
+        modified[last] ^= 1;
+        assert!(decrypt(Cursor::new(modified), io::sink(), &key).is_err());
+
+        assert!(decrypt(Cursor::new(b"outputs"), io::sink(), &key).is_err());
+    }
+}
@@ -5,7 +5,7 @@ use tracing::trace;
 use crate::native::glob::{build_glob_set, contains_glob_pattern, glob_transform::partition_glob};
 use crate::native::logger::enable_logger;
 use crate::native::utils::Normalize;
-use crate::native::walker::{nx_walker, nx_walker_sync};
+use crate::native::walker::{nx_walker, nx_walker_sync, SymlinkPolicy};
 
 #[napi]
 pub fn expand_outputs(directory: String, entries: Vec<String>) -> anyhow::Result<Vec<String>> {
@@ -59,7 +59,7 @@ where
     trace!(?negated_globs, ?regular_globs, "Expanding globs");
 
     let glob_set = build_glob_set(&regular_globs)?;
-    let found_paths = nx_walker_sync(directory, Some(&negated_globs))
+    let found_paths = nx_walker_sync(directory, Some(&negated_globs), SymlinkPolicy::record)
         .filter_map(|path| {
             if glob_set.is_match(&path) {
                 Some(path.to_normalized_string())
@@ -125,7 +125,7 @@ pub fn get_files_for_outputs(
             let glob_set = build_glob_set(&patterns)?;
             trace!("walking directory: {:?}", root_path);
 
-            let found_paths: Vec<String> = nx_walker(&root_path, false)
+            let found_paths: Vec<String> = nx_walker(&root_path, false, SymlinkPolicy::follow)
                 .filter_map(|file| {
                     if glob_set.is_match(&file.normalized_path) {
                         Some(
@@ -149,7 +149,7 @@ pub fn get_files_for_outputs(
         for dir in directories {
             let dir = PathBuf::from(dir);
             let dir_path = directory.join(&dir);
-            let files_in_dir = nx_walker(&dir_path, false).filter_map(|e| {
+            let files_in_dir = nx_walker(&dir_path, false, SymlinkPolicy::follow).filter_map(|e| {
                 let path = dir_path.join(&e.normalized_path);
 
                 if path.is_file() {
@@ -2,6 +2,7 @@ use std::path::{Path, PathBuf};
 use std::{fs, io};
 
 use fs_extra::error::ErrorKind;
+use tracing::trace;
 
 #[napi]
 pub fn remove(src: String) -> anyhow::Result<()> {
@@ -31,24 +32,52 @@ pub fn _copy<P>(src: P, dest: P) -> anyhow::Result<()> where P: AsRef<Path> {
     } else if src.is_symlink() {
         symlink(fs::read_link(src)?, dest)?;
     } else {
+        clone_or_copy(&src, &dest)?;
+    }
+
+    Ok(())
+}
+
+/// Copies a file by cloning it when the file system supports it: with `FICLONE` on Linux (btrfs, XFS),
+/// `clonefile` on macOS (APFS) and block cloning on Windows (ReFS, Dev Drives). A clone shares the blocks
+/// of the file until one of them is written to, so it is instant and does not take more space.
+/// The file is copied on other file systems, and when `dest` already exists, since only new files can be clones
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn clone_or_copy(src: &Path, dest: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(dest).is_ok() {
         fs::copy(src, dest)?;
+        return Ok(());
+    }
+    match reflink_copy::reflink_or_copy(src, dest)? {
+        Some(_) => trace!("copied {:?} to {:?}", src, dest),
+        None => {
+            trace!("cloned {:?} to {:?}", src, dest);
+            // fs::copy keeps the permissions of the file, but clones are created with the default permissions
+            fs::set_permissions(dest, fs::metadata(src)?.permissions())?;
+        }
     }
+    Ok(())
+}
 
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn clone_or_copy(src: &Path, dest: &Path) -> io::Result<()> {
+    trace!("copying {:?} to {:?}", src, dest);
+    fs::copy(src, dest)?;
     Ok(())
 }
 
 #[cfg(windows)]
-fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> io::Result<()> {
+pub(crate) fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> io::Result<()> {
     std::os::windows::fs::symlink_file(original, link)
 }
 
 #[cfg(unix)]
-fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> io::Result<()> {
+pub(crate) fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> io::Result<()> {
     std::os::unix::fs::symlink(original, link)
 }
 
 #[cfg(target_os = "wasi")]
-fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> io::Result<()> {
+pub(crate) fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> io::Result<()> {
     std::os::wasi::fs::symlink_path(original, link)
 }
 
@@ -65,7 +94,7 @@ fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<()>
                 dst.as_ref().join(entry.file_name()),
             )?;
         } else {
-            fs::copy(entry.path(), dst.as_ref().join(entry.file_name()))?;
+            clone_or_copy(&entry.path(), &dst.as_ref().join(entry.file_name()))?;
         }
     }
     Ok(())
@@ -111,6 +140,27 @@ mod test {
         assert!(temp.child("new-parent/file.txt").exists());
     }
 
+    #[test]
+    fn should_clone_or_copy_files_with_their_permissions() {
+        let temp = TempDir::new().unwrap();
+        let src = temp.child("src.txt");
+        src.write_str("content").unwrap();
+        let mut permissions = fs::metadata(&src).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&src, permissions).unwrap();
+
+        let dest = temp.child("dest.txt");
+        clone_or_copy(&src, &dest).unwrap();
+        dest.assert("content");
+        assert!(fs::metadata(&dest).unwrap().permissions().readonly());
+
+        // existing files are overwritten
+        let existing = temp.child("existing.txt");
+        existing.write_str("previous content").unwrap();
+        clone_or_copy(&src, &existing).unwrap();
+        existing.assert("content");
+    }
+
     #[test]
     fn should_copy_symlinks() {
         let temp = TempDir::new().unwrap();
@@ -0,0 +1,258 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::{Component, Path};
+
+use anyhow::{bail, Context};
+use serde_json::{json, Value};
+use walkdir::WalkDir;
+
+use crate::native::cache::chunking::chunk_digest;
+
+const MANIFEST_VERSION: u64 = 1;
+
+/// The content type of the manifests that are stored in place of artifacts that are stored file by file
+pub const FILE_TREE_TYPE: &str = "application/vnd.nx.file-tree+json";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TreeEntryKind {
+    Directory,
+    /// A file stored in the content addressable storage of the remote cache by its digest
+    File {
+        digest: String,
+        size: u64,
+        mode: u32,
+    },
+    Symlink {
+        target: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeEntry {
+    /// The path of the entry in the outputs, with `/` separators
+    pub path: String,
+    pub kind: TreeEntryKind,
+}
+
+/// The outputs of a task with its exit code and terminal output, which are restored file by file
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileTree {
+    pub code: i16,
+    pub terminal_output: String,
+    /// Sorted by path, so directories come before their contents
+    pub entries: Vec<TreeEntry>,
+}
+
+/// Lists the outputs in `task_dir`, with the blake3 digests of their files
+pub fn read_tree(task_dir: &Path, code: i16, terminal_output: &str) -> anyhow::Result<FileTree> {
+    let mut entries = vec![];
+    if task_dir.is_dir() {
+        for entry in WalkDir::new(task_dir).min_depth(1).sort_by_file_name() {
+            let entry = entry?;
+            let path = entry
+                .path()
+                .strip_prefix(task_dir)?
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let kind = if entry.path_is_symlink() {
+                TreeEntryKind::Symlink {
+                    target: fs::read_link(entry.path())?
+                        .to_string_lossy()
+                        .replace('\\', "/"),
+                }
+            } else if entry.file_type().is_dir() {
+                TreeEntryKind::Directory
+            } else {
+                let metadata = entry.metadata()?;
+                TreeEntryKind::File {
+                    digest: file_digest(entry.path())?,
+                    size: metadata.len(),
+                    mode: file_mode(&metadata),
+                }
+            };
+            entries.push(TreeEntry { path, kind });
+        }
+    }
+    Ok(FileTree {
+        code,
+        terminal_output: terminal_output.to_string(),
+        entries,
+    })
+}
+
+pub fn file_digest(path: &Path) -> anyhow::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Whether `data` is the content of a file of the tree
+pub fn matches_digest(data: &[u8], digest: &str, size: u64) -> bool {
+    data.len() as u64 == size && chunk_digest(data) == digest
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o777
+}
+
+#[cfg(not(unix))]
+fn file_mode(_: &fs::Metadata) -> u32 {
+    0o644
+}
+
+pub fn tree_manifest(tree: &FileTree) -> Value {
+    let entries = tree
+        .entries
+        .iter()
+        .map(|entry| match &entry.kind {
+            TreeEntryKind::Directory => json!({ "path": entry.path, "type": "directory" }),
+            TreeEntryKind::File { digest, size, mode } => json!({
+                "path": entry.path,
+                "type": "file",
+                "digest": digest,
+                "size": size,
+                "mode": mode,
+            }),
+            TreeEntryKind::Symlink { target } => json!({
+                "path": entry.path,
+                "type": "symlink",
+                "target": target,
+            }),
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "version": MANIFEST_VERSION,
+        "code": tree.code,
+        "terminalOutput": tree.terminal_output,
+        "entries": entries,
+    })
+}
+
+pub fn parse_tree_manifest(manifest: &[u8]) -> anyhow::Result<FileTree> {
+    let manifest: Value =
+        serde_json::from_slice(manifest).context("The manifest of the artifact is invalid")?;
+    let version = manifest["version"].as_u64();
+    if version != Some(MANIFEST_VERSION) {
+        bail!(
+            "The artifact was stored by an other version of Nx (version {:?} of the manifest, {} is supported)",
+            version,
+            MANIFEST_VERSION
+        );
+    }
+    let entries = manifest["entries"]
+        .as_array()
+        .context("The manifest of the artifact has no entries")?
+        .iter()
+        .map(parse_entry)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(FileTree {
+        code: manifest["code"]
+            .as_i64()
+            .and_then(|code| i16::try_from(code).ok())
+            .context("The artifact does not contain an exit code")?,
+        terminal_output: manifest["terminalOutput"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        entries,
+    })
+}
+
+fn parse_entry(entry: &Value) -> anyhow::Result<TreeEntry> {
+    let path = entry["path"]
+        .as_str()
+        .context("An entry of the artifact has no path")?;
+    // entries are restored into the cache directory, they can not be anywhere else
+    if path.is_empty()
+        || Path::new(path)
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        bail!("The artifact contains an invalid path: {:?}", path);
+    }
+    let kind = match entry["type"].as_str() {
+        Some("directory") => TreeEntryKind::Directory,
+        Some("file") => TreeEntryKind::File {
+            digest: entry["digest"]
+                .as_str()
+                .filter(|digest| {
+                    digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit())
+                })
+                .with_context(|| {
+                    format!("The file {} of the artifact has an invalid digest", path)
+                })?
+                .to_string(),
+            size: entry["size"]
+                .as_u64()
+                .with_context(|| format!("The file {} of the artifact has no size", path))?,
+            mode: entry["mode"]
+                .as_u64()
+                .map_or(0o644, |mode| mode as u32 & 0o777),
+        },
+        Some("symlink") => TreeEntryKind::Symlink {
+            target: entry["target"]
+                .as_str()
+                .with_context(|| format!("The symlink {} of the artifact has no target", path))?
+                .to_string(),
+        },
+        kind => bail!(
+            "The entry {} of the artifact has an unknown type: {:?}",
+            path,
+            kind
+        ),
+    };
+    Ok(TreeEntry {
+        path: path.to_string(),
+        kind,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn should_parse_the_manifests_it_writes() {
+        let temp = TempDir::new().unwrap();
+        temp.child("dist/main.js").write_str("main").unwrap();
+        temp.child("dist/assets/logo.svg")
+            .write_str("logo")
+            .unwrap();
+
+        let tree = read_tree(&temp, 1, "> built").unwrap();
+        assert_eq!(
+            tree.entries
+                .iter()
+                .map(|entry| entry.path.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "dist",
+                "dist/assets",
+                "dist/assets/logo.svg",
+                "dist/main.js"
+            ]
+        );
+        let TreeEntryKind::File { digest, size, .. } = &tree.entries[3].kind else {
+            panic!("dist/main.js is not a file");
+        };
+        assert!(matches_digest(b"main", digest, *size));
+
+        let manifest = serde_json::to_vec(&tree_manifest(&tree)).unwrap();
+        assert_eq!(parse_tree_manifest(&manifest).unwrap(), tree);
+    }
+
+    #[test]
+    fn should_reject_paths_outside_of_the_outputs() {
+        for path in ["../main.js", "/main.js", "dist/../../main.js"] {
+            let entry = json!({ "path": path, "type": "directory" });
+            assert!(parse_entry(&entry).is_err(), "{} was accepted", path);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use walkdir::WalkDir;
+
+const MAX_AGE_ENV: &str = "NX_CACHE_MAX_AGE";
+const MAX_SIZE_ENV: &str = "NX_CACHE_MAX_SIZE";
+/// Entries that were not used for a week are removed by default
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct CacheGcOptions {
+    /// Entries that were not used for longer than this are removed, e.g. `12h` or `7d`.
+    /// `NX_CACHE_MAX_AGE`, or 7 days by default
+    pub max_age: Option<String>,
+    /// The least recently used entries are removed until the cache is smaller than this, e.g. `512MB` or `10GB`.
+    /// `NX_CACHE_MAX_SIZE` by default, the size of the cache is not limited when neither is set
+    pub max_size: Option<String>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct CacheGcResult {
+    pub removed_entries: u32,
+    /// The size of the removed entries, in bytes
+    pub freed_bytes: i64,
+    /// The size of the remaining entries, in bytes
+    pub remaining_bytes: i64,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct GcPolicy {
+    pub max_age: Duration,
+    pub max_size: Option<u64>,
+}
+
+impl GcPolicy {
+    pub fn from_options(options: CacheGcOptions) -> anyhow::Result<Self> {
+        let max_age = options
+            .max_age
+            .or_else(|| std::env::var(MAX_AGE_ENV).ok())
+            .map(|max_age| parse_duration(&max_age))
+            .transpose()?
+            .unwrap_or(DEFAULT_MAX_AGE);
+        let max_size = options
+            .max_size
+            .or_else(|| std::env::var(MAX_SIZE_ENV).ok())
+            .map(|max_size| parse_size(&max_size))
+            .transpose()?;
+        Ok(Self { max_age, max_size })
+    }
+}
+
+/// The size of the outputs and of the terminal output of a cache entry, in bytes.
+/// Files that are stored once for several entries are counted for each of them
+pub fn entry_size(task_dir: &Path, terminal_output: &str) -> u64 {
+    let outputs = WalkDir::new(task_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum::<u64>();
+    outputs + terminal_output.len() as u64
+}
+
+/// The entries to remove so that the remaining entries fit in `max_size`.
+/// `entries` are sorted from the most recently used, with their sizes
+pub fn entries_over_size(entries: Vec<(String, i64)>, max_size: u64) -> Vec<(String, i64)> {
+    let mut total = 0u64;
+    entries
+        .into_iter()
+        .filter(|(_, size)| {
+            total = total.saturating_add(*size as u64);
+            total > max_size
+        })
+        .collect()
+}
+
+/// Parses sizes like `1073741824` (bytes), `512MB` or `1.5GB`. Units are powers of 1024
+pub fn parse_size(value: &str) -> anyhow::Result<u64> {
+    let (number, unit) = split_unit(value);
+    let multiplier: u64 = match unit.to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        _ => bail!(
+            "The unit of the size {:?} is not one of B, KB, MB, GB or TB",
+            value
+        ),
+    };
+    let number = number
+        .parse::<f64>()
+        .with_context(|| format!("Invalid size: {:?}", value))?;
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Parses durations like `30m`, `12h`, `7d` or `2w`. A number without a unit is a number of days
+pub fn parse_duration(value: &str) -> anyhow::Result<Duration> {
+    let (number, unit) = split_unit(value);
+    let seconds: u64 = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "" | "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => bail!(
+            "The unit of the duration {:?} is not one of s, m, h, d or w",
+            value
+        ),
+    };
+    let number = number
+        .parse::<f64>()
+        .with_context(|| format!("Invalid duration: {:?}", value))?;
+    Duration::try_from_secs_f64(number * seconds as f64)
+        .with_context(|| format!("Invalid duration: {:?}", value))
+}
+
+fn split_unit(value: &str) -> (&str, &str) {
+    let value = value.trim();
+    let index = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(index);
+    (number, unit.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_sizes_and_durations() {
+        assert_eq!(parse_size("1024").unwrap(), 1024);
+        assert_eq!(parse_size("512MB").unwrap(), 512 * 1024 * 1024);
+        assert_eq!(parse_size("1.5 gb").unwrap(), 3 * 512 * 1024 * 1024);
+        assert!(parse_size("10 parsecs").is_err());
+        assert!(parse_size("GB").is_err());
+
+        assert_eq!(
+            parse_duration("12h").unwrap(),
+            Duration::from_secs(12 * 3600)
+        );
+        assert_eq!(parse_duration("7").unwrap(), DEFAULT_MAX_AGE);
+        assert_eq!(parse_duration("1w").unwrap(), DEFAULT_MAX_AGE);
+        assert!(parse_duration("1y").is_err());
+    }
+
+    #[test]
+    fn should_remove_the_least_recently_used_entries() {
+        let entries = vec![
+            ("newest".to_string(), 40),
+            ("newer".to_string(), 40),
+            ("older".to_string(), 40),
+            ("oldest".to_string(), 10),
+        ];
+        let hashes = |entries: Vec<(String, i64)>| {
+            entries
+                .into_iter()
+                .map(|(hash, _)| hash)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            hashes(entries_over_size(entries.clone(), 100)),
+            vec!["older", "oldest"]
+        );
+        assert_eq!(
+            hashes(entries_over_size(entries.clone(), 130)),
+            Vec::<String>::new()
+        );
+        assert_eq!(hashes(entries_over_size(entries, 0)).len(), 4);
+    }
+
+    #[test]
+    fn should_read_the_policy_from_options() {
+        let policy = GcPolicy::from_options(CacheGcOptions {
+            max_age: Some("3d".into()),
+            max_size: Some("1GB".into()),
+        })
+        .unwrap();
+        assert_eq!(
+            policy,
+            GcPolicy {
+                max_age: Duration::from_secs(3 * 24 * 60 * 60),
+                max_size: Some(1 << 30),
+            }
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use std::fs;
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use tracing::warn;
+use walkdir::WalkDir;
+use xxhash_rust::xxh3::Xxh3;
+
+const VERIFY_ENV: &str = "NX_CACHE_VERIFY_INTEGRITY";
+const TERMINAL_OUTPUT_FILE: &str = "terminalOutput";
+const OUTPUTS_DIR: &str = "outputs";
+
+/// Whether the digests of cache entries are verified before they are restored, true unless
+/// `NX_CACHE_VERIFY_INTEGRITY=false`
+pub fn integrity_checks_enabled() -> bool {
+    std::env::var(VERIFY_ENV).map_or(true, |value| value != "false")
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct CorruptedCacheEntry {
+    pub hash: String,
+    /// The digest that was recorded when the entry was cached
+    pub expected_digest: String,
+    pub actual_digest: String,
+    /// Where the outputs and the terminal output of the entry were moved to
+    pub quarantine_path: String,
+}
+
+/// Hashes the outputs of a cache entry (their paths, types and contents) with its terminal output.
+/// Entries are walked in a sorted order, so the digest only depends on what is in the cache
+pub fn digest_entry(task_dir: &Path, terminal_output: &str) -> anyhow::Result<String> {
+    let mut hasher = Xxh3::new();
+    hasher.update(terminal_output.as_bytes());
+
+    if task_dir.exists() {
+        let mut buffer = vec![0; 64 * 1024];
+        for entry in WalkDir::new(task_dir).min_depth(1).sort_by_file_name() {
+            let entry = entry?;
+            let relative = entry.path().strip_prefix(task_dir)?;
+            hasher.update(b"\0");
+            hasher.update(relative.to_string_lossy().replace('\\', "/").as_bytes());
+
+            let file_type = entry.file_type();
+            if file_type.is_symlink() {
+                hasher.update(b"\0l");
+                hasher.update(fs::read_link(entry.path())?.to_string_lossy().as_bytes());
+            } else if file_type.is_dir() {
+                hasher.update(b"\0d");
+            } else {
+                hasher.update(b"\0f");
+                let mut file = fs::File::open(entry.path())?;
+                loop {
+                    match file.read(&mut buffer) {
+                        Ok(0) => break,
+                        Ok(len) => hasher.update(&buffer[..len]),
+                        Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(format!("{:032x}", hasher.digest128()))
+}
+
+/// Moves a corrupted cache entry out of the cache, where it can be inspected
+/// until it is removed with the other old cache records
+pub fn quarantine_entry(
+    cache_path: &Path,
+    task_dir: &Path,
+    terminal_output_path: &Path,
+    hash: &str,
+) -> anyhow::Result<PathBuf> {
+    let quarantine_path = cache_path.join("quarantine").join(hash);
+    fs_extra::remove_items(&[&quarantine_path])?;
+    fs::create_dir_all(&quarantine_path)?;
+
+    for (src, dest) in [
+        (task_dir, OUTPUTS_DIR),
+        (terminal_output_path, TERMINAL_OUTPUT_FILE),
+    ] {
+        match fs::rename(src, quarantine_path.join(dest)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+
+    warn!(
+        hash,
+        quarantine = ?quarantine_path,
+        "a corrupted cache entry was quarantined"
+    );
+    Ok(quarantine_path)
+}
+
+/// Removes the quarantined entries that were quarantined before `before`
+pub fn remove_quarantined_entries(cache_path: &Path, before: SystemTime) -> anyhow::Result<()> {
+    let quarantine = cache_path.join("quarantine");
+    if !quarantine.exists() {
+        return Ok(());
+    }
+    let outdated = fs::read_dir(quarantine)?
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| modified < before)
+        })
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    fs_extra::remove_items(&outdated)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn should_digest_the_contents_of_entries() {
+        let temp = TempDir::new().unwrap();
+        for entry in ["a", "b"] {
+            temp.child(format!("{}/dist/main.js", entry))
+                .write_str("main")
+                .unwrap();
+            temp.child(format!("{}/dist/assets/logo.svg", entry))
+                .write_str("logo")
+                .unwrap();
+        }
+        let digest = digest_entry(&temp.join("a"), "> built").unwrap();
+        assert_eq!(digest, digest_entry(&temp.join("b"), "> built").unwrap());
+        assert_ne!(digest, digest_entry(&temp.join("b"), "> other").unwrap());
+
+        temp.child("b/dist/main.js").write_str("mian").unwrap();
+        assert_ne!(digest, digest_entry(&temp.join("b"), "> built").unwrap());
+
+        fs::remove_file(temp.join("b/dist/main.js")).unwrap();
+        temp.child("b/dist/main.ts").write_str("main").unwrap();
+        assert_ne!(digest, digest_entry(&temp.join("b"), "> built").unwrap());
+    }
+
+    #[test]
+    fn should_quarantine_entries() {
+        let temp = TempDir::new().unwrap();
+        temp.child("cache/hash/dist/main.js")
+            .write_str("main")
+            .unwrap();
+        temp.child("cache/terminalOutputs/hash")
+            .write_str("> built")
+            .unwrap();
+
+        let quarantine_path = quarantine_entry(
+            &temp.join("cache"),
+            &temp.join("cache/hash"),
+            &temp.join("cache/terminalOutputs/hash"),
+            "hash",
+        )
+        .unwrap();
+        assert!(!temp.join("cache/hash").exists());
+        assert!(!temp.join("cache/terminalOutputs/hash").exists());
+        temp.child("cache/quarantine/hash/outputs/dist/main.js")
+            .assert("main");
+        temp.child("cache/quarantine/hash/terminalOutput")
+            .assert("> built");
+
+        remove_quarantined_entries(
+            &temp.join("cache"),
+            SystemTime::now() - Duration::from_secs(60),
+        )
+        .unwrap();
+        assert!(quarantine_path.exists());
+        remove_quarantined_entries(
+            &temp.join("cache"),
+            SystemTime::now() + Duration::from_secs(60),
+        )
+        .unwrap();
+        assert!(!quarantine_path.exists());
+    }
+}
@@ -1,6 +1,30 @@
+pub mod archive;
 pub mod expand_outputs;
 pub mod file_ops;
 pub mod validate_outputs;
+pub mod verify_outputs;
 
 #[cfg(not(target_arch = "wasm32"))]
-pub mod cache;
\ No newline at end of file
+pub mod blob_storage;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod bundle;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cache;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod chunking;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod content_store;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod encryption;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod file_tree;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod gc;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod integrity;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod output_search;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod reapi;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod remote_cache;
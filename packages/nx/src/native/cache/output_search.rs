@@ -0,0 +1,236 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::read_to_string;
+use std::path::Path;
+
+use anyhow::Context;
+use regex::Regex;
+use rusqlite::params;
+use tracing::trace_span;
+
+use crate::native::db::connection::NxDbConnection;
+use crate::native::pseudo_terminal::ansi::{AnsiFilter, AnsiMode};
+use crate::native::utils::parallel::prelude::*;
+
+/// Which of the stored terminal outputs are searched, every output by default
+#[napi(object)]
+#[derive(Default)]
+pub struct TaskOutputSearchRange {
+    /// Only the outputs that were stored at or after this time, in milliseconds since the epoch
+    pub since: Option<i64>,
+    /// Only the outputs that were stored at or before this time, in milliseconds since the epoch
+    pub until: Option<i64>,
+    /// Only the outputs of these tasks
+    pub task_ids: Option<Vec<String>>,
+    /// The maximum number of matches, the earliest are returned
+    pub limit: Option<u32>,
+}
+
+/// A line of a stored terminal output that matches the searched pattern
+#[napi(object)]
+#[derive(Debug, PartialEq)]
+pub struct TaskOutputMatch {
+    pub task_id: String,
+    pub hash: String,
+    /// When the output was stored, in milliseconds since the epoch
+    pub recorded_at: i64,
+    /// The number of the line in the output, from 1
+    pub line: u32,
+    /// The line, without its colors and other escape sequences
+    pub text: String,
+}
+
+/// Indexes the terminal output of a task that was stored in the cache under `hash`
+pub(crate) fn record_terminal_output(
+    db: &NxDbConnection,
+    task_id: String,
+    hash: String,
+    recorded_at: i64,
+) -> anyhow::Result<()> {
+    db.write(move |db| {
+        db.execute(
+            "INSERT INTO terminal_outputs (task_id, hash, recorded_at) VALUES (?1, ?2, ?3)",
+            params![task_id, hash, recorded_at],
+        )?;
+        Ok(())
+    })
+}
+
+/// Searches the indexed terminal outputs in `terminal_outputs` for the lines that match `pattern`, earliest output
+/// first. The outputs are searched in parallel, and an output that was stored by several runs is searched once.
+/// Outputs that were removed from the cache since they were stored have no matches
+pub(crate) fn search_terminal_outputs(
+    db: &NxDbConnection,
+    terminal_outputs: &Path,
+    pattern: &str,
+    range: TaskOutputSearchRange,
+) -> anyhow::Result<Vec<TaskOutputMatch>> {
+    let _span = trace_span!("search_terminal_outputs", pattern).entered();
+    let regex =
+        Regex::new(pattern).with_context(|| format!("{} is not a valid pattern", pattern))?;
+    let task_ids = range
+        .task_ids
+        .map(|task_ids| task_ids.into_iter().collect::<HashSet<_>>());
+
+    let records = db
+        .connection()?
+        .prepare(
+            "SELECT task_id, hash, recorded_at FROM terminal_outputs
+                WHERE recorded_at >= ?1 AND recorded_at <= ?2
+                ORDER BY recorded_at, id",
+        )?
+        .query_map(
+            params![
+                range.since.unwrap_or(i64::MIN),
+                range.until.unwrap_or(i64::MAX)
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            },
+        )?
+        .filter(|record| {
+            record.as_ref().map_or(true, |(task_id, _, _)| {
+                task_ids
+                    .as_ref()
+                    .is_none_or(|task_ids| task_ids.contains(task_id))
+            })
+        })
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut hashes = records
+        .iter()
+        .map(|(_, hash, _)| hash.as_str())
+        .collect::<Vec<_>>();
+    hashes.sort_unstable();
+    hashes.dedup();
+    let lines_by_hash = hashes
+        .into_par_iter()
+        .map(|hash| (hash, matching_lines(&regex, &terminal_outputs.join(hash))))
+        .collect::<Vec<_>>()
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+    let limit = range.limit.map_or(usize::MAX, |limit| limit as usize);
+    Ok(records
+        .iter()
+        .flat_map(|(task_id, hash, recorded_at)| {
+            lines_by_hash[hash.as_str()]
+                .iter()
+                .map(|(line, text)| TaskOutputMatch {
+                    task_id: task_id.clone(),
+                    hash: hash.clone(),
+                    recorded_at: *recorded_at,
+                    line: *line,
+                    text: text.clone(),
+                })
+        })
+        .take(limit)
+        .collect())
+}
+
+/// The numbers and the text of the lines of an output that match, an output that cannot be read has none
+fn matching_lines(regex: &Regex, path: &Path) -> Vec<(u32, String)> {
+    let Ok(output) = read_to_string(path) else {
+        return vec![];
+    };
+    AnsiFilter::new(AnsiMode::strip)
+        .filter(&output)
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index as u32 + 1, line.trim_end_matches('\r')))
+        .filter(|(_, line)| regex.is_match(line))
+        .map(|(line, text)| (line, text.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn should_find_the_first_runs_that_printed_a_line() {
+        let temp = TempDir::new().unwrap();
+        let db = NxDbConnection::open(&temp.join("nx.db")).unwrap();
+        let outputs = temp.child("terminalOutputs");
+        outputs.child("a").write_str("compiled\r\n").unwrap();
+        outputs
+            .child("b")
+            .write_str("compiled\n\x1b[33mwarning\x1b[39m: foo is deprecated\n")
+            .unwrap();
+        outputs
+            .child("c")
+            .write_str("warning: foo is deprecated\nwarning: bar is deprecated\n")
+            .unwrap();
+        for (task_id, hash, recorded_at) in [
+            ("lib:build", "a", 1000),
+            ("lib:build", "b", 2000),
+            ("app:build", "c", 3000),
+            ("lib:build", "b", 4000),
+            // removed from the cache
+            ("lib:build", "d", 5000),
+        ] {
+            record_terminal_output(&db, task_id.into(), hash.into(), recorded_at).unwrap();
+        }
+
+        let search = |pattern: &str, range: TaskOutputSearchRange| {
+            search_terminal_outputs(&db, &outputs, pattern, range)
+                .unwrap()
+                .into_iter()
+                .map(|found| (found.task_id, found.recorded_at, found.line, found.text))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            search("^warning: foo", Default::default()),
+            [
+                (
+                    "lib:build".to_string(),
+                    2000,
+                    2,
+                    "warning: foo is deprecated".to_string()
+                ),
+                (
+                    "app:build".to_string(),
+                    3000,
+                    1,
+                    "warning: foo is deprecated".to_string()
+                ),
+                (
+                    "lib:build".to_string(),
+                    4000,
+                    2,
+                    "warning: foo is deprecated".to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            search(
+                "deprecated",
+                TaskOutputSearchRange {
+                    since: Some(2500),
+                    task_ids: Some(vec!["app:build".into()]),
+                    limit: Some(1),
+                    ..Default::default()
+                }
+            ),
+            [(
+                "app:build".to_string(),
+                3000,
+                1,
+                "warning: foo is deprecated".to_string()
+            )]
+        );
+        assert_eq!(
+            search("compiled$", Default::default()).len(),
+            3,
+            "carriage returns are not part of the lines"
+        );
+        assert!(search_terminal_outputs(&db, &outputs, "(", Default::default()).is_err());
+    }
+}
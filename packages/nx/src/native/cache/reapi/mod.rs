@@ -0,0 +1,673 @@
+mod proto;
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::future::Future;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context};
+use futures_util::{stream, StreamExt};
+use prost::Message;
+use sha2::{Digest as _, Sha256};
+use tokio::sync::OnceCell;
+use tonic::codec::ProstCodec;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::metadata::MetadataValue;
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+use tonic::{Code, Status};
+use tracing::{debug, trace};
+
+use crate::native::cache::cache::CachedResult;
+use crate::native::cache::remote_cache::{backoff, pack_artifact, unpack_artifact};
+use crate::native::compression::{record_throughput, CompressionTarget};
+use crate::native::utils::Normalize;
+
+use proto::*;
+
+const DEFAULT_RETRIES: u32 = 3;
+/// Blobs up to this size are sent in batches, bigger blobs are streamed.
+/// This stays under the default 4 MiB limit of gRPC messages
+const MAX_BATCH_BLOB_SIZE: u64 = 1024 * 1024;
+const STREAM_CHUNK_SIZE: usize = 1024 * 1024;
+/// The action cache can not be asked for several actions at once, this many are looked up at the same time
+const CONCURRENT_LOOKUPS: usize = 16;
+/// The path of the artifact of a task in the outputs of its action
+const ARTIFACT_PATH: &str = "nx-artifact.tar.zst";
+
+const GET_ACTION_RESULT: &str = "/build.bazel.remote.execution.v2.ActionCache/GetActionResult";
+const UPDATE_ACTION_RESULT: &str =
+    "/build.bazel.remote.execution.v2.ActionCache/UpdateActionResult";
+const FIND_MISSING_BLOBS: &str =
+    "/build.bazel.remote.execution.v2.ContentAddressableStorage/FindMissingBlobs";
+const BATCH_UPDATE_BLOBS: &str =
+    "/build.bazel.remote.execution.v2.ContentAddressableStorage/BatchUpdateBlobs";
+const BATCH_READ_BLOBS: &str =
+    "/build.bazel.remote.execution.v2.ContentAddressableStorage/BatchReadBlobs";
+const BYTESTREAM_READ: &str = "/google.bytestream.ByteStream/Read";
+const BYTESTREAM_WRITE: &str = "/google.bytestream.ByteStream/Write";
+
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct RemoteExecutionCacheOptions {
+    /// The url of the server, `grpc://` or `grpcs://` (TLS)
+    pub url: String,
+    /// The instance name of the server, empty by default
+    pub instance_name: Option<String>,
+    /// Sent as a bearer token
+    pub access_token: Option<String>,
+    /// How many times failed requests are retried, 3 by default
+    pub retries: Option<u32>,
+}
+
+/// A remote cache backed by a Bazel Remote Execution API (v2) server, e.g. BuildBarn or Buildfarm.
+/// The artifact of a task is a blob in the content addressable storage, that is an output of an action
+/// in the action cache. The action is derived from the hash of the task, and is never executed
+#[napi]
+pub struct RemoteExecutionCacheClient {
+    endpoint: Endpoint,
+    channel: OnceCell<Channel>,
+    instance_name: String,
+    authorization: Option<MetadataValue<tonic::metadata::Ascii>>,
+    retries: u32,
+}
+
+#[napi]
+impl RemoteExecutionCacheClient {
+    #[napi(constructor)]
+    pub fn new(options: RemoteExecutionCacheOptions) -> anyhow::Result<Self> {
+        let (url, tls) = match options.url.split_once("://") {
+            Some(("grpcs" | "https", address)) => (format!("https://{}", address), true),
+            Some(("grpc" | "http", address)) => (format!("http://{}", address), false),
+            _ => bail!(
+                "The url of a remote execution cache has to start with grpc:// or grpcs://: {}",
+                options.url
+            ),
+        };
+        let mut endpoint = Endpoint::from_shared(url)?;
+        if tls {
+            endpoint = endpoint.tls_config(ClientTlsConfig::new().with_native_roots())?;
+        }
+        let authorization = options
+            .access_token
+            .map(|token| format!("Bearer {}", token).parse())
+            .transpose()
+            .context("The access token of the remote execution cache is invalid")?;
+
+        Ok(Self {
+            endpoint,
+            channel: OnceCell::new(),
+            instance_name: options.instance_name.unwrap_or_default(),
+            authorization,
+            retries: options.retries.unwrap_or(DEFAULT_RETRIES),
+        })
+    }
+
+    /// Downloads the artifact of a task into `<cache_directory>/<hash>`.
+    /// Resolves with null when the remote cache does not have it
+    #[napi]
+    pub async fn retrieve(
+        &self,
+        hash: String,
+        cache_directory: String,
+    ) -> napi::Result<Option<CachedResult>> {
+        self.retrieve_artifact(&hash, Path::new(&cache_directory))
+            .await
+            .map_err(|e| napi::Error::from_reason(format!("{:#}", e)))
+    }
+
+    /// Uploads the outputs in `<cache_directory>/<hash>` with the terminal output and exit code of the task
+    #[napi]
+    pub async fn store(
+        &self,
+        hash: String,
+        cache_directory: String,
+        terminal_output: String,
+        code: i16,
+    ) -> napi::Result<bool> {
+        self.store_artifact(&hash, Path::new(&cache_directory), terminal_output, code)
+            .await
+            .map(|_| true)
+            .map_err(|e| napi::Error::from_reason(format!("{:#}", e)))
+    }
+
+    /// Whether the remote cache has the artifact of each hash, without downloading them.
+    /// The content addressable storage is asked for all of the artifacts at once, as it may have evicted some
+    /// of the artifacts that the action cache still refers to
+    #[napi]
+    pub async fn check_cache_hits(
+        &self,
+        hashes: Vec<String>,
+    ) -> napi::Result<HashMap<String, bool>> {
+        self.check_artifacts(&hashes)
+            .await
+            .map_err(|e| napi::Error::from_reason(format!("{:#}", e)))
+    }
+}
+
+impl RemoteExecutionCacheClient {
+    async fn retrieve_artifact(
+        &self,
+        hash: &str,
+        cache_directory: &Path,
+    ) -> anyhow::Result<Option<CachedResult>> {
+        let Some(digest) = self.artifact_digest(hash).await? else {
+            return Ok(None);
+        };
+
+        let artifact = cache_directory.join(format!("{}.download.tar.zst", hash));
+        let result = async {
+            self.download_blob(&digest, &artifact).await?;
+            let task_dir = cache_directory.join(hash);
+            let downloaded = artifact.clone();
+            let hash = hash.to_string();
+            tokio::task::spawn_blocking(move || {
+                if file_digest(&downloaded)? != digest {
+                    bail!("The artifact of {} does not match its digest", hash);
+                }
+                let (code, terminal_output) = unpack_artifact(&downloaded, &task_dir)?;
+                Ok(Some(CachedResult {
+                    code,
+                    terminal_output,
+                    outputs_path: task_dir.to_normalized_string(),
+                    hash_version: None,
+                }))
+            })
+            .await?
+        }
+        .await;
+        fs::remove_file(&artifact).ok();
+        result
+    }
+
+    /// The digest of the artifact of a task, from the result of its action in the action cache
+    async fn artifact_digest(&self, hash: &str) -> anyhow::Result<Option<Digest>> {
+        let (_, action) = task_action(hash);
+        let request = GetActionResultRequest {
+            instance_name: self.instance_name.clone(),
+            action_digest: Some(digest_of(&action.encode_to_vec())),
+            inline_stdout: false,
+        };
+        let result: ActionResult = match self.unary(GET_ACTION_RESULT, request).await {
+            Ok(result) => result,
+            Err(status) if status.code() == Code::NotFound => return Ok(None),
+            Err(status) => return Err(status.into()),
+        };
+        Ok(result
+            .output_files
+            .into_iter()
+            .find(|file| file.path == ARTIFACT_PATH)
+            .and_then(|file| file.digest))
+    }
+
+    async fn check_artifacts(&self, hashes: &[String]) -> anyhow::Result<HashMap<String, bool>> {
+        let lookups = stream::iter(hashes.iter().cloned())
+            .map(|hash| async move {
+                let digest = self.artifact_digest(&hash).await;
+                (hash, digest)
+            })
+            .buffer_unordered(CONCURRENT_LOOKUPS)
+            .collect::<Vec<_>>()
+            .await;
+        let mut digests = HashMap::new();
+        for (hash, digest) in lookups {
+            if let Some(digest) = digest? {
+                digests.insert(hash, digest);
+            }
+        }
+
+        let missing = if digests.is_empty() {
+            vec![]
+        } else {
+            let missing: FindMissingBlobsResponse = self
+                .unary(
+                    FIND_MISSING_BLOBS,
+                    FindMissingBlobsRequest {
+                        instance_name: self.instance_name.clone(),
+                        blob_digests: digests.values().cloned().collect(),
+                    },
+                )
+                .await?;
+            missing.missing_blob_digests
+        };
+        Ok(hashes
+            .iter()
+            .map(|hash| {
+                let hit = digests
+                    .get(hash.as_str())
+                    .is_some_and(|digest| !missing.contains(digest));
+                (hash.clone(), hit)
+            })
+            .collect())
+    }
+
+    async fn store_artifact(
+        &self,
+        hash: &str,
+        cache_directory: &Path,
+        terminal_output: String,
+        code: i16,
+    ) -> anyhow::Result<()> {
+        let artifact = cache_directory.join(format!("{}.upload.tar.zst", hash));
+        let result = async {
+            let task_dir = cache_directory.join(hash);
+            let packed = artifact.clone();
+            let output = terminal_output.clone();
+            let (packing, artifact_digest) = tokio::task::spawn_blocking(move || {
+                let packing = pack_artifact(&task_dir, &output, code, &packed)?;
+                Ok::<_, anyhow::Error>((packing, file_digest(&packed)?))
+            })
+            .await??;
+
+            let (command, action) = task_action(hash);
+            let command = command.encode_to_vec();
+            let action = action.encode_to_vec();
+            let action_digest = digest_of(&action);
+            let started = Instant::now();
+            self.upload_blobs(
+                vec![
+                    (digest_of(&command), command),
+                    (action_digest.clone(), action),
+                ],
+                (artifact_digest.clone(), artifact.clone()),
+            )
+            .await?;
+            record_throughput(CompressionTarget::Remote, packing, started.elapsed());
+
+            let request = UpdateActionResultRequest {
+                instance_name: self.instance_name.clone(),
+                action_digest: Some(action_digest),
+                action_result: Some(ActionResult {
+                    output_files: vec![OutputFile {
+                        path: ARTIFACT_PATH.to_string(),
+                        digest: Some(artifact_digest),
+                        is_executable: false,
+                    }],
+                    exit_code: code as i32,
+                    stdout_raw: terminal_output.into_bytes(),
+                }),
+            };
+            let _: ActionResult = self.unary(UPDATE_ACTION_RESULT, request).await?;
+            Ok(())
+        }
+        .await;
+        fs::remove_file(&artifact).ok();
+        result
+    }
+
+    /// Uploads the blobs that the server does not have yet, small blobs in one batch
+    async fn upload_blobs(
+        &self,
+        blobs: Vec<(Digest, Vec<u8>)>,
+        (artifact_digest, artifact): (Digest, PathBuf),
+    ) -> anyhow::Result<()> {
+        let mut digests = blobs
+            .iter()
+            .map(|(digest, _)| digest.clone())
+            .collect::<Vec<_>>();
+        digests.push(artifact_digest.clone());
+        let missing: FindMissingBlobsResponse = self
+            .unary(
+                FIND_MISSING_BLOBS,
+                FindMissingBlobsRequest {
+                    instance_name: self.instance_name.clone(),
+                    blob_digests: digests,
+                },
+            )
+            .await?;
+        let is_missing = |digest: &Digest| missing.missing_blob_digests.contains(digest);
+
+        let mut batch = blobs
+            .into_iter()
+            .filter(|(digest, _)| is_missing(digest))
+            .map(|(digest, data)| UpdateBlobRequest {
+                digest: Some(digest),
+                data,
+            })
+            .collect::<Vec<_>>();
+        if is_missing(&artifact_digest) {
+            if artifact_digest.size_bytes as u64 <= MAX_BATCH_BLOB_SIZE {
+                batch.push(UpdateBlobRequest {
+                    digest: Some(artifact_digest),
+                    data: fs::read(&artifact)?,
+                });
+            } else {
+                self.write_blob(&artifact_digest, &artifact).await?;
+            }
+        }
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        let response: BatchUpdateBlobsResponse = self
+            .unary(
+                BATCH_UPDATE_BLOBS,
+                BatchUpdateBlobsRequest {
+                    instance_name: self.instance_name.clone(),
+                    requests: batch,
+                },
+            )
+            .await?;
+        rpc_result(
+            response
+                .responses
+                .into_iter()
+                .map(|response| (response.digest, response.status)),
+        )
+    }
+
+    async fn download_blob(&self, digest: &Digest, dest: &Path) -> anyhow::Result<()> {
+        if digest.size_bytes as u64 <= MAX_BATCH_BLOB_SIZE {
+            let response: BatchReadBlobsResponse = self
+                .unary(
+                    BATCH_READ_BLOBS,
+                    BatchReadBlobsRequest {
+                        instance_name: self.instance_name.clone(),
+                        digests: vec![digest.clone()],
+                    },
+                )
+                .await?;
+            let blob =
+                response.responses.into_iter().next().ok_or_else(|| {
+                    anyhow!("The remote execution cache did not return the artifact")
+                })?;
+            rpc_result([(blob.digest, blob.status)])?;
+            fs::write(dest, blob.data)?;
+            return Ok(());
+        }
+
+        let resource_name = format!(
+            "{}blobs/{}/{}",
+            self.resource_prefix(),
+            digest.hash,
+            digest.size_bytes
+        );
+        File::create(dest)?;
+        // interrupted downloads continue from what was written
+        self.with_retries(|| {
+            let resource_name = resource_name.clone();
+            async move {
+                let mut file = OpenOptions::new()
+                    .append(true)
+                    .open(dest)
+                    .map_err(io_status)?;
+                let offset = file.metadata().map_err(io_status)?.len();
+                let mut stream = self
+                    .grpc()
+                    .await?
+                    .server_streaming(
+                        self.request(ReadRequest {
+                            resource_name,
+                            read_offset: offset as i64,
+                            read_limit: 0,
+                        }),
+                        PathAndQuery::from_static(BYTESTREAM_READ),
+                        ProstCodec::<ReadRequest, ReadResponse>::default(),
+                    )
+                    .await?
+                    .into_inner();
+                while let Some(response) = stream.message().await? {
+                    file.write_all(&response.data).map_err(io_status)?;
+                }
+                Ok(())
+            }
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Streams a blob to the server in chunks
+    async fn write_blob(&self, digest: &Digest, path: &Path) -> anyhow::Result<()> {
+        let resource_name = format!(
+            "{}uploads/{}/blobs/{}/{}",
+            self.resource_prefix(),
+            upload_id(),
+            digest.hash,
+            digest.size_bytes
+        );
+        let size = digest.size_bytes;
+        trace!("streaming {} ({} bytes)", digest.hash, size);
+        let response: WriteResponse = self
+            .with_retries(|| {
+                let resource_name = resource_name.clone();
+                async move {
+                    let mut file = File::open(path).map_err(io_status)?;
+                    let mut offset = 0;
+                    let requests = std::iter::from_fn(move || {
+                        if offset >= size {
+                            return None;
+                        }
+                        let mut data = vec![0; STREAM_CHUNK_SIZE.min((size - offset) as usize)];
+                        file.read_exact(&mut data).ok()?;
+                        let request = WriteRequest {
+                            // only the first request has to name the resource
+                            resource_name: if offset == 0 {
+                                resource_name.clone()
+                            } else {
+                                String::new()
+                            },
+                            write_offset: offset,
+                            finish_write: offset + data.len() as i64 >= size,
+                            data,
+                        };
+                        offset += request.data.len() as i64;
+                        Some(request)
+                    });
+                    self.grpc()
+                        .await?
+                        .client_streaming(
+                            self.request(tokio_stream::iter(requests)),
+                            PathAndQuery::from_static(BYTESTREAM_WRITE),
+                            ProstCodec::<WriteRequest, WriteResponse>::default(),
+                        )
+                        .await
+                        .map(|response| response.into_inner())
+                }
+            })
+            .await?;
+        if response.committed_size != size {
+            bail!(
+                "The remote execution cache committed {} of {} bytes",
+                response.committed_size,
+                size
+            );
+        }
+        Ok(())
+    }
+
+    async fn unary<Req, Res>(&self, path: &'static str, message: Req) -> Result<Res, Status>
+    where
+        Req: Message + Clone + Send + Sync + 'static,
+        Res: Message + Default + Send + 'static,
+    {
+        self.with_retries(|| {
+            let message = message.clone();
+            async move {
+                self.grpc()
+                    .await?
+                    .unary(
+                        self.request(message),
+                        PathAndQuery::from_static(path),
+                        ProstCodec::<Req, Res>::default(),
+                    )
+                    .await
+                    .map(|response| response.into_inner())
+            }
+        })
+        .await
+    }
+
+    /// Retries calls that failed because the server was unavailable or overloaded, with exponential backoff
+    async fn with_retries<T, F, Fut>(&self, mut call: F) -> Result<T, Status>
+    where
+        F: FnMut() -> Fut + Send,
+        Fut: Future<Output = Result<T, Status>> + Send,
+    {
+        let mut attempt = 0;
+        loop {
+            match call().await {
+                Err(status) if attempt < self.retries && is_retryable(&status) => {
+                    let delay = backoff(attempt);
+                    debug!(
+                        "retrying a remote execution cache call in {:?}: {}",
+                        delay, status
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn grpc(&self) -> Result<tonic::client::Grpc<Channel>, Status> {
+        let channel = self
+            .channel
+            .get_or_try_init(|| self.endpoint.connect())
+            .await
+            .map_err(|e| Status::unavailable(e.to_string()))?;
+        Ok(tonic::client::Grpc::new(channel.clone()))
+    }
+
+    fn request<T>(&self, message: T) -> tonic::Request<T> {
+        let mut request = tonic::Request::new(message);
+        if let Some(authorization) = &self.authorization {
+            request
+                .metadata_mut()
+                .insert("authorization", authorization.clone());
+        }
+        request
+    }
+
+    fn resource_prefix(&self) -> String {
+        if self.instance_name.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", self.instance_name)
+        }
+    }
+}
+
+/// The command and action of a task. They only depend on the hash of the task, so they are the key of its artifact
+fn task_action(hash: &str) -> (Command, Action) {
+    let command = Command {
+        arguments: vec!["nx".to_string(), "run-task".to_string(), hash.to_string()],
+        output_paths: vec![ARTIFACT_PATH.to_string()],
+    };
+    let action = Action {
+        command_digest: Some(digest_of(&command.encode_to_vec())),
+        input_root_digest: Some(digest_of(&[])),
+        do_not_cache: false,
+    };
+    (command, action)
+}
+
+fn digest_of(data: &[u8]) -> Digest {
+    Digest {
+        hash: format!("{:x}", Sha256::digest(data)),
+        size_bytes: data.len() as i64,
+    }
+}
+
+fn file_digest(path: &Path) -> anyhow::Result<Digest> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let size = std::io::copy(&mut file, &mut hasher)?;
+    Ok(Digest {
+        hash: format!("{:x}", hasher.finalize()),
+        size_bytes: size as i64,
+    })
+}
+
+/// Fails with the first error of the statuses of a batch
+fn rpc_result(
+    statuses: impl IntoIterator<Item = (Option<Digest>, Option<RpcStatus>)>,
+) -> anyhow::Result<()> {
+    for (digest, status) in statuses {
+        if let Some(status) = status.filter(|status| status.code != Code::Ok as i32) {
+            bail!(
+                "The remote execution cache failed to transfer {}: {}",
+                digest.map(|digest| digest.hash).unwrap_or_default(),
+                Status::new(Code::from_i32(status.code), status.message)
+            );
+        }
+    }
+    Ok(())
+}
+
+fn is_retryable(status: &Status) -> bool {
+    matches!(
+        status.code(),
+        Code::Unavailable | Code::ResourceExhausted | Code::DeadlineExceeded | Code::Aborted
+    )
+}
+
+fn io_status(error: std::io::Error) -> Status {
+    Status::internal(error.to_string())
+}
+
+/// Uploads are named by a UUID, whose uniqueness is all that matters to the server
+fn upload_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_nanos();
+    let id = format!("{:032x}", nanos ^ ((std::process::id() as u128) << 96));
+    format!(
+        "{}-{}-{}-{}-{}",
+        &id[..8],
+        &id[8..12],
+        &id[12..16],
+        &id[16..20],
+        &id[20..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_derive_actions_from_task_hashes() {
+        let (_, action) = task_action("123");
+        let (_, same_action) = task_action("123");
+        let (_, other_action) = task_action("456");
+        assert_eq!(
+            digest_of(&action.encode_to_vec()),
+            digest_of(&same_action.encode_to_vec())
+        );
+        assert_ne!(
+            digest_of(&action.encode_to_vec()),
+            digest_of(&other_action.encode_to_vec())
+        );
+        assert_eq!(
+            action.input_root_digest.unwrap().hash,
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn should_report_failed_blobs() {
+        let ok = RpcStatus {
+            code: Code::Ok as i32,
+            message: String::new(),
+        };
+        let failed = RpcStatus {
+            code: Code::InvalidArgument as i32,
+            message: "digest mismatch".into(),
+        };
+        assert!(rpc_result([(Some(digest_of(b"a")), Some(ok.clone())), (None, None)]).is_ok());
+        let error = rpc_result([(Some(digest_of(b"a")), Some(ok)), (None, Some(failed))])
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("digest mismatch"));
+    }
+
+    #[test]
+    fn should_generate_upload_ids() {
+        let id = upload_id();
+        assert_eq!(id.len(), 36);
+        assert_eq!(id.matches('-').count(), 4);
+    }
+}
@@ -0,0 +1,191 @@
+//! The messages of the Bazel Remote Execution API (v2) and of the ByteStream API that are used by the cache client.
+//! Only the fields that Nx reads or writes are declared, the field numbers match the upstream `.proto` files
+
+/// `build.bazel.remote.execution.v2.Digest`
+#[derive(Clone, PartialEq, Eq, Hash, prost::Message)]
+pub struct Digest {
+    #[prost(string, tag = "1")]
+    pub hash: String,
+    #[prost(int64, tag = "2")]
+    pub size_bytes: i64,
+}
+
+/// `build.bazel.remote.execution.v2.Command`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Command {
+    #[prost(string, repeated, tag = "1")]
+    pub arguments: Vec<String>,
+    #[prost(string, repeated, tag = "7")]
+    pub output_paths: Vec<String>,
+}
+
+/// `build.bazel.remote.execution.v2.Action`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Action {
+    #[prost(message, optional, tag = "1")]
+    pub command_digest: Option<Digest>,
+    #[prost(message, optional, tag = "2")]
+    pub input_root_digest: Option<Digest>,
+    #[prost(bool, tag = "7")]
+    pub do_not_cache: bool,
+}
+
+/// `build.bazel.remote.execution.v2.OutputFile`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OutputFile {
+    #[prost(string, tag = "1")]
+    pub path: String,
+    #[prost(message, optional, tag = "2")]
+    pub digest: Option<Digest>,
+    #[prost(bool, tag = "4")]
+    pub is_executable: bool,
+}
+
+/// `build.bazel.remote.execution.v2.ActionResult`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ActionResult {
+    #[prost(message, repeated, tag = "2")]
+    pub output_files: Vec<OutputFile>,
+    #[prost(int32, tag = "4")]
+    pub exit_code: i32,
+    #[prost(bytes = "vec", tag = "5")]
+    pub stdout_raw: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GetActionResultRequest {
+    #[prost(string, tag = "1")]
+    pub instance_name: String,
+    #[prost(message, optional, tag = "2")]
+    pub action_digest: Option<Digest>,
+    #[prost(bool, tag = "3")]
+    pub inline_stdout: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct UpdateActionResultRequest {
+    #[prost(string, tag = "1")]
+    pub instance_name: String,
+    #[prost(message, optional, tag = "2")]
+    pub action_digest: Option<Digest>,
+    #[prost(message, optional, tag = "3")]
+    pub action_result: Option<ActionResult>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FindMissingBlobsRequest {
+    #[prost(string, tag = "1")]
+    pub instance_name: String,
+    #[prost(message, repeated, tag = "2")]
+    pub blob_digests: Vec<Digest>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FindMissingBlobsResponse {
+    #[prost(message, repeated, tag = "2")]
+    pub missing_blob_digests: Vec<Digest>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BatchUpdateBlobsRequest {
+    #[prost(string, tag = "1")]
+    pub instance_name: String,
+    #[prost(message, repeated, tag = "2")]
+    pub requests: Vec<UpdateBlobRequest>,
+}
+
+/// `BatchUpdateBlobsRequest.Request`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct UpdateBlobRequest {
+    #[prost(message, optional, tag = "1")]
+    pub digest: Option<Digest>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BatchUpdateBlobsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub responses: Vec<UpdateBlobResponse>,
+}
+
+/// `BatchUpdateBlobsResponse.Response`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct UpdateBlobResponse {
+    #[prost(message, optional, tag = "1")]
+    pub digest: Option<Digest>,
+    #[prost(message, optional, tag = "2")]
+    pub status: Option<RpcStatus>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BatchReadBlobsRequest {
+    #[prost(string, tag = "1")]
+    pub instance_name: String,
+    #[prost(message, repeated, tag = "2")]
+    pub digests: Vec<Digest>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BatchReadBlobsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub responses: Vec<ReadBlobResponse>,
+}
+
+/// `BatchReadBlobsResponse.Response`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ReadBlobResponse {
+    #[prost(message, optional, tag = "1")]
+    pub digest: Option<Digest>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub data: Vec<u8>,
+    #[prost(message, optional, tag = "3")]
+    pub status: Option<RpcStatus>,
+}
+
+/// `google.rpc.Status`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RpcStatus {
+    #[prost(int32, tag = "1")]
+    pub code: i32,
+    #[prost(string, tag = "2")]
+    pub message: String,
+}
+
+/// `google.bytestream.ReadRequest`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ReadRequest {
+    #[prost(string, tag = "1")]
+    pub resource_name: String,
+    #[prost(int64, tag = "2")]
+    pub read_offset: i64,
+    #[prost(int64, tag = "3")]
+    pub read_limit: i64,
+}
+
+/// `google.bytestream.ReadResponse`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ReadResponse {
+    #[prost(bytes = "vec", tag = "10")]
+    pub data: Vec<u8>,
+}
+
+/// `google.bytestream.WriteRequest`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct WriteRequest {
+    #[prost(string, tag = "1")]
+    pub resource_name: String,
+    #[prost(int64, tag = "2")]
+    pub write_offset: i64,
+    #[prost(bool, tag = "3")]
+    pub finish_write: bool,
+    #[prost(bytes = "vec", tag = "10")]
+    pub data: Vec<u8>,
+}
+
+/// `google.bytestream.WriteResponse`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct WriteResponse {
+    #[prost(int64, tag = "1")]
+    pub committed_size: i64,
+}
@@ -0,0 +1,1040 @@
+// the errors of ureq are large, and are matched on their status where the requests are sent
+#![allow(clippy::result_large_err)]
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context};
+use napi::bindgen_prelude::AsyncTask;
+use napi::{Env, Task};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use tracing::{debug, trace};
+
+use crate::native::cache::cache::CachedResult;
+use crate::native::cache::chunking::{
+    chunk_file, manifest, parse_manifest, unique_digests, Chunk, CHUNKED_ARTIFACT_TYPE,
+};
+use crate::native::cache::encryption::{decrypt_bytes, decrypted, encrypt_bytes, encrypt_in_place};
+use crate::native::cache::file_tree::{
+    matches_digest, parse_tree_manifest, read_tree, tree_manifest, FileTree, TreeEntryKind,
+    FILE_TREE_TYPE,
+};
+use crate::native::compression::{self, decoder, encoder, record_throughput, CompressionTarget};
+use crate::native::utils::Normalize;
+
+const DEFAULT_RETRIES: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_millis(250);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Artifacts bigger than this are uploaded and downloaded in chunks of this size
+const CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+const PARALLEL_DOWNLOADS: usize = 4;
+/// Artifacts bigger than this are uploaded in content-defined chunks when chunking is enabled
+const MIN_CHUNKED_ARTIFACT_SIZE: u64 = CHUNK_SIZE;
+
+/// The entries of an artifact, the outputs are in the `outputs` directory
+const CODE_ENTRY: &str = "code";
+const TERMINAL_OUTPUT_ENTRY: &str = "terminalOutput";
+const OUTPUTS_ENTRY: &str = "outputs";
+
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct RemoteCacheOptions {
+    /// The url of the cache server, artifacts are read from and written to `<url>/v1/cache/<hash>`
+    pub url: String,
+    /// Sent as a bearer token
+    pub access_token: Option<String>,
+    /// How many times failed requests are retried, 3 by default
+    pub retries: Option<u32>,
+    /// The timeout of connecting and of reading, in milliseconds
+    pub timeout: Option<u32>,
+    /// Uploads big artifacts in content-defined chunks, and only the chunks that the server does not have yet.
+    /// The server has to implement the content addressable storage endpoints (`<url>/v1/cas`), false by default
+    pub chunking: Option<bool>,
+    /// Uploads the outputs file by file, so they are downloaded in parallel directly into the cache directory
+    /// instead of as an archive that is then unpacked. This is faster for outputs of many small files over high-latency links.
+    /// The server has to implement the content addressable storage endpoints, false by default. Takes precedence over `chunking`
+    pub file_transfers: Option<bool>,
+}
+
+/// A client of the Nx remote cache HTTP protocol.
+/// Artifacts are compressed tarballs of the outputs of a task, with its terminal output and exit code
+#[napi]
+pub struct RemoteCacheClient {
+    http: HttpClient,
+}
+
+#[napi]
+impl RemoteCacheClient {
+    #[napi(constructor)]
+    pub fn new(options: RemoteCacheOptions) -> Self {
+        let timeout = options
+            .timeout
+            .map(|timeout| Duration::from_millis(timeout as u64))
+            .unwrap_or(DEFAULT_TIMEOUT);
+        Self {
+            http: HttpClient {
+                agent: ureq::AgentBuilder::new()
+                    .timeout_connect(timeout)
+                    .timeout_read(timeout)
+                    .build(),
+                url: options.url.trim_end_matches('/').to_string(),
+                access_token: options.access_token,
+                retries: options.retries.unwrap_or(DEFAULT_RETRIES),
+                chunking: options.chunking.unwrap_or(false),
+                file_transfers: options.file_transfers.unwrap_or(false),
+            },
+        }
+    }
+
+    /// Downloads the artifact of a task into `<cache_directory>/<hash>`.
+    /// Resolves with null when the remote cache does not have it
+    #[napi(ts_return_type = "Promise<CachedResult | null>")]
+    pub fn retrieve(&self, hash: String, cache_directory: String) -> AsyncTask<RetrieveArtifact> {
+        AsyncTask::new(RetrieveArtifact {
+            http: self.http.clone(),
+            hash,
+            cache_directory: PathBuf::from(cache_directory),
+        })
+    }
+
+    /// Uploads the outputs in `<cache_directory>/<hash>` with the terminal output and exit code of the task.
+    /// Resolves with false when the remote cache already has the artifact
+    #[napi(ts_return_type = "Promise<boolean>")]
+    pub fn store(
+        &self,
+        hash: String,
+        cache_directory: String,
+        terminal_output: String,
+        code: i16,
+    ) -> AsyncTask<StoreArtifact> {
+        AsyncTask::new(StoreArtifact {
+            http: self.http.clone(),
+            hash,
+            cache_directory: PathBuf::from(cache_directory),
+            terminal_output,
+            code,
+        })
+    }
+
+    /// Whether the remote cache has the artifact of each hash, without downloading them.
+    /// Asks for all of the hashes in a single request, or with a `HEAD` request per hash
+    /// when the server does not implement `POST <url>/v1/cache/missing`
+    #[napi(ts_return_type = "Promise<Record<string, boolean>>")]
+    pub fn check_cache_hits(&self, hashes: Vec<String>) -> AsyncTask<CheckArtifacts> {
+        AsyncTask::new(CheckArtifacts {
+            http: self.http.clone(),
+            hashes,
+        })
+    }
+}
+
+pub struct CheckArtifacts {
+    http: HttpClient,
+    hashes: Vec<String>,
+}
+
+impl Task for CheckArtifacts {
+    type Output = HashMap<String, bool>;
+    type JsValue = HashMap<String, bool>;
+
+    fn compute(&mut self) -> napi::Result<Self::Output> {
+        let missing = self
+            .http
+            .missing_artifacts(&self.hashes)
+            .map_err(|e| napi::Error::from_reason(format!("{:#}", e)))?;
+        Ok(self
+            .hashes
+            .iter()
+            .map(|hash| (hash.clone(), !missing.contains(hash)))
+            .collect())
+    }
+
+    fn resolve(&mut self, _: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+pub struct RetrieveArtifact {
+    http: HttpClient,
+    hash: String,
+    cache_directory: PathBuf,
+}
+
+impl Task for RetrieveArtifact {
+    type Output = Option<CachedResult>;
+    type JsValue = Option<CachedResult>;
+
+    fn compute(&mut self) -> napi::Result<Self::Output> {
+        let artifact = self
+            .cache_directory
+            .join(format!("{}.download.tar.zst", self.hash));
+        let task_dir = self.cache_directory.join(&self.hash);
+        let result = self
+            .http
+            .download(&self.hash, &artifact, &task_dir)
+            .and_then(|downloaded| {
+                let (code, terminal_output) = match downloaded {
+                    None => return Ok(None),
+                    Some(Downloaded::Artifact) => unpack_artifact(&artifact, &task_dir)?,
+                    Some(Downloaded::Tarball) => unpack_tarball(&artifact, &task_dir)?,
+                    Some(Downloaded::Files {
+                        code,
+                        terminal_output,
+                    }) => (code, terminal_output),
+                };
+                Ok(Some(CachedResult {
+                    code,
+                    terminal_output,
+                    outputs_path: task_dir.to_normalized_string(),
+                    hash_version: None,
+                }))
+            });
+        fs::remove_file(&artifact).ok();
+        result.map_err(|e| napi::Error::from_reason(format!("{:#}", e)))
+    }
+
+    fn resolve(&mut self, _: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+pub struct StoreArtifact {
+    http: HttpClient,
+    hash: String,
+    cache_directory: PathBuf,
+    terminal_output: String,
+    code: i16,
+}
+
+impl Task for StoreArtifact {
+    type Output = bool;
+    type JsValue = bool;
+
+    fn compute(&mut self) -> napi::Result<Self::Output> {
+        if self.http.file_transfers {
+            let task_dir = self.cache_directory.join(&self.hash);
+            match self
+                .http
+                .upload_files(&self.hash, &task_dir, &self.terminal_output, self.code)
+            {
+                Ok(Some(stored)) => return Ok(stored),
+                Ok(None) => {}
+                Err(e) => return Err(napi::Error::from_reason(format!("{:#}", e))),
+            }
+        } else if self.http.chunking {
+            let tarball = self
+                .cache_directory
+                .join(format!("{}.upload.tar", self.hash));
+            let result = self.store_chunked(&tarball);
+            fs::remove_file(&tarball).ok();
+            match result {
+                Ok(Some(stored)) => return Ok(stored),
+                Ok(None) => {}
+                Err(e) => return Err(napi::Error::from_reason(format!("{:#}", e))),
+            }
+        }
+
+        let artifact = self
+            .cache_directory
+            .join(format!("{}.upload.tar.zst", self.hash));
+        let result = pack_artifact(
+            &self.cache_directory.join(&self.hash),
+            &self.terminal_output,
+            self.code,
+            &artifact,
+        )
+        .and_then(|packing| {
+            let started = Instant::now();
+            let stored = self.http.upload(&self.hash, &artifact)?;
+            if stored {
+                record_throughput(CompressionTarget::Remote, packing, started.elapsed());
+            }
+            Ok(stored)
+        });
+        fs::remove_file(&artifact).ok();
+        result.map_err(|e| napi::Error::from_reason(format!("{:#}", e)))
+    }
+
+    fn resolve(&mut self, _: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+impl StoreArtifact {
+    /// Uploads the artifact in chunks when it is big enough.
+    /// Returns None when it should be uploaded as a whole instead, because it is small or because the server can not store chunks
+    fn store_chunked(&self, tarball: &Path) -> anyhow::Result<Option<bool>> {
+        pack_tarball(
+            &self.cache_directory.join(&self.hash),
+            &self.terminal_output,
+            self.code,
+            tarball,
+        )?;
+        if fs::metadata(tarball)?.len() <= MIN_CHUNKED_ARTIFACT_SIZE {
+            return Ok(None);
+        }
+        self.http.upload_chunked(&self.hash, tarball)
+    }
+}
+
+/// How a downloaded artifact is stored
+enum Downloaded {
+    /// A compressed tarball, which may be encrypted
+    Artifact,
+    /// The tarball of a chunked artifact, reassembled from its chunks
+    Tarball,
+    /// The files of an artifact stored file by file, which are already in the task directory
+    Files { code: i16, terminal_output: String },
+}
+
+#[derive(Clone)]
+struct HttpClient {
+    agent: ureq::Agent,
+    url: String,
+    access_token: Option<String>,
+    retries: u32,
+    chunking: bool,
+    file_transfers: bool,
+}
+
+impl HttpClient {
+    fn artifact_url(&self, hash: &str) -> String {
+        format!("{}/v1/cache/{}", self.url, hash)
+    }
+
+    fn blob_url(&self, digest: &str) -> String {
+        format!("{}/v1/cas/{}", self.url, digest)
+    }
+
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        let request = self.agent.request(method, url);
+        match &self.access_token {
+            Some(token) => request.set("Authorization", &format!("Bearer {}", token)),
+            None => request,
+        }
+    }
+
+    fn with_retries<T>(
+        &self,
+        send: impl FnMut() -> Result<T, ureq::Error>,
+    ) -> Result<T, ureq::Error> {
+        with_retries(self.retries, send)
+    }
+
+    /// Downloads an artifact to `dest`, in parallel chunks when the server supports range requests.
+    /// Chunked artifacts are reassembled from their chunks, and artifacts stored file by file are downloaded into `task_dir`.
+    /// Returns None when the artifact is not found
+    fn download(
+        &self,
+        hash: &str,
+        dest: &Path,
+        task_dir: &Path,
+    ) -> anyhow::Result<Option<Downloaded>> {
+        let url = self.artifact_url(hash);
+        let ranged_len = match self.with_retries(|| self.request("HEAD", &url).call()) {
+            // manifests are small
+            Ok(response)
+                if [CHUNKED_ARTIFACT_TYPE, FILE_TREE_TYPE].contains(&response.content_type()) =>
+            {
+                None
+            }
+            Ok(response) => response
+                .header("Accept-Ranges")
+                .filter(|unit| *unit == "bytes")
+                .and(response.header("Content-Length"))
+                .and_then(|len| len.parse::<u64>().ok()),
+            Err(ureq::Error::Status(404, _)) => return Ok(None),
+            // servers that do not support HEAD requests are downloaded from in one request
+            Err(ureq::Error::Status(405, _)) => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        match ranged_len {
+            Some(len) if len > CHUNK_SIZE => {
+                trace!("downloading {} ({} bytes) in chunks", hash, len);
+                self.download_chunks(&url, len, dest)?;
+            }
+            _ => {
+                let response = match self.with_retries(|| self.request("GET", &url).call()) {
+                    Err(ureq::Error::Status(404, _)) => return Ok(None),
+                    response => response?,
+                };
+                if response.content_type() == CHUNKED_ARTIFACT_TYPE {
+                    let mut manifest = vec![];
+                    response.into_reader().read_to_end(&mut manifest)?;
+                    trace!("downloading the chunks of {}", hash);
+                    self.download_chunked(&parse_manifest(&manifest)?, dest)?;
+                    return Ok(Some(Downloaded::Tarball));
+                }
+                if response.content_type() == FILE_TREE_TYPE {
+                    let mut manifest = vec![];
+                    response.into_reader().read_to_end(&mut manifest)?;
+                    let tree = parse_tree_manifest(&decrypt_bytes(manifest)?)?;
+                    self.download_files(&tree, task_dir)?;
+                    return Ok(Some(Downloaded::Files {
+                        code: tree.code,
+                        terminal_output: tree.terminal_output,
+                    }));
+                }
+                let mut file = BufWriter::new(File::create(dest)?);
+                io::copy(&mut response.into_reader(), &mut file)?;
+                file.flush()?;
+            }
+        }
+        Ok(Some(Downloaded::Artifact))
+    }
+
+    /// Downloads the chunks of an artifact in parallel into `dest`, and verifies their digests
+    fn download_chunked(&self, chunks: &[Chunk], dest: &Path) -> anyhow::Result<()> {
+        let len = chunks.last().map_or(0, |chunk| chunk.offset + chunk.len);
+        File::create(dest)?.set_len(len)?;
+        in_parallel(chunks, |chunk| {
+            let data = self.download_blob(&chunk.digest)?;
+            if !matches_digest(&data, &chunk.digest, chunk.len) {
+                bail!(
+                    "The chunk {} of the remote cache is corrupted",
+                    chunk.digest
+                );
+            }
+            let mut file = OpenOptions::new().write(true).open(dest)?;
+            file.seek(SeekFrom::Start(chunk.offset))?;
+            file.write_all(&data)?;
+            Ok(())
+        })
+    }
+
+    /// Uploads the chunks of a tarball that the server does not have yet, and then the manifest of the chunks in place of the artifact.
+    /// Returns None when the server does not store chunks, and false when it already has the artifact
+    fn upload_chunked(&self, hash: &str, tarball: &Path) -> anyhow::Result<Option<bool>> {
+        let chunks = chunk_file(tarball)?;
+        let digests = unique_digests(&chunks);
+        let Some(missing) = self.missing_blobs(&digests)? else {
+            debug!(
+                "the remote cache does not store chunks, {} is uploaded as a whole",
+                hash
+            );
+            return Ok(None);
+        };
+        let mut uploaded = HashSet::new();
+        let to_upload = chunks
+            .iter()
+            .filter(|chunk| missing.contains(&chunk.digest) && uploaded.insert(&chunk.digest))
+            .collect::<Vec<_>>();
+        trace!(
+            "uploading {} of the {} chunks of {}",
+            to_upload.len(),
+            digests.len(),
+            hash
+        );
+
+        in_parallel(&to_upload, |chunk| {
+            let mut data = vec![0; chunk.len as usize];
+            let mut file = File::open(tarball)?;
+            file.seek(SeekFrom::Start(chunk.offset))?;
+            file.read_exact(&mut data)?;
+            self.upload_blob(&chunk.digest, &data)
+        })?;
+
+        let manifest = serde_json::to_vec(&manifest(&chunks))?;
+        self.upload_manifest(hash, CHUNKED_ARTIFACT_TYPE, manifest)
+            .map(Some)
+    }
+
+    /// Downloads the files of an artifact in parallel directly into `task_dir`, and verifies their digests.
+    /// Files with the same contents are downloaded once
+    fn download_files(&self, tree: &FileTree, task_dir: &Path) -> anyhow::Result<()> {
+        fs_extra::remove_items(&[task_dir])?;
+        fs::create_dir_all(task_dir)?;
+        let mut files: BTreeMap<&str, Vec<(&str, u64, u32)>> = BTreeMap::new();
+        for entry in &tree.entries {
+            match &entry.kind {
+                TreeEntryKind::Directory => fs::create_dir_all(task_dir.join(&entry.path))?,
+                TreeEntryKind::File { digest, size, mode } => files
+                    .entry(digest.as_str())
+                    .or_default()
+                    .push((entry.path.as_str(), *size, *mode)),
+                TreeEntryKind::Symlink { .. } => {}
+            }
+        }
+        trace!(
+            "downloading {} files ({} distinct) into {:?}",
+            files.values().map(Vec::len).sum::<usize>(),
+            files.len(),
+            task_dir
+        );
+
+        let files = files.into_iter().collect::<Vec<_>>();
+        in_parallel(&files, |(digest, paths)| {
+            let data = self.download_blob(digest)?;
+            for &(path, size, mode) in paths {
+                if !matches_digest(&data, digest, size) {
+                    bail!("The file {} of the remote cache is corrupted", path);
+                }
+                let dest = task_dir.join(path);
+                if let Some(parent) = dest.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&dest, &data)?;
+                set_mode(&dest, mode)?;
+            }
+            Ok(())
+        })?;
+
+        for entry in &tree.entries {
+            if let TreeEntryKind::Symlink { target } = &entry.kind {
+                let dest = task_dir.join(&entry.path);
+                if let Some(parent) = dest.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                symlink(target, &dest)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Uploads the files of the outputs in `task_dir` that the server does not have yet, and then the manifest of the files
+    /// in place of the artifact. Returns None when the server does not store files, and false when it already has the artifact
+    fn upload_files(
+        &self,
+        hash: &str,
+        task_dir: &Path,
+        terminal_output: &str,
+        code: i16,
+    ) -> anyhow::Result<Option<bool>> {
+        let tree = read_tree(task_dir, code, terminal_output)?;
+        let mut paths = BTreeMap::new();
+        for entry in &tree.entries {
+            if let TreeEntryKind::File { digest, .. } = &entry.kind {
+                paths.entry(digest.as_str()).or_insert(&entry.path);
+            }
+        }
+        let digests = paths.keys().copied().collect::<Vec<_>>();
+        let Some(missing) = self.missing_blobs(&digests)? else {
+            debug!(
+                "the remote cache does not store files, {} is uploaded as an archive",
+                hash
+            );
+            return Ok(None);
+        };
+        let to_upload = paths
+            .into_iter()
+            .filter(|(digest, _)| missing.contains(*digest))
+            .collect::<Vec<_>>();
+        trace!(
+            "uploading {} of the {} files of {}",
+            to_upload.len(),
+            digests.len(),
+            hash
+        );
+
+        in_parallel(&to_upload, |(digest, path)| {
+            self.upload_blob(digest, &fs::read(task_dir.join(path))?)
+        })?;
+
+        // the manifest contains the terminal output, it is encrypted like artifacts are
+        let manifest = encrypt_bytes(serde_json::to_vec(&tree_manifest(&tree))?)?;
+        self.upload_manifest(hash, FILE_TREE_TYPE, manifest)
+            .map(Some)
+    }
+
+    /// The hashes whose artifacts the server does not have
+    fn missing_artifacts(&self, hashes: &[String]) -> anyhow::Result<HashSet<String>> {
+        if hashes.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let body = serde_json::to_vec(&json!({ "hashes": hashes }))?;
+        let url = format!("{}/v1/cache/missing", self.url);
+        match self.with_retries(|| {
+            self.request("POST", &url)
+                .set("Content-Type", "application/json")
+                .send_bytes(&body)
+        }) {
+            Ok(response) => {
+                let missing: Value = serde_json::from_str(&response.into_string()?)?;
+                return Ok(missing["missing"]
+                    .as_array()
+                    .context("The remote cache did not list the missing artifacts")?
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(String::from)
+                    .collect());
+            }
+            Err(ureq::Error::Status(404 | 405 | 501, _)) => {
+                trace!("the remote cache can not check artifacts in batches");
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        let missing = Mutex::new(HashSet::new());
+        in_parallel(hashes, |hash| {
+            let url = self.artifact_url(hash);
+            match self.with_retries(|| self.request("HEAD", &url).call()) {
+                Ok(_) => {}
+                Err(ureq::Error::Status(404, _)) => {
+                    missing.lock().insert(hash.clone());
+                }
+                Err(e) => return Err(e.into()),
+            }
+            Ok(())
+        })?;
+        Ok(missing.into_inner())
+    }
+
+    /// The digests that the content addressable storage of the server does not have.
+    /// Returns None when the server does not implement it
+    fn missing_blobs(&self, digests: &[&str]) -> anyhow::Result<Option<HashSet<String>>> {
+        let body = serde_json::to_vec(&json!({ "digests": digests }))?;
+        let url = format!("{}/v1/cas/missing", self.url);
+        let response = match self.with_retries(|| {
+            self.request("POST", &url)
+                .set("Content-Type", "application/json")
+                .send_bytes(&body)
+        }) {
+            Ok(response) => response,
+            Err(ureq::Error::Status(404 | 405 | 501, _)) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let missing: Value = serde_json::from_str(&response.into_string()?)?;
+        let missing = missing["missing"]
+            .as_array()
+            .context("The remote cache did not list the missing blobs")?
+            .iter()
+            .filter_map(Value::as_str)
+            .map(String::from)
+            .collect();
+        Ok(Some(missing))
+    }
+
+    /// Stores `data` in the content addressable storage, compressed and encrypted when a key is configured
+    fn upload_blob(&self, digest: &str, data: &[u8]) -> anyhow::Result<()> {
+        let started = Instant::now();
+        let body = encrypt_bytes(compression::compress(data, CompressionTarget::Remote)?)?;
+        let compressing = started.elapsed();
+        let url = self.blob_url(digest);
+        let started = Instant::now();
+        match self.with_retries(|| {
+            self.request("PUT", &url)
+                .set("Content-Type", "application/octet-stream")
+                .send_bytes(&body)
+        }) {
+            Ok(_) => {
+                record_throughput(CompressionTarget::Remote, compressing, started.elapsed());
+                Ok(())
+            }
+            Err(ureq::Error::Status(409, _)) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Reads a blob stored by [HttpClient::upload_blob], its digest is verified by the caller
+    fn download_blob(&self, digest: &str) -> anyhow::Result<Vec<u8>> {
+        let url = self.blob_url(digest);
+        let response = self.with_retries(|| self.request("GET", &url).call())?;
+        let mut body = vec![];
+        response.into_reader().read_to_end(&mut body)?;
+        compression::decompress(&decrypt_bytes(body)?)
+    }
+
+    /// Stores the manifest of a chunked artifact or of an artifact stored file by file in place of the artifact.
+    /// Returns false when the server already has the artifact
+    fn upload_manifest(
+        &self,
+        hash: &str,
+        content_type: &str,
+        manifest: Vec<u8>,
+    ) -> anyhow::Result<bool> {
+        let url = self.artifact_url(hash);
+        match self.with_retries(|| {
+            self.request("PUT", &url)
+                .set("Content-Type", content_type)
+                .send_bytes(&manifest)
+        }) {
+            Ok(_) => Ok(true),
+            Err(ureq::Error::Status(409, _)) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn download_chunks(&self, url: &str, len: u64, dest: &Path) -> anyhow::Result<()> {
+        File::create(dest)?.set_len(len)?;
+        let ranges = chunk_ranges(len, CHUNK_SIZE);
+        let next = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            let workers = (0..PARALLEL_DOWNLOADS.min(ranges.len()))
+                .map(|_| {
+                    scope.spawn(|| -> anyhow::Result<()> {
+                        let mut file = OpenOptions::new().write(true).open(dest)?;
+                        while let Some(&(start, end)) =
+                            ranges.get(next.fetch_add(1, Ordering::Relaxed))
+                        {
+                            let response = self.with_retries(|| {
+                                self.request("GET", url)
+                                    .set("Range", &format!("bytes={}-{}", start, end))
+                                    .call()
+                            })?;
+                            if response.status() != 206 {
+                                bail!("The remote cache ignored a range request");
+                            }
+                            file.seek(SeekFrom::Start(start))?;
+                            let expected = end - start + 1;
+                            let copied =
+                                io::copy(&mut response.into_reader().take(expected), &mut file)?;
+                            if copied != expected {
+                                bail!("Downloaded {} bytes of a {} bytes chunk", copied, expected);
+                            }
+                        }
+                        Ok(())
+                    })
+                })
+                .collect::<Vec<_>>();
+            workers.into_iter().try_for_each(|worker| {
+                worker
+                    .join()
+                    .map_err(|_| anyhow!("A download thread panicked"))?
+            })
+        })
+    }
+
+    /// Uploads an artifact, in chunks that are resumed from what the server received when a chunk fails.
+    /// Returns false when the server already has the artifact
+    fn upload(&self, hash: &str, artifact: &Path) -> anyhow::Result<bool> {
+        let url = self.artifact_url(hash);
+        let len = fs::metadata(artifact)?.len();
+
+        if len <= CHUNK_SIZE {
+            let body = fs::read(artifact)?;
+            return match self.with_retries(|| {
+                self.request("PUT", &url)
+                    .set("Content-Type", "application/octet-stream")
+                    .send_bytes(&body)
+            }) {
+                Ok(_) => Ok(true),
+                Err(ureq::Error::Status(409, _)) => Ok(false),
+                Err(e) => Err(e.into()),
+            };
+        }
+
+        let mut file = File::open(artifact)?;
+        let mut offset = 0;
+        let mut failures = 0;
+        while offset < len {
+            let end = (offset + CHUNK_SIZE).min(len) - 1;
+            let mut chunk = vec![0; (end - offset + 1) as usize];
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut chunk)?;
+
+            match self
+                .request("PUT", &url)
+                .set("Content-Type", "application/octet-stream")
+                .set(
+                    "Content-Range",
+                    &format!("bytes {}-{}/{}", offset, end, len),
+                )
+                .send_bytes(&chunk)
+            {
+                Ok(_) => {
+                    offset = end + 1;
+                    failures = 0;
+                }
+                Err(ureq::Error::Status(409, _)) => return Ok(false),
+                Err(e) if failures < self.retries && is_retryable(&e) => {
+                    let delay = backoff(failures);
+                    debug!("resuming the upload of {} in {:?}: {}", hash, delay, e);
+                    thread::sleep(delay);
+                    failures += 1;
+                    offset = self.received_bytes(&url, len).unwrap_or(offset);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Asks the server how much of an upload it received, with an empty `Content-Range: bytes */<len>` request
+    fn received_bytes(&self, url: &str, len: u64) -> Option<u64> {
+        let response = self
+            .request("PUT", url)
+            .set("Content-Range", &format!("bytes */{}", len))
+            .call()
+            .ok()?;
+        Some(
+            response
+                .header("Range")
+                .and_then(parse_received_range)
+                .unwrap_or(0),
+        )
+    }
+}
+
+/// Retries requests that failed because of the network, rate limiting or the server, with exponential backoff
+pub(crate) fn with_retries<T>(
+    retries: u32,
+    mut send: impl FnMut() -> Result<T, ureq::Error>,
+) -> Result<T, ureq::Error> {
+    let mut attempt = 0;
+    loop {
+        match send() {
+            Err(e) if attempt < retries && is_retryable(&e) => {
+                let delay = backoff(attempt);
+                debug!("retrying a request in {:?}: {}", delay, e);
+                thread::sleep(delay);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+pub(super) fn is_retryable(error: &ureq::Error) -> bool {
+    match error {
+        ureq::Error::Transport(_) => true,
+        ureq::Error::Status(code, _) => *code == 429 || *code >= 500,
+    }
+}
+
+pub(super) fn backoff(attempt: u32) -> Duration {
+    RETRY_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_RETRY_DELAY)
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_: &Path, _: u32) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn symlink(target: &str, path: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+#[cfg(windows)]
+fn symlink(target: &str, path: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(target, path)
+}
+
+/// Calls `work` for each item on `PARALLEL_DOWNLOADS` threads, and stops at the first error
+pub(super) fn in_parallel<T: Sync>(
+    items: &[T],
+    work: impl Fn(&T) -> anyhow::Result<()> + Sync,
+) -> anyhow::Result<()> {
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        let workers = (0..PARALLEL_DOWNLOADS.min(items.len()))
+            .map(|_| {
+                scope.spawn(|| -> anyhow::Result<()> {
+                    while let Some(item) = items.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        if let Err(e) = work(item) {
+                            // the other threads stop after their current item
+                            next.store(items.len(), Ordering::Relaxed);
+                            return Err(e);
+                        }
+                    }
+                    Ok(())
+                })
+            })
+            .collect::<Vec<_>>();
+        workers.into_iter().try_for_each(|worker| {
+            worker
+                .join()
+                .map_err(|_| anyhow!("A transfer thread panicked"))?
+        })
+    })
+}
+
+/// Splits `len` bytes into inclusive ranges of at most `size` bytes
+fn chunk_ranges(len: u64, size: u64) -> Vec<(u64, u64)> {
+    (0..len)
+        .step_by(size as usize)
+        .map(|start| (start, (start + size).min(len) - 1))
+        .collect()
+}
+
+/// Parses the `Range: bytes=0-<last>` header of a resumable upload into the number of bytes received
+fn parse_received_range(range: &str) -> Option<u64> {
+    let (start, last) = range.strip_prefix("bytes=")?.split_once('-')?;
+    if start.trim() != "0" {
+        return None;
+    }
+    last.trim().parse::<u64>().ok().map(|last| last + 1)
+}
+
+/// Packs an artifact to be uploaded, and returns the time it took, which adapts the compression level to the
+/// throughput of the upload with [record_throughput]
+pub(super) fn pack_artifact(
+    task_dir: &Path,
+    terminal_output: &str,
+    code: i16,
+    dest: &Path,
+) -> anyhow::Result<Duration> {
+    let started = Instant::now();
+    let file = BufWriter::new(File::create(dest)?);
+    let encoder = write_entries(
+        encoder(file, CompressionTarget::Remote)?,
+        task_dir,
+        terminal_output,
+        code,
+    )?;
+    encoder.finish_untimed()?.flush()?;
+    encrypt_in_place(dest)?;
+    Ok(started.elapsed())
+}
+
+/// Packs an artifact into an uncompressed and unencrypted tarball, which is compressed and encrypted chunk by chunk instead
+fn pack_tarball(
+    task_dir: &Path,
+    terminal_output: &str,
+    code: i16,
+    dest: &Path,
+) -> anyhow::Result<()> {
+    let file = BufWriter::new(File::create(dest)?);
+    write_entries(file, task_dir, terminal_output, code)?.flush()?;
+    Ok(())
+}
+
+fn write_entries<W: Write>(
+    writer: W,
+    task_dir: &Path,
+    terminal_output: &str,
+    code: i16,
+) -> anyhow::Result<W> {
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+    for (path, data) in [
+        (CODE_ENTRY, code.to_string().into_bytes()),
+        (TERMINAL_OUTPUT_ENTRY, terminal_output.as_bytes().to_vec()),
+    ] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, data.as_slice())?;
+    }
+    if task_dir.is_dir() {
+        builder.append_dir_all(OUTPUTS_ENTRY, task_dir)?;
+    }
+    Ok(builder.into_inner()?)
+}
+
+/// Extracts the outputs of an artifact into `task_dir`, and returns its exit code and terminal output
+pub(super) fn unpack_artifact(artifact: &Path, task_dir: &Path) -> anyhow::Result<(i16, String)> {
+    let artifact = decrypted(artifact)?;
+    let file = BufReader::new(File::open(&*artifact)?);
+    unpack_entries(tar::Archive::new(decoder(file)?), task_dir)
+}
+
+/// Extracts the outputs of a tarball written by [pack_tarball] into `task_dir`
+fn unpack_tarball(tarball: &Path, task_dir: &Path) -> anyhow::Result<(i16, String)> {
+    let file = BufReader::new(File::open(tarball)?);
+    unpack_entries(tar::Archive::new(file), task_dir)
+}
+
+fn unpack_entries<R: Read>(
+    mut archive: tar::Archive<R>,
+    task_dir: &Path,
+) -> anyhow::Result<(i16, String)> {
+    fs_extra::remove_items(&[task_dir])?;
+    fs::create_dir_all(task_dir)?;
+    archive.set_preserve_permissions(true);
+
+    let mut code = None;
+    let mut terminal_output = String::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        if path == Path::new(CODE_ENTRY) {
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents)?;
+            code = contents.trim().parse::<i16>().ok();
+        } else if path == Path::new(TERMINAL_OUTPUT_ENTRY) {
+            entry.read_to_string(&mut terminal_output)?;
+        } else if let Ok(output) = path.strip_prefix(OUTPUTS_ENTRY) {
+            if output.as_os_str().is_empty() {
+                continue;
+            }
+            if output
+                .components()
+                .any(|component| !matches!(component, Component::Normal(_)))
+            {
+                bail!("The artifact contains an invalid path: {:?}", path);
+            }
+            let dest = task_dir.join(output);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            entry.unpack(&dest)?;
+        }
+    }
+    Ok((
+        code.context("The artifact does not contain an exit code")?,
+        terminal_output,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    #[test]
+    fn should_split_artifacts_into_chunks() {
+        assert_eq!(chunk_ranges(10, 4), vec![(0, 3), (4, 7), (8, 9)]);
+        assert_eq!(chunk_ranges(8, 4), vec![(0, 3), (4, 7)]);
+        assert_eq!(chunk_ranges(0, 4), vec![]);
+
+        assert_eq!(parse_received_range("bytes=0-8388607"), Some(8388608));
+        assert_eq!(parse_received_range("bytes=100-200"), None);
+        assert_eq!(parse_received_range("items=0-1"), None);
+    }
+
+    #[test]
+    fn should_back_off_exponentially() {
+        assert_eq!(backoff(0), Duration::from_millis(250));
+        assert_eq!(backoff(2), Duration::from_millis(1000));
+        assert_eq!(backoff(10), MAX_RETRY_DELAY);
+        assert_eq!(backoff(u32::MAX), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn should_pack_and_unpack_artifacts() {
+        let temp = TempDir::new().unwrap();
+        temp.child("cache/hash/dist/main.js")
+            .write_str("main")
+            .unwrap();
+        temp.child("cache/hash/dist/assets/logo.svg")
+            .write_str("logo")
+            .unwrap();
+        let artifact = temp.join("hash.tar.zst");
+        pack_artifact(&temp.join("cache/hash"), "> built", 1, &artifact).unwrap();
+
+        let task_dir = temp.child("remote/hash");
+        task_dir.child("stale.js").touch().unwrap();
+        let (code, terminal_output) = unpack_artifact(&artifact, &task_dir).unwrap();
+        assert_eq!(code, 1);
+        assert_eq!(terminal_output, "> built");
+        task_dir.child("dist/main.js").assert("main");
+        task_dir.child("dist/assets/logo.svg").assert("logo");
+        assert!(!task_dir.join("stale.js").exists());
+
+        let tarball = temp.join("hash.tar");
+        pack_tarball(&temp.join("cache/hash"), "> built", 0, &tarball).unwrap();
+        let (code, _) = unpack_tarball(&tarball, &task_dir).unwrap();
+        assert_eq!(code, 0);
+        task_dir.child("dist/main.js").assert("main");
+    }
+}
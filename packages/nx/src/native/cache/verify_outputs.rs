@@ -0,0 +1,143 @@
+use std::fs;
+use std::path::Path;
+
+use tracing::trace;
+
+use crate::native::cache::expand_outputs::get_files_for_outputs;
+use crate::native::utils::parallel::prelude::*;
+
+#[napi(object)]
+#[derive(Debug, Default, PartialEq)]
+pub struct OutputsVerification {
+    /// The files of the cached outputs that are not in the workspace, relative to the workspace root
+    pub missing: Vec<String>,
+    /// The files whose size in the workspace is not their size in the cache
+    pub mismatched: Vec<String>,
+}
+
+impl OutputsVerification {
+    fn is_intact(&self) -> bool {
+        self.missing.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+/// Compares the outputs of a task in the workspace with its cached outputs in `cached_outputs_path`,
+/// by the size of every file of the cache, which are stat-ed in parallel.
+/// Outputs that are missing or have an other size were overwritten or removed since they were restored,
+/// so they have to be restored again. Archived outputs (e.g. `outputs.tar.zst`) do not have the sizes of their files
+/// without unpacking them, so they are not verified
+#[napi]
+pub fn verify_outputs(
+    workspace_root: String,
+    cached_outputs_path: String,
+    outputs: Vec<String>,
+) -> anyhow::Result<OutputsVerification> {
+    let workspace_root = Path::new(&workspace_root);
+    let cached_outputs = Path::new(&cached_outputs_path);
+    if cached_outputs.is_file() {
+        trace!("not verifying the archived outputs {:?}", cached_outputs);
+        return Ok(OutputsVerification::default());
+    }
+    let files = get_files_for_outputs(cached_outputs_path.clone(), outputs)?;
+
+    let changed = files
+        .into_par_iter()
+        .filter_map(|file| {
+            let cached_len = fs::metadata(cached_outputs.join(&file)).ok()?.len();
+            match fs::metadata(workspace_root.join(&file)) {
+                Ok(metadata) if metadata.is_file() && metadata.len() == cached_len => None,
+                Ok(_) => Some((file, false)),
+                Err(_) => Some((file, true)),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // the files are sorted, and stay sorted when they are collected
+    let mut verification = OutputsVerification::default();
+    for (file, missing) in changed {
+        if missing {
+            verification.missing.push(file);
+        } else {
+            verification.mismatched.push(file);
+        }
+    }
+    trace!(
+        "verified the outputs in {:?}, intact: {}",
+        cached_outputs,
+        verification.is_intact()
+    );
+    Ok(verification)
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    use crate::native::utils::Normalize;
+
+    use super::*;
+
+    #[test]
+    fn should_report_missing_and_mismatched_outputs() {
+        let cache = TempDir::new().unwrap();
+        cache.child("dist/main.js").write_str("main").unwrap();
+        cache.child("dist/styles.css").write_str("body").unwrap();
+        cache
+            .child("dist/assets/logo.svg")
+            .write_str("logo")
+            .unwrap();
+        let workspace = TempDir::new().unwrap();
+        workspace.child("dist/main.js").write_str("main").unwrap();
+        workspace
+            .child("dist/styles.css")
+            .write_str("html, body")
+            .unwrap();
+
+        let verification = verify_outputs(
+            workspace.to_normalized_string(),
+            cache.to_normalized_string(),
+            vec!["dist".into()],
+        )
+        .unwrap();
+        assert_eq!(
+            verification,
+            OutputsVerification {
+                missing: vec!["dist/assets/logo.svg".into()],
+                mismatched: vec!["dist/styles.css".into()],
+            }
+        );
+
+        workspace
+            .child("dist/styles.css")
+            .write_str("body")
+            .unwrap();
+        workspace
+            .child("dist/assets/logo.svg")
+            .write_str("logo")
+            .unwrap();
+        let verification = verify_outputs(
+            workspace.to_normalized_string(),
+            cache.to_normalized_string(),
+            vec!["dist".into()],
+        )
+        .unwrap();
+        assert!(verification.is_intact());
+    }
+
+    #[test]
+    fn should_not_verify_archived_outputs() {
+        let cache = TempDir::new().unwrap();
+        cache.child("outputs.tar.zst").write_str("archive").unwrap();
+        let workspace = TempDir::new().unwrap();
+        workspace.child("dist/main.js").write_str("main").unwrap();
+
+        let verification = verify_outputs(
+            workspace.to_normalized_string(),
+            cache.child("outputs.tar.zst").to_normalized_string(),
+            vec!["dist".into()],
+        )
+        .unwrap();
+        assert!(verification.is_intact());
+    }
+}
@@ -0,0 +1,363 @@
+use std::io::{self, BufReader, Chain, Cursor, Read, Write};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU8, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::bail;
+use tracing::trace;
+
+/// Brotli streams have no magic number, so they are prefixed with this one to be told apart from zstd frames
+const BROTLI_MAGIC: [u8; 4] = *b"NXBR";
+const BROTLI_BUFFER_SIZE: usize = 64 * 1024;
+const BROTLI_WINDOW_BITS: u32 = 22;
+
+#[napi(string_enum)]
+#[derive(Debug, PartialEq, Eq)]
+pub enum CompressionAlgorithm {
+    #[allow(non_camel_case_types)]
+    zstd,
+    #[allow(non_camel_case_types)]
+    brotli,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct CompressionOptions {
+    /// Defaults to zstd
+    pub algorithm: Option<CompressionAlgorithm>,
+    /// The level of the artifacts written to the local cache and of the messages of the daemon.
+    /// Chosen from the throughput of the disk by default
+    pub local_level: Option<i32>,
+    /// The level of the artifacts uploaded to remote caches. Chosen from the throughput of the network by default
+    pub remote_level: Option<i32>,
+}
+
+/// Where compressed data is written, which decides how much time is worth spending on compressing it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionTarget {
+    /// The local cache and the daemon, which are fast: low levels keep compression from being the bottleneck
+    Local,
+    /// Remote caches, which are slow: high levels save more time transferring than they cost compressing
+    Remote,
+}
+
+/// The levels a target is compressed at with an algorithm: the lowest, the initial and the highest
+fn levels(algorithm: CompressionAlgorithm, target: CompressionTarget) -> (i32, i32, i32) {
+    match (algorithm, target) {
+        (CompressionAlgorithm::zstd, CompressionTarget::Local) => (1, 3, 3),
+        (CompressionAlgorithm::zstd, CompressionTarget::Remote) => (3, 9, 19),
+        (CompressionAlgorithm::brotli, CompressionTarget::Local) => (1, 4, 5),
+        (CompressionAlgorithm::brotli, CompressionTarget::Remote) => (4, 9, 11),
+    }
+}
+
+fn valid_levels(algorithm: CompressionAlgorithm) -> std::ops::RangeInclusive<i32> {
+    match algorithm {
+        CompressionAlgorithm::zstd => 1..=22,
+        CompressionAlgorithm::brotli => 0..=11,
+    }
+}
+
+/// The level of a target, which follows the measured throughput unless it was set explicitly
+struct AdaptiveLevel {
+    level: AtomicI32,
+    pinned: AtomicBool,
+}
+
+impl AdaptiveLevel {
+    const fn new(level: i32) -> Self {
+        Self {
+            level: AtomicI32::new(level),
+            pinned: AtomicBool::new(false),
+        }
+    }
+
+    /// Compression that is much faster than the transfer is worth a higher level, compression that is much slower
+    /// should be faster. Returns the previous and the adapted level
+    fn adapt(
+        &self,
+        min: i32,
+        max: i32,
+        compressing: Duration,
+        transferring: Duration,
+    ) -> (i32, i32) {
+        let adapt = |level: i32| {
+            let adapted = if compressing * 2 < transferring {
+                level + 1
+            } else if compressing > transferring * 2 {
+                level - 1
+            } else {
+                level
+            };
+            adapted.clamp(min, max)
+        };
+        let previous = self
+            .level
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |level| {
+                Some(adapt(level))
+            })
+            .unwrap_or_else(|level| level);
+        (previous, adapt(previous))
+    }
+}
+
+static ALGORITHM: AtomicU8 = AtomicU8::new(0);
+static LOCAL_LEVEL: AdaptiveLevel = AdaptiveLevel::new(3);
+static REMOTE_LEVEL: AdaptiveLevel = AdaptiveLevel::new(9);
+
+fn adaptive_level(target: CompressionTarget) -> &'static AdaptiveLevel {
+    match target {
+        CompressionTarget::Local => &LOCAL_LEVEL,
+        CompressionTarget::Remote => &REMOTE_LEVEL,
+    }
+}
+
+/// Sets the algorithm that data is compressed with, and the levels that override the adaptive ones
+#[napi]
+pub fn configure_compression(options: CompressionOptions) -> anyhow::Result<()> {
+    let algorithm = options.algorithm.unwrap_or(CompressionAlgorithm::zstd);
+    for (target, level) in [
+        (CompressionTarget::Local, options.local_level),
+        (CompressionTarget::Remote, options.remote_level),
+    ] {
+        if let Some(level) = level {
+            if !valid_levels(algorithm).contains(&level) {
+                bail!(
+                    "{} is not a valid level for {:?}, use a level between {} and {}",
+                    level,
+                    algorithm,
+                    valid_levels(algorithm).start(),
+                    valid_levels(algorithm).end()
+                );
+            }
+        }
+        let adaptive = adaptive_level(target);
+        adaptive.level.store(
+            level.unwrap_or(levels(algorithm, target).1),
+            Ordering::Relaxed,
+        );
+        adaptive.pinned.store(level.is_some(), Ordering::Relaxed);
+    }
+    ALGORITHM.store(algorithm as u8, Ordering::Relaxed);
+    Ok(())
+}
+
+pub fn algorithm() -> CompressionAlgorithm {
+    match ALGORITHM.load(Ordering::Relaxed) {
+        1 => CompressionAlgorithm::brotli,
+        _ => CompressionAlgorithm::zstd,
+    }
+}
+
+pub fn level(target: CompressionTarget) -> i32 {
+    adaptive_level(target).level.load(Ordering::Relaxed)
+}
+
+/// Adapts the level of a target to the time spent compressing data and transferring it
+pub fn record_throughput(target: CompressionTarget, compressing: Duration, transferring: Duration) {
+    let adaptive = adaptive_level(target);
+    if adaptive.pinned.load(Ordering::Relaxed) {
+        return;
+    }
+    let (min, _, max) = levels(algorithm(), target);
+    let (previous, adapted) = adaptive.adapt(min, max, compressing, transferring);
+    trace!(
+        "compressed in {:?} and transferred in {:?}, the {:?} level went from {} to {}",
+        compressing,
+        transferring,
+        target,
+        previous,
+        adapted
+    );
+}
+
+pub fn compress(data: &[u8], target: CompressionTarget) -> anyhow::Result<Vec<u8>> {
+    let mut encoder = encoder(Vec::with_capacity(data.len() / 2), target)?;
+    encoder.write_all(data)?;
+    Ok(encoder.finish_untimed()?)
+}
+
+/// Decompresses data compressed with any of the algorithms
+pub fn decompress(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut decompressed = vec![];
+    decoder(data)?.read_to_end(&mut decompressed)?;
+    Ok(decompressed)
+}
+
+/// Compresses what is written to `sink` with the configured algorithm, at the level of `target`
+pub fn encoder<W: Write>(mut sink: W, target: CompressionTarget) -> io::Result<Encoder<W>> {
+    let level = level(target);
+    let started = Instant::now();
+    let algorithm = algorithm();
+    if algorithm == CompressionAlgorithm::brotli {
+        sink.write_all(&BROTLI_MAGIC)?;
+    }
+    let sink = TimedWriter {
+        inner: sink,
+        elapsed: Duration::ZERO,
+    };
+    let inner = match algorithm {
+        CompressionAlgorithm::zstd => EncoderKind::Zstd(zstd::Encoder::new(sink, level)?),
+        CompressionAlgorithm::brotli => {
+            EncoderKind::Brotli(Box::new(brotli::CompressorWriter::new(
+                sink,
+                BROTLI_BUFFER_SIZE,
+                level as u32,
+                BROTLI_WINDOW_BITS,
+            )))
+        }
+    };
+    Ok(Encoder {
+        inner,
+        target,
+        started,
+    })
+}
+
+pub struct Encoder<W: Write> {
+    inner: EncoderKind<W>,
+    target: CompressionTarget,
+    started: Instant,
+}
+
+enum EncoderKind<W: Write> {
+    Zstd(zstd::Encoder<'static, TimedWriter<W>>),
+    // the state of brotli is much larger than the state of zstd
+    Brotli(Box<brotli::CompressorWriter<TimedWriter<W>>>),
+}
+
+impl<W: Write> Encoder<W> {
+    /// Completes the compressed data, and adapts the level of the target to the time spent writing it to the sink
+    pub fn finish(self) -> io::Result<W> {
+        let target = self.target;
+        let started = self.started;
+        let sink = self.finish_timed()?;
+        let elapsed = started.elapsed();
+        record_throughput(target, elapsed.saturating_sub(sink.elapsed), sink.elapsed);
+        Ok(sink.inner)
+    }
+
+    /// Completes the compressed data. For sinks that are not the destination of the data, like buffers
+    pub fn finish_untimed(self) -> io::Result<W> {
+        Ok(self.finish_timed()?.inner)
+    }
+
+    fn finish_timed(self) -> io::Result<TimedWriter<W>> {
+        let mut sink = match self.inner {
+            EncoderKind::Zstd(encoder) => encoder.finish()?,
+            EncoderKind::Brotli(encoder) => encoder.into_inner(),
+        };
+        sink.flush()?;
+        Ok(sink)
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            EncoderKind::Zstd(encoder) => encoder.write(buf),
+            EncoderKind::Brotli(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.inner {
+            EncoderKind::Zstd(encoder) => encoder.flush(),
+            EncoderKind::Brotli(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Measures the time spent writing to the sink, the rest of the time of an [Encoder] is spent compressing
+struct TimedWriter<W: Write> {
+    inner: W,
+    elapsed: Duration,
+}
+
+impl<W: Write> Write for TimedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let started = Instant::now();
+        let written = self.inner.write(buf);
+        self.elapsed += started.elapsed();
+        written
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let started = Instant::now();
+        let flushed = self.inner.flush();
+        self.elapsed += started.elapsed();
+        flushed
+    }
+}
+
+/// Decompresses data compressed with any of the algorithms, which is told from the magic number of the data
+pub fn decoder<R: Read>(mut source: R) -> io::Result<Decoder<R>> {
+    let mut magic = [0; 4];
+    source.read_exact(&mut magic)?;
+    if magic == BROTLI_MAGIC {
+        Ok(Decoder::Brotli(Box::new(brotli::Decompressor::new(
+            source,
+            BROTLI_BUFFER_SIZE,
+        ))))
+    } else {
+        Ok(Decoder::Zstd(zstd::Decoder::new(
+            Cursor::new(magic).chain(source),
+        )?))
+    }
+}
+
+pub enum Decoder<R: Read> {
+    Zstd(zstd::Decoder<'static, BufReader<Chain<Cursor<[u8; 4]>, R>>>),
+    Brotli(Box<brotli::Decompressor<R>>),
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Decoder::Zstd(decoder) => decoder.read(buf),
+            Decoder::Brotli(decoder) => decoder.read(buf),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_decompress_every_algorithm() {
+        let data = "console.log('main');\n".repeat(1000);
+        let zstd = zstd::encode_all(data.as_bytes(), 3).unwrap();
+        let mut brotli = BROTLI_MAGIC.to_vec();
+        let mut writer = brotli::CompressorWriter::new(&mut brotli, 4096, 5, BROTLI_WINDOW_BITS);
+        writer.write_all(data.as_bytes()).unwrap();
+        writer.into_inner();
+
+        for compressed in [zstd, brotli] {
+            assert!(compressed.len() < data.len());
+            assert_eq!(decompress(&compressed).unwrap(), data.as_bytes());
+        }
+        assert!(decompress(data.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn should_adapt_the_level_to_the_throughput() {
+        let (min, initial, max) = levels(CompressionAlgorithm::zstd, CompressionTarget::Remote);
+        let adaptive = AdaptiveLevel::new(initial);
+        let fast = Duration::from_millis(10);
+        let slow = Duration::from_millis(100);
+
+        assert_eq!(adaptive.adapt(min, max, fast, slow), (initial, initial + 1));
+        assert_eq!(
+            adaptive.adapt(min, max, fast, fast),
+            (initial + 1, initial + 1)
+        );
+        for _ in 0..30 {
+            adaptive.adapt(min, max, fast, slow);
+        }
+        assert_eq!(adaptive.level.load(Ordering::Relaxed), max);
+        for _ in 0..30 {
+            adaptive.adapt(min, max, slow, fast);
+        }
+        assert_eq!(adaptive.level.load(Ordering::Relaxed), min);
+    }
+}
@@ -0,0 +1,342 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use anyhow::anyhow;
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
+use parking_lot::Mutex;
+use rusqlite::{Connection, TransactionBehavior};
+use tracing::{debug, trace, warn};
+
+use crate::native::db::migrations::{migrate, MIGRATIONS};
+use crate::native::file_lock::{lock, lock_path, LockMode, DEFAULT_LOCK_TIMEOUT};
+use crate::native::utils::start_work;
+
+/// The connections kept open for reads, more are opened when they are all in use
+const MAX_IDLE_CONNECTIONS: usize = 4;
+/// The writes committed in a single transaction at most
+const MAX_BATCH_SIZE: usize = 256;
+/// The WAL is truncated back to this after checkpoints, so it does not keep the size of its largest transaction
+const JOURNAL_SIZE_LIMIT: i64 = 64 * 1024 * 1024;
+/// About 30 seconds of retries with the delays below
+const MAX_BUSY_ATTEMPTS: i32 = 400;
+const BUSY_BASE_DELAY: Duration = Duration::from_millis(2);
+const BUSY_MAX_DELAY: Duration = Duration::from_millis(100);
+
+/// The connections of this process to the Nx database.
+/// Reads use a pool of connections, and writes are sent to a dedicated writer thread,
+/// which commits the writes that are queued at the same time in a single transaction
+pub struct NxDbConnection {
+    path: PathBuf,
+    idle: Mutex<Vec<Connection>>,
+    sender: Option<Sender<Write>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+type ApplyWrite = Box<dyn FnOnce(&Connection) -> rusqlite::Result<()> + Send>;
+
+struct Write {
+    apply: ApplyWrite,
+    done: Sender<anyhow::Result<()>>,
+}
+
+impl NxDbConnection {
+    /// `connection` is a connection to `path` that was already opened, which is added to the pool
+    pub fn new(path: &Path, connection: Connection) -> anyhow::Result<Self> {
+        let writer_connection = create_connection(path)?;
+        let (sender, receiver) = unbounded();
+        let writer = thread::Builder::new()
+            .name("nx-db-writer".into())
+            .spawn(move || write_batches(writer_connection, receiver))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            idle: Mutex::new(vec![connection]),
+            sender: Some(sender),
+            writer: Some(writer),
+        })
+    }
+
+    /// Opens the database at `path`, and migrates it to the latest schema.
+    /// Processes that open the database at the same time migrate it one after the other
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let migration_lock = lock(&lock_path(path), LockMode::Exclusive, DEFAULT_LOCK_TIMEOUT)?;
+        let connection = migrate(create_connection(path)?, path, MIGRATIONS)?;
+        drop(migration_lock);
+        Self::new(path, connection)
+    }
+
+    /// A connection of the pool, for reads and for transactions that need the results of their statements
+    pub fn connection(&self) -> anyhow::Result<PooledConnection<'_>> {
+        let connection = match self.idle.lock().pop() {
+            Some(connection) => connection,
+            None => create_connection(&self.path)?,
+        };
+        Ok(PooledConnection {
+            connection: Some(connection),
+            pool: self,
+        })
+    }
+
+    /// Applies `write` on the writer thread, and waits until it is committed.
+    /// A write that fails is rolled back without rolling back the other writes of its batch.
+    /// Writes are refused once the process is shutting down, and the process waits for the writes that were queued
+    pub fn write(
+        &self,
+        write: impl FnOnce(&Connection) -> rusqlite::Result<()> + Send + 'static,
+    ) -> anyhow::Result<()> {
+        let _work = start_work("writing to the Nx database")?;
+        let stopped = || anyhow!("The writer of the Nx database has stopped");
+        let (done, result) = bounded(1);
+        self.sender
+            .as_ref()
+            .ok_or_else(stopped)?
+            .send(Write {
+                apply: Box::new(write),
+                done,
+            })
+            .map_err(|_| stopped())?;
+        result.recv().map_err(|_| stopped())?
+    }
+
+    /// Moves the pages of the WAL into the database and truncates the WAL, so the database file is complete on its own.
+    /// Returns false when the WAL could not be checkpointed completely, because other connections were still using it
+    pub fn checkpoint(&self) -> anyhow::Result<bool> {
+        let connection = self.connection()?;
+        let (busy, wal_pages, checkpointed_pages) =
+            connection.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            })?;
+        debug!(
+            "checkpointed {} of the {} pages of the WAL of the Nx database",
+            checkpointed_pages, wal_pages
+        );
+        Ok(busy == 0 && wal_pages == checkpointed_pages)
+    }
+}
+
+impl Drop for NxDbConnection {
+    fn drop(&mut self) {
+        // the writer commits the queued writes and stops once the channel is closed
+        drop(self.sender.take());
+        if let Some(writer) = self.writer.take() {
+            writer.join().ok();
+        }
+    }
+}
+
+/// A connection that goes back to its pool when dropped
+pub struct PooledConnection<'a> {
+    connection: Option<Connection>,
+    pool: &'a NxDbConnection,
+}
+
+impl Deref for PooledConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.connection.as_ref().expect("the connection is in use")
+    }
+}
+
+impl DerefMut for PooledConnection<'_> {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.connection.as_mut().expect("the connection is in use")
+    }
+}
+
+impl Drop for PooledConnection<'_> {
+    fn drop(&mut self) {
+        let Some(connection) = self.connection.take() else {
+            return;
+        };
+        let mut idle = self.pool.idle.lock();
+        if idle.len() < MAX_IDLE_CONNECTIONS {
+            idle.push(connection);
+        }
+    }
+}
+
+fn write_batches(mut connection: Connection, receiver: Receiver<Write>) {
+    while let Ok(first) = receiver.recv() {
+        let mut batch = vec![first];
+        batch.extend(receiver.try_iter().take(MAX_BATCH_SIZE - 1));
+        trace!("committing {} writes to the Nx database", batch.len());
+
+        let mut results = Vec::with_capacity(batch.len());
+        let committed = (|| -> rusqlite::Result<()> {
+            let mut transaction =
+                connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            for write in batch {
+                // every write has a savepoint, so one that fails does not roll back the others
+                let savepoint = transaction.savepoint()?;
+                match (write.apply)(&savepoint) {
+                    Ok(()) => {
+                        savepoint.commit()?;
+                        results.push((write.done, None));
+                    }
+                    Err(e) => results.push((write.done, Some(e))),
+                }
+            }
+            transaction.commit()
+        })();
+
+        if let Err(e) = &committed {
+            warn!("unable to commit writes to the Nx database: {}", e);
+        }
+        for (done, error) in results {
+            let result = match (&committed, error) {
+                (_, Some(e)) => Err(e.into()),
+                (Err(e), None) => Err(anyhow!("Unable to commit to the Nx database: {}", e)),
+                (Ok(()), None) => Ok(()),
+            };
+            done.send(result).ok();
+        }
+    }
+    debug!("the writer of the Nx database has stopped");
+}
+
+pub(crate) fn create_connection(db_path: &Path) -> anyhow::Result<Connection> {
+    debug!("Creating connection to {:?}", db_path);
+    let c = Connection::open(db_path).map_err(anyhow::Error::from)?;
+
+    // Other processes (the daemon, or other Nx commands) write to the same database
+    c.busy_handler(Some(retry_when_busy))?;
+
+    // This allows writes at the same time as reads
+    c.pragma_update(None, "journal_mode", "WAL")?;
+
+    // This makes things less synchronous than default
+    c.pragma_update(None, "synchronous", "NORMAL")?;
+
+    c.pragma_update(None, "journal_size_limit", JOURNAL_SIZE_LIMIT)?;
+
+    rusqlite::vtab::array::load_module(&c)?;
+
+    Ok(c)
+}
+
+/// Waits while another connection holds the lock of the database instead of failing with `SQLITE_BUSY`.
+/// The delays grow exponentially and are randomized, so the connections that wait do not all retry at once
+fn retry_when_busy(attempts: i32) -> bool {
+    if attempts >= MAX_BUSY_ATTEMPTS {
+        warn!(
+            "the Nx database is still locked after {} attempts",
+            attempts
+        );
+        return false;
+    }
+    let ceiling = BUSY_BASE_DELAY
+        .saturating_mul(1 << attempts.clamp(0, 6))
+        .min(BUSY_MAX_DELAY);
+    thread::sleep(ceiling.mul_f64(0.5 + jitter() / 2.0));
+    true
+}
+
+/// A random number between 0 and 1
+fn jitter() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::TempDir;
+    use rusqlite::params;
+
+    use super::*;
+
+    fn open() -> (TempDir, NxDbConnection) {
+        let temp = TempDir::new().unwrap();
+        let db = NxDbConnection::open(&temp.join("nx.db")).unwrap();
+        db.connection()
+            .unwrap()
+            .execute("CREATE TABLE runs (id INTEGER PRIMARY KEY NOT NULL)", [])
+            .unwrap();
+        (temp, db)
+    }
+
+    fn count(db: &NxDbConnection) -> i64 {
+        db.connection()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM runs", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn should_write_from_concurrent_threads() {
+        let (_temp, db) = open();
+        thread::scope(|scope| {
+            for thread in 0..8 {
+                let db = &db;
+                scope.spawn(move || {
+                    for run in 0..50 {
+                        db.write(move |c| {
+                            c.execute(
+                                "INSERT INTO runs (id) VALUES (?1)",
+                                params![thread * 50 + run],
+                            )
+                            .map(|_| ())
+                        })
+                        .unwrap();
+                    }
+                });
+            }
+        });
+        assert_eq!(count(&db), 400);
+    }
+
+    #[test]
+    fn should_roll_back_failed_writes_only() {
+        let (_temp, db) = open();
+        db.write(|c| {
+            c.execute("INSERT INTO runs (id) VALUES (1)", [])
+                .map(|_| ())
+        })
+        .unwrap();
+        assert!(db
+            .write(|c| {
+                c.execute("INSERT INTO runs (id) VALUES (2)", [])?;
+                c.execute("INSERT INTO runs (id) VALUES (1)", [])
+                    .map(|_| ())
+            })
+            .is_err());
+        db.write(|c| {
+            c.execute("INSERT INTO runs (id) VALUES (3)", [])
+                .map(|_| ())
+        })
+        .unwrap();
+        assert_eq!(count(&db), 2);
+    }
+
+    #[test]
+    fn should_checkpoint_the_wal_into_the_database() {
+        let (temp, db) = open();
+        db.write(|c| {
+            c.execute("INSERT INTO runs (id) VALUES (1)", [])
+                .map(|_| ())
+        })
+        .unwrap();
+        assert!(std::fs::metadata(temp.join("nx.db-wal")).unwrap().len() > 0);
+
+        assert!(db.checkpoint().unwrap());
+        assert_eq!(std::fs::metadata(temp.join("nx.db-wal")).unwrap().len(), 0);
+        assert_eq!(count(&db), 1);
+    }
+
+    #[test]
+    fn should_back_off_with_jitter() {
+        for _ in 0..100 {
+            let jitter = jitter();
+            assert!((0.0..1.0).contains(&jitter));
+        }
+        assert!(!retry_when_busy(MAX_BUSY_ATTEMPTS));
+    }
+}
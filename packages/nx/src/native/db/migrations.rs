@@ -0,0 +1,377 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use rusqlite::{Connection, TransactionBehavior};
+use tracing::{debug, warn};
+
+use crate::native::db::connection::create_connection;
+
+#[derive(Clone)]
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+/// The migrations of the schema of the Nx database, applied in order of their versions.
+/// They are forward-only: a released migration is never changed, the schema is changed by adding a migration
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description:
+            "create the tables of the cache, the task details, the task history and the hashes",
+        sql: "
+        CREATE TABLE IF NOT EXISTS metadata (
+            key TEXT NOT NULL PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS task_details (
+            hash    TEXT PRIMARY KEY NOT NULL,
+            project  TEXT NOT NULL,
+            target  TEXT NOT NULL,
+            configuration  TEXT
+        );
+        CREATE TABLE IF NOT EXISTS task_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+            hash TEXT NOT NULL,
+            status TEXT NOT NULL,
+            code INTEGER NOT NULL,
+            start TIMESTAMP NOT NULL,
+            end TIMESTAMP NOT NULL,
+            FOREIGN KEY (hash) REFERENCES task_details (hash)
+        );
+        CREATE INDEX IF NOT EXISTS hash_idx ON task_history (hash);
+        CREATE TABLE IF NOT EXISTS cache_outputs (
+            hash    TEXT PRIMARY KEY NOT NULL,
+            code   INTEGER NOT NULL,
+            digest TEXT,
+            size INTEGER NOT NULL DEFAULT 0,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            accessed_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (hash) REFERENCES task_details (hash)
+        );
+        CREATE TABLE IF NOT EXISTS task_hash_cache (
+            cache_key TEXT PRIMARY KEY NOT NULL,
+            hash TEXT NOT NULL,
+            fingerprint TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS workspace_walk_checkpoint (
+            path TEXT PRIMARY KEY NOT NULL,
+            hash TEXT NOT NULL,
+            mod_time INTEGER NOT NULL
+        );
+    ",
+    },
+    Migration {
+        version: 2,
+        description: "record the version of the hashing algorithm of the cached outputs",
+        sql: "ALTER TABLE cache_outputs ADD COLUMN hash_version INTEGER;",
+    },
+    Migration {
+        version: 3,
+        description: "create the tables of the snapshots of the outputs of tasks, keyed on the outputs that tasks can share",
+        sql: "
+        CREATE TABLE IF NOT EXISTS task_outputs_snapshots (
+            outputs TEXT PRIMARY KEY NOT NULL,
+            hash TEXT NOT NULL,
+            digest TEXT NOT NULL,
+            recorded_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE TABLE IF NOT EXISTS task_outputs_files (
+            outputs TEXT NOT NULL,
+            path TEXT NOT NULL,
+            digest TEXT NOT NULL,
+            PRIMARY KEY (outputs, path),
+            FOREIGN KEY (outputs) REFERENCES task_outputs_snapshots (outputs)
+        );
+    ",
+    },
+    Migration {
+        version: 4,
+        description: "index the terminal outputs that were stored in the cache",
+        sql: "
+        CREATE TABLE IF NOT EXISTS terminal_outputs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+            task_id TEXT NOT NULL,
+            hash TEXT NOT NULL,
+            recorded_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS terminal_outputs_recorded_at_idx ON terminal_outputs (recorded_at);
+    ",
+    },
+];
+
+/// The columns that were added to the tables of the first migration after the versions of Nx without migrations, whose
+/// databases have the schema version 0 and some of these tables already. `CREATE TABLE IF NOT EXISTS` keeps them as
+/// they are, so their missing columns are added before migrating
+const LEGACY_COLUMNS: &[(&str, &str, &str)] = &[
+    ("cache_outputs", "digest", "TEXT"),
+    ("cache_outputs", "size", "INTEGER NOT NULL DEFAULT 0"),
+];
+
+/// Applies the migrations that the database does not have yet, after backing it up.
+/// A database migrated by a newer version of Nx has a schema that is not known, so it is set aside
+/// (next to its backups) and a new database is created instead
+pub fn migrate(
+    mut connection: Connection,
+    db_path: &Path,
+    migrations: &[Migration],
+) -> anyhow::Result<Connection> {
+    if migrations
+        .windows(2)
+        .any(|pair| pair[0].version >= pair[1].version)
+    {
+        bail!("The migrations of the Nx database are not sorted by version");
+    }
+    let latest = migrations.last().map_or(0, |migration| migration.version);
+    let current = schema_version(&connection)?;
+
+    if current > latest {
+        warn!(
+            "the Nx database has the schema {} of a newer version of Nx, a new database is created",
+            current
+        );
+        drop(connection);
+        move_database(db_path, &backup_path(db_path, current))?;
+        return migrate(create_connection(db_path)?, db_path, migrations);
+    }
+    if current == latest {
+        return Ok(connection);
+    }
+
+    if current > 0 {
+        back_up(&connection, &backup_path(db_path, current))?;
+    }
+
+    let transaction = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    // another process may have migrated the database while this one was waiting for the lock
+    let current = schema_version(&transaction)?;
+    if current == 0 {
+        add_legacy_columns(&transaction)?;
+    }
+    for migration in migrations
+        .iter()
+        .filter(|migration| migration.version > current)
+    {
+        debug!(
+            "Migrating the Nx database to version {}: {}",
+            migration.version, migration.description
+        );
+        transaction.execute_batch(migration.sql)?;
+    }
+    transaction.pragma_update(None, "user_version", latest.max(current))?;
+    transaction.commit()?;
+    Ok(connection)
+}
+
+fn add_legacy_columns(connection: &Connection) -> rusqlite::Result<()> {
+    for (table, column, definition) in LEGACY_COLUMNS {
+        let columns = connection.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info(?1)",
+            [table],
+            |row| row.get::<_, u32>(0),
+        )?;
+        let has_column = connection.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+            [table, column],
+            |row| row.get::<_, u32>(0),
+        )? > 0;
+        // tables without columns do not exist
+        if columns > 0 && !has_column {
+            debug!("Adding the column {} to the legacy table {}", column, table);
+            connection.execute_batch(&format!(
+                "ALTER TABLE {} ADD COLUMN {} {};",
+                table, column, definition
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+fn schema_version(connection: &Connection) -> rusqlite::Result<u32> {
+    connection.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
+/// The backup of the database with the schema `version`, only the latest backup of each version is kept
+fn backup_path(db_path: &Path, version: u32) -> PathBuf {
+    let mut name = db_path.file_name().unwrap_or_default().to_owned();
+    name.push(format!(".v{}.bak", version));
+    db_path.with_file_name(name)
+}
+
+fn back_up(connection: &Connection, backup: &Path) -> anyhow::Result<()> {
+    debug!("Backing up the Nx database to {:?}", backup);
+    remove_if_exists(backup)?;
+    connection.execute("VACUUM INTO ?1", [backup.to_string_lossy()])?;
+    Ok(())
+}
+
+fn move_database(db_path: &Path, dest: &Path) -> anyhow::Result<()> {
+    remove_if_exists(dest)?;
+    fs::rename(db_path, dest)?;
+    // the journal of the database belongs to the schema that was set aside
+    for suffix in ["-wal", "-shm"] {
+        let mut journal = db_path.as_os_str().to_owned();
+        journal.push(suffix);
+        remove_if_exists(Path::new(&journal))?;
+    }
+    Ok(())
+}
+
+fn remove_if_exists(path: &Path) -> anyhow::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    const TEST_MIGRATIONS: &[Migration] = &[
+        Migration {
+            version: 1,
+            description: "create runs",
+            sql: "CREATE TABLE runs (id INTEGER PRIMARY KEY NOT NULL);",
+        },
+        Migration {
+            version: 2,
+            description: "add the code of runs",
+            sql: "ALTER TABLE runs ADD COLUMN code INTEGER NOT NULL DEFAULT 0;",
+        },
+    ];
+
+    #[test]
+    fn should_migrate_forward_and_back_up() {
+        let temp = TempDir::new().unwrap();
+        let db_path = temp.join("nx.db");
+
+        let connection = migrate(
+            create_connection(&db_path).unwrap(),
+            &db_path,
+            &TEST_MIGRATIONS[..1],
+        )
+        .unwrap();
+        assert_eq!(schema_version(&connection).unwrap(), 1);
+        connection
+            .execute("INSERT INTO runs (id) VALUES (1)", [])
+            .unwrap();
+        assert!(!backup_path(&db_path, 1).exists());
+
+        let connection = migrate(connection, &db_path, TEST_MIGRATIONS).unwrap();
+        assert_eq!(schema_version(&connection).unwrap(), 2);
+        let code: i64 = connection
+            .query_row("SELECT code FROM runs WHERE id = 1", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(code, 0);
+
+        let backup = Connection::open(backup_path(&db_path, 1)).unwrap();
+        assert_eq!(schema_version(&backup).unwrap(), 1);
+        let runs: i64 = backup
+            .query_row("SELECT COUNT(*) FROM runs", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(runs, 1);
+
+        // migrating again does nothing
+        let connection = migrate(connection, &db_path, TEST_MIGRATIONS).unwrap();
+        assert_eq!(schema_version(&connection).unwrap(), 2);
+    }
+
+    #[test]
+    fn should_set_aside_databases_of_newer_versions() {
+        let temp = TempDir::new().unwrap();
+        let db_path = temp.join("nx.db");
+
+        let connection = migrate(
+            create_connection(&db_path).unwrap(),
+            &db_path,
+            TEST_MIGRATIONS,
+        )
+        .unwrap();
+        let connection = migrate(connection, &db_path, &TEST_MIGRATIONS[..1]).unwrap();
+        assert_eq!(schema_version(&connection).unwrap(), 1);
+        assert!(backup_path(&db_path, 2).exists());
+
+        assert!(migrate(
+            connection,
+            &db_path,
+            &[TEST_MIGRATIONS[1].clone(), TEST_MIGRATIONS[0].clone()]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn should_migrate_the_databases_of_versions_without_migrations() {
+        let temp = TempDir::new().unwrap();
+        let db_path = temp.join("nx.db");
+        let legacy = create_connection(&db_path).unwrap();
+        legacy
+            .execute_batch(
+                "
+            CREATE TABLE metadata (
+                key TEXT NOT NULL PRIMARY KEY,
+                value TEXT NOT NULL
+            );
+            CREATE TABLE task_details (
+                hash    TEXT PRIMARY KEY NOT NULL,
+                project  TEXT NOT NULL,
+                target  TEXT NOT NULL,
+                configuration  TEXT
+            );
+            CREATE TABLE cache_outputs (
+                hash    TEXT PRIMARY KEY NOT NULL,
+                code   INTEGER NOT NULL,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                accessed_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (hash) REFERENCES task_details (hash)
+            );
+            INSERT INTO task_details (hash, project, target)
+                VALUES ('legacy', 'web', 'build'), ('hash', 'web', 'test');
+            INSERT INTO cache_outputs (hash, code) VALUES ('legacy', 0);
+            ",
+            )
+            .unwrap();
+
+        let connection = migrate(legacy, &db_path, MIGRATIONS).unwrap();
+        connection
+            .execute(
+                "INSERT INTO cache_outputs
+                    (hash, code, digest, size, hash_version)
+                    VALUES ('hash', 1, 'digest', 10, 1)",
+                [],
+            )
+            .unwrap();
+        let (code, digest, size): (i64, Option<String>, i64) = connection
+            .query_row(
+                "UPDATE cache_outputs
+                    SET accessed_at = CURRENT_TIMESTAMP
+                    WHERE hash = 'hash'
+                    RETURNING code, digest, size",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!((code, digest.as_deref(), size), (1, Some("digest"), 10));
+        let legacy_size: i64 = connection
+            .query_row(
+                "SELECT size FROM cache_outputs WHERE hash = 'legacy'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(legacy_size, 0);
+    }
+
+    #[test]
+    fn should_keep_the_migrations_sorted() {
+        assert!(MIGRATIONS
+            .windows(2)
+            .all(|pair| pair[0].version < pair[1].version));
+        assert_eq!(MIGRATIONS[0].version, 1);
+    }
+}
@@ -1,73 +1,109 @@
-use std::fs::{create_dir_all, remove_file};
-use std::path::PathBuf;
+pub mod connection;
+pub mod migrations;
+
+use std::fs::create_dir_all;
+use std::path::{Path, PathBuf};
 
 use napi::bindgen_prelude::External;
-use rusqlite::Connection;
 use tracing::debug;
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::native::db::connection::NxDbConnection;
 use crate::native::machine_id::get_machine_id;
+use crate::native::utils::Normalize;
 
+pub(crate) use crate::native::db::connection::create_connection;
+
+/// Connects to the database of the workspace, and migrates it to the schema of this version of Nx
 #[napi]
 pub fn connect_to_nx_db(
     cache_dir: String,
     nx_version: String,
-) -> anyhow::Result<External<Connection>> {
-    let machine_id = get_machine_id();
+    workspace_root: Option<String>,
+) -> anyhow::Result<External<NxDbConnection>> {
     let cache_dir_buf = PathBuf::from(cache_dir);
-    let db_path = cache_dir_buf.join(format!("{}.db", machine_id));
-    create_dir_all(cache_dir_buf)?;
-
-    let c = create_connection(&db_path)?;
-
-    debug!("Checking if current existing database is compatible with Nx {}", nx_version);
-    let db_version = c.query_row(
-        "SELECT value FROM metadata WHERE key='NX_VERSION'",
-        [],
-        |row| {
-            let r: String = row.get(0)?;
-            Ok(r)
-        },
-    );
-
-    let c = match db_version {
-        Ok(version) if version == nx_version => c,
-        _ => {
-            debug!("Disconnecting from existing incompatible database");
-            c.close().map_err(|(_, error)| anyhow::Error::from(error))?;
-            debug!("Removing existing incompatible database");
-            remove_file(&db_path)?;
-
-            debug!("Creating a new connection to a new database");
-            create_connection(&db_path)?
-        }
-    };
+    let db_path = nx_db_path(&cache_dir_buf, workspace_root.as_deref().map(Path::new));
+    create_dir_all(&cache_dir_buf)?;
+    if workspace_root.is_some() {
+        move_legacy_db(&legacy_db_path(&cache_dir_buf), &db_path);
+    }
 
-    debug!("Creating table for metadata");
-    c.execute(
-        "CREATE TABLE IF NOT EXISTS metadata (
-            key TEXT NOT NULL PRIMARY KEY,
-            value TEXT NOT NULL
-        )",
-        [],
-    )?;
+    let db = NxDbConnection::open(&db_path)?;
 
     debug!("Recording Nx Version: {}", nx_version);
-    c.execute(
-        "INSERT OR REPLACE INTO metadata (key, value) VALUES ('NX_VERSION', ?)",
-        [nx_version],
-    )?;
+    db.write(move |c| {
+        c.execute(
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES ('NX_VERSION', ?)",
+            [nx_version],
+        )?;
+        Ok(())
+    })?;
 
-    Ok(External::new(c))
+    Ok(External::new(db))
 }
 
-fn create_connection(db_path: &PathBuf) -> anyhow::Result<Connection> {
-    debug!("Creating connection to {:?}", db_path);
-    let c = Connection::open(db_path).map_err(anyhow::Error::from)?;
+/// The database of the current machine and of the workspace in the cache directory.
+/// Workspaces that share a cache directory have databases of their own, as they may use different versions of Nx
+pub(crate) fn nx_db_path(cache_dir: &Path, workspace_root: Option<&Path>) -> PathBuf {
+    match workspace_root {
+        Some(workspace_root) => cache_dir.join(format!(
+            "{}-{:016x}.db",
+            get_machine_id(),
+            xxh3_64(workspace_root.to_normalized_string().as_bytes())
+        )),
+        None => legacy_db_path(cache_dir),
+    }
+}
+
+/// The database of the current machine, which was shared by the workspaces of the cache directory
+fn legacy_db_path(cache_dir: &Path) -> PathBuf {
+    cache_dir.join(format!("{}.db", get_machine_id()))
+}
+
+/// Moves the database that was shared by the workspaces, with its WAL, to the database of the workspace when the
+/// workspace has none yet, so the task history is kept. A database that cannot be moved is left as it is
+fn move_legacy_db(legacy_db_path: &Path, db_path: &Path) {
+    if db_path.exists() || !legacy_db_path.exists() {
+        return;
+    }
+    debug!("Moving {:?} to {:?}", legacy_db_path, db_path);
+    for suffix in ["", "-wal", "-shm"] {
+        let mut legacy = legacy_db_path.as_os_str().to_owned();
+        legacy.push(suffix);
+        let mut moved = db_path.as_os_str().to_owned();
+        moved.push(suffix);
+        let legacy = PathBuf::from(legacy);
+        if !legacy.exists() {
+            continue;
+        }
+        if let Err(e) = std::fs::rename(&legacy, PathBuf::from(moved)) {
+            debug!("Could not move {:?}: {}", legacy, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    use super::*;
 
-    // This allows writes at the same time as reads
-    c.pragma_update(None, "journal_mode", "WAL")?;
+    #[test]
+    fn should_move_the_legacy_db_to_the_db_of_the_workspace() {
+        let temp = TempDir::new().unwrap();
+        temp.child("machine.db").write_str("db").unwrap();
+        temp.child("machine.db-wal").write_str("wal").unwrap();
 
-    // This makes things less synchronous than default
-    c.pragma_update(None, "synchronous", "NORMAL")?;
+        move_legacy_db(&temp.join("machine.db"), &temp.join("machine-1.db"));
+        temp.child("machine-1.db").assert("db");
+        temp.child("machine-1.db-wal").assert("wal");
+        assert!(!temp.join("machine.db").exists());
+        assert!(!temp.join("machine-1.db-shm").exists());
 
-    Ok(c)
+        temp.child("machine.db").write_str("other").unwrap();
+        move_legacy_db(&temp.join("machine.db"), &temp.join("machine-1.db"));
+        temp.child("machine-1.db").assert("db");
+        temp.child("machine.db").assert("other");
+    }
 }
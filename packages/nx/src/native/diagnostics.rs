@@ -0,0 +1,179 @@
+use napi::bindgen_prelude::External;
+use rusqlite::params;
+use tracing::debug;
+
+use crate::native::db::connection::NxDbConnection;
+use crate::native::logger::{recent_errors, LoggedError};
+use crate::native::watch::{watcher_health, WatcherHealth};
+
+/// The state of the Nx database, as seen by this process
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct DbHealth {
+    pub connected: bool,
+    /// The size of the database, in bytes
+    pub size: Option<i64>,
+    /// Why the database could not be queried
+    pub error: Option<String>,
+}
+
+/// The task outputs recorded in the cache
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct CacheHealth {
+    pub entries: i64,
+    /// The size of the cached outputs, in bytes
+    pub size: i64,
+}
+
+/// A report of how a long running process (like the daemon) is doing
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct DaemonDiagnostics {
+    pub watcher: WatcherHealth,
+    pub db: DbHealth,
+    /// Only reported when the database could be queried
+    pub cache: Option<CacheHealth>,
+    /// The resident memory of the process, in bytes.
+    /// This is the peak resident memory on platforms other than Linux, and it is not measured on Windows
+    pub memory: Option<i64>,
+    /// The last errors and warnings that were logged, the oldest first
+    pub recent_errors: Vec<LoggedError>,
+    /// What is not working as it should. The process should be restarted when there is any
+    pub degradations: Vec<String>,
+}
+
+/// Checks the health of the file watcher, the database and the cache, and reports what has degraded.
+/// The memory of the process is reported as a degradation when it is over `max_memory` bytes
+#[napi]
+pub fn daemon_diagnostics(
+    db_connection: Option<External<NxDbConnection>>,
+    max_memory: Option<i64>,
+) -> DaemonDiagnostics {
+    let watcher = watcher_health();
+    let (db, cache) = match db_connection.as_deref().map(db_health) {
+        Some(Ok((db, cache))) => (db, Some(cache)),
+        Some(Err(e)) => {
+            debug!("could not query the Nx database: {:?}", e);
+            let db = DbHealth {
+                error: Some(e.to_string()),
+                ..Default::default()
+            };
+            (db, None)
+        }
+        None => (DbHealth::default(), None),
+    };
+    let memory = resident_memory();
+
+    let mut degradations = vec![];
+    if watcher.dropped_events > 0 {
+        degradations.push(format!(
+            "The file watcher dropped {} events",
+            watcher.dropped_events
+        ));
+    }
+    if watcher.errors > 0 && watcher.polled_roots == 0 {
+        degradations.push(format!(
+            "The file watcher reported {} errors",
+            watcher.errors
+        ));
+    }
+    if let Some(error) = &db.error {
+        degradations.push(format!("The Nx database can not be queried: {}", error));
+    }
+    if let (Some(memory), Some(max_memory)) = (memory, max_memory) {
+        if memory > max_memory {
+            degradations.push(format!(
+                "The process uses {} MB of memory, more than {} MB",
+                memory / 1024 / 1024,
+                max_memory / 1024 / 1024
+            ));
+        }
+    }
+
+    DaemonDiagnostics {
+        watcher,
+        db,
+        cache,
+        memory,
+        recent_errors: recent_errors(),
+        degradations,
+    }
+}
+
+fn db_health(db: &NxDbConnection) -> anyhow::Result<(DbHealth, CacheHealth)> {
+    let connection = db.connection()?;
+    let size = connection.query_row(
+        "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        params![],
+        |row| row.get(0),
+    )?;
+    let cache = connection.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(size), 0) FROM cache_outputs",
+        params![],
+        |row| {
+            Ok(CacheHealth {
+                entries: row.get(0)?,
+                size: row.get(1)?,
+            })
+        },
+    )?;
+    let db = DbHealth {
+        connected: true,
+        size: Some(size),
+        error: None,
+    };
+    Ok((db, cache))
+}
+
+#[cfg(target_os = "linux")]
+fn resident_memory() -> Option<i64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages = statm.split_whitespace().nth(1)?.parse::<i64>().ok()?;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(1) as i64;
+    Some(pages * page_size)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn resident_memory() -> Option<i64> {
+    let mut usage = std::mem::MaybeUninit::<libc::rusage>::uninit();
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, usage.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    let max_rss = unsafe { usage.assume_init() }.ru_maxrss as i64;
+    // macOS reports bytes, the BSDs report kilobytes
+    if cfg!(target_os = "macos") {
+        Some(max_rss)
+    } else {
+        Some(max_rss * 1024)
+    }
+}
+
+#[cfg(windows)]
+fn resident_memory() -> Option<i64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn should_report_the_cache_and_the_database() {
+        let temp = TempDir::new().unwrap();
+        let db = NxDbConnection::open(&temp.join("nx.db")).unwrap();
+
+        let diagnostics = daemon_diagnostics(Some(External::new(db)), None);
+        assert!(diagnostics.db.connected);
+        assert!(diagnostics.db.size.unwrap() > 0);
+        assert_eq!(diagnostics.cache.unwrap().entries, 0);
+        assert!(diagnostics.degradations.is_empty());
+
+        let diagnostics = daemon_diagnostics(None, Some(1));
+        assert!(!diagnostics.db.connected);
+        #[cfg(unix)]
+        assert_eq!(diagnostics.degradations.len(), 1);
+    }
+}
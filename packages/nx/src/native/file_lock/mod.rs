@@ -0,0 +1,351 @@
+#[cfg_attr(windows, path = "os/windows.rs")]
+#[cfg_attr(not(windows), path = "os/unix.rs")]
+mod os;
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::Lazy;
+use parking_lot::lock_api::{ArcRwLockReadGuard, ArcRwLockWriteGuard};
+use parking_lot::{Mutex, RawRwLock, RwLock};
+use tracing::{debug, trace, warn};
+
+/// How long locks are waited for by default
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+const MIN_RETRY_DELAY: Duration = Duration::from_millis(5);
+const MAX_RETRY_DELAY: Duration = Duration::from_millis(100);
+/// The file in lock directories that records their holder
+const HOLDER_FILE: &str = "holder";
+/// Lock directories are created before their holder is recorded, so they are only stale without a holder after a while
+const STALE_LOCK_DIRECTORY_AGE: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    /// Held by any number of holders at once, while there is no exclusive holder
+    Shared,
+    Exclusive,
+}
+
+/// The process that holds a lock exclusively
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LockHolder {
+    pub pid: u32,
+    /// When the lock was acquired, in milliseconds since the unix epoch
+    pub acquired_at: f64,
+}
+
+/// The lock could not be acquired before the timeout
+#[derive(Debug, thiserror::Error)]
+#[error("{message}")]
+pub struct LockTimeout {
+    pub holder: Option<LockHolder>,
+    message: String,
+}
+
+/// The locks of the other threads of this process, since the locks of the OS are held by the whole process
+/// (or by every handle of the file), and do not keep the threads of the process from locking the same file
+static PROCESS_LOCKS: Lazy<Mutex<HashMap<PathBuf, Arc<RwLock<()>>>>> = Lazy::new(Default::default);
+
+/// The guards are only held until the lock is released
+#[allow(dead_code)]
+enum ProcessGuard {
+    Shared(ArcRwLockReadGuard<RawRwLock, ()>),
+    Exclusive(ArcRwLockWriteGuard<RawRwLock, ()>),
+}
+
+enum OsLock {
+    File(File),
+    /// A directory that exists while the lock is held, on file systems that can not lock files
+    Directory(PathBuf),
+}
+
+/// An advisory lock on a file, shared by the Nx processes of a workspace (e.g. to remove cache entries
+/// while no other process is restoring them). The lock is released when this is dropped, or when the process exits
+pub struct LockGuard {
+    path: PathBuf,
+    mode: LockMode,
+    os_lock: OsLock,
+    _process_guard: ProcessGuard,
+}
+
+impl Drop for LockGuard {
+    fn drop(&mut self) {
+        match &self.os_lock {
+            OsLock::File(file) => {
+                if self.mode == LockMode::Exclusive {
+                    file.set_len(0).ok();
+                }
+                if let Err(e) = os::unlock(file) {
+                    debug!("could not unlock {:?}: {}", self.path, e);
+                }
+            }
+            OsLock::Directory(directory) => {
+                fs::remove_dir_all(directory).ok();
+            }
+        }
+        trace!("released the lock {:?}", self.path);
+    }
+}
+
+/// The lock file of `path`, next to it
+pub fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(".lock");
+    path.with_file_name(name)
+}
+
+/// Acquires the lock at `path`, waiting for `timeout` at most while other processes (or threads) hold it.
+/// Exclusive holders record their process in the lock file, so the processes waiting for them can tell which one it is.
+/// Some network file systems can not lock files, a lock directory is created instead, which is held exclusively.
+/// The lock directories of processes that are not running anymore are stale, and they are removed
+pub fn lock(path: &Path, mode: LockMode, timeout: Duration) -> anyhow::Result<LockGuard> {
+    let deadline = Instant::now() + timeout;
+    let process_lock = Arc::clone(PROCESS_LOCKS.lock().entry(path.to_path_buf()).or_default());
+    let process_guard = match mode {
+        LockMode::Shared => process_lock
+            .try_read_arc_until(deadline)
+            .map(ProcessGuard::Shared),
+        LockMode::Exclusive => process_lock
+            .try_write_arc_until(deadline)
+            .map(ProcessGuard::Exclusive),
+    }
+    .ok_or_else(|| timed_out(path, timeout))?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    let mut delay = MIN_RETRY_DELAY;
+    loop {
+        match os::try_lock(&file, mode) {
+            Ok(true) => break,
+            Ok(false) => {}
+            Err(e) if os::is_unsupported(&e) => {
+                debug!(
+                    "{:?} can not be locked ({}), using a lock directory",
+                    path, e
+                );
+                let directory = lock_directory(path, deadline, timeout)?;
+                return Ok(LockGuard {
+                    path: path.to_path_buf(),
+                    mode,
+                    os_lock: OsLock::Directory(directory),
+                    _process_guard: process_guard,
+                });
+            }
+            Err(e) => return Err(anyhow::anyhow!("Unable to lock {:?}: {}", path, e)),
+        }
+        if Instant::now() >= deadline {
+            return Err(timed_out(path, timeout).into());
+        }
+        thread::sleep(delay);
+        delay = (delay * 2).min(MAX_RETRY_DELAY);
+    }
+
+    if mode == LockMode::Exclusive {
+        file.set_len(0)?;
+        file.write_all(holder_record().as_bytes())?;
+    }
+    trace!("acquired the lock {:?} ({:?})", path, mode);
+    Ok(LockGuard {
+        path: path.to_path_buf(),
+        mode,
+        os_lock: OsLock::File(file),
+        _process_guard: process_guard,
+    })
+}
+
+/// The process that holds the lock at `path` exclusively, shared holders are not recorded
+pub fn lock_holder(path: &Path) -> Option<LockHolder> {
+    [
+        path.to_path_buf(),
+        lock_directory_path(path).join(HOLDER_FILE),
+    ]
+    .iter()
+    .find_map(|record| parse_holder(&fs::read_to_string(record).ok()?))
+    .filter(|holder| os::is_running(holder.pid))
+}
+
+fn timed_out(path: &Path, timeout: Duration) -> LockTimeout {
+    let holder = lock_holder(path);
+    let held_by = match &holder {
+        Some(holder) => format!("process {}", holder.pid),
+        None => "other Nx processes".to_string(),
+    };
+    LockTimeout {
+        message: format!(
+            "Timed out after {:?} waiting for the lock {:?}, which is held by {}",
+            timeout, path, held_by
+        ),
+        holder,
+    }
+}
+
+/// Acquires the lock at `path` by creating its lock directory, which only one process can do
+fn lock_directory(path: &Path, deadline: Instant, timeout: Duration) -> anyhow::Result<PathBuf> {
+    let directory = lock_directory_path(path);
+    let mut delay = MIN_RETRY_DELAY;
+    loop {
+        match fs::create_dir(&directory) {
+            Ok(()) => {
+                fs::write(directory.join(HOLDER_FILE), holder_record())?;
+                return Ok(directory);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                if is_stale(&directory) {
+                    warn!("removing the stale lock {:?}", directory);
+                    fs::remove_dir_all(&directory).ok();
+                    continue;
+                }
+            }
+            Err(e) => return Err(e.into()),
+        }
+        if Instant::now() >= deadline {
+            return Err(timed_out(path, timeout).into());
+        }
+        thread::sleep(delay);
+        delay = (delay * 2).min(MAX_RETRY_DELAY);
+    }
+}
+
+fn lock_directory_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(".d");
+    path.with_file_name(name)
+}
+
+fn is_stale(directory: &Path) -> bool {
+    let holder = fs::read_to_string(directory.join(HOLDER_FILE))
+        .ok()
+        .and_then(|record| parse_holder(&record));
+    match holder {
+        Some(holder) => !os::is_running(holder.pid),
+        None => fs::metadata(directory)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > STALE_LOCK_DIRECTORY_AGE),
+    }
+}
+
+fn holder_record() -> String {
+    let acquired_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    format!("{}\n{}\n", std::process::id(), acquired_at)
+}
+
+fn parse_holder(record: &str) -> Option<LockHolder> {
+    let mut lines = record.lines();
+    Some(LockHolder {
+        pid: lines.next()?.parse().ok()?,
+        acquired_at: lines.next()?.parse().ok()?,
+    })
+}
+
+/// An advisory lock shared by the Nx processes of a workspace, e.g. on the cache directory
+#[napi]
+pub struct FileLock {
+    guard: Option<LockGuard>,
+}
+
+#[napi]
+impl FileLock {
+    /// Waits until the lock at `path` is acquired, for `timeout_ms` at most (30 seconds by default).
+    /// Fails with the process that holds the lock when it is not acquired in time
+    #[napi(factory)]
+    pub fn acquire(
+        path: String,
+        exclusive: Option<bool>,
+        timeout_ms: Option<u32>,
+    ) -> anyhow::Result<Self> {
+        let mode = if exclusive.unwrap_or(true) {
+            LockMode::Exclusive
+        } else {
+            LockMode::Shared
+        };
+        let timeout = timeout_ms.map_or(DEFAULT_LOCK_TIMEOUT, |timeout| {
+            Duration::from_millis(timeout.into())
+        });
+        Ok(Self {
+            guard: Some(lock(Path::new(&path), mode, timeout)?),
+        })
+    }
+
+    #[napi]
+    pub fn release(&mut self) {
+        self.guard.take();
+    }
+}
+
+/// The process that holds the lock at `path` exclusively, shared holders are not recorded
+#[napi]
+pub fn get_lock_holder(path: String) -> Option<LockHolder> {
+    lock_holder(Path::new(&path))
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    /// Acquires the lock at `path` if it is not held, without waiting
+    fn try_lock(path: &Path, mode: LockMode) -> anyhow::Result<Option<LockGuard>> {
+        match lock(path, mode, Duration::ZERO) {
+            Ok(guard) => Ok(Some(guard)),
+            Err(e) if e.is::<LockTimeout>() => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    #[test]
+    fn should_exclude_other_holders() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.join("nx-cache.lock");
+
+        let shared = lock(&path, LockMode::Shared, Duration::ZERO).unwrap();
+        assert!(try_lock(&path, LockMode::Shared).unwrap().is_some());
+        assert!(try_lock(&path, LockMode::Exclusive).unwrap().is_none());
+        drop(shared);
+
+        let exclusive = try_lock(&path, LockMode::Exclusive).unwrap().unwrap();
+        assert_eq!(lock_holder(&path).unwrap().pid, std::process::id());
+        let Err(error) = lock(&path, LockMode::Shared, Duration::from_millis(20)) else {
+            panic!("the lock is held exclusively");
+        };
+        let timeout = error.downcast_ref::<LockTimeout>().unwrap();
+        assert_eq!(timeout.holder.as_ref().unwrap().pid, std::process::id());
+        drop(exclusive);
+
+        assert_eq!(lock_holder(&path), None);
+        assert!(try_lock(&path, LockMode::Shared).unwrap().is_some());
+    }
+
+    #[test]
+    fn should_remove_stale_lock_directories() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.join("nx.db.lock");
+        let directory = lock_directory_path(&path);
+        fs::create_dir(&directory).unwrap();
+        // no process runs with the largest pid
+        fs::write(directory.join(HOLDER_FILE), format!("{}\n0\n", u32::MAX)).unwrap();
+
+        let deadline = Instant::now() + Duration::from_millis(20);
+        lock_directory(&path, deadline, Duration::from_millis(20)).unwrap();
+        assert_eq!(lock_holder(&path).unwrap().pid, std::process::id());
+        assert!(lock_directory(&path, Instant::now(), Duration::ZERO).is_err());
+    }
+}
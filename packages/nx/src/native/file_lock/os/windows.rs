@@ -0,0 +1,68 @@
+use std::fs::File;
+use std::io;
+use std::os::windows::io::AsRawHandle;
+
+use winapi::shared::minwindef::{DWORD, FALSE};
+use winapi::shared::winerror::{ERROR_ACCESS_DENIED, ERROR_LOCK_VIOLATION};
+use winapi::um::fileapi::{LockFileEx, UnlockFileEx};
+use winapi::um::handleapi::CloseHandle;
+use winapi::um::minwinbase::{
+    LOCKFILE_EXCLUSIVE_LOCK, LOCKFILE_FAIL_IMMEDIATELY, OVERLAPPED, STILL_ACTIVE,
+};
+use winapi::um::processthreadsapi::{GetExitCodeProcess, OpenProcess};
+use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+
+use crate::native::file_lock::LockMode;
+
+/// Locked regions can not be read by other handles, so a byte after the holder of the lock is locked instead,
+/// which keeps the holder readable. Regions can be locked past the end of the file
+fn lock_region() -> OVERLAPPED {
+    let mut overlapped: OVERLAPPED = unsafe { std::mem::zeroed() };
+    unsafe { overlapped.u.s_mut().OffsetHigh = 1 };
+    overlapped
+}
+
+pub fn try_lock(file: &File, mode: LockMode) -> io::Result<bool> {
+    let flags = match mode {
+        LockMode::Shared => LOCKFILE_FAIL_IMMEDIATELY,
+        LockMode::Exclusive => LOCKFILE_FAIL_IMMEDIATELY | LOCKFILE_EXCLUSIVE_LOCK,
+    };
+    let mut overlapped = lock_region();
+    let locked = unsafe { LockFileEx(file.as_raw_handle() as _, flags, 0, 1, 0, &mut overlapped) };
+    if locked != 0 {
+        return Ok(true);
+    }
+    let error = io::Error::last_os_error();
+    if error.raw_os_error() == Some(ERROR_LOCK_VIOLATION as i32) {
+        Ok(false)
+    } else {
+        Err(error)
+    }
+}
+
+pub fn unlock(file: &File) -> io::Result<()> {
+    let mut overlapped = lock_region();
+    if unsafe { UnlockFileEx(file.as_raw_handle() as _, 0, 1, 0, &mut overlapped) } != 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Every file system with Windows clients supports `LockFileEx`
+pub fn is_unsupported(_error: &io::Error) -> bool {
+    false
+}
+
+pub fn is_running(pid: u32) -> bool {
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, pid);
+        if process.is_null() {
+            return io::Error::last_os_error().raw_os_error() == Some(ERROR_ACCESS_DENIED as i32);
+        }
+        let mut exit_code: DWORD = 0;
+        let queried = GetExitCodeProcess(process, &mut exit_code) != 0;
+        CloseHandle(process);
+        queried && exit_code == STILL_ACTIVE
+    }
+}
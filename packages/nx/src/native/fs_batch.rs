@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use napi::bindgen_prelude::Buffer;
+use tracing::trace;
+
+use crate::native::utils::parallel::prelude::*;
+
+/// Files with a NUL byte in their first bytes are binary, and are never formatted
+const BINARY_DETECTION_LENGTH: usize = 8 * 1024;
+
+/// How the lines of the formatted files end
+#[napi(string_enum)]
+#[derive(Debug, PartialEq)]
+pub enum EndOfLine {
+    #[allow(non_camel_case_types)]
+    lf,
+    #[allow(non_camel_case_types)]
+    crlf,
+}
+
+/// A change of a file that is written with `writeFilesAtomic`
+#[napi(object)]
+pub struct FileEdit {
+    pub path: String,
+    /// The new content of the file. The file, or the directory, is deleted when there is none
+    pub content: Option<Buffer>,
+    /// The permissions of the file (e.g. `0o755`), the file keeps its permissions when there are none
+    pub mode: Option<u32>,
+}
+
+/// How the written text files are formatted, they are written as they are by default
+#[napi(object)]
+#[derive(Default)]
+pub struct WriteFilesOptions {
+    /// The line endings of the files, the files end their lines like their first line when there is none
+    pub end_of_line: Option<EndOfLine>,
+    /// Ends the files that are not empty with a newline
+    pub insert_final_newline: Option<bool>,
+    /// Removes the spaces and the tabs at the end of the lines
+    pub trim_trailing_whitespace: Option<bool>,
+}
+
+#[napi]
+/// Reads files in parallel. A file that cannot be read (e.g. because it does not exist) has no content
+pub fn read_files(paths: Vec<String>) -> Vec<Option<Buffer>> {
+    trace!("reading {} files", paths.len());
+    paths
+        .into_par_iter()
+        .map(|path| fs::read(path).ok())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|content| content.map(Buffer::from))
+        .collect()
+}
+
+#[napi]
+/// Applies the edits of a generator to the disk in parallel: files without content are deleted first, then the other
+/// files are formatted with `options` and written. Every file is written to a temporary file next to it which is then
+/// renamed over it, so a file is never partially written, even when the process is interrupted.
+/// Symlinks are kept and their targets are written.
+///
+/// When a path is edited more than once, its last edit is applied
+pub fn write_files_atomic(
+    edits: Vec<FileEdit>,
+    options: Option<WriteFilesOptions>,
+) -> anyhow::Result<()> {
+    let options = options.unwrap_or_default();
+    let edits = edits
+        .into_iter()
+        .map(|edit| {
+            let content = edit.content.map(|content| content.to_vec());
+            (edit.path, (content, edit.mode))
+        })
+        .collect::<HashMap<_, _>>();
+    let (deleted, written): (Vec<_>, Vec<_>) = edits
+        .into_iter()
+        .partition(|(_, (content, _))| content.is_none());
+    trace!(
+        "deleting {} files and writing {} files",
+        deleted.len(),
+        written.len()
+    );
+
+    deleted
+        .into_par_iter()
+        .map(|(path, _)| {
+            delete(Path::new(&path)).with_context(|| format!("Failed to delete {}", path))
+        })
+        .collect::<anyhow::Result<()>>()?;
+
+    written
+        .into_par_iter()
+        .map(|(path, (content, mode))| {
+            let content = format(content.unwrap_or_default(), &options);
+            write_atomically(Path::new(&path), &content, mode)
+                .with_context(|| format!("Failed to write {}", path))
+        })
+        .collect()
+}
+
+fn delete(path: &Path) -> std::io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+fn write_atomically(path: &Path, content: &[u8], mode: Option<u32>) -> anyhow::Result<()> {
+    // the rename would replace the symlink itself
+    let path = match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => fs::canonicalize(path)?,
+        _ => path.to_path_buf(),
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // the file keeps its permissions, like when it is written in place
+    let permissions = permissions(mode, fs::metadata(&path).ok().map(|m| m.permissions()));
+
+    let temp = temp_path(&path);
+    let result = (|| -> anyhow::Result<()> {
+        let mut file = File::create(&temp)?;
+        file.write_all(content)?;
+        drop(file);
+        if let Some(permissions) = permissions {
+            fs::set_permissions(&temp, permissions)?;
+        }
+        fs::rename(&temp, &path)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        fs::remove_file(&temp).ok();
+    }
+    result
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(format!(".tmp-{}", std::process::id()));
+    path.with_file_name(name)
+}
+
+#[cfg(unix)]
+fn permissions(mode: Option<u32>, existing: Option<fs::Permissions>) -> Option<fs::Permissions> {
+    use std::os::unix::fs::PermissionsExt;
+    mode.map(fs::Permissions::from_mode).or(existing)
+}
+
+/// Only the read-only flag of a file can be set on Windows, which is kept
+#[cfg(not(unix))]
+fn permissions(_mode: Option<u32>, existing: Option<fs::Permissions>) -> Option<fs::Permissions> {
+    existing
+}
+
+/// Formats the content of a text file, binary files and files that are not UTF-8 are left as they are
+fn format(content: Vec<u8>, options: &WriteFilesOptions) -> Vec<u8> {
+    let trim = options.trim_trailing_whitespace.unwrap_or_default();
+    let final_newline = options.insert_final_newline.unwrap_or_default();
+    if !trim && !final_newline && options.end_of_line.is_none() {
+        return content;
+    }
+    if content[..content.len().min(BINARY_DETECTION_LENGTH)].contains(&0) {
+        return content;
+    }
+    let text = match String::from_utf8(content) {
+        Ok(text) => text,
+        Err(e) => return e.into_bytes(),
+    };
+
+    let newline = match options.end_of_line {
+        Some(EndOfLine::lf) => "\n",
+        Some(EndOfLine::crlf) => "\r\n",
+        None if text
+            .find('\n')
+            .is_some_and(|end| text[..end].ends_with('\r')) =>
+        {
+            "\r\n"
+        }
+        None => "\n",
+    };
+    let mut lines = text.split('\n').collect::<Vec<_>>();
+    // the text after the last newline
+    let last = trim_line(lines.pop().unwrap_or_default(), trim);
+    let mut formatted = String::with_capacity(text.len() + newline.len());
+    for line in lines {
+        formatted.push_str(trim_line(line.strip_suffix('\r').unwrap_or(line), trim));
+        formatted.push_str(newline);
+    }
+    formatted.push_str(last);
+    if final_newline && !last.is_empty() {
+        formatted.push_str(newline);
+    }
+    formatted.into_bytes()
+}
+
+fn trim_line(line: &str, trim: bool) -> &str {
+    if trim {
+        line.trim_end_matches([' ', '\t'])
+    } else {
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    fn edit(temp: &TempDir, path: &str, content: Option<&str>, mode: Option<u32>) -> FileEdit {
+        FileEdit {
+            path: temp.join(path).display().to_string(),
+            content: content.map(|content| Buffer::from(content.as_bytes().to_vec())),
+            mode,
+        }
+    }
+
+    #[test]
+    fn should_apply_the_edits_to_the_disk() {
+        let temp = TempDir::new().unwrap();
+        temp.child("libs/a/old.ts").write_str("old").unwrap();
+        temp.child("libs/b/index.ts").write_str("b").unwrap();
+        temp.child("tools/run.sh").write_str("echo run").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(temp.join("tools/run.sh"), fs::Permissions::from_mode(0o755))
+                .unwrap();
+        }
+
+        write_files_atomic(
+            vec![
+                edit(&temp, "libs/a/old.ts", None, None),
+                edit(&temp, "libs/b", None, None),
+                edit(&temp, "libs/missing.ts", None, None),
+                edit(&temp, "apps/new/main.ts", Some("first"), None),
+                edit(&temp, "apps/new/main.ts", Some("main"), None),
+                edit(&temp, "tools/run.sh", Some("echo updated"), None),
+                edit(&temp, "tools/new.sh", Some("echo new"), Some(0o744)),
+            ],
+            None,
+        )
+        .unwrap();
+
+        assert!(!temp.join("libs/a/old.ts").exists());
+        assert!(!temp.join("libs/b").exists());
+        temp.child("apps/new/main.ts").assert("main");
+        temp.child("tools/run.sh").assert("echo updated");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode =
+                |path: &str| fs::metadata(temp.join(path)).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode("tools/run.sh"), 0o755);
+            assert_eq!(mode("tools/new.sh"), 0o744);
+        }
+        assert_eq!(
+            fs::read_dir(temp.join("tools")).unwrap().count(),
+            2,
+            "the temporary files are renamed"
+        );
+
+        assert_eq!(
+            read_files(vec![
+                temp.join("apps/new/main.ts").display().to_string(),
+                temp.join("libs/a/old.ts").display().to_string(),
+            ])
+            .into_iter()
+            .map(|content| content.map(|content| content.to_vec()))
+            .collect::<Vec<_>>(),
+            [Some(b"main".to_vec()), None]
+        );
+    }
+
+    #[test]
+    fn should_format_text_files() {
+        let format =
+            |content: &[u8], options: WriteFilesOptions| format(content.to_vec(), &options);
+
+        assert_eq!(
+            format(b"a  \r\nb\t\r\nc ", WriteFilesOptions::default()),
+            b"a  \r\nb\t\r\nc "
+        );
+        assert_eq!(
+            format(
+                b"a  \r\nb\t\nc ",
+                WriteFilesOptions {
+                    trim_trailing_whitespace: Some(true),
+                    insert_final_newline: Some(true),
+                    ..Default::default()
+                }
+            ),
+            b"a\r\nb\r\nc\r\n"
+        );
+        assert_eq!(
+            format(
+                b"a\r\nb\n",
+                WriteFilesOptions {
+                    end_of_line: Some(EndOfLine::lf),
+                    insert_final_newline: Some(true),
+                    ..Default::default()
+                }
+            ),
+            b"a\nb\n"
+        );
+        assert_eq!(
+            format(
+                b"a\0 \n",
+                WriteFilesOptions {
+                    trim_trailing_whitespace: Some(true),
+                    ..Default::default()
+                }
+            ),
+            b"a\0 \n"
+        );
+    }
+}
@@ -1,23 +1,81 @@
+mod glob_braces;
 mod glob_group;
 mod glob_parser;
 pub mod glob_transform;
 
+use crate::native::glob::glob_braces::expand_braces;
 use crate::native::glob::glob_transform::convert_glob;
+use crate::native::utils::normalize_separators;
 use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use lru::LruCache;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
 use std::fmt::Debug;
+use std::num::NonZeroUsize;
 use std::path::Path;
-use tracing::trace;
+use std::sync::Arc;
+use tracing::{trace, trace_span};
+
+const CASE_INSENSITIVE_ENV: &str = "NX_GLOB_CASE_INSENSITIVE";
+
+/// The number of compiled glob sets that are kept, the least recently used ones are compiled again when needed
+const GLOB_CACHE_CAPACITY: usize = 1024;
+
+/// Compiled glob sets by their patterns. The task hasher builds the same globs for every task with the same inputs,
+/// so they are only parsed and compiled once per process
+static GLOB_CACHE: Lazy<Mutex<LruCache<GlobCacheKey, NxGlobSet>>> = Lazy::new(|| {
+    Mutex::new(LruCache::new(
+        NonZeroUsize::new(GLOB_CACHE_CAPACITY).expect("the capacity is not 0"),
+    ))
+});
+
+#[derive(PartialEq, Eq, Hash)]
+struct GlobCacheKey {
+    globs: Vec<String>,
+    case_insensitive: bool,
+}
+
+/// Whether the globs of users are matched case insensitively when no case sensitivity is given, from
+/// `NX_GLOB_CASE_INSENSITIVE=true`. Read once per process
+static CASE_INSENSITIVE_BY_DEFAULT: Lazy<bool> =
+    Lazy::new(|| std::env::var(CASE_INSENSITIVE_ENV).is_ok_and(|value| value == "true"));
+
+/// Options used when building glob sets. Globs are case sensitive by default, like the JS glob implementation,
+/// so that the inputs, the outputs and the hashes of tasks match the same files on every platform
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GlobOptions {
+    pub case_insensitive: bool,
+}
+
+impl GlobOptions {
+    /// Options for the globs of users (e.g. the globs of `WorkspaceContext.glob`): the given case sensitivity,
+    /// or case insensitive when it is opted into with `NX_GLOB_CASE_INSENSITIVE=true`
+    pub fn with_case_insensitive(case_insensitive: Option<bool>) -> Self {
+        Self {
+            case_insensitive: case_insensitive.unwrap_or(*CASE_INSENSITIVE_BY_DEFAULT),
+        }
+    }
+}
 
 pub struct NxGlobSetBuilder {
     included_globs: GlobSetBuilder,
     excluded_globs: GlobSetBuilder,
+    options: GlobOptions,
 }
 
 impl NxGlobSetBuilder {
     pub fn new<S: AsRef<str>>(globs: &[S]) -> anyhow::Result<Self> {
+        Self::with_options(globs, GlobOptions::default())
+    }
+
+    pub(crate) fn with_options<S: AsRef<str>>(
+        globs: &[S],
+        options: GlobOptions,
+    ) -> anyhow::Result<Self> {
         let mut glob_set_builder = NxGlobSetBuilder {
             included_globs: GlobSetBuilder::new(),
             excluded_globs: GlobSetBuilder::new(),
+            options,
         };
         let mut globs: Vec<&str> = globs.iter().map(|s| s.as_ref()).collect();
         globs.sort();
@@ -29,7 +87,8 @@ impl NxGlobSetBuilder {
 
     pub fn add(&mut self, glob: &str) -> anyhow::Result<&mut NxGlobSetBuilder> {
         let negated = glob.starts_with('!');
-        let glob_string = glob.strip_prefix('!').unwrap_or(glob).to_string();
+        // paths are matched with `/` separators, so globs written with windows separators match them too
+        let glob_string = normalize_separators(glob.strip_prefix('!').unwrap_or(glob)).into_owned();
 
         let glob_string = if glob_string.ends_with('/') {
             format!("{}**", glob_string)
@@ -39,6 +98,7 @@ impl NxGlobSetBuilder {
 
         let glob = GlobBuilder::new(&glob_string)
             .literal_separator(true)
+            .case_insensitive(self.options.case_insensitive)
             .build()
             .map_err(anyhow::Error::from)?;
 
@@ -53,15 +113,17 @@ impl NxGlobSetBuilder {
 
     pub fn build(&self) -> anyhow::Result<NxGlobSet> {
         Ok(NxGlobSet {
-            excluded_globs: self.excluded_globs.build()?,
-            included_globs: self.included_globs.build()?,
+            excluded_globs: Arc::new(self.excluded_globs.build()?),
+            included_globs: Arc::new(self.included_globs.build()?),
         })
     }
 }
 
+/// Cloning a glob set shares its compiled globs
+#[derive(Clone)]
 pub struct NxGlobSet {
-    included_globs: GlobSet,
-    excluded_globs: GlobSet,
+    included_globs: Arc<GlobSet>,
+    excluded_globs: Arc<GlobSet>,
 }
 impl NxGlobSet {
     pub fn is_match<P: AsRef<Path>>(&self, path: P) -> bool {
@@ -76,27 +138,120 @@ impl NxGlobSet {
     }
 }
 
-fn potential_glob_split(
-    glob: &str,
-) -> itertools::Either<std::str::Split<char>, std::iter::Once<&str>> {
-    use itertools::Either::*;
-    if glob.starts_with('{') && glob.ends_with('}') {
-        Left(glob.trim_matches('{').trim_end_matches('}').split(','))
+/// Evaluates globs in the order they were provided, where later globs override earlier ones (like .gitignore).
+/// A path matches when the last glob that matches it is not negated.
+pub struct OrderedNxGlobSet {
+    globs: Vec<(bool, NxGlobSet)>,
+    match_by_default: bool,
+}
+impl OrderedNxGlobSet {
+    pub fn is_match<P: AsRef<Path>>(&self, path: P) -> bool {
+        let path = path.as_ref();
+        self.globs
+            .iter()
+            .rev()
+            .find(|(_, glob_set)| glob_set.is_match(path))
+            .map(|(negated, _)| !negated)
+            .unwrap_or(self.match_by_default)
+    }
+}
+
+/// Matches paths with negated globs applied either set-wise or in order
+pub enum NxGlobMatcher {
+    Unordered(NxGlobSet),
+    Ordered(OrderedNxGlobSet),
+}
+impl NxGlobMatcher {
+    pub fn is_match<P: AsRef<Path>>(&self, path: P) -> bool {
+        match self {
+            NxGlobMatcher::Unordered(glob_set) => glob_set.is_match(path),
+            NxGlobMatcher::Ordered(glob_set) => glob_set.is_match(path),
+        }
+    }
+}
+
+/// Builds a glob set where each glob is evaluated in order, so negated globs only exclude paths matched by globs before them
+pub(crate) fn build_ordered_glob_set<S: AsRef<str> + Debug>(
+    globs: &[S],
+) -> anyhow::Result<OrderedNxGlobSet> {
+    build_ordered_glob_set_with_options(globs, GlobOptions::default())
+}
+
+fn build_ordered_glob_set_with_options<S: AsRef<str> + Debug>(
+    globs: &[S],
+    options: GlobOptions,
+) -> anyhow::Result<OrderedNxGlobSet> {
+    let globs = globs
+        .iter()
+        .map(|glob| {
+            let glob = glob.as_ref();
+            // !(a|b) is an extglob, not a negated glob
+            let negated = glob.starts_with('!') && !glob.starts_with("!(");
+            let glob = if negated { &glob[1..] } else { glob };
+            Ok((negated, build_glob_set_with_options(&[glob], options)?))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    trace!(globs_len = globs.len(), "built ordered globs");
+
+    Ok(OrderedNxGlobSet {
+        match_by_default: globs.iter().all(|(negated, _)| *negated),
+        globs,
+    })
+}
+
+pub fn build_glob_matcher<S: AsRef<str> + Debug>(
+    globs: &[S],
+    ordered: bool,
+    options: GlobOptions,
+) -> anyhow::Result<NxGlobMatcher> {
+    if ordered {
+        build_ordered_glob_set_with_options(globs, options).map(NxGlobMatcher::Ordered)
     } else {
-        Right(std::iter::once(glob))
+        build_glob_set_with_options(globs, options).map(NxGlobMatcher::Unordered)
+    }
+}
+
+pub fn build_glob_set<S: AsRef<str> + Debug>(globs: &[S]) -> anyhow::Result<NxGlobSet> {
+    build_glob_set_with_options(globs, GlobOptions::default())
+}
+
+/// Builds the glob set of `globs`, or reuses the one that was built for the same globs and options
+pub(crate) fn build_glob_set_with_options<S: AsRef<str> + Debug>(
+    globs: &[S],
+    options: GlobOptions,
+) -> anyhow::Result<NxGlobSet> {
+    let key = GlobCacheKey {
+        globs: globs.iter().map(|glob| glob.as_ref().to_string()).collect(),
+        case_insensitive: options.case_insensitive,
+    };
+    if let Some(glob_set) = GLOB_CACHE.lock().get(&key) {
+        return Ok(glob_set.clone());
     }
+
+    let glob_set = compile_glob_set(globs, options)?;
+    GLOB_CACHE.lock().put(key, glob_set.clone());
+    Ok(glob_set)
+}
+
+/// Forgets every compiled glob set, so the next globs are compiled again (e.g. to measure compiling them)
+pub fn clear_glob_cache() {
+    GLOB_CACHE.lock().clear();
 }
 
-pub(crate) fn build_glob_set<S: AsRef<str> + Debug>(globs: &[S]) -> anyhow::Result<NxGlobSet> {
+fn compile_glob_set<S: AsRef<str> + Debug>(
+    globs: &[S],
+    options: GlobOptions,
+) -> anyhow::Result<NxGlobSet> {
+    let _span = trace_span!("compile_glob_set", ?globs).entered();
     let result = globs
         .iter()
-        .flat_map(|s| potential_glob_split(s.as_ref()))
+        .flat_map(|s| expand_braces(s.as_ref()))
         .map(|glob| {
-            if glob.contains('!') || glob.contains('|') || glob.contains('(') || glob.contains("{,")
-            {
-                convert_glob(glob)
+            if glob.contains('!') || glob.contains('|') || glob.contains('(') {
+                convert_glob(&glob)
             } else {
-                Ok(vec![glob.to_string()])
+                Ok(vec![glob])
             }
         })
         .collect::<anyhow::Result<Vec<_>>>()?
@@ -104,7 +259,7 @@ pub(crate) fn build_glob_set<S: AsRef<str> + Debug>(globs: &[S]) -> anyhow::Resu
 
     trace!(?globs, ?result, "converted globs");
 
-    NxGlobSetBuilder::new(&result)?.build()
+    NxGlobSetBuilder::with_options(&result, options)?.build()
 }
 
 pub(crate) fn contains_glob_pattern(value: &str) -> bool {
@@ -360,6 +515,80 @@ mod test {
         assert!(!glob_set.is_match("packages/package-a/package.json"));
     }
 
+    #[test]
+    fn supports_multiple_and_nested_brace_expansion() {
+        let glob_set = build_glob_set(&["{src,lib}/**/*.{ts,tsx}"]).unwrap();
+        assert!(glob_set.is_match("src/index.ts"));
+        assert!(glob_set.is_match("lib/nested/component.tsx"));
+        assert!(!glob_set.is_match("src/index.js"));
+        assert!(!glob_set.is_match("test/index.ts"));
+
+        let glob_set = build_glob_set(&["packages/{app,lib/{core,ui}}/*.ts"]).unwrap();
+        assert!(glob_set.is_match("packages/app/index.ts"));
+        assert!(glob_set.is_match("packages/lib/core/index.ts"));
+        assert!(glob_set.is_match("packages/lib/ui/index.ts"));
+        assert!(!glob_set.is_match("packages/lib/index.ts"));
+
+        let glob_set = build_glob_set(&["{src,lib}/**/@(index|main).ts"]).unwrap();
+        assert!(glob_set.is_match("src/index.ts"));
+        assert!(glob_set.is_match("lib/nested/main.ts"));
+        assert!(!glob_set.is_match("lib/nested/other.ts"));
+    }
+
+    #[test]
+    fn supports_extglob_patterns() {
+        let glob_set = build_glob_set(&["src/@(a|b).ts"]).unwrap();
+        assert!(glob_set.is_match("src/a.ts"));
+        assert!(glob_set.is_match("src/b.ts"));
+        assert!(!glob_set.is_match("src/c.ts"));
+        assert!(!glob_set.is_match("src/ab.ts"));
+
+        let glob_set = build_glob_set(&["src/*.ts?(x)"]).unwrap();
+        assert!(glob_set.is_match("src/index.ts"));
+        assert!(glob_set.is_match("src/index.tsx"));
+        assert!(!glob_set.is_match("src/index.tsxx"));
+
+        let glob_set = build_glob_set(&["src/+(index).ts"]).unwrap();
+        assert!(glob_set.is_match("src/index.ts"));
+        assert!(!glob_set.is_match("src/main.ts"));
+    }
+
+    #[test]
+    fn should_apply_negated_globs_in_order() {
+        let glob_set = build_ordered_glob_set(&["libs/**/*", "!libs/**/*.spec.ts"]).unwrap();
+        assert!(glob_set.is_match("libs/src/index.ts"));
+        assert!(!glob_set.is_match("libs/src/index.spec.ts"));
+
+        // later globs override earlier negated globs
+        let glob_set =
+            build_ordered_glob_set(&["libs/**/*", "!libs/**/*.spec.ts", "libs/important.spec.ts"])
+                .unwrap();
+        assert!(glob_set.is_match("libs/important.spec.ts"));
+        assert!(!glob_set.is_match("libs/src/index.spec.ts"));
+
+        let glob_set = build_ordered_glob_set(&["!libs/**/*.spec.ts", "libs/**/*"]).unwrap();
+        assert!(glob_set.is_match("libs/src/index.spec.ts"));
+
+        // only negated globs match everything else
+        let glob_set = build_ordered_glob_set(&["!libs/**/*.spec.ts"]).unwrap();
+        assert!(glob_set.is_match("libs/src/index.ts"));
+        assert!(!glob_set.is_match("libs/src/index.spec.ts"));
+
+        // extglob negations are not negated globs
+        let glob_set = build_ordered_glob_set(&["libs/!(*.spec).ts"]).unwrap();
+        assert!(glob_set.is_match("libs/index.ts"));
+        assert!(!glob_set.is_match("libs/index.spec.ts"));
+    }
+
+    #[test]
+    fn should_build_unordered_matchers_by_default() {
+        let globs = ["!libs/**/*.spec.ts", "libs/**/*"];
+        let unordered = build_glob_matcher(&globs, false, GlobOptions::default()).unwrap();
+        let ordered = build_glob_matcher(&globs, true, GlobOptions::default()).unwrap();
+        assert!(!unordered.is_match("libs/src/index.spec.ts"));
+        assert!(ordered.is_match("libs/src/index.spec.ts"));
+    }
+
     #[test]
     fn should_handle_invalid_group_globs() {
         let glob_set = build_glob_set(&[
@@ -375,4 +604,56 @@ mod test {
         assert!(glob_set.is_match("libs/src/index.ts"));
         assert!(!glob_set.is_match("libs/src/index.spec.ts"));
     }
+
+    #[test]
+    fn should_match_case_insensitively() {
+        let glob_set = build_glob_set_with_options(
+            &["**/Dockerfile"],
+            GlobOptions {
+                case_insensitive: true,
+            },
+        )
+        .unwrap();
+        assert!(glob_set.is_match("apps/web/dockerfile"));
+        assert!(glob_set.is_match("apps/web/DOCKERFILE"));
+
+        let glob_set = build_glob_set_with_options(
+            &["**/Dockerfile"],
+            GlobOptions {
+                case_insensitive: false,
+            },
+        )
+        .unwrap();
+        assert!(glob_set.is_match("apps/web/Dockerfile"));
+        assert!(!glob_set.is_match("apps/web/dockerfile"));
+    }
+
+    #[test]
+    fn should_reuse_compiled_glob_sets() {
+        let globs = ["libs/cached/**/*.ts", "!libs/cached/**/*.spec.ts"];
+        let glob_set = build_glob_set(&globs).unwrap();
+        let reused = build_glob_set(&globs).unwrap();
+        assert!(Arc::ptr_eq(
+            &glob_set.included_globs,
+            &reused.included_globs
+        ));
+        assert!(Arc::ptr_eq(
+            &glob_set.excluded_globs,
+            &reused.excluded_globs
+        ));
+
+        let other_options = build_glob_set_with_options(
+            &globs,
+            GlobOptions {
+                case_insensitive: true,
+            },
+        )
+        .unwrap();
+        assert!(!Arc::ptr_eq(
+            &glob_set.included_globs,
+            &other_options.included_globs
+        ));
+        assert!(reused.is_match("libs/cached/src/index.ts"));
+        assert!(!reused.is_match("libs/cached/src/index.spec.ts"));
+    }
 }
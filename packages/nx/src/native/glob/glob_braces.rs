@@ -0,0 +1,137 @@
+/// Expands brace groups in a glob, including nested groups.
+/// Example:
+/// - {src,lib}/**/*.{ts,tsx} -> src/**/*.ts, src/**/*.tsx, lib/**/*.ts, lib/**/*.tsx
+/// - {a,{b,c}d} -> a, bd, cd
+///
+/// Groups without a top-level comma (e.g. `{a}`) and unbalanced braces are left as they are,
+/// braces inside of character classes (`[{]`) or escaped with `\` are ignored.
+pub fn expand_braces(glob: &str) -> Vec<String> {
+    let Some((start, end, alternatives)) = find_brace_group(glob) else {
+        return vec![glob.to_string()];
+    };
+
+    let prefix = &glob[..start];
+    let suffix = &glob[end + 1..];
+    alternatives
+        .into_iter()
+        .flat_map(|alternative| expand_braces(&format!("{}{}{}", prefix, alternative, suffix)))
+        .collect()
+}
+
+/// Finds the first brace group that has alternatives, returning its start index, end index, and alternatives
+fn find_brace_group(glob: &str) -> Option<(usize, usize, Vec<&str>)> {
+    let bytes = glob.as_bytes();
+    let mut index = 0;
+    let mut in_class = false;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 1,
+            b'[' if !in_class => in_class = true,
+            b']' if in_class => in_class = false,
+            b'{' if !in_class => {
+                if let Some((end, commas)) = find_closing_brace(bytes, index) {
+                    if !commas.is_empty() {
+                        let mut alternatives = Vec::with_capacity(commas.len() + 1);
+                        let mut alternative_start = index + 1;
+                        for comma in commas {
+                            alternatives.push(&glob[alternative_start..comma]);
+                            alternative_start = comma + 1;
+                        }
+                        alternatives.push(&glob[alternative_start..end]);
+                        return Some((index, end, alternatives));
+                    }
+                }
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+    None
+}
+
+/// Finds the closing brace of the group starting at `start`, along with the indexes of its top-level commas
+fn find_closing_brace(bytes: &[u8], start: usize) -> Option<(usize, Vec<usize>)> {
+    let mut depth = 0;
+    let mut commas = vec![];
+    let mut in_class = false;
+    let mut index = start;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 1,
+            b'[' if !in_class => in_class = true,
+            b']' if in_class => in_class = false,
+            b'{' if !in_class => depth += 1,
+            b'}' if !in_class => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((index, commas));
+                }
+            }
+            b',' if !in_class && depth == 1 => commas.push(index),
+            _ => {}
+        }
+        index += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::expand_braces;
+
+    #[test]
+    fn should_expand_simple_braces() {
+        assert_eq!(expand_braces("{a,b}"), ["a", "b"]);
+        assert_eq!(
+            expand_braces("{package-lock.json,yarn.lock,pnpm-lock.yaml}"),
+            ["package-lock.json", "yarn.lock", "pnpm-lock.yaml"]
+        );
+        assert_eq!(
+            expand_braces("**/*.spec.ts{,.snap}"),
+            ["**/*.spec.ts", "**/*.spec.ts.snap"]
+        );
+    }
+
+    #[test]
+    fn should_expand_multiple_braces() {
+        assert_eq!(
+            expand_braces("{src,lib}/**/*.{ts,tsx}"),
+            [
+                "src/**/*.ts",
+                "src/**/*.tsx",
+                "lib/**/*.ts",
+                "lib/**/*.tsx"
+            ]
+        );
+    }
+
+    #[test]
+    fn should_expand_nested_braces() {
+        assert_eq!(expand_braces("{a,{b,c}d}"), ["a", "bd", "cd"]);
+        assert_eq!(
+            expand_braces("packages/{app,lib/{core,ui}}/*.ts"),
+            [
+                "packages/app/*.ts",
+                "packages/lib/core/*.ts",
+                "packages/lib/ui/*.ts"
+            ]
+        );
+    }
+
+    #[test]
+    fn should_not_expand_braces_without_alternatives() {
+        assert_eq!(expand_braces("{a}"), ["{a}"]);
+        assert_eq!(expand_braces("{a"), ["{a"]);
+        assert_eq!(expand_braces("[{,}]"), ["[{,}]"]);
+        assert_eq!(expand_braces(r"\{a,b}"), [r"\{a,b}"]);
+        assert_eq!(expand_braces("{a}/{b,c}"), ["{a}/b", "{a}/c"]);
+    }
+
+    #[test]
+    fn should_keep_extglobs_when_expanding() {
+        assert_eq!(
+            expand_braces("!{src,lib}/**/@(a|b).ts"),
+            ["!src/**/@(a|b).ts", "!lib/**/@(a|b).ts"]
+        );
+    }
+}
@@ -1,12 +1,86 @@
+use std::io::{ErrorKind, Read};
 use std::path::Path;
 
+use sha2::Digest;
 use tracing::trace;
 use xxhash_rust::xxh3;
 
+use crate::native::utils::to_fs_path;
+
 pub fn hash(content: &[u8]) -> String {
     xxh3::xxh3_64(content).to_string()
 }
 
+/// The algorithms the hash of a task can be computed with.
+/// Some remote caches address artifacts by a cryptographic digest, which `blake3` and `sha256` provide
+#[napi(string_enum)]
+#[derive(Debug, Default, PartialEq)]
+pub enum HashAlgorithm {
+    /// The decimal 64 bits xxh3 hash, like `hash`
+    #[default]
+    #[allow(non_camel_case_types)]
+    xxh3,
+    /// The hexadecimal 256 bits blake3 digest
+    #[allow(non_camel_case_types)]
+    blake3,
+    /// The hexadecimal 256 bits sha256 digest
+    #[allow(non_camel_case_types)]
+    sha256,
+}
+
+impl HashAlgorithm {
+    pub fn hasher(self) -> Box<dyn Hasher> {
+        match self {
+            HashAlgorithm::xxh3 => Box::new(xxh3::Xxh3::new()),
+            HashAlgorithm::blake3 => Box::new(blake3::Hasher::new()),
+            HashAlgorithm::sha256 => Box::new(sha2::Sha256::new()),
+        }
+    }
+
+    pub fn hash(self, content: &[u8]) -> String {
+        let mut hasher = self.hasher();
+        hasher.update(content);
+        hasher.finish()
+    }
+}
+
+/// Hashes bytes incrementally with one of the `HashAlgorithm`s
+pub trait Hasher {
+    fn update(&mut self, bytes: &[u8]);
+
+    fn finish(self: Box<Self>) -> String;
+}
+
+impl Hasher for xxh3::Xxh3 {
+    fn update(&mut self, bytes: &[u8]) {
+        xxh3::Xxh3::update(self, bytes);
+    }
+
+    fn finish(self: Box<Self>) -> String {
+        self.digest().to_string()
+    }
+}
+
+impl Hasher for blake3::Hasher {
+    fn update(&mut self, bytes: &[u8]) {
+        blake3::Hasher::update(self, bytes);
+    }
+
+    fn finish(self: Box<Self>) -> String {
+        self.finalize().to_hex().to_string()
+    }
+}
+
+impl Hasher for sha2::Sha256 {
+    fn update(&mut self, bytes: &[u8]) {
+        Digest::update(self, bytes);
+    }
+
+    fn finish(self: Box<Self>) -> String {
+        format!("{:x}", Digest::finalize(*self))
+    }
+}
+
 #[napi]
 pub fn hash_array(input: Vec<String>) -> String {
     let joined = input.join(",");
@@ -21,9 +95,10 @@ pub fn hash_file(file: String) -> Option<String> {
 
 #[inline]
 pub fn hash_file_path<P: AsRef<Path>>(path: P) -> Option<String> {
-    let path = path.as_ref();
+    // long paths can only be read as verbatim paths on windows
+    let path = to_fs_path(path.as_ref());
     trace!("Reading {:?} to hash", path);
-    let Ok(content) = std::fs::read(path) else {
+    let Ok(content) = std::fs::read(&path) else {
         trace!("Failed to read file: {:?}", path);
         return None;
     };
@@ -34,9 +109,38 @@ pub fn hash_file_path<P: AsRef<Path>>(path: P) -> Option<String> {
     Some(hash)
 }
 
+/// Hashes a file by streaming its contents from disk instead of reading it into memory at once.
+/// The resulting hash is the same as `hash_file_path` for the same content.
+pub fn hash_file_path_streamed<P: AsRef<Path>>(path: P) -> Option<String> {
+    let path = to_fs_path(path.as_ref());
+    trace!("Streaming {:?} to hash", path);
+    let Ok(mut file) = std::fs::File::open(&path) else {
+        trace!("Failed to open file: {:?}", path);
+        return None;
+    };
+
+    let mut hasher = xxh3::Xxh3::new();
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(len) => hasher.update(&buffer[..len]),
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => {
+                trace!("Failed to read file: {:?} - {:?}", path, e);
+                return None;
+            }
+        }
+    }
+    let hash = hasher.digest().to_string();
+    trace!("Hashed file {:?} - {:?}", path, hash);
+
+    Some(hash)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::native::hasher::hash_file;
+    use crate::native::hasher::{hash, hash_file, hash_file_path_streamed, HashAlgorithm};
     use assert_fs::prelude::*;
     use assert_fs::TempDir;
 
@@ -72,4 +176,35 @@ mod tests {
 
         assert_eq!(content.unwrap(), "6193209363630369380");
     }
+
+    #[test]
+    fn it_hashes_with_every_algorithm() {
+        assert_eq!(HashAlgorithm::xxh3.hash(b"content"), hash(b"content"));
+        assert_eq!(
+            HashAlgorithm::blake3.hash(b""),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+        assert_eq!(
+            HashAlgorithm::sha256.hash(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+
+        let mut hasher = HashAlgorithm::sha256.hasher();
+        hasher.update(b"con");
+        hasher.update(b"tent");
+        assert_eq!(hasher.finish(), HashAlgorithm::sha256.hash(b"content"));
+    }
+
+    #[test]
+    fn it_hashes_a_streamed_file_the_same_as_a_read_file() {
+        assert!(hash_file_path_streamed("").is_none());
+
+        let temp_dir = setup_fs();
+
+        let test_file_path = temp_dir.display().to_string() + "/test.txt";
+        assert_eq!(
+            hash_file_path_streamed(&test_file_path),
+            hash_file(test_file_path)
+        );
+    }
 }
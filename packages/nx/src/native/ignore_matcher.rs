@@ -0,0 +1,212 @@
+use std::path::{Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use tracing::{trace, warn};
+
+use crate::native::glob::{build_glob_set, NxGlobSet};
+use crate::native::utils::Normalize;
+use crate::native::walker::{create_walker, SymlinkPolicy, ALWAYS_IGNORED_GLOBS};
+
+const NX_IGNORE: &str = ".nxignore";
+const IGNORE: &str = ".ignore";
+const GIT_IGNORE: &str = ".gitignore";
+
+/// Matches paths against the ignore files of a workspace with the semantics of the native walker:
+/// - the directories that are always ignored (like `node_modules`) are ignored
+/// - `.nxignore`, `.ignore` and `.gitignore` files apply to the directory that contains them, the deeper ones take precedence
+/// - `.nxignore` files take precedence over `.ignore` files, which take precedence over `.gitignore` files,
+///   so they can un-ignore files with `!`
+/// - a path inside an ignored directory is ignored, since the walker never enters the directory
+#[napi]
+pub struct IgnoreMatcher {
+    root: PathBuf,
+    always_ignored: NxGlobSet,
+    /// The matchers of the `.nxignore` files, the deepest first
+    nx_ignores: Vec<Gitignore>,
+    /// The matchers of the `.ignore` files, the deepest first
+    ignores: Vec<Gitignore>,
+    /// The matchers of the `.gitignore` files, the deepest first,
+    /// followed by the exclude file of the repository and the global gitignore
+    git_ignores: Vec<Gitignore>,
+}
+
+#[napi]
+impl IgnoreMatcher {
+    /// Returns whether the walker would leave out the path. Paths are relative to the root, absolute paths outside of it are never ignored
+    #[napi]
+    pub fn is_ignored(&self, path: String) -> bool {
+        let path = Path::new(&path);
+        let relative_path = if path.is_absolute() {
+            match path.strip_prefix(&self.root) {
+                Ok(relative_path) => relative_path,
+                Err(_) => return false,
+            }
+        } else {
+            path
+        };
+
+        let is_dir = self.root.join(relative_path).is_dir();
+        let mut ancestors = relative_path
+            .ancestors()
+            .filter(|ancestor| !ancestor.as_os_str().is_empty())
+            .collect::<Vec<_>>();
+        // the walker filters every directory on the way to the path
+        ancestors.reverse();
+        let last = ancestors.len().saturating_sub(1);
+        ancestors
+            .into_iter()
+            .enumerate()
+            .any(|(index, ancestor)| self.is_entry_ignored(ancestor, index < last || is_dir))
+    }
+}
+
+impl IgnoreMatcher {
+    pub fn new<P: AsRef<Path>>(root: P) -> anyhow::Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        let always_ignored = build_glob_set(&ALWAYS_IGNORED_GLOBS)?;
+
+        // only the ignore files that the walker reaches apply
+        let mut ignore_files = create_walker(&root, true, SymlinkPolicy::follow)
+            .build()
+            .flatten()
+            .filter(|entry| {
+                entry.file_type().is_some_and(|t| t.is_file())
+                    && matches!(
+                        entry.file_name().to_str(),
+                        Some(NX_IGNORE) | Some(IGNORE) | Some(GIT_IGNORE)
+                    )
+            })
+            .map(|entry| entry.into_path())
+            .collect::<Vec<_>>();
+        ignore_files.sort_by_key(|path| std::cmp::Reverse(path.components().count()));
+
+        let mut nx_ignores = vec![];
+        let mut ignores = vec![];
+        let mut git_ignores = vec![];
+        for path in ignore_files {
+            let ignore = build_ignore(path.parent().unwrap_or(&root), &path)?;
+            match path.file_name().and_then(|name| name.to_str()) {
+                Some(NX_IGNORE) => nx_ignores.push(ignore),
+                Some(IGNORE) => ignores.push(ignore),
+                _ => git_ignores.push(ignore),
+            }
+        }
+
+        let exclude = root.join(".git/info/exclude");
+        if exclude.is_file() {
+            git_ignores.push(build_ignore(&root, &exclude)?);
+        }
+        let (global, error) = Gitignore::global();
+        if let Some(e) = error {
+            warn!("unable to read the global gitignore: {}", e);
+        }
+        if !global.is_empty() {
+            git_ignores.push(global);
+        }
+
+        trace!(
+            "matching {} nxignore, {} ignore and {} gitignore files",
+            nx_ignores.len(),
+            ignores.len(),
+            git_ignores.len()
+        );
+        Ok(Self {
+            root,
+            always_ignored,
+            nx_ignores,
+            ignores,
+            git_ignores,
+        })
+    }
+
+    fn is_entry_ignored(&self, relative_path: &Path, is_dir: bool) -> bool {
+        if self
+            .always_ignored
+            .is_match(relative_path.to_normalized_string().as_str())
+        {
+            return true;
+        }
+
+        let path = self.root.join(relative_path);
+        let matched = |ignores: &[Gitignore]| {
+            ignores
+                .iter()
+                .filter(|ignore| path.starts_with(ignore.path()))
+                .map(|ignore| ignore.matched(&path, is_dir))
+                .find(|matched| !matched.is_none())
+                .map(|matched| matched.is_ignore())
+        };
+        matched(&self.nx_ignores)
+            .or_else(|| matched(&self.ignores))
+            .or_else(|| matched(&self.git_ignores))
+            .unwrap_or_default()
+    }
+}
+
+fn build_ignore(directory: &Path, path: &Path) -> anyhow::Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(directory);
+    if let Some(e) = builder.add(path) {
+        return Err(anyhow::Error::from(e));
+    }
+    builder.build().map_err(anyhow::Error::from)
+}
+
+/// Reads the ignore files of a workspace, so JS can match paths exactly like the native walker does
+/// (e.g. to infer projects only from files that are not ignored)
+#[napi]
+pub fn create_ignore_matcher(root: String) -> anyhow::Result<IgnoreMatcher> {
+    IgnoreMatcher::new(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    use super::*;
+    use crate::native::walker::nx_walker;
+
+    #[test]
+    fn should_ignore_the_files_the_walker_leaves_out() {
+        let files = [
+            "README.md",
+            "dist/main.js",
+            "node_modules/pkg/index.js",
+            "packages/a/index.ts",
+            "packages/a/tmp/out.txt",
+            "packages/a/generated/keep.ts",
+            "packages/b/logs/build.log",
+            "packages/b/index.ts",
+        ];
+        let temp = TempDir::new().unwrap();
+        for file in files {
+            temp.child(file).write_str("content").unwrap();
+        }
+        temp.child(".gitignore")
+            .write_str("dist\n*.log\ngenerated/\n")
+            .unwrap();
+        temp.child(".nxignore")
+            .write_str("!packages/a/generated/\n")
+            .unwrap();
+        temp.child("packages/a/.gitignore")
+            .write_str("tmp/\n")
+            .unwrap();
+
+        let matcher = IgnoreMatcher::new(temp.path()).unwrap();
+        let walked = nx_walker(temp.path(), true, SymlinkPolicy::follow)
+            .map(|file| file.normalized_path)
+            .collect::<Vec<_>>();
+        for file in files {
+            assert_eq!(
+                matcher.is_ignored(file.to_string()),
+                !walked.iter().any(|walked| walked == file),
+                "{}",
+                file
+            );
+        }
+        assert!(matcher.is_ignored("dist".to_string()));
+        assert!(!matcher.is_ignored("packages/a/generated/keep.ts".to_string()));
+        assert!(matcher.is_ignored(temp.join("packages/a/tmp/out.txt").to_string_lossy().into()));
+        assert!(!matcher.is_ignored("/outside/of/the/workspace".to_string()));
+    }
+}
@@ -7,10 +7,66 @@ export declare class ExternalObject<T> {
     [K: symbol]: T
   }
 }
+/**
+ * A remote cache that reads and writes artifacts in a bucket of S3 (or S3 compatible storage),
+ * of Google Cloud Storage, or in a container of Azure Blob Storage.
+ *
+ * Credentials are read from the environment:
+ * - S3: `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`
+ * - GCS: `GOOGLE_OAUTH_ACCESS_TOKEN`, or the HMAC keys `GCS_HMAC_ACCESS_KEY_ID` and `GCS_HMAC_SECRET`
+ * - Azure: `AZURE_STORAGE_ACCOUNT` with `AZURE_STORAGE_KEY` or `AZURE_STORAGE_SAS_TOKEN`
+ */
+export declare class BlobStorageCacheClient {
+  constructor(options: BlobStorageCacheOptions)
+  /**
+   * Downloads the artifact of a task into `<cache_directory>/<hash>`.
+   * Resolves with null when the bucket does not have it
+   */
+  retrieve(hash: string, cacheDirectory: string): Promise<CachedResult | null>
+  /**
+   * Uploads the outputs in `<cache_directory>/<hash>` with the terminal output and exit code of the task.
+   * Resolves with false when the bucket already has the artifact
+   */
+  store(hash: string, cacheDirectory: string, terminalOutput: string, code: number): Promise<boolean>
+  /**
+   * Whether the bucket has the artifact of each hash, without downloading them.
+   * Buckets can not be asked for several objects at once, so the objects are checked in parallel
+   */
+  checkCacheHits(hashes: Array<string>): Promise<Record<string, boolean>>
+}
+
 export declare class ChildProcess {
-  kill(): void
-  onExit(callback: (message: string) => void): void
-  onOutput(callback: (message: string) => void): void
+  /** Kills the process and every process it started, with `SIGTERM` unless another `signal` is given */
+  kill(signal?: string | undefined | null): void
+  /**
+   * Calls `callback` with the exit message of the process and the details of how it exited,
+   * e.g. the signal that killed it
+   */
+  onExit(callback: (message: string, details: ExitDetails) => void): void
+  /**
+   * Calls `callback` with the output of the process, with escape sequences filtered according to `ansi`.
+   * The output printed to the terminal is not filtered
+   */
+  onOutput(callback: (message: string) => void, ansi?: AnsiMode | undefined | null): void
+}
+
+/** An advisory lock shared by the Nx processes of a workspace, e.g. on the cache directory */
+export declare class FileLock {
+  /**
+   * Waits until the lock at `path` is acquired, for `timeout_ms` at most (30 seconds by default).
+   * Fails with the process that holds the lock when it is not acquired in time
+   */
+  static acquire(path: string, exclusive?: boolean | undefined | null, timeoutMs?: number | undefined | null): FileLock
+  release(): void
+}
+
+/**
+ * Lazily matches workspace files against globs, handing them to JS in batches
+ * so that large workspaces do not need to transfer every match at once
+ */
+export declare class GlobStream {
+  /** Returns the next batch of matched files, or `null` when there are no more matches */
+  nextBatch(): Array<FileData> | null
 }
 
 export declare class HashPlanner {
@@ -19,6 +75,19 @@ export declare class HashPlanner {
   getPlansReference(taskIds: Array<string>, taskGraph: TaskGraph): JsExternal
 }
 
+/**
+ * Matches paths against the ignore files of a workspace with the semantics of the native walker:
+ * - the directories that are always ignored (like `node_modules`) are ignored
+ * - `.nxignore`, `.ignore` and `.gitignore` files apply to the directory that contains them, the deeper ones take precedence
+ * - `.nxignore` files take precedence over `.ignore` files, which take precedence over `.gitignore` files,
+ *   so they can un-ignore files with `!`
+ * - a path inside an ignored directory is ignored, since the walker never enters the directory
+ */
+export declare class IgnoreMatcher {
+  /** Returns whether the walker would leave out the path. Paths are relative to the root, absolute paths outside of it are never ignored */
+  isIgnored(path: string): boolean
+}
+
 export declare class ImportResult {
   file: string
   sourceProject: string
@@ -26,22 +95,217 @@ export declare class ImportResult {
   staticImportExpressions: Array<string>
 }
 
+/** Finds the imports of the files of a workspace, and keeps them so that the next scan only reads the files that changed */
+export declare class ImportScanner {
+  constructor(workspaceRoot: string)
+  /**
+   * Finds the `import`, `export ... from` and `require` specifiers of the files of each project.
+   * The files whose hash did not change since the previous scan are not read again,
+   * and the files that are not in `project_file_map` anymore are forgotten
+   */
+  scan(projectFileMap: Record<string, Array<FileData>>): Array<ImportResult>
+}
+
+/** Hashes external dependencies using the lock file of the workspace */
+export declare class LockFileHasher {
+  constructor(workspaceRoot: string)
+  /**
+   * Hashes an external dependency and its transitive dependencies.
+   * Accepts package names (`react`), versioned names (`react@18.2.0`) and external node names (`npm:react`)
+   */
+  hashExternalDependency(name: string): string | null
+}
+
+/** Splits the data received on a socket into the frames of the binary daemon protocol, and decodes their messages */
+export declare class MessageDecoder {
+  constructor()
+  /** Returns the messages of the frames that were completed by `chunk`, in the order they were sent */
+  push(chunk: Buffer): Array<any>
+}
+
 export declare class NxCache {
   cacheDirectory: string
-  constructor(workspaceRoot: string, cachePath: string, dbConnection: ExternalObject<Connection>)
+  constructor(workspaceRoot: string, cachePath: string, dbConnection: ExternalObject<NxDbConnection>)
   get(hash: string): CachedResult | null
-  put(hash: string, terminalOutput: string, outputs: Array<string>, code: number): void
+  /**
+   * Whether the cache has an entry for each hash, in a single query. Entries are neither restored nor verified,
+   * and their access times are not updated, so this can run before a run starts to report what will be replayed
+   */
+  checkCacheHits(hashes: Array<string>): Record<string, boolean>
+  /**
+   * Stores the outputs and the terminal output of a task. The terminal output is indexed by `task_id` when it is
+   * given, to search it later. Returns the terminal output as it was stored, with the secrets of the env of Nx masked
+   */
+  put(hash: string, terminalOutput: string, outputs: Array<string>, code: number, taskId?: string | undefined | null): string
   applyRemoteCacheResults(hash: string, result: CachedResult): void
+  /**
+   * Searches the terminal outputs that were stored with a task id for the lines that match the regular expression
+   * `pattern`, e.g. to find when a warning first appeared. Matches are sorted by when their output was stored
+   */
+  searchTaskOutput(pattern: string, range?: TaskOutputSearchRange | undefined | null): Array<TaskOutputMatch>
   getTaskOutputsPath(hash: string): string
+  /**
+   * The cache entries that were found to be corrupted since this was last called.
+   * They were quarantined, and treated as if they were not in the cache
+   */
+  takeCorruptedEntries(): Array<CorruptedCacheEntry>
+  /**
+   * Restores the outputs of a cached task into the workspace.
+   * Files are hard linked from the cache when `hard_links` is true (`NX_CACHE_HARD_LINKS=true` by default),
+   * and copied otherwise. Directories are restored atomically when `atomic` is true (`NX_CACHE_ATOMIC_RESTORE=true` by default):
+   * they are restored next to the previous outputs, which they then replace at once, so an interrupted restore does not
+   * leave a mix of previous and restored files. Returns false when the task is not in the cache, or when its entry is corrupted
+   */
+  restore(hash: string, outputs: Array<string>, hardLinks?: boolean | undefined | null, atomic?: boolean | undefined | null): boolean
   copyFilesFromCache(cachedResult: CachedResult, outputs: Array<string>): void
+  /**
+   * Bundles the entries of `hashes` (every entry by default) with their metadata from the Nx database into a single
+   * archive at `destination` (e.g. `cache.tar.zst`), which `import_cache` can seed the cache of an other machine with.
+   * Returns the hashes that were exported, the ones that are not in the cache are skipped
+   */
+  exportCache(hashes: Array<string> | undefined | null, destination: string): Array<string>
+  /**
+   * Adds the entries of a bundle created by `export_cache` to the cache, with their metadata.
+   * Entries that are already in the cache are skipped, and entries whose outputs do not match their digest are not imported
+   */
+  importCache(archive: string): CacheImportResult
   removeOldCacheRecords(): void
+  /**
+   * Removes the entries that were not used for longer than the max age, and then the least recently used
+   * entries until the cache is smaller than the max size.
+   * Waits until no other process is writing or restoring entries, and keeps them from doing so while entries are removed
+   */
+  collectGarbage(options?: CacheGcOptions | undefined | null): CacheGcResult
+}
+
+export declare class NxHashCache {
+  constructor(db: ExternalObject<NxDbConnection>)
+  getHashes(): Array<PersistedHash>
+  /** Replaces the persisted hashes with the given hashes */
+  saveHashes(hashes: Array<PersistedHash>): void
+}
+
+export declare class NxOutputsSnapshots {
+  constructor(db: ExternalObject<NxDbConnection>, workspaceRoot: string)
+  /**
+   * Replaces the snapshot of the outputs with the outputs on disk, after the task with the hash
+   * `hash` ran or its outputs were restored
+   */
+  recordOutputs(hash: string, outputs: Array<string>): void
+  /**
+   * Compares the outputs on disk with their snapshot, whichever task wrote them last.
+   * Returns nothing when the outputs have no snapshot or they are the ones of the snapshot
+   */
+  detectDrift(outputs: Array<string>): OutputsDrift | null
+}
+
+/**
+ * A pool of the processes that run plugins in isolation, one worker per plugin.
+ * Requests are routed to the current process of the worker of a plugin, and the workers are checked in
+ * the background: a worker that crashes is restarted, and a worker with a request that runs for longer than
+ * the request timeout is killed and restarted, so a hung plugin fails its requests instead of stalling the graph
+ */
+export declare class NxPluginWorkerPool {
+  constructor(options: PluginWorkerPoolOptions)
+  /** Starts the worker of a plugin, or returns its worker when it is already running */
+  startWorker(key: string): PluginWorker
+  /** The workers that are running */
+  workers(): Array<PluginWorker>
+  /** Records that the request `tx` was sent to a worker, to detect it when it hangs */
+  requestStarted(workerId: number, tx: string): void
+  /** Records that the request `tx` of a worker finished */
+  requestFinished(workerId: number, tx: string): void
+  /** Calls `callback` when a worker hangs, crashes, or is restarted */
+  onEvent(callback: (event: PluginWorkerEvent) => void): void
+  /** Removes a worker from the pool once it was asked to shut down. It is killed when it does not exit in time */
+  stopWorker(workerId: number): void
+  /** Kills every worker and stops checking them */
+  shutdown(): void
 }
 
 export declare class NxTaskHistory {
-  constructor(db: ExternalObject<Connection>)
+  constructor(db: ExternalObject<NxDbConnection>)
   recordTaskRuns(taskRuns: Array<TaskRun>): void
   getFlakyTasks(hashes: Array<string>): Array<string>
+  /**
+   * Finds the targets that passed after failing with the same inputs within a time window,
+   * sorted from the flakiest
+   */
+  getFlakyTargets(window: FlakinessWindow): Array<FlakyTarget>
+  /** Exports the recorded task runs, oldest first */
+  exportTaskRuns(format: TaskRunsExportFormat, options?: TaskRunsExportOptions | undefined | null): string
+  /** Pushes the recorded task runs as spans to an OTLP/HTTP endpoint, e.g. `http://localhost:4318` */
+  pushTaskRuns(endpoint: string, options?: TaskRunsExportOptions | undefined | null, headers?: Record<string, string> | undefined | null): Promise<number>
   getEstimatedTaskTimings(targets: Array<TaskTarget>): Record<string, number>
+  /**
+   * The durations of the last executed runs of the targets, by `project:target[:configuration]`.
+   * Runs restored from the cache take no time, so they are not considered. Targets that never ran are not returned
+   */
+  getTaskDurationStats(targets: Array<TaskTarget>): Record<string, TaskDurationStats>
+}
+
+/**
+ * Reserves the ports of the tasks that run at the same time, so that two tasks never listen on the same port.
+ * Ports are only allocated when no process listens on them, and are allocated in turn, so a port that was just
+ * released is not allocated again while the process that listened on it may still hold it
+ */
+export declare class PortAllocator {
+  constructor(options?: PortAllocatorOptions | undefined | null)
+  /**
+   * Reserves a port for a task: its preferred port when it is free, or the next free port of the range.
+   * A task that already has a port keeps it
+   */
+  reserve(taskId: string, preferred?: number | undefined | null): number
+  /** Releases the port of a task, once it stopped listening on it */
+  release(taskId: string): void
+  /** The reserved ports, sorted */
+  reservations(): Array<PortReservation>
+}
+
+/**
+ * A client of the Nx remote cache HTTP protocol.
+ * Artifacts are zstd-compressed tarballs of the outputs of a task, with its terminal output and exit code
+ */
+export declare class RemoteCacheClient {
+  constructor(options: RemoteCacheOptions)
+  /**
+   * Downloads the artifact of a task into `<cache_directory>/<hash>`.
+   * Resolves with null when the remote cache does not have it
+   */
+  retrieve(hash: string, cacheDirectory: string): Promise<CachedResult | null>
+  /**
+   * Uploads the outputs in `<cache_directory>/<hash>` with the terminal output and exit code of the task.
+   * Resolves with false when the remote cache already has the artifact
+   */
+  store(hash: string, cacheDirectory: string, terminalOutput: string, code: number): Promise<boolean>
+  /**
+   * Whether the remote cache has the artifact of each hash, without downloading them.
+   * Asks for all of the hashes in a single request, or with a `HEAD` request per hash
+   * when the server does not implement `POST <url>/v1/cache/missing`
+   */
+  checkCacheHits(hashes: Array<string>): Promise<Record<string, boolean>>
+}
+
+/**
+ * A remote cache backed by a Bazel Remote Execution API (v2) server, e.g. BuildBarn or Buildfarm.
+ * The artifact of a task is a blob in the content addressable storage, that is an output of an action
+ * in the action cache. The action is derived from the hash of the task, and is never executed
+ */
+export declare class RemoteExecutionCacheClient {
+  constructor(options: RemoteExecutionCacheOptions)
+  /**
+   * Downloads the artifact of a task into `<cache_directory>/<hash>`.
+   * Resolves with null when the remote cache does not have it
+   */
+  retrieve(hash: string, cacheDirectory: string): Promise<CachedResult | null>
+  /** Uploads the outputs in `<cache_directory>/<hash>` with the terminal output and exit code of the task */
+  store(hash: string, cacheDirectory: string, terminalOutput: string, code: number): Promise<boolean>
+  /**
+   * Whether the remote cache has the artifact of each hash, without downloading them.
+   * The content addressable storage is asked for all of the artifacts at once, as it may have evicted some
+   * of the artifacts that the action cache still refers to
+   */
+  checkCacheHits(hashes: Array<string>): Promise<Record<string, boolean>>
 }
 
 export declare class RustPseudoTerminal {
@@ -49,19 +313,69 @@ export declare class RustPseudoTerminal {
   runCommand(command: string, commandDir?: string | undefined | null, jsEnv?: Record<string, string> | undefined | null, execArgv?: Array<string> | undefined | null, quiet?: boolean | undefined | null, tty?: boolean | undefined | null): ChildProcess
   /**
    * This allows us to run a pseudoterminal with a fake node ipc channel
-   * this makes it possible to be backwards compatible with the old implementation
+   * this makes it possible to be backwards compatible with the old implementation.
+   * The task and the processes it starts are limited to `resource_limits`
+   */
+  fork(id: string, forkScript: string, pseudoIpcPath: string, commandDir: string | undefined | null, jsEnv: Record<string, string> | undefined | null, execArgv: Array<string> | undefined | null, quiet: boolean, resourceLimits?: ResourceLimits | undefined | null): ChildProcess
+  /**
+   * Replays the recent output of a forked task from `fromOffset` (the start of the output by default),
+   * so consumers can re-attach to a running task. Returns nothing for tasks that were not forked
    */
-  fork(id: string, forkScript: string, pseudoIpcPath: string, commandDir: string | undefined | null, jsEnv: Record<string, string> | undefined | null, execArgv: Array<string> | undefined | null, quiet: boolean): ChildProcess
+  getTaskOutput(taskId: string, fromOffset?: number | undefined | null): TaskOutput | null
 }
 
 export declare class TaskDetails {
-  constructor(db: ExternalObject<Connection>)
+  constructor(db: ExternalObject<NxDbConnection>)
   recordTaskDetails(tasks: Array<HashedTask>): void
 }
 
 export declare class TaskHasher {
   constructor(workspaceRoot: string, projectGraph: ExternalObject<ProjectGraph>, projectFileMap: ExternalObject<ProjectFiles>, allWorkspaceFiles: ExternalObject<Array<FileData>>, tsConfig: Buffer, tsConfigPaths: Record<string, Array<string>>, options?: HasherOptions | undefined | null)
+  /**
+   * Updates the project graph and the files used for hashing after the workspace has changed.
+   * Only the cached hashes that include one of the changed files are invalidated, so the hasher can be reused between file changes
+   */
+  updateFiles(projectGraph: ExternalObject<ProjectGraph>, projectFileMap: ExternalObject<ProjectFiles>, allWorkspaceFiles: ExternalObject<Array<FileData>>, tsConfig: Buffer, tsConfigPaths: Record<string, Array<string>>, changedFiles: Array<string>): void
+  /** Exports the cached hashes of workspace file sets and files without hashes so they can be persisted */
+  exportCache(): Array<PersistedHash>
+  /**
+   * Restores hashes exported by a previous process. Only hashes whose fingerprint still matches the
+   * current files of the file set (or the file on disk) are restored. Returns the number of restored hashes
+   */
+  importCache(persisted: Array<PersistedHash>): number
   hashPlans(hashPlans: ExternalObject<Record<string, Array<HashInstruction>>>, jsEnv: Record<string, string>): NapiDashMap
+  /**
+   * Returns the hashes of directories of the workspace, computed from the names and hashes of every file below them
+   * (the hashes of the file sets matching every file of a directory). A directory keeps its hash until a file
+   * below it changes, so caches can tell which parts of the workspace changed. Directories without files are left out
+   */
+  hashDirectories(directories: Array<string>): Record<string, string>
+  /**
+   * Resolves the globs, matched files and file hashes behind every input in the hash plan of a task,
+   * so tooling can show exactly why two hashes of the same task differ
+   */
+  getHashDetails(taskId: string, hashPlans: ExternalObject<Record<string, Array<HashInstruction>>>, jsEnv: Record<string, string>): Array<HashInputDetails>
+}
+
+/**
+ * Runs the tasks of a task graph again when the files that they read change.
+ * The changes reported by the watcher are matched against the inputs of the hash plans of the tasks, so only the
+ * tasks whose inputs changed run again, with the tasks that depend on them. Changes are debounced, and a run that is
+ * still in progress when more files change is cancelled: its tasks are killed and run again with the new changes
+ */
+export declare class TaskWatchRunner {
+  constructor(projectGraph: ExternalObject<ProjectGraph>, taskGraph: TaskGraph, hashPlans: ExternalObject<Record<string, Array<HashInstruction>>>, commands: Record<string, TaskCommand>, options?: TaskWatchOptions | undefined | null)
+  /** Calls `callback` when a run starts, finishes or is cancelled, and when its tasks start and finish */
+  onEvent(callback: (event: TaskWatchEvent) => void): void
+  /**
+   * Invalidates the tasks whose inputs include one of the changed files, and the tasks that depend on them.
+   * They run once no more files changed for the debounce duration. Returns the invalidated tasks
+   */
+  filesChanged(events: Array<WatchEvent>): Array<string>
+  /** Runs tasks and the tasks that depend on them after the debounce duration, e.g. every task when watching starts */
+  runTasks(taskIds: Array<string>): void
+  /** Cancels the run that is in progress and stops running the tasks again */
+  stop(): void
 }
 
 export declare class Watcher {
@@ -72,51 +386,401 @@ export declare class Watcher {
    * * .git/
    * * node_modules/
    * * .nx/
+   *
+   * Additional roots (absolute or relative to the origin) are watched as well,
+   * events for files in them have paths relative to the origin (e.g. `../shared-lib/index.ts`)
    */
-  constructor(origin: string, additionalGlobs?: Array<string> | undefined | null, useIgnore?: boolean | undefined | null)
-  watch(callback: (err: string | null, events: WatchEvent[]) => void): void
+  constructor(origin: string, additionalGlobs?: Array<string> | undefined | null, useIgnore?: boolean | undefined | null, additionalRoots?: Array<string> | undefined | null)
+  /**
+   * Watches the roots for changes.
+   * When some changes could be missed (e.g. because the watch limit of the OS was reached), the roots that cannot be
+   * fully watched are scanned for changes periodically instead, and `on_warning` is called with the reason
+   */
+  watch(callback: (err: string | null, events: WatchEvent[]) => void, options?: WatchOptions | undefined | null, onWarning?: (warning: WatcherWarning) => void): void
   stop(): Promise<void>
 }
 
 export declare class WorkspaceContext {
   workspaceRoot: string
-  constructor(workspaceRoot: string, cacheDir: string)
+  /**
+   * Symlinks are followed unless another `symlinkPolicy` is given.
+   * When files are discovered with git, symlinks are always recorded, because git stores them as links
+   */
+  constructor(workspaceRoot: string, cacheDir: string, symlinkPolicy?: SymlinkPolicy | undefined | null)
   getWorkspaceFiles(projectRootMap: Record<string, string>): NxWorkspaceFiles
-  glob(globs: Array<string>, exclude?: Array<string> | undefined | null): Array<string>
-  hashFilesMatchingGlob(globs: Array<string>, exclude?: Array<string> | undefined | null): string
-  incrementalUpdate(updatedFiles: Array<string>, deletedFiles: Array<string>): Record<string, string>
+  /**
+   * Publishes the workspace files to `directory`, where clients read them with `readSharedWorkspaceFiles`
+   * instead of receiving them over the daemon socket. The files are only published again once they changed,
+   * or when they are published for other projects. Returns the generation of the published files
+   */
+  publishWorkspaceFiles(directory: string, projectRootMap: Record<string, string>): number
+  glob(globs: Array<string>, exclude?: Array<string> | undefined | null, caseInsensitive?: boolean | undefined | null): Array<string>
+  /**
+   * Matches files in batches instead of returning every match at once.
+   * Useful for large workspaces, where transferring all matches in a single array is expensive
+   */
+  globStream(globs: Array<string>, exclude?: Array<string> | undefined | null, batchSize?: number | undefined | null, caseInsensitive?: boolean | undefined | null): GlobStream
+  hashFilesMatchingGlob(globs: Array<string>, exclude?: Array<string> | undefined | null, caseInsensitive?: boolean | undefined | null): string
+  incrementalUpdate(updatedFiles: Array<string>, deletedFiles: Array<string>, movedFiles?: Record<string, string> | undefined | null): Record<string, string>
   updateProjectFiles(projectRootMappings: ProjectRootMappings, projectFiles: ExternalObject<ProjectFiles>, globalFiles: ExternalObject<Array<FileData>>, updatedFiles: Record<string, string>, deletedFiles: Array<string>): UpdatedWorkspaceFiles
+  /**
+   * Returns the files that were created, updated or deleted since the previous call, and takes a new snapshot.
+   * The first call returns every file of the workspace as created
+   */
+  getFileMapDelta(): FileMapDelta
   allFileData(): Array<FileData>
   getFilesInDirectory(directory: string): Array<string>
+  /**
+   * Returns the files of a project without scanning every file of the workspace.
+   * The files of the projects nested in the root are left out, like in the project file map,
+   * once the project roots are known from `getWorkspaceFiles`
+   */
+  getFilesForProject(root: string): Array<FileData>
 }
 
+/**
+ * The projects and the external nodes that are affected by changes to `changed_files`.
+ * A project is touched when one of its files changed, or when it is in `touched_projects`
+ * (projects found by the other locators of `nx affected`), and the nodes that depend on a
+ * touched node, directly or not, are affected as well
+ */
+export declare export function affectedProjects(changedFiles: Array<string>, projectGraph: ExternalObject<ProjectGraph>, touchedProjects?: Array<string> | undefined | null): Array<string>
+
+/** How ANSI escape sequences are handled in the output of a command */
+export declare const enum AnsiMode {
+  /** The output is forwarded as is */
+  preserve = 'preserve',
+  /** Colors and styles are kept, cursor movements and other control sequences are removed */
+  normalize = 'normalize',
+  /** Every escape sequence is removed */
+  strip = 'strip'
+}
+
+export declare const enum ArchiveFormat {
+  tarGz = 'tarGz',
+  zip = 'zip'
+}
+
+export interface ArchiveOptions {
+  /** Inferred from the extension of the archive (.tar.gz, .tgz or .zip) by default */
+  format?: ArchiveFormat
+  /** The directory that the entries are archived under, e.g. `package` for npm tarballs */
+  prefix?: string
+  /**
+   * The modification time of every entry, in seconds since the unix epoch.
+   * Defaults to SOURCE_DATE_EPOCH, or to 1985-10-26T08:15:00Z like npm
+   */
+  mtime?: number
+}
+
+export interface BlobStorageCacheOptions {
+  /** Where artifacts are stored: `s3://<bucket>/<prefix>`, `gs://<bucket>/<prefix>` or `azure://<container>/<prefix>` */
+  url: string
+  /** The region of an S3 bucket, `AWS_REGION` by default */
+  region?: string
+  /** A custom endpoint, e.g. of S3 compatible storage like MinIO or of an Azure storage emulator */
+  endpoint?: string
+  /** How many parts of an artifact are uploaded at the same time, 4 by default */
+  concurrency?: number
+  /** How many times failed requests are retried, 3 by default */
+  retries?: number
+}
+
+/** A node of the project graph, as the module boundaries see it */
+export interface BoundaryNode {
+  tags?: Array<string>
+  /** The package of an external node, which is not set for the projects of the workspace */
+  packageName?: string
+}
+
+export interface BoundaryViolation {
+  file: string
+  specifier: string
+  sourceProject: string
+  targetProject: string
+  /**
+   * The id of the message of the lint rule that reports the violation:
+   * `projectWithoutTagsCannotHaveDependencies`, `bannedExternalImportsViolation`, `onlyTagsConstraintViolation`,
+   * `emptyOnlyTagsConstraintViolation` or `notTagsConstraintViolation`
+   */
+  messageId: string
+  /** The source tags of the violated constraint */
+  sourceTags: Array<string>
+  /** The tags of the violated constraint that the target is checked against */
+  tags: Array<string>
+  /** The paths of dependencies from the target to the projects with banned tags, for `notTagsConstraintViolation` */
+  paths: Array<Array<string>>
+}
+
+/**
+ * Builds the project graph of the projects and of the files of each project.
+ * The dependencies are the imports of the files that resolve to other projects or to external nodes,
+ * and the explicit dependencies (from `package.json` files, `implicitDependencies`, or plugins)
+ */
+export declare export function buildProjectGraph(workspaceRoot: string, projects: Record<string, Project>, projectFileMap: Record<string, Array<string>>, externalNodes?: Record<string, ExternalNode> | undefined | null, explicitDependencies?: Array<ExplicitDependency> | undefined | null, tsConfigPaths?: Record<string, Array<string>> | undefined | null): ProjectGraph
+
 export interface CachedResult {
   code: number
   terminalOutput: string
   outputsPath: string
+  /**
+   * The version of the hashing algorithm of the hash the entry was cached with.
+   * Unknown for entries that were cached before it was recorded, and for remote entries
+   */
+  hashVersion?: number
 }
 
-export declare export function connectToNxDb(cacheDir: string, nxVersion: string): ExternalObject<Connection>
+export interface CacheGcOptions {
+  /**
+   * Entries that were not used for longer than this are removed, e.g. `12h` or `7d`.
+   * `NX_CACHE_MAX_AGE`, or 7 days by default
+   */
+  maxAge?: string
+  /**
+   * The least recently used entries are removed until the cache is smaller than this, e.g. `512MB` or `10GB`.
+   * `NX_CACHE_MAX_SIZE` by default, the size of the cache is not limited when neither is set
+   */
+  maxSize?: string
+}
+
+export interface CacheGcResult {
+  removedEntries: number
+  /** The size of the removed entries, in bytes */
+  freedBytes: number
+  /** The size of the remaining entries, in bytes */
+  remainingBytes: number
+}
+
+/** The task outputs recorded in the cache */
+export interface CacheHealth {
+  entries: number
+  /** The size of the cached outputs, in bytes */
+  size: number
+}
+
+export interface CacheImportResult {
+  imported: Array<string>
+  /** The entries that were already in the cache */
+  skipped: Array<string>
+  /** The entries whose outputs did not match their digest, which were not imported */
+  corrupted: Array<string>
+}
+
+/**
+ * Checks the imports between the projects of a workspace against the dependency constraints of
+ * the `@nx/enforce-module-boundaries` lint rule, and returns the violations in the order of the imports.
+ * Like the rule, at most one violation is reported per import: the first constraint of the source project
+ * that is violated. `dependencies` are the targets of the dependencies of the nodes in `nodes` by their names,
+ * which are followed to find the projects with the tags that a project can not depend on
+ */
+export declare export function checkModuleBoundaries(nodes: Record<string, BoundaryNode>, dependencies: Record<string, Array<string>>, imports: Array<ProjectImport>, constraints: Array<DependencyConstraint>): Array<BoundaryViolation>
+
+export declare const enum CompressionAlgorithm {
+  zstd = 'zstd',
+  brotli = 'brotli'
+}
+
+export interface CompressionOptions {
+  /** Defaults to zstd */
+  algorithm?: CompressionAlgorithm
+  /**
+   * The level of the artifacts written to the local cache and of the messages of the daemon.
+   * Chosen from the throughput of the disk by default
+   */
+  localLevel?: number
+  /** The level of the artifacts uploaded to remote caches. Chosen from the throughput of the network by default */
+  remoteLevel?: number
+}
+
+/** A problem of a configuration file */
+export interface ConfigDiagnostic {
+  file: string
+  /** The JSON pointer of the invalid member, which is empty for the root and for syntax errors */
+  pointer: string
+  message: string
+  /** Where the invalid member is in the file, or its closest parent */
+  position?: JsonPosition
+}
+
+/** Sets the algorithm that data is compressed with, and the levels that override the adaptive ones */
+export declare export function configureCompression(options: CompressionOptions): void
+
+/** Connects to the database of the workspace, and migrates it to the schema of this version of Nx */
+export declare export function connectToNxDb(cacheDir: string, nxVersion: string, workspaceRoot?: string | undefined | null): ExternalObject<NxDbConnection>
 
 export declare export function copy(src: string, dest: string): void
 
+export interface CorruptedCacheEntry {
+  hash: string
+  /** The digest that was recorded when the entry was cached */
+  expectedDigest: string
+  actualDigest: string
+  /** Where the outputs and the terminal output of the entry were moved to */
+  quarantinePath: string
+}
+
+/**
+ * Archives the outputs of `directory` into a tar.gz or a zip archive at `destination`, which is the same for the same
+ * outputs on every machine: the entries are sorted, and their modification times, owners and permissions are normalized.
+ * Returns the names of the entries that were archived
+ */
+export declare export function createArchive(directory: string, outputs: Array<string>, destination: string, options?: ArchiveOptions | undefined | null): Array<string>
+
+/**
+ * Reads the ignore files of a workspace, so JS can match paths exactly like the native walker does
+ * (e.g. to infer projects only from files that are not ignored)
+ */
+export declare export function createIgnoreMatcher(root: string): IgnoreMatcher
+
+/** A report of how a long running process (like the daemon) is doing */
+export interface DaemonDiagnostics {
+  watcher: WatcherHealth
+  db: DbHealth
+  /** Only reported when the database could be queried */
+  cache?: CacheHealth
+  /**
+   * The resident memory of the process, in bytes.
+   * This is the peak resident memory on platforms other than Linux, and it is not measured on Windows
+   */
+  memory?: number
+  /** The last errors and warnings that were logged, the oldest first */
+  recentErrors: Array<LoggedError>
+  /** What is not working as it should. The process should be restarted when there is any */
+  degradations: Array<string>
+}
+
+/**
+ * Checks the health of the file watcher, the database and the cache, and reports what has degraded.
+ * The memory of the process is reported as a degradation when it is over `max_memory` bytes
+ */
+export declare export function daemonDiagnostics(dbConnection?: ExternalObject<NxDbConnection> | undefined | null, maxMemory?: number | undefined | null): DaemonDiagnostics
+
+/** The state of the Nx database, as seen by this process */
+export interface DbHealth {
+  connected: boolean
+  /** The size of the database, in bytes */
+  size?: number
+  /** Why the database could not be queried */
+  error?: string
+}
+
+/** A dependency constraint of the `@nx/enforce-module-boundaries` lint rule */
+export interface DependencyConstraint {
+  sourceTag?: string
+  /** The constraint applies to the projects that have all of these tags, instead of `source_tag` */
+  allSourceTags?: Array<string>
+  onlyDependOnLibsWithTags?: Array<string>
+  notDependOnLibsWithTags?: Array<string>
+  allowedExternalImports?: Array<string>
+  bannedExternalImports?: Array<string>
+}
+
 export interface DepsOutputsInput {
   dependentTasksOutputFiles: string
   transitive?: boolean
 }
 
+/**
+ * Finds the tasks that listen on the same port and can run at the same time: tasks that do not depend on each other,
+ * and continuous tasks with the tasks that depend on them, since they keep running next to them.
+ * Ports that another process already listens on are conflicts as well, even with a single task
+ */
+export declare export function detectPortConflicts(taskGraph: TaskGraph, ports: Record<string, number>, continuous?: Array<string> | undefined | null): Array<PortConflict>
+
+/**
+ * Compares two hash computations of a task (as returned by `TaskHasher.getHashDetails`)
+ * and reports which inputs, files, environment variables and runtime inputs caused the hashes to differ
+ */
+export declare export function diffTaskHashes(oldPlan: Array<HashInputDetails>, newPlan: Array<HashInputDetails>): TaskHashDiff
+
+/**
+ * Writes the logs of the native module as JSON lines to `<workspace_data_directory>/logs/<file_name>`,
+ * rotating the file once it grows too large.
+ * Enabling file logging again switches to the new file
+ */
+export declare export function enableFileLogging(workspaceDataDirectory: string, options?: FileLoggingOptions | undefined | null): void
+
+/**
+ * Encodes a message as a frame of the binary daemon protocol: the length of the payload, followed by the message
+ * as MessagePack. Messages are passed serialized, so they are encoded exactly like `JSON.stringify` serializes them,
+ * and serialized responses (like the project graph) are transcoded without being parsed in JS.
+ * With `compress`, large payloads are compressed at the level of the local cache
+ */
+export declare export function encodeMessage(message: string, compress?: boolean | undefined | null): Buffer
+
+/** How the lines of the formatted files end */
+export declare const enum EndOfLine {
+  lf = 'lf',
+  crlf = 'crlf'
+}
+
+/** The environment Nx runs in, found once per process */
+export interface EnvironmentFingerprint {
+  machineId: string
+  /** The OS, like `linux`, `macos` or `windows` */
+  os: string
+  /** The CPU architecture, like `x86_64` or `aarch64` */
+  arch: string
+  isCi: boolean
+  /** The CI provider (e.g. `github-actions`), when it is one that is known */
+  ciProvider?: string
+  /** Whether Nx runs in a container (e.g. Docker, Podman or a Kubernetes pod) */
+  isContainer: boolean
+  /** The version of Node.js, like `20.11.1` */
+  nodeVersion: string
+}
+
 export interface EnvironmentInput {
   env: string
 }
 
+/**
+ * How long running the tasks of a graph on `parallel` threads is estimated to take in milliseconds, when no task
+ * fails, with the estimated durations of the tasks. Used to print how long a run will take before it starts
+ */
+export declare export function estimateTaskGraphDuration(taskGraph: TaskGraph, estimatedDurations: Record<string, number>, parallel: number): number
+
+/** How the events of a batch are coalesced into a single event per path */
+export declare const enum EventCoalescing {
+  /** Delete > Create > Modify, for consumers that process batches of changes */
+  batched = 'batched',
+  /** The latest event of a path wins, for consumers that need to follow every change */
+  lastWriteWins = 'lastWriteWins'
+}
+
 export declare const enum EventType {
   delete = 'delete',
   update = 'update',
-  create = 'create'
+  create = 'create',
+  moved = 'moved'
+}
+
+/** Why and how a process exited */
+export interface ExitDetails {
+  /** The exit code, when the process exited by itself */
+  code?: number
+  /** The name of the signal that terminated the process (e.g. `SIGKILL`), or its number when it is not known */
+  signal?: string
+  /** Whether the signal dumps the core of the process by default (e.g. `SIGSEGV`) */
+  coreDumpSignal: boolean
+  /**
+   * Whether the process was killed by the kernel because it ran out of memory.
+   * Only detected on Linux, from the OOM kills of the cgroup of the process
+   */
+  oomKilled: boolean
+  /** Whether the process went over the memory limit of its task, see `ResourceLimits` */
+  memoryLimitExceeded: boolean
+  /** The resources used by the process and the processes it started */
+  metrics?: TaskMetrics
 }
 
 export declare export function expandOutputs(directory: string, entries: Array<string>): Array<string>
 
+export interface ExplicitDependency {
+  source: string
+  target: string
+}
+
 export interface ExternalDependenciesInput {
   externalDependencies: Array<string>
 }
@@ -127,32 +791,178 @@ export interface ExternalNode {
   hash?: string
 }
 
+/**
+ * Extracts an archive created by `create_archive` into `destination`. Entries that would be outside of it are skipped.
+ * Returns the names of the entries that were extracted
+ */
+export declare export function extractArchive(source: string, destination: string, format?: ArchiveFormat | undefined | null): Array<string>
+
 export interface FileData {
   file: string
   hash: string
 }
 
+/** A change of a file that is written with `writeFilesAtomic` */
+export interface FileEdit {
+  path: string
+  /** The new content of the file. The file, or the directory, is deleted when there is none */
+  content?: Buffer
+  /** The permissions of the file (e.g. `0o755`), the file keeps its permissions when there are none */
+  mode?: number
+}
+
+export interface FileLoggingOptions {
+  /** The name of the log file in the logs directory, defaults to `daemon.log` */
+  fileName?: string
+  /** The directives of the logs to write (e.g. `info,nx::native::watch=trace`), defaults to `info` */
+  filter?: string
+  /** The size in bytes after which the log file is rotated, defaults to 10MB */
+  maxFileSize?: number
+  /** How many log files are kept, including the current one, defaults to 5 */
+  maxFiles?: number
+}
+
 export interface FileMap {
   projectFileMap: ProjectFiles
   nonProjectFiles: Array<FileData>
 }
 
+/**
+ * The files that changed in the workspace since the previous `FileMapDelta` was taken.
+ * Deleted files have the last hash that was known for them
+ */
+export interface FileMapDelta {
+  created: Array<FileData>
+  updated: Array<FileData>
+  deleted: Array<FileData>
+}
+
 export interface FileSetInput {
   fileset: string
+  /** Match the globs of the file set case insensitively (e.g. `Dockerfile` also matches `dockerfile`) */
+  caseInsensitive?: boolean
+}
+
+/** What a project is filtered by */
+export interface FilteredProject {
+  root: string
+  tags?: Array<string>
 }
 
+/**
+ * The names of the projects that match `patterns`, sorted, like the patterns of `--projects` and `--exclude`.
+ * Each pattern is an expression of project patterns, e.g. `tag:api && !(tag:experimental || apps/legacy/*)`:
+ * - `name:<glob>`, `tag:<glob>` and `directory:<glob>` match the names, the tags and the roots of projects
+ * - a pattern without a type matches the project names, or the project roots when no name matches
+ * - `&&`, `||`, `!` and parentheses combine patterns, `!` binds tighter than `&&`, which binds tighter than `||`
+ *
+ * The patterns are applied in order: the projects that match a pattern are added to the result,
+ * unless the pattern is a negation (`!tag:experimental`), which removes the projects that match it instead.
+ * When the first pattern is a negation, it removes the projects from all of the projects
+ */
+export declare export function filterProjects(patterns: Array<string>, projects: Record<string, FilteredProject>): Array<string>
+
+/**
+ * Every elementary cycle of the dependencies between the projects of the graph, found with Johnson's algorithm.
+ * A cycle is the path of the projects it goes through, starting from the first of them in alphabetical order:
+ * `["a", "b", "c"]` is `a -> b -> c -> a`.
+ * The number of cycles can grow exponentially with the size of the graph, so at most `limit` cycles are returned
+ */
+export declare export function findCycles(projectGraph: ExternalObject<ProjectGraph>, limit?: number | undefined | null): Array<Array<string>>
+
+/** A port that no process listens on, picked by the OS */
+export declare export function findFreePort(host?: string | undefined | null): number
+
 export declare export function findImports(projectFileMap: Record<string, Array<string>>): Array<ImportResult>
 
+export interface FlakinessWindow {
+  /** Runs that started before this, in milliseconds since the epoch, are not considered */
+  since: number
+  /** Runs that started after this are not considered, defaults to now */
+  until?: number
+  /** Targets that flipped less often than this are not returned, defaults to 0 */
+  minFlipRate?: number
+}
+
+export interface FlakyTarget {
+  project: string
+  target: string
+  configuration?: string
+  /** The runs of the target within the window */
+  runs: number
+  /** The runs that succeeded right after a failed run with the same hash */
+  flips: number
+  /** `flips` divided by `runs` */
+  flipRate: number
+  /** When the last flip started, in milliseconds since the epoch */
+  lastFlip: number
+}
+
 export declare export function getBinaryTarget(): string
 
+/**
+ * The machine id, OS, architecture, CI provider, container and Node.js version of the environment, in one call.
+ * Nothing is spawned to find them, and everything but the Node.js version is only found once per process
+ */
+export declare export function getEnvironmentFingerprint(): EnvironmentFingerprint
+
 /**
  * Expands the given outputs into a list of existing files.
  * This is used when hashing outputs
  */
 export declare export function getFilesForOutputs(directory: string, entries: Array<string>): Array<string>
 
+/** The process that holds the lock at `path` exclusively, shared holders are not recorded */
+export declare export function getLockHolder(path: string): LockHolder | null
+
+/**
+ * Resolves the environment of every task from the environment of Nx, by task id.
+ *
+ * The variables that Nx loaded from the `.env`, `.local.env` and `.env.local` files of the workspace root when it
+ * started are removed first, so that they are layered like the other files. Then, when `load_dot_env_files` is set,
+ * the .env files of the task are loaded. A variable is taken from the first of these that sets it:
+ *
+ * 1. the environment of Nx
+ * 2. the files of the project of the task, then the files of the workspace root, each in this order:
+ *    1. `.env.[target].[configuration].local`, `.env.[target].[configuration]`, `.env.[configuration].local`,
+ *       `.env.[configuration]`, `.[target].[configuration].local.env`, `.[target].[configuration].env`,
+ *       `.[configuration].local.env` and `.[configuration].env`, when the task has a configuration
+ *    2. `.env.[target].local`, `.env.[target]`, `.[target].local.env` and `.[target].env`
+ *    3. `.env.local`, `.local.env` and `.env` in the project, `.local.env`, `.env.local` and `.env` in the workspace root
+ *
+ * References to variables in the values of the files are expanded with the variables that are already set,
+ * then with the variables of the same file. Every file is read once for all the tasks
+ */
+export declare export function getTaskEnvs(workspaceRoot: string, tasks: Array<Task>, env: Record<string, string>, loadDotEnvFiles: boolean): Record<string, Record<string, string>>
+
+/**
+ * The priority of every task of a graph: the length of the longest chain of tasks that waits for it, including itself.
+ * The orchestrator of `nx run` starts the ready tasks on the critical path first with it
+ */
+export declare export function getTaskGraphPriorities(taskGraph: TaskGraph, estimatedDurations?: Record<string, number> | undefined | null): Record<string, number>
+
 export declare export function getTransformableOutputs(outputs: Array<string>): Array<string>
 
+/**
+ * The version of the hashing algorithm, mixed into the hash of every task and recorded with every cache entry.
+ * It has to be bumped whenever a change makes the same inputs hash differently, so that the results cached
+ * with the previous algorithm (and the persisted hashes of file sets) are not reused
+ */
+export const HASH_VERSION: number
+
+/**
+ * The algorithms the hash of a task can be computed with.
+ * Some remote caches address artifacts by a cryptographic digest, which `blake3` and `sha256` provide
+ */
+export declare const enum HashAlgorithm {
+  /** The decimal 64 bits xxh3 hash, like `hash` */
+  xxh3 = 'xxh3',
+  /** The hexadecimal 256 bits blake3 digest */
+  blake3 = 'blake3',
+  /** The hexadecimal 256 bits sha256 digest */
+  sha256 = 'sha256'
+}
+
 export declare export function hashArray(input: Array<string>): string
 
 export interface HashDetails {
@@ -167,12 +977,58 @@ export interface HashedTask {
   configuration?: string
 }
 
+/**
+ * Public NAPI error codes of the task hasher that are for Node.
+ * `InvalidInput` is a mistake in the inputs of the workspace (e.g. a glob that does not parse),
+ * the other codes are failures of the hasher
+ */
+export declare const enum HasherErrors {
+  InvalidInput = 'InvalidInput',
+  Io = 'Io',
+  Internal = 'Internal'
+}
+
 export interface HasherOptions {
   selectivelyHashTsConfig: boolean
+  /** Hash file contents from disk when a file does not have a hash (e.g. untracked files) */
+  hashMissingFiles?: boolean
+  /**
+   * Apply negated file set globs in order, so later globs override earlier ones (like .gitignore).
+   * Defaults to excluding every file matched by a negated glob, regardless of its position
+   */
+  orderedNegatedGlobs?: boolean
+  /**
+   * The maximum time in milliseconds a runtime input command can take before hashing fails.
+   * Defaults to no timeout
+   */
+  runtimeTimeout?: number
+  /** Hash external dependencies along with their transitive dependencies from the workspace lock file */
+  hashExternalsFromLockFile?: boolean
+  /**
+   * Mixed into the hash of every task (e.g. the `hashSalt` of nx.json), so changing it intentionally
+   * invalidates every cached result, locally and in remote caches
+   */
+  hashSalt?: string
+  /** Mix the OS and the CPU architecture into the hash of every task, so results are not shared between platforms */
+  includePlatform?: boolean
+  /**
+   * The algorithm of the hash of every task, computed from the hashes of its inputs. Defaults to xxh3.
+   * The inputs themselves (e.g. the files of the workspace) are still identified by their xxh3 hashes
+   */
+  hashAlgorithm?: HashAlgorithm
 }
 
 export declare export function hashFile(file: string): string | null
 
+export interface HashInputDetails {
+  input: string
+  value: string
+  /** The globs after resolving `{workspaceRoot}` and `{projectRoot}`. Empty for inputs that are not file sets */
+  globs: Array<string>
+  /** The matched files along with the hashes that went into the input hash */
+  files: Array<FileData>
+}
+
 export interface InputsInput {
   input: string
   dependencies?: boolean
@@ -181,6 +1037,89 @@ export interface InputsInput {
 
 export const IS_WASM: boolean
 
+/**
+ * A parsed JSON document with the locations of its members by their JSON pointers,
+ * e.g. `/targets/build/options/main`, where the root is the empty pointer
+ */
+export interface JsonDocument {
+  value: any
+  locations: Record<string, JsonLocation>
+}
+
+/** Where a member of a JSON document is in its file */
+export interface JsonLocation {
+  /** The span of the key of the member, which the root and the items of arrays do not have */
+  key?: JsonSpan
+  value: JsonSpan
+}
+
+/** A position in a JSON file. Lines and columns start at 1, columns count characters */
+export interface JsonPosition {
+  line: number
+  column: number
+  /** In bytes from the start of the file */
+  offset: number
+}
+
+export interface JsonSpan {
+  start: JsonPosition
+  /** The position after the last character of the span */
+  end: JsonPosition
+}
+
+/** Whether no process listens on a port of a host, or of any IPv4 and IPv6 address without a host */
+export declare export function isPortFree(port: number, host?: string | undefined | null): boolean
+
+/**
+ * Kills a process and all of its descendants, so that workers started by a task (e.g. by webpack or vitest) do not outlive it.
+ * `signal` is the name of the signal to send (`SIGTERM` by default), processes are always terminated on Windows
+ */
+export declare export function killTree(pid: number, signal?: string | undefined | null): void
+
+/** The process that holds a lock exclusively */
+export interface LockHolder {
+  pid: number
+  /** When the lock was acquired, in milliseconds since the unix epoch */
+  acquiredAt: number
+}
+
+/** An error or a warning logged by the native module */
+export interface LoggedError {
+  /** `ERROR` or `WARN` */
+  level: string
+  /** The module that logged it, e.g. `nx::native::watch::watcher` */
+  target: string
+  message: string
+  /** When it was logged, in milliseconds since the unix epoch */
+  timestamp: number
+}
+
+export interface MaterializedProjects {
+  /** The JSON of the projects by their roots, in the order of the roots that were given */
+  projects: string
+  /** The source map keys of the properties that were set by target defaults, by the roots of the projects */
+  targetDefaultProperties: Record<string, Array<string>>
+  conflicts: Array<TargetDefaultConflict>
+}
+
+/**
+ * Materializes the targets of the projects, which are the targets of their project files merged with their
+ * inferred targets, by their roots:
+ * - the `command` syntactic sugar is expanded to the `nx:run-commands` executor
+ * - the `{workspaceRoot}`, `{projectRoot}` and `{projectName}` tokens are replaced in the options and configurations
+ * - the target defaults of `nx.json` are merged into the targets, the ones for the executor of a target first,
+ *   then the ones for its name, then the longest glob that matches its name
+ * - the targets that do nothing and depend on nothing are removed, the ones that only depend on other targets use `nx:noop`
+ *
+ * Target defaults override the properties that are not set by core plugins according to `source_maps`, and skip the
+ * targets of other executors or commands, which are reported as conflicts. Keys keep the order of the given objects.
+ *
+ * The projects and the target defaults are passed as JSON, like `JSON.stringify` of the projects by their roots, which
+ * is faster to transfer than objects, leaves out their `undefined` properties, which have no value in JSON, and keeps
+ * the order of their keys
+ */
+export declare export function materializeProjectTargets(projects: string, targetDefaults: string | undefined | null, sourceMaps: Record<string, Record<string, Array<string | undefined | null>>>): MaterializedProjects
+
 /** Stripped version of the NxJson interface for use in rust */
 export interface NxJson {
   namedInputs?: Record<string, Array<JsInputs>>
@@ -198,6 +1137,146 @@ export interface NxWorkspaceFilesExternals {
   allWorkspaceFiles: ExternalObject<Array<FileData>>
 }
 
+/**
+ * How the outputs of a task on disk differ from the outputs that were last written by a task.
+ * The paths are relative to the workspace root
+ */
+export interface OutputsDrift {
+  /** The hash of the last task that wrote the outputs */
+  hash: string
+  /** The files that were changed since the last run */
+  modified: Array<string>
+  /** The files that were created since the last run */
+  added: Array<string>
+  /** The files that were removed since the last run */
+  removed: Array<string>
+}
+
+export interface OutputsVerification {
+  /** The files of the cached outputs that are not in the workspace, relative to the workspace root */
+  missing: Array<string>
+  /** The files whose size in the workspace is not their size in the cache */
+  mismatched: Array<string>
+}
+
+/**
+ * Maps file paths to the projects that own them: the project with the deepest root that contains a path,
+ * so the files of a project nested in an other project belong to the nested project.
+ * `project_roots` are the roots of the projects by their names, and the paths are relative to the workspace root,
+ * or absolute paths in `workspace_root`. Paths that no project owns (e.g. the files at the root of
+ * a workspace without a root project, or the paths outside of the workspace) are not returned
+ */
+export declare export function owningProjects(paths: Array<string>, projectRoots: Record<string, string>, workspaceRoot?: string | undefined | null): Record<string, string>
+
+/**
+ * Resolves the outputs of a task against the workspace and copies them to `destination` in parallel.
+ * When `compress` is true, the outputs are packed into a zstd-compressed tarball at `destination` instead.
+ * Returns the outputs that were found
+ */
+export declare export function packOutputs(workspaceRoot: string, outputs: Array<string>, destination: string, compress?: boolean | undefined | null): Array<string>
+
+/**
+ * Parses JSON with comments and trailing commas (like `project.json`, `nx.json` and `tsconfig.json` files)
+ * with the locations of its keys and values, so the errors of configuration files can point at their lines.
+ * Syntax errors are reported with their line and column in `file`
+ */
+export declare export function parseJsonWithLocations(contents: string, file?: string | undefined | null): JsonDocument
+
+/**
+ * A cached hash that can be persisted between processes.
+ * The fingerprint identifies the files the hash was computed from
+ */
+export interface PersistedHash {
+  cacheKey: string
+  hash: string
+  fingerprint: string
+}
+
+/** A plugin worker process of the pool */
+export interface PluginWorker {
+  id: number
+  /** The plugin the worker runs requests for */
+  key: string
+  pid: number
+  /** The socket the worker listens on, which changes when the worker is restarted */
+  socketPath: string
+  /** How many times the worker was restarted */
+  respawns: number
+}
+
+export interface PluginWorkerEvent {
+  kind: PluginWorkerEventKind
+  worker: PluginWorker
+  /** The requests of the worker that did not finish */
+  requests: Array<string>
+  /** The exit code of the process that exited, when it exited with one */
+  exitCode?: number
+}
+
+export declare const enum PluginWorkerEventKind {
+  /** A request ran for longer than the request timeout, the worker was killed */
+  hung = 'hung',
+  /** The worker crashed or was killed, and a new process replaced it */
+  respawned = 'respawned',
+  /** The worker crashed or was killed, and was not restarted */
+  exited = 'exited'
+}
+
+export interface PluginWorkerPoolOptions {
+  /** The program that runs the workers, e.g. the path of node */
+  program: string
+  /** The arguments of the workers, followed by the path of the socket of each worker */
+  args: Array<string>
+  env?: Record<string, string>
+  /** The path of the sockets of the workers, in which `{id}` is replaced by a unique id of each worker process */
+  socketPath: string
+  /** How long a request can run before the worker is considered hung and restarted. Requests never time out by default */
+  requestTimeoutMs?: number
+  /** How many times a worker that crashed or hung is restarted, 3 by default */
+  maxRespawns?: number
+  /** How often the workers are checked, 250 milliseconds by default */
+  healthCheckIntervalMs?: number
+}
+
+export interface PortAllocatorOptions {
+  /** The first port that is allocated, 4200 by default */
+  rangeStart?: number
+  /** The last port that is allocated, 4999 by default */
+  rangeEnd?: number
+  /** The address that the ports have to be free on, any IPv4 and IPv6 address by default */
+  host?: string
+}
+
+export interface PortConflict {
+  port: number
+  /** The tasks that listen on the port and can run at the same time, sorted */
+  taskIds: Array<string>
+  /** Another process already listens on the port */
+  inUse: boolean
+}
+
+export interface PortReservation {
+  taskId: string
+  port: number
+}
+
+/**
+ * An event of the Chrome trace event format, which can be opened in about://tracing or https://ui.perfetto.dev.
+ * Spans are complete events (`ph: "X"`), and the names of the threads are metadata events (`ph: "M"`)
+ */
+export interface ProfileEvent {
+  name: string
+  cat: string
+  ph: string
+  /** When the span started, in microseconds since the unix epoch */
+  ts: number
+  /** How long the span lasted, in microseconds */
+  dur?: number
+  pid: number
+  tid: number
+  args: Record<string, string>
+}
+
 export interface Project {
   root: string
   namedInputs?: Record<string, Array<JsInputs>>
@@ -211,12 +1290,85 @@ export interface ProjectGraph {
   externalNodes: Record<string, ExternalNode>
 }
 
-export declare export function remove(src: string): void
+export interface ProjectGraphArchive {
+  projectGraph: ExternalObject<ProjectGraph>
+  files: NxWorkspaceFilesExternals
+}
+
+/** An import of a source file of `source_project` that resolves to `target_project` */
+export interface ProjectImport {
+  file: string
+  specifier: string
+  sourceProject: string
+  targetProject: string
+}
 
 export interface RuntimeInput {
   runtime: string
 }
 
+/**
+ * Searches the files of the workspace for the lines that match the regular expression `pattern`, like ripgrep does,
+ * so generators and migrations can find the usages of an import or an API without reading every file in JS.
+ *
+ * Only the files that match `include_globs` (e.g. `libs/**`, `!libs/legacy/**`) are searched, every file when there
+ * are none. Later globs override earlier ones, as in a .gitignore. Files that are ignored by .gitignore and
+ * .nxignore, binary files and files that are not UTF-8 are skipped. `^` and `$` match at the start and the end of
+ * the lines, and a match that spans lines is reported on the line where it starts.
+ *
+ * The files are read from the disk, so the changes of a generator that were not written yet are not searched.
+ * Matches are sorted by file, then by line
+ */
+export declare export function searchWorkspace(workspaceRoot: string, pattern: string, includeGlobs: Array<string>, options?: WorkspaceSearchOptions | undefined | null): Array<WorkspaceSearchMatch>
+
+/**
+ * Replaces the filter of the logs written to the log file, without restarting the process.
+ * The filter has the syntax of `NX_NATIVE_LOGGING`, e.g. `info,nx::native::watch=trace`
+ */
+export declare export function setLogFilter(filter: string): void
+
+/**
+ * Changes the level of the logs of a module (e.g. `nx::native::watch`) written to the log file,
+ * keeping the rest of its filter. Returns the filter of the log file
+ */
+export declare export function setLogLevel(module: string, level: string): string
+
+/**
+ * Shuts the native work of the process down before it exits: no hashing or writes to the Nx database are started anymore,
+ * and the ones in flight are waited for until `timeout_ms` (5 seconds by default) have passed.
+ * The database is then checkpointed, so its file is complete even if the WAL is removed afterwards (e.g. by `nx reset`)
+ */
+export declare export function shutdownGracefully(dbConnection?: ExternalObject<NxDbConnection> | undefined | null, timeoutMs?: number | undefined | null): ShutdownReport
+
+export interface ShutdownReport {
+  /** The hashing and the writes to the Nx database that were still in flight at the deadline */
+  abandonedWork: number
+  /** Whether the WAL of the Nx database was moved into the database completely */
+  checkpointed: boolean
+}
+
+/**
+ * Starts recording the spans of the native module (glob compilation, hashing, cache operations, task runs).
+ * The spans recorded by a previous profile are dropped
+ */
+export declare export function startProfiling(): void
+
+/** Stops recording spans, and returns the spans that were recorded since profiling started as trace events */
+export declare export function stopProfiling(): Array<ProfileEvent>
+
+/** How the walkers handle symlinks */
+export declare const enum SymlinkPolicy {
+  /**
+   * Symlinks are resolved, the files of symlinked directories are walked as if they were in the directory.
+   * Symlinks that point to one of their parents are skipped, so cycles are only walked once
+   */
+  follow = 'follow',
+  /** Symlinks (including symlinked directories) are recorded as a single entry and are not resolved */
+  record = 'record',
+  /** Symlinks are left out */
+  skip = 'skip'
+}
+
 export interface Target {
   executor?: string
   inputs?: Array<JsInputs>
@@ -226,6 +1378,18 @@ export interface Target {
   parallelism?: boolean
 }
 
+/**
+ * A target default that matched a target but was not applied to it,
+ * because it is for an other executor, command or script than the target
+ */
+export interface TargetDefaultConflict {
+  projectRoot: string
+  target: string
+  /** The key of the target default in `nx.json` */
+  targetDefault: string
+  message: string
+}
+
 export interface Task {
   id: string
   target: TaskTarget
@@ -233,12 +1397,148 @@ export interface Task {
   projectRoot?: string
 }
 
+export interface TaskCommand {
+  command: string
+  cwd?: string
+  env?: Record<string, string>
+}
+
+/** How long the last runs of a target took to execute, in milliseconds */
+export interface TaskDurationStats {
+  /** The runs that are considered, at most the last 20 runs that were not restored from the cache */
+  runs: number
+  average: number
+  /** Used to estimate the next run, a run that was much slower or faster than usual weighs less than in `average` */
+  median: number
+  max: number
+}
+
 export interface TaskGraph {
   roots: Array<string>
   tasks: Record<string, Task>
   dependencies: Record<string, Array<string>>
 }
 
+/** The task `source` depends on the task `target` */
+export interface TaskGraphEdge {
+  source: string
+  target: string
+}
+
+export interface TaskGraphNode {
+  id: string
+  project: string
+  target: string
+  configuration?: string
+  status?: string
+  startTime?: number
+  endTime?: number
+  /** In milliseconds, 0 when it is not known */
+  duration: number
+  /** How long after the start of the run the task can start at the earliest, when its dependencies finished */
+  earliestStart: number
+  /** How long the task can be delayed without delaying the end of the run */
+  slack: number
+  /** Whether the task is on the critical path */
+  critical: boolean
+}
+
+/** The result of a task in a run, or the estimated duration of a task that has not run */
+export interface TaskGraphRunResult {
+  status?: string
+  /** In milliseconds since the unix epoch */
+  startTime?: number
+  endTime?: number
+  /** In milliseconds, used when the start and end times are not known */
+  duration?: number
+}
+
+export interface TaskGraphVisualization {
+  /** Sorted by id */
+  nodes: Array<TaskGraphNode>
+  edges: Array<TaskGraphEdge>
+  /** The longest chain of dependent tasks, in the order they run, which bounds the duration of the run */
+  criticalPath: Array<string>
+  criticalPathDuration: number
+  /** The duration of all of the tasks, as if they ran one after the other */
+  totalDuration: number
+}
+
+export interface TaskHashDiff {
+  /** Inputs that are only part of the new hash */
+  addedInputs: Array<string>
+  /** Inputs that are only part of the old hash */
+  removedInputs: Array<string>
+  /** Inputs that are part of both hashes but hashed to a different value */
+  changedInputs: Array<string>
+  addedFiles: Array<string>
+  removedFiles: Array<string>
+  changedFiles: Array<string>
+  /** Names of the environment variables whose inputs were added, removed or changed */
+  changedEnvVars: Array<string>
+  /** Runtime commands whose inputs were added, removed or changed */
+  changedRuntimeInputs: Array<string>
+}
+
+export interface TaskLifecycleEvent {
+  taskId: string
+  status: TaskRunStatus
+  /** The exit code of the command, once the task has finished */
+  code?: number
+  /** How long the task ran in milliseconds, once the task has finished */
+  duration?: number
+}
+
+/**
+ * The resources used by a task and the processes it started.
+ * They are sampled on Linux and macOS, so short-lived processes may be missed
+ */
+export interface TaskMetrics {
+  /** User and system CPU time, in milliseconds */
+  cpuTime: number
+  /**
+   * The peak memory of all the processes together, in bytes.
+   * This is the resident memory on Linux and macOS, and the committed memory on Windows
+   */
+  peakRss: number
+  /** Bytes read from storage, not measured on macOS */
+  readBytes: number
+  /** Bytes written to storage, not measured on macOS */
+  writeBytes: number
+}
+
+export interface TaskOutput {
+  output: string
+  /** The offset to read the output that comes after this output */
+  nextOffset: number
+  /** Whether output after the requested offset was dropped, because the buffer was full */
+  truncated: boolean
+}
+
+/** A line of a stored terminal output that matches the searched pattern */
+export interface TaskOutputMatch {
+  taskId: string
+  hash: string
+  /** When the output was stored, in milliseconds since the epoch */
+  recordedAt: number
+  /** The number of the line in the output, from 1 */
+  line: number
+  /** The line, without its colors and other escape sequences */
+  text: string
+}
+
+/** Which of the stored terminal outputs are searched, every output by default */
+export interface TaskOutputSearchRange {
+  /** Only the outputs that were stored at or after this time, in milliseconds since the epoch */
+  since?: number
+  /** Only the outputs that were stored at or before this time, in milliseconds since the epoch */
+  until?: number
+  /** Only the outputs of these tasks */
+  taskIds?: Array<string>
+  /** The maximum number of matches, the earliest are returned */
+  limit?: number
+}
+
 export interface TaskRun {
   hash: string
   status: string
@@ -247,12 +1547,74 @@ export interface TaskRun {
   end: number
 }
 
+/** The formats that task runs are exported to */
+export declare const enum TaskRunsExportFormat {
+  /** One JSON object per task run and per line */
+  jsonl = 'jsonl',
+  /** An OTLP `ExportTraceServiceRequest` in its JSON encoding, with one span per task run */
+  otlp = 'otlp'
+}
+
+export interface TaskRunsExportOptions {
+  /** Runs that started before this, in milliseconds since the epoch, are not exported */
+  since?: number
+  /** Runs that started after this are not exported */
+  until?: number
+  /** The `service.name` of the resource of the spans, `nx` by default */
+  serviceName?: string
+}
+
+export declare const enum TaskRunStatus {
+  started = 'started',
+  success = 'success',
+  failure = 'failure',
+  /** The task did not run, because one of its dependencies failed or the run was bailed */
+  skipped = 'skipped'
+}
+
 export interface TaskTarget {
   project: string
   target: string
   configuration?: string
 }
 
+export interface TaskWatchEvent {
+  kind: TaskWatchEventKind
+  /** The number of the run, which increases with every run */
+  run: number
+  /** The tasks of the run */
+  tasks: Array<string>
+  /** The files whose changes invalidated the tasks of the run */
+  changedFiles: Array<string>
+  /** The lifecycle event of the task, for `task` events */
+  task?: TaskLifecycleEvent
+  /**
+   * The final status of every task of the run, for `finished` and `cancelled` events.
+   * Tasks that were killed or did not start because the run was cancelled are skipped
+   */
+  statuses?: Record<string, TaskRunStatus>
+}
+
+export declare const enum TaskWatchEventKind {
+  /** A run of the invalidated tasks started */
+  started = 'started',
+  /** A task of the run started, finished or was skipped */
+  task = 'task',
+  /** Every task of the run has finished or was skipped */
+  finished = 'finished',
+  /** More files changed before the run finished, its tasks were killed and run again with the new changes */
+  cancelled = 'cancelled'
+}
+
+export interface TaskWatchOptions {
+  /** How long to wait for more changes before the invalidated tasks run again, 300 milliseconds by default */
+  debounceMs?: number
+  /** The maximum number of tasks that run at the same time, defaults to the number of cpus */
+  parallel?: number
+  /** Matches negated globs of the inputs in order, like the task hasher does with the same option */
+  orderedNegatedGlobs?: boolean
+}
+
 export declare export function testOnlyTransferFileMap(projectFiles: Record<string, Array<FileData>>, nonProjectFiles: Array<FileData>): NxWorkspaceFilesExternals
 
 /**
@@ -261,16 +1623,93 @@ export declare export function testOnlyTransferFileMap(projectFiles: Record<stri
  */
 export declare export function transferProjectGraph(projectGraph: ProjectGraph): ExternalObject<ProjectGraph>
 
+/**
+ * Restores outputs packed by `pack_outputs` from `source` (a directory or a tarball) into the workspace.
+ * Existing outputs are removed first, so stale files do not survive the restore
+ */
+export declare export function unpackOutputs(source: string, workspaceRoot: string, outputs: Array<string>): void
+
 export interface UpdatedWorkspaceFiles {
   fileMap: FileMap
   externalReferences: NxWorkspaceFilesExternals
 }
 
+/**
+ * Validates a configuration file (`nx.json` or a `project.json`) against the schema of the files of its name.
+ * Returns the syntax error of the file, or the members that do not match the schema, in the order of the file.
+ * The file is read as JSON with comments and trailing commas, like Nx reads it
+ */
+export declare export function validateConfig(path: string): Array<ConfigDiagnostic>
+
 export declare export function validateOutputs(outputs: Array<string>): void
 
+/**
+ * Compares the outputs of a task in the workspace with its cached outputs in `cached_outputs_path`,
+ * by the size of every file of the cache, which are stat-ed in parallel.
+ * Outputs that are missing or have an other size were overwritten or removed since they were restored,
+ * so they have to be restored again. Archived outputs (e.g. `outputs.tar.zst`) do not have the sizes of their files
+ * without unpacking them, so they are not verified
+ */
+export declare export function verifyOutputs(workspaceRoot: string, cachedOutputsPath: string, outputs: Array<string>): OutputsVerification
+
+/**
+ * Computes what the task graph view of `nx graph` renders: the tasks with their results, their dependencies,
+ * and the critical path of the run weighted by the durations of the tasks
+ */
+export declare export function visualizeTaskGraph(taskGraph: TaskGraph, results?: Record<string, TaskGraphRunResult> | undefined | null): TaskGraphVisualization
+
+/** How the file watcher has been doing since it started */
+export interface WatcherHealth {
+  watching: boolean
+  /** The roots that are watched, including the origin */
+  watchedRoots: number
+  /** The roots that are scanned periodically, because they could not be fully watched */
+  polledRoots: number
+  eventBatches: number
+  events: number
+  /** Events that could not be read, or that were not sent to the callback of the watcher */
+  droppedEvents: number
+  /** Errors reported by the file system watcher */
+  errors: number
+}
+
+export interface WatcherWarning {
+  kind: WatcherWarningKind
+  message: string
+  /** The command that raises the limit that was reached, when it can be raised with sysctl */
+  sysctl?: string
+  /** The roots whose changes are found by scanning them periodically instead */
+  polledRoots: Array<string>
+}
+
+/** Why some changes could be missed by the watcher */
+export declare const enum WatcherWarningKind {
+  /** The OS limit of the number of watched files (`fs.inotify.max_user_watches` on Linux) was reached */
+  watchLimit = 'watchLimit',
+  /** The OS limit of the number of open file handles was reached */
+  handleLimit = 'handleLimit',
+  /** A root could not be watched for another reason */
+  unwatchable = 'unwatchable'
+}
+
 export interface WatchEvent {
   path: string
   type: EventType
+  /** The previous path of moved files */
+  from?: string
+  /** The new path of moved files, same as `path` */
+  to?: string
+}
+
+export interface WatchOptions {
+  /** How long to wait for more events before sending a batch of events, in milliseconds */
+  debounce?: number
+  coalescing?: EventCoalescing
+  /**
+   * How often the roots that cannot be watched (e.g. because the watch limit of the OS was reached)
+   * are scanned for changes instead, in milliseconds. Defaults to 2000
+   */
+  pollInterval?: number
 }
 
 /** Public NAPI error codes that are for Node */
@@ -279,3 +1718,47 @@ export declare const enum WorkspaceErrors {
   Generic = 'Generic'
 }
 
+/** A line of a file of the workspace that matches the searched pattern */
+export interface WorkspaceSearchMatch {
+  /** The path of the file from the workspace root */
+  file: string
+  /** The number of the line in the file, from 1 */
+  line: number
+  /** The column where the match starts in the line, in characters from 1 */
+  column: number
+  text: string
+}
+
+export interface WorkspaceSearchOptions {
+  /** Matches the pattern regardless of case */
+  ignoreCase?: boolean
+  /** The maximum number of matches, the first files in path order are returned */
+  limit?: number
+}
+
+/**
+ * Applies the edits of a generator to the disk in parallel: files without content are deleted first, then the other
+ * files are formatted with `options` and written. Every file is written to a temporary file next to it which is then
+ * renamed over it, so a file is never partially written, even when the process is interrupted.
+ * Symlinks are kept and their targets are written.
+ *
+ * When a path is edited more than once, its last edit is applied
+ */
+export declare export function writeFilesAtomic(edits: Array<FileEdit>, options?: WriteFilesOptions | undefined | null): void
+
+/** How the written text files are formatted, they are written as they are by default */
+export interface WriteFilesOptions {
+  /** The line endings of the files, the files end their lines like their first line when there is none */
+  endOfLine?: EndOfLine
+  /** Ends the files that are not empty with a newline */
+  insertFinalNewline?: boolean
+  /** Removes the spaces and the tabs at the end of the lines */
+  trimTrailingWhitespace?: boolean
+}
+
+/**
+ * Writes the project graph and the files of the workspace to a binary archive in `cache_dir`,
+ * which is much faster to load than the JSON of the graph
+ */
+export declare export function writeProjectGraphArchive(cacheDir: string, projectGraph: ExternalObject<ProjectGraph>, files: NxWorkspaceFilesExternals): void
+
@@ -0,0 +1,220 @@
+use anyhow::bail;
+use napi::bindgen_prelude::Buffer;
+use napi::{Env, JsUnknown};
+use tracing::trace;
+
+/// Frames start with the length of their payload, as a big endian u32
+const LENGTH_PREFIX_SIZE: usize = 4;
+/// The highest bit of the length of a frame is set when its payload is compressed
+const COMPRESSED_FLAG: u32 = 1 << 31;
+/// Smaller payloads are sent as they are, compressing them would take longer than sending them
+const MIN_COMPRESSED_SIZE: usize = 64 * 1024;
+
+/// Encodes a message as a frame of the binary daemon protocol: the length of the payload, followed by the message
+/// as MessagePack. Messages are passed serialized, so they are encoded exactly like `JSON.stringify` serializes them,
+/// and serialized responses (like the project graph) are transcoded without being parsed in JS.
+/// With `compress`, large payloads are compressed at the level of the local cache
+#[napi]
+pub fn encode_message(message: String, compress: Option<bool>) -> anyhow::Result<Buffer> {
+    Ok(encode_frame(&message, compress.unwrap_or(false))?.into())
+}
+
+fn encode_frame(json: &str, compress: bool) -> anyhow::Result<Vec<u8>> {
+    let mut frame = vec![0; LENGTH_PREFIX_SIZE];
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    serde_transcode::transcode(
+        &mut deserializer,
+        &mut rmp_serde::Serializer::new(&mut frame),
+    )?;
+    deserializer.end()?;
+
+    let mut flag = 0;
+    if compress && frame.len() - LENGTH_PREFIX_SIZE >= MIN_COMPRESSED_SIZE {
+        if let Some(compressed) = compression::compress_payload(&frame[LENGTH_PREFIX_SIZE..])? {
+            frame.truncate(LENGTH_PREFIX_SIZE);
+            frame.extend(compressed);
+            flag = COMPRESSED_FLAG;
+        }
+    }
+
+    let length = u32::try_from(frame.len() - LENGTH_PREFIX_SIZE)?;
+    if length & COMPRESSED_FLAG != 0 {
+        bail!(
+            "The message is too big to be sent to the daemon ({} bytes)",
+            length
+        );
+    }
+    frame[..LENGTH_PREFIX_SIZE].copy_from_slice(&(length | flag).to_be_bytes());
+    Ok(frame)
+}
+
+/// Splits the data received on a socket into the frames of the binary daemon protocol, and decodes their messages
+#[napi]
+#[derive(Default)]
+pub struct MessageDecoder {
+    /// The bytes of the frame that was not received completely yet
+    buffer: Vec<u8>,
+}
+
+#[napi]
+impl MessageDecoder {
+    #[napi(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the messages of the frames that were completed by `chunk`, in the order they were sent
+    #[napi(ts_return_type = "Array<any>")]
+    pub fn push(&mut self, env: Env, chunk: Buffer) -> napi::Result<Vec<JsUnknown>> {
+        // the messages are transcoded to JS values directly, which keeps the order of their keys
+        self.push_bytes(&chunk)?
+            .iter()
+            .map(|payload| {
+                let mut deserializer = rmp_serde::Deserializer::new(payload.as_slice());
+                env.to_js_value(&serde_transcode::Transcoder::new(&mut deserializer))
+            })
+            .collect()
+    }
+}
+
+impl MessageDecoder {
+    /// Returns the payloads of the frames that were completed by `chunk`, decompressed
+    fn push_bytes(&mut self, chunk: &[u8]) -> anyhow::Result<Vec<Vec<u8>>> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut frames = vec![];
+        let mut start = 0;
+        while let Some((compressed, payload)) = next_payload(&self.buffer[start..]) {
+            frames.push((compressed, payload.to_vec()));
+            start += LENGTH_PREFIX_SIZE + payload.len();
+        }
+        self.buffer.drain(..start);
+
+        if !self.buffer.is_empty() {
+            trace!(
+                "waiting for the rest of a frame, {} bytes received",
+                self.buffer.len()
+            );
+        }
+        frames
+            .into_iter()
+            .map(|(compressed, payload)| {
+                if compressed {
+                    compression::decompress_payload(&payload)
+                } else {
+                    Ok(payload)
+                }
+            })
+            .collect()
+    }
+}
+
+/// Whether the payload of the first frame of `bytes` is compressed, and the payload, when it was received completely
+fn next_payload(bytes: &[u8]) -> Option<(bool, &[u8])> {
+    let length = bytes.get(..LENGTH_PREFIX_SIZE)?;
+    let length = u32::from_be_bytes(length.try_into().ok()?);
+    let compressed = length & COMPRESSED_FLAG != 0;
+    let length = (length & !COMPRESSED_FLAG) as usize;
+    let payload = bytes.get(LENGTH_PREFIX_SIZE..LENGTH_PREFIX_SIZE + length)?;
+    Some((compressed, payload))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod compression {
+    use crate::native::compression::{compress, decompress, CompressionTarget};
+
+    pub fn compress_payload(payload: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        compress(payload, CompressionTarget::Local).map(Some)
+    }
+
+    pub fn decompress_payload(payload: &[u8]) -> anyhow::Result<Vec<u8>> {
+        decompress(payload)
+    }
+}
+
+/// The daemon does not run on wasm, its messages are never compressed
+#[cfg(target_arch = "wasm32")]
+mod compression {
+    use anyhow::bail;
+
+    pub fn compress_payload(_: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+
+    pub fn decompress_payload(_: &[u8]) -> anyhow::Result<Vec<u8>> {
+        bail!("Compressed messages can not be decoded on wasm")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    #[test]
+    fn should_decode_messages_split_across_chunks() {
+        let mut stream = encode_frame(
+            r#"{"type":"GLOB","globs":["**/*.ts"],"exclude":null}"#,
+            false,
+        )
+        .unwrap();
+        stream.extend(
+            encode_frame(
+                r#"{ "error": null, "projectGraph": { "nodes": {} }, "count": 2.5 }"#,
+                false,
+            )
+            .unwrap(),
+        );
+        stream.extend(encode_frame("true", false).unwrap());
+
+        let mut decoder = MessageDecoder::new();
+        let mut messages = vec![];
+        for chunk in stream.chunks(7) {
+            messages.extend(
+                decoder
+                    .push_bytes(chunk)
+                    .unwrap()
+                    .iter()
+                    .map(|payload| rmp_serde::from_slice::<Value>(payload).unwrap()),
+            );
+        }
+        assert_eq!(
+            messages,
+            [
+                json!({ "type": "GLOB", "globs": ["**/*.ts"], "exclude": null }),
+                json!({ "error": null, "projectGraph": { "nodes": {} }, "count": 2.5 }),
+                json!(true),
+            ]
+        );
+        assert!(decoder.buffer.is_empty());
+    }
+
+    #[test]
+    fn should_not_encode_invalid_json() {
+        assert!(encode_frame("{ \"projectGraph\": ", false).is_err());
+        assert!(encode_frame("{} {}", false).is_err());
+    }
+
+    #[test]
+    fn should_compress_large_messages() {
+        let nodes = (0..10_000)
+            .map(|i| (format!("project-{}", i), json!({ "type": "lib" })))
+            .collect::<serde_json::Map<_, _>>();
+        let message = json!({ "projectGraph": { "nodes": nodes } });
+        let json = message.to_string();
+
+        let small = encode_frame("true", true).unwrap();
+        let large = encode_frame(&json, true).unwrap();
+        assert!(!next_payload(&small).unwrap().0);
+        assert!(next_payload(&large).unwrap().0);
+        assert!(large.len() < encode_frame(&json, false).unwrap().len());
+
+        let mut decoder = MessageDecoder::new();
+        let payloads = decoder.push_bytes(&large).unwrap();
+        assert_eq!(
+            rmp_serde::from_slice::<Value>(&payloads[0]).unwrap(),
+            message
+        );
+    }
+}
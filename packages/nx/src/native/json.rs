@@ -0,0 +1,449 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+/// A position in a JSON file. Lines and columns start at 1, columns count characters
+#[napi(object)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct JsonPosition {
+    pub line: u32,
+    pub column: u32,
+    /// In bytes from the start of the file
+    pub offset: u32,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct JsonSpan {
+    pub start: JsonPosition,
+    /// The position after the last character of the span
+    pub end: JsonPosition,
+}
+
+/// Where a member of a JSON document is in its file
+#[napi(object)]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JsonLocation {
+    /// The span of the key of the member, which the root and the items of arrays do not have
+    pub key: Option<JsonSpan>,
+    pub value: JsonSpan,
+}
+
+/// A parsed JSON document with the locations of its members by their JSON pointers,
+/// e.g. `/targets/build/options/main`, where the root is the empty pointer
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct JsonDocument {
+    pub value: Value,
+    pub locations: HashMap<String, JsonLocation>,
+}
+
+impl JsonDocument {
+    /// Prefixes `message` with the location of the member at `pointer` in `file`, or of its closest parent
+    /// that was parsed, like `project.json:12:7: ...`
+    pub fn error_at(&self, file: &str, pointer: &str, message: impl fmt::Display) -> anyhow::Error {
+        match self.position_of(pointer) {
+            Some(start) => anyhow::anyhow!("{}:{}:{}: {}", file, start.line, start.column, message),
+            None => anyhow::anyhow!("{}: {}", file, message),
+        }
+    }
+
+    /// Where the member at `pointer` starts, or its closest parent that was parsed:
+    /// the position of its key, or of its value for the root and the items of arrays
+    pub fn position_of(&self, pointer: &str) -> Option<JsonPosition> {
+        let mut pointer = pointer;
+        let location = loop {
+            if let Some(location) = self.locations.get(pointer) {
+                break location;
+            }
+            pointer = &pointer[..pointer.rfind('/')?];
+        };
+        Some(location.key.unwrap_or(location.value).start)
+    }
+}
+
+/// A syntax error of a JSON document
+#[derive(Debug, thiserror::Error, PartialEq)]
+#[error("{message} at line {} column {}", .position.line, .position.column)]
+pub struct JsonSyntaxError {
+    pub message: String,
+    pub position: JsonPosition,
+}
+
+#[napi]
+/// Parses JSON with comments and trailing commas (like `project.json`, `nx.json` and `tsconfig.json` files)
+/// with the locations of its keys and values, so the errors of configuration files can point at their lines.
+/// Syntax errors are reported with their line and column in `file`
+pub fn parse_json_with_locations(
+    contents: String,
+    file: Option<String>,
+) -> anyhow::Result<JsonDocument> {
+    parse_json(&contents).map_err(|e| match file {
+        Some(file) => anyhow::anyhow!(
+            "{}:{}:{}: {}",
+            file,
+            e.position.line,
+            e.position.column,
+            e.message
+        ),
+        None => e.into(),
+    })
+}
+
+/// Parses JSON with comments and trailing commas, with the locations of its members
+pub fn parse_json(contents: &str) -> Result<JsonDocument, JsonSyntaxError> {
+    let mut parser = JsonParser {
+        contents,
+        bytes: contents.as_bytes(),
+        position: JsonPosition {
+            line: 1,
+            column: 1,
+            offset: 0,
+        },
+        locations: HashMap::new(),
+    };
+    parser.skip_trivia()?;
+    let mut pointer = String::new();
+    let value = parser.parse_value(&mut pointer, None)?;
+    parser.skip_trivia()?;
+    if parser.peek().is_some() {
+        return Err(parser.error("Unexpected content after the end of the document"));
+    }
+    Ok(JsonDocument {
+        value,
+        locations: parser.locations,
+    })
+}
+
+struct JsonParser<'a> {
+    contents: &'a str,
+    bytes: &'a [u8],
+    position: JsonPosition,
+    locations: HashMap<String, JsonLocation>,
+}
+
+impl JsonParser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position.offset as usize).copied()
+    }
+
+    fn advance(&mut self) {
+        let Some(byte) = self.peek() else {
+            return;
+        };
+        self.position.offset += 1;
+        if byte == b'\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else if byte & 0xC0 != 0x80 {
+            // the continuation bytes of a character are not columns
+            self.position.column += 1;
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> JsonSyntaxError {
+        JsonSyntaxError {
+            message: message.into(),
+            position: self.position,
+        }
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), JsonSyntaxError> {
+        if self.peek() != Some(expected) {
+            return Err(self.unexpected(&format!("'{}'", expected as char)));
+        }
+        self.advance();
+        Ok(())
+    }
+
+    fn unexpected(&self, expected: &str) -> JsonSyntaxError {
+        match self.peek() {
+            None => self.error(format!("Expected {} but the document ended", expected)),
+            Some(_) => {
+                let found = self.contents[self.position.offset as usize..]
+                    .chars()
+                    .next()
+                    .unwrap_or_default();
+                self.error(format!("Expected {} but found '{}'", expected, found))
+            }
+        }
+    }
+
+    /// Skips whitespace and comments
+    fn skip_trivia(&mut self) -> Result<(), JsonSyntaxError> {
+        loop {
+            match self.peek() {
+                Some(b' ' | b'\t' | b'\n' | b'\r') => self.advance(),
+                Some(b'/') => match self.bytes.get(self.position.offset as usize + 1) {
+                    Some(b'/') => {
+                        while !matches!(self.peek(), None | Some(b'\n')) {
+                            self.advance();
+                        }
+                    }
+                    Some(b'*') => {
+                        let start = self.position;
+                        self.advance();
+                        self.advance();
+                        loop {
+                            match self.peek() {
+                                None => {
+                                    return Err(JsonSyntaxError {
+                                        message: "The comment is not closed".into(),
+                                        position: start,
+                                    })
+                                }
+                                Some(b'*')
+                                    if self.bytes.get(self.position.offset as usize + 1)
+                                        == Some(&b'/') =>
+                                {
+                                    self.advance();
+                                    self.advance();
+                                    break;
+                                }
+                                Some(_) => self.advance(),
+                            }
+                        }
+                    }
+                    _ => return Err(self.unexpected("a value")),
+                },
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn parse_value(
+        &mut self,
+        pointer: &mut String,
+        key: Option<JsonSpan>,
+    ) -> Result<Value, JsonSyntaxError> {
+        let start = self.position;
+        let value = match self.peek() {
+            Some(b'{') => self.parse_object(pointer)?,
+            Some(b'[') => self.parse_array(pointer)?,
+            Some(b'"') => Value::String(self.parse_string()?),
+            Some(b't') => self.parse_literal("true", Value::Bool(true))?,
+            Some(b'f') => self.parse_literal("false", Value::Bool(false))?,
+            Some(b'n') => self.parse_literal("null", Value::Null)?,
+            Some(b'-' | b'0'..=b'9') => self.parse_number()?,
+            _ => return Err(self.unexpected("a value")),
+        };
+        self.locations.insert(
+            pointer.clone(),
+            JsonLocation {
+                key,
+                value: JsonSpan {
+                    start,
+                    end: self.position,
+                },
+            },
+        );
+        Ok(value)
+    }
+
+    fn parse_object(&mut self, pointer: &mut String) -> Result<Value, JsonSyntaxError> {
+        self.expect(b'{')?;
+        let mut object = Map::new();
+        loop {
+            self.skip_trivia()?;
+            if self.peek() == Some(b'}') {
+                break;
+            }
+            if self.peek() != Some(b'"') {
+                return Err(self.unexpected("a key"));
+            }
+            let key_start = self.position;
+            let key = self.parse_string()?;
+            let key_span = JsonSpan {
+                start: key_start,
+                end: self.position,
+            };
+            self.skip_trivia()?;
+            self.expect(b':')?;
+            self.skip_trivia()?;
+
+            let parent_len = pointer.len();
+            pointer.push('/');
+            pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+            let value = self.parse_value(pointer, Some(key_span))?;
+            pointer.truncate(parent_len);
+            object.insert(key, value);
+
+            self.skip_trivia()?;
+            match self.peek() {
+                Some(b',') => self.advance(),
+                Some(b'}') => break,
+                _ => return Err(self.unexpected("',' or '}'")),
+            }
+        }
+        self.advance();
+        Ok(Value::Object(object))
+    }
+
+    fn parse_array(&mut self, pointer: &mut String) -> Result<Value, JsonSyntaxError> {
+        self.expect(b'[')?;
+        let mut array = vec![];
+        loop {
+            self.skip_trivia()?;
+            if self.peek() == Some(b']') {
+                break;
+            }
+            let parent_len = pointer.len();
+            pointer.push('/');
+            pointer.push_str(&array.len().to_string());
+            let value = self.parse_value(pointer, None)?;
+            pointer.truncate(parent_len);
+            array.push(value);
+
+            self.skip_trivia()?;
+            match self.peek() {
+                Some(b',') => self.advance(),
+                Some(b']') => break,
+                _ => return Err(self.unexpected("',' or ']'")),
+            }
+        }
+        self.advance();
+        Ok(Value::Array(array))
+    }
+
+    fn parse_string(&mut self) -> Result<String, JsonSyntaxError> {
+        let start = self.position;
+        self.advance();
+        loop {
+            match self.peek() {
+                None | Some(b'\n') => {
+                    return Err(JsonSyntaxError {
+                        message: "The string is not closed".into(),
+                        position: start,
+                    })
+                }
+                Some(b'\\') => {
+                    self.advance();
+                    self.advance();
+                }
+                Some(b'"') => {
+                    self.advance();
+                    break;
+                }
+                Some(_) => self.advance(),
+            }
+        }
+        let literal = &self.contents[start.offset as usize..self.position.offset as usize];
+        // the escapes of the string are the ones of JSON
+        serde_json::from_str(literal).map_err(|e| JsonSyntaxError {
+            message: format!("The string is invalid: {}", e),
+            position: start,
+        })
+    }
+
+    fn parse_literal(&mut self, literal: &str, value: Value) -> Result<Value, JsonSyntaxError> {
+        let offset = self.position.offset as usize;
+        if !self.contents[offset..].starts_with(literal) {
+            return Err(self.unexpected("a value"));
+        }
+        for _ in 0..literal.len() {
+            self.advance();
+        }
+        Ok(value)
+    }
+
+    fn parse_number(&mut self) -> Result<Value, JsonSyntaxError> {
+        let start = self.position;
+        while matches!(
+            self.peek(),
+            Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+        ) {
+            self.advance();
+        }
+        let literal = &self.contents[start.offset as usize..self.position.offset as usize];
+        serde_json::from_str(literal).map_err(|_| JsonSyntaxError {
+            message: format!("The number {} is invalid", literal),
+            position: start,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn position(line: u32, column: u32) -> (u32, u32) {
+        (line, column)
+    }
+
+    fn start(span: JsonSpan) -> (u32, u32) {
+        (span.start.line, span.start.column)
+    }
+
+    #[test]
+    fn should_locate_the_keys_and_values() {
+        let contents = r#"{
+  // the name of the project
+  "name": "app",
+  "targets": {
+    /* the build */
+    "build": {
+      "executor": "@nx/js:tsc",
+      "outputs": ["{options.outputPath}", "dist/ü/~"],
+    },
+  },
+}"#;
+        let document = parse_json(contents).unwrap();
+        assert_eq!(
+            document.value,
+            json!({
+                "name": "app",
+                "targets": {
+                    "build": {
+                        "executor": "@nx/js:tsc",
+                        "outputs": ["{options.outputPath}", "dist/ü/~"],
+                    },
+                },
+            })
+        );
+
+        let name = &document.locations["/name"];
+        assert_eq!(start(name.key.unwrap()), position(3, 3));
+        assert_eq!(start(name.value), position(3, 11));
+        let executor = &document.locations["/targets/build/executor"];
+        assert_eq!(start(executor.key.unwrap()), position(7, 7));
+        assert_eq!(executor.value.end.column, 31);
+        let output = &document.locations["/targets/build/outputs/1"];
+        assert_eq!(output.key, None);
+        assert_eq!(start(output.value), position(8, 43));
+        assert_eq!(output.value.end.column, 53);
+        assert_eq!(document.locations[""].value.end.line, 11);
+
+        assert_eq!(
+            document
+                .error_at(
+                    "project.json",
+                    "/targets/build/options/main",
+                    "main is missing"
+                )
+                .to_string(),
+            "project.json:6:5: main is missing"
+        );
+    }
+
+    #[test]
+    fn should_report_the_position_of_syntax_errors() {
+        let error = parse_json("{\n  \"name\": \"app\"\n  \"tags\": []\n}").unwrap_err();
+        assert_eq!((error.position.line, error.position.column), position(3, 3));
+        assert_eq!(error.message, "Expected ',' or '}' but found '\"'");
+
+        let error = parse_json("{ /* \"name\": 1 }").unwrap_err();
+        assert_eq!(error.message, "The comment is not closed");
+        assert!(parse_json("[1, 2] 3").is_err());
+        assert!(parse_json("{ \"a\": tru }").is_err());
+
+        assert_eq!(
+            parse_json_with_locations("[01]".into(), Some("nx.json".into()))
+                .unwrap_err()
+                .to_string(),
+            "nx.json:1:2: The number 01 is invalid"
+        );
+    }
+}
@@ -0,0 +1,225 @@
+mod package_lock;
+mod pnpm_lock;
+mod yarn_lock;
+
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use tracing::trace;
+
+pub use package_lock::parse_package_lock;
+pub use pnpm_lock::parse_pnpm_lock;
+pub use yarn_lock::parse_yarn_lock;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LockFilePackage {
+    pub name: String,
+    pub version: String,
+    pub integrity: Option<String>,
+    /// Keys of the packages this package depends on, already resolved by the lock file parser
+    pub dependencies: Vec<String>,
+}
+
+/// The packages of a lock file, keyed by an identifier that is unique within the lock file
+/// (`node_modules/a` for npm, `a@1.0.0` for yarn and pnpm)
+#[derive(Debug, Default)]
+pub struct LockFile {
+    packages: HashMap<String, LockFilePackage>,
+}
+
+type LockFileParser = fn(&str) -> anyhow::Result<LockFile>;
+
+impl LockFile {
+    pub fn new(packages: HashMap<String, LockFilePackage>) -> Self {
+        Self { packages }
+    }
+
+    /// Reads the lock file of the package manager used in the workspace (npm, yarn or pnpm)
+    pub fn read<P: AsRef<Path>>(workspace_root: P) -> anyhow::Result<Option<Self>> {
+        let workspace_root = workspace_root.as_ref();
+        let parsers: [(&str, LockFileParser); 3] = [
+            ("package-lock.json", parse_package_lock),
+            ("yarn.lock", |content| Ok(parse_yarn_lock(content))),
+            ("pnpm-lock.yaml", |content| Ok(parse_pnpm_lock(content))),
+        ];
+        for (file_name, parse) in parsers {
+            let lock_file_path = workspace_root.join(file_name);
+            if !lock_file_path.exists() {
+                continue;
+            }
+            trace!("parsing lock file {:?}", lock_file_path);
+            let content = std::fs::read_to_string(&lock_file_path)?;
+            return parse(&content).map(Some);
+        }
+        Ok(None)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&LockFilePackage> {
+        self.packages.get(key)
+    }
+
+    /// Finds the key of a package by name and optionally version.
+    /// Without a version, the top level (hoisted) package is preferred, otherwise the highest version is used
+    pub fn find_package(&self, name: &str, version: Option<&str>) -> Option<&str> {
+        if version.is_none() {
+            let hoisted = format!("node_modules/{}", name);
+            if let Some((key, _)) = self.packages.get_key_value(&hoisted) {
+                return Some(key);
+            }
+        }
+
+        self.packages
+            .iter()
+            .filter(|(_, package)| {
+                package.name == name && version.is_none_or(|version| package.version == version)
+            })
+            .max_by(|(a_key, a), (b_key, b)| {
+                compare_versions(&a.version, &b.version)
+                    // shorter keys are closer to the top level, and keys break ties deterministically
+                    .then_with(|| b_key.len().cmp(&a_key.len()))
+                    .then_with(|| b_key.cmp(a_key))
+            })
+            .map(|(key, _)| key.as_str())
+    }
+
+    /// Returns the keys of the package and every package it (transitively) depends on
+    pub fn transitive_dependencies<'a>(&'a self, key: &'a str) -> BTreeSet<&'a str> {
+        let mut visited = BTreeSet::new();
+        let mut queue = vec![key];
+        while let Some(key) = queue.pop() {
+            if !visited.insert(key) {
+                continue;
+            }
+            if let Some(package) = self.packages.get(key) {
+                queue.extend(package.dependencies.iter().map(String::as_str));
+            }
+        }
+        visited
+    }
+
+    /// Hashes the versions and integrities of a package and its transitive dependencies
+    pub fn hash_package(&self, name: &str, version: Option<&str>) -> Option<String> {
+        let key = self.find_package(name, version)?;
+        let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+        for dependency in self.transitive_dependencies(key) {
+            let Some(package) = self.packages.get(dependency) else {
+                continue;
+            };
+            hasher.update(package.name.as_bytes());
+            hasher.update(b"@");
+            hasher.update(package.version.as_bytes());
+            hasher.update(b"\0");
+            hasher.update(package.integrity.as_deref().unwrap_or("").as_bytes());
+            hasher.update(b"\0");
+        }
+        Some(hasher.digest().to_string())
+    }
+}
+
+/// Hashes external dependencies using the lock file of the workspace
+#[napi]
+pub struct LockFileHasher {
+    lock_file: LockFile,
+}
+
+#[napi]
+impl LockFileHasher {
+    #[napi(constructor)]
+    pub fn new(workspace_root: String) -> anyhow::Result<Self> {
+        let lock_file = LockFile::read(&workspace_root)?
+            .ok_or_else(|| anyhow::anyhow!("No lock file found in {}", workspace_root))?;
+        Ok(Self { lock_file })
+    }
+
+    /// Hashes an external dependency and its transitive dependencies.
+    /// Accepts package names (`react`), versioned names (`react@18.2.0`) and external node names (`npm:react`)
+    #[napi]
+    pub fn hash_external_dependency(&self, name: String) -> Option<String> {
+        let (name, version) = split_package_version(name.strip_prefix("npm:").unwrap_or(&name));
+        self.lock_file.hash_package(name, version)
+    }
+}
+
+/// Splits `name@version` into its name and version, keeping the `@` of scoped packages
+pub(crate) fn split_package_version(spec: &str) -> (&str, Option<&str>) {
+    match spec.get(1..).and_then(|rest| rest.find('@')) {
+        Some(index) => (&spec[..index + 1], Some(&spec[index + 2..])),
+        None => (spec, None),
+    }
+}
+
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parse = |version: &str| {
+        version
+            .split(['.', '-', '+'])
+            .map(|part| part.parse::<u64>().ok())
+            .collect::<Vec<_>>()
+    };
+    parse(a).cmp(&parse(b))
+}
+
+pub(crate) fn unquote(value: &str) -> &str {
+    value.trim().trim_matches(|c| c == '"' || c == '\'')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, version: &str, dependencies: &[&str]) -> LockFilePackage {
+        LockFilePackage {
+            name: name.into(),
+            version: version.into(),
+            integrity: Some(format!("{}-{}", name, version)),
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn should_split_package_versions() {
+        assert_eq!(split_package_version("react"), ("react", None));
+        assert_eq!(
+            split_package_version("react@18.2.0"),
+            ("react", Some("18.2.0"))
+        );
+        assert_eq!(
+            split_package_version("@nx/devkit@19.0.0"),
+            ("@nx/devkit", Some("19.0.0"))
+        );
+        assert_eq!(split_package_version("@nx/devkit"), ("@nx/devkit", None));
+    }
+
+    #[test]
+    fn should_hash_transitive_dependencies() {
+        let lock_file = LockFile::new(HashMap::from([
+            ("a@1.0.0".into(), package("a", "1.0.0", &["b@1.0.0"])),
+            ("b@1.0.0".into(), package("b", "1.0.0", &["a@1.0.0"])),
+            ("b@2.0.0".into(), package("b", "2.0.0", &[])),
+            ("c@1.0.0".into(), package("c", "1.0.0", &[])),
+        ]));
+
+        assert_eq!(
+            lock_file.transitive_dependencies("a@1.0.0"),
+            BTreeSet::from(["a@1.0.0", "b@1.0.0"])
+        );
+        assert_eq!(lock_file.find_package("b", None), Some("b@2.0.0"));
+        assert_eq!(lock_file.find_package("b", Some("1.0.0")), Some("b@1.0.0"));
+
+        let hash = lock_file.hash_package("a", None).unwrap();
+        let changed_lock_file = LockFile::new(HashMap::from([
+            ("a@1.0.0".into(), package("a", "1.0.0", &["b@1.0.0"])),
+            ("b@1.0.0".into(), package("b", "1.0.1", &["a@1.0.0"])),
+        ]));
+        assert_ne!(hash, changed_lock_file.hash_package("a", None).unwrap());
+        assert_eq!(
+            lock_file.hash_package("c", None),
+            LockFile::new(HashMap::from([(
+                "c@1.0.0".into(),
+                package("c", "1.0.0", &[])
+            )]))
+            .hash_package("c", None)
+        );
+        assert_eq!(lock_file.hash_package("d", None), None);
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+use crate::native::lock_file::{LockFile, LockFilePackage};
+
+const DEPENDENCY_FIELDS: [&str; 2] = ["dependencies", "optionalDependencies"];
+
+/// Parses the `packages` section of a `package-lock.json` (lockfileVersion 2 and 3).
+/// Dependencies are resolved the same way node resolves them, by walking up the `node_modules` folders
+pub fn parse_package_lock(content: &str) -> anyhow::Result<LockFile> {
+    let lock_file: Value = serde_json::from_str(content)?;
+    let packages = lock_file
+        .get("packages")
+        .and_then(Value::as_object)
+        .ok_or_else(|| {
+            anyhow::anyhow!("package-lock.json without a \"packages\" section is not supported")
+        })?;
+
+    let packages = packages
+        .iter()
+        .filter(|(path, package)| {
+            !path.is_empty() && package.get("link").and_then(Value::as_bool) != Some(true)
+        })
+        .filter_map(|(path, package)| {
+            let version = package.get("version")?.as_str()?.to_string();
+            let name = package
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or_else(|| package_name_from_path(path))
+                .to_string();
+            let integrity = package
+                .get("integrity")
+                .and_then(Value::as_str)
+                .map(String::from);
+            let dependencies = DEPENDENCY_FIELDS
+                .iter()
+                .filter_map(|field| package.get(*field).and_then(Value::as_object))
+                .flat_map(|dependencies| dependencies.keys())
+                .filter_map(|dependency| resolve_dependency(packages, path, dependency))
+                .collect();
+            Some((
+                path.to_string(),
+                LockFilePackage {
+                    name,
+                    version,
+                    integrity,
+                    dependencies,
+                },
+            ))
+        })
+        .collect::<HashMap<_, _>>();
+
+    Ok(LockFile::new(packages))
+}
+
+fn package_name_from_path(path: &str) -> &str {
+    path.rfind("node_modules/")
+        .map(|index| &path[index + "node_modules/".len()..])
+        .unwrap_or(path)
+}
+
+fn resolve_dependency(
+    packages: &Map<String, Value>,
+    path: &str,
+    dependency: &str,
+) -> Option<String> {
+    let mut parent = path;
+    loop {
+        let candidate = if parent.is_empty() {
+            format!("node_modules/{}", dependency)
+        } else {
+            format!("{}/node_modules/{}", parent, dependency)
+        };
+        if packages.contains_key(&candidate) {
+            return Some(candidate);
+        }
+        if parent.is_empty() {
+            return None;
+        }
+        parent = parent
+            .rfind("/node_modules/")
+            .map(|index| &parent[..index])
+            .unwrap_or("");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_package_lock() {
+        let lock_file = parse_package_lock(
+            r#"{
+              "name": "workspace",
+              "lockfileVersion": 3,
+              "packages": {
+                "": { "name": "workspace", "dependencies": { "a": "^1.0.0" } },
+                "node_modules/a": {
+                  "version": "1.0.0",
+                  "integrity": "sha512-a",
+                  "dependencies": { "b": "^2.0.0", "c": "^1.0.0" }
+                },
+                "node_modules/a/node_modules/b": { "version": "2.0.0", "integrity": "sha512-b2" },
+                "node_modules/b": { "version": "1.0.0", "integrity": "sha512-b1" },
+                "node_modules/c": {
+                  "version": "1.0.0",
+                  "optionalDependencies": { "b": "^1.0.0" }
+                },
+                "node_modules/@scope/d": { "version": "3.0.0" },
+                "node_modules/my-lib": { "resolved": "libs/my-lib", "link": true }
+              }
+            }"#,
+        )
+        .unwrap();
+
+        let a = lock_file.get("node_modules/a").unwrap();
+        assert_eq!(a.name, "a");
+        assert_eq!(a.integrity.as_deref(), Some("sha512-a"));
+        assert_eq!(
+            a.dependencies,
+            ["node_modules/a/node_modules/b", "node_modules/c"]
+        );
+        assert_eq!(
+            lock_file.get("node_modules/c").unwrap().dependencies,
+            ["node_modules/b"]
+        );
+        assert_eq!(
+            lock_file.get("node_modules/@scope/d").unwrap().name,
+            "@scope/d"
+        );
+        assert!(lock_file.get("node_modules/my-lib").is_none());
+        assert_eq!(lock_file.find_package("b", None), Some("node_modules/b"));
+        assert_eq!(
+            lock_file.find_package("b", Some("2.0.0")),
+            Some("node_modules/a/node_modules/b")
+        );
+    }
+
+    #[test]
+    fn should_not_support_lock_files_without_packages() {
+        assert!(parse_package_lock(r#"{ "lockfileVersion": 1, "dependencies": {} }"#).is_err());
+    }
+}
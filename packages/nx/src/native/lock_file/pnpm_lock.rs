@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+
+use crate::native::lock_file::{split_package_version, unquote, LockFile, LockFilePackage};
+
+const PACKAGE_SECTIONS: [&str; 2] = ["packages", "snapshots"];
+const DEPENDENCY_FIELDS: [&str; 2] = ["dependencies", "optionalDependencies"];
+
+#[derive(Default)]
+struct PnpmLockEntry {
+    integrity: Option<String>,
+    in_snapshots: bool,
+    dependencies: Vec<(String, String)>,
+}
+
+/// Parses the `packages` (and for lockfile v9, `snapshots`) sections of a `pnpm-lock.yaml`.
+/// Package keys look like `a@1.0.0` (v6+, optionally with a peer dependency suffix like `(react@18.2.0)`)
+/// or `/a/1.0.0` (v5), and dependencies are resolved by joining the dependency name with the resolved version
+pub fn parse_pnpm_lock(content: &str) -> LockFile {
+    let mut entries: HashMap<String, PnpmLockEntry> = HashMap::new();
+    let mut section: Option<&str> = None;
+    let mut current_key: Option<String> = None;
+    let mut dependency_field = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let indent = line.len() - line.trim_start().len();
+        match indent {
+            0 => {
+                section = Some(trimmed.trim_end_matches(':'));
+                current_key = None;
+            }
+            2 if section.is_some_and(|section| PACKAGE_SECTIONS.contains(&section)) => {
+                let key = normalize_key(split_field(trimmed).0);
+                let entry = entries.entry(key.to_string()).or_default();
+                entry.in_snapshots |= section == Some("snapshots");
+                current_key = Some(key.to_string());
+                dependency_field = false;
+            }
+            4 => {
+                let Some(entry) = current_key.as_ref().and_then(|key| entries.get_mut(key)) else {
+                    continue;
+                };
+                let (key, value) = split_field(trimmed);
+                dependency_field = DEPENDENCY_FIELDS.contains(&key);
+                if key == "resolution" {
+                    entry.integrity = parse_integrity(value).or(entry.integrity.take());
+                }
+            }
+            6 if dependency_field => {
+                let Some(entry) = current_key.as_ref().and_then(|key| entries.get_mut(key)) else {
+                    continue;
+                };
+                let (name, version) = split_field(trimmed);
+                entry
+                    .dependencies
+                    .push((name.to_string(), version.to_string()));
+            }
+            _ => {}
+        }
+    }
+
+    let resolve = |name: &str, version: &str| {
+        [
+            format!("{}@{}", name, version),
+            format!("{}/{}", name, version),
+            normalize_key(version).to_string(),
+        ]
+        .into_iter()
+        .find(|key| entries.contains_key(key))
+    };
+
+    // with snapshots, entries that only exist in `packages` hold metadata for the snapshots with peer dependencies
+    let has_snapshots = entries.values().any(|entry| entry.in_snapshots);
+    let packages = entries
+        .iter()
+        .filter(|(_, entry)| !has_snapshots || entry.in_snapshots)
+        .filter_map(|(key, entry)| {
+            let (name, version) = split_key(key)?;
+            // lockfile v9 keeps the integrity in `packages` and the dependencies in `snapshots`
+            let integrity = entry.integrity.clone().or_else(|| {
+                key.find('(')
+                    .and_then(|index| entries.get(&key[..index]))
+                    .and_then(|entry| entry.integrity.clone())
+            });
+            let dependencies = entry
+                .dependencies
+                .iter()
+                .filter(|(_, version)| !version.starts_with("link:"))
+                .filter_map(|(name, version)| resolve(name, version))
+                .collect();
+            Some((
+                key.clone(),
+                LockFilePackage {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                    integrity,
+                    dependencies,
+                },
+            ))
+        })
+        .collect();
+
+    LockFile::new(packages)
+}
+
+fn normalize_key(key: &str) -> &str {
+    key.strip_prefix('/').unwrap_or(key)
+}
+
+/// Splits `a@1.0.0(peer@1.0.0)` or `a/1.0.0` into the package name and version without the peer dependency suffix
+fn split_key(key: &str) -> Option<(&str, &str)> {
+    let key = key.find('(').map(|index| &key[..index]).unwrap_or(key);
+    match split_package_version(key) {
+        (name, Some(version)) => Some((name, version)),
+        (name, None) => name.rsplit_once('/'),
+    }
+}
+
+fn split_field(line: &str) -> (&str, &str) {
+    let (key, value) = if line.starts_with('\'') || line.starts_with('"') {
+        let quote = &line[..1];
+        match line[1..].find(quote) {
+            Some(end) => (&line[1..end + 1], &line[end + 2..]),
+            None => (&line[1..], ""),
+        }
+    } else {
+        line.split_once(':').unwrap_or((line, ""))
+    };
+    (key, unquote(value.trim_start_matches(':')))
+}
+
+/// Reads the integrity from `resolution: {integrity: sha512-...}`
+fn parse_integrity(resolution: &str) -> Option<String> {
+    let start = resolution.find("integrity:")? + "integrity:".len();
+    let integrity = resolution[start..]
+        .split([',', '}'])
+        .next()
+        .map(str::trim)?;
+    (!integrity.is_empty()).then(|| integrity.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_pnpm_lock_files() {
+        let lock_file = parse_pnpm_lock(
+            r#"lockfileVersion: '6.0'
+
+importers:
+  .:
+    dependencies:
+      a:
+        specifier: ^1.0.0
+        version: 1.0.0
+
+packages:
+
+  /a@1.0.0:
+    resolution: {integrity: sha512-a}
+    dependencies:
+      '@scope/b': 2.0.0(react@18.2.0)
+      my-lib: link:../libs/my-lib
+    dev: false
+
+  /@scope/b@2.0.0(react@18.2.0):
+    resolution: {integrity: sha512-b}
+    dev: false
+"#,
+        );
+
+        let a = lock_file.get("a@1.0.0").unwrap();
+        assert_eq!(a.name, "a");
+        assert_eq!(a.integrity.as_deref(), Some("sha512-a"));
+        assert_eq!(a.dependencies, ["@scope/b@2.0.0(react@18.2.0)"]);
+
+        let b = lock_file.get("@scope/b@2.0.0(react@18.2.0)").unwrap();
+        assert_eq!(b.name, "@scope/b");
+        assert_eq!(b.version, "2.0.0");
+        assert!(lock_file.get(".").is_none());
+    }
+
+    #[test]
+    fn should_parse_pnpm_v9_lock_files() {
+        let lock_file = parse_pnpm_lock(
+            r#"lockfileVersion: '9.0'
+
+packages:
+
+  a@1.0.0:
+    resolution: {integrity: sha512-a, tarball: https://example.com/a.tgz}
+
+  b@2.0.0:
+    resolution: {integrity: sha512-b}
+
+snapshots:
+
+  a@1.0.0(b@2.0.0):
+    dependencies:
+      b: 2.0.0
+
+  b@2.0.0: {}
+"#,
+        );
+
+        let a = lock_file.get("a@1.0.0(b@2.0.0)").unwrap();
+        assert_eq!(a.integrity.as_deref(), Some("sha512-a"));
+        assert_eq!(a.dependencies, ["b@2.0.0"]);
+        assert!(lock_file.get("a@1.0.0").is_none());
+        assert_eq!(lock_file.find_package("a", None), Some("a@1.0.0(b@2.0.0)"));
+        assert_eq!(
+            lock_file.get("b@2.0.0").unwrap().integrity.as_deref(),
+            Some("sha512-b")
+        );
+    }
+
+    #[test]
+    fn should_parse_pnpm_v5_keys() {
+        assert_eq!(split_key("a/1.0.0"), Some(("a", "1.0.0")));
+        assert_eq!(split_key("@scope/a/1.0.0"), Some(("@scope/a", "1.0.0")));
+        assert_eq!(
+            split_key("@scope/a@1.0.0(react@18.2.0)"),
+            Some(("@scope/a", "1.0.0"))
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+
+use crate::native::lock_file::{split_package_version, unquote, LockFile, LockFilePackage};
+
+const DEPENDENCY_FIELDS: [&str; 2] = ["dependencies", "optionalDependencies"];
+
+#[derive(Default)]
+struct YarnLockEntry {
+    specs: Vec<String>,
+    version: Option<String>,
+    integrity: Option<String>,
+    dependencies: Vec<String>,
+}
+
+/// Parses a `yarn.lock` of yarn classic (v1) or yarn berry (v2+).
+/// Both are indentation based, entries are headed by the specs they resolve (`a@^1.0.0, a@^1.1.0:`)
+/// and dependencies are resolved back to entries through these specs
+pub fn parse_yarn_lock(content: &str) -> LockFile {
+    let mut entries: Vec<YarnLockEntry> = vec![];
+    let mut section: Option<String> = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let indent = line.len() - line.trim_start().len();
+        match indent {
+            0 => {
+                section = None;
+                let specs = trimmed
+                    .trim_end_matches(':')
+                    .split(", ")
+                    .map(|spec| unquote(spec).to_string())
+                    .collect();
+                entries.push(YarnLockEntry {
+                    specs,
+                    ..Default::default()
+                });
+            }
+            2 => {
+                let Some(entry) = entries.last_mut() else {
+                    continue;
+                };
+                let (key, value) = split_field(trimmed);
+                section = None;
+                match key {
+                    "version" => entry.version = Some(value.to_string()),
+                    "integrity" | "checksum" => entry.integrity = Some(value.to_string()),
+                    _ if value.is_empty() => section = Some(key.to_string()),
+                    _ => {}
+                }
+            }
+            _ => {
+                let (Some(entry), Some(section)) = (entries.last_mut(), &section) else {
+                    continue;
+                };
+                if DEPENDENCY_FIELDS.contains(&section.as_str()) {
+                    let (name, range) = split_field(trimmed);
+                    entry.dependencies.push(format!("{}@{}", name, range));
+                }
+            }
+        }
+    }
+
+    let entries = entries
+        .into_iter()
+        .filter_map(|entry| {
+            let version = entry.version.clone()?;
+            // entries without a range (e.g. __metadata) are not packages
+            let (name, range) = split_package_version(entry.specs.first()?);
+            let name = range.map(|_| name.to_string())?;
+            Some((name, version, entry))
+        })
+        .collect::<Vec<_>>();
+
+    let keys_by_spec: HashMap<&str, String> = entries
+        .iter()
+        .flat_map(|(name, version, entry)| {
+            let key = format!("{}@{}", name, version);
+            entry
+                .specs
+                .iter()
+                .map(move |spec| (spec.as_str(), key.clone()))
+        })
+        .collect();
+    let resolve = |spec: &str| {
+        keys_by_spec.get(spec).cloned().or_else(|| {
+            // yarn berry prefixes the ranges of npm packages in the entry specs
+            let (name, range) = split_package_version(spec);
+            keys_by_spec
+                .get(format!("{}@npm:{}", name, range?).as_str())
+                .cloned()
+        })
+    };
+
+    let packages = entries
+        .iter()
+        .map(|(name, version, entry)| {
+            (
+                format!("{}@{}", name, version),
+                LockFilePackage {
+                    name: name.clone(),
+                    version: version.clone(),
+                    integrity: entry.integrity.clone(),
+                    dependencies: entry
+                        .dependencies
+                        .iter()
+                        .filter_map(|spec| resolve(spec.as_str()))
+                        .collect(),
+                },
+            )
+        })
+        .collect();
+
+    LockFile::new(packages)
+}
+
+/// Splits `key "value"` (yarn classic) and `key: value` (yarn berry) lines, where keys can be quoted
+fn split_field(line: &str) -> (&str, &str) {
+    let (key, rest) = if let Some(quoted) = line.strip_prefix('"') {
+        match quoted.find('"') {
+            Some(end) => (&quoted[..end], &quoted[end + 1..]),
+            None => (quoted, ""),
+        }
+    } else {
+        let end = line.find([' ', ':']).unwrap_or(line.len());
+        (&line[..end], &line[end..])
+    };
+    (key, unquote(rest.trim_start().trim_start_matches(':')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_yarn_classic_lock_files() {
+        let lock_file = parse_yarn_lock(
+            r#"# THIS IS AN AUTOGENERATED FILE. DO NOT EDIT THIS FILE DIRECTLY.
+# yarn lockfile v1
+
+
+"@scope/a@^1.0.0", "@scope/a@^1.1.0":
+  version "1.2.0"
+  resolved "https://registry.yarnpkg.com/@scope/a/-/a-1.2.0.tgz#abc"
+  integrity sha512-a
+  dependencies:
+    b "^2.0.0"
+    c "~1.0.0"
+
+b@^2.0.0:
+  version "2.1.0"
+  integrity sha512-b
+
+c@~1.0.0:
+  version "1.0.3"
+  integrity sha512-c
+  optionalDependencies:
+    b "^2.0.0"
+"#,
+        );
+
+        let a = lock_file.get("@scope/a@1.2.0").unwrap();
+        assert_eq!(a.name, "@scope/a");
+        assert_eq!(a.integrity.as_deref(), Some("sha512-a"));
+        assert_eq!(a.dependencies, ["b@2.1.0", "c@1.0.3"]);
+        assert_eq!(lock_file.get("c@1.0.3").unwrap().dependencies, ["b@2.1.0"]);
+        assert_eq!(
+            lock_file.find_package("@scope/a", None),
+            Some("@scope/a@1.2.0")
+        );
+    }
+
+    #[test]
+    fn should_parse_yarn_berry_lock_files() {
+        let lock_file = parse_yarn_lock(
+            r#"# This file is generated by running "yarn install" inside your project.
+
+__metadata:
+  version: 6
+  cacheKey: 8
+
+"a@npm:^1.0.0":
+  version: 1.0.0
+  resolution: "a@npm:1.0.0"
+  dependencies:
+    "@scope/b": "npm:^2.0.0"
+  checksum: abc
+  languageName: node
+  linkType: hard
+
+"@scope/b@npm:^2.0.0":
+  version: 2.0.0
+  resolution: "@scope/b@npm:2.0.0"
+  checksum: def
+  languageName: node
+  linkType: hard
+
+"workspace@workspace:.":
+  version: 0.0.0-use.local
+  resolution: "workspace@workspace:."
+  dependencies:
+    a: ^1.0.0
+  languageName: unknown
+  linkType: soft
+"#,
+        );
+
+        let a = lock_file.get("a@1.0.0").unwrap();
+        assert_eq!(a.integrity.as_deref(), Some("abc"));
+        assert_eq!(a.dependencies, ["@scope/b@2.0.0"]);
+        assert_eq!(
+            lock_file
+                .get("workspace@0.0.0-use.local")
+                .unwrap()
+                .dependencies,
+            ["a@1.0.0"]
+        );
+        assert!(lock_file.find_package("__metadata", None).is_none());
+    }
+}
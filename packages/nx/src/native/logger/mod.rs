@@ -1,9 +1,28 @@
+pub mod profiler;
+mod recent_errors;
+mod rotating_file;
+
 use colored::Colorize;
-use std::io::IsTerminal;
+use once_cell::sync::{Lazy, OnceCell};
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use tracing::level_filters::LevelFilter;
 use tracing::{Event, Level, Subscriber};
-use tracing_subscriber::fmt::{format, FmtContext, FormatEvent, FormatFields, FormattedFields};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::fmt::{
+    format, FmtContext, FormatEvent, FormatFields, FormattedFields, MakeWriter,
+};
+use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::registry::LookupSpan;
-use tracing_subscriber::EnvFilter;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Layer, Registry};
+
+use profiler::profile_layer;
+use recent_errors::recent_errors_layer;
+pub use recent_errors::{recent_errors, LoggedError};
+use rotating_file::RotatingFile;
 
 struct NxLogFormatter;
 impl<S, N> FormatEvent<S, N> for NxLogFormatter
@@ -81,6 +100,70 @@ where
     }
 }
 
+/// The filter of the logs written to the log file, which can be changed while the process runs
+static FILE_FILTER: OnceCell<reload::Handle<EnvFilter, Registry>> = OnceCell::new();
+/// The directives of the filter of the log file
+static FILE_DIRECTIVES: Lazy<Mutex<FileDirectives>> = Lazy::new(|| {
+    Mutex::new(FileDirectives {
+        filter: "off".to_string(),
+        levels: BTreeMap::new(),
+    })
+});
+/// The log file, once file logging is enabled
+static LOG_FILE: Mutex<Option<RotatingFile>> = Mutex::new(None);
+
+const DEFAULT_FILE_FILTER: &str = "info";
+const DEFAULT_MAX_FILE_SIZE: u32 = 10 * 1024 * 1024;
+const DEFAULT_MAX_FILES: u32 = 5;
+
+/// A filter, with the levels of the modules that were changed since it was set
+struct FileDirectives {
+    filter: String,
+    levels: BTreeMap<String, LevelFilter>,
+}
+
+impl FileDirectives {
+    /// The levels of modules come last, so they take precedence over the directives of the filter for the same modules
+    fn to_filter(&self) -> String {
+        std::iter::once(self.filter.clone())
+            .chain(
+                self.levels
+                    .iter()
+                    .map(|(module, level)| format!("{}={}", module, level)),
+            )
+            .filter(|directive| !directive.is_empty())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// The logs written to the log file go through the same writer, whether or not there is a log file yet
+struct LogFileWriter;
+
+impl Write for LogFileWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match LOG_FILE.lock().as_mut() {
+            Some(file) => file.write(buf),
+            None => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match LOG_FILE.lock().as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogFileWriter {
+    type Writer = LogFileWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        LogFileWriter
+    }
+}
+
 /// Enable logging for the native module
 /// You can set log levels and different logs by setting the `NX_NATIVE_LOGGING` environment variable
 /// Examples:
@@ -88,13 +171,159 @@ where
 /// - `NX_NATIVE_LOGGING=nx=trace` - enable all logs for the `nx` (this) crate
 /// - `NX_NATIVE_LOGGING=nx::native::tasks::hashers::hash_project_files=trace` - enable all logs for the `hash_project_files` module
 /// - `NX_NATIVE_LOGGING=[{project_name=project}]` - enable logs that contain the project in its span
+///
+/// The logs are also written as JSON to a log file once it is enabled with `enableFileLogging`
 pub(crate) fn enable_logger() {
-    let env_filter =
-        EnvFilter::try_from_env("NX_NATIVE_LOGGING").unwrap_or_else(|_| EnvFilter::new("ERROR"));
-    _ = tracing_subscriber::fmt()
-        .with_env_filter(env_filter)
-        .with_ansi(std::io::stdout().is_terminal())
-        .event_format(NxLogFormatter)
-        .try_init()
-        .ok();
+    FILE_FILTER.get_or_init(|| {
+        let env_filter = EnvFilter::try_from_env("NX_NATIVE_LOGGING")
+            .unwrap_or_else(|_| EnvFilter::new("ERROR"));
+        let (file_filter, file_filter_handle) = reload::Layer::new(EnvFilter::new("off"));
+
+        // the file layer is the first one, so its filter can be reloaded with a handle of the registry
+        let file_layer = tracing_subscriber::fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            // the duration of the spans is logged when they close
+            .with_span_events(FmtSpan::CLOSE)
+            .with_writer(LogFileWriter)
+            .with_filter(file_filter);
+        let stdout_layer = tracing_subscriber::fmt::layer()
+            .with_ansi(std::io::stdout().is_terminal())
+            .event_format(NxLogFormatter)
+            .with_filter(env_filter);
+
+        _ = tracing_subscriber::registry()
+            .with(file_layer)
+            .with(stdout_layer)
+            .with(profile_layer())
+            .with(recent_errors_layer())
+            .try_init()
+            .ok();
+        file_filter_handle
+    });
+}
+
+#[napi(object)]
+pub struct FileLoggingOptions {
+    /// The name of the log file in the logs directory, defaults to `daemon.log`
+    pub file_name: Option<String>,
+    /// The directives of the logs to write (e.g. `info,nx::native::watch=trace`), defaults to `info`
+    pub filter: Option<String>,
+    /// The size in bytes after which the log file is rotated, defaults to 10MB
+    pub max_file_size: Option<u32>,
+    /// How many log files are kept, including the current one, defaults to 5
+    pub max_files: Option<u32>,
+}
+
+/// Writes the logs of the native module as JSON lines to `<workspace_data_directory>/logs/<file_name>`,
+/// rotating the file once it grows too large.
+/// Enabling file logging again switches to the new file
+#[napi]
+pub fn enable_file_logging(
+    workspace_data_directory: String,
+    options: Option<FileLoggingOptions>,
+) -> anyhow::Result<()> {
+    let options = options.unwrap_or(FileLoggingOptions {
+        file_name: None,
+        filter: None,
+        max_file_size: None,
+        max_files: None,
+    });
+    let path = PathBuf::from(workspace_data_directory)
+        .join("logs")
+        .join(options.file_name.as_deref().unwrap_or("daemon.log"));
+    let file = RotatingFile::open(
+        path,
+        options.max_file_size.unwrap_or(DEFAULT_MAX_FILE_SIZE) as u64,
+        options.max_files.unwrap_or(DEFAULT_MAX_FILES) as usize,
+    )?;
+    *LOG_FILE.lock() = Some(file);
+
+    set_log_filter(
+        options
+            .filter
+            .unwrap_or_else(|| DEFAULT_FILE_FILTER.to_string()),
+    )
+}
+
+/// Replaces the filter of the logs written to the log file, without restarting the process.
+/// The filter has the syntax of `NX_NATIVE_LOGGING`, e.g. `info,nx::native::watch=trace`
+#[napi]
+pub fn set_log_filter(filter: String) -> anyhow::Result<()> {
+    let mut directives = FILE_DIRECTIVES.lock();
+    let changed = FileDirectives {
+        filter,
+        levels: BTreeMap::new(),
+    };
+    reload_file_filter(&changed.to_filter())?;
+    *directives = changed;
+    Ok(())
+}
+
+/// Changes the level of the logs of a module (e.g. `nx::native::watch`) written to the log file,
+/// keeping the rest of its filter. Returns the filter of the log file
+#[napi]
+pub fn set_log_level(module: String, level: String) -> anyhow::Result<String> {
+    if module.is_empty() || module.contains(['=', ',', '[', ']']) {
+        anyhow::bail!(
+            "invalid module {:?}, expected a path like nx::native::watch",
+            module
+        );
+    }
+    let level: LevelFilter = level.parse().map_err(|_| {
+        anyhow::anyhow!(
+            "invalid log level {:?}, expected one of off, error, warn, info, debug or trace",
+            level
+        )
+    })?;
+
+    let mut directives = FILE_DIRECTIVES.lock();
+    let mut levels = directives.levels.clone();
+    levels.insert(module, level);
+    let changed = FileDirectives {
+        filter: directives.filter.clone(),
+        levels,
+    };
+    let filter = changed.to_filter();
+    reload_file_filter(&filter)?;
+    *directives = changed;
+    Ok(filter)
+}
+
+fn reload_file_filter(filter: &str) -> anyhow::Result<()> {
+    let env_filter = EnvFilter::try_new(filter)
+        .map_err(|e| anyhow::anyhow!("invalid log filter {:?}: {}", filter, e))?;
+    enable_logger();
+    FILE_FILTER
+        .get()
+        .expect("the logger is enabled")
+        .reload(env_filter)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_give_precedence_to_the_levels_of_modules() {
+        let directives = FileDirectives {
+            filter: "info,nx::native::watch=debug".to_string(),
+            levels: BTreeMap::from([
+                ("nx::native::watch".to_string(), LevelFilter::TRACE),
+                ("nx::native::glob".to_string(), LevelFilter::OFF),
+            ]),
+        };
+        assert_eq!(
+            directives.to_filter(),
+            "info,nx::native::watch=debug,nx::native::glob=off,nx::native::watch=trace"
+        );
+
+        let directives = FileDirectives {
+            filter: String::new(),
+            levels: BTreeMap::from([("nx".to_string(), LevelFilter::WARN)]),
+        };
+        assert_eq!(directives.to_filter(), "nx=warn");
+    }
 }
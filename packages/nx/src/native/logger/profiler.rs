@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::ThreadId;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::Subscriber;
+use tracing_subscriber::filter::DynFilterFn;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use crate::native::logger::enable_logger;
+
+static PROFILING: AtomicBool = AtomicBool::new(false);
+static PROFILE: Lazy<Mutex<Profile>> = Lazy::new(|| Mutex::new(Profile::new()));
+
+/// An event of the Chrome trace event format, which can be opened in about://tracing or https://ui.perfetto.dev.
+/// Spans are complete events (`ph: "X"`), and the names of the threads are metadata events (`ph: "M"`)
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct ProfileEvent {
+    pub name: String,
+    pub cat: String,
+    pub ph: String,
+    /// When the span started, in microseconds since the unix epoch
+    pub ts: f64,
+    /// How long the span lasted, in microseconds
+    pub dur: Option<f64>,
+    pub pid: u32,
+    pub tid: u32,
+    pub args: HashMap<String, String>,
+}
+
+struct Profile {
+    started_at: SystemTime,
+    started: Instant,
+    events: Vec<ProfileEvent>,
+    threads: HashMap<ThreadId, u32>,
+}
+
+impl Profile {
+    fn new() -> Self {
+        Self {
+            started_at: SystemTime::now(),
+            started: Instant::now(),
+            events: vec![],
+            threads: HashMap::new(),
+        }
+    }
+
+    /// Microseconds since the unix epoch, measured with the monotonic clock since the profile started
+    fn timestamp(&self, instant: Instant) -> f64 {
+        let started_at = self
+            .started_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        (started_at + instant.saturating_duration_since(self.started)).as_secs_f64() * 1_000_000.0
+    }
+
+    /// The id of the current thread in the profile, naming the thread the first time it records a span
+    fn thread_id(&mut self) -> u32 {
+        let thread = std::thread::current();
+        if let Some(tid) = self.threads.get(&thread.id()) {
+            return *tid;
+        }
+        // the threads of the JS side of the profile are numbered from 0
+        let tid = 1000 + self.threads.len() as u32;
+        self.threads.insert(thread.id(), tid);
+        self.events.push(ProfileEvent {
+            name: "thread_name".to_string(),
+            cat: "__metadata".to_string(),
+            ph: "M".to_string(),
+            ts: 0.0,
+            dur: None,
+            pid: std::process::id(),
+            tid,
+            args: HashMap::from([(
+                "name".to_string(),
+                thread.name().unwrap_or("native").to_string(),
+            )]),
+        });
+        tid
+    }
+}
+
+/// Records the spans of the native module while profiling
+struct ProfileLayer;
+
+/// The profile only looks at spans, so the events logged in hot paths are not filtered again while profiling
+pub(super) fn profile_layer<S>() -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    ProfileLayer.with_filter(
+        DynFilterFn::new(|metadata, _| metadata.is_span() && PROFILING.load(Ordering::Relaxed))
+            .with_callsite_filter(|metadata| {
+                if metadata.is_span() {
+                    Interest::sometimes()
+                } else {
+                    Interest::never()
+                }
+            }),
+    )
+}
+
+/// The state of a span that is being profiled, kept in the extensions of the span
+struct ProfiledSpan {
+    start: Instant,
+    tid: u32,
+    args: HashMap<String, String>,
+}
+
+impl<S> Layer<S> for ProfileLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut args = HashMap::new();
+        attrs.record(&mut ArgsVisitor(&mut args));
+        let tid = PROFILE.lock().thread_id();
+        span.extensions_mut().insert(ProfiledSpan {
+            start: Instant::now(),
+            tid,
+            args,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(profiled) = span.extensions_mut().get_mut::<ProfiledSpan>() {
+                values.record(&mut ArgsVisitor(&mut profiled.args));
+            }
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(profiled) = span.extensions_mut().remove::<ProfiledSpan>() else {
+            return;
+        };
+        let end = Instant::now();
+        let mut profile = PROFILE.lock();
+        let ts = profile.timestamp(profiled.start);
+        profile.events.push(ProfileEvent {
+            name: span.name().to_string(),
+            cat: span.metadata().target().to_string(),
+            ph: "X".to_string(),
+            ts,
+            dur: Some(end.duration_since(profiled.start).as_secs_f64() * 1_000_000.0),
+            pid: std::process::id(),
+            tid: profiled.tid,
+            args: profiled.args,
+        });
+    }
+}
+
+struct ArgsVisitor<'a>(&'a mut HashMap<String, String>);
+
+impl Visit for ArgsVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+/// Starts recording the spans of the native module (glob compilation, hashing, cache operations, task runs).
+/// The spans recorded by a previous profile are dropped
+#[napi]
+pub fn start_profiling() {
+    enable_logger();
+    *PROFILE.lock() = Profile::new();
+    PROFILING.store(true, Ordering::Relaxed);
+}
+
+/// Stops recording spans, and returns the spans that were recorded since profiling started as trace events
+#[napi]
+pub fn stop_profiling() -> Vec<ProfileEvent> {
+    PROFILING.store(false, Ordering::Relaxed);
+    std::mem::take(&mut PROFILE.lock().events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing::trace_span;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn should_record_spans_as_complete_events() {
+        let subscriber = tracing_subscriber::registry().with(profile_layer());
+        tracing::subscriber::with_default(subscriber, || {
+            trace_span!("before_profiling").in_scope(|| {});
+            PROFILING.store(true, Ordering::Relaxed);
+            trace_span!("hashing", task_id = "app:build").in_scope(|| {
+                trace_span!("hash_project_files", project_name = "app").in_scope(|| {});
+            });
+            PROFILING.store(false, Ordering::Relaxed);
+        });
+
+        let events = std::mem::take(&mut PROFILE.lock().events);
+        let spans: Vec<&ProfileEvent> = events.iter().filter(|event| event.ph == "X").collect();
+        assert_eq!(
+            spans
+                .iter()
+                .map(|span| span.name.as_str())
+                .collect::<Vec<_>>(),
+            ["hash_project_files", "hashing"]
+        );
+        assert_eq!(spans[1].args["task_id"], "app:build");
+        assert!(spans[1].ts <= spans[0].ts);
+        assert!(spans[1].dur >= spans[0].dur);
+        assert!(events
+            .iter()
+            .any(|event| event.ph == "M" && event.tid == spans[0].tid));
+    }
+}
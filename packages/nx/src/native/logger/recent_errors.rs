@@ -0,0 +1,111 @@
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// How many of the last errors and warnings are kept
+const MAX_RECENT_ERRORS: usize = 50;
+
+static RECENT_ERRORS: Mutex<VecDeque<LoggedError>> = Mutex::new(VecDeque::new());
+
+/// An error or a warning logged by the native module
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct LoggedError {
+    /// `ERROR` or `WARN`
+    pub level: String,
+    /// The module that logged it, e.g. `nx::native::watch::watcher`
+    pub target: String,
+    pub message: String,
+    /// When it was logged, in milliseconds since the unix epoch
+    pub timestamp: f64,
+}
+
+struct RecentErrorsLayer;
+
+/// Keeps the last errors and warnings in a ring buffer, whether or not they are written anywhere,
+/// so a long running process (like the daemon) can report what went wrong recently
+pub(super) fn recent_errors_layer<S>() -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    RecentErrorsLayer.with_filter(LevelFilter::WARN)
+}
+
+impl<S: Subscriber> Layer<S> for RecentErrorsLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut message = MessageVisitor(String::new());
+        event.record(&mut message);
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64()
+            * 1000.0;
+        record_error(LoggedError {
+            level: event.metadata().level().to_string(),
+            target: event.metadata().target().to_string(),
+            message: message.0,
+            timestamp,
+        });
+    }
+}
+
+fn record_error(error: LoggedError) {
+    let mut errors = RECENT_ERRORS.lock();
+    if errors.len() == MAX_RECENT_ERRORS {
+        errors.pop_front();
+    }
+    errors.push_back(error);
+}
+
+/// The last errors and warnings that were logged, the oldest first
+pub fn recent_errors() -> Vec<LoggedError> {
+    RECENT_ERRORS.lock().iter().cloned().collect()
+}
+
+/// Formats the message of an event, followed by its other fields
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        if field.name() == "message" {
+            self.0.push_str(&format!("{:?}", value));
+        } else {
+            self.0.push_str(&format!("{}={:?}", field.name(), value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_keep_the_last_errors() {
+        for index in 0..MAX_RECENT_ERRORS + 2 {
+            record_error(LoggedError {
+                level: "ERROR".into(),
+                target: "nx::native::tests".into(),
+                message: format!("error {}", index),
+                timestamp: 0.0,
+            });
+        }
+        let errors = recent_errors();
+        assert_eq!(errors.len(), MAX_RECENT_ERRORS);
+        assert_eq!(errors[0].message, "error 2");
+        assert_eq!(
+            errors[MAX_RECENT_ERRORS - 1].message,
+            format!("error {}", MAX_RECENT_ERRORS + 1)
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// A log file that is rotated once it grows past `max_size` bytes:
+/// `daemon.log` becomes `daemon.log.1`, `daemon.log.1` becomes `daemon.log.2`, and so on,
+/// keeping at most `max_files` files including the current one
+pub(super) struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    pub fn open(path: PathBuf, max_size: u64, max_files: usize) -> std::io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_size,
+            max_files: max_files.max(1),
+            file,
+            size,
+        })
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let rotated = |index: usize| -> PathBuf {
+            let mut path = self.path.clone().into_os_string();
+            path.push(format!(".{}", index));
+            path.into()
+        };
+
+        // the oldest file is overwritten by the next one
+        for index in (1..self.max_files).rev() {
+            let from = if index == 1 {
+                self.path.clone()
+            } else {
+                rotated(index - 1)
+            };
+            if from.exists() {
+                // renaming over an existing file fails on windows
+                let to = rotated(index);
+                if to.exists() {
+                    std::fs::remove_file(&to)?;
+                }
+                std::fs::rename(&from, to)?;
+            }
+        }
+        if self.max_files == 1 {
+            std::fs::remove_file(&self.path)?;
+        }
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+/// Every record is written with a single call, so a record is never split between two files
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(buf)?;
+        self.size += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    #[test]
+    fn should_keep_the_most_recent_files() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("logs/daemon.log");
+        let mut file = RotatingFile::open(path.to_path_buf(), 10, 3).unwrap();
+        for record in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_all(record.as_bytes()).unwrap();
+        }
+
+        path.assert("fourth\n");
+        temp.child("logs/daemon.log.1").assert("third\n");
+        temp.child("logs/daemon.log.2").assert("second\n");
+        assert!(!temp.child("logs/daemon.log.3").exists());
+    }
+}
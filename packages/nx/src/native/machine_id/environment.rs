@@ -0,0 +1,137 @@
+use std::env::consts;
+use std::path::Path;
+
+use napi::Env;
+use once_cell::sync::Lazy;
+
+use crate::native::machine_id::get_machine_id;
+
+/// The CI providers by the environment variable that is set when running on them, in order of precedence
+const CI_PROVIDERS: &[(&str, &str)] = &[
+    ("GITHUB_ACTIONS", "github-actions"),
+    ("GITLAB_CI", "gitlab"),
+    ("CIRCLECI", "circleci"),
+    ("TF_BUILD", "azure-pipelines"),
+    ("BUILDKITE", "buildkite"),
+    ("CIRRUS_CI", "cirrus"),
+    ("TRAVIS", "travis"),
+    ("CODEBUILD_BUILD_ID", "aws-codebuild"),
+    ("bamboo.buildKey", "bamboo"),
+    ("bamboo_buildKey", "bamboo"),
+    ("HEROKU_TEST_RUN_ID", "heroku"),
+    ("TEAMCITY_VERSION", "teamcity"),
+    ("BITBUCKET_BUILD_NUMBER", "bitbucket"),
+    ("JENKINS_URL", "jenkins"),
+    ("BUILD_BUILDID", "azure-pipelines"),
+];
+
+/// The environment Nx runs in, found once per process
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct EnvironmentFingerprint {
+    pub machine_id: String,
+    /// The OS, like `linux`, `macos` or `windows`
+    pub os: String,
+    /// The CPU architecture, like `x86_64` or `aarch64`
+    pub arch: String,
+    pub is_ci: bool,
+    /// The CI provider (e.g. `github-actions`), when it is one that is known
+    pub ci_provider: Option<String>,
+    /// Whether Nx runs in a container (e.g. Docker, Podman or a Kubernetes pod)
+    pub is_container: bool,
+    /// The version of Node.js, like `20.11.1`
+    pub node_version: String,
+}
+
+#[napi]
+/// The machine id, OS, architecture, CI provider, container and Node.js version of the environment, in one call.
+/// Nothing is spawned to find them, and everything but the Node.js version is only found once per process
+pub fn get_environment_fingerprint(env: Env) -> napi::Result<EnvironmentFingerprint> {
+    static FINGERPRINT: Lazy<EnvironmentFingerprint> = Lazy::new(|| {
+        let (is_ci, ci_provider) = detect_ci(|name| std::env::var(name).ok());
+        EnvironmentFingerprint {
+            machine_id: get_machine_id(),
+            os: consts::OS.to_string(),
+            arch: consts::ARCH.to_string(),
+            is_ci,
+            ci_provider: ci_provider.map(String::from),
+            is_container: is_container(),
+            node_version: String::new(),
+        }
+    });
+
+    let node_version = env.get_node_version()?;
+    Ok(EnvironmentFingerprint {
+        node_version: format!(
+            "{}.{}.{}",
+            node_version.major, node_version.minor, node_version.patch
+        ),
+        ..FINGERPRINT.clone()
+    })
+}
+
+/// The OS and the architecture, which the outputs of some tasks depend on
+pub(crate) fn platform() -> String {
+    format!("{}-{}", consts::OS, consts::ARCH)
+}
+
+/// Whether this runs on CI, and on which provider, with the same environment variables as `isCI()` in JS
+fn detect_ci(var: impl Fn(&str) -> Option<String>) -> (bool, Option<&'static str>) {
+    let is_set = |name: &str| var(name).is_some_and(|value| !value.is_empty() && value != "false");
+    let ci_provider = CI_PROVIDERS
+        .iter()
+        .find(|(name, _)| is_set(name))
+        .map(|(_, provider)| *provider);
+    let is_ci = ci_provider.is_some() || is_set("CI") || is_set("BUILD_ID");
+    (is_ci, ci_provider)
+}
+
+fn is_container() -> bool {
+    Path::new("/.dockerenv").exists()
+        || Path::new("/run/.containerenv").exists()
+        || std::env::var_os("KUBERNETES_SERVICE_HOST").is_some()
+        || std::fs::read_to_string("/proc/1/cgroup")
+            .is_ok_and(|cgroup| is_container_cgroup(&cgroup))
+}
+
+/// Whether the control groups of the init process are the ones of a container runtime
+fn is_container_cgroup(cgroup: &str) -> bool {
+    ["docker", "kubepods", "containerd", "libpod", "lxc"]
+        .iter()
+        .any(|runtime| cgroup.contains(runtime))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn detect(vars: &[(&str, &str)]) -> (bool, Option<&'static str>) {
+        let vars: HashMap<&str, &str> = vars.iter().copied().collect();
+        detect_ci(|name| vars.get(name).map(|value| value.to_string()))
+    }
+
+    #[test]
+    fn should_detect_ci_providers() {
+        assert_eq!(detect(&[]), (false, None));
+        assert_eq!(detect(&[("CI", "false")]), (false, None));
+        assert_eq!(detect(&[("CI", "true")]), (true, None));
+        assert_eq!(
+            detect(&[("CI", "true"), ("GITHUB_ACTIONS", "true")]),
+            (true, Some("github-actions"))
+        );
+        assert_eq!(
+            detect(&[("JENKINS_URL", "https://ci.example.com")]),
+            (true, Some("jenkins"))
+        );
+    }
+
+    #[test]
+    fn should_detect_containers_from_cgroups() {
+        assert!(is_container_cgroup(
+            "0::/system.slice/docker-3c1f0c9e2a.scope"
+        ));
+        assert!(is_container_cgroup("11:memory:/kubepods/burstable/pod1234"));
+        assert!(!is_container_cgroup("0::/init.scope"));
+    }
+}
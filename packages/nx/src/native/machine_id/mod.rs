@@ -1,3 +1,7 @@
+pub mod environment;
+
+pub use environment::*;
+
 pub fn get_machine_id() -> String {
     #[cfg(not(target_arch = "wasm32"))]
     return machine_uid::get().unwrap_or(String::from("machine"));
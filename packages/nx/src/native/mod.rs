@@ -1,7 +1,11 @@
 pub mod cache;
 pub mod glob;
 pub mod hasher;
-mod logger;
+pub mod ignore_matcher;
+pub mod ipc;
+pub mod json;
+pub mod lock_file;
+pub mod logger;
 pub mod metadata;
 pub mod plugins;
 pub mod project_graph;
@@ -10,7 +14,7 @@ mod types;
 mod utils;
 mod walker;
 pub mod workspace;
-mod machine_id;
+pub mod machine_id;
 
 #[cfg(not(target_arch = "wasm32"))]
 pub mod pseudo_terminal;
@@ -18,3 +22,15 @@ pub mod pseudo_terminal;
 pub mod watch;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod db;
+#[cfg(not(target_arch = "wasm32"))]
+mod archive;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod compression;
+#[cfg(not(target_arch = "wasm32"))]
+mod diagnostics;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod file_lock;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod fs_batch;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod shutdown;
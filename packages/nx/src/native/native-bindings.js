@@ -361,29 +361,98 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
+module.exports.BlobStorageCacheClient = nativeBinding.BlobStorageCacheClient
 module.exports.ChildProcess = nativeBinding.ChildProcess
+module.exports.FileLock = nativeBinding.FileLock
+module.exports.GlobStream = nativeBinding.GlobStream
 module.exports.HashPlanner = nativeBinding.HashPlanner
+module.exports.IgnoreMatcher = nativeBinding.IgnoreMatcher
 module.exports.ImportResult = nativeBinding.ImportResult
+module.exports.ImportScanner = nativeBinding.ImportScanner
+module.exports.LockFileHasher = nativeBinding.LockFileHasher
+module.exports.MessageDecoder = nativeBinding.MessageDecoder
 module.exports.NxCache = nativeBinding.NxCache
+module.exports.NxHashCache = nativeBinding.NxHashCache
+module.exports.NxOutputsSnapshots = nativeBinding.NxOutputsSnapshots
+module.exports.NxPluginWorkerPool = nativeBinding.NxPluginWorkerPool
 module.exports.NxTaskHistory = nativeBinding.NxTaskHistory
+module.exports.PortAllocator = nativeBinding.PortAllocator
+module.exports.RemoteCacheClient = nativeBinding.RemoteCacheClient
+module.exports.RemoteExecutionCacheClient = nativeBinding.RemoteExecutionCacheClient
 module.exports.RustPseudoTerminal = nativeBinding.RustPseudoTerminal
 module.exports.TaskDetails = nativeBinding.TaskDetails
 module.exports.TaskHasher = nativeBinding.TaskHasher
+module.exports.TaskWatchRunner = nativeBinding.TaskWatchRunner
 module.exports.Watcher = nativeBinding.Watcher
 module.exports.WorkspaceContext = nativeBinding.WorkspaceContext
+module.exports.affectedProjects = nativeBinding.affectedProjects
+module.exports.AnsiMode = nativeBinding.AnsiMode
+module.exports.ArchiveFormat = nativeBinding.ArchiveFormat
+module.exports.buildProjectGraph = nativeBinding.buildProjectGraph
+module.exports.checkModuleBoundaries = nativeBinding.checkModuleBoundaries
+module.exports.CompressionAlgorithm = nativeBinding.CompressionAlgorithm
+module.exports.configureCompression = nativeBinding.configureCompression
 module.exports.connectToNxDb = nativeBinding.connectToNxDb
 module.exports.copy = nativeBinding.copy
+module.exports.createArchive = nativeBinding.createArchive
+module.exports.createIgnoreMatcher = nativeBinding.createIgnoreMatcher
+module.exports.daemonDiagnostics = nativeBinding.daemonDiagnostics
+module.exports.detectPortConflicts = nativeBinding.detectPortConflicts
+module.exports.diffTaskHashes = nativeBinding.diffTaskHashes
+module.exports.enableFileLogging = nativeBinding.enableFileLogging
+module.exports.encodeMessage = nativeBinding.encodeMessage
+module.exports.EndOfLine = nativeBinding.EndOfLine
+module.exports.estimateTaskGraphDuration = nativeBinding.estimateTaskGraphDuration
+module.exports.EventCoalescing = nativeBinding.EventCoalescing
 module.exports.EventType = nativeBinding.EventType
 module.exports.expandOutputs = nativeBinding.expandOutputs
+module.exports.extractArchive = nativeBinding.extractArchive
+module.exports.filterProjects = nativeBinding.filterProjects
+module.exports.findCycles = nativeBinding.findCycles
+module.exports.findFreePort = nativeBinding.findFreePort
 module.exports.findImports = nativeBinding.findImports
 module.exports.getBinaryTarget = nativeBinding.getBinaryTarget
+module.exports.getEnvironmentFingerprint = nativeBinding.getEnvironmentFingerprint
 module.exports.getFilesForOutputs = nativeBinding.getFilesForOutputs
+module.exports.getLockHolder = nativeBinding.getLockHolder
+module.exports.getTaskEnvs = nativeBinding.getTaskEnvs
+module.exports.getTaskGraphPriorities = nativeBinding.getTaskGraphPriorities
 module.exports.getTransformableOutputs = nativeBinding.getTransformableOutputs
+module.exports.HASH_VERSION = nativeBinding.HASH_VERSION
+module.exports.HashAlgorithm = nativeBinding.HashAlgorithm
 module.exports.hashArray = nativeBinding.hashArray
+module.exports.HasherErrors = nativeBinding.HasherErrors
 module.exports.hashFile = nativeBinding.hashFile
 module.exports.IS_WASM = nativeBinding.IS_WASM
+module.exports.isPortFree = nativeBinding.isPortFree
+module.exports.killTree = nativeBinding.killTree
+module.exports.materializeProjectTargets = nativeBinding.materializeProjectTargets
+module.exports.owningProjects = nativeBinding.owningProjects
+module.exports.packOutputs = nativeBinding.packOutputs
+module.exports.parseJsonWithLocations = nativeBinding.parseJsonWithLocations
+module.exports.PluginWorkerEventKind = nativeBinding.PluginWorkerEventKind
+module.exports.readFiles = nativeBinding.readFiles
+module.exports.readProjectGraphArchive = nativeBinding.readProjectGraphArchive
+module.exports.readSharedWorkspaceFiles = nativeBinding.readSharedWorkspaceFiles
 module.exports.remove = nativeBinding.remove
+module.exports.searchWorkspace = nativeBinding.searchWorkspace
+module.exports.setLogFilter = nativeBinding.setLogFilter
+module.exports.setLogLevel = nativeBinding.setLogLevel
+module.exports.shutdownGracefully = nativeBinding.shutdownGracefully
+module.exports.startProfiling = nativeBinding.startProfiling
+module.exports.stopProfiling = nativeBinding.stopProfiling
+module.exports.SymlinkPolicy = nativeBinding.SymlinkPolicy
+module.exports.TaskRunsExportFormat = nativeBinding.TaskRunsExportFormat
+module.exports.TaskRunStatus = nativeBinding.TaskRunStatus
+module.exports.TaskWatchEventKind = nativeBinding.TaskWatchEventKind
 module.exports.testOnlyTransferFileMap = nativeBinding.testOnlyTransferFileMap
 module.exports.transferProjectGraph = nativeBinding.transferProjectGraph
+module.exports.unpackOutputs = nativeBinding.unpackOutputs
+module.exports.validateConfig = nativeBinding.validateConfig
 module.exports.validateOutputs = nativeBinding.validateOutputs
+module.exports.verifyOutputs = nativeBinding.verifyOutputs
+module.exports.visualizeTaskGraph = nativeBinding.visualizeTaskGraph
+module.exports.WatcherWarningKind = nativeBinding.WatcherWarningKind
 module.exports.WorkspaceErrors = nativeBinding.WorkspaceErrors
+module.exports.writeFilesAtomic = nativeBinding.writeFilesAtomic
+module.exports.writeProjectGraphArchive = nativeBinding.writeProjectGraphArchive
@@ -125,11 +125,14 @@ function __napi_rs_initialize_modules(__napiInstance) {
   __napiInstance.exports['__napi_register__FileMap_struct_53']?.()
   __napiInstance.exports['__napi_register____test_only_transfer_file_map_54']?.()
 }
+module.exports.GlobStream = __napiModule.exports.GlobStream
 module.exports.HashPlanner = __napiModule.exports.HashPlanner
 module.exports.ImportResult = __napiModule.exports.ImportResult
+module.exports.LockFileHasher = __napiModule.exports.LockFileHasher
 module.exports.TaskHasher = __napiModule.exports.TaskHasher
 module.exports.WorkspaceContext = __napiModule.exports.WorkspaceContext
 module.exports.copy = __napiModule.exports.copy
+module.exports.diffTaskHashes = __napiModule.exports.diffTaskHashes
 module.exports.expandOutputs = __napiModule.exports.expandOutputs
 module.exports.findImports = __napiModule.exports.findImports
 module.exports.getBinaryTarget = __napiModule.exports.getBinaryTarget
@@ -1 +1,2 @@
-mod ts_import_locators;
+pub(crate) mod import_scanner;
+pub(crate) mod ts_import_locators;
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use tracing::trace;
+
+use crate::native::logger::enable_logger;
+use crate::native::plugins::js::ts_import_locators::{process_file, ImportResult};
+use crate::native::types::FileData;
+use crate::native::utils::parallel::prelude::*;
+
+struct ScannedFile {
+    hash: String,
+    imports: ImportResult,
+}
+
+#[napi]
+/// Finds the imports of the files of a workspace, and keeps them so that the next scan only reads the files that changed
+pub struct ImportScanner {
+    workspace_root: String,
+    scanned_files: HashMap<String, ScannedFile>,
+}
+
+#[napi]
+impl ImportScanner {
+    #[napi(constructor)]
+    pub fn new(workspace_root: String) -> Self {
+        enable_logger();
+        Self {
+            workspace_root,
+            scanned_files: HashMap::new(),
+        }
+    }
+
+    #[napi]
+    /// Finds the `import`, `export ... from` and `require` specifiers of the files of each project.
+    /// The files whose hash did not change since the previous scan are not read again,
+    /// and the files that are not in `project_file_map` anymore are forgotten
+    pub fn scan(
+        &mut self,
+        project_file_map: HashMap<String, Vec<FileData>>,
+    ) -> anyhow::Result<Vec<ImportResult>> {
+        let mut scanned_files = HashMap::with_capacity(self.scanned_files.len());
+        let mut files_to_scan = vec![];
+        for (project, files) in &project_file_map {
+            for FileData { file, hash } in files {
+                match self.scanned_files.remove(file) {
+                    Some(scanned)
+                        if scanned.hash == *hash && scanned.imports.source_project == *project =>
+                    {
+                        scanned_files.insert(file.clone(), scanned);
+                    }
+                    _ => files_to_scan.push((project, file, hash)),
+                }
+            }
+        }
+        trace!(
+            "scanning {} files for imports, {} did not change",
+            files_to_scan.len(),
+            scanned_files.len()
+        );
+
+        let newly_scanned = files_to_scan
+            .into_par_iter()
+            .map(|(project, file, hash)| {
+                let path = Path::new(&self.workspace_root)
+                    .join(file)
+                    .display()
+                    .to_string();
+                let imports = process_file((project, &path))?.unwrap_or_else(|| ImportResult {
+                    file: path,
+                    source_project: project.clone(),
+                    static_import_expressions: vec![],
+                    dynamic_import_expressions: vec![],
+                });
+                Ok((
+                    file.clone(),
+                    ScannedFile {
+                        hash: hash.clone(),
+                        imports,
+                    },
+                ))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        scanned_files.extend(newly_scanned);
+
+        self.scanned_files = scanned_files;
+        Ok(self
+            .scanned_files
+            .values()
+            .map(|scanned| scanned.imports.clone())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    fn file(file: &str, hash: &str) -> FileData {
+        FileData {
+            file: file.into(),
+            hash: hash.into(),
+        }
+    }
+
+    fn imports_of<'a>(results: &'a [ImportResult], file: &str) -> &'a ImportResult {
+        results
+            .iter()
+            .find(|result| result.file.ends_with(file))
+            .unwrap()
+    }
+
+    #[test]
+    fn should_only_scan_files_that_changed() {
+        let temp = TempDir::new().unwrap();
+        temp.child("libs/a/index.ts")
+            .write_str("import { b } from '@proj/b'; export * from './lib';")
+            .unwrap();
+        temp.child("libs/a/lib.ts")
+            .write_str("const c = require('@proj/c');")
+            .unwrap();
+
+        let mut scanner = ImportScanner::new(temp.display().to_string());
+        let results = scanner
+            .scan(HashMap::from([(
+                "a".into(),
+                vec![file("libs/a/index.ts", "1"), file("libs/a/lib.ts", "1")],
+            )]))
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            imports_of(&results, "index.ts").static_import_expressions,
+            vec!["@proj/b", "./lib"]
+        );
+        assert_eq!(
+            imports_of(&results, "lib.ts").static_import_expressions,
+            vec!["@proj/c"]
+        );
+
+        temp.child("libs/a/index.ts")
+            .write_str("import('@proj/d');")
+            .unwrap();
+        // the hash of lib.ts did not change so it is not read again, even though its content did
+        temp.child("libs/a/lib.ts")
+            .write_str("const e = require('@proj/e');")
+            .unwrap();
+        let results = scanner
+            .scan(HashMap::from([(
+                "a".into(),
+                vec![file("libs/a/index.ts", "2"), file("libs/a/lib.ts", "1")],
+            )]))
+            .unwrap();
+        assert_eq!(
+            imports_of(&results, "index.ts").dynamic_import_expressions,
+            vec!["@proj/d"]
+        );
+        assert_eq!(
+            imports_of(&results, "lib.ts").static_import_expressions,
+            vec!["@proj/c"]
+        );
+
+        let results = scanner
+            .scan(HashMap::from([(
+                "a".into(),
+                vec![file("libs/a/index.ts", "2")],
+            )]))
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(scanner.scanned_files.len(), 1);
+    }
+}
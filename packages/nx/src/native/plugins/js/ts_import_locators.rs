@@ -5,7 +5,6 @@ use std::path::Path;
 use std::sync::Arc;
 use std::time::Instant;
 
-use rayon::prelude::*;
 use tracing::debug;
 use tracing::trace;
 
@@ -22,9 +21,10 @@ use swc_ecma_parser::token::{BinOpToken, Token, TokenAndSpan};
 use swc_ecma_parser::{Syntax, Tokens, TsConfig};
 
 use crate::native::logger::enable_logger;
+use crate::native::utils::parallel::prelude::*;
 
 #[napi]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ImportResult {
     pub file: String,
     pub source_project: String,
@@ -537,7 +537,7 @@ fn find_specifier_in_require(state: &mut State) -> Option<(String, ImportType)>
     None
 }
 
-fn process_file(
+pub(crate) fn process_file(
     (source_project, file_path): (&String, &String),
 ) -> anyhow::Result<Option<ImportResult>> {
     let now = Instant::now();
@@ -678,7 +678,7 @@ fn process_file(
 }
 
 #[napi]
-fn find_imports(
+pub(crate) fn find_imports(
     project_file_map: HashMap<String, Vec<String>>,
 ) -> anyhow::Result<Vec<ImportResult>> {
     enable_logger();
@@ -712,7 +712,7 @@ fn find_imports(
 mod find_imports {
     use super::*;
     use crate::native::glob::build_glob_set;
-    use crate::native::walker::nx_walker;
+    use crate::native::walker::{nx_walker, SymlinkPolicy};
     use assert_fs::prelude::*;
     use assert_fs::TempDir;
     use std::env;
@@ -1480,7 +1480,7 @@ import(myTag`react@${version}`);
         let root = PathBuf::from(ancestors.next().unwrap());
 
         let glob = build_glob_set(&["**/*.[jt]s"]).unwrap();
-        let files = nx_walker(root.clone(), true)
+        let files = nx_walker(root.clone(), true, SymlinkPolicy::follow)
             .filter(|file| glob.is_match(&file.full_path))
             .map(|file| file.full_path)
             .collect::<Vec<_>>();
@@ -1 +1,3 @@
-mod js;
+pub(crate) mod js;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod worker_pool;
@@ -0,0 +1,476 @@
+use std::collections::HashMap;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use napi::threadsafe_function::{
+    ErrorStrategy::Fatal, ThreadsafeFunction, ThreadsafeFunctionCallMode::NonBlocking,
+};
+use napi::{Env, JsFunction};
+use parking_lot::Mutex;
+use tracing::{debug, trace, warn};
+
+use crate::native::pseudo_terminal::process_tree::kill_tree;
+
+const DEFAULT_MAX_RESPAWNS: u32 = 3;
+const DEFAULT_HEALTH_CHECK_INTERVAL_MS: u32 = 250;
+/// How long a worker that was asked to shut down has to exit before it is killed
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+#[napi(object)]
+pub struct PluginWorkerPoolOptions {
+    /// The program that runs the workers, e.g. the path of node
+    pub program: String,
+    /// The arguments of the workers, followed by the path of the socket of each worker
+    pub args: Vec<String>,
+    pub env: Option<HashMap<String, String>>,
+    /// The path of the sockets of the workers, in which `{id}` is replaced by a unique id of each worker process
+    pub socket_path: String,
+    /// How long a request can run before the worker is considered hung and restarted. Requests never time out by default
+    pub request_timeout_ms: Option<u32>,
+    /// How many times a worker that crashed or hung is restarted, 3 by default
+    pub max_respawns: Option<u32>,
+    /// How often the workers are checked, 250 milliseconds by default
+    pub health_check_interval_ms: Option<u32>,
+}
+
+/// A plugin worker process of the pool
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct PluginWorker {
+    pub id: u32,
+    /// The plugin the worker runs requests for
+    pub key: String,
+    pub pid: u32,
+    /// The socket the worker listens on, which changes when the worker is restarted
+    pub socket_path: String,
+    /// How many times the worker was restarted
+    pub respawns: u32,
+}
+
+#[napi(string_enum)]
+#[derive(Debug, PartialEq)]
+pub enum PluginWorkerEventKind {
+    /// A request ran for longer than the request timeout, the worker was killed
+    #[allow(non_camel_case_types)]
+    hung,
+    /// The worker crashed or was killed, and a new process replaced it
+    #[allow(non_camel_case_types)]
+    respawned,
+    /// The worker crashed or was killed, and was not restarted
+    #[allow(non_camel_case_types)]
+    exited,
+}
+
+#[napi(object)]
+#[derive(Debug)]
+pub struct PluginWorkerEvent {
+    pub kind: PluginWorkerEventKind,
+    pub worker: PluginWorker,
+    /// The requests of the worker that did not finish
+    pub requests: Vec<String>,
+    /// The exit code of the process that exited, when it exited with one
+    pub exit_code: Option<i32>,
+}
+
+struct WorkerProcess {
+    info: PluginWorker,
+    child: Child,
+    /// The requests sent to the worker, with when they were sent
+    requests: HashMap<String, Instant>,
+    /// The requests that were running when the worker was killed because it hung
+    hung_requests: Option<Vec<String>>,
+}
+
+#[derive(Default)]
+struct PoolState {
+    workers: HashMap<u32, WorkerProcess>,
+    /// Workers that were asked to shut down, killed when they do not exit in time
+    stopping: Vec<(Child, Instant)>,
+    next_id: u32,
+    next_process: u32,
+}
+
+type EventCallback = ThreadsafeFunction<PluginWorkerEvent, Fatal>;
+
+/// A pool of the processes that run plugins in isolation, one worker per plugin.
+/// Requests are routed to the current process of the worker of a plugin, and the workers are checked in
+/// the background: a worker that crashes is restarted, and a worker with a request that runs for longer than
+/// the request timeout is killed and restarted, so a hung plugin fails its requests instead of stalling the graph
+#[napi]
+pub struct NxPluginWorkerPool {
+    options: Arc<PluginWorkerPoolOptions>,
+    state: Arc<Mutex<PoolState>>,
+    callback: Arc<Mutex<Option<EventCallback>>>,
+    stopped: Arc<AtomicBool>,
+}
+
+#[napi]
+impl NxPluginWorkerPool {
+    #[napi(constructor)]
+    pub fn new(options: PluginWorkerPoolOptions) -> Self {
+        let pool = Self {
+            options: Arc::new(options),
+            state: Default::default(),
+            callback: Default::default(),
+            stopped: Default::default(),
+        };
+        pool.start_health_checks();
+        pool
+    }
+
+    /// Starts the worker of a plugin, or returns its worker when it is already running
+    #[napi]
+    pub fn start_worker(&self, key: String) -> anyhow::Result<PluginWorker> {
+        let mut state = self.state.lock();
+        if let Some(worker) = state.workers.values().find(|worker| worker.info.key == key) {
+            return Ok(worker.info.clone());
+        }
+
+        let id = state.next_id;
+        state.next_id += 1;
+        let worker = spawn_worker(&self.options, &mut state, id, key, 0)?;
+        let info = worker.info.clone();
+        state.workers.insert(id, worker);
+        Ok(info)
+    }
+
+    /// The workers that are running
+    #[napi]
+    pub fn workers(&self) -> Vec<PluginWorker> {
+        let mut workers = self
+            .state
+            .lock()
+            .workers
+            .values()
+            .map(|worker| worker.info.clone())
+            .collect::<Vec<_>>();
+        workers.sort_by_key(|worker| worker.id);
+        workers
+    }
+
+    /// Records that the request `tx` was sent to a worker, to detect it when it hangs
+    #[napi]
+    pub fn request_started(&self, worker_id: u32, tx: String) {
+        if let Some(worker) = self.state.lock().workers.get_mut(&worker_id) {
+            worker.requests.insert(tx, Instant::now());
+        }
+    }
+
+    /// Records that the request `tx` of a worker finished
+    #[napi]
+    pub fn request_finished(&self, worker_id: u32, tx: String) {
+        if let Some(worker) = self.state.lock().workers.get_mut(&worker_id) {
+            worker.requests.remove(&tx);
+        }
+    }
+
+    /// Calls `callback` when a worker hangs, crashes, or is restarted
+    #[napi]
+    pub fn on_event(
+        &self,
+        env: Env,
+        #[napi(ts_arg_type = "(event: PluginWorkerEvent) => void")] callback: JsFunction,
+    ) -> napi::Result<()> {
+        let mut callback: EventCallback =
+            callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
+        // the health checks do not keep the process alive
+        callback.unref(&env)?;
+        *self.callback.lock() = Some(callback);
+        Ok(())
+    }
+
+    /// Removes a worker from the pool once it was asked to shut down. It is killed when it does not exit in time
+    #[napi]
+    pub fn stop_worker(&self, worker_id: u32) {
+        let mut state = self.state.lock();
+        if let Some(worker) = state.workers.remove(&worker_id) {
+            debug!("stopping the plugin worker {}", worker.info.key);
+            state.stopping.push((worker.child, Instant::now()));
+        }
+    }
+
+    /// Kills every worker and stops checking them
+    #[napi]
+    pub fn shutdown(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        let mut state = self.state.lock();
+        let state = &mut *state;
+        let workers = state.workers.drain().map(|(_, worker)| worker.child);
+        let stopping = std::mem::take(&mut state.stopping).into_iter();
+        for mut child in workers.chain(stopping.map(|(child, _)| child)) {
+            kill_tree(child.id(), Some("SIGKILL".into())).ok();
+            child.wait().ok();
+        }
+    }
+}
+
+impl NxPluginWorkerPool {
+    fn start_health_checks(&self) {
+        let options = self.options.clone();
+        let state = self.state.clone();
+        let callback = self.callback.clone();
+        let stopped = self.stopped.clone();
+        let interval = Duration::from_millis(
+            options
+                .health_check_interval_ms
+                .unwrap_or(DEFAULT_HEALTH_CHECK_INTERVAL_MS) as u64,
+        );
+
+        std::thread::spawn(move || {
+            while !stopped.load(Ordering::SeqCst) {
+                std::thread::sleep(interval);
+                let events = check_workers(&options, &mut state.lock());
+                if let Some(callback) = callback.lock().as_ref() {
+                    for event in events {
+                        callback.call(event, NonBlocking);
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Checks the workers of the pool: kills the workers with hung requests, restarts the workers that exited,
+/// and reaps the workers that were stopped
+fn check_workers(
+    options: &PluginWorkerPoolOptions,
+    state: &mut PoolState,
+) -> Vec<PluginWorkerEvent> {
+    let mut events = vec![];
+
+    state.stopping.retain_mut(|(child, since)| {
+        if matches!(child.try_wait(), Ok(None)) && since.elapsed() < SHUTDOWN_GRACE_PERIOD {
+            return true;
+        }
+        kill_tree(child.id(), Some("SIGKILL".into())).ok();
+        child.wait().ok();
+        false
+    });
+
+    let timeout = options
+        .request_timeout_ms
+        .map(|timeout| Duration::from_millis(timeout as u64));
+    let max_respawns = options.max_respawns.unwrap_or(DEFAULT_MAX_RESPAWNS);
+    let mut exited = vec![];
+    for (id, worker) in state.workers.iter_mut() {
+        match worker.child.try_wait() {
+            Ok(Some(status)) => exited.push((*id, status.code())),
+            Ok(None) => {
+                let hung = timeout.is_some_and(|timeout| {
+                    worker
+                        .requests
+                        .values()
+                        .any(|started| started.elapsed() > timeout)
+                });
+                if hung && worker.hung_requests.is_none() {
+                    warn!("the plugin worker {} hung, killing it", worker.info.key);
+                    let requests = worker
+                        .requests
+                        .drain()
+                        .map(|(tx, _)| tx)
+                        .collect::<Vec<_>>();
+                    kill_tree(worker.info.pid, Some("SIGKILL".into())).ok();
+                    events.push(PluginWorkerEvent {
+                        kind: PluginWorkerEventKind::hung,
+                        worker: worker.info.clone(),
+                        requests: requests.clone(),
+                        exit_code: None,
+                    });
+                    worker.hung_requests = Some(requests);
+                }
+            }
+            Err(e) => warn!(
+                "failed to check the plugin worker {}: {}",
+                worker.info.key, e
+            ),
+        }
+    }
+
+    exited.sort();
+    for (id, exit_code) in exited {
+        let Some(mut worker) = state.workers.remove(&id) else {
+            continue;
+        };
+        // the requests of a hung worker were already reported
+        let requests = match worker.hung_requests {
+            Some(_) => vec![],
+            None => worker.requests.drain().map(|(tx, _)| tx).collect(),
+        };
+        debug!(
+            "the plugin worker {} exited with {:?}",
+            worker.info.key, exit_code
+        );
+
+        let respawns = worker.info.respawns + 1;
+        let respawned = if respawns <= max_respawns {
+            spawn_worker(options, state, id, worker.info.key.clone(), respawns)
+                .map_err(|e| {
+                    warn!(
+                        "failed to restart the plugin worker {}: {}",
+                        worker.info.key, e
+                    )
+                })
+                .ok()
+        } else {
+            None
+        };
+        match respawned {
+            Some(respawned) => {
+                events.push(PluginWorkerEvent {
+                    kind: PluginWorkerEventKind::respawned,
+                    worker: respawned.info.clone(),
+                    requests,
+                    exit_code,
+                });
+                state.workers.insert(id, respawned);
+            }
+            None => events.push(PluginWorkerEvent {
+                kind: PluginWorkerEventKind::exited,
+                worker: worker.info,
+                requests,
+                exit_code,
+            }),
+        }
+    }
+    events
+}
+
+fn spawn_worker(
+    options: &PluginWorkerPoolOptions,
+    state: &mut PoolState,
+    id: u32,
+    key: String,
+    respawns: u32,
+) -> anyhow::Result<WorkerProcess> {
+    // every process listens on its own socket, the socket of a process that crashed may not have been removed
+    let socket_path = options
+        .socket_path
+        .replace("{id}", &state.next_process.to_string());
+    state.next_process += 1;
+
+    let mut command = Command::new(&options.program);
+    command
+        .args(&options.args)
+        .arg(&socket_path)
+        .envs(options.env.iter().flatten())
+        .stdin(Stdio::null())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit());
+    detach(&mut command);
+
+    let child = command
+        .spawn()
+        .with_context(|| format!("Failed to start the plugin worker {}", key))?;
+    trace!("started the plugin worker {} ({})", key, child.id());
+    Ok(WorkerProcess {
+        info: PluginWorker {
+            id,
+            key,
+            pid: child.id(),
+            socket_path,
+            respawns,
+        },
+        child,
+        requests: HashMap::new(),
+        hung_requests: None,
+    })
+}
+
+/// Starts the workers in their own process groups, without a window on Windows,
+/// so the signals of the terminal are handled by Nx which shuts them down
+fn detach(command: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn options(script: &str, request_timeout_ms: Option<u32>) -> PluginWorkerPoolOptions {
+        PluginWorkerPoolOptions {
+            program: "sh".into(),
+            args: vec!["-c".into(), script.into()],
+            env: None,
+            socket_path: "/tmp/plugin-{id}.sock".into(),
+            request_timeout_ms,
+            max_respawns: Some(1),
+            health_check_interval_ms: None,
+        }
+    }
+
+    fn wait_for_events(
+        options: &PluginWorkerPoolOptions,
+        state: &mut PoolState,
+        count: usize,
+    ) -> Vec<PluginWorkerEvent> {
+        let mut events = vec![];
+        let started = Instant::now();
+        while events.len() < count && started.elapsed() < Duration::from_secs(10) {
+            std::thread::sleep(Duration::from_millis(20));
+            events.extend(check_workers(options, state));
+        }
+        events
+    }
+
+    #[test]
+    fn should_respawn_workers_that_crash() {
+        let options = options("exit 3", None);
+        let mut state = PoolState::default();
+        let worker = spawn_worker(&options, &mut state, 0, "plugin".into(), 0).unwrap();
+        assert_eq!(worker.info.socket_path, "/tmp/plugin-0.sock");
+        state.workers.insert(0, worker);
+        state
+            .workers
+            .get_mut(&0)
+            .unwrap()
+            .requests
+            .insert("tx".into(), Instant::now());
+
+        let events = wait_for_events(&options, &mut state, 2);
+        assert_eq!(
+            events
+                .iter()
+                .map(|event| (&event.kind, event.worker.respawns, event.exit_code))
+                .collect::<Vec<_>>(),
+            vec![
+                (&PluginWorkerEventKind::respawned, 1, Some(3)),
+                (&PluginWorkerEventKind::exited, 1, Some(3)),
+            ]
+        );
+        assert_eq!(events[0].requests, vec!["tx"]);
+        assert_eq!(events[0].worker.socket_path, "/tmp/plugin-1.sock");
+        assert!(state.workers.is_empty());
+    }
+
+    #[test]
+    fn should_kill_hung_workers() {
+        let options = options("sleep 30", Some(50));
+        let mut state = PoolState::default();
+        let mut worker = spawn_worker(&options, &mut state, 0, "plugin".into(), 0).unwrap();
+        worker.requests.insert("tx".into(), Instant::now());
+        state.workers.insert(0, worker);
+
+        let events = wait_for_events(&options, &mut state, 2);
+        assert_eq!(events[0].kind, PluginWorkerEventKind::hung);
+        assert_eq!(events[0].requests, vec!["tx"]);
+        assert_eq!(events[1].kind, PluginWorkerEventKind::respawned);
+        assert!(events[1].requests.is_empty());
+        state.workers.drain().for_each(|(_, mut worker)| {
+            worker.child.kill().ok();
+            worker.child.wait().ok();
+        });
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use anyhow::bail;
+use napi::bindgen_prelude::External;
+
+use crate::native::project_graph::types::ProjectGraph;
+use crate::native::project_graph::utils::{create_project_root_mappings, find_project_for_path};
+
+#[napi]
+/// The projects and the external nodes that are affected by changes to `changed_files`.
+/// A project is touched when one of its files changed, or when it is in `touched_projects`
+/// (projects found by the other locators of `nx affected`), and the nodes that depend on a
+/// touched node, directly or not, are affected as well
+pub fn affected_projects(
+    changed_files: Vec<String>,
+    project_graph: External<ProjectGraph>,
+    touched_projects: Option<Vec<String>>,
+) -> anyhow::Result<Vec<String>> {
+    let project_root_mappings = create_project_root_mappings(&project_graph.nodes);
+    let mut touched: Vec<String> = changed_files
+        .iter()
+        .filter_map(|file| find_project_for_path(file, &project_root_mappings))
+        .map(String::from)
+        .collect();
+    touched.extend(touched_projects.unwrap_or_default());
+
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (source, targets) in &project_graph.dependencies {
+        for target in targets {
+            dependents.entry(target).or_default().push(source);
+        }
+    }
+
+    let mut affected: HashSet<&str> = HashSet::new();
+    let mut queue = VecDeque::new();
+    for project in &touched {
+        if !project_graph.nodes.contains_key(project)
+            && !project_graph.external_nodes.contains_key(project)
+        {
+            bail!("Invalid project name is detected: \"{}\"", project);
+        }
+        if affected.insert(project) {
+            queue.push_back(project.as_str());
+        }
+    }
+    while let Some(project) = queue.pop_front() {
+        for &dependent in dependents.get(project).into_iter().flatten() {
+            if affected.insert(dependent) {
+                queue.push_back(dependent);
+            }
+        }
+    }
+
+    let mut affected: Vec<String> = affected.into_iter().map(String::from).collect();
+    affected.sort();
+    Ok(affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::native::project_graph::types::{ExternalNode, Project};
+
+    fn project(root: &str) -> Project {
+        Project {
+            root: root.into(),
+            ..Default::default()
+        }
+    }
+
+    fn graph() -> External<ProjectGraph> {
+        External::new(ProjectGraph {
+            nodes: HashMap::from([
+                ("app".into(), project("apps/app")),
+                ("app-e2e".into(), project("apps/app-e2e")),
+                ("feature".into(), project("libs/feature")),
+                ("ui".into(), project("libs/ui")),
+                ("util".into(), project("libs/util")),
+                ("other".into(), project("libs/other")),
+            ]),
+            dependencies: HashMap::from([
+                ("app-e2e".into(), vec!["app".into()]),
+                ("app".into(), vec!["feature".into(), "ui".into()]),
+                ("feature".into(), vec!["ui".into(), "npm:lodash".into()]),
+                ("ui".into(), vec!["util".into()]),
+                // cycles do not loop forever
+                ("util".into(), vec!["ui".into()]),
+                ("other".into(), vec![]),
+            ]),
+            external_nodes: HashMap::from([(
+                "npm:lodash".into(),
+                ExternalNode {
+                    package_name: Some("lodash".into()),
+                    version: "4.17.21".into(),
+                    hash: None,
+                },
+            )]),
+        })
+    }
+
+    #[test]
+    fn should_include_the_dependents_of_touched_projects() {
+        assert_eq!(
+            affected_projects(vec!["libs/feature/src/index.ts".into()], graph(), None).unwrap(),
+            vec!["app", "app-e2e", "feature"]
+        );
+        assert_eq!(
+            affected_projects(
+                vec!["libs/util/src/index.ts".into(), "README.md".into()],
+                graph(),
+                None
+            )
+            .unwrap(),
+            vec!["app", "app-e2e", "feature", "ui", "util"]
+        );
+        assert_eq!(
+            affected_projects(vec![], graph(), Some(vec!["npm:lodash".into()])).unwrap(),
+            vec!["app", "app-e2e", "feature", "npm:lodash"]
+        );
+        assert!(affected_projects(vec![], graph(), Some(vec!["unknown".into()])).is_err());
+    }
+}
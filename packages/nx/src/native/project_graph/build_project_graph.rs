@@ -0,0 +1,311 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use tracing::trace;
+
+use crate::native::plugins::js::ts_import_locators::{find_imports, ImportResult};
+use crate::native::project_graph::types::{ExternalNode, Project, ProjectGraph};
+use crate::native::project_graph::utils::{
+    create_project_root_mappings, find_project_for_path, ProjectRootMappings,
+};
+
+/// The files that are scanned for imports
+const MODULE_EXTENSIONS: &[&str] = &[".ts", ".js", ".tsx", ".jsx", ".mts", ".mjs", ".cjs", ".cts"];
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct ExplicitDependency {
+    pub source: String,
+    pub target: String,
+}
+
+#[napi]
+/// Builds the project graph of the projects and of the files of each project.
+/// The dependencies are the imports of the files that resolve to other projects or to external nodes,
+/// and the explicit dependencies (from `package.json` files, `implicitDependencies`, or plugins)
+pub fn build_project_graph(
+    workspace_root: String,
+    projects: HashMap<String, Project>,
+    project_file_map: HashMap<String, Vec<String>>,
+    external_nodes: Option<HashMap<String, ExternalNode>>,
+    explicit_dependencies: Option<Vec<ExplicitDependency>>,
+    ts_config_paths: Option<HashMap<String, Vec<String>>>,
+) -> anyhow::Result<ProjectGraph> {
+    let external_nodes = external_nodes.unwrap_or_default();
+    let ts_config_paths = ts_config_paths.unwrap_or_default();
+
+    let files_to_process = project_file_map
+        .into_iter()
+        .map(|(project, files)| {
+            let files = files
+                .into_iter()
+                .filter(|file| MODULE_EXTENSIONS.iter().any(|ext| file.ends_with(ext)))
+                .map(|file| Path::new(&workspace_root).join(file).display().to_string())
+                .collect();
+            (project, files)
+        })
+        .collect();
+    let imports = find_imports(files_to_process)?;
+
+    let resolver = ImportResolver {
+        project_root_mappings: create_project_root_mappings(&projects),
+        ts_config_paths: &ts_config_paths,
+        external_nodes: &external_nodes,
+    };
+
+    let mut dependencies: HashMap<String, BTreeSet<String>> = projects
+        .keys()
+        .map(|project| (project.clone(), BTreeSet::new()))
+        .collect();
+    for ImportResult {
+        file,
+        source_project,
+        static_import_expressions,
+        dynamic_import_expressions,
+    } in imports
+    {
+        let file = relative_to_workspace(&workspace_root, &file);
+        for specifier in static_import_expressions
+            .iter()
+            .chain(dynamic_import_expressions.iter())
+        {
+            let Some(target) = resolver.resolve(specifier, &file) else {
+                trace!("{} in {} is not a dependency", specifier, file);
+                continue;
+            };
+            // TODO: These edges technically should be allowed but we need to figure out how to separate config files out from root
+            if target == source_project
+                || (!is_root(&projects, &source_project) && is_root(&projects, &target))
+            {
+                continue;
+            }
+            if let Some(targets) = dependencies.get_mut(&source_project) {
+                targets.insert(target);
+            }
+        }
+    }
+
+    for ExplicitDependency { source, target } in explicit_dependencies.unwrap_or_default() {
+        let is_node =
+            |name: &String| projects.contains_key(name) || external_nodes.contains_key(name);
+        if source == target || !is_node(&source) || !is_node(&target) {
+            continue;
+        }
+        dependencies.entry(source).or_default().insert(target);
+    }
+
+    Ok(ProjectGraph {
+        nodes: projects,
+        dependencies: dependencies
+            .into_iter()
+            .map(|(source, targets)| (source, targets.into_iter().collect()))
+            .collect(),
+        external_nodes,
+    })
+}
+
+fn is_root(projects: &HashMap<String, Project>, project: &str) -> bool {
+    projects
+        .get(project)
+        .is_some_and(|project| project.root == ".")
+}
+
+fn relative_to_workspace(workspace_root: &str, file: &str) -> String {
+    Path::new(file)
+        .strip_prefix(workspace_root)
+        .unwrap_or(Path::new(file))
+        .display()
+        .to_string()
+        .replace('\\', "/")
+}
+
+/// Finds the project or the external node that an import specifier refers to
+struct ImportResolver<'a> {
+    project_root_mappings: ProjectRootMappings,
+    ts_config_paths: &'a HashMap<String, Vec<String>>,
+    external_nodes: &'a HashMap<String, ExternalNode>,
+}
+
+impl ImportResolver<'_> {
+    fn resolve(&self, specifier: &str, source_file: &str) -> Option<String> {
+        if specifier.starts_with("./") || specifier.starts_with("../") {
+            let source_dir = source_file.rsplit_once('/').map_or("", |(dir, _)| dir);
+            return self.find_project(&join_relative(source_dir, specifier));
+        }
+        if let Some(path) = self.resolve_ts_config_path(specifier) {
+            return self.find_project(&path);
+        }
+        let external_node = format!("npm:{}", package_name(specifier));
+        self.external_nodes
+            .contains_key(&external_node)
+            .then_some(external_node)
+    }
+
+    fn find_project(&self, path: &str) -> Option<String> {
+        find_project_for_path(path, &self.project_root_mappings).map(String::from)
+    }
+
+    /// The path of the first mapping of the `paths` of the root tsconfig that matches the specifier.
+    /// An exact mapping wins over wildcards, and the wildcard with the longest prefix wins over the others
+    fn resolve_ts_config_path(&self, specifier: &str) -> Option<String> {
+        if let Some(paths) = self.ts_config_paths.get(specifier) {
+            return paths.first().map(|path| normalize_path(path));
+        }
+        self.ts_config_paths
+            .iter()
+            .filter_map(|(pattern, paths)| {
+                let (prefix, suffix) = pattern.split_once('*')?;
+                let matched = specifier.strip_prefix(prefix)?.strip_suffix(suffix)?;
+                let path = paths.first()?.replacen('*', matched, 1);
+                Some((prefix.len(), normalize_path(&path)))
+            })
+            .max_by_key(|(prefix_len, _)| *prefix_len)
+            .map(|(_, path)| path)
+    }
+}
+
+/// `lodash/fp` is in the `lodash` package and `@nx/js/src/utils` in the `@nx/js` package
+fn package_name(specifier: &str) -> &str {
+    let segments = if specifier.starts_with('@') { 2 } else { 1 };
+    match specifier.match_indices('/').nth(segments - 1) {
+        Some((index, _)) => &specifier[..index],
+        None => specifier,
+    }
+}
+
+fn normalize_path(path: &str) -> String {
+    join_relative("", path)
+}
+
+/// Joins `path` to `dir` without touching the file system, `.` and `..` segments are removed
+fn join_relative(dir: &str, path: &str) -> String {
+    let mut segments: Vec<&str> = dir.split('/').filter(|s| !s.is_empty()).collect();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    fn project(root: &str) -> Project {
+        Project {
+            root: root.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn should_build_dependencies_from_imports_and_explicit_dependencies() {
+        let temp = TempDir::new().unwrap();
+        temp.child("apps/app/src/main.ts")
+            .write_str(
+                r#"
+                import { ui } from '@proj/ui';
+                import { button } from '@proj/ui/button';
+                import { util } from '../../../libs/util/src/index';
+                import { map } from 'lodash/fp';
+                const lazy = import('@proj/feature');
+                import { missing } from 'not-installed';
+                "#,
+            )
+            .unwrap();
+        temp.child("libs/ui/src/index.ts")
+            .write_str("import { util } from '@proj/util'; import './button';")
+            .unwrap();
+        temp.child("libs/ui/README.md")
+            .write_str("import '@proj/feature';")
+            .unwrap();
+        temp.child("libs/feature/src/index.ts")
+            .write_str("import { root } from '../../../tools/root';")
+            .unwrap();
+        temp.child("tools/root.ts")
+            .write_str("import { ui } from '@proj/ui';")
+            .unwrap();
+
+        let graph = build_project_graph(
+            temp.display().to_string(),
+            HashMap::from([
+                ("app".into(), project("apps/app")),
+                ("ui".into(), project("libs/ui")),
+                ("util".into(), project("libs/util")),
+                ("feature".into(), project("libs/feature")),
+                ("root".into(), project(".")),
+            ]),
+            HashMap::from([
+                ("app".into(), vec!["apps/app/src/main.ts".into()]),
+                (
+                    "ui".into(),
+                    vec!["libs/ui/src/index.ts".into(), "libs/ui/README.md".into()],
+                ),
+                ("feature".into(), vec!["libs/feature/src/index.ts".into()]),
+                ("root".into(), vec!["tools/root.ts".into()]),
+            ]),
+            Some(HashMap::from([(
+                "npm:lodash".into(),
+                ExternalNode {
+                    package_name: Some("lodash".into()),
+                    version: "4.17.21".into(),
+                    hash: None,
+                },
+            )])),
+            Some(vec![
+                ExplicitDependency {
+                    source: "util".into(),
+                    target: "npm:lodash".into(),
+                },
+                ExplicitDependency {
+                    source: "util".into(),
+                    target: "unknown".into(),
+                },
+            ]),
+            Some(HashMap::from([
+                ("@proj/ui".into(), vec!["libs/ui/src/index.ts".into()]),
+                ("@proj/ui/*".into(), vec!["./libs/ui/src/lib/*".into()]),
+                ("@proj/util".into(), vec!["libs/util/src/index.ts".into()]),
+                (
+                    "@proj/feature".into(),
+                    vec!["libs/feature/src/index.ts".into()],
+                ),
+            ])),
+        )
+        .unwrap();
+
+        assert_eq!(
+            graph.dependencies["app"],
+            vec!["feature", "npm:lodash", "ui", "util"]
+        );
+        assert_eq!(graph.dependencies["ui"], vec!["util"]);
+        // projects do not depend on the root project
+        assert!(graph.dependencies["feature"].is_empty());
+        assert_eq!(graph.dependencies["root"], vec!["ui"]);
+        assert_eq!(graph.dependencies["util"], vec!["npm:lodash"]);
+        assert_eq!(graph.nodes.len(), 5);
+        assert_eq!(graph.external_nodes.len(), 1);
+    }
+
+    #[test]
+    fn should_resolve_specifiers() {
+        assert_eq!(package_name("lodash"), "lodash");
+        assert_eq!(package_name("lodash/fp"), "lodash");
+        assert_eq!(package_name("@nx/js"), "@nx/js");
+        assert_eq!(package_name("@nx/js/src/utils"), "@nx/js");
+
+        assert_eq!(join_relative("libs/ui/src", "../../util/x"), "libs/util/x");
+        assert_eq!(
+            join_relative("libs/ui", "./lib/./button"),
+            "libs/ui/lib/button"
+        );
+        assert_eq!(normalize_path("./libs/ui/"), "libs/ui");
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::{HashMap, HashSet};
+
+use napi::bindgen_prelude::External;
+
+use crate::native::project_graph::types::ProjectGraph;
+
+#[napi]
+/// Every elementary cycle of the dependencies between the projects of the graph, found with Johnson's algorithm.
+/// A cycle is the path of the projects it goes through, starting from the first of them in alphabetical order:
+/// `["a", "b", "c"]` is `a -> b -> c -> a`.
+/// The number of cycles can grow exponentially with the size of the graph, so at most `limit` cycles are returned
+pub fn find_cycles(project_graph: External<ProjectGraph>, limit: Option<u32>) -> Vec<Vec<String>> {
+    let mut projects: Vec<&str> = project_graph.nodes.keys().map(String::as_str).collect();
+    projects.sort();
+    let indexes: HashMap<&str, usize> = projects
+        .iter()
+        .enumerate()
+        .map(|(index, project)| (*project, index))
+        .collect();
+    let adjacency: Vec<Vec<usize>> = projects
+        .iter()
+        .map(|project| {
+            let mut targets: Vec<usize> = project_graph
+                .dependencies
+                .get(*project)
+                .into_iter()
+                .flatten()
+                .filter_map(|target| indexes.get(target.as_str()).copied())
+                .collect();
+            targets.sort();
+            targets.dedup();
+            targets
+        })
+        .collect();
+
+    CycleFinder::new(&adjacency, limit.map(|limit| limit as usize))
+        .find()
+        .into_iter()
+        .map(|cycle| {
+            cycle
+                .into_iter()
+                .map(|index| projects[index].to_string())
+                .collect()
+        })
+        .collect()
+}
+
+struct CycleFinder<'a> {
+    adjacency: &'a [Vec<usize>],
+    limit: Option<usize>,
+    cycles: Vec<Vec<usize>>,
+    // the state of the search of the cycles that start from `start`
+    start: usize,
+    component: HashSet<usize>,
+    stack: Vec<usize>,
+    blocked: Vec<bool>,
+    blocked_by: Vec<HashSet<usize>>,
+}
+
+impl<'a> CycleFinder<'a> {
+    fn new(adjacency: &'a [Vec<usize>], limit: Option<usize>) -> Self {
+        Self {
+            adjacency,
+            limit,
+            cycles: vec![],
+            start: 0,
+            component: HashSet::new(),
+            stack: vec![],
+            blocked: vec![false; adjacency.len()],
+            blocked_by: vec![HashSet::new(); adjacency.len()],
+        }
+    }
+
+    fn find(mut self) -> Vec<Vec<usize>> {
+        let mut start = 0;
+        while start < self.adjacency.len() && !self.is_done() {
+            // the cycles through the vertices before `start` were already found,
+            // the next cycles are in the component of the least vertex that is in a cycle
+            let Some(component) = cyclic_components(self.adjacency, start)
+                .into_iter()
+                .min_by_key(|component| component.iter().min().copied())
+            else {
+                break;
+            };
+            self.start = *component.iter().min().expect("components are not empty");
+            for &vertex in &component {
+                self.blocked[vertex] = false;
+                self.blocked_by[vertex].clear();
+            }
+            self.component = component;
+            self.circuit(self.start);
+            start = self.start + 1;
+        }
+        self.cycles
+    }
+
+    fn is_done(&self) -> bool {
+        self.limit.is_some_and(|limit| self.cycles.len() >= limit)
+    }
+
+    fn circuit(&mut self, vertex: usize) -> bool {
+        let mut found = false;
+        self.stack.push(vertex);
+        self.blocked[vertex] = true;
+        let adjacency = self.adjacency;
+        for &next in &adjacency[vertex] {
+            if self.is_done() {
+                return true;
+            }
+            if !self.component.contains(&next) {
+                continue;
+            }
+            if next == self.start {
+                self.cycles.push(self.stack.clone());
+                found = true;
+            } else if !self.blocked[next] && self.circuit(next) {
+                found = true;
+            }
+        }
+        if found {
+            self.unblock(vertex);
+        } else {
+            for &next in &adjacency[vertex] {
+                if self.component.contains(&next) {
+                    self.blocked_by[next].insert(vertex);
+                }
+            }
+        }
+        self.stack.pop();
+        found
+    }
+
+    fn unblock(&mut self, vertex: usize) {
+        let mut to_unblock = vec![vertex];
+        while let Some(vertex) = to_unblock.pop() {
+            self.blocked[vertex] = false;
+            for blocked in std::mem::take(&mut self.blocked_by[vertex]) {
+                if self.blocked[blocked] {
+                    to_unblock.push(blocked);
+                }
+            }
+        }
+    }
+}
+
+/// The strongly connected components (found with Tarjan's algorithm) of the subgraph of the vertices from `start`,
+/// that have a cycle: more than one vertex, or a vertex that depends on itself
+fn cyclic_components(adjacency: &[Vec<usize>], start: usize) -> Vec<HashSet<usize>> {
+    struct Tarjan<'a> {
+        adjacency: &'a [Vec<usize>],
+        start: usize,
+        next_index: usize,
+        indexes: Vec<Option<usize>>,
+        low_links: Vec<usize>,
+        on_stack: Vec<bool>,
+        stack: Vec<usize>,
+        components: Vec<HashSet<usize>>,
+    }
+
+    impl Tarjan<'_> {
+        fn visit(&mut self, vertex: usize) {
+            self.indexes[vertex] = Some(self.next_index);
+            self.low_links[vertex] = self.next_index;
+            self.next_index += 1;
+            self.stack.push(vertex);
+            self.on_stack[vertex] = true;
+
+            let (adjacency, start) = (self.adjacency, self.start);
+            for &next in adjacency[vertex].iter().filter(|next| **next >= start) {
+                match self.indexes[next] {
+                    None => {
+                        self.visit(next);
+                        self.low_links[vertex] = self.low_links[vertex].min(self.low_links[next]);
+                    }
+                    Some(index) if self.on_stack[next] => {
+                        self.low_links[vertex] = self.low_links[vertex].min(index);
+                    }
+                    _ => {}
+                }
+            }
+
+            if Some(self.low_links[vertex]) == self.indexes[vertex] {
+                let mut component = HashSet::new();
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack[member] = false;
+                    component.insert(member);
+                    if member == vertex {
+                        break;
+                    }
+                }
+                if component.len() > 1 || adjacency[vertex].contains(&vertex) {
+                    self.components.push(component);
+                }
+            }
+        }
+    }
+
+    let mut tarjan = Tarjan {
+        adjacency,
+        start,
+        next_index: 0,
+        indexes: vec![None; adjacency.len()],
+        low_links: vec![0; adjacency.len()],
+        on_stack: vec![false; adjacency.len()],
+        stack: vec![],
+        components: vec![],
+    };
+    for vertex in start..adjacency.len() {
+        if tarjan.indexes[vertex].is_none() {
+            tarjan.visit(vertex);
+        }
+    }
+    tarjan.components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::native::project_graph::types::Project;
+
+    fn graph(dependencies: &[(&str, &[&str])]) -> External<ProjectGraph> {
+        External::new(ProjectGraph {
+            nodes: dependencies
+                .iter()
+                .map(|(project, _)| (project.to_string(), Project::default()))
+                .collect(),
+            dependencies: dependencies
+                .iter()
+                .map(|(project, targets)| {
+                    (
+                        project.to_string(),
+                        targets.iter().map(|target| target.to_string()).collect(),
+                    )
+                })
+                .collect(),
+            external_nodes: HashMap::new(),
+        })
+    }
+
+    #[test]
+    fn should_find_every_cycle() {
+        let cycles = find_cycles(
+            graph(&[
+                ("a", &["b", "npm:lodash"]),
+                ("b", &["c", "d"]),
+                ("c", &["a"]),
+                ("d", &["a", "b"]),
+                ("e", &["e", "a"]),
+                ("f", &[]),
+            ]),
+            None,
+        );
+        assert_eq!(
+            cycles,
+            vec![
+                vec!["a", "b", "c"],
+                vec!["a", "b", "d"],
+                vec!["b", "d"],
+                vec!["e"],
+            ]
+        );
+    }
+
+    #[test]
+    fn should_find_no_cycles_in_acyclic_graphs() {
+        assert!(find_cycles(
+            graph(&[("a", &["b", "c"]), ("b", &["c"]), ("c", &[])]),
+            None
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn should_stop_at_the_limit() {
+        // every pair of projects of a complete graph is a cycle, among many others
+        let projects = ["a", "b", "c", "d", "e", "f", "g", "h"];
+        let dependencies: Vec<(&str, Vec<&str>)> = projects
+            .iter()
+            .map(|project| (*project, projects.to_vec()))
+            .collect();
+        let dependencies: Vec<(&str, &[&str])> = dependencies
+            .iter()
+            .map(|(project, targets)| (*project, targets.as_slice()))
+            .collect();
+        assert_eq!(find_cycles(graph(&dependencies), Some(10)).len(), 10);
+    }
+}
@@ -0,0 +1,514 @@
+use std::fs::{self, File};
+use std::path::Path;
+
+use anyhow::{anyhow, bail};
+use memmap2::Mmap;
+use napi::bindgen_prelude::{Either7, External};
+use napi::Either;
+use rkyv::{Archive, Deserialize, Infallible, Serialize};
+use tracing::{debug, trace};
+
+use crate::native::project_graph::types::{ExternalNode, Project, ProjectGraph, Target};
+use crate::native::types::{
+    DepsOutputsInput, EnvironmentInput, ExternalDependenciesInput, FileData, FileSetInput,
+    InputsInput, JsInputs, RuntimeInput,
+};
+use crate::native::workspace::types::{NxWorkspaceFilesExternals, ProjectFiles};
+
+const GRAPH_ARCHIVE: &str = "project-graph.nxg";
+const MAGIC: &[u8; 8] = b"NXGRAPH\0";
+/// Changed whenever the layout of the archive changes, archives of other versions are not read
+const FORMAT_VERSION: u32 = 2;
+/// The magic, the format version, and 4 bytes of padding, which keeps the archive aligned in the memory map
+const HEADER_LEN: usize = 16;
+
+#[derive(Archive, Serialize, Deserialize)]
+#[archive(check_bytes)]
+struct StoredGraph {
+    nodes: Vec<(String, StoredProject)>,
+    dependencies: Vec<(String, Vec<String>)>,
+    external_nodes: Vec<(String, StoredExternalNode)>,
+    project_files: Vec<(String, Vec<StoredFile>)>,
+    global_files: Vec<StoredFile>,
+    all_workspace_files: Vec<StoredFile>,
+}
+
+#[derive(Archive, Serialize, Deserialize)]
+#[archive(check_bytes)]
+struct StoredProject {
+    root: String,
+    named_inputs: Option<Vec<(String, Vec<StoredInput>)>>,
+    tags: Option<Vec<String>>,
+    targets: Vec<(String, StoredTarget)>,
+}
+
+#[derive(Archive, Serialize, Deserialize)]
+#[archive(check_bytes)]
+struct StoredTarget {
+    executor: Option<String>,
+    inputs: Option<Vec<StoredInput>>,
+    outputs: Option<Vec<String>>,
+    options: Option<String>,
+    configurations: Option<String>,
+    parallelism: Option<bool>,
+}
+
+#[derive(Archive, Serialize, Deserialize)]
+#[archive(check_bytes)]
+enum StoredInput {
+    Inputs {
+        input: String,
+        dependencies: Option<bool>,
+        projects: Option<StoredProjects>,
+    },
+    String(String),
+    FileSet {
+        fileset: String,
+        case_insensitive: Option<bool>,
+    },
+    Runtime(String),
+    Environment(String),
+    ExternalDependencies(Vec<String>),
+    DepsOutputs {
+        dependent_tasks_output_files: String,
+        transitive: Option<bool>,
+    },
+}
+
+#[derive(Archive, Serialize, Deserialize)]
+#[archive(check_bytes)]
+enum StoredProjects {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Archive, Serialize, Deserialize)]
+#[archive(check_bytes)]
+struct StoredExternalNode {
+    package_name: Option<String>,
+    version: String,
+    hash: Option<String>,
+}
+
+#[derive(Archive, Serialize, Deserialize)]
+#[archive(check_bytes)]
+struct StoredFile(String, String);
+
+#[napi(object)]
+pub struct ProjectGraphArchive {
+    pub project_graph: External<ProjectGraph>,
+    pub files: NxWorkspaceFilesExternals,
+}
+
+#[napi]
+/// Writes the project graph and the files of the workspace to a binary archive in `cache_dir`,
+/// which is much faster to load than the JSON of the graph
+pub fn write_project_graph_archive(
+    cache_dir: String,
+    project_graph: External<ProjectGraph>,
+    files: NxWorkspaceFilesExternals,
+) -> anyhow::Result<()> {
+    let now = std::time::Instant::now();
+    let stored = StoredGraph::new(&project_graph, &files);
+    let archive = rkyv::to_bytes::<_, 4096>(&stored).map_err(|e| anyhow!("{}", e))?;
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN + archive.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.resize(HEADER_LEN, 0);
+    bytes.extend_from_slice(&archive);
+
+    // the archive is replaced at once, so that a daemon starting at the same time never maps a partial archive
+    let archive_path = Path::new(&cache_dir).join(GRAPH_ARCHIVE);
+    let temp_path = archive_path.with_extension(format!("nxg.{}", std::process::id()));
+    fs::write(&temp_path, bytes)?;
+    fs::rename(&temp_path, &archive_path)?;
+    trace!("wrote the project graph archive in {:?}", now.elapsed());
+    Ok(())
+}
+
+#[napi]
+/// Loads the archive written by `writeProjectGraphArchive`.
+/// There is no archive when it was not written, or when it was written by a version of Nx with another format
+pub fn read_project_graph_archive(cache_dir: String) -> Option<ProjectGraphArchive> {
+    let now = std::time::Instant::now();
+    match read_archive(&Path::new(&cache_dir).join(GRAPH_ARCHIVE)) {
+        Ok(archive) => {
+            trace!("read the project graph archive in {:?}", now.elapsed());
+            Some(archive)
+        }
+        Err(e) => {
+            debug!("could not read the project graph archive: {:?}", e);
+            None
+        }
+    }
+}
+
+fn read_archive(archive_path: &Path) -> anyhow::Result<ProjectGraphArchive> {
+    let file = File::open(archive_path)?;
+    // Safety: the archive is only replaced by renames, so the mapped file is never written to
+    let mmap = unsafe { Mmap::map(&file)? };
+    if mmap.len() < HEADER_LEN || &mmap[..MAGIC.len()] != MAGIC {
+        bail!("{:?} is not a project graph archive", archive_path);
+    }
+    let version = u32::from_le_bytes(mmap[MAGIC.len()..MAGIC.len() + 4].try_into()?);
+    if version != FORMAT_VERSION {
+        bail!(
+            "the project graph archive has the format {} instead of {}",
+            version,
+            FORMAT_VERSION
+        );
+    }
+
+    let archived = rkyv::check_archived_root::<StoredGraph>(&mmap[HEADER_LEN..])
+        .map_err(|e| anyhow!("invalid project graph archive: {}", e))?;
+    let stored: StoredGraph = archived.deserialize(&mut Infallible)?;
+    Ok(stored.into_archive())
+}
+
+impl StoredGraph {
+    fn new(project_graph: &ProjectGraph, files: &NxWorkspaceFilesExternals) -> Self {
+        Self {
+            nodes: project_graph
+                .nodes
+                .iter()
+                .map(|(name, project)| (name.clone(), StoredProject::from(project)))
+                .collect(),
+            dependencies: project_graph
+                .dependencies
+                .iter()
+                .map(|(source, targets)| (source.clone(), targets.clone()))
+                .collect(),
+            external_nodes: project_graph
+                .external_nodes
+                .iter()
+                .map(|(name, node)| {
+                    (
+                        name.clone(),
+                        StoredExternalNode {
+                            package_name: node.package_name.clone(),
+                            version: node.version.clone(),
+                            hash: node.hash.clone(),
+                        },
+                    )
+                })
+                .collect(),
+            project_files: files
+                .project_files
+                .iter()
+                .map(|(project, files)| (project.clone(), stored_files(files)))
+                .collect(),
+            global_files: stored_files(&files.global_files),
+            all_workspace_files: stored_files(&files.all_workspace_files),
+        }
+    }
+
+    fn into_archive(self) -> ProjectGraphArchive {
+        let project_files: ProjectFiles = self
+            .project_files
+            .into_iter()
+            .map(|(project, files)| (project, file_data(files)))
+            .collect();
+        ProjectGraphArchive {
+            project_graph: External::new(ProjectGraph {
+                nodes: self
+                    .nodes
+                    .into_iter()
+                    .map(|(name, project)| (name, project.into()))
+                    .collect(),
+                dependencies: self.dependencies.into_iter().collect(),
+                external_nodes: self
+                    .external_nodes
+                    .into_iter()
+                    .map(|(name, node)| {
+                        (
+                            name,
+                            ExternalNode {
+                                package_name: node.package_name,
+                                version: node.version,
+                                hash: node.hash,
+                            },
+                        )
+                    })
+                    .collect(),
+            }),
+            files: NxWorkspaceFilesExternals {
+                project_files: External::new(project_files),
+                global_files: External::new(file_data(self.global_files)),
+                all_workspace_files: External::new(file_data(self.all_workspace_files)),
+            },
+        }
+    }
+}
+
+fn stored_files(files: &[FileData]) -> Vec<StoredFile> {
+    files
+        .iter()
+        .map(|file| StoredFile(file.file.clone(), file.hash.clone()))
+        .collect()
+}
+
+fn file_data(files: Vec<StoredFile>) -> Vec<FileData> {
+    files
+        .into_iter()
+        .map(|StoredFile(file, hash)| FileData { file, hash })
+        .collect()
+}
+
+impl From<&Project> for StoredProject {
+    fn from(project: &Project) -> Self {
+        Self {
+            root: project.root.clone(),
+            named_inputs: project.named_inputs.as_ref().map(|named_inputs| {
+                named_inputs
+                    .iter()
+                    .map(|(name, inputs)| (name.clone(), stored_inputs(inputs)))
+                    .collect()
+            }),
+            tags: project.tags.clone(),
+            targets: project
+                .targets
+                .iter()
+                .map(|(name, target)| {
+                    (
+                        name.clone(),
+                        StoredTarget {
+                            executor: target.executor.clone(),
+                            inputs: target.inputs.as_deref().map(stored_inputs),
+                            outputs: target.outputs.clone(),
+                            options: target.options.clone(),
+                            configurations: target.configurations.clone(),
+                            parallelism: target.parallelism,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<StoredProject> for Project {
+    fn from(project: StoredProject) -> Self {
+        Self {
+            root: project.root,
+            named_inputs: project.named_inputs.map(|named_inputs| {
+                named_inputs
+                    .into_iter()
+                    .map(|(name, inputs)| (name, js_inputs(inputs)))
+                    .collect()
+            }),
+            tags: project.tags,
+            targets: project
+                .targets
+                .into_iter()
+                .map(|(name, target)| {
+                    (
+                        name,
+                        Target {
+                            executor: target.executor,
+                            inputs: target.inputs.map(js_inputs),
+                            outputs: target.outputs,
+                            options: target.options,
+                            configurations: target.configurations,
+                            parallelism: target.parallelism,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+fn stored_inputs(inputs: &[JsInputs]) -> Vec<StoredInput> {
+    inputs
+        .iter()
+        .map(|input| match input {
+            Either7::A(inputs) => StoredInput::Inputs {
+                input: inputs.input.clone(),
+                dependencies: inputs.dependencies,
+                projects: inputs.projects.as_ref().map(|projects| match projects {
+                    Either::A(project) => StoredProjects::One(project.clone()),
+                    Either::B(projects) => StoredProjects::Many(projects.clone()),
+                }),
+            },
+            Either7::B(string) => StoredInput::String(string.clone()),
+            Either7::C(file_set) => StoredInput::FileSet {
+                fileset: file_set.fileset.clone(),
+                case_insensitive: file_set.case_insensitive,
+            },
+            Either7::D(runtime) => StoredInput::Runtime(runtime.runtime.clone()),
+            Either7::E(environment) => StoredInput::Environment(environment.env.clone()),
+            Either7::F(external_dependencies) => StoredInput::ExternalDependencies(
+                external_dependencies.external_dependencies.clone(),
+            ),
+            Either7::G(deps_outputs) => StoredInput::DepsOutputs {
+                dependent_tasks_output_files: deps_outputs.dependent_tasks_output_files.clone(),
+                transitive: deps_outputs.transitive,
+            },
+        })
+        .collect()
+}
+
+fn js_inputs(inputs: Vec<StoredInput>) -> Vec<JsInputs> {
+    inputs
+        .into_iter()
+        .map(|input| match input {
+            StoredInput::Inputs {
+                input,
+                dependencies,
+                projects,
+            } => Either7::A(InputsInput {
+                input,
+                dependencies,
+                projects: projects.map(|projects| match projects {
+                    StoredProjects::One(project) => Either::A(project),
+                    StoredProjects::Many(projects) => Either::B(projects),
+                }),
+            }),
+            StoredInput::String(string) => Either7::B(string),
+            StoredInput::FileSet {
+                fileset,
+                case_insensitive,
+            } => Either7::C(FileSetInput {
+                fileset,
+                case_insensitive,
+            }),
+            StoredInput::Runtime(runtime) => Either7::D(RuntimeInput { runtime }),
+            StoredInput::Environment(env) => Either7::E(EnvironmentInput { env }),
+            StoredInput::ExternalDependencies(external_dependencies) => {
+                Either7::F(ExternalDependenciesInput {
+                    external_dependencies,
+                })
+            }
+            StoredInput::DepsOutputs {
+                dependent_tasks_output_files,
+                transitive,
+            } => Either7::G(DepsOutputsInput {
+                dependent_tasks_output_files,
+                transitive,
+            }),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    fn file(file: &str) -> FileData {
+        FileData {
+            file: file.into(),
+            hash: format!("{}-hash", file),
+        }
+    }
+
+    #[test]
+    fn should_read_the_archive_that_was_written() {
+        let temp = TempDir::new().unwrap();
+        let cache_dir = temp.display().to_string();
+        assert!(read_project_graph_archive(cache_dir.clone()).is_none());
+
+        let project_graph = External::new(ProjectGraph {
+            nodes: HashMap::from([(
+                "app".into(),
+                Project {
+                    root: "apps/app".into(),
+                    named_inputs: Some(HashMap::from([(
+                        "production".into(),
+                        vec![
+                            Either7::B("default".into()),
+                            Either7::A(InputsInput {
+                                input: "production".into(),
+                                dependencies: Some(true),
+                                projects: Some(Either::B(vec!["lib".into()])),
+                            }),
+                            Either7::C(FileSetInput {
+                                fileset: "{workspaceRoot}/Dockerfile".into(),
+                                case_insensitive: Some(true),
+                            }),
+                        ],
+                    )])),
+                    tags: Some(vec!["scope:app".into()]),
+                    targets: HashMap::from([(
+                        "build".into(),
+                        Target {
+                            executor: Some("nx:run-commands".into()),
+                            inputs: Some(vec![Either7::E(EnvironmentInput {
+                                env: "NODE_ENV".into(),
+                            })]),
+                            ..Default::default()
+                        },
+                    )]),
+                },
+            )]),
+            dependencies: HashMap::from([("app".into(), vec!["npm:lodash".into()])]),
+            external_nodes: HashMap::from([(
+                "npm:lodash".into(),
+                ExternalNode {
+                    package_name: Some("lodash".into()),
+                    version: "4.17.21".into(),
+                    hash: None,
+                },
+            )]),
+        });
+        let files = NxWorkspaceFilesExternals {
+            project_files: External::new(HashMap::from([(
+                "app".into(),
+                vec![file("apps/app/main.ts")],
+            )])),
+            global_files: External::new(vec![file("package.json")]),
+            all_workspace_files: External::new(vec![
+                file("apps/app/main.ts"),
+                file("package.json"),
+            ]),
+        };
+        write_project_graph_archive(cache_dir.clone(), project_graph, files).unwrap();
+
+        let archive = read_project_graph_archive(cache_dir.clone()).unwrap();
+        let app = &archive.project_graph.nodes["app"];
+        assert_eq!(app.root, "apps/app");
+        assert_eq!(app.tags, Some(vec!["scope:app".to_string()]));
+        let production = &app.named_inputs.as_ref().unwrap()["production"];
+        assert!(matches!(&production[0], Either7::B(input) if input == "default"));
+        assert!(matches!(
+            &production[1],
+            Either7::A(InputsInput { projects: Some(Either::B(projects)), .. }) if projects == &["lib"]
+        ));
+        assert!(matches!(
+            &production[2],
+            Either7::C(FileSetInput { fileset, case_insensitive: Some(true) })
+                if fileset == "{workspaceRoot}/Dockerfile"
+        ));
+        let build = &app.targets["build"];
+        assert_eq!(build.executor.as_deref(), Some("nx:run-commands"));
+        assert!(matches!(
+            &build.inputs.as_ref().unwrap()[0],
+            Either7::E(EnvironmentInput { env }) if env == "NODE_ENV"
+        ));
+        assert_eq!(
+            archive.project_graph.dependencies["app"],
+            vec!["npm:lodash"]
+        );
+        assert_eq!(
+            archive.project_graph.external_nodes["npm:lodash"].version,
+            "4.17.21"
+        );
+        assert_eq!(
+            archive.files.project_files["app"],
+            vec![file("apps/app/main.ts")]
+        );
+        assert_eq!(archive.files.global_files.len(), 1);
+        assert_eq!(archive.files.all_workspace_files.len(), 2);
+
+        // archives of other formats are not read
+        let archive_path = temp.join(GRAPH_ARCHIVE);
+        let mut bytes = fs::read(&archive_path).unwrap();
+        bytes[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        fs::write(&archive_path, bytes).unwrap();
+        assert!(read_project_graph_archive(cache_dir).is_none());
+    }
+}
@@ -1,3 +1,12 @@
+pub mod affected;
+pub mod build_project_graph;
+pub mod cycles;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod graph_archive;
+pub mod module_boundaries;
+pub mod ownership;
+pub mod project_filter;
+pub mod target_defaults;
 pub mod transfer_project_graph;
 pub mod types;
 pub mod utils;
@@ -0,0 +1,498 @@
+use std::collections::{HashMap, VecDeque};
+
+use anyhow::Context;
+use regex::Regex;
+
+/// A dependency constraint of the `@nx/enforce-module-boundaries` lint rule
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct DependencyConstraint {
+    pub source_tag: Option<String>,
+    /// The constraint applies to the projects that have all of these tags, instead of `source_tag`
+    pub all_source_tags: Option<Vec<String>>,
+    pub only_depend_on_libs_with_tags: Option<Vec<String>>,
+    pub not_depend_on_libs_with_tags: Option<Vec<String>>,
+    pub allowed_external_imports: Option<Vec<String>>,
+    pub banned_external_imports: Option<Vec<String>>,
+}
+
+/// A node of the project graph, as the module boundaries see it
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct BoundaryNode {
+    pub tags: Option<Vec<String>>,
+    /// The package of an external node, which is not set for the projects of the workspace
+    pub package_name: Option<String>,
+}
+
+/// An import of a source file of `source_project` that resolves to `target_project`
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct ProjectImport {
+    pub file: String,
+    pub specifier: String,
+    pub source_project: String,
+    pub target_project: String,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundaryViolation {
+    pub file: String,
+    pub specifier: String,
+    pub source_project: String,
+    pub target_project: String,
+    /// The id of the message of the lint rule that reports the violation:
+    /// `projectWithoutTagsCannotHaveDependencies`, `bannedExternalImportsViolation`, `onlyTagsConstraintViolation`,
+    /// `emptyOnlyTagsConstraintViolation` or `notTagsConstraintViolation`
+    pub message_id: String,
+    /// The source tags of the violated constraint
+    pub source_tags: Vec<String>,
+    /// The tags of the violated constraint that the target is checked against
+    pub tags: Vec<String>,
+    /// The paths of dependencies from the target to the projects with banned tags, for `notTagsConstraintViolation`
+    pub paths: Vec<Vec<String>>,
+}
+
+#[napi]
+/// Checks the imports between the projects of a workspace against the dependency constraints of
+/// the `@nx/enforce-module-boundaries` lint rule, and returns the violations in the order of the imports.
+/// Like the rule, at most one violation is reported per import: the first constraint of the source project
+/// that is violated. `dependencies` are the targets of the dependencies of the nodes in `nodes` by their names,
+/// which are followed to find the projects with the tags that a project can not depend on
+pub fn check_module_boundaries(
+    nodes: HashMap<String, BoundaryNode>,
+    dependencies: HashMap<String, Vec<String>>,
+    imports: Vec<ProjectImport>,
+    constraints: Vec<DependencyConstraint>,
+) -> anyhow::Result<Vec<BoundaryViolation>> {
+    if constraints.is_empty() {
+        return Ok(vec![]);
+    }
+    let constraints = constraints
+        .iter()
+        .map(CompiledConstraint::new)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let mut checker = BoundaryChecker {
+        nodes: &nodes,
+        dependencies: &dependencies,
+        constraints: &constraints,
+        reachable: HashMap::new(),
+    };
+
+    let mut violations = vec![];
+    for import in imports {
+        if import.source_project == import.target_project {
+            continue;
+        }
+        let (Some(source), Some(target)) = (
+            nodes.get(&import.source_project),
+            nodes.get(&import.target_project),
+        ) else {
+            continue;
+        };
+        if let Some(violation) = checker.check(source, target, &import) {
+            violations.push(BoundaryViolation {
+                file: import.file,
+                specifier: import.specifier,
+                source_project: import.source_project,
+                target_project: import.target_project,
+                message_id: violation.message_id.to_string(),
+                source_tags: violation.source_tags,
+                tags: violation.tags,
+                paths: violation.paths,
+            });
+        }
+    }
+    Ok(violations)
+}
+
+/// A tag of a constraint: `*`, a `/regex/`, a glob with `*`, or a tag
+enum TagPattern {
+    Any,
+    Regex(Regex),
+    Tag(String),
+}
+
+impl TagPattern {
+    fn new(tag: &str) -> anyhow::Result<Self> {
+        Ok(if tag == "*" {
+            TagPattern::Any
+        } else if tag.len() > 1 && tag.starts_with('/') && tag.ends_with('/') {
+            TagPattern::Regex(
+                Regex::new(&tag[1..tag.len() - 1])
+                    .with_context(|| format!("The tag {} is not a valid regex", tag))?,
+            )
+        } else if tag.contains('*') {
+            TagPattern::Regex(glob_regex(tag)?)
+        } else {
+            TagPattern::Tag(tag.to_string())
+        })
+    }
+
+    fn matches(&self, node: &BoundaryNode) -> bool {
+        let mut tags = node.tags.iter().flatten();
+        match self {
+            TagPattern::Any => true,
+            TagPattern::Regex(regex) => tags.any(|tag| regex.is_match(tag)),
+            TagPattern::Tag(expected) => tags.any(|tag| tag == expected),
+        }
+    }
+}
+
+/// The regex of a pattern of tags or imports, where `*`, `**` and `.*` match anything,
+/// like the lint rule which does not escape the rest of the pattern either
+fn glob_regex(pattern: &str) -> anyhow::Result<Regex> {
+    let wildcards = Regex::new(r"\.\*|\*+").expect("the regex of wildcards is valid");
+    let source = wildcards.split(pattern).collect::<Vec<_>>().join(".*");
+    Regex::new(&format!("^(?:{})$", source))
+        .with_context(|| format!("The pattern {} is not valid", pattern))
+}
+
+struct CompiledConstraint<'a> {
+    constraint: &'a DependencyConstraint,
+    source_tags: Vec<String>,
+    source_patterns: Vec<TagPattern>,
+    only_tags: Option<Vec<TagPattern>>,
+    not_tags: Vec<TagPattern>,
+    allowed_imports: Option<Vec<Regex>>,
+    banned_imports: Vec<Regex>,
+}
+
+impl<'a> CompiledConstraint<'a> {
+    fn new(constraint: &'a DependencyConstraint) -> anyhow::Result<Self> {
+        let source_tags = match (&constraint.all_source_tags, &constraint.source_tag) {
+            (Some(tags), _) => tags.clone(),
+            (None, Some(tag)) => vec![tag.clone()],
+            (None, None) => vec![],
+        };
+        let patterns = |tags: &[String]| {
+            tags.iter()
+                .map(|tag| TagPattern::new(tag))
+                .collect::<anyhow::Result<Vec<_>>>()
+        };
+        let regexes = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| glob_regex(pattern))
+                .collect::<anyhow::Result<Vec<_>>>()
+        };
+        Ok(Self {
+            constraint,
+            source_patterns: patterns(&source_tags)?,
+            source_tags,
+            only_tags: constraint
+                .only_depend_on_libs_with_tags
+                .as_deref()
+                .map(patterns)
+                .transpose()?,
+            not_tags: patterns(
+                constraint
+                    .not_depend_on_libs_with_tags
+                    .as_deref()
+                    .unwrap_or_default(),
+            )?,
+            allowed_imports: constraint
+                .allowed_external_imports
+                .as_deref()
+                .map(regexes)
+                .transpose()?,
+            banned_imports: regexes(
+                constraint
+                    .banned_external_imports
+                    .as_deref()
+                    .unwrap_or_default(),
+            )?,
+        })
+    }
+
+    fn applies_to(&self, source: &BoundaryNode) -> bool {
+        !self.source_patterns.is_empty()
+            && self
+                .source_patterns
+                .iter()
+                .all(|pattern| pattern.matches(source))
+    }
+
+    /// Whether the constraint bans importing `specifier` from the package of an external node
+    fn bans(&self, package_name: &str, specifier: &str) -> bool {
+        if specifier != package_name && !specifier.starts_with(&format!("{}/", package_name)) {
+            return false;
+        }
+        if self
+            .banned_imports
+            .iter()
+            .any(|regex| regex.is_match(specifier))
+        {
+            return true;
+        }
+        // the imports of a package are banned unless they are allowed, when some imports are allowed
+        self.allowed_imports
+            .as_ref()
+            .is_some_and(|allowed| !allowed.iter().any(|regex| regex.is_match(specifier)))
+    }
+}
+
+struct Violation {
+    message_id: &'static str,
+    source_tags: Vec<String>,
+    tags: Vec<String>,
+    paths: Vec<Vec<String>>,
+}
+
+struct BoundaryChecker<'a> {
+    nodes: &'a HashMap<String, BoundaryNode>,
+    dependencies: &'a HashMap<String, Vec<String>>,
+    constraints: &'a [CompiledConstraint<'a>],
+    /// The paths from a project to the projects it depends on (and itself) with the tags of a constraint,
+    /// by the project and the index of the constraint
+    reachable: HashMap<(String, usize), Vec<Vec<String>>>,
+}
+
+impl BoundaryChecker<'_> {
+    fn check(
+        &mut self,
+        source: &BoundaryNode,
+        target: &BoundaryNode,
+        import: &ProjectImport,
+    ) -> Option<Violation> {
+        let all_constraints = self.constraints;
+        let constraints = all_constraints
+            .iter()
+            .enumerate()
+            .filter(|(_, constraint)| constraint.applies_to(source))
+            .collect::<Vec<_>>();
+        if constraints.is_empty() {
+            return Some(Violation {
+                message_id: "projectWithoutTagsCannotHaveDependencies",
+                source_tags: vec![],
+                tags: vec![],
+                paths: vec![],
+            });
+        }
+
+        if let Some(package_name) = &target.package_name {
+            return constraints
+                .iter()
+                .find(|(_, constraint)| constraint.bans(package_name, &import.specifier))
+                .map(|(_, constraint)| Violation {
+                    message_id: "bannedExternalImportsViolation",
+                    source_tags: constraint.source_tags.clone(),
+                    tags: vec![],
+                    paths: vec![],
+                });
+        }
+
+        for (index, constraint) in constraints {
+            let violation = |message_id, tags: &Option<Vec<String>>, paths| Violation {
+                message_id,
+                source_tags: constraint.source_tags.clone(),
+                tags: tags.clone().unwrap_or_default(),
+                paths,
+            };
+            match &constraint.only_tags {
+                Some(only_tags)
+                    if !only_tags.is_empty()
+                        && !only_tags.iter().any(|pattern| pattern.matches(target)) =>
+                {
+                    return Some(violation(
+                        "onlyTagsConstraintViolation",
+                        &constraint.constraint.only_depend_on_libs_with_tags,
+                        vec![],
+                    ));
+                }
+                Some(only_tags)
+                    if only_tags.is_empty() && target.tags.iter().flatten().next().is_some() =>
+                {
+                    return Some(violation("emptyOnlyTagsConstraintViolation", &None, vec![]));
+                }
+                _ => {}
+            }
+            if !constraint.not_tags.is_empty() {
+                let paths = self.paths_to_banned_tags(&import.target_project, index);
+                if !paths.is_empty() {
+                    return Some(violation(
+                        "notTagsConstraintViolation",
+                        &constraint.constraint.not_depend_on_libs_with_tags,
+                        paths,
+                    ));
+                }
+            }
+        }
+        None
+    }
+
+    /// The shortest paths of dependencies from `project` to each project with one of the tags that
+    /// the constraint bans, including `project` itself
+    fn paths_to_banned_tags(&mut self, project: &str, constraint: usize) -> Vec<Vec<String>> {
+        let key = (project.to_string(), constraint);
+        if let Some(paths) = self.reachable.get(&key) {
+            return paths.clone();
+        }
+
+        let not_tags = &self.constraints[constraint].not_tags;
+        let mut parents: HashMap<&str, Option<&str>> = HashMap::from([(project, None)]);
+        let mut queue = VecDeque::from([project]);
+        let mut paths = vec![];
+        while let Some(current) = queue.pop_front() {
+            let Some(node) = self.nodes.get(current) else {
+                continue;
+            };
+            if node.package_name.is_some() {
+                continue;
+            }
+            if not_tags.iter().any(|pattern| pattern.matches(node)) {
+                let mut path = vec![current.to_string()];
+                let mut parent = parents[current];
+                while let Some(project) = parent {
+                    path.push(project.to_string());
+                    parent = parents[project];
+                }
+                path.reverse();
+                paths.push(path);
+            }
+            for dependency in self.dependencies.get(current).into_iter().flatten() {
+                if !parents.contains_key(dependency.as_str()) {
+                    parents.insert(dependency, Some(current));
+                    queue.push_back(dependency);
+                }
+            }
+        }
+
+        self.reachable.insert(key, paths.clone());
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nodes() -> HashMap<String, BoundaryNode> {
+        let project = |tags: &[&str]| BoundaryNode {
+            tags: Some(tags.iter().map(|tag| tag.to_string()).collect()),
+            package_name: None,
+        };
+        HashMap::from([
+            ("app".into(), project(&["type:app", "scope:shop"])),
+            ("feature".into(), project(&["type:feature", "scope:shop"])),
+            ("ui".into(), project(&["type:ui", "scope:shared"])),
+            ("data".into(), project(&["type:data", "scope:admin"])),
+            ("untagged".into(), project(&[])),
+            (
+                "npm:lodash".into(),
+                BoundaryNode {
+                    tags: None,
+                    package_name: Some("lodash".into()),
+                },
+            ),
+        ])
+    }
+
+    fn dependencies() -> HashMap<String, Vec<String>> {
+        HashMap::from([
+            ("app".into(), vec!["feature".into()]),
+            ("feature".into(), vec!["ui".into(), "npm:lodash".into()]),
+            ("ui".into(), vec!["data".into()]),
+        ])
+    }
+
+    fn import(source: &str, target: &str, specifier: &str) -> ProjectImport {
+        ProjectImport {
+            file: format!("{}/src/index.ts", source),
+            specifier: specifier.into(),
+            source_project: source.into(),
+            target_project: target.into(),
+        }
+    }
+
+    fn constraint(source_tag: &str) -> DependencyConstraint {
+        DependencyConstraint {
+            source_tag: Some(source_tag.into()),
+            ..Default::default()
+        }
+    }
+
+    fn tags(tags: &[&str]) -> Option<Vec<String>> {
+        Some(tags.iter().map(|tag| tag.to_string()).collect())
+    }
+
+    fn message_ids(violations: &[BoundaryViolation]) -> Vec<(&str, &str)> {
+        violations
+            .iter()
+            .map(|violation| (violation.file.as_str(), violation.message_id.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn should_report_the_imports_that_violate_tag_constraints() {
+        let constraints = vec![
+            DependencyConstraint {
+                only_depend_on_libs_with_tags: tags(&[]),
+                ..constraint("type:data")
+            },
+            DependencyConstraint {
+                only_depend_on_libs_with_tags: tags(&["type:feature", "type:ui"]),
+                ..constraint("type:app")
+            },
+            DependencyConstraint {
+                not_depend_on_libs_with_tags: tags(&["scope:admin"]),
+                ..constraint("scope:*")
+            },
+        ];
+        let violations = check_module_boundaries(
+            nodes(),
+            dependencies(),
+            vec![
+                import("app", "feature", "@shop/feature"),
+                import("app", "data", "@admin/data"),
+                import("feature", "ui", "@shared/ui"),
+                import("data", "ui", "@shared/ui"),
+                import("untagged", "ui", "@shared/ui"),
+                import("feature", "feature", "@shop/feature"),
+            ],
+            constraints,
+        )
+        .unwrap();
+
+        assert_eq!(
+            message_ids(&violations),
+            vec![
+                ("app/src/index.ts", "notTagsConstraintViolation"),
+                ("app/src/index.ts", "onlyTagsConstraintViolation"),
+                ("feature/src/index.ts", "notTagsConstraintViolation"),
+                ("data/src/index.ts", "emptyOnlyTagsConstraintViolation"),
+                (
+                    "untagged/src/index.ts",
+                    "projectWithoutTagsCannotHaveDependencies"
+                ),
+            ]
+        );
+        assert_eq!(violations[0].paths, vec![vec!["feature", "ui", "data"]]);
+        assert_eq!(violations[1].tags, vec!["type:feature", "type:ui"]);
+        assert_eq!(violations[1].specifier, "@admin/data");
+    }
+
+    #[test]
+    fn should_report_banned_external_imports() {
+        let constraints = vec![DependencyConstraint {
+            banned_external_imports: tags(&["lodash/*"]),
+            ..constraint("type:feature")
+        }];
+        let violations = check_module_boundaries(
+            nodes(),
+            dependencies(),
+            vec![
+                import("feature", "npm:lodash", "lodash"),
+                import("feature", "npm:lodash", "lodash/fp"),
+                import("feature", "npm:lodash", "lodash-es"),
+            ],
+            constraints,
+        )
+        .unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].specifier, "lodash/fp");
+        assert_eq!(violations[0].message_id, "bannedExternalImportsViolation");
+        assert_eq!(violations[0].source_tags, vec!["type:feature"]);
+    }
+}
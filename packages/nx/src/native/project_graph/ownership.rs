@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::native::project_graph::utils::{
+    find_project_for_path, normalize_project_root, ProjectRootMappings,
+};
+
+#[napi]
+/// Maps file paths to the projects that own them: the project with the deepest root that contains a path,
+/// so the files of a project nested in an other project belong to the nested project.
+/// `project_roots` are the roots of the projects by their names, and the paths are relative to the workspace root,
+/// or absolute paths in `workspace_root`. Paths that no project owns (e.g. the files at the root of
+/// a workspace without a root project, or the paths outside of the workspace) are not returned
+pub fn owning_projects(
+    paths: Vec<String>,
+    project_roots: HashMap<String, String>,
+    workspace_root: Option<String>,
+) -> HashMap<String, String> {
+    let project_root_mappings: ProjectRootMappings = project_roots
+        .into_iter()
+        .filter_map(|(project, root)| {
+            let root = relative_path(&root, workspace_root.as_deref())?;
+            Some((normalize_project_root(&root), project))
+        })
+        .collect();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let relative = relative_path(&path, workspace_root.as_deref())?;
+            let project = find_project_for_path(relative, &project_root_mappings)?.to_string();
+            Some((path, project))
+        })
+        .collect()
+}
+
+/// Normalizes `path` to a path relative to the workspace root with `/` separators, which is `.` for the root.
+/// Returns nothing for the paths that are not in the workspace
+fn relative_path(path: &str, workspace_root: Option<&str>) -> Option<String> {
+    let path = path.replace('\\', "/");
+    let path = if path.starts_with('/') || Path::new(&path).is_absolute() {
+        let workspace_root = workspace_root?.replace('\\', "/");
+        let rest = path.strip_prefix(workspace_root.trim_end_matches('/'))?;
+        if !rest.is_empty() && !rest.starts_with('/') {
+            return None;
+        }
+        rest.to_string()
+    } else {
+        path
+    };
+
+    let mut components = vec![];
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop()?;
+            }
+            component => components.push(component),
+        }
+    }
+    if components.is_empty() {
+        Some(".".into())
+    } else {
+        Some(components.join("/"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roots(roots: &[(&str, &str)]) -> HashMap<String, String> {
+        roots
+            .iter()
+            .map(|(project, root)| (project.to_string(), root.to_string()))
+            .collect()
+    }
+
+    fn owners(
+        paths: &[&str],
+        project_roots: &[(&str, &str)],
+        workspace_root: Option<&str>,
+    ) -> Vec<(String, String)> {
+        let mut owners = owning_projects(
+            paths.iter().map(|path| path.to_string()).collect(),
+            roots(project_roots),
+            workspace_root.map(String::from),
+        )
+        .into_iter()
+        .collect::<Vec<_>>();
+        owners.sort();
+        owners
+    }
+
+    fn owner(path: &str, project: &str) -> (String, String) {
+        (path.into(), project.into())
+    }
+
+    #[test]
+    fn should_map_paths_to_the_deepest_project() {
+        let project_roots = [
+            ("ui", "libs/ui/"),
+            ("ui-icons", "libs/ui/icons"),
+            ("app", "apps/app"),
+        ];
+        assert_eq!(
+            owners(
+                &[
+                    "libs/ui/src/button.ts",
+                    "libs/ui/icons/src/logo.svg",
+                    "libs\\ui\\icons\\project.json",
+                    "./apps/app",
+                    "apps/application/main.ts",
+                    "libs/ui/../../apps/app/main.ts",
+                    "package.json",
+                ],
+                &project_roots,
+                None
+            ),
+            vec![
+                owner("./apps/app", "app"),
+                owner("libs/ui/../../apps/app/main.ts", "app"),
+                owner("libs/ui/icons/src/logo.svg", "ui-icons"),
+                owner("libs/ui/src/button.ts", "ui"),
+                owner("libs\\ui\\icons\\project.json", "ui-icons"),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_map_root_level_files_to_the_root_project() {
+        let project_roots = [("workspace", ""), ("app", "apps/app")];
+        assert_eq!(
+            owners(
+                &[
+                    "package.json",
+                    "/repo/apps/app/main.ts",
+                    "/repo/tools/script.ts",
+                    "/repository/package.json",
+                    "../package.json",
+                ],
+                &project_roots,
+                Some("/repo/")
+            ),
+            vec![
+                owner("/repo/apps/app/main.ts", "app"),
+                owner("/repo/tools/script.ts", "workspace"),
+                owner("package.json", "workspace"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail};
+
+use crate::native::glob::{build_glob_set_with_options, contains_glob_pattern, GlobOptions};
+
+/// What a project is filtered by
+#[napi(object)]
+pub struct FilteredProject {
+    pub root: String,
+    pub tags: Option<Vec<String>>,
+}
+
+#[napi]
+/// The names of the projects that match `patterns`, sorted, like the patterns of `--projects` and `--exclude`.
+/// Each pattern is an expression of project patterns, e.g. `tag:api && !(tag:experimental || apps/legacy/*)`:
+/// - `name:<glob>`, `tag:<glob>` and `directory:<glob>` match the names, the tags and the roots of projects
+/// - a pattern without a type matches the project names, or the project roots when no name matches
+/// - `&&`, `||`, `!` and parentheses combine patterns, `!` binds tighter than `&&`, which binds tighter than `||`
+///
+/// The patterns are applied in order: the projects that match a pattern are added to the result,
+/// unless the pattern is a negation (`!tag:experimental`), which removes the projects that match it instead.
+/// When the first pattern is a negation, it removes the projects from all of the projects
+pub fn filter_projects(
+    patterns: Vec<String>,
+    projects: HashMap<String, FilteredProject>,
+) -> anyhow::Result<Vec<String>> {
+    let mut projects = projects.into_iter().collect::<Vec<_>>();
+    projects.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut selected: Option<Vec<bool>> = None;
+    for pattern in patterns.iter().filter(|pattern| !pattern.trim().is_empty()) {
+        let expression = parse_expression(pattern, &projects)?;
+        let (exclude, expression) = match expression {
+            Expression::Not(expression) => (true, *expression),
+            expression => (false, expression),
+        };
+        let matches = expression.evaluate(&projects)?;
+        let selected = selected.get_or_insert_with(|| vec![exclude; projects.len()]);
+        for (selected, matches) in selected.iter_mut().zip(matches) {
+            if matches {
+                *selected = !exclude;
+            }
+        }
+    }
+
+    Ok(selected
+        .unwrap_or_default()
+        .into_iter()
+        .zip(projects)
+        .filter_map(|(selected, (name, _))| selected.then_some(name))
+        .collect())
+}
+
+#[derive(Debug, PartialEq)]
+enum PatternType {
+    Name,
+    Tag,
+    Directory,
+    /// Matches the names, or the roots when no name matches
+    Unlabeled,
+}
+
+#[derive(Debug, PartialEq)]
+enum Expression {
+    Pattern(PatternType, String),
+    Not(Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+}
+
+impl Expression {
+    /// Whether each project matches the expression
+    fn evaluate(&self, projects: &[(String, FilteredProject)]) -> anyhow::Result<Vec<bool>> {
+        Ok(match self {
+            Expression::Pattern(pattern_type, value) => {
+                match_pattern(pattern_type, value, projects)?
+            }
+            Expression::Not(expression) => expression
+                .evaluate(projects)?
+                .into_iter()
+                .map(|matches| !matches)
+                .collect(),
+            Expression::And(left, right) => left
+                .evaluate(projects)?
+                .into_iter()
+                .zip(right.evaluate(projects)?)
+                .map(|(left, right)| left && right)
+                .collect(),
+            Expression::Or(left, right) => left
+                .evaluate(projects)?
+                .into_iter()
+                .zip(right.evaluate(projects)?)
+                .map(|(left, right)| left || right)
+                .collect(),
+        })
+    }
+}
+
+fn match_pattern(
+    pattern_type: &PatternType,
+    value: &str,
+    projects: &[(String, FilteredProject)],
+) -> anyhow::Result<Vec<bool>> {
+    if value == "*" {
+        return Ok(vec![true; projects.len()]);
+    }
+    let matcher = if contains_glob_pattern(value) {
+        // project patterns are case sensitive, wherever the workspace is
+        let options = GlobOptions {
+            case_insensitive: false,
+        };
+        Some(
+            build_glob_set_with_options(&[value], options)
+                .map_err(|e| anyhow!("Invalid glob pattern {}: {}", value, e))?,
+        )
+    } else {
+        None
+    };
+    let matches = |candidate: &str| {
+        candidate == value
+            || matcher
+                .as_ref()
+                .is_some_and(|matcher| matcher.is_match(candidate))
+    };
+
+    let by_name = || {
+        projects
+            .iter()
+            .map(|(name, _)| matches(name))
+            .collect::<Vec<_>>()
+    };
+    let by_directory = || {
+        projects
+            .iter()
+            .map(|(_, project)| matches(&project.root))
+            .collect::<Vec<_>>()
+    };
+    Ok(match pattern_type {
+        PatternType::Name => by_name(),
+        PatternType::Tag => projects
+            .iter()
+            .map(|(_, project)| project.tags.iter().flatten().any(|tag| matches(tag)))
+            .collect(),
+        PatternType::Directory => by_directory(),
+        PatternType::Unlabeled => {
+            let by_name = by_name();
+            if by_name.contains(&true) {
+                by_name
+            } else {
+                by_directory()
+            }
+        }
+    })
+}
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+    Pattern(&'a str),
+}
+
+fn tokenize(expression: &str) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let bytes = expression.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b' ' | b'\t' | b'\n' | b'\r' => i += 1,
+            b'&' if bytes.get(i + 1) == Some(&b'&') => {
+                tokens.push(Token::And);
+                i += 2;
+            }
+            b'|' if bytes.get(i + 1) == Some(&b'|') => {
+                tokens.push(Token::Or);
+                i += 2;
+            }
+            b'!' => {
+                tokens.push(Token::Not);
+                i += 1;
+            }
+            b'(' => {
+                tokens.push(Token::Open);
+                i += 1;
+            }
+            b')' => {
+                tokens.push(Token::Close);
+                i += 1;
+            }
+            _ => {
+                // parentheses in a pattern are the ones of its globs, e.g. `@(app|lib)-*`
+                let start = i;
+                let mut depth = 0;
+                while i < bytes.len() {
+                    match bytes[i] {
+                        b' ' | b'\t' | b'\n' | b'\r' => break,
+                        b'&' | b'|' if depth == 0 && bytes.get(i + 1) == Some(&bytes[i]) => break,
+                        b'(' => depth += 1,
+                        b')' if depth == 0 => break,
+                        b')' => depth -= 1,
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                tokens.push(Token::Pattern(&expression[start..i]));
+            }
+        }
+    }
+    tokens
+}
+
+fn parse_expression(
+    expression: &str,
+    projects: &[(String, FilteredProject)],
+) -> anyhow::Result<Expression> {
+    let tokens = tokenize(expression);
+    let mut parser = Parser {
+        tokens: &tokens,
+        position: 0,
+        projects,
+    };
+    let parsed = parser.parse_or()?;
+    if parser.position < tokens.len() {
+        bail!(
+            "Invalid project pattern {:?}: unexpected {:?}",
+            expression,
+            tokens[parser.position]
+        );
+    }
+    Ok(parsed)
+}
+
+/// A recursive descent parser of the expressions of project patterns
+struct Parser<'a> {
+    tokens: &'a [Token<'a>],
+    position: usize,
+    projects: &'a [(String, FilteredProject)],
+}
+
+impl Parser<'_> {
+    fn parse_or(&mut self) -> anyhow::Result<Expression> {
+        let mut expression = self.parse_and()?;
+        while self.tokens.get(self.position) == Some(&Token::Or) {
+            self.position += 1;
+            expression = Expression::Or(Box::new(expression), Box::new(self.parse_and()?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_and(&mut self) -> anyhow::Result<Expression> {
+        let mut expression = self.parse_unary()?;
+        while self.tokens.get(self.position) == Some(&Token::And) {
+            self.position += 1;
+            expression = Expression::And(Box::new(expression), Box::new(self.parse_unary()?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_unary(&mut self) -> anyhow::Result<Expression> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        match token {
+            Some(Token::Not) => Ok(Expression::Not(Box::new(self.parse_unary()?))),
+            Some(Token::Open) => {
+                let expression = self.parse_or()?;
+                if self.tokens.get(self.position) != Some(&Token::Close) {
+                    bail!("Invalid project pattern: a parenthesis is not closed");
+                }
+                self.position += 1;
+                Ok(expression)
+            }
+            Some(Token::Pattern(pattern)) => Ok(self.parse_pattern(pattern)),
+            Some(token) => bail!("Invalid project pattern: unexpected {:?}", token),
+            None => bail!("Invalid project pattern: a pattern is missing"),
+        }
+    }
+
+    fn parse_pattern(&self, pattern: &str) -> Expression {
+        // a project whose name looks like a pattern is matched by its name
+        if self.projects.iter().any(|(name, _)| name == pattern) {
+            return Expression::Pattern(PatternType::Name, pattern.to_string());
+        }
+        let (pattern_type, value) = match pattern.split_once(':') {
+            Some(("name", value)) => (PatternType::Name, value),
+            Some(("tag", value)) => (PatternType::Tag, value),
+            Some(("directory", value)) => (PatternType::Directory, value),
+            Some((_, value)) => (PatternType::Unlabeled, value),
+            None => (PatternType::Unlabeled, pattern),
+        };
+        Expression::Pattern(pattern_type, value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn projects() -> HashMap<String, FilteredProject> {
+        [
+            ("api", "apps/api", vec!["type:app", "api"]),
+            ("api-e2e", "apps/api-e2e", vec!["type:e2e"]),
+            ("web", "apps/web", vec!["type:app"]),
+            ("auth", "libs/auth", vec!["api"]),
+            ("labs", "libs/labs", vec!["api", "experimental"]),
+            ("@org/ui", "libs/ui", vec![]),
+        ]
+        .into_iter()
+        .map(|(name, root, tags)| {
+            let project = FilteredProject {
+                root: root.into(),
+                tags: Some(tags.into_iter().map(String::from).collect()),
+            };
+            (name.to_string(), project)
+        })
+        .collect()
+    }
+
+    fn filter(patterns: &[&str]) -> Vec<String> {
+        filter_projects(
+            patterns.iter().map(|pattern| pattern.to_string()).collect(),
+            projects(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn should_filter_projects_by_expressions() {
+        assert_eq!(
+            filter(&["tag:api && !tag:experimental"]),
+            vec!["api", "auth"]
+        );
+        assert_eq!(
+            filter(&["tag:type:app || libs/*"]),
+            vec!["@org/ui", "api", "auth", "labs", "web"]
+        );
+        assert_eq!(
+            filter(&["directory:apps/* && !(api-* || name:web)"]),
+            vec!["api"]
+        );
+        assert_eq!(filter(&["@(api|web)"]), vec!["api", "web"]);
+        assert_eq!(filter(&["@org/ui", "!tag:api*"]), vec!["@org/ui"]);
+        assert!(filter_projects(vec!["tag:api &&".into()], projects()).is_err());
+        assert!(filter_projects(vec!["(api || web".into()], projects()).is_err());
+    }
+
+    #[test]
+    fn should_apply_patterns_in_order() {
+        assert_eq!(
+            filter(&["!tag:api", "labs"]),
+            vec!["@org/ui", "api-e2e", "labs", "web"]
+        );
+        assert_eq!(filter(&["apps/*", "!api*"]), vec!["web"]);
+        // only negations remove projects
+        assert_eq!(
+            filter(&["*", "!tag:type:app && apps/*"]),
+            vec!["@org/ui", "api", "api-e2e", "auth", "labs", "web"]
+        );
+    }
+}
@@ -0,0 +1,839 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use anyhow::{anyhow, bail};
+use indexmap::IndexMap;
+use serde::de::{Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Number;
+
+use crate::native::glob::{
+    build_glob_set_with_options, contains_glob_pattern, GlobOptions, NxGlobSet,
+};
+
+const NOOP_EXECUTOR: &str = "nx:noop";
+const RUN_COMMANDS_EXECUTOR: &str = "nx:run-commands";
+const RUN_SCRIPT_EXECUTOR: &str = "nx:run-script";
+
+/// A target default that matched a target but was not applied to it,
+/// because it is for an other executor, command or script than the target
+#[napi(object)]
+#[derive(Debug, PartialEq)]
+pub struct TargetDefaultConflict {
+    pub project_root: String,
+    pub target: String,
+    /// The key of the target default in `nx.json`
+    pub target_default: String,
+    pub message: String,
+}
+
+/// The objects of the projects and of the target defaults, which keep the order of their keys like the objects of
+/// JavaScript do. The keys of the objects of `serde_json::Value` are sorted
+type Object = IndexMap<String, Json>;
+
+/// A JSON value like `serde_json::Value`, whose objects keep the order of their keys
+#[derive(Clone, Debug, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+    Array(Vec<Json>),
+    Object(Object),
+}
+
+impl Json {
+    fn get(&self, key: &str) -> Option<&Json> {
+        self.as_object()?.get(key)
+    }
+
+    fn as_object(&self) -> Option<&Object> {
+        match self {
+            Json::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    fn as_object_mut(&mut self) -> Option<&mut Object> {
+        match self {
+            Json::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    fn as_array(&self) -> Option<&Vec<Json>> {
+        match self {
+            Json::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    fn is_null(&self) -> bool {
+        matches!(self, Json::Null)
+    }
+
+    fn is_object(&self) -> bool {
+        matches!(self, Json::Object(_))
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+
+impl Serialize for Json {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Json::Null => serializer.serialize_unit(),
+            Json::Bool(value) => serializer.serialize_bool(*value),
+            Json::Number(value) => value.serialize(serializer),
+            Json::String(value) => serializer.serialize_str(value),
+            Json::Array(values) => serializer.collect_seq(values),
+            Json::Object(object) => serializer.collect_map(object),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Json {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct JsonVisitor;
+
+        impl<'de> Visitor<'de> for JsonVisitor {
+            type Value = Json;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a JSON value")
+            }
+
+            fn visit_unit<E>(self) -> Result<Json, E> {
+                Ok(Json::Null)
+            }
+
+            fn visit_bool<E>(self, value: bool) -> Result<Json, E> {
+                Ok(Json::Bool(value))
+            }
+
+            fn visit_i64<E>(self, value: i64) -> Result<Json, E> {
+                Ok(Json::Number(value.into()))
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<Json, E> {
+                Ok(Json::Number(value.into()))
+            }
+
+            fn visit_f64<E>(self, value: f64) -> Result<Json, E> {
+                Ok(Number::from_f64(value).map_or(Json::Null, Json::Number))
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Json, E> {
+                Ok(Json::String(value.to_string()))
+            }
+
+            fn visit_string<E>(self, value: String) -> Result<Json, E> {
+                Ok(Json::String(value))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Json, A::Error> {
+                let mut array = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(value) = seq.next_element()? {
+                    array.push(value);
+                }
+                Ok(Json::Array(array))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Json, A::Error> {
+                let mut object = Object::with_capacity(map.size_hint().unwrap_or(0));
+                while let Some((key, value)) = map.next_entry()? {
+                    object.insert(key, value);
+                }
+                Ok(Json::Object(object))
+            }
+        }
+
+        deserializer.deserialize_any(JsonVisitor)
+    }
+}
+
+#[napi(object)]
+pub struct MaterializedProjects {
+    /// The JSON of the projects by their roots, in the order of the roots that were given
+    pub projects: String,
+    /// The source map keys of the properties that were set by target defaults, by the roots of the projects
+    pub target_default_properties: HashMap<String, Vec<String>>,
+    pub conflicts: Vec<TargetDefaultConflict>,
+}
+
+#[napi]
+/// Materializes the targets of the projects, which are the targets of their project files merged with their
+/// inferred targets, by their roots:
+/// - the `command` syntactic sugar is expanded to the `nx:run-commands` executor
+/// - the `{workspaceRoot}`, `{projectRoot}` and `{projectName}` tokens are replaced in the options and configurations
+/// - the target defaults of `nx.json` are merged into the targets, the ones for the executor of a target first,
+///   then the ones for its name, then the longest glob that matches its name
+/// - the targets that do nothing and depend on nothing are removed, the ones that only depend on other targets use `nx:noop`
+///
+/// Target defaults override the properties that are not set by core plugins according to `source_maps`, and skip the
+/// targets of other executors or commands, which are reported as conflicts. Keys keep the order of the given objects.
+///
+/// The projects and the target defaults are passed as JSON, like `JSON.stringify` of the projects by their roots, which
+/// is faster to transfer than objects, leaves out their `undefined` properties, which have no value in JSON, and keeps
+/// the order of their keys
+pub fn materialize_project_targets(
+    projects: String,
+    target_defaults: Option<String>,
+    source_maps: HashMap<String, HashMap<String, Vec<Option<String>>>>,
+) -> anyhow::Result<MaterializedProjects> {
+    let projects = parse_object(&projects)?;
+    let target_defaults = match target_defaults {
+        Some(target_defaults) => parse_object(&target_defaults)?,
+        None => Object::new(),
+    };
+    let target_defaults = TargetDefaults::new(target_defaults)?;
+    let no_source_map = HashMap::new();
+
+    let mut materialized_projects = Object::new();
+    let mut target_default_properties = HashMap::new();
+    let mut conflicts = vec![];
+    for (root, project) in projects {
+        let Json::Object(mut project) = project else {
+            materialized_projects.insert(root, project);
+            continue;
+        };
+        let name = project
+            .get("name")
+            .and_then(Json::as_str)
+            .unwrap_or_default()
+            .to_string();
+        let context = ProjectContext {
+            root: &root,
+            name: &name,
+            source_map: source_maps.get(&root).unwrap_or(&no_source_map),
+        };
+
+        let mut applied = vec![];
+        let targets = match project.get("targets") {
+            Some(Json::Object(targets)) => {
+                let mut materialized_targets = Object::new();
+                for (target_name, target) in targets {
+                    let target = context.materialize_target(
+                        target_name,
+                        target,
+                        &target_defaults,
+                        &mut applied,
+                        &mut conflicts,
+                    )?;
+                    if let Some(target) = target {
+                        materialized_targets.insert(target_name.clone(), Json::Object(target));
+                    }
+                }
+                Some(materialized_targets)
+            }
+            _ => None,
+        };
+        if let Some(targets) = targets {
+            project.insert("targets".into(), Json::Object(targets));
+        }
+
+        if !applied.is_empty() {
+            target_default_properties.insert(root.clone(), applied);
+        }
+        materialized_projects.insert(root, Json::Object(project));
+    }
+    Ok(MaterializedProjects {
+        projects: serde_json::to_string(&Json::Object(materialized_projects))?,
+        target_default_properties,
+        conflicts,
+    })
+}
+
+fn parse_object(json: &str) -> anyhow::Result<Object> {
+    match serde_json::from_str(json)? {
+        Json::Object(object) => Ok(object),
+        _ => bail!("Expected a JSON object"),
+    }
+}
+
+/// The target defaults of `nx.json`, with the globs of their keys
+struct TargetDefaults {
+    defaults: Object,
+    globs: Vec<(String, NxGlobSet)>,
+}
+
+impl TargetDefaults {
+    fn new(defaults: Object) -> anyhow::Result<Self> {
+        // target names are case sensitive, wherever the workspace is
+        let options = GlobOptions {
+            case_insensitive: false,
+        };
+        let globs = defaults
+            .keys()
+            .filter(|key| contains_glob_pattern(key))
+            .map(|key| {
+                let glob = build_glob_set_with_options(&[key.as_str()], options)
+                    .map_err(|e| anyhow!("Invalid target default {}: {}", key, e))?;
+                Ok((key.clone(), glob))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { defaults, globs })
+    }
+
+    /// The key and the target default of a target: the target default for its executor,
+    /// for its name, or for the longest glob that matches its name
+    fn for_target(&self, target_name: &str, executor: Option<&Json>) -> Option<(&str, &Json)> {
+        let get = |key: &str| {
+            self.defaults
+                .get_key_value(key)
+                .filter(|(_, target_default)| is_truthy(target_default))
+                .map(|(key, target_default)| (key.as_str(), target_default))
+        };
+
+        if let Some(executor) = executor.filter(|executor| is_truthy(executor)) {
+            if let Some(target_default) = executor.as_str().and_then(get) {
+                return Some(target_default);
+            }
+        }
+        if let Some(target_default) = get(target_name) {
+            return Some(target_default);
+        }
+
+        let mut matching: Option<&str> = None;
+        for (key, glob) in &self.globs {
+            if glob.is_match(target_name)
+                && matching.is_none_or(|matching| matching.len() < key.len())
+            {
+                matching = Some(key.as_str());
+            }
+        }
+        matching.and_then(get)
+    }
+}
+
+struct ProjectContext<'a> {
+    root: &'a str,
+    name: &'a str,
+    source_map: &'a HashMap<String, Vec<Option<String>>>,
+}
+
+impl ProjectContext<'_> {
+    /// The materialized target, or nothing when the target is removed because it does nothing
+    fn materialize_target(
+        &self,
+        target_name: &str,
+        target: &Json,
+        target_defaults: &TargetDefaults,
+        applied: &mut Vec<String>,
+        conflicts: &mut Vec<TargetDefaultConflict>,
+    ) -> anyhow::Result<Option<Object>> {
+        let mut target = self.normalize_target(target, target_name)?;
+
+        if let Some((key, target_default)) =
+            target_defaults.for_target(target_name, target.get("executor"))
+        {
+            let target_default = self.normalize_target(target_default, target_name)?;
+            if is_compatible_target(&target, &target_default) {
+                target = self.merge_target_default(target_name, target, target_default, applied);
+            } else {
+                conflicts.push(TargetDefaultConflict {
+                    project_root: self.root.to_string(),
+                    target: target_name.to_string(),
+                    target_default: key.to_string(),
+                    message: format!(
+                        "The target defaults for {} were not applied to {}:{}, because they are for an other executor or command",
+                        key, self.root, target_name
+                    ),
+                });
+            }
+        }
+
+        let is_set = |key: &str| target.get(key).is_some_and(is_truthy);
+        if !is_set("executor") && !is_set("command") {
+            let depends_on = target
+                .get("dependsOn")
+                .and_then(Json::as_array)
+                .is_some_and(|depends_on| !depends_on.is_empty());
+            if !depends_on {
+                return Ok(None);
+            }
+            target.insert("executor".into(), NOOP_EXECUTOR.into());
+        }
+        Ok(Some(target))
+    }
+
+    /// Expands the `command` syntactic sugar and replaces the tokens of the options and configurations
+    fn normalize_target(&self, target: &Json, target_name: &str) -> anyhow::Result<Object> {
+        let mut target = target.as_object().cloned().unwrap_or_default();
+        let configurations = target
+            .get("configurations")
+            .and_then(Json::as_object)
+            .cloned()
+            .unwrap_or_default();
+        target.insert("configurations".into(), Json::Object(configurations));
+
+        if let Some(command) = target.get("command").filter(|c| is_truthy(c)).cloned() {
+            if target.get("executor").is_some_and(is_truthy) {
+                bail!(
+                    "Project at {} should not have executor and command both configured.",
+                    self.root
+                );
+            }
+            let mut options = target
+                .get("options")
+                .and_then(Json::as_object)
+                .cloned()
+                .unwrap_or_default();
+            options.insert("command".into(), command);
+            target = target
+                .into_iter()
+                .filter(|(key, _)| key != "command")
+                .collect();
+            target.insert("executor".into(), RUN_COMMANDS_EXECUTOR.into());
+            target.insert("options".into(), Json::Object(options));
+        }
+
+        let options = self.resolve_tokens(
+            target.get("options"),
+            &format!("{}:{}", self.root, target_name),
+        )?;
+        target.insert("options".into(), options);
+
+        let configurations = match target.get("configurations") {
+            Some(Json::Object(configurations)) => configurations
+                .iter()
+                .map(|(configuration, options)| {
+                    let key = format!("{}:{}:{}", self.root, target_name, configuration);
+                    Ok((
+                        configuration.clone(),
+                        self.resolve_tokens(Some(options), &key)?,
+                    ))
+                })
+                .collect::<anyhow::Result<Object>>()?,
+            _ => Object::new(),
+        };
+        target.insert("configurations".into(), Json::Object(configurations));
+
+        if target.get("parallelism").is_none_or(Json::is_null) {
+            target.insert("parallelism".into(), Json::Bool(true));
+        }
+        Ok(target)
+    }
+
+    /// Replaces the `{workspaceRoot}`, `{projectRoot}` and `{projectName}` tokens of the strings of options.
+    /// `{workspaceRoot}` is only valid at the beginning of an option, where it is removed
+    fn resolve_tokens(&self, options: Option<&Json>, key: &str) -> anyhow::Result<Json> {
+        let entries: Box<dyn Iterator<Item = (String, &Json)>> = match options {
+            Some(Json::Object(options)) => Box::new(
+                options
+                    .iter()
+                    .map(|(option, value)| (option.clone(), value)),
+            ),
+            Some(Json::Array(options)) => Box::new(
+                options
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| (i.to_string(), value)),
+            ),
+            None | Some(Json::Null) => return Ok(Json::Object(Object::new())),
+            Some(options) => return Ok(options.clone()),
+        };
+
+        let mut resolved = vec![];
+        for (option, value) in entries {
+            let value = match value {
+                Json::String(value) => {
+                    let value = value
+                        .strip_prefix("{workspaceRoot}/")
+                        .or_else(|| value.strip_prefix("{workspaceRoot}"))
+                        .unwrap_or(value);
+                    if value.contains("{workspaceRoot}") {
+                        bail!(
+                            "The {{workspaceRoot}} token is only valid at the beginning of an option. ({})",
+                            key
+                        );
+                    }
+                    Json::String(
+                        value
+                            .replace("{projectRoot}", self.root)
+                            .replace("{projectName}", self.name),
+                    )
+                }
+                Json::Object(_) | Json::Array(_) => {
+                    self.resolve_tokens(Some(value), &format!("{}.{}", key, option))?
+                }
+                value => value.clone(),
+            };
+            resolved.push((option, value));
+        }
+
+        Ok(match options {
+            Some(Json::Array(_)) => Json::Array(resolved.into_iter().map(|(_, v)| v).collect()),
+            _ => Json::Object(resolved.into_iter().collect()),
+        })
+    }
+
+    /// Merges a normalized target default into a normalized target. The properties of the target default
+    /// override the properties of the target that are not set, or not set by core plugins
+    fn merge_target_default(
+        &self,
+        target_name: &str,
+        target: Object,
+        target_default: Object,
+        applied: &mut Vec<String>,
+    ) -> Object {
+        let mut result = target.clone();
+        let mut apply = |key: String, is_unset: bool| {
+            // the values of plugins that are not core plugins are overridden by target defaults
+            let should_apply = is_unset
+                || self.source_map.get(&key).is_none_or(|source| {
+                    !source
+                        .get(1)
+                        .and_then(Option::as_deref)
+                        .is_some_and(|plugin| plugin.starts_with("nx/"))
+                });
+            if should_apply {
+                applied.push(key);
+            }
+            should_apply
+        };
+
+        for (key, default_value) in target_default {
+            match (key.as_str(), default_value) {
+                ("options", Json::Object(default_options)) => {
+                    let options = target.get("options").and_then(Json::as_object);
+                    let result_options = object_entry(&mut result, "options");
+                    for (option, value) in default_options {
+                        let is_unset = options
+                            .and_then(|o| o.get(&option))
+                            .is_none_or(Json::is_null);
+                        if apply(
+                            format!("targets.{}.options.{}", target_name, option),
+                            is_unset,
+                        ) {
+                            result_options.insert(option, value);
+                        }
+                    }
+                }
+                ("configurations", Json::Object(default_configurations)) => {
+                    let configurations = target.get("configurations").and_then(Json::as_object);
+                    for (configuration, default_options) in default_configurations {
+                        let result_configurations = object_entry(&mut result, "configurations");
+                        if !result_configurations
+                            .get(&configuration)
+                            .is_some_and(is_truthy)
+                        {
+                            result_configurations
+                                .insert(configuration.clone(), Json::Object(Object::new()));
+                            apply(
+                                format!("targets.{}.configurations.{}", target_name, configuration),
+                                true,
+                            );
+                        }
+                        let Json::Object(default_options) = default_options else {
+                            continue;
+                        };
+                        let options = configurations
+                            .and_then(|c| c.get(&configuration))
+                            .and_then(Json::as_object);
+                        for (option, value) in default_options {
+                            let is_unset = options
+                                .and_then(|o| o.get(&option))
+                                .is_none_or(Json::is_null);
+                            let source_map_key = format!(
+                                "targets.{}.configurations.{}.{}",
+                                target_name, configuration, option
+                            );
+                            if apply(source_map_key, is_unset) {
+                                object_entry(
+                                    object_entry(&mut result, "configurations"),
+                                    &configuration,
+                                )
+                                .insert(option, value);
+                            }
+                        }
+                    }
+                }
+                (_, value) => {
+                    let is_unset = target.get(&key).is_none_or(Json::is_null);
+                    if apply(format!("targets.{}.{}", target_name, key), is_unset) {
+                        result.insert(key.clone(), value);
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+/// The object at `key` of `object`, which replaces the value at `key` when it is not an object
+fn object_entry<'a>(object: &'a mut Object, key: &str) -> &'a mut Object {
+    let value = object
+        .entry(key.to_string())
+        .or_insert_with(|| Json::Object(Object::new()));
+    if !value.is_object() {
+        *value = Json::Object(Object::new());
+    }
+    value
+        .as_object_mut()
+        .expect("the value was replaced by an object")
+}
+
+/// Whether a target default can be applied to a target: when either has no executor,
+/// or they have the same executor and do not run different commands or scripts
+fn is_compatible_target(a: &Object, b: &Object) -> bool {
+    let (Some(a_executor), Some(b_executor)) = (
+        a.get("executor").filter(|e| is_truthy(e)),
+        b.get("executor").filter(|e| is_truthy(e)),
+    ) else {
+        return true;
+    };
+    if a_executor != b_executor {
+        return false;
+    }
+
+    let option = |target: &Object, option: &str| {
+        target
+            .get("options")
+            .and_then(|options| options.get(option))
+            .filter(|value| !value.is_null())
+            .cloned()
+    };
+    let (a_run, b_run) = match a_executor.as_str() {
+        Some(RUN_COMMANDS_EXECUTOR) => {
+            let command = |target: &Object| {
+                option(target, "command").or_else(|| match option(target, "commands") {
+                    Some(Json::Array(commands)) => Some(Json::String(
+                        commands
+                            .iter()
+                            .map(js_string)
+                            .collect::<Vec<_>>()
+                            .join(" && "),
+                    )),
+                    _ => None,
+                })
+            };
+            (command(a), command(b))
+        }
+        Some(RUN_SCRIPT_EXECUTOR) => (option(a, "script"), option(b, "script")),
+        _ => return true,
+    };
+    match (a_run, b_run) {
+        (Some(a_run), Some(b_run)) if is_truthy(&a_run) && is_truthy(&b_run) => a_run == b_run,
+        _ => true,
+    }
+}
+
+/// The value of `String(value)` in JavaScript, for the values of the commands of `nx:run-commands`
+fn js_string(value: &Json) -> String {
+    match value {
+        Json::Null => String::new(),
+        Json::String(value) => value.clone(),
+        Json::Array(values) => values.iter().map(js_string).collect::<Vec<_>>().join(","),
+        Json::Object(_) => "[object Object]".into(),
+        Json::Bool(value) => value.to_string(),
+        Json::Number(value) => value.to_string(),
+    }
+}
+
+fn is_truthy(value: &Json) -> bool {
+    match value {
+        Json::Null => false,
+        Json::Bool(value) => *value,
+        Json::Number(value) => value.as_f64().is_some_and(|value| value != 0.0),
+        Json::String(value) => !value.is_empty(),
+        Json::Array(_) | Json::Object(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn materialize(
+        projects: Value,
+        target_defaults: Option<Value>,
+        source_maps: HashMap<String, HashMap<String, Vec<Option<String>>>>,
+    ) -> anyhow::Result<(Value, MaterializedProjects)> {
+        let target_defaults = target_defaults.map(|defaults| defaults.to_string());
+        let materialized =
+            materialize_project_targets(projects.to_string(), target_defaults, source_maps)?;
+        Ok((serde_json::from_str(&materialized.projects)?, materialized))
+    }
+
+    fn core_source_map(keys: &[&str]) -> HashMap<String, HashMap<String, Vec<Option<String>>>> {
+        let source_map = keys
+            .iter()
+            .map(|key| {
+                let source = vec![
+                    Some("libs/lib/project.json".into()),
+                    Some("nx/core/project-json".into()),
+                ];
+                (key.to_string(), source)
+            })
+            .collect();
+        HashMap::from([("libs/lib".to_string(), source_map)])
+    }
+
+    #[test]
+    fn should_merge_target_defaults_into_targets() {
+        let projects = json!({
+            "libs/lib": {
+                "name": "lib",
+                "root": "libs/lib",
+                "targets": {
+                    "build": {
+                        "executor": "@nx/js:tsc",
+                        "options": { "main": "{projectRoot}/src/index.ts", "tsConfig": "custom.json" }
+                    },
+                    "test": { "command": "jest {projectName}", "options": { "cwd": "{workspaceRoot}/libs" } },
+                    "e2e-ci--a": { "dependsOn": ["build"] },
+                    "empty": {},
+                    "lint": { "executor": "nx:run-commands", "options": { "command": "eslint ." } }
+                }
+            }
+        });
+        let target_defaults = json!({
+            "@nx/js:tsc": {
+                "cache": true,
+                "options": { "tsConfig": "{projectRoot}/tsconfig.lib.json", "outputPath": "dist/{projectRoot}" },
+                "configurations": { "production": { "sourceMap": false } }
+            },
+            "build": { "inputs": ["production"] },
+            "e2e-ci--*": { "cache": false },
+            "e2e-ci--**": { "cache": true },
+            "lint": { "executor": "nx:run-commands", "options": { "command": "tslint ." } }
+        });
+
+        let (projects, materialized) = materialize(
+            projects,
+            Some(target_defaults),
+            core_source_map(&["targets.build.options.tsConfig"]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            projects,
+            json!({
+                "libs/lib": {
+                    "name": "lib",
+                    "root": "libs/lib",
+                    "targets": {
+                        "build": {
+                            "executor": "@nx/js:tsc",
+                            "options": {
+                                "main": "libs/lib/src/index.ts",
+                                "tsConfig": "custom.json",
+                                "outputPath": "dist/libs/lib"
+                            },
+                            "configurations": { "production": { "sourceMap": false } },
+                            "parallelism": true,
+                            "cache": true
+                        },
+                        "test": {
+                            "executor": "nx:run-commands",
+                            "options": { "cwd": "libs", "command": "jest lib" },
+                            "configurations": {},
+                            "parallelism": true
+                        },
+                        "e2e-ci--a": {
+                            "dependsOn": ["build"],
+                            "configurations": {},
+                            "options": {},
+                            "parallelism": true,
+                            "cache": true,
+                            "executor": "nx:noop"
+                        },
+                        "lint": {
+                            "executor": "nx:run-commands",
+                            "options": { "command": "eslint ." },
+                            "configurations": {},
+                            "parallelism": true
+                        }
+                    }
+                }
+            })
+        );
+        let mut applied = materialized.target_default_properties["libs/lib"].clone();
+        applied.sort();
+        assert_eq!(
+            applied,
+            vec![
+                "targets.build.cache",
+                "targets.build.configurations.production",
+                "targets.build.configurations.production.sourceMap",
+                "targets.build.options.outputPath",
+                "targets.build.parallelism",
+                "targets.e2e-ci--a.cache",
+                "targets.e2e-ci--a.parallelism",
+            ]
+        );
+        assert_eq!(
+            materialized.conflicts,
+            vec![TargetDefaultConflict {
+                project_root: "libs/lib".into(),
+                target: "lint".into(),
+                target_default: "lint".into(),
+                message: "The target defaults for lint were not applied to libs/lib:lint, because they are for an other executor or command".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn should_keep_the_order_of_projects_targets_and_target_defaults() {
+        // `json!` would sort the keys
+        let projects = r#"{
+            "b": { "name": "b", "targets": { "z": { "executor": "nx:noop" }, "e2e-a-e2e": { "executor": "nx:noop" } } },
+            "a": { "name": "a" }
+        }"#;
+        let target_defaults = r#"{ "e2e-*": { "cache": false }, "*-e2e": { "cache": true } }"#;
+        let materialized = materialize_project_targets(
+            projects.to_string(),
+            Some(target_defaults.to_string()),
+            HashMap::new(),
+        )
+        .unwrap();
+
+        let projects = parse_object(&materialized.projects).unwrap();
+        assert_eq!(projects.keys().collect::<Vec<_>>(), ["b", "a"]);
+        let targets = projects["b"]
+            .get("targets")
+            .and_then(Json::as_object)
+            .unwrap();
+        assert_eq!(targets.keys().collect::<Vec<_>>(), ["z", "e2e-a-e2e"]);
+        // the first of the longest globs that match a target applies to it
+        assert_eq!(targets["e2e-a-e2e"].get("cache"), Some(&Json::Bool(false)));
+        assert_eq!(
+            materialized.projects,
+            r#"{"b":{"name":"b","targets":{"z":{"executor":"nx:noop","configurations":{},"options":{},"parallelism":true},"e2e-a-e2e":{"executor":"nx:noop","configurations":{},"options":{},"parallelism":true,"cache":false}}},"a":{"name":"a"}}"#
+        );
+    }
+
+    #[test]
+    fn should_fail_on_invalid_targets() {
+        let error = |target: Value| {
+            let projects = json!({ "libs/lib": { "name": "lib", "targets": { "build": target } } });
+            materialize(projects, None, HashMap::new())
+                .err()
+                .map(|e| e.to_string())
+        };
+        assert_eq!(
+            error(json!({ "executor": "nx:run-commands", "command": "tsc" })),
+            Some(
+                "Project at libs/lib should not have executor and command both configured.".into()
+            )
+        );
+        assert_eq!(
+            error(json!({ "command": "tsc", "options": { "args": ["--out={workspaceRoot}/dist"] } })),
+            Some("The {workspaceRoot} token is only valid at the beginning of an option. (libs/lib:build.args)".into())
+        );
+    }
+}
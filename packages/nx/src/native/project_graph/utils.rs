@@ -8,7 +8,7 @@ pub type ProjectRootMappings = HashMap<String, String>;
 pub fn create_project_root_mappings(nodes: &HashMap<String, Project>) -> ProjectRootMappings {
     let mut project_root_mappings = HashMap::new();
     for (project_name, node) in nodes {
-        project_root_mappings.insert(node.root.clone(), normalize_project_root(project_name));
+        project_root_mappings.insert(normalize_project_root(&node.root), project_name.clone());
     }
     project_root_mappings
 }
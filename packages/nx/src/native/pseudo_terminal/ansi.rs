@@ -0,0 +1,193 @@
+use std::borrow::Cow;
+
+/// Escape sequences that are not terminated after this many bytes are dropped
+const MAX_SEQUENCE_LEN: usize = 4 * 1024;
+
+/// How ANSI escape sequences are handled in the output of a command
+#[napi(string_enum)]
+#[derive(Debug)]
+pub enum AnsiMode {
+    /// The output is forwarded as is
+    #[allow(non_camel_case_types)]
+    preserve,
+    /// Colors and styles are kept, cursor movements and other control sequences are removed
+    #[allow(non_camel_case_types)]
+    normalize,
+    /// Every escape sequence is removed
+    #[allow(non_camel_case_types)]
+    strip,
+}
+
+enum Escape {
+    Incomplete,
+    Sequence { len: usize, is_style: bool },
+}
+
+/// Filters escape sequences out of a stream of output.
+/// Sequences that are split between two chunks are held back until the rest of the sequence arrives
+pub struct AnsiFilter {
+    mode: AnsiMode,
+    pending: String,
+}
+
+impl AnsiFilter {
+    pub fn new(mode: AnsiMode) -> Self {
+        Self {
+            mode,
+            pending: String::new(),
+        }
+    }
+
+    pub fn filter(&mut self, chunk: &str) -> String {
+        if matches!(self.mode, AnsiMode::preserve) {
+            return chunk.to_string();
+        }
+
+        let input: Cow<str> = if self.pending.is_empty() {
+            Cow::Borrowed(chunk)
+        } else {
+            let mut pending = std::mem::take(&mut self.pending);
+            pending.push_str(chunk);
+            Cow::Owned(pending)
+        };
+
+        let mut output = String::with_capacity(input.len());
+        let mut rest = input.as_ref();
+        while let Some(start) = rest.find('\x1B') {
+            output.push_str(&rest[..start]);
+            let sequence = &rest[start..];
+            match parse_escape(sequence) {
+                Escape::Incomplete => {
+                    if sequence.len() <= MAX_SEQUENCE_LEN {
+                        self.pending = sequence.to_string();
+                    }
+                    return output;
+                }
+                Escape::Sequence { len, is_style } => {
+                    if is_style && matches!(self.mode, AnsiMode::normalize) {
+                        output.push_str(&sequence[..len]);
+                    }
+                    rest = &sequence[len..];
+                }
+            }
+        }
+        output.push_str(rest);
+        output
+    }
+}
+
+/// Parses the escape sequence at the start of `sequence`, which starts with ESC
+fn parse_escape(sequence: &str) -> Escape {
+    let bytes = sequence.as_bytes();
+    let Some(&kind) = bytes.get(1) else {
+        return Escape::Incomplete;
+    };
+
+    match kind {
+        // CSI: parameters and intermediates followed by a final byte, `m` selects colors and styles
+        b'[' => {
+            for (i, byte) in bytes.iter().enumerate().skip(2) {
+                match byte {
+                    0x40..=0x7E => {
+                        return Escape::Sequence {
+                            len: i + 1,
+                            is_style: *byte == b'm',
+                        }
+                    }
+                    0x20..=0x3F => continue,
+                    // malformed sequences are dropped up to the unexpected byte
+                    _ => {
+                        return Escape::Sequence {
+                            len: i,
+                            is_style: false,
+                        }
+                    }
+                }
+            }
+            Escape::Incomplete
+        }
+        // OSC: terminated by BEL or ST (ESC \)
+        b']' => {
+            for (i, byte) in bytes.iter().enumerate().skip(2) {
+                match byte {
+                    0x07 => {
+                        return Escape::Sequence {
+                            len: i + 1,
+                            is_style: false,
+                        }
+                    }
+                    0x1B => {
+                        return match bytes.get(i + 1) {
+                            Some(b'\\') => Escape::Sequence {
+                                len: i + 2,
+                                is_style: false,
+                            },
+                            Some(_) => Escape::Sequence {
+                                len: i,
+                                is_style: false,
+                            },
+                            None => Escape::Incomplete,
+                        }
+                    }
+                    _ => continue,
+                }
+            }
+            Escape::Incomplete
+        }
+        // an intermediate byte followed by a final byte, e.g. character set selection
+        0x20..=0x2F => {
+            if bytes.len() > 2 && bytes[2].is_ascii() {
+                Escape::Sequence {
+                    len: 3,
+                    is_style: false,
+                }
+            } else if bytes.len() > 2 {
+                Escape::Sequence {
+                    len: 2,
+                    is_style: false,
+                }
+            } else {
+                Escape::Incomplete
+            }
+        }
+        _ if kind.is_ascii() => Escape::Sequence {
+            len: 2,
+            is_style: false,
+        },
+        _ => Escape::Sequence {
+            len: 1,
+            is_style: false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "\x1B[2K\x1B[1G\x1B[32m✔\x1B[39m built \x1B]8;;https://nx.dev\x07nx\x1B]8;;\x07\x1B7done\x1B8\r\n";
+
+    #[test]
+    fn should_strip_escape_sequences() {
+        let mut filter = AnsiFilter::new(AnsiMode::strip);
+        assert_eq!(filter.filter(OUTPUT), "✔ built nxdone\r\n");
+    }
+
+    #[test]
+    fn should_keep_styles_when_normalizing() {
+        let mut filter = AnsiFilter::new(AnsiMode::normalize);
+        assert_eq!(filter.filter(OUTPUT), "\x1B[32m✔\x1B[39m built nxdone\r\n");
+
+        let mut filter = AnsiFilter::new(AnsiMode::preserve);
+        assert_eq!(filter.filter(OUTPUT), OUTPUT);
+    }
+
+    #[test]
+    fn should_handle_sequences_split_between_chunks() {
+        let mut filter = AnsiFilter::new(AnsiMode::normalize);
+        assert_eq!(filter.filter("a\x1B["), "a");
+        assert_eq!(filter.filter("31"), "");
+        assert_eq!(filter.filter("mred\x1B[0m\x1B[1"), "\x1B[31mred\x1B[0m");
+        assert_eq!(filter.filter("A"), "");
+    }
+}
@@ -0,0 +1,84 @@
+use std::fs;
+use std::path::PathBuf;
+
+use tracing::trace;
+
+use super::exit_status::{find_cgroup, parse_oom_kills};
+use super::resource_limits::ResourceLimits;
+
+/// The cgroup that task cgroups are created in, the cgroup of this process by default.
+/// It has to be delegated to the user running nx (e.g. with `systemd-run --user -p Delegate=yes`)
+const CGROUP_PARENT_ENV: &str = "NX_TASK_CGROUP";
+
+/// The `cpu.max` period, in microseconds
+const CPU_PERIOD: u64 = 100_000;
+
+/// A cgroup (v2) that limits the resources of a task, removed when it is dropped
+pub struct TaskCgroup {
+    path: PathBuf,
+}
+
+impl TaskCgroup {
+    /// Moves the process `pid` into a new cgroup with `limits`.
+    /// Processes that it starts afterwards are part of the cgroup too
+    pub fn create(pid: u32, limits: &ResourceLimits) -> anyhow::Result<Self> {
+        let parent = match std::env::var_os(CGROUP_PARENT_ENV) {
+            Some(parent) => PathBuf::from(parent),
+            None => {
+                let cgroups = fs::read_to_string("/proc/self/cgroup")?;
+                let cgroup = find_cgroup(&cgroups, "")
+                    .ok_or_else(|| anyhow::anyhow!("cgroup v2 is not available"))?;
+                PathBuf::from("/sys/fs/cgroup").join(cgroup.trim_start_matches('/'))
+            }
+        };
+
+        // the controllers may already be enabled, in which case the write fails harmlessly
+        let controllers = [
+            limits.memory.map(|_| "+memory"),
+            limits.cpus.map(|_| "+cpu"),
+        ];
+        for controller in controllers.into_iter().flatten() {
+            if let Err(e) = fs::write(parent.join("cgroup.subtree_control"), controller) {
+                trace!("unable to enable the {} controller: {}", controller, e);
+            }
+        }
+
+        let path = parent.join(format!("nx-task-{}", pid));
+        fs::create_dir(&path)?;
+        let cgroup = Self { path };
+
+        if let Some(memory) = limits.memory_bytes() {
+            fs::write(cgroup.path.join("memory.max"), memory.to_string())?;
+            // swapping would hide that the task went over its limit
+            fs::write(cgroup.path.join("memory.swap.max"), "0").ok();
+        }
+        if let Some(cpus) = limits.cpus {
+            let quota = (cpus * CPU_PERIOD as f64).max(1000.0) as u64;
+            fs::write(
+                cgroup.path.join("cpu.max"),
+                format!("{} {}", quota, CPU_PERIOD),
+            )?;
+        }
+        fs::write(cgroup.path.join("cgroup.procs"), pid.to_string())?;
+
+        trace!("limited process {} with {:?}", pid, cgroup.path);
+        Ok(cgroup)
+    }
+
+    /// Whether a process of the cgroup was killed because the cgroup reached its memory limit
+    pub fn memory_limit_exceeded(&self) -> bool {
+        fs::read_to_string(self.path.join("memory.events"))
+            .ok()
+            .and_then(|events| parse_oom_kills(&events))
+            .is_some_and(|kills| kills > 0)
+    }
+}
+
+impl Drop for TaskCgroup {
+    fn drop(&mut self) {
+        // this fails while processes started by the task are still running, the cgroup is then left behind
+        if let Err(e) = fs::remove_dir(&self.path) {
+            trace!("unable to remove cgroup {:?}: {}", self.path, e);
+        }
+    }
+}
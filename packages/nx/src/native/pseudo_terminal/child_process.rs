@@ -1,12 +1,21 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
 use crossbeam_channel::Receiver;
 use napi::{
     threadsafe_function::{
         ErrorStrategy::Fatal, ThreadsafeFunction, ThreadsafeFunctionCallMode::NonBlocking,
     },
-    Env, JsFunction,
+    Either, Env, JsFunction,
 };
 use portable_pty::ChildKiller;
 
+use super::ansi::{AnsiFilter, AnsiMode};
+use super::exit_status::ExitDetails;
+use super::process_tree::kill_tree;
+
 pub enum ChildProcessMessage {
     Kill,
 }
@@ -14,54 +23,75 @@ pub enum ChildProcessMessage {
 #[napi]
 pub struct ChildProcess {
     process_killer: Box<dyn ChildKiller + Sync + Send>,
+    pid: Option<u32>,
+    exited: Arc<AtomicBool>,
     message_receiver: Receiver<String>,
-    pub(crate) wait_receiver: Receiver<String>,
+    pub(crate) wait_receiver: Receiver<(String, ExitDetails)>,
 }
 #[napi]
 impl ChildProcess {
     pub fn new(
         process_killer: Box<dyn ChildKiller + Sync + Send>,
+        pid: Option<u32>,
+        exited: Arc<AtomicBool>,
         message_receiver: Receiver<String>,
-        exit_receiver: Receiver<String>,
+        exit_receiver: Receiver<(String, ExitDetails)>,
     ) -> Self {
         Self {
             process_killer,
+            pid,
+            exited,
             message_receiver,
             wait_receiver: exit_receiver,
         }
     }
 
+    /// Kills the process and every process it started, with `SIGTERM` unless another `signal` is given
     #[napi]
-    pub fn kill(&mut self) -> anyhow::Result<()> {
-        self.process_killer.kill().map_err(anyhow::Error::from)
+    pub fn kill(&mut self, signal: Option<String>) -> anyhow::Result<()> {
+        match self.pid {
+            // the pid may belong to another process once the process exited
+            Some(pid) if !self.exited.load(Ordering::SeqCst) => kill_tree(pid, signal),
+            _ => self.process_killer.kill().map_err(anyhow::Error::from),
+        }
     }
 
+    /// Calls `callback` with the exit message of the process and the details of how it exited,
+    /// e.g. the signal that killed it
     #[napi]
     pub fn on_exit(
         &mut self,
-        #[napi(ts_arg_type = "(message: string) => void")] callback: JsFunction,
+        #[napi(ts_arg_type = "(message: string, details: ExitDetails) => void")]
+        callback: JsFunction,
     ) -> napi::Result<()> {
         let wait = self.wait_receiver.clone();
-        let callback_tsfn: ThreadsafeFunction<String, Fatal> =
-            callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
+        let callback_tsfn: ThreadsafeFunction<(String, ExitDetails), Fatal> = callback
+            .create_threadsafe_function(0, |ctx| {
+                let (message, details) = ctx.value;
+                Ok(vec![Either::A(message), Either::B(details)])
+            })?;
 
         std::thread::spawn(move || {
             // we will only get one exit_code here, so we dont need to do a while loop
-            if let Ok(exit_code) = wait.recv() {
-                callback_tsfn.call(exit_code, NonBlocking);
+            if let Ok(exit) = wait.recv() {
+                callback_tsfn.call(exit, NonBlocking);
             }
         });
 
         Ok(())
     }
 
+    /// Calls `callback` with the output of the process, with escape sequences filtered according to `ansi`.
+    /// The output printed to the terminal is not filtered
     #[napi]
     pub fn on_output(
         &mut self,
         env: Env,
         #[napi(ts_arg_type = "(message: string) => void")] callback: JsFunction,
+        ansi: Option<AnsiMode>,
     ) -> napi::Result<()> {
         let rx = self.message_receiver.clone();
+        let mut ansi_filter = AnsiFilter::new(ansi.unwrap_or(AnsiMode::preserve));
 
         let mut callback_tsfn: ThreadsafeFunction<String, Fatal> =
             callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
@@ -76,7 +106,10 @@ impl ChildProcess {
                 #[cfg(windows)]
                 let content = content.replace("\x1B[6n", "");
 
-                callback_tsfn.call(content, NonBlocking);
+                let content = ansi_filter.filter(&content);
+                if !content.is_empty() {
+                    callback_tsfn.call(content, NonBlocking);
+                }
             }
         });
 
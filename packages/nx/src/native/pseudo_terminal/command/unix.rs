@@ -1,10 +1,23 @@
+#[cfg(target_os = "linux")]
+use std::collections::HashMap;
 use std::{
     io::{Read, Stdin, Write},
-    os::fd::AsRawFd,
+    os::{fd::AsRawFd, unix::process::ExitStatusExt},
+    process::ExitStatus,
 };
 
 use mio::{unix::SourceFd, Events};
-use tracing::trace;
+#[cfg(target_os = "linux")]
+use once_cell::sync::Lazy;
+#[cfg(target_os = "linux")]
+use parking_lot::Mutex;
+use portable_pty::Child;
+use tracing::{trace, warn};
+
+#[cfg(target_os = "linux")]
+use crate::native::pseudo_terminal::cgroup::TaskCgroup;
+use crate::native::pseudo_terminal::process_tree::descendants;
+use crate::native::pseudo_terminal::resource_limits::ResourceLimits;
 
 pub fn handle_path_space(path: String) -> String {
     if path.contains(' ') {
@@ -65,3 +78,121 @@ pub fn write_to_pty(stdin: &mut Stdin, writer: &mut impl Write) -> anyhow::Resul
         }
     }
 }
+
+/// Waits for a child to exit with the status of the process, which tells the signal that terminated it
+pub fn wait_for_exit(child: &mut dyn Child) -> std::io::Result<ExitStatus> {
+    let Some(pid) = child.process_id() else {
+        return child
+            .wait()
+            .map(|exit| ExitStatus::from_raw((exit.exit_code() as i32 & 0xff) << 8));
+    };
+    let mut status = 0;
+    loop {
+        if unsafe { libc::waitpid(pid as libc::pid_t, &mut status, 0) } != -1 {
+            return Ok(ExitStatus::from_raw(status));
+        }
+        let error = std::io::Error::last_os_error();
+        if error.kind() != std::io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+}
+
+/// Sends `signal` to a process, its process group and its descendants.
+/// Processes spawned in a pseudo terminal lead their own process group,
+/// so the group still reaches workers whose parent already exited
+pub fn kill_tree(pid: u32, signal: &str) -> anyhow::Result<()> {
+    let signal = parse_signal(signal)?;
+    // orphans are reparented, so the tree is listed before anything is killed
+    let descendants = descendants(pid, &list_processes().unwrap_or_default());
+
+    let pid = pid as libc::pid_t;
+    let group = unsafe { libc::getpgid(pid) };
+    let target = if (group == pid || group == -1) && pid != unsafe { libc::getpgrp() } {
+        -pid
+    } else {
+        pid
+    };
+    if unsafe { libc::kill(target, signal) } != 0 {
+        let error = std::io::Error::last_os_error();
+        if error.raw_os_error() != Some(libc::ESRCH) {
+            anyhow::bail!("failed to kill process {}: {}", pid, error);
+        }
+    }
+
+    for descendant in descendants {
+        trace!("killing descendant {} of {}", descendant, pid);
+        unsafe { libc::kill(descendant as libc::pid_t, signal) };
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+static CGROUPS: Lazy<Mutex<HashMap<u32, TaskCgroup>>> = Lazy::new(Default::default);
+
+/// Pseudo terminals already start every command in a new process group,
+/// so processes are only tracked to limit their resources
+#[cfg(target_os = "linux")]
+pub fn track_process_tree(pid: u32, limits: Option<&ResourceLimits>) {
+    let Some(limits) = limits.filter(|limits| !limits.is_empty()) else {
+        return;
+    };
+    match TaskCgroup::create(pid, limits) {
+        Ok(cgroup) => {
+            CGROUPS.lock().insert(pid, cgroup);
+        }
+        Err(e) => warn!("unable to limit the resources of process {}: {}", pid, e),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn track_process_tree(_pid: u32, limits: Option<&ResourceLimits>) {
+    if limits.is_some_and(|limits| !limits.is_empty()) {
+        warn!("resource limits are not supported on this platform");
+    }
+}
+
+/// Stops tracking a process that exited, returns whether it exceeded its memory limit
+#[cfg(target_os = "linux")]
+pub fn untrack_process_tree(pid: u32) -> bool {
+    CGROUPS
+        .lock()
+        .remove(&pid)
+        .is_some_and(|cgroup| cgroup.memory_limit_exceeded())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn untrack_process_tree(_pid: u32) -> bool {
+    false
+}
+
+fn parse_signal(signal: &str) -> anyhow::Result<libc::c_int> {
+    if let Ok(number) = signal.parse() {
+        return Ok(number);
+    }
+    Ok(match signal.trim_start_matches("SIG") {
+        "HUP" => libc::SIGHUP,
+        "INT" => libc::SIGINT,
+        "QUIT" => libc::SIGQUIT,
+        "ABRT" => libc::SIGABRT,
+        "KILL" => libc::SIGKILL,
+        "USR1" => libc::SIGUSR1,
+        "USR2" => libc::SIGUSR2,
+        "TERM" => libc::SIGTERM,
+        _ => anyhow::bail!("unsupported signal {}", signal),
+    })
+}
+
+/// Lists every process as `(pid, parent pid)` with `ps`, which is available on Linux and macOS
+fn list_processes() -> anyhow::Result<Vec<(u32, u32)>> {
+    let output = std::process::Command::new("ps")
+        .args(["-A", "-o", "pid=", "-o", "ppid="])
+        .output()?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+        })
+        .collect())
+}
@@ -1,8 +1,36 @@
+use std::collections::HashMap;
 use std::io::{Stdin, Write};
 use std::os::windows::ffi::OsStrExt;
+use std::os::windows::process::ExitStatusExt;
+use std::process::ExitStatus;
 use std::{ffi::OsString, os::windows::ffi::OsStringExt};
 
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use portable_pty::Child;
+use tracing::{trace, warn};
+use winapi::shared::minwindef::FALSE;
 use winapi::um::fileapi::GetShortPathNameW;
+use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+use winapi::um::jobapi2::{
+    AssignProcessToJobObject, CreateJobObjectW, QueryInformationJobObject, SetInformationJobObject,
+    TerminateJobObject,
+};
+use winapi::um::processthreadsapi::{OpenProcess, TerminateProcess};
+use winapi::um::tlhelp32::{
+    CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W, TH32CS_SNAPPROCESS,
+};
+use winapi::um::winnt::{
+    JobObjectBasicAndIoAccountingInformation, JobObjectCpuRateControlInformation,
+    JobObjectExtendedLimitInformation, HANDLE, JOBOBJECT_BASIC_AND_IO_ACCOUNTING_INFORMATION,
+    JOBOBJECT_CPU_RATE_CONTROL_INFORMATION, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+    JOB_OBJECT_CPU_RATE_CONTROL_ENABLE, JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP,
+    JOB_OBJECT_LIMIT_JOB_MEMORY, PROCESS_SET_QUOTA, PROCESS_TERMINATE,
+};
+
+use crate::native::pseudo_terminal::metrics::TaskMetrics;
+use crate::native::pseudo_terminal::process_tree::descendants;
+use crate::native::pseudo_terminal::resource_limits::ResourceLimits;
 
 pub fn handle_path_space(path: String) -> String {
     let wide: Vec<u16> = std::path::PathBuf::from(&path)
@@ -29,3 +57,203 @@ pub fn write_to_pty(stdin: &mut Stdin, writer: &mut impl Write) -> anyhow::Resul
         .map_err(|e| anyhow::anyhow!(e))
         .map(|_| ())
 }
+
+/// A job object that contains a process and every process it starts
+struct Job {
+    handle: HANDLE,
+    memory_limit: Option<u64>,
+}
+
+// job handles can be used from any thread
+unsafe impl Send for Job {}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        unsafe { CloseHandle(self.handle) };
+    }
+}
+
+static JOBS: Lazy<Mutex<HashMap<u32, Job>>> = Lazy::new(Default::default);
+
+impl Job {
+    /// Allocations that would go over the memory limit fail, so the peak usage ends up right below the limit
+    fn memory_limit_exceeded(&self) -> bool {
+        let Some(memory_limit) = self.memory_limit else {
+            return false;
+        };
+        unsafe {
+            let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+            let queried = QueryInformationJobObject(
+                self.handle,
+                JobObjectExtendedLimitInformation,
+                &mut info as *mut _ as *mut _,
+                std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+                std::ptr::null_mut(),
+            ) != 0;
+            queried && info.PeakJobMemoryUsed as u64 + 1024 * 1024 >= memory_limit
+        }
+    }
+}
+
+/// Assigns a process to a new job object, so that `kill_tree` can terminate everything it starts,
+/// and limits the resources of the job
+pub fn track_process_tree(pid: u32, limits: Option<&ResourceLimits>) {
+    unsafe {
+        let handle = CreateJobObjectW(std::ptr::null_mut(), std::ptr::null());
+        if handle.is_null() {
+            return;
+        }
+        let job = Job {
+            handle,
+            memory_limit: limits.and_then(|limits| limits.memory_bytes()),
+        };
+        if let Some(limits) = limits {
+            set_job_limits(&job, limits);
+        }
+        let process = OpenProcess(PROCESS_SET_QUOTA | PROCESS_TERMINATE, FALSE, pid);
+        if process.is_null() {
+            return;
+        }
+        let assigned = AssignProcessToJobObject(job.handle, process) != 0;
+        CloseHandle(process);
+        if assigned {
+            JOBS.lock().insert(pid, job);
+        } else {
+            trace!("failed to assign process {} to a job object", pid);
+        }
+    }
+}
+
+/// The resources used by the job of a process, which contains every process that it started
+pub fn job_metrics(pid: u32) -> Option<TaskMetrics> {
+    let jobs = JOBS.lock();
+    let job = jobs.get(&pid)?;
+    unsafe {
+        let mut accounting: JOBOBJECT_BASIC_AND_IO_ACCOUNTING_INFORMATION = std::mem::zeroed();
+        if QueryInformationJobObject(
+            job.handle,
+            JobObjectBasicAndIoAccountingInformation,
+            &mut accounting as *mut _ as *mut _,
+            std::mem::size_of::<JOBOBJECT_BASIC_AND_IO_ACCOUNTING_INFORMATION>() as u32,
+            std::ptr::null_mut(),
+        ) == 0
+        {
+            return None;
+        }
+        let mut limits: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+        QueryInformationJobObject(
+            job.handle,
+            JobObjectExtendedLimitInformation,
+            &mut limits as *mut _ as *mut _,
+            std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+            std::ptr::null_mut(),
+        );
+
+        let basic = &accounting.BasicInfo;
+        // in units of 100 nanoseconds
+        let cpu_time = *basic.TotalUserTime.QuadPart() + *basic.TotalKernelTime.QuadPart();
+        Some(TaskMetrics {
+            cpu_time: cpu_time as f64 / 10_000.0,
+            peak_rss: limits.PeakJobMemoryUsed as i64,
+            read_bytes: accounting.IoInfo.ReadTransferCount as i64,
+            write_bytes: accounting.IoInfo.WriteTransferCount as i64,
+        })
+    }
+}
+
+/// Stops tracking a process that exited, returns whether it exceeded its memory limit
+pub fn untrack_process_tree(pid: u32) -> bool {
+    JOBS.lock()
+        .remove(&pid)
+        .is_some_and(|job| job.memory_limit_exceeded())
+}
+
+unsafe fn set_job_limits(job: &Job, limits: &ResourceLimits) {
+    if let Some(memory) = job.memory_limit {
+        let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+        info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_JOB_MEMORY;
+        info.JobMemoryLimit = memory as usize;
+        if SetInformationJobObject(
+            job.handle,
+            JobObjectExtendedLimitInformation,
+            &mut info as *mut _ as *mut _,
+            std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+        ) == 0
+        {
+            warn!(
+                "unable to limit the memory of a job: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+
+    if let Some(cpus) = limits.cpus {
+        let available = std::thread::available_parallelism().map_or(1, |n| n.get()) as f64;
+        // the rate is the percentage of the CPU cycles of the machine, times 100
+        let rate = ((cpus / available) * 10_000.0).clamp(1.0, 10_000.0) as u32;
+        let mut info: JOBOBJECT_CPU_RATE_CONTROL_INFORMATION = std::mem::zeroed();
+        info.ControlFlags =
+            JOB_OBJECT_CPU_RATE_CONTROL_ENABLE | JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP;
+        *info.u.CpuRate_mut() = rate;
+        if SetInformationJobObject(
+            job.handle,
+            JobObjectCpuRateControlInformation,
+            &mut info as *mut _ as *mut _,
+            std::mem::size_of::<JOBOBJECT_CPU_RATE_CONTROL_INFORMATION>() as u32,
+        ) == 0
+        {
+            warn!(
+                "unable to limit the CPU usage of a job: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+}
+
+/// Waits for a child to exit, processes only exit with a code on Windows
+pub fn wait_for_exit(child: &mut dyn Child) -> std::io::Result<ExitStatus> {
+    child
+        .wait()
+        .map(|exit| ExitStatus::from_raw(exit.exit_code()))
+}
+
+/// Terminates the job object of a process, and its descendants that are not part of the job
+pub fn kill_tree(pid: u32, _signal: &str) -> anyhow::Result<()> {
+    let descendants = descendants(pid, &list_processes().unwrap_or_default());
+    if let Some(job) = JOBS.lock().remove(&pid) {
+        unsafe { TerminateJobObject(job.handle, 1) };
+    }
+    for pid in std::iter::once(pid).chain(descendants) {
+        unsafe {
+            let process = OpenProcess(PROCESS_TERMINATE, FALSE, pid);
+            if !process.is_null() {
+                TerminateProcess(process, 1);
+                CloseHandle(process);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Lists every process as `(pid, parent pid)`
+fn list_processes() -> anyhow::Result<Vec<(u32, u32)>> {
+    let mut processes = vec![];
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+        if snapshot == INVALID_HANDLE_VALUE {
+            anyhow::bail!(
+                "failed to list processes: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+        let mut entry: PROCESSENTRY32W = std::mem::zeroed();
+        entry.dwSize = std::mem::size_of::<PROCESSENTRY32W>() as u32;
+        let mut found = Process32FirstW(snapshot, &mut entry);
+        while found != 0 {
+            processes.push((entry.th32ProcessID, entry.th32ParentProcessID));
+            found = Process32NextW(snapshot, &mut entry);
+        }
+        CloseHandle(snapshot);
+    }
+    Ok(processes)
+}
@@ -0,0 +1,192 @@
+use std::process::ExitStatus;
+
+use super::metrics::TaskMetrics;
+
+/// Why and how a process exited
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct ExitDetails {
+    /// The exit code, when the process exited by itself
+    pub code: Option<u32>,
+    /// The name of the signal that terminated the process (e.g. `SIGKILL`), or its number when it is not known
+    pub signal: Option<String>,
+    /// Whether the signal dumps the core of the process by default (e.g. `SIGSEGV`)
+    pub core_dump_signal: bool,
+    /// Whether the process was killed by the kernel because it ran out of memory.
+    /// Only detected on Linux, from the OOM kills of the cgroup of the process
+    pub oom_killed: bool,
+    /// Whether the process went over the memory limit of its task, see `ResourceLimits`
+    pub memory_limit_exceeded: bool,
+    /// The resources used by the process and the processes it started
+    pub metrics: Option<TaskMetrics>,
+}
+
+/// Signals by number, with their names and whether they dump core by default
+#[cfg(unix)]
+const SIGNALS: [(libc::c_int, &str, bool); 16] = [
+    (libc::SIGHUP, "SIGHUP", false),
+    (libc::SIGINT, "SIGINT", false),
+    (libc::SIGQUIT, "SIGQUIT", true),
+    (libc::SIGILL, "SIGILL", true),
+    (libc::SIGTRAP, "SIGTRAP", true),
+    (libc::SIGABRT, "SIGABRT", true),
+    (libc::SIGBUS, "SIGBUS", true),
+    (libc::SIGFPE, "SIGFPE", true),
+    (libc::SIGKILL, "SIGKILL", false),
+    (libc::SIGSEGV, "SIGSEGV", true),
+    (libc::SIGPIPE, "SIGPIPE", false),
+    (libc::SIGALRM, "SIGALRM", false),
+    (libc::SIGTERM, "SIGTERM", false),
+    (libc::SIGXCPU, "SIGXCPU", true),
+    (libc::SIGXFSZ, "SIGXFSZ", true),
+    (libc::SIGSYS, "SIGSYS", true),
+];
+
+/// `oom_kills_before` is the number of OOM kills in the cgroup of this process before the child was spawned
+pub fn exit_details(status: &ExitStatus, oom_kills_before: Option<u64>) -> ExitDetails {
+    let Some((signal, core_dump_signal)) = terminating_signal(status) else {
+        return ExitDetails {
+            code: Some(status.code().map_or(1, |code| code as u32)),
+            ..Default::default()
+        };
+    };
+
+    let oom_killed = signal == "SIGKILL"
+        && oom_kills_before
+            .zip(oom_kill_count())
+            .is_some_and(|(before, after)| after > before);
+
+    ExitDetails {
+        code: None,
+        signal: Some(signal),
+        core_dump_signal,
+        oom_killed,
+        memory_limit_exceeded: false,
+        metrics: None,
+    }
+}
+
+/// The name of the signal that terminated the process, and whether it dumps core by default
+#[cfg(unix)]
+fn terminating_signal(status: &ExitStatus) -> Option<(String, bool)> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let signal = status.signal()?;
+    Some(
+        SIGNALS
+            .iter()
+            .find(|(number, _, _)| *number == signal)
+            .map_or((signal.to_string(), false), |(_, name, core_dump)| {
+                (name.to_string(), *core_dump)
+            }),
+    )
+}
+
+/// Processes are not terminated by signals on Windows
+#[cfg(not(unix))]
+fn terminating_signal(_status: &ExitStatus) -> Option<(String, bool)> {
+    None
+}
+
+/// The number of processes that were killed because the cgroup of this process ran out of memory
+#[cfg(target_os = "linux")]
+pub fn oom_kill_count() -> Option<u64> {
+    use std::path::Path;
+
+    let cgroups = std::fs::read_to_string("/proc/self/cgroup").ok()?;
+    let root = Path::new("/sys/fs/cgroup");
+    let events = match find_cgroup(&cgroups, "") {
+        // cgroup v2 has a single hierarchy
+        Some(cgroup) => root
+            .join(cgroup.trim_start_matches('/'))
+            .join("memory.events"),
+        None => root
+            .join("memory")
+            .join(find_cgroup(&cgroups, "memory")?.trim_start_matches('/'))
+            .join("memory.oom_control"),
+    };
+    parse_oom_kills(&std::fs::read_to_string(events).ok()?)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn oom_kill_count() -> Option<u64> {
+    None
+}
+
+/// Finds the path of a cgroup in `/proc/self/cgroup`, where lines are `<id>:<controllers>:<path>`
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(super) fn find_cgroup<'a>(cgroups: &'a str, controller: &str) -> Option<&'a str> {
+    cgroups.lines().find_map(|line| {
+        let mut parts = line.splitn(3, ':');
+        let (_, controllers, path) = (parts.next()?, parts.next()?, parts.next()?);
+        (controllers == controller || controllers.split(',').any(|c| c == controller))
+            .then_some(path)
+    })
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(super) fn parse_oom_kills(events: &str) -> Option<u64> {
+    events.lines().find_map(|line| {
+        let (key, value) = line.split_once(' ')?;
+        if key == "oom_kill" {
+            value.trim().parse().ok()
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn should_describe_exits() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let details = exit_details(&ExitStatus::from_raw(2 << 8), None);
+        assert_eq!(details.code, Some(2));
+        assert_eq!(details.signal, None);
+
+        let details = exit_details(&ExitStatus::from_raw(libc::SIGSEGV), None);
+        assert_eq!(details.code, None);
+        assert_eq!(details.signal.as_deref(), Some("SIGSEGV"));
+        assert!(details.core_dump_signal);
+        assert!(!details.oom_killed);
+
+        let details = exit_details(&ExitStatus::from_raw(libc::SIGUSR1), None);
+        assert_eq!(details.signal, Some(libc::SIGUSR1.to_string()));
+        assert!(!details.core_dump_signal);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn should_describe_the_signals_that_terminated_children() {
+        let mut child = std::process::Command::new("sh")
+            .args(["-c", "kill -KILL $$"])
+            .spawn()
+            .unwrap();
+        let status = super::super::os::wait_for_exit(&mut child).unwrap();
+
+        let details = exit_details(&status, None);
+        assert_eq!(details.code, None);
+        assert_eq!(details.signal.as_deref(), Some("SIGKILL"));
+        assert!(!details.core_dump_signal);
+    }
+
+    #[test]
+    fn should_parse_oom_kills() {
+        let cgroups = "12:memory:/docker/abc\n0::/user.slice/session-1.scope\n";
+        assert_eq!(
+            find_cgroup(cgroups, ""),
+            Some("/user.slice/session-1.scope")
+        );
+        assert_eq!(find_cgroup(cgroups, "memory"), Some("/docker/abc"));
+        assert_eq!(find_cgroup("3:cpu,cpuacct:/a", "cpu"), Some("/a"));
+
+        let events = "low 0\nhigh 0\nmax 12\noom 3\noom_kill 2\noom_group_kill 0\n";
+        assert_eq!(parse_oom_kills(events), Some(2));
+        assert_eq!(parse_oom_kills("oom_kill_disable 0\nunder_oom 0\n"), None);
+    }
+}
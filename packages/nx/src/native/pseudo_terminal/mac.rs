@@ -1,21 +1,31 @@
 use std::collections::HashMap;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
 
 use tracing::trace;
 
 use super::child_process::ChildProcess;
 use super::os;
+use super::output_buffer::{OutputRingBuffer, TaskOutput, TaskOutputs};
 use super::pseudo_terminal::{create_pseudo_terminal, run_command};
+use super::redaction::command_redactor;
+use super::resource_limits::ResourceLimits;
 use crate::native::logger::enable_logger;
 
 #[napi]
-pub struct RustPseudoTerminal {}
+pub struct RustPseudoTerminal {
+    task_outputs: TaskOutputs,
+}
 
 #[napi]
 impl RustPseudoTerminal {
     #[napi(constructor)]
     pub fn new() -> napi::Result<Self> {
         enable_logger();
-        Ok(Self {})
+        Ok(Self {
+            task_outputs: TaskOutputs::default(),
+        })
     }
 
     #[napi]
@@ -27,8 +37,38 @@ impl RustPseudoTerminal {
         exec_argv: Option<Vec<String>>,
         quiet: Option<bool>,
         tty: Option<bool>,
+    ) -> napi::Result<ChildProcess> {
+        self.run(
+            command,
+            command_dir,
+            js_env,
+            exec_argv,
+            quiet,
+            tty,
+            None,
+            None,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn run(
+        &self,
+        command: String,
+        command_dir: Option<String>,
+        js_env: Option<HashMap<String, String>>,
+        exec_argv: Option<Vec<String>>,
+        quiet: Option<bool>,
+        tty: Option<bool>,
+        output: Option<Arc<Mutex<OutputRingBuffer>>>,
+        resource_limits: Option<ResourceLimits>,
     ) -> napi::Result<ChildProcess> {
         let pseudo_terminal = create_pseudo_terminal()?;
+        pseudo_terminal.record_output(output);
+        pseudo_terminal.limit_resources(resource_limits);
+        pseudo_terminal.redact_output(
+            command_redactor(js_env.as_ref())
+                .map_err(|e| napi::Error::from_reason(e.to_string()))?,
+        );
         run_command(
             &pseudo_terminal,
             command,
@@ -41,7 +81,8 @@ impl RustPseudoTerminal {
     }
 
     /// This allows us to run a pseudoterminal with a fake node ipc channel
-    /// this makes it possible to be backwards compatible with the old implementation
+    /// this makes it possible to be backwards compatible with the old implementation.
+    /// The task and the processes it starts are limited to `resource_limits`
     #[napi]
     #[allow(clippy::too_many_arguments)]
     pub fn fork(
@@ -53,6 +94,7 @@ impl RustPseudoTerminal {
         js_env: Option<HashMap<String, String>>,
         exec_argv: Option<Vec<String>>,
         quiet: bool,
+        resource_limits: Option<ResourceLimits>,
     ) -> napi::Result<ChildProcess> {
         let command = format!(
             "node {} {} {}",
@@ -62,13 +104,24 @@ impl RustPseudoTerminal {
         );
 
         trace!("nx_fork command: {}", &command);
-        self.run_command(
+        let output = self.task_outputs.create(id);
+        self.run(
             command,
             command_dir,
             js_env,
             exec_argv,
             Some(quiet),
             Some(true),
+            Some(output),
+            resource_limits,
         )
     }
+
+    /// Replays the recent output of a forked task from `fromOffset` (the start of the output by default),
+    /// so consumers can re-attach to a running task. Returns nothing for tasks that were not forked
+    #[napi]
+    pub fn get_task_output(&self, task_id: String, from_offset: Option<i64>) -> Option<TaskOutput> {
+        let offset = from_offset.unwrap_or(0).max(0) as u64;
+        self.task_outputs.read(&task_id, offset)
+    }
 }
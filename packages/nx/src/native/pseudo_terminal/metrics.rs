@@ -0,0 +1,294 @@
+// samples are aggregated and parsed on Linux and macOS only
+#![cfg_attr(windows, allow(dead_code))]
+
+use std::collections::HashMap;
+#[cfg(not(windows))]
+use std::collections::HashSet;
+#[cfg(not(windows))]
+use std::thread::JoinHandle;
+#[cfg(not(windows))]
+use std::time::Duration;
+
+#[cfg(not(windows))]
+use crossbeam_channel::{bounded, RecvTimeoutError, Sender};
+
+#[cfg(not(windows))]
+use super::process_tree::descendants;
+
+/// How often the processes of a task are sampled
+#[cfg(not(windows))]
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(200);
+
+/// The resources used by a task and the processes it started.
+/// They are sampled on Linux and macOS, so short-lived processes may be missed
+#[napi(object)]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TaskMetrics {
+    /// User and system CPU time, in milliseconds
+    pub cpu_time: f64,
+    /// The peak memory of all the processes together, in bytes.
+    /// This is the resident memory on Linux and macOS, and the committed memory on Windows
+    pub peak_rss: i64,
+    /// Bytes read from storage, not measured on macOS
+    pub read_bytes: i64,
+    /// Bytes written to storage, not measured on macOS
+    pub write_bytes: i64,
+}
+
+/// A sample of the resources used by a process
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(super) struct ProcessStats {
+    pub pid: u32,
+    pub ppid: u32,
+    /// In milliseconds
+    pub cpu_time: f64,
+    pub rss: u64,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+}
+
+/// Aggregates samples of the process tree of a task.
+/// Processes that exited keep the values of their last sample
+#[derive(Default)]
+struct MetricsAggregator {
+    processes: HashMap<u32, ProcessStats>,
+    peak_rss: u64,
+}
+
+impl MetricsAggregator {
+    fn add_sample(&mut self, sample: Vec<ProcessStats>) {
+        let rss = sample.iter().map(|stats| stats.rss).sum::<u64>();
+        self.peak_rss = self.peak_rss.max(rss);
+        for stats in sample {
+            self.processes.insert(stats.pid, stats);
+        }
+    }
+
+    fn metrics(&self) -> TaskMetrics {
+        let processes = self.processes.values();
+        TaskMetrics {
+            cpu_time: processes.clone().map(|stats| stats.cpu_time).sum(),
+            peak_rss: self.peak_rss as i64,
+            read_bytes: processes.clone().map(|stats| stats.read_bytes).sum::<u64>() as i64,
+            write_bytes: processes.map(|stats| stats.write_bytes).sum::<u64>() as i64,
+        }
+    }
+}
+
+/// Samples the resources used by the process tree of a task until it is finished
+#[cfg(not(windows))]
+pub struct MetricsSampler {
+    stop: Sender<()>,
+    thread: JoinHandle<MetricsAggregator>,
+}
+
+#[cfg(not(windows))]
+impl MetricsSampler {
+    pub fn start(pid: u32) -> Self {
+        let (stop, stopped) = bounded(1);
+        let thread = std::thread::spawn(move || {
+            let mut aggregator = MetricsAggregator::default();
+            loop {
+                aggregator.add_sample(sample_process_tree(pid));
+                match stopped.recv_timeout(SAMPLE_INTERVAL) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    _ => break aggregator,
+                }
+            }
+        });
+        Self { stop, thread }
+    }
+
+    pub fn finish(self) -> TaskMetrics {
+        self.stop.send(()).ok();
+        self.thread
+            .join()
+            .map(|aggregator| aggregator.metrics())
+            .unwrap_or_default()
+    }
+}
+
+/// Job objects account for the resources of every process of the job, so nothing is sampled
+#[cfg(windows)]
+pub struct MetricsSampler {
+    pid: u32,
+}
+
+#[cfg(windows)]
+impl MetricsSampler {
+    pub fn start(pid: u32) -> Self {
+        Self { pid }
+    }
+
+    /// Has to be called before the process tree is untracked
+    pub fn finish(self) -> TaskMetrics {
+        super::os::job_metrics(self.pid).unwrap_or_default()
+    }
+}
+
+#[cfg(not(windows))]
+fn sample_process_tree(pid: u32) -> Vec<ProcessStats> {
+    let processes = list_process_stats();
+    let descendants = descendants(
+        pid,
+        &processes
+            .iter()
+            .map(|stats| (stats.pid, stats.ppid))
+            .collect::<Vec<_>>(),
+    )
+    .into_iter()
+    .collect::<HashSet<_>>();
+    let tree = processes
+        .into_iter()
+        .filter(|stats| stats.pid == pid || descendants.contains(&stats.pid));
+    #[cfg(target_os = "linux")]
+    let tree = tree.map(|mut stats| {
+        if let Ok(io) = std::fs::read_to_string(format!("/proc/{}/io", stats.pid)) {
+            (stats.read_bytes, stats.write_bytes) = parse_proc_io(&io);
+        }
+        stats
+    });
+    tree.collect()
+}
+
+#[cfg(target_os = "linux")]
+fn list_process_stats() -> Vec<ProcessStats> {
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as f64;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(1) as u64;
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return vec![];
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter_map(|pid| {
+            let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+            parse_proc_stat(pid, &stat, ticks_per_second, page_size)
+        })
+        .collect()
+}
+
+/// Parses `/proc/<pid>/stat`, the name of the process is in parentheses and may contain spaces
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_proc_stat(
+    pid: u32,
+    stat: &str,
+    ticks_per_second: f64,
+    page_size: u64,
+) -> Option<ProcessStats> {
+    let fields = stat[stat.rfind(')')? + 1..]
+        .split_whitespace()
+        .collect::<Vec<_>>();
+    let field = |index: usize| fields.get(index)?.parse::<u64>().ok();
+    // the fields after the name start at the 3rd field of the file
+    let cpu_ticks = field(11)? + field(12)?;
+    Some(ProcessStats {
+        pid,
+        ppid: field(1)? as u32,
+        cpu_time: cpu_ticks as f64 * 1000.0 / ticks_per_second,
+        rss: field(21)? * page_size,
+        ..Default::default()
+    })
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_proc_io(io: &str) -> (u64, u64) {
+    let value = |key: &str| {
+        io.lines()
+            .find_map(|line| line.strip_prefix(key)?.trim().parse().ok())
+            .unwrap_or(0)
+    };
+    (value("read_bytes:"), value("write_bytes:"))
+}
+
+/// Lists the processes with `ps`, which does not report I/O
+#[cfg(all(unix, not(target_os = "linux")))]
+fn list_process_stats() -> Vec<ProcessStats> {
+    let Ok(output) = std::process::Command::new("ps")
+        .args([
+            "-A", "-o", "pid=", "-o", "ppid=", "-o", "rss=", "-o", "time=",
+        ])
+        .output()
+    else {
+        return vec![];
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(parse_ps_line)
+        .collect()
+}
+
+#[cfg_attr(target_os = "linux", allow(dead_code))]
+fn parse_ps_line(line: &str) -> Option<ProcessStats> {
+    let mut parts = line.split_whitespace();
+    Some(ProcessStats {
+        pid: parts.next()?.parse().ok()?,
+        ppid: parts.next()?.parse().ok()?,
+        // in KiB
+        rss: parts.next()?.parse::<u64>().ok()? * 1024,
+        cpu_time: parse_cpu_time(parts.next()?)?,
+        ..Default::default()
+    })
+}
+
+/// Parses `[[dd-]hh:]mm:ss[.ss]` into milliseconds
+#[cfg_attr(target_os = "linux", allow(dead_code))]
+fn parse_cpu_time(time: &str) -> Option<f64> {
+    let (days, time) = match time.split_once('-') {
+        Some((days, time)) => (days.parse::<f64>().ok()?, time),
+        None => (0.0, time),
+    };
+    let mut seconds = days * 86_400.0;
+    for (part, unit) in time.rsplit(':').zip([1.0, 60.0, 3600.0]) {
+        seconds += part.parse::<f64>().ok()? * unit;
+    }
+    Some(seconds * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_keep_the_last_sample_of_exited_processes() {
+        let stats = |pid, cpu_time, rss| ProcessStats {
+            pid,
+            cpu_time,
+            rss,
+            read_bytes: 10,
+            ..Default::default()
+        };
+        let mut aggregator = MetricsAggregator::default();
+        aggregator.add_sample(vec![stats(1, 10.0, 100), stats(2, 5.0, 300)]);
+        aggregator.add_sample(vec![stats(1, 20.0, 200)]);
+        assert_eq!(
+            aggregator.metrics(),
+            TaskMetrics {
+                cpu_time: 25.0,
+                peak_rss: 400,
+                read_bytes: 20,
+                write_bytes: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn should_parse_proc_files() {
+        let stat = "42 (node (worker)) S 7 42 42 0 -1 4194560 1000 0 0 0 150 50 0 0 20 0 11 0 100 1000 25 18446744073709551615";
+        let stats = parse_proc_stat(42, stat, 100.0, 4096).unwrap();
+        assert_eq!(stats.ppid, 7);
+        assert_eq!(stats.cpu_time, 2000.0);
+        assert_eq!(stats.rss, 25 * 4096);
+
+        let io = "rchar: 100\nwchar: 200\nsyscr: 1\nsyscw: 2\nread_bytes: 4096\nwrite_bytes: 8192\ncancelled_write_bytes: 0\n";
+        assert_eq!(parse_proc_io(io), (4096, 8192));
+    }
+
+    #[test]
+    fn should_parse_ps_output() {
+        let stats = parse_ps_line("  501   1  20480   1:02.50").unwrap();
+        assert_eq!((stats.pid, stats.ppid, stats.rss), (501, 1, 20480 * 1024));
+        assert_eq!(stats.cpu_time, 62_500.0);
+        assert_eq!(parse_cpu_time("1-02:00:00"), Some(93_600_000.0));
+    }
+}
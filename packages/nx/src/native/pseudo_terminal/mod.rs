@@ -5,7 +5,16 @@ mod os;
 #[allow(clippy::module_inception)]
 mod pseudo_terminal;
 
+pub mod ansi;
+#[cfg(target_os = "linux")]
+mod cgroup;
 pub mod child_process;
+pub mod exit_status;
+pub mod metrics;
+pub mod output_buffer;
+pub mod process_tree;
+pub mod redaction;
+pub mod resource_limits;
 
 #[cfg_attr(target_os = "macos", path = "mac.rs")]
 #[cfg_attr(not(target_os = "macos"), path = "non_mac.rs")]
@@ -1,15 +1,22 @@
 use std::collections::HashMap;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
 
 use tracing::trace;
 
 use super::child_process::ChildProcess;
 use super::os;
+use super::output_buffer::{OutputRingBuffer, TaskOutput, TaskOutputs};
 use super::pseudo_terminal::{create_pseudo_terminal, run_command, PseudoTerminal};
+use super::redaction::command_redactor;
+use super::resource_limits::ResourceLimits;
 use crate::native::logger::enable_logger;
 
 #[napi]
 pub struct RustPseudoTerminal {
     pseudo_terminal: PseudoTerminal,
+    task_outputs: TaskOutputs,
 }
 
 #[napi]
@@ -20,7 +27,10 @@ impl RustPseudoTerminal {
 
         let pseudo_terminal = create_pseudo_terminal()?;
 
-        Ok(Self { pseudo_terminal })
+        Ok(Self {
+            pseudo_terminal,
+            task_outputs: TaskOutputs::default(),
+        })
     }
 
     #[napi]
@@ -33,6 +43,36 @@ impl RustPseudoTerminal {
         quiet: Option<bool>,
         tty: Option<bool>,
     ) -> napi::Result<ChildProcess> {
+        self.run(
+            command,
+            command_dir,
+            js_env,
+            exec_argv,
+            quiet,
+            tty,
+            None,
+            None,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn run(
+        &self,
+        command: String,
+        command_dir: Option<String>,
+        js_env: Option<HashMap<String, String>>,
+        exec_argv: Option<Vec<String>>,
+        quiet: Option<bool>,
+        tty: Option<bool>,
+        output: Option<Arc<Mutex<OutputRingBuffer>>>,
+        resource_limits: Option<ResourceLimits>,
+    ) -> napi::Result<ChildProcess> {
+        self.pseudo_terminal.record_output(output);
+        self.pseudo_terminal.limit_resources(resource_limits);
+        self.pseudo_terminal.redact_output(
+            command_redactor(js_env.as_ref())
+                .map_err(|e| napi::Error::from_reason(e.to_string()))?,
+        );
         run_command(
             &self.pseudo_terminal,
             command,
@@ -45,8 +85,10 @@ impl RustPseudoTerminal {
     }
 
     /// This allows us to run a pseudoterminal with a fake node ipc channel
-    /// this makes it possible to be backwards compatible with the old implementation
+    /// this makes it possible to be backwards compatible with the old implementation.
+    /// The task and the processes it starts are limited to `resource_limits`
     #[napi]
+    #[allow(clippy::too_many_arguments)]
     pub fn fork(
         &self,
         id: String,
@@ -56,6 +98,7 @@ impl RustPseudoTerminal {
         js_env: Option<HashMap<String, String>>,
         exec_argv: Option<Vec<String>>,
         quiet: bool,
+        resource_limits: Option<ResourceLimits>,
     ) -> napi::Result<ChildProcess> {
         let command = format!(
             "node {} {} {}",
@@ -65,6 +108,24 @@ impl RustPseudoTerminal {
         );
 
         trace!("nx_fork command: {}", &command);
-        self.run_command(command, command_dir, js_env, exec_argv, Some(quiet), Some(true))
+        let output = self.task_outputs.create(id);
+        self.run(
+            command,
+            command_dir,
+            js_env,
+            exec_argv,
+            Some(quiet),
+            Some(true),
+            Some(output),
+            resource_limits,
+        )
+    }
+
+    /// Replays the recent output of a forked task from `fromOffset` (the start of the output by default),
+    /// so consumers can re-attach to a running task. Returns nothing for tasks that were not forked
+    #[napi]
+    pub fn get_task_output(&self, task_id: String, from_offset: Option<i64>) -> Option<TaskOutput> {
+        let offset = from_offset.unwrap_or(0).max(0) as u64;
+        self.task_outputs.read(&task_id, offset)
     }
 }
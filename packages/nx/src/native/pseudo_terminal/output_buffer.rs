@@ -0,0 +1,122 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use parking_lot::Mutex;
+
+/// How much output is kept for every task, older output is dropped
+pub const TASK_OUTPUT_BUFFER_SIZE: usize = 1024 * 1024;
+
+#[napi(object)]
+#[derive(Debug)]
+pub struct TaskOutput {
+    pub output: String,
+    /// The offset to read the output that comes after this output
+    pub next_offset: i64,
+    /// Whether output after the requested offset was dropped, because the buffer was full
+    pub truncated: bool,
+}
+
+/// Keeps the latest output of a task, addressed by the offset of the bytes in the whole output of the task
+pub struct OutputRingBuffer {
+    buffer: VecDeque<u8>,
+    capacity: usize,
+    /// Offset of the first byte in the buffer
+    start_offset: u64,
+}
+
+impl OutputRingBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            buffer: VecDeque::with_capacity(capacity),
+            capacity,
+            start_offset: 0,
+        }
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        let bytes = if bytes.len() > self.capacity {
+            self.start_offset += (bytes.len() - self.capacity) as u64;
+            &bytes[bytes.len() - self.capacity..]
+        } else {
+            bytes
+        };
+
+        let overflow = (self.buffer.len() + bytes.len()).saturating_sub(self.capacity);
+        self.buffer.drain(..overflow);
+        self.start_offset += overflow as u64;
+        self.buffer.extend(bytes);
+    }
+
+    /// Reads the output from `offset`, or from the oldest output that is still buffered.
+    /// Output that is cut in the middle of a character is read lossily
+    pub fn read_from(&self, offset: u64) -> TaskOutput {
+        let end_offset = self.start_offset + self.buffer.len() as u64;
+        let offset = offset.min(end_offset);
+        let truncated = offset < self.start_offset;
+        let start = offset.saturating_sub(self.start_offset) as usize;
+
+        let bytes = self.buffer.range(start..).copied().collect::<Vec<_>>();
+        TaskOutput {
+            output: String::from_utf8_lossy(&bytes).to_string(),
+            next_offset: end_offset as i64,
+            truncated,
+        }
+    }
+}
+
+/// The output buffers of the tasks that ran in a pseudo terminal, by task id
+#[derive(Default, Clone)]
+pub struct TaskOutputs(Arc<DashMap<String, Arc<Mutex<OutputRingBuffer>>>>);
+
+impl TaskOutputs {
+    /// Creates an empty buffer for a task, replacing the output of a previous run of the task
+    pub fn create(&self, task_id: String) -> Arc<Mutex<OutputRingBuffer>> {
+        let buffer = Arc::new(Mutex::new(OutputRingBuffer::new(TASK_OUTPUT_BUFFER_SIZE)));
+        self.0.insert(task_id, Arc::clone(&buffer));
+        buffer
+    }
+
+    pub fn read(&self, task_id: &str, offset: u64) -> Option<TaskOutput> {
+        self.0
+            .get(task_id)
+            .map(|buffer| buffer.lock().read_from(offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_replay_output_from_an_offset() {
+        let mut buffer = OutputRingBuffer::new(8);
+        buffer.write(b"hello ");
+        let output = buffer.read_from(0);
+        assert_eq!(output.output, "hello ");
+        assert_eq!(output.next_offset, 6);
+
+        buffer.write(b"world");
+        let output = buffer.read_from(6);
+        assert_eq!(output.output, "world");
+        assert_eq!(output.next_offset, 11);
+        assert!(!output.truncated);
+
+        // the first 3 bytes were dropped
+        let output = buffer.read_from(0);
+        assert_eq!(output.output, "lo world");
+        assert!(output.truncated);
+
+        assert_eq!(buffer.read_from(100).output, "");
+    }
+
+    #[test]
+    fn should_keep_the_end_of_large_writes() {
+        let mut buffer = OutputRingBuffer::new(4);
+        buffer.write(b"abcdefgh");
+        let output = buffer.read_from(0);
+        assert_eq!(output.output, "efgh");
+        assert_eq!(output.next_offset, 8);
+        assert!(output.truncated);
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use super::os;
+
+/// Kills a process and all of its descendants, so that workers started by a task (e.g. by webpack or vitest) do not outlive it.
+/// `signal` is the name of the signal to send (`SIGTERM` by default), processes are always terminated on Windows
+#[napi]
+pub fn kill_tree(pid: u32, signal: Option<String>) -> anyhow::Result<()> {
+    os::kill_tree(pid, signal.as_deref().unwrap_or("SIGTERM"))
+}
+
+/// Finds the descendants of `pid` in a list of `(pid, parent pid)` pairs, parents before their children
+pub(super) fn descendants(pid: u32, processes: &[(u32, u32)]) -> Vec<u32> {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for (child, parent) in processes {
+        if child != parent {
+            children.entry(*parent).or_default().push(*child);
+        }
+    }
+
+    let mut descendants = vec![];
+    let mut seen = HashSet::from([pid]);
+    let mut queue = VecDeque::from([pid]);
+    while let Some(parent) = queue.pop_front() {
+        for child in children.get(&parent).into_iter().flatten() {
+            if seen.insert(*child) {
+                descendants.push(*child);
+                queue.push_back(*child);
+            }
+        }
+    }
+    descendants
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_find_descendants() {
+        let processes = [
+            (1, 0),
+            (10, 1),
+            (11, 10),
+            (12, 10),
+            (13, 11),
+            (20, 1),
+            (0, 0),
+        ];
+        assert_eq!(descendants(10, &processes), [11, 12, 13]);
+        assert_eq!(descendants(13, &processes), Vec::<u32>::new());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn should_kill_process_trees() {
+        use std::os::unix::process::ExitStatusExt;
+        use std::process::Command;
+
+        let mut child = Command::new("sh")
+            .args(["-c", "sleep 30 & sleep 30 & wait"])
+            .spawn()
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(200));
+
+        kill_tree(child.id(), Some("SIGKILL".into())).unwrap();
+        assert_eq!(child.wait().unwrap().signal(), Some(9));
+    }
+}
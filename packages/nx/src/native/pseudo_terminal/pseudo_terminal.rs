@@ -15,10 +15,16 @@ use crossterm::{
     terminal::{disable_raw_mode, enable_raw_mode},
     tty::IsTty,
 };
+use parking_lot::Mutex;
 use portable_pty::{CommandBuilder, NativePtySystem, PtyPair, PtySize, PtySystem};
 use tracing::log::trace;
 
+use super::exit_status::{exit_details, oom_kill_count, ExitDetails};
+use super::metrics::MetricsSampler;
 use super::os;
+use super::output_buffer::OutputRingBuffer;
+use super::redaction::{Redactor, StreamRedaction};
+use super::resource_limits::ResourceLimits;
 use crate::native::pseudo_terminal::child_process::ChildProcess;
 
 pub struct PseudoTerminal {
@@ -27,11 +33,36 @@ pub struct PseudoTerminal {
     pub printing_rx: Receiver<()>,
     pub quiet: Arc<AtomicBool>,
     pub running: Arc<AtomicBool>,
+    /// Where the output of the running command is recorded, if anywhere
+    pub output: Arc<Mutex<Option<Arc<Mutex<OutputRingBuffer>>>>>,
+    /// The resource limits of the next commands
+    pub resource_limits: Mutex<Option<ResourceLimits>>,
+    /// What is masked in the output of the running command, if anything
+    pub redactor: Arc<Mutex<Option<Arc<Redactor>>>>,
+}
+
+impl PseudoTerminal {
+    /// Records the output of the next commands in `output`, or stops recording it
+    pub fn record_output(&self, output: Option<Arc<Mutex<OutputRingBuffer>>>) {
+        *self.output.lock() = output;
+    }
+
+    /// Limits the resources of the next commands with `limits`, or stops limiting them
+    pub fn limit_resources(&self, limits: Option<ResourceLimits>) {
+        *self.resource_limits.lock() = limits;
+    }
+
+    /// Masks the secrets of `redactor` in the output of the next commands, or stops masking them
+    pub fn redact_output(&self, redactor: Option<Arc<Redactor>>) {
+        *self.redactor.lock() = redactor;
+    }
 }
 
 pub fn create_pseudo_terminal() -> napi::Result<PseudoTerminal> {
     let quiet = Arc::new(AtomicBool::new(true));
     let running = Arc::new(AtomicBool::new(false));
+    let output: Arc<Mutex<Option<Arc<Mutex<OutputRingBuffer>>>>> = Arc::new(Mutex::new(None));
+    let redactor: Arc<Mutex<Option<Arc<Redactor>>>> = Arc::new(Mutex::new(None));
 
     let pty_system = NativePtySystem::default();
 
@@ -67,52 +98,79 @@ pub fn create_pseudo_terminal() -> napi::Result<PseudoTerminal> {
     // Output -> stdout handling
     let quiet_clone = quiet.clone();
     let running_clone = running.clone();
+    let output_clone = output.clone();
+    let redactor_clone = redactor.clone();
     std::thread::spawn(move || {
         let mut stdout = std::io::stdout();
         let mut buf = [0; 8 * 1024];
+        let mut redaction = StreamRedaction::default();
 
-        'read_loop: loop {
-            if let Ok(len) = reader.read(&mut buf) {
-                if len == 0 {
-                    break;
+        // Prints a chunk of the output, returns false when stdout cannot be written to anymore
+        let mut print = |chunk: &[u8]| -> bool {
+            message_tx
+                .send(String::from_utf8_lossy(chunk).to_string())
+                .ok();
+            if let Some(output) = output_clone.lock().as_ref() {
+                output.lock().write(chunk);
+            }
+            let quiet = quiet_clone.load(Ordering::Relaxed);
+            trace!("Quiet: {}", quiet);
+            if !quiet {
+                let mut content = String::from_utf8_lossy(chunk).to_string();
+                if content.contains("\x1B[6n") {
+                    trace!("Prevented terminal escape sequence ESC[6n from being printed.");
+                    content = content.replace("\x1B[6n", "");
                 }
-                message_tx
-                    .send(String::from_utf8_lossy(&buf[0..len]).to_string())
-                    .ok();
-                let quiet = quiet_clone.load(Ordering::Relaxed);
-                trace!("Quiet: {}", quiet);
-                if !quiet {
-                    let mut content = String::from_utf8_lossy(&buf[0..len]).to_string();
-                    if content.contains("\x1B[6n") {
-                        trace!("Prevented terminal escape sequence ESC[6n from being printed.");
-                        content = content.replace("\x1B[6n", "");
-                    }
-                    let mut logged_interrupted_error = false;
-                    while let Err(e) = stdout.write_all(content.as_bytes()) {
-                        match e.kind() {
-                            std::io::ErrorKind::Interrupted => {
-                                if !logged_interrupted_error {
-                                    trace!("Interrupted error writing to stdout: {:?}", e);
-                                    logged_interrupted_error = true;
-                                }
-                                continue;
-                            }
-                            _ => {
-                                // We should figure out what to do for more error types as they appear.
-                                trace!("Error writing to stdout: {:?}", e);
-                                trace!("Error kind: {:?}", e.kind());
-                                break 'read_loop;
+                let mut logged_interrupted_error = false;
+                while let Err(e) = stdout.write_all(content.as_bytes()) {
+                    match e.kind() {
+                        std::io::ErrorKind::Interrupted => {
+                            if !logged_interrupted_error {
+                                trace!("Interrupted error writing to stdout: {:?}", e);
+                                logged_interrupted_error = true;
                             }
+                            continue;
+                        }
+                        _ => {
+                            // We should figure out what to do for more error types as they appear.
+                            trace!("Error writing to stdout: {:?}", e);
+                            trace!("Error kind: {:?}", e.kind());
+                            return false;
                         }
                     }
-                    let _ = stdout.flush();
+                }
+                let _ = stdout.flush();
+            }
+            true
+        };
+
+        loop {
+            if let Ok(len) = reader.read(&mut buf) {
+                if len == 0 {
+                    break;
+                }
+                let redactor = redactor_clone.lock().clone();
+                let chunk = redaction.redact(redactor.as_deref(), &buf[0..len]);
+                if !print(&chunk) {
+                    break;
                 }
             }
             if !running_clone.load(Ordering::SeqCst) {
+                // the output that was held back cannot be continued by a command that exited
+                let redactor = redactor_clone.lock().clone();
+                let pending = redaction.flush(redactor.as_deref());
+                if !pending.is_empty() && !print(&pending) {
+                    break;
+                }
                 printing_tx.send(()).ok();
             }
         }
 
+        let redactor = redactor_clone.lock().clone();
+        let pending = redaction.flush(redactor.as_deref());
+        if !pending.is_empty() {
+            print(&pending);
+        }
         printing_tx.send(()).ok();
     });
     if std::io::stdout().is_tty() {
@@ -125,6 +183,9 @@ pub fn create_pseudo_terminal() -> napi::Result<PseudoTerminal> {
         pty_pair,
         message_rx,
         printing_rx,
+        output,
+        resource_limits: Mutex::new(None),
+        redactor,
     })
 }
 pub fn run_command(
@@ -159,7 +220,13 @@ pub fn run_command(
     }
 
     let (exit_to_process_tx, exit_to_process_rx) = bounded(1);
+    let oom_kills = oom_kill_count();
     let mut child = pair.slave.spawn_command(cmd)?;
+    let pid = child.process_id();
+    if let Some(pid) = pid {
+        os::track_process_tree(pid, pseudo_terminal.resource_limits.lock().as_ref());
+    }
+    let metrics = pid.map(MetricsSampler::start);
     pseudo_terminal.running.store(true, Ordering::SeqCst);
     trace!("Running {}", command);
     let is_tty = tty.unwrap_or_else(|| std::io::stdout().is_tty());
@@ -174,11 +241,14 @@ pub fn run_command(
     trace!("Getting printing_rx clone");
     let printing_rx = pseudo_terminal.printing_rx.clone();
 
+    let exited = Arc::new(AtomicBool::new(false));
+    let exited_clone = exited.clone();
+
     trace!("spawning thread to wait for command");
     std::thread::spawn(move || {
         trace!("Waiting for {}", command);
 
-        let res = child.wait();
+        let res = os::wait_for_exit(child.as_mut());
         if let Ok(exit) = res {
             trace!("{} Exited", command);
             // This mitigates the issues with ConPTY on windows and makes it work.
@@ -201,7 +271,16 @@ pub fn run_command(
                 trace!("Disabling raw mode");
                 disable_raw_mode().expect("Failed to restore non-raw terminal");
             }
-            exit_to_process_tx.send(exit.to_string()).ok();
+            exited_clone.store(true, Ordering::SeqCst);
+            let metrics = metrics.map(MetricsSampler::finish);
+            let memory_limit_exceeded = pid.is_some_and(os::untrack_process_tree);
+            let details = ExitDetails {
+                memory_limit_exceeded,
+                metrics,
+                ..exit_details(&exit, oom_kills)
+            };
+            let message = portable_pty::ExitStatus::from(exit).to_string();
+            exit_to_process_tx.send((message, details)).ok();
         } else {
             trace!("Error waiting for {}", command);
         };
@@ -210,6 +289,8 @@ pub fn run_command(
     trace!("Returning ChildProcess");
     Ok(ChildProcess::new(
         process_killer,
+        pid,
+        exited,
         pseudo_terminal.message_rx.clone(),
         exit_to_process_rx,
     ))
@@ -255,8 +336,16 @@ mod tests {
         let pseudo_terminal = create_pseudo_terminal().unwrap();
         while i < 10 {
             println!("Running {}", i);
-            let cp1 =
-                run_command(&pseudo_terminal, String::from("whoami"), None, None, None).unwrap();
+            let cp1 = run_command(
+                &pseudo_terminal,
+                String::from("whoami"),
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
             cp1.wait_receiver.recv().unwrap();
             i += 1;
         }
@@ -0,0 +1,20 @@
+/// Limits on the resources that a task and the processes it starts can use.
+/// They are enforced with cgroups (v2) on Linux and job objects on Windows, and are not supported on macOS
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct ResourceLimits {
+    /// The memory the task can use, in MiB. The task is killed when it needs more
+    pub memory: Option<u32>,
+    /// The number of CPUs the task can use, e.g. `1.5`. The task is throttled when it uses more
+    pub cpus: Option<f64>,
+}
+
+impl ResourceLimits {
+    pub fn is_empty(&self) -> bool {
+        self.memory.is_none() && self.cpus.is_none()
+    }
+
+    pub fn memory_bytes(&self) -> Option<u64> {
+        self.memory.map(|memory| memory as u64 * 1024 * 1024)
+    }
+}
@@ -0,0 +1,56 @@
+use std::time::{Duration, Instant};
+
+use napi::bindgen_prelude::External;
+use tracing::{debug, warn};
+
+use crate::native::db::connection::NxDbConnection;
+use crate::native::utils::drain_work;
+
+/// How long the work in flight is waited for by default
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownReport {
+    /// The hashing and the writes to the Nx database that were still in flight at the deadline
+    pub abandoned_work: u32,
+    /// Whether the WAL of the Nx database was moved into the database completely
+    pub checkpointed: bool,
+}
+
+/// Shuts the native work of the process down before it exits: no hashing or writes to the Nx database are started anymore,
+/// and the ones in flight are waited for until `timeout_ms` (5 seconds by default) have passed.
+/// The database is then checkpointed, so its file is complete even if the WAL is removed afterwards (e.g. by `nx reset`)
+#[napi]
+pub fn shutdown_gracefully(
+    db_connection: Option<External<NxDbConnection>>,
+    timeout_ms: Option<u32>,
+) -> ShutdownReport {
+    let timeout = timeout_ms.map_or(DEFAULT_SHUTDOWN_TIMEOUT, |timeout| {
+        Duration::from_millis(timeout.into())
+    });
+    let abandoned_work = drain_work(Instant::now() + timeout);
+    if abandoned_work > 0 {
+        warn!(
+            "shutting down with {} units of work still in flight after {:?}",
+            abandoned_work, timeout
+        );
+    }
+
+    let checkpointed = match db_connection.as_deref().map(NxDbConnection::checkpoint) {
+        Some(Ok(checkpointed)) => checkpointed,
+        Some(Err(e)) => {
+            warn!("could not checkpoint the Nx database: {:?}", e);
+            false
+        }
+        None => {
+            debug!("there is no connection to the Nx database to checkpoint");
+            false
+        }
+    };
+
+    ShutdownReport {
+        abandoned_work: abandoned_work as u32,
+        checkpointed,
+    }
+}
@@ -1,5 +1,7 @@
 use napi::bindgen_prelude::*;
-use rusqlite::{params, Connection};
+use rusqlite::params;
+
+use crate::native::db::connection::NxDbConnection;
 
 #[napi(object)]
 #[derive(Default, Clone)]
@@ -12,45 +14,27 @@ pub struct HashedTask {
 
 #[napi]
 struct TaskDetails {
-    db: External<Connection>,
+    db: External<NxDbConnection>,
 }
 
 #[napi]
 impl TaskDetails {
     #[napi(constructor)]
-    pub fn new(db: External<Connection>) -> anyhow::Result<Self> {
-        let r = Self { db };
-
-        r.setup()?;
-
-        Ok(r)
-    }
-
-    fn setup(&self) -> anyhow::Result<()> {
-        self.db.execute(
-            "
-            CREATE TABLE IF NOT EXISTS task_details (
-                hash    TEXT PRIMARY KEY NOT NULL,
-                project  TEXT NOT NULL,
-                target  TEXT NOT NULL,
-                configuration  TEXT
-            );",
-            params![],
-        )?;
-
-        Ok(())
+    pub fn new(db: External<NxDbConnection>) -> anyhow::Result<Self> {
+        Ok(Self { db })
     }
 
     #[napi]
     pub fn record_task_details(&self, tasks: Vec<HashedTask>) -> anyhow::Result<()> {
-        for task in tasks.iter() {
-            self.db.execute(
-                "INSERT OR REPLACE INTO task_details  (hash, project, target, configuration)
-                    VALUES (?1, ?2, ?3, ?4)",
-                params![task.hash, task.project, task.target, task.configuration],
-            )?;
-        }
-
-        Ok(())
+        self.db.write(move |db| {
+            for task in tasks.iter() {
+                db.execute(
+                    "INSERT OR REPLACE INTO task_details  (hash, project, target, configuration)
+                        VALUES (?1, ?2, ?3, ?4)",
+                    params![task.hash, task.project, task.target, task.configuration],
+                )?;
+            }
+            Ok(())
+        })
     }
 }
@@ -0,0 +1,76 @@
+use napi::bindgen_prelude::*;
+use rusqlite::params;
+
+use crate::native::db::connection::NxDbConnection;
+use crate::native::tasks::task_hasher::PersistedHash;
+
+/// Persists the caches of the task hasher so they survive daemon restarts
+#[napi]
+pub struct NxHashCache {
+    db: External<NxDbConnection>,
+}
+
+#[napi]
+impl NxHashCache {
+    #[napi(constructor)]
+    pub fn new(db: External<NxDbConnection>) -> anyhow::Result<Self> {
+        Ok(Self { db })
+    }
+
+    #[napi]
+    pub fn get_hashes(&self) -> anyhow::Result<Vec<PersistedHash>> {
+        let db = self.db.connection()?;
+        let mut stmt = db.prepare("SELECT cache_key, hash, fingerprint FROM task_hash_cache")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(PersistedHash {
+                cache_key: row.get(0)?,
+                hash: row.get(1)?,
+                fingerprint: row.get(2)?,
+            })
+        })?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(anyhow::Error::from)
+    }
+
+    /// Replaces the persisted hashes with the given hashes
+    #[napi]
+    pub fn save_hashes(&self, hashes: Vec<PersistedHash>) -> anyhow::Result<()> {
+        // the write is atomic, so the previous hashes are only deleted when the new ones are saved
+        self.db.write(move |db| {
+            db.execute("DELETE FROM task_hash_cache", [])?;
+            let mut stmt = db.prepare(
+                "INSERT OR REPLACE INTO task_hash_cache (cache_key, hash, fingerprint) VALUES (?1, ?2, ?3)",
+            )?;
+            for hash in hashes.iter() {
+                stmt.execute(params![hash.cache_key, hash.hash, hash.fingerprint])?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn should_replace_persisted_hashes() {
+        let temp = TempDir::new().unwrap();
+        let db = NxDbConnection::open(&temp.join("nx.db")).unwrap();
+        let cache = NxHashCache::new(External::new(db)).unwrap();
+        let hash = |key: &str, hash: &str| PersistedHash {
+            cache_key: key.into(),
+            hash: hash.into(),
+            fingerprint: "fingerprint".into(),
+        };
+
+        cache
+            .save_hashes(vec![hash("file:a", "1"), hash("file:b", "2")])
+            .unwrap();
+        cache.save_hashes(vec![hash("file:b", "3")]).unwrap();
+
+        assert_eq!(cache.get_hashes().unwrap(), vec![hash("file:b", "3")]);
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::native::tasks::task_hasher::HashInputDetails;
+
+#[napi(object)]
+#[derive(Debug, Default, PartialEq)]
+pub struct TaskHashDiff {
+    /// Inputs that are only part of the new hash
+    pub added_inputs: Vec<String>,
+    /// Inputs that are only part of the old hash
+    pub removed_inputs: Vec<String>,
+    /// Inputs that are part of both hashes but hashed to a different value
+    pub changed_inputs: Vec<String>,
+    pub added_files: Vec<String>,
+    pub removed_files: Vec<String>,
+    pub changed_files: Vec<String>,
+    /// Names of the environment variables whose inputs were added, removed or changed
+    pub changed_env_vars: Vec<String>,
+    /// Runtime commands whose inputs were added, removed or changed
+    pub changed_runtime_inputs: Vec<String>,
+}
+
+/// Compares two hash computations of a task (as returned by `TaskHasher.getHashDetails`)
+/// and reports which inputs, files, environment variables and runtime inputs caused the hashes to differ
+#[napi]
+pub fn diff_task_hashes(
+    old_plan: Vec<HashInputDetails>,
+    new_plan: Vec<HashInputDetails>,
+) -> TaskHashDiff {
+    let old_inputs = input_values(&old_plan);
+    let new_inputs = input_values(&new_plan);
+
+    let mut diff = TaskHashDiff::default();
+    let mut changed_inputs = BTreeSet::new();
+    for (input, value) in &new_inputs {
+        match old_inputs.get(input) {
+            None => {
+                diff.added_inputs.push(input.to_string());
+                changed_inputs.insert(*input);
+            }
+            Some(old_value) if old_value != value => {
+                diff.changed_inputs.push(input.to_string());
+                changed_inputs.insert(*input);
+            }
+            _ => {}
+        }
+    }
+    for input in old_inputs.keys() {
+        if !new_inputs.contains_key(input) {
+            diff.removed_inputs.push(input.to_string());
+            changed_inputs.insert(*input);
+        }
+    }
+
+    for input in changed_inputs {
+        if let Some(env_var) = input.strip_prefix("env:") {
+            diff.changed_env_vars.push(env_var.to_string());
+        } else if let Some(runtime) = input.strip_prefix("runtime:") {
+            diff.changed_runtime_inputs.push(runtime.to_string());
+        }
+    }
+
+    let old_files = file_hashes(&old_plan);
+    let new_files = file_hashes(&new_plan);
+    for (file, hash) in &new_files {
+        match old_files.get(file) {
+            None => diff.added_files.push(file.to_string()),
+            Some(old_hash) if old_hash != hash => diff.changed_files.push(file.to_string()),
+            _ => {}
+        }
+    }
+    diff.removed_files = old_files
+        .keys()
+        .filter(|file| !new_files.contains_key(*file))
+        .map(|file| file.to_string())
+        .collect();
+
+    diff
+}
+
+fn input_values(plan: &[HashInputDetails]) -> BTreeMap<&str, &str> {
+    plan.iter()
+        .map(|details| (details.input.as_str(), details.value.as_str()))
+        .collect()
+}
+
+fn file_hashes(plan: &[HashInputDetails]) -> BTreeMap<&str, &str> {
+    plan.iter()
+        .flat_map(|details| &details.files)
+        .map(|file| (file.file.as_str(), file.hash.as_str()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::native::types::FileData;
+
+    fn input(input: &str, value: &str, files: &[(&str, &str)]) -> HashInputDetails {
+        HashInputDetails {
+            input: input.into(),
+            value: value.into(),
+            globs: vec![],
+            files: files
+                .iter()
+                .map(|(file, hash)| FileData {
+                    file: file.to_string(),
+                    hash: hash.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn should_not_report_differences_for_identical_plans() {
+        let plan = || {
+            vec![
+                input("app:{projectRoot}/**/*", "1", &[("app/index.ts", "a")]),
+                input("env:CI", "2", &[]),
+            ]
+        };
+        assert_eq!(diff_task_hashes(plan(), plan()), TaskHashDiff::default());
+    }
+
+    #[test]
+    fn should_diff_files_and_inputs() {
+        let old_plan = vec![
+            input(
+                "app:{projectRoot}/**/*",
+                "1",
+                &[("app/index.ts", "a"), ("app/removed.ts", "b")],
+            ),
+            input("env:CI", "2", &[]),
+            input("runtime:node -v", "3", &[]),
+            input("npm:react", "4", &[]),
+        ];
+        let new_plan = vec![
+            input(
+                "app:{projectRoot}/**/*",
+                "5",
+                &[("app/index.ts", "c"), ("app/added.ts", "d")],
+            ),
+            input("env:CI", "6", &[]),
+            input("runtime:node -v", "3", &[]),
+            input("env:NX_CLOUD", "7", &[]),
+        ];
+
+        assert_eq!(
+            diff_task_hashes(old_plan, new_plan),
+            TaskHashDiff {
+                added_inputs: vec!["env:NX_CLOUD".into()],
+                removed_inputs: vec!["npm:react".into()],
+                changed_inputs: vec!["app:{projectRoot}/**/*".into(), "env:CI".into()],
+                added_files: vec!["app/added.ts".into()],
+                removed_files: vec!["app/removed.ts".into()],
+                changed_files: vec!["app/index.ts".into()],
+                changed_env_vars: vec!["CI".into(), "NX_CLOUD".into()],
+                changed_runtime_inputs: vec![],
+            }
+        );
+    }
+}
@@ -1,16 +1,18 @@
+use crate::native::glob::GlobOptions;
 use crate::native::logger::enable_logger;
 use crate::native::tasks::{
     dep_outputs::get_dep_output,
+    hashers::{HashError, HasherErrors},
     types::{HashInstruction, TaskGraph},
 };
 use crate::native::types::{Input, NxJson};
+use crate::native::utils::parallel::prelude::*;
 use crate::native::{
     project_graph::types::ProjectGraph,
     tasks::{inputs::SplitInputs, types::Task},
 };
 use napi::bindgen_prelude::External;
 use napi::{Env, JsExternal};
-use rayon::prelude::*;
 use std::collections::HashMap;
 use tracing::trace;
 
@@ -71,11 +73,14 @@ impl HashPlanner {
                 let mut inputs: Vec<HashInstruction> = target
                     .unwrap_or(vec![])
                     .into_iter()
-                    .chain(vec![HashInstruction::WorkspaceFileSet(vec![
-                        "{workspaceRoot}/nx.json".to_string(),
-                        "{workspaceRoot}/.gitignore".to_string(),
-                        "{workspaceRoot}/.nxignore".to_string(),
-                    ])])
+                    .chain(vec![HashInstruction::WorkspaceFileSet(
+                        vec![
+                            "{workspaceRoot}/nx.json".to_string(),
+                            "{workspaceRoot}/.gitignore".to_string(),
+                            "{workspaceRoot}/.nxignore".to_string(),
+                        ],
+                        GlobOptions::default(),
+                    )])
                     .chain(self_inputs)
                     .collect();
 
@@ -92,8 +97,9 @@ impl HashPlanner {
         &self,
         task_ids: Vec<&str>,
         task_graph: TaskGraph,
-    ) -> anyhow::Result<HashMap<String, Vec<HashInstruction>>> {
+    ) -> napi::Result<HashMap<String, Vec<HashInstruction>>, HasherErrors> {
         self.get_plans_internal(task_ids, task_graph)
+            .map_err(|e| HashError::from(e).into())
     }
 
     #[napi]
@@ -102,10 +108,12 @@ impl HashPlanner {
         env: Env,
         task_ids: Vec<&str>,
         task_graph: TaskGraph,
-    ) -> anyhow::Result<JsExternal> {
-        let plans = self.get_plans_internal(task_ids, task_graph)?;
+    ) -> napi::Result<JsExternal, HasherErrors> {
+        let plans = self
+            .get_plans_internal(task_ids, task_graph)
+            .map_err(HashError::from)?;
         env.create_external(plans, None)
-            .map_err(anyhow::Error::from)
+            .map_err(|e| HashError::Internal(e.into()).into())
     }
 
     fn target_input<'a>(
@@ -315,22 +323,24 @@ impl HashPlanner {
         project_name: &str,
         self_inputs: &[Input],
     ) -> Vec<HashInstruction> {
-        let (project_file_sets, workspace_file_sets): (Vec<&str>, Vec<&str>) = self_inputs
+        let (project_file_sets, workspace_file_sets): (Vec<_>, Vec<_>) = self_inputs
             .iter()
             .filter_map(|input| match input {
-                Input::FileSet(file_set) => Some(file_set),
+                Input::FileSet(file_set, options) => Some((*file_set, *options)),
                 _ => None,
             })
-            .partition(|file_set| {
+            .partition(|(file_set, _)| {
                 file_set.starts_with("{projectRoot}/") || file_set.starts_with("!{projectRoot}/")
             });
 
         let project_file_set_inputs = project_file_set_inputs(project_name, project_file_sets);
-        // let workspace_file_set_inputs = workspace_file_set_inputs(workspace_file_sets);
-        let workspace_file_set_inputs = match workspace_file_sets.is_empty() {
-            true => vec![],
-            false => vec![workspace_file_set_inputs(workspace_file_sets)],
-        };
+        let project_root = self
+            .project_graph
+            .nodes
+            .get(project_name)
+            .map(|project| project.root.as_str());
+        let workspace_file_set_inputs =
+            workspace_file_set_inputs(project_name, project_root, workspace_file_sets);
         let runtime_and_env_inputs = self_inputs.iter().filter_map(|i| match i {
             Input::Runtime(runtime) => Some(HashInstruction::Runtime(runtime.to_string())),
             Input::Environment(env) => Some(HashInstruction::Environment(env.to_string())),
@@ -423,17 +433,125 @@ fn find_external_dependency_node_name<'a>(
     }
 }
 
-fn project_file_set_inputs(project_name: &str, file_sets: Vec<&str>) -> Vec<HashInstruction> {
-    vec![
+fn project_file_set_inputs(
+    project_name: &str,
+    file_sets: Vec<(&str, GlobOptions)>,
+) -> Vec<HashInstruction> {
+    let (case_insensitive_file_sets, file_sets) = split_case_insensitive_file_sets(file_sets);
+    let case_insensitive_file_set_input = (!case_insensitive_file_sets.is_empty()).then(|| {
+        HashInstruction::ProjectFileSet(
+            project_name.to_string(),
+            case_insensitive_file_sets,
+            GlobOptions {
+                case_insensitive: true,
+            },
+        )
+    });
+    [
         HashInstruction::ProjectFileSet(
             project_name.to_string(),
-            file_sets.iter().map(|f| f.to_string()).collect(),
+            file_sets,
+            GlobOptions::default(),
         ),
         HashInstruction::ProjectConfiguration(project_name.to_string()),
         HashInstruction::TsConfiguration(project_name.to_string()),
     ]
+    .into_iter()
+    .chain(case_insensitive_file_set_input)
+    .collect()
+}
+
+fn workspace_file_set_inputs(
+    project_name: &str,
+    project_root: Option<&str>,
+    file_sets: Vec<(&str, GlobOptions)>,
+) -> Vec<HashInstruction> {
+    let (case_insensitive_file_sets, file_sets) = split_case_insensitive_file_sets(file_sets);
+    let interpolate = |file_sets: Vec<String>| {
+        file_sets
+            .iter()
+            .map(|f| interpolate_project_tokens(f, project_name, project_root))
+            .collect()
+    };
+    [(file_sets, false), (case_insensitive_file_sets, true)]
+        .into_iter()
+        .filter(|(file_sets, _)| !file_sets.is_empty())
+        .map(|(file_sets, case_insensitive)| {
+            HashInstruction::WorkspaceFileSet(
+                interpolate(file_sets),
+                GlobOptions { case_insensitive },
+            )
+        })
+        .collect()
 }
 
-fn workspace_file_set_inputs(file_sets: Vec<&str>) -> HashInstruction {
-    HashInstruction::WorkspaceFileSet(file_sets.iter().map(|f| f.to_string()).collect())
+/// Splits the file sets that are matched case insensitively from the others, since the globs of a file set
+/// instruction are matched with the same options
+fn split_case_insensitive_file_sets(
+    file_sets: Vec<(&str, GlobOptions)>,
+) -> (Vec<String>, Vec<String>) {
+    let (case_insensitive, case_sensitive): (Vec<_>, Vec<_>) = file_sets
+        .into_iter()
+        .partition(|(_, options)| options.case_insensitive);
+    let into_strings = |file_sets: Vec<(&str, GlobOptions)>| {
+        file_sets
+            .into_iter()
+            .map(|(file_set, _)| file_set.to_string())
+            .collect()
+    };
+    (into_strings(case_insensitive), into_strings(case_sensitive))
+}
+
+/// Resolves `{projectRoot}` and `{projectName}` in a workspace file set (e.g. `{workspaceRoot}/dist/{projectName}/**/*`)
+/// with the project that the inputs belong to, so shared named inputs can be reused across projects
+fn interpolate_project_tokens(
+    file_set: &str,
+    project_name: &str,
+    project_root: Option<&str>,
+) -> String {
+    let file_set = match project_root {
+        Some(".") => file_set
+            .replace("{projectRoot}/", "")
+            .replace("{projectRoot}", "."),
+        Some(project_root) => file_set.replace("{projectRoot}", project_root),
+        None => file_set.to_string(),
+    };
+    file_set.replace("{projectName}", project_name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_interpolate_project_tokens_in_workspace_file_sets() {
+        assert_eq!(
+            interpolate_project_tokens(
+                "{workspaceRoot}/dist/{projectName}/**/*",
+                "app",
+                Some("apps/app")
+            ),
+            "{workspaceRoot}/dist/app/**/*"
+        );
+        assert_eq!(
+            interpolate_project_tokens(
+                "!{workspaceRoot}/{projectRoot}/generated/**/*",
+                "app",
+                Some("apps/app")
+            ),
+            "!{workspaceRoot}/apps/app/generated/**/*"
+        );
+        assert_eq!(
+            interpolate_project_tokens(
+                "{workspaceRoot}/{projectRoot}/config.json",
+                "root",
+                Some(".")
+            ),
+            "{workspaceRoot}/config.json"
+        );
+        assert_eq!(
+            interpolate_project_tokens("{workspaceRoot}/package.json", "app", Some("apps/app")),
+            "{workspaceRoot}/package.json"
+        );
+    }
 }
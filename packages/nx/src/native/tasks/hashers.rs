@@ -1,5 +1,8 @@
+mod directory_tree;
+mod errors;
 mod hash_env;
 mod hash_external;
+mod hash_missing_files;
 mod hash_project_config;
 mod hash_project_files;
 mod hash_runtime;
@@ -7,8 +10,11 @@ mod hash_task_output;
 mod hash_workspace_files;
 mod hash_tsconfig;
 
+pub use directory_tree::*;
+pub use errors::*;
 pub use hash_env::*;
 pub use hash_external::*;
+pub use hash_missing_files::*;
 pub use hash_project_config::*;
 pub use hash_project_files::*;
 pub use hash_runtime::*;
@@ -0,0 +1,253 @@
+use std::collections::{BTreeSet, HashMap};
+
+use dashmap::DashMap;
+use tracing::trace;
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::native::tasks::hashers::{get_file_hash, MissingFileHasher};
+use crate::native::types::FileData;
+
+/// A Merkle tree of the directories of the workspace files: the hash of a directory is computed from the names
+/// and hashes of its files and subdirectories, so the hash of a directory only changes when a file inside it changes
+pub struct DirectoryTree {
+    directories: HashMap<String, Directory>,
+}
+
+#[derive(Default)]
+struct Directory {
+    /// The paths of the subdirectories, sorted
+    directories: BTreeSet<String>,
+    /// The files directly inside the directory, sorted by path
+    files: Vec<FileData>,
+}
+
+impl DirectoryTree {
+    pub fn new(files: &[FileData]) -> Self {
+        let mut directories: HashMap<String, Directory> = HashMap::new();
+        directories.entry(String::new()).or_default();
+        for file in files {
+            let mut directory = parent_directory(&file.file);
+            directories
+                .entry(directory.to_string())
+                .or_default()
+                .files
+                .push(file.clone());
+            // links the directory to its ancestors, up to the first one that was already linked
+            while !directory.is_empty() {
+                let parent = parent_directory(directory);
+                let is_new = directories
+                    .entry(parent.to_string())
+                    .or_default()
+                    .directories
+                    .insert(directory.to_string());
+                if !is_new {
+                    break;
+                }
+                directory = parent;
+            }
+        }
+        for directory in directories.values_mut() {
+            directory
+                .files
+                .sort_by(|a, b| a.file.cmp(&b.file).then_with(|| a.hash.cmp(&b.hash)));
+        }
+        trace!("built a tree of {} directories", directories.len());
+        Self { directories }
+    }
+
+    /// Hashes a directory with every file below it. `hashes` keeps the hashes of the directories between calls,
+    /// so only the directories that were invalidated since (see [invalidate_directory_hashes]) are hashed again.
+    /// Returns `None` when the directory does not contain any file
+    pub fn hash(
+        &self,
+        directory: &str,
+        hashes: &DashMap<String, String>,
+        missing_file_hasher: Option<&MissingFileHasher>,
+    ) -> Option<String> {
+        let entry = self.directories.get(directory)?;
+        if let Some(hash) = hashes.get(directory).map(|hash| hash.clone()) {
+            return Some(hash);
+        }
+
+        let mut hasher = Xxh3::new();
+        for subdirectory in &entry.directories {
+            let subdirectory_hash = self.hash(subdirectory, hashes, missing_file_hasher)?;
+            hasher.update(b"d\0");
+            hasher.update(file_name(subdirectory).as_bytes());
+            hasher.update(b"\0");
+            hasher.update(subdirectory_hash.as_bytes());
+            hasher.update(b"\0");
+        }
+        for file in &entry.files {
+            hasher.update(b"f\0");
+            hasher.update(file_name(&file.file).as_bytes());
+            hasher.update(b"\0");
+            hasher.update(get_file_hash(file, missing_file_hasher).as_bytes());
+            hasher.update(b"\0");
+        }
+        let hash = hasher.digest().to_string();
+
+        hashes.insert(directory.to_string(), hash.clone());
+        Some(hash)
+    }
+
+    /// The hash of a file of the tree. Returns `None` when the tree does not contain the file
+    pub fn file_hash(
+        &self,
+        path: &str,
+        missing_file_hasher: Option<&MissingFileHasher>,
+    ) -> Option<String> {
+        let files = &self.directories.get(parent_directory(path))?.files;
+        let index = files
+            .binary_search_by(|file| file.file.as_str().cmp(path))
+            .ok()?;
+        Some(get_file_hash(&files[index], missing_file_hasher).into_owned())
+    }
+}
+
+/// Removes the hashes of the changed paths and of every directory containing them.
+/// Returns the number of invalidated directories
+pub fn invalidate_directory_hashes<S: AsRef<str>>(
+    hashes: &DashMap<String, String>,
+    changed_files: &[S],
+) -> usize {
+    let mut invalidated = 0;
+    for changed_file in changed_files {
+        // a changed path can also be a directory that was removed
+        let mut path = changed_file.as_ref();
+        if hashes.remove(path).is_some() {
+            invalidated += 1;
+        }
+        while !path.is_empty() {
+            path = parent_directory(path);
+            if hashes.remove(path).is_some() {
+                invalidated += 1;
+            }
+        }
+    }
+    invalidated
+}
+
+/// The directory of a glob that matches every file below it: `docs` for `docs/**/*`, and the workspace root
+/// (an empty path) for `**/*`. Returns `None` for any other glob
+pub fn glob_directory(glob: &str) -> Option<&str> {
+    if glob == "**/*" {
+        return Some("");
+    }
+    let directory = glob.strip_suffix("/**/*")?;
+    let is_literal = directory
+        .split('/')
+        .all(|segment| !matches!(segment, "" | "." | ".."))
+        && !directory.contains(['*', '?', '[', ']', '{', '}', '(', ')', '!', '\\']);
+    is_literal.then_some(directory)
+}
+
+/// The leading directories of a glob before its first pattern, which contain every file the glob can match,
+/// and whether the glob has no pattern at all (e.g. the path of a file): `docs` for `docs/*.md`, `nx.json` for `nx.json`
+pub fn glob_base(glob: &str) -> (&str, bool) {
+    let glob = glob.strip_prefix('!').unwrap_or(glob);
+    match glob.find(['*', '?', '[', ']', '{', '}', '(', ')', '!', '\\']) {
+        Some(index) => (parent_directory(&glob[..index + 1]), false),
+        None => (glob.trim_end_matches('/'), true),
+    }
+}
+
+fn parent_directory(path: &str) -> &str {
+    path.rfind('/').map_or("", |index| &path[..index])
+}
+
+fn file_name(path: &str) -> &str {
+    path.rfind('/').map_or(path, |index| &path[index + 1..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(file: &str, hash: &str) -> FileData {
+        FileData {
+            file: file.into(),
+            hash: hash.into(),
+        }
+    }
+
+    #[test]
+    fn should_only_rehash_the_directories_of_changed_files() {
+        let files = vec![
+            file("docs/guides/intro.md", "1"),
+            file("docs/reference/api.md", "2"),
+            file("package.json", "3"),
+        ];
+        let hashes = DashMap::new();
+        let tree = DirectoryTree::new(&files);
+        let docs_hash = tree.hash("docs", &hashes, None).unwrap();
+        let root_hash = tree.hash("", &hashes, None).unwrap();
+        assert_eq!(hashes.len(), 4);
+        assert!(tree.hash("packages", &hashes, None).is_none());
+
+        let changed_files = vec![file("docs/guides/intro.md", "4"), files[1].clone()];
+        assert_eq!(
+            invalidate_directory_hashes(&hashes, &["docs/guides/intro.md"]),
+            3
+        );
+        assert!(hashes.contains_key("docs/reference"));
+
+        let tree = DirectoryTree::new(&changed_files);
+        assert_ne!(tree.hash("docs", &hashes, None).unwrap(), docs_hash);
+        assert_ne!(tree.hash("", &hashes, None).unwrap(), root_hash);
+    }
+
+    #[test]
+    fn should_hash_directories_by_their_contents() {
+        let hashes = DashMap::new();
+        let hash = |files: &[FileData], directory: &str| {
+            hashes.clear();
+            DirectoryTree::new(files).hash(directory, &hashes, None)
+        };
+
+        // the hash of a directory does not depend on where it is
+        assert_eq!(
+            hash(&[file("docs/a.md", "1")], "docs"),
+            hash(&[file("website/docs/a.md", "1")], "website/docs")
+        );
+        // nor on the order of the workspace files
+        assert_eq!(
+            hash(&[file("docs/a.md", "1"), file("docs/b/c.md", "2")], "docs"),
+            hash(&[file("docs/b/c.md", "2"), file("docs/a.md", "1")], "docs")
+        );
+        // a renamed file changes the hash
+        assert_ne!(
+            hash(&[file("docs/a.md", "1")], "docs"),
+            hash(&[file("docs/b.md", "1")], "docs")
+        );
+    }
+
+    #[test]
+    fn should_find_the_directory_of_globs_matching_every_file() {
+        assert_eq!(glob_directory("**/*"), Some(""));
+        assert_eq!(glob_directory("docs/**/*"), Some("docs"));
+        assert_eq!(glob_directory("docs/shared/**/*"), Some("docs/shared"));
+        assert_eq!(glob_directory("docs/*/**/*"), None);
+        assert_eq!(glob_directory("docs/{a,b}/**/*"), None);
+        assert_eq!(glob_directory("docs/**/*.md"), None);
+        assert_eq!(glob_directory("../docs/**/*"), None);
+    }
+
+    #[test]
+    fn should_find_the_base_of_globs() {
+        assert_eq!(glob_base("**/*"), ("", false));
+        assert_eq!(glob_base("docs/*.md"), ("docs", false));
+        assert_eq!(glob_base("docs/shared/{a,b}/*.md"), ("docs/shared", false));
+        assert_eq!(glob_base("!docs/cache/**"), ("docs/cache", false));
+        assert_eq!(glob_base("nx.json"), ("nx.json", true));
+        assert_eq!(glob_base("docs/shared/"), ("docs/shared", true));
+    }
+
+    #[test]
+    fn should_find_the_hashes_of_files() {
+        let tree = DirectoryTree::new(&[file("docs/a.md", "1"), file("docs/b.md", "2")]);
+        assert_eq!(tree.file_hash("docs/b.md", None), Some("2".into()));
+        assert_eq!(tree.file_hash("docs/c.md", None), None);
+        assert_eq!(tree.file_hash("docs", None), None);
+    }
+}
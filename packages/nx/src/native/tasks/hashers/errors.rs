@@ -0,0 +1,111 @@
+use napi::bindgen_prelude::*;
+use thiserror::Error;
+
+/// Public NAPI error codes of the task hasher that are for Node.
+/// `InvalidInput` is a mistake in the inputs of the workspace (e.g. a glob that does not parse),
+/// the other codes are failures of the hasher
+#[napi(string_enum)]
+#[derive(Debug)]
+pub enum HasherErrors {
+    InvalidInput,
+    Io,
+    Internal,
+}
+
+impl AsRef<str> for HasherErrors {
+    fn as_ref(&self) -> &str {
+        match self {
+            HasherErrors::InvalidInput => "InvalidInput",
+            HasherErrors::Io => "Io",
+            HasherErrors::Internal => "Internal",
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum HashError {
+    #[error("Unable to parse the globs {globs:?}: {source}")]
+    InvalidGlob {
+        globs: Vec<String>,
+        source: anyhow::Error,
+    },
+    #[error(
+        r#""{0}" is an invalid fileset.
+All filesets have to start with either {{workspaceRoot}} or {{projectRoot}}.
+For instance: "!{{projectRoot}}/**/*.spec.ts" or "{{workspaceRoot}}/package.json".
+If "{0}" is a named input, make sure it is defined in nx.json.
+"#
+    )]
+    MissingFileSetToken(String),
+    #[error("Could not find project '{0}'")]
+    ProjectNotFound(String),
+    #[error("Could not find external {0}")]
+    ExternalNotFound(String),
+    #[error("{context}: {source}")]
+    Io {
+        context: String,
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    Internal(anyhow::Error),
+}
+
+impl HashError {
+    pub fn invalid_glob<S: AsRef<str>>(globs: &[S], source: anyhow::Error) -> Self {
+        HashError::InvalidGlob {
+            globs: globs.iter().map(|glob| glob.as_ref().to_string()).collect(),
+            source,
+        }
+    }
+
+    pub fn code(&self) -> HasherErrors {
+        match self {
+            HashError::InvalidGlob { .. }
+            | HashError::MissingFileSetToken(_)
+            | HashError::ProjectNotFound(_)
+            | HashError::ExternalNotFound(_) => HasherErrors::InvalidInput,
+            HashError::Io { .. } => HasherErrors::Io,
+            HashError::Internal(_) => HasherErrors::Internal,
+        }
+    }
+}
+
+/// Errors that went through `anyhow` (e.g. from the hash planner) keep their kind when they are a `HashError`
+impl From<anyhow::Error> for HashError {
+    fn from(error: anyhow::Error) -> Self {
+        match error.downcast::<HashError>() {
+            Ok(error) => error,
+            Err(error) => HashError::Internal(error),
+        }
+    }
+}
+
+impl From<HashError> for napi::Error<HasherErrors> {
+    fn from(value: HashError) -> Self {
+        Error::new(value.code(), value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_keep_the_kind_of_errors_that_went_through_anyhow() {
+        let error: anyhow::Error = HashError::ProjectNotFound("app".into()).into();
+        let error = HashError::from(error.context("while hashing app:build"));
+        assert!(matches!(error, HashError::ProjectNotFound(project) if project == "app"));
+
+        let error: anyhow::Error = HashError::MissingFileSetToken("src/**/*".into()).into();
+        let error = HashError::from(error);
+        assert!(matches!(error.code(), HasherErrors::InvalidInput));
+        assert!(error
+            .to_string()
+            .starts_with(r#""src/**/*" is an invalid fileset."#));
+
+        let error = HashError::from(anyhow::anyhow!("something broke"));
+        assert!(matches!(error.code(), HasherErrors::Internal));
+        assert_eq!(error.to_string(), "something broke");
+    }
+}
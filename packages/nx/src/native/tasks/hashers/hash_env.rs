@@ -1,11 +1,38 @@
+use crate::native::glob::{build_glob_set, contains_glob_pattern};
 use crate::native::hasher::hash;
+use crate::native::tasks::hashers::HashError;
 use std::collections::HashMap;
 
-pub fn hash_env(env_name: &str, env: &HashMap<String, String>) -> anyhow::Result<String> {
+/// Hashes the value of an environment variable.
+/// Env inputs with glob patterns (e.g. `NX_*`) hash the names and values of every matching variable, sorted by name
+pub fn hash_env(env_name: &str, env: &HashMap<String, String>) -> Result<String, HashError> {
+    if contains_glob_pattern(env_name) {
+        return hash_env_glob(env_name, env);
+    }
+
     let env_value = env.get(env_name).map(|s| s.as_str()).unwrap_or("");
     Ok(hash(env_value.as_bytes()))
 }
 
+fn hash_env_glob(env_glob: &str, env: &HashMap<String, String>) -> Result<String, HashError> {
+    let matcher =
+        build_glob_set(&[env_glob]).map_err(|e| HashError::invalid_glob(&[env_glob], e))?;
+    let mut matching_env = env
+        .iter()
+        .filter(|(name, _)| matcher.is_match(name.as_str()))
+        .collect::<Vec<_>>();
+    matching_env.sort();
+
+    let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+    for (name, value) in matching_env {
+        hasher.update(name.as_bytes());
+        hasher.update(b"=");
+        hasher.update(value.as_bytes());
+        hasher.update(b"\0");
+    }
+    Ok(hasher.digest().to_string())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -27,4 +54,28 @@ mod test {
 
         assert_eq!(hash, "3244421341483603138");
     }
+
+    #[test]
+    fn should_hash_env_globs_in_a_stable_order() {
+        let env = HashMap::from([
+            ("NX_A".to_string(), "a".to_string()),
+            ("NX_B".to_string(), "b".to_string()),
+            ("VITE_A".to_string(), "c".to_string()),
+        ]);
+        let reordered_env = HashMap::from([
+            ("NX_B".to_string(), "b".to_string()),
+            ("OTHER".to_string(), "d".to_string()),
+            ("NX_A".to_string(), "a".to_string()),
+        ]);
+
+        let hash = hash_env("NX_*", &env).unwrap();
+        assert_eq!(hash, hash_env("NX_*", &reordered_env).unwrap());
+        assert_ne!(hash, hash_env("VITE_*", &env).unwrap());
+
+        let changed_env = HashMap::from([
+            ("NX_A".to_string(), "a".to_string()),
+            ("NX_B".to_string(), "changed".to_string()),
+        ]);
+        assert_ne!(hash, hash_env("NX_*", &changed_env).unwrap());
+    }
 }
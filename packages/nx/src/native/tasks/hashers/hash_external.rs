@@ -1,25 +1,35 @@
 use crate::native::hasher::{hash, hash_array};
+use crate::native::lock_file::LockFile;
 use crate::native::project_graph::types::ExternalNode;
+use crate::native::tasks::hashers::HashError;
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use anyhow::*;
 use dashmap::DashMap;
 
 pub fn hash_external(
     external_name: &str,
     externals: &HashMap<String, ExternalNode>,
     cache: Arc<DashMap<String, String>>,
-) -> Result<String> {
+    lock_file: Option<&LockFile>,
+) -> Result<String, HashError> {
     let external = externals
         .get(external_name)
-        .ok_or_else(|| anyhow!("Could not find external {}", external_name))?;
+        .ok_or_else(|| HashError::ExternalNotFound(external_name.to_string()))?;
 
     if let Some(cached_hash) = cache.get(external_name) {
         return Ok(cached_hash.clone());
     }
 
-    let hash = if let Some(external_hash) = &external.hash {
+    // the lock file includes the transitive dependencies of the external in its hash
+    let lock_file_hash = lock_file.and_then(|lock_file| {
+        let package_name = external.package_name.as_deref()?;
+        lock_file.hash_package(package_name, Some(&external.version))
+    });
+
+    let hash = if let Some(lock_file_hash) = lock_file_hash {
+        lock_file_hash
+    } else if let Some(external_hash) = &external.hash {
         hash(external_hash.as_bytes())
     } else {
         hash(external.version.as_bytes())
@@ -34,11 +44,12 @@ pub fn hash_all_externals<S: AsRef<str>>(
     sorted_externals: &[S],
     externals: &HashMap<String, ExternalNode>,
     cache: Arc<DashMap<String, String>>,
-) -> Result<String> {
+    lock_file: Option<&LockFile>,
+) -> Result<String, HashError> {
     let hashes = sorted_externals
         .iter()
-        .map(|name| hash_external(name.as_ref(), externals, Arc::clone(&cache)))
-        .collect::<Result<Vec<_>>>()?;
+        .map(|name| hash_external(name.as_ref(), externals, Arc::clone(&cache), lock_file))
+        .collect::<Result<Vec<_>, HashError>>()?;
     Ok(hash_array(hashes))
 }
 
@@ -74,11 +85,15 @@ mod test {
         let external_nodes = get_external_nodes_map();
         let cache: Arc<DashMap<String, String>> = Arc::new(DashMap::new());
         let no_external_node_hash =
-            hash_external("my_external", &external_nodes, Arc::clone(&cache));
+            hash_external("my_external", &external_nodes, Arc::clone(&cache), None);
         assert_eq!(no_external_node_hash.unwrap(), "3342527690135000204");
 
-        let external_node_hash =
-            hash_external("my_external_with_hash", &external_nodes, Arc::clone(&cache));
+        let external_node_hash = hash_external(
+            "my_external_with_hash",
+            &external_nodes,
+            Arc::clone(&cache),
+            None,
+        );
         assert_eq!(external_node_hash.unwrap(), "4204073044699973956");
     }
 
@@ -90,7 +105,42 @@ mod test {
             &["my_external", "my_external_with_hash"],
             &external_nodes,
             Arc::clone(&cache),
+            None,
         );
         assert_eq!(all_externals.unwrap(), "9354284926255893100");
     }
+
+    #[test]
+    fn should_hash_externals_from_the_lock_file() {
+        use crate::native::lock_file::parse_yarn_lock;
+
+        let external_nodes = get_external_nodes_map();
+        let lock_file = parse_yarn_lock(
+            r#"my_external@^0.0.1:
+  version "0.0.1"
+  integrity sha512-a
+"#,
+        );
+        let hash = hash_external(
+            "my_external",
+            &external_nodes,
+            Arc::new(DashMap::new()),
+            Some(&lock_file),
+        )
+        .unwrap();
+        assert_eq!(
+            Some(hash),
+            lock_file.hash_package("my_external", Some("0.0.1"))
+        );
+
+        // externals that are not in the lock file fall back to their version
+        let hash = hash_external(
+            "my_external_with_hash",
+            &external_nodes,
+            Arc::new(DashMap::new()),
+            Some(&lock_file),
+        )
+        .unwrap();
+        assert_eq!(hash, "4204073044699973956");
+    }
 }
@@ -0,0 +1,106 @@
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use tracing::trace;
+
+use crate::native::hasher::hash_file_path_streamed;
+use crate::native::types::FileData;
+
+/// Fills in hashes for files that were collected without one (e.g. untracked files in detached worktrees)
+/// by hashing their contents from disk. Results are cached by file path.
+pub struct MissingFileHasher {
+    workspace_root: PathBuf,
+    cache: Arc<DashMap<String, String>>,
+}
+
+impl MissingFileHasher {
+    pub fn new<P: AsRef<Path>>(workspace_root: P, cache: Arc<DashMap<String, String>>) -> Self {
+        Self {
+            workspace_root: workspace_root.as_ref().to_path_buf(),
+            cache,
+        }
+    }
+
+    pub fn hash_file<'a>(&self, file: &'a FileData) -> Cow<'a, str> {
+        if let Some(cached_hash) = self.cache.get(&file.file) {
+            return Cow::Owned(cached_hash.clone());
+        }
+
+        trace!("{:?} has no hash, hashing contents from disk", file.file);
+        let Some(hash) = hash_file_path_streamed(self.workspace_root.join(&file.file)) else {
+            return Cow::Borrowed(&file.hash);
+        };
+
+        self.cache.insert(file.file.clone(), hash.clone());
+        Cow::Owned(hash)
+    }
+}
+
+/// Returns the hash of the file, falling back to hashing its contents when the hash is missing and a `MissingFileHasher` is provided
+pub fn get_file_hash<'a>(
+    file: &'a FileData,
+    missing_file_hasher: Option<&MissingFileHasher>,
+) -> Cow<'a, str> {
+    match missing_file_hasher {
+        Some(missing_file_hasher) if file.hash.is_empty() => missing_file_hasher.hash_file(file),
+        _ => Cow::Borrowed(&file.hash),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::native::hasher::hash;
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    #[test]
+    fn should_hash_files_with_missing_hashes_from_disk() {
+        let temp = TempDir::new().unwrap();
+        temp.child("untracked.txt").write_str("content").unwrap();
+
+        let cache = Arc::new(DashMap::new());
+        let missing_file_hasher = MissingFileHasher::new(temp.path(), Arc::clone(&cache));
+
+        let untracked_file = FileData {
+            file: "untracked.txt".into(),
+            hash: "".into(),
+        };
+        let tracked_file = FileData {
+            file: "tracked.txt".into(),
+            hash: "123".into(),
+        };
+
+        assert_eq!(
+            get_file_hash(&untracked_file, Some(&missing_file_hasher)),
+            hash(b"content")
+        );
+        assert_eq!(
+            get_file_hash(&tracked_file, Some(&missing_file_hasher)),
+            "123"
+        );
+        assert_eq!(get_file_hash(&untracked_file, None), "");
+        assert_eq!(
+            cache.get("untracked.txt").map(|h| h.clone()),
+            Some(hash(b"content"))
+        );
+    }
+
+    #[test]
+    fn should_keep_empty_hash_for_unreadable_files() {
+        let temp = TempDir::new().unwrap();
+        let missing_file_hasher = MissingFileHasher::new(temp.path(), Arc::new(DashMap::new()));
+
+        let deleted_file = FileData {
+            file: "deleted.txt".into(),
+            hash: "".into(),
+        };
+
+        assert_eq!(
+            get_file_hash(&deleted_file, Some(&missing_file_hasher)),
+            ""
+        );
+    }
+}
@@ -1,19 +1,19 @@
 use std::collections::HashMap;
 
-use anyhow::*;
 use itertools::Itertools;
 
 use crate::native::hasher::hash;
 use crate::native::project_graph::types::Project;
+use crate::native::tasks::hashers::HashError;
 use crate::native::types::Input;
 
 pub fn hash_project_config(
     project_name: &str,
     projects: &HashMap<String, Project>,
-) -> Result<String> {
+) -> Result<String, HashError> {
     let project = projects
         .get(project_name)
-        .ok_or_else(|| anyhow!("Could not find project '{}'", project_name))?;
+        .ok_or_else(|| HashError::ProjectNotFound(project_name.to_string()))?;
     let targets = project
         .targets
         .iter()
@@ -1,23 +1,39 @@
 use std::collections::HashMap;
 
-use anyhow::*;
 use tracing::{trace, trace_span};
 
-use crate::native::glob::build_glob_set;
+use crate::native::glob::{build_glob_matcher, GlobOptions};
+use crate::native::tasks::hashers::{get_file_hash, HashError, MissingFileHasher};
 use crate::native::types::FileData;
+use crate::native::utils::parallel::prelude::*;
 
 pub fn hash_project_files(
     project_name: &str,
     project_root: &str,
     file_sets: &[String],
     project_file_map: &HashMap<String, Vec<FileData>>,
-) -> Result<String> {
+    missing_file_hasher: Option<&MissingFileHasher>,
+    ordered_negated_globs: bool,
+    options: GlobOptions,
+) -> Result<String, HashError> {
     let _span = trace_span!("hash_project_files", project_name).entered();
-    let collected_files = collect_files(project_name, project_root, file_sets, project_file_map)?;
+    let collected_files = collect_files(
+        project_name,
+        project_root,
+        file_sets,
+        project_file_map,
+        ordered_negated_globs,
+        options,
+    )?;
     trace!("collected_files: {:?}", collected_files.len());
+    // missing hashes are filled in parallel, then every file is fed to the hasher in its original order
+    let file_hashes = collected_files
+        .par_iter()
+        .map(|file| get_file_hash(file, missing_file_hasher))
+        .collect::<Vec<_>>();
     let mut hasher = xxhash_rust::xxh3::Xxh3::new();
-    for file in collected_files {
-        hasher.update(file.hash.as_bytes());
+    for (file, file_hash) in collected_files.iter().zip(&file_hashes) {
+        hasher.update(file_hash.as_bytes());
         hasher.update(file.file.as_bytes());
     }
     Ok(hasher.digest().to_string())
@@ -29,8 +45,22 @@ fn collect_files<'a>(
     project_root: &str,
     file_sets: &[String],
     project_file_map: &'a HashMap<String, Vec<FileData>>,
-) -> Result<Vec<&'a FileData>> {
-    let globs = file_sets
+    ordered_negated_globs: bool,
+    options: GlobOptions,
+) -> Result<Vec<&'a FileData>, HashError> {
+    let globs = project_file_set_globs(project_root, file_sets);
+    collect_project_files(
+        project_name,
+        &globs,
+        project_file_map,
+        ordered_negated_globs,
+        options,
+    )
+}
+
+/// Resolves `{projectRoot}` in the project file sets to get the globs that are matched against the project files
+pub fn project_file_set_globs(project_root: &str, file_sets: &[String]) -> Vec<String> {
+    file_sets
         .iter()
         .map(|f| {
             if project_root == "." {
@@ -39,18 +69,29 @@ fn collect_files<'a>(
                 f.replace("{projectRoot}", project_root)
             }
         })
-        .collect::<Vec<_>>();
+        .collect()
+}
+
+/// Collects the files of a project that match the already resolved globs, in their original order
+pub fn collect_project_files<'a>(
+    project_name: &str,
+    globs: &[String],
+    project_file_map: &'a HashMap<String, Vec<FileData>>,
+    ordered_negated_globs: bool,
+    options: GlobOptions,
+) -> Result<Vec<&'a FileData>, HashError> {
     let now = std::time::Instant::now();
-    let glob_set = build_glob_set(&globs)?;
-    trace!("build_glob_set for {:?}", now.elapsed());
+    let glob_set = build_glob_matcher(globs, ordered_negated_globs, options)
+        .map_err(|e| HashError::invalid_glob(globs, e))?;
+    trace!("build_glob_matcher for {:?}", now.elapsed());
 
     project_file_map.get(project_name).map_or_else(
-        || Err(anyhow!("project {} not found", project_name)),
+        || Err(HashError::ProjectNotFound(project_name.to_string())),
         |files| {
             trace!("files: {:?}", files.len());
             let now = std::time::Instant::now();
             let hashes = files
-                .iter()
+                .par_iter()
                 .filter(|file| glob_set.is_match(&file.file))
                 .collect::<Vec<_>>();
             trace!("hash_files for {}: {:?}", project_name, now.elapsed());
@@ -58,6 +99,7 @@ fn collect_files<'a>(
         },
     )
 }
+
 #[cfg(test)]
 mod tests {
     use crate::native::hasher::hash;
@@ -100,7 +142,15 @@ mod tests {
             ],
         );
 
-        let result = collect_files(proj_name, proj_root, file_sets, &file_map).unwrap();
+        let result = collect_files(
+            proj_name,
+            proj_root,
+            file_sets,
+            &file_map,
+            false,
+            GlobOptions::default(),
+        )
+        .unwrap();
 
         assert_eq!(result, vec![&tsfile_1, &tsfile_2]);
 
@@ -109,6 +159,8 @@ mod tests {
             proj_root,
             &["!{projectRoot}/**/*.spec.ts".into()],
             &file_map,
+            false,
+            GlobOptions::default(),
         )
         .unwrap();
         assert_eq!(
@@ -119,6 +171,32 @@ mod tests {
                 /* testfile_2 is included because it ends with spectsx.snap */ &testfile_2
             ]
         );
+
+        // with ordered negated globs, the later glob includes the test files again
+        let result = collect_files(
+            proj_name,
+            proj_root,
+            file_sets,
+            &file_map,
+            true,
+            GlobOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(result, vec![&tsfile_1, &testfile_1, &tsfile_2, &testfile_2]);
+
+        let result = collect_files(
+            proj_name,
+            proj_root,
+            &[
+                "{projectRoot}/**/*".into(),
+                "!{projectRoot}/**/?(*.)+(spec|test).[jt]s?(x)?(.snap)".into(),
+            ],
+            &file_map,
+            true,
+            GlobOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(result, vec![&tsfile_1, &tsfile_2]);
     }
 
     #[test]
@@ -155,15 +233,27 @@ mod tests {
                 file_data4.clone(),
             ],
         );
-        let hash_result = hash_project_files(proj_name, proj_root, file_sets, &file_map).unwrap();
+        let hash_result = hash_project_files(
+            proj_name,
+            proj_root,
+            file_sets,
+            &file_map,
+            None,
+            false,
+            GlobOptions::default(),
+        )
+        .unwrap();
         assert_eq!(
             hash_result,
-            hash(&[
-                file_data1.hash.as_bytes(),
-                file_data1.file.as_bytes(),
-                file_data3.hash.as_bytes(),
-                file_data3.file.as_bytes()
-            ].concat())
+            hash(
+                &[
+                    file_data1.hash.as_bytes(),
+                    file_data1.file.as_bytes(),
+                    file_data3.hash.as_bytes(),
+                    file_data3.file.as_bytes()
+                ]
+                .concat()
+            )
         );
     }
 
@@ -202,15 +292,27 @@ mod tests {
                 file_data4.clone(),
             ],
         );
-        let hash_result = hash_project_files(proj_name, proj_root, file_sets, &file_map).unwrap();
+        let hash_result = hash_project_files(
+            proj_name,
+            proj_root,
+            file_sets,
+            &file_map,
+            None,
+            false,
+            GlobOptions::default(),
+        )
+        .unwrap();
         assert_eq!(
             hash_result,
-            hash(&[
-                file_data1.hash.as_bytes(),
-                file_data1.file.as_bytes(),
-                file_data3.hash.as_bytes(),
-                file_data3.file.as_bytes(),
-            ].concat())
+            hash(
+                &[
+                    file_data1.hash.as_bytes(),
+                    file_data1.file.as_bytes(),
+                    file_data3.hash.as_bytes(),
+                    file_data3.file.as_bytes(),
+                ]
+                .concat()
+            )
         );
     }
 }
@@ -1,8 +1,12 @@
 use crate::native::hasher::hash;
 use dashmap::DashMap;
+use once_cell::sync::Lazy;
 use std::collections::HashMap;
-use std::process::Command;
+use std::io::Read;
+use std::process::{Command, Stdio};
 use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 use tracing::trace;
 
 #[cfg(target_os = "windows")]
@@ -12,11 +16,22 @@ use std::os::windows::process::CommandExt;
 #[cfg(target_os = "windows")]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Runtime inputs (e.g. `node -v`) rarely change while the process is alive,
+/// so their results are shared by every task hasher created by this process (e.g. the daemon)
+static RUNTIME_CACHE: Lazy<Arc<DashMap<String, String>>> = Lazy::new(Default::default);
+
+pub fn runtime_cache() -> Arc<DashMap<String, String>> {
+    Arc::clone(&RUNTIME_CACHE)
+}
+
 pub fn hash_runtime(
     workspace_root: &str,
     command: &str,
     env: &HashMap<String, String>,
     cache: Arc<DashMap<String, String>>,
+    timeout: Option<Duration>,
 ) -> anyhow::Result<String> {
     let cache_key = format!("{}-{:?}", command, env);
 
@@ -32,14 +47,47 @@ pub fn hash_runtime(
     env.iter().for_each(|(key, value)| {
         command_builder.env(key, value);
     });
+    command_builder
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
     trace!("executing: {:?}", command_builder);
-    let output = command_builder
-        .output()
+    let mut child = command_builder
+        .spawn()
         .map_err(|e| anyhow::anyhow!("Failed to execute: '{}'\n{}", command, e))?;
-    trace!("{} output: {:?}", command, output);
 
-    let std_out = std::str::from_utf8(&output.stdout)?.trim();
-    let std_err = std::str::from_utf8(&output.stderr)?.trim();
+    // the output is read while waiting, so the command can't block on a full pipe
+    let std_out = read_in_background(child.stdout.take());
+    let std_err = read_in_background(child.stderr.take());
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+            child.kill().ok();
+            child.wait().ok();
+            anyhow::bail!(
+                "Runtime input '{}' did not finish within {:?}",
+                command,
+                start.elapsed()
+            );
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let std_out = std_out.join().unwrap_or_default();
+    let std_err = std_err.join().unwrap_or_default();
+    trace!(
+        "{} exited with {:?} after {:?}",
+        command,
+        status,
+        start.elapsed()
+    );
+
+    let std_out = std::str::from_utf8(&std_out)?.trim();
+    let std_err = std::str::from_utf8(&std_err)?.trim();
     let hash_result = hash(&[std_out.as_bytes(), std_err.as_bytes()].concat());
 
     cache.insert(cache_key, hash_result.clone());
@@ -47,6 +95,16 @@ pub fn hash_runtime(
     Ok(hash_result)
 }
 
+fn read_in_background<R: Read + Send + 'static>(reader: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut output = vec![];
+        if let Some(mut reader) = reader {
+            reader.read_to_end(&mut output).ok();
+        }
+        output
+    })
+}
+
 #[cfg(target_os = "windows")]
 pub fn create_command_builder() -> Command {
     let comspec = std::env::var("COMSPEC");
@@ -81,7 +139,35 @@ mod tests {
         let env: HashMap<String, String> = HashMap::new();
         let cache = Arc::new(DashMap::new());
 
-        let result = hash_runtime(workspace_root, command, &env, Arc::clone(&cache)).unwrap();
+        let result = hash_runtime(workspace_root, command, &env, Arc::clone(&cache), None).unwrap();
+        assert_eq!(result, "10571312846059850300");
+    }
+
+    #[test]
+    fn should_trim_and_cache_runtime_output() {
+        let env: HashMap<String, String> = HashMap::new();
+        let cache = Arc::new(DashMap::new());
+
+        let result =
+            hash_runtime("/tmp", "echo '  runtime  '", &env, Arc::clone(&cache), None).unwrap();
         assert_eq!(result, "10571312846059850300");
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn should_fail_when_runtime_command_times_out() {
+        let env: HashMap<String, String> = HashMap::new();
+        let cache = Arc::new(DashMap::new());
+
+        let result = hash_runtime(
+            "/tmp",
+            "sleep 5",
+            &env,
+            Arc::clone(&cache),
+            Some(Duration::from_millis(100)),
+        );
+        assert!(result.is_err());
+        assert!(cache.is_empty());
     }
 }
@@ -1,18 +1,185 @@
+use std::borrow::Cow;
 use std::sync::Arc;
 
-use anyhow::*;
 use dashmap::DashMap;
+use parking_lot::RwLock;
 use tracing::{trace, warn};
 
+use crate::native::glob::{build_glob_matcher, GlobOptions, NxGlobMatcher};
+use crate::native::hasher::hash;
+use crate::native::tasks::hashers::{
+    get_file_hash, glob_base, glob_directory, invalidate_directory_hashes, DirectoryTree,
+    HashError, MissingFileHasher,
+};
 use crate::native::types::FileData;
-use crate::native::{glob::build_glob_set, hasher::hash};
+use crate::native::utils::parallel::prelude::*;
 
-pub fn hash_workspace_files(
-    workspace_file_sets: &[String],
-    all_workspace_files: &[FileData],
-    cache: Arc<DashMap<String, String>>,
-) -> Result<String> {
-    let globs: Vec<String> = workspace_file_sets
+/// Caches the hashes of workspace file sets along with the globs that produced them,
+/// so that only the file sets containing changed files are invalidated.
+/// Globs matching every file of a directory are hashed with the directory tree of the workspace files,
+/// which keeps the hashes of the unchanged directories when a file set is invalidated
+#[derive(Default)]
+pub struct WorkspaceFilesCache {
+    file_sets: DashMap<String, CachedFileSet>,
+    directory_tree: RwLock<Option<Arc<DirectoryTree>>>,
+    directory_hashes: DashMap<String, String>,
+}
+
+struct CachedFileSet {
+    hash: String,
+    globs: Vec<String>,
+    options: GlobOptions,
+    glob_set: NxGlobMatcher,
+}
+
+/// The key of the cached hash of a file set. The same globs match other files when they are case insensitive
+pub fn file_set_cache_key(globs: &[String], options: GlobOptions) -> String {
+    let globs = globs.join(",");
+    if options.case_insensitive {
+        format!("caseInsensitive:{}", globs)
+    } else {
+        globs
+    }
+}
+
+impl WorkspaceFilesCache {
+    pub fn get(&self, cache_key: &str) -> Option<String> {
+        self.file_sets
+            .get(cache_key)
+            .map(|file_set| file_set.hash.clone())
+    }
+
+    fn insert(
+        &self,
+        globs: &[String],
+        options: GlobOptions,
+        hash: String,
+        glob_set: NxGlobMatcher,
+    ) {
+        self.file_sets.insert(
+            file_set_cache_key(globs, options),
+            CachedFileSet {
+                hash,
+                globs: globs.to_vec(),
+                options,
+                glob_set,
+            },
+        );
+    }
+
+    /// Removes every cached file set that matches at least one of the changed files.
+    /// Returns the number of invalidated file sets
+    pub fn invalidate<S: AsRef<str>>(&self, changed_files: &[S]) -> usize {
+        if changed_files.is_empty() {
+            return 0;
+        }
+
+        // the tree is built again from the new workspace files the next time a directory is hashed
+        *self.directory_tree.write() = None;
+        let invalidated_directories =
+            invalidate_directory_hashes(&self.directory_hashes, changed_files);
+        trace!("invalidated {} directory hashes", invalidated_directories);
+
+        let mut invalidated = 0;
+        self.file_sets.retain(|cache_key, file_set| {
+            let is_affected = changed_files
+                .iter()
+                .any(|file| file_set.glob_set.is_match(file.as_ref()));
+            if is_affected {
+                trace!("invalidating workspace file set: {}", cache_key);
+                invalidated += 1;
+            }
+            !is_affected
+        });
+        invalidated
+    }
+
+    /// Hashes a directory with every file below it, reusing the hashes of the directories that did not change.
+    /// Returns `None` when the directory does not contain any file
+    pub fn hash_directory(
+        &self,
+        directory: &str,
+        all_workspace_files: &[FileData],
+        missing_file_hasher: Option<&MissingFileHasher>,
+    ) -> Option<String> {
+        let directory_tree = self.directory_tree(all_workspace_files);
+        directory_tree.hash(directory, &self.directory_hashes, missing_file_hasher)
+    }
+
+    /// Identifies the files that the globs of a file set can match, by the hashes of the files and directories
+    /// before their first patterns. The fingerprint only changes when one of these files changes.
+    /// Case insensitive globs can match files below other directories, so they are identified by every workspace file
+    pub fn fingerprint(
+        &self,
+        globs: &[String],
+        options: GlobOptions,
+        all_workspace_files: &[FileData],
+        missing_file_hasher: Option<&MissingFileHasher>,
+    ) -> String {
+        let directory_tree = self.directory_tree(all_workspace_files);
+        let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+        for glob in globs {
+            let (base, is_literal) = if options.case_insensitive {
+                ("", false)
+            } else {
+                glob_base(glob)
+            };
+            // a glob without patterns can also be a directory
+            let base_hash = is_literal
+                .then(|| directory_tree.file_hash(base, missing_file_hasher))
+                .flatten()
+                .or_else(|| directory_tree.hash(base, &self.directory_hashes, missing_file_hasher));
+            hasher.update(glob.as_bytes());
+            hasher.update(b"\0");
+            hasher.update(base_hash.unwrap_or_default().as_bytes());
+            hasher.update(b"\0");
+        }
+        hasher.digest().to_string()
+    }
+
+    fn directory_tree(&self, all_workspace_files: &[FileData]) -> Arc<DirectoryTree> {
+        if let Some(directory_tree) = self.directory_tree.read().as_ref() {
+            return Arc::clone(directory_tree);
+        }
+        let mut directory_tree = self.directory_tree.write();
+        let directory_tree =
+            directory_tree.get_or_insert_with(|| Arc::new(DirectoryTree::new(all_workspace_files)));
+        Arc::clone(directory_tree)
+    }
+
+    /// Returns the globs, options and hashes of every cached file set
+    pub fn entries(&self) -> Vec<(Vec<String>, GlobOptions, String)> {
+        self.file_sets
+            .iter()
+            .map(|file_set| {
+                (
+                    file_set.globs.clone(),
+                    file_set.options,
+                    file_set.hash.clone(),
+                )
+            })
+            .collect()
+    }
+
+    /// Restores a file set hash that was computed for the same workspace files before (e.g. by a previous process)
+    pub fn restore(
+        &self,
+        globs: Vec<String>,
+        options: GlobOptions,
+        hash: String,
+        ordered_negated_globs: bool,
+    ) -> Result<(), HashError> {
+        let glob_set = build_glob_matcher(&globs, ordered_negated_globs, options)
+            .map_err(|e| HashError::invalid_glob(&globs, e))?;
+        self.insert(&globs, options, hash, glob_set);
+        Ok(())
+    }
+}
+
+/// Strips `{workspaceRoot}/` from the workspace file sets to get the globs that are matched against the workspace files.
+/// File sets that do not start with `{workspaceRoot}/` are ignored
+pub fn workspace_file_set_globs(workspace_file_sets: &[String]) -> Vec<String> {
+    workspace_file_sets
         .iter()
         .inspect(|&x| trace!("Workspace file set: {}", x))
         .filter_map(|x| {
@@ -33,33 +200,145 @@ pub fn hash_workspace_files(
                 None
             }
         })
+        .collect()
+}
+
+/// Collects the workspace files that match the already resolved globs, sorted by path
+pub fn collect_workspace_files<'a>(
+    globs: &[String],
+    all_workspace_files: &'a [FileData],
+    ordered_negated_globs: bool,
+    options: GlobOptions,
+) -> Result<Vec<&'a FileData>, HashError> {
+    if globs.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let glob = build_glob_matcher(globs, ordered_negated_globs, options)
+        .map_err(|e| HashError::invalid_glob(globs, e))?;
+    let mut files: Vec<&FileData> = all_workspace_files
+        .par_iter()
+        .filter(|file| glob.is_match(&file.file))
         .collect();
+    files.par_sort_by(|a, b| compare_files(a, b));
+    Ok(files)
+}
+
+/// The order of the matched files of a file set: by path, so the hash of a file set does not depend on
+/// the order of the workspace files it is matched against. Files are unique by path, the hash only
+/// breaks the tie when a file map has duplicates
+fn compare_files(a: &FileData, b: &FileData) -> std::cmp::Ordering {
+    a.file.cmp(&b.file).then_with(|| a.hash.cmp(&b.hash))
+}
+
+pub fn hash_workspace_files(
+    workspace_file_sets: &[String],
+    all_workspace_files: &[FileData],
+    cache: Arc<WorkspaceFilesCache>,
+    missing_file_hasher: Option<&MissingFileHasher>,
+    ordered_negated_globs: bool,
+    options: GlobOptions,
+) -> Result<String, HashError> {
+    let globs = workspace_file_set_globs(workspace_file_sets);
 
     if globs.is_empty() {
         return Ok(hash(b""));
     }
 
-    let cache_key = globs.join(",");
-    if let Some(cache_results) = cache.get(&cache_key) {
-        return Ok(cache_results.clone());
+    // negated globs exclude files matched by the other globs, so they have to be hashed together
+    if globs.iter().any(|glob| glob.starts_with('!')) {
+        return hash_file_set(
+            &globs,
+            all_workspace_files,
+            &cache,
+            missing_file_hasher,
+            ordered_negated_globs,
+            options,
+        );
     }
 
-    let glob = build_glob_set(&globs)?;
+    // every glob is hashed and cached on its own, so file sets that share globs can reuse each other's hashes
+    let mut globs = globs;
+    globs.sort();
+    globs.dedup();
+    if let [glob] = globs.as_slice() {
+        return hash_file_set(
+            std::slice::from_ref(glob),
+            all_workspace_files,
+            &cache,
+            missing_file_hasher,
+            ordered_negated_globs,
+            options,
+        );
+    }
 
     let mut hasher = xxhash_rust::xxh3::Xxh3::new();
-    let mut hashes: Vec<String> = Vec::new();
-    for file in all_workspace_files
-        .iter()
+    for glob in &globs {
+        let glob_hash = hash_file_set(
+            std::slice::from_ref(glob),
+            all_workspace_files,
+            &cache,
+            missing_file_hasher,
+            ordered_negated_globs,
+            options,
+        )?;
+        hasher.update(glob_hash.as_bytes());
+    }
+    Ok(hasher.digest().to_string())
+}
+
+fn hash_file_set(
+    globs: &[String],
+    all_workspace_files: &[FileData],
+    cache: &WorkspaceFilesCache,
+    missing_file_hasher: Option<&MissingFileHasher>,
+    ordered_negated_globs: bool,
+    options: GlobOptions,
+) -> Result<String, HashError> {
+    let cache_key = file_set_cache_key(globs, options);
+    if let Some(cache_results) = cache.get(&cache_key) {
+        return Ok(cache_results);
+    }
+
+    let glob = build_glob_matcher(globs, ordered_negated_globs, options)
+        .map_err(|e| HashError::invalid_glob(globs, e))?;
+
+    // the hash of a whole directory is computed from the hashes of its subdirectories instead of every file.
+    // The directories of the tree only match their own case
+    let directory = match globs {
+        [glob] if !options.case_insensitive => glob_directory(glob),
+        _ => None,
+    };
+    if let Some(hashed_value) = directory.and_then(|directory| {
+        cache.hash_directory(directory, all_workspace_files, missing_file_hasher)
+    }) {
+        trace!("hashed the directory of {:?}", globs);
+        cache.insert(globs, options, hashed_value.clone(), glob);
+        return Ok(hashed_value);
+    }
+
+    // matching (and hashing missing files) happens in parallel, the matched files are then sorted by path
+    let mut matched_files: Vec<(&FileData, Cow<str>)> = all_workspace_files
+        .par_iter()
         .filter(|file| glob.is_match(&file.file))
-    {
+        .map(|file| (file, get_file_hash(file, missing_file_hasher)))
+        .collect();
+    matched_files.par_sort_by(|(a, _), (b, _)| compare_files(a, b));
+
+    // feeds the same bytes as joining every hash and file with "," without building the joined string
+    let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+    for (index, (file, file_hash)) in matched_files.iter().enumerate() {
         trace!("{:?} was found with glob {:?}", file.file, globs);
-        hashes.push(file.hash.clone());
-        hashes.push(file.file.clone());
+        if index > 0 {
+            hasher.update(b",");
+        }
+        hasher.update(file_hash.as_bytes());
+        hasher.update(b",");
+        hasher.update(file.file.as_bytes());
     }
-    hasher.update(hashes.join(",").as_bytes());
     let hashed_value = hasher.digest().to_string();
 
-    cache.insert(cache_key.to_string(), hashed_value.clone());
+    cache.insert(globs, options, hashed_value.clone(), glob);
     Ok(hashed_value)
 }
 
@@ -76,7 +355,10 @@ mod test {
         let result = hash_workspace_files(
             &["packages/{package}".to_string()],
             &[],
-            Arc::new(DashMap::new()),
+            Arc::new(WorkspaceFilesCache::default()),
+            None,
+            false,
+            GlobOptions::default(),
         )
         .unwrap();
         assert_eq!(result, hash(b""));
@@ -108,12 +390,388 @@ mod test {
                 package_json_file.clone(),
                 project_file.clone(),
             ],
-            Arc::new(DashMap::new()),
+            Arc::new(WorkspaceFilesCache::default()),
+            None,
+            false,
+            GlobOptions::default(),
         )
         .unwrap();
-        assert_eq!(result, hash([
-            gitignore_file.hash,
-            gitignore_file.file
-        ].join(",").as_bytes()));
+        assert_eq!(
+            result,
+            hash(
+                [gitignore_file.hash, gitignore_file.file]
+                    .join(",")
+                    .as_bytes()
+            )
+        );
+    }
+
+    #[test]
+    fn should_hash_contents_of_files_without_hashes() {
+        use assert_fs::prelude::*;
+
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child(".gitignore").write_str("node_modules").unwrap();
+
+        let gitignore_file = FileData {
+            file: ".gitignore".into(),
+            hash: "".into(),
+        };
+        let missing_file_hasher = MissingFileHasher::new(temp.path(), Arc::new(DashMap::new()));
+        let result = hash_workspace_files(
+            &["{workspaceRoot}/.gitignore".to_string()],
+            std::slice::from_ref(&gitignore_file),
+            Arc::new(WorkspaceFilesCache::default()),
+            Some(&missing_file_hasher),
+            false,
+            GlobOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            result,
+            hash(
+                [hash(b"node_modules"), gitignore_file.file]
+                    .join(",")
+                    .as_bytes()
+            )
+        );
+    }
+
+    #[test]
+    fn should_only_invalidate_file_sets_with_changed_files() {
+        let files = [
+            FileData {
+                file: ".gitignore".into(),
+                hash: "123".into(),
+            },
+            FileData {
+                file: "package.json".into(),
+                hash: "456".into(),
+            },
+        ];
+        let cache = Arc::new(WorkspaceFilesCache::default());
+        let gitignore_file_set = ["{workspaceRoot}/.gitignore".to_string()];
+        let package_json_file_set = ["{workspaceRoot}/package.json".to_string()];
+        hash_workspace_files(
+            &gitignore_file_set,
+            &files,
+            Arc::clone(&cache),
+            None,
+            false,
+            GlobOptions::default(),
+        )
+        .unwrap();
+        hash_workspace_files(
+            &package_json_file_set,
+            &files,
+            Arc::clone(&cache),
+            None,
+            false,
+            GlobOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(cache.invalidate(&["packages/project/index.ts"]), 0);
+        assert_eq!(cache.invalidate(&["package.json"]), 1);
+        assert!(cache.get(".gitignore").is_some());
+        assert!(cache.get("package.json").is_none());
+    }
+
+    #[test]
+    fn should_cache_each_glob_separately() {
+        let files = [
+            FileData {
+                file: ".gitignore".into(),
+                hash: "123".into(),
+            },
+            FileData {
+                file: "package.json".into(),
+                hash: "456".into(),
+            },
+        ];
+        let cache = Arc::new(WorkspaceFilesCache::default());
+        let result = hash_workspace_files(
+            &[
+                "{workspaceRoot}/.gitignore".to_string(),
+                "{workspaceRoot}/package.json".to_string(),
+            ],
+            &files,
+            Arc::clone(&cache),
+            None,
+            false,
+            GlobOptions::default(),
+        )
+        .unwrap();
+
+        let gitignore_hash = cache.get(".gitignore").unwrap();
+        let package_json_hash = cache.get("package.json").unwrap();
+        assert_eq!(
+            gitignore_hash,
+            hash(
+                [&files[0].hash, &files[0].file]
+                    .map(String::as_str)
+                    .join(",")
+                    .as_bytes()
+            )
+        );
+        assert_eq!(
+            result,
+            hash(
+                [gitignore_hash.as_bytes(), package_json_hash.as_bytes()]
+                    .concat()
+                    .as_slice()
+            )
+        );
+
+        let reordered_result = hash_workspace_files(
+            &[
+                "{workspaceRoot}/package.json".to_string(),
+                "{workspaceRoot}/.gitignore".to_string(),
+            ],
+            &files,
+            Arc::clone(&cache),
+            None,
+            false,
+            GlobOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(result, reordered_result);
+
+        // only the glob containing the changed file is invalidated
+        assert_eq!(cache.invalidate(&["package.json"]), 1);
+        assert!(cache.get(".gitignore").is_some());
+    }
+
+    #[test]
+    fn should_hash_negated_globs_together() {
+        let files = [
+            FileData {
+                file: "config/a.json".into(),
+                hash: "123".into(),
+            },
+            FileData {
+                file: "config/b.json".into(),
+                hash: "456".into(),
+            },
+        ];
+        let cache = Arc::new(WorkspaceFilesCache::default());
+        let result = hash_workspace_files(
+            &[
+                "{workspaceRoot}/config/*.json".to_string(),
+                "!{workspaceRoot}/config/b.json".to_string(),
+            ],
+            &files,
+            Arc::clone(&cache),
+            None,
+            false,
+            GlobOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            result,
+            hash(
+                [&files[0].hash, &files[0].file]
+                    .map(String::as_str)
+                    .join(",")
+                    .as_bytes()
+            )
+        );
+        assert!(cache.get("config/*.json,!config/b.json").is_some());
+        assert!(cache.get("config/*.json").is_none());
+    }
+
+    #[test]
+    fn should_hash_files_in_order_without_joining_them() {
+        let files = (0..100)
+            .map(|i| FileData {
+                file: format!("config/{:03}.json", i),
+                hash: i.to_string(),
+            })
+            .collect::<Vec<_>>();
+        let result = hash_workspace_files(
+            &["{workspaceRoot}/config/*.json".to_string()],
+            &files,
+            Arc::new(WorkspaceFilesCache::default()),
+            None,
+            false,
+            GlobOptions::default(),
+        )
+        .unwrap();
+        let joined = files
+            .iter()
+            .flat_map(|file| [file.hash.as_str(), file.file.as_str()])
+            .collect::<Vec<_>>()
+            .join(",");
+        assert_eq!(result, hash(joined.as_bytes()));
+    }
+
+    #[test]
+    fn should_collect_files_matching_workspace_file_sets() {
+        let files = [
+            FileData {
+                file: ".gitignore".into(),
+                hash: "123".into(),
+            },
+            FileData {
+                file: "config/a.json".into(),
+                hash: "456".into(),
+            },
+            FileData {
+                file: "config/b.json".into(),
+                hash: "789".into(),
+            },
+        ];
+        let globs = workspace_file_set_globs(&[
+            "{workspaceRoot}/config/*.json".to_string(),
+            "!{workspaceRoot}/config/b.json".to_string(),
+            "packages/{package}".to_string(),
+        ]);
+        assert_eq!(globs, ["config/*.json", "!config/b.json"]);
+        assert_eq!(
+            collect_workspace_files(&globs, &files, false, GlobOptions::default()).unwrap(),
+            vec![&files[1]]
+        );
+        assert!(
+            collect_workspace_files(&[], &files, false, GlobOptions::default())
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn should_hash_whole_directories_with_the_directory_tree() {
+        let files = vec![
+            FileData {
+                file: "docs/a.md".into(),
+                hash: "123".into(),
+            },
+            FileData {
+                file: "docs/guides/b.md".into(),
+                hash: "456".into(),
+            },
+            FileData {
+                file: "package.json".into(),
+                hash: "789".into(),
+            },
+        ];
+        let cache = Arc::new(WorkspaceFilesCache::default());
+        let docs_file_set = ["{workspaceRoot}/docs/**/*".to_string()];
+        let result = hash_workspace_files(
+            &docs_file_set,
+            &files,
+            Arc::clone(&cache),
+            None,
+            false,
+            GlobOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            Some(result.clone()),
+            DirectoryTree::new(&files).hash("docs", &DashMap::new(), None)
+        );
+        assert_eq!(cache.get("docs/**/*"), Some(result.clone()));
+
+        let mut changed_files = files.clone();
+        changed_files[1].hash = "abc".into();
+        assert_eq!(cache.invalidate(&["docs/guides/b.md"]), 1);
+        let changed_result = hash_workspace_files(
+            &docs_file_set,
+            &changed_files,
+            Arc::clone(&cache),
+            None,
+            false,
+            GlobOptions::default(),
+        )
+        .unwrap();
+        assert_ne!(changed_result, result);
+        assert_eq!(
+            Some(changed_result),
+            cache.hash_directory("docs", &changed_files, None)
+        );
+    }
+
+    #[test]
+    fn should_restore_cached_file_sets() {
+        let cache = WorkspaceFilesCache::default();
+        cache
+            .restore(
+                vec!["config/*.json".into()],
+                GlobOptions::default(),
+                "123".into(),
+                false,
+            )
+            .unwrap();
+
+        assert_eq!(cache.get("config/*.json").as_deref(), Some("123"));
+        assert_eq!(
+            cache.entries(),
+            [(
+                vec!["config/*.json".to_string()],
+                GlobOptions::default(),
+                "123".to_string()
+            )]
+        );
+        assert_eq!(cache.invalidate(&["config/a.json"]), 1);
+        assert!(cache.entries().is_empty());
+    }
+
+    mod ordering {
+        use super::*;
+        use proptest::prelude::*;
+
+        fn file_hash(files: &[FileData]) -> String {
+            hash_workspace_files(
+                &["{workspaceRoot}/**/*.json".to_string()],
+                files,
+                Arc::new(WorkspaceFilesCache::default()),
+                None,
+                false,
+                GlobOptions::default(),
+            )
+            .unwrap()
+        }
+
+        /// Workspace files with unique paths, in sorted order
+        fn workspace_files() -> impl Strategy<Value = Vec<FileData>> {
+            prop::collection::btree_map(
+                "[a-z]{1,4}(/[a-z]{1,4}){0,2}\\.(json|ts)",
+                "[0-9]{1,6}",
+                0..40,
+            )
+            .prop_map(|files| {
+                files
+                    .into_iter()
+                    .map(|(file, hash)| FileData { file, hash })
+                    .collect()
+            })
+        }
+
+        proptest! {
+            #[test]
+            fn should_not_depend_on_the_order_of_the_files(
+                (sorted, shuffled) in workspace_files()
+                    .prop_flat_map(|files| (Just(files.clone()), Just(files).prop_shuffle()))
+            ) {
+                prop_assert_eq!(file_hash(&sorted), file_hash(&shuffled));
+                prop_assert_eq!(
+                    collect_workspace_files(&["**/*.json".to_string()], &shuffled, false, GlobOptions::default()).unwrap(),
+                    collect_workspace_files(&["**/*.json".to_string()], &sorted, false, GlobOptions::default()).unwrap()
+                );
+            }
+
+            #[test]
+            fn should_hash_the_matched_files_by_path(files in workspace_files()) {
+                let joined = files
+                    .iter()
+                    .filter(|file| file.file.ends_with(".json"))
+                    .flat_map(|file| [file.hash.as_str(), file.file.as_str()])
+                    .collect::<Vec<_>>()
+                    .join(",");
+
+                let mut reversed = files;
+                reversed.reverse();
+                prop_assert_eq!(file_hash(&reversed), hash(joined.as_bytes()));
+            }
+        }
     }
 }
@@ -0,0 +1,282 @@
+// the errors of ureq are large, and are matched on their status where the requests are sent
+#![allow(clippy::result_large_err)]
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::Context;
+use napi::{Env, Task};
+use serde_json::{json, Value};
+use xxhash_rust::xxh3::{xxh3_128, xxh3_64};
+
+use crate::native::cache::remote_cache::with_retries;
+
+const DEFAULT_SERVICE_NAME: &str = "nx";
+const TRACES_PATH: &str = "/v1/traces";
+const PUSH_RETRIES: u32 = 3;
+const PUSH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The formats that task runs are exported to
+#[napi(string_enum)]
+#[derive(Debug)]
+pub enum TaskRunsExportFormat {
+    /// One JSON object per task run and per line
+    #[allow(non_camel_case_types)]
+    jsonl,
+    /// An OTLP `ExportTraceServiceRequest` in its JSON encoding, with one span per task run
+    #[allow(non_camel_case_types)]
+    otlp,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct TaskRunsExportOptions {
+    /// Runs that started before this, in milliseconds since the epoch, are not exported
+    pub since: Option<i64>,
+    /// Runs that started after this are not exported
+    pub until: Option<i64>,
+    /// The `service.name` of the resource of the spans, `nx` by default
+    pub service_name: Option<String>,
+}
+
+/// A task run of the task history, with the task it ran
+pub struct ExportedTaskRun {
+    pub id: i64,
+    pub hash: String,
+    pub project: String,
+    pub target: String,
+    pub configuration: Option<String>,
+    pub status: String,
+    pub code: i16,
+    pub start: i64,
+    pub end: i64,
+}
+
+impl ExportedTaskRun {
+    fn task_name(&self) -> String {
+        match &self.configuration {
+            Some(configuration) => format!("{}:{}:{}", self.project, self.target, configuration),
+            None => format!("{}:{}", self.project, self.target),
+        }
+    }
+
+    fn cache_status(&self) -> &'static str {
+        match self.status.as_str() {
+            "local-cache" | "local-cache-kept-existing" => "local-hit",
+            "remote-cache" => "remote-hit",
+            _ => "miss",
+        }
+    }
+
+    fn duration(&self) -> i64 {
+        self.end.saturating_sub(self.start)
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "task": self.task_name(),
+            "project": self.project,
+            "target": self.target,
+            "configuration": self.configuration,
+            "hash": self.hash,
+            "status": self.status,
+            "cacheStatus": self.cache_status(),
+            "code": self.code,
+            "start": self.start,
+            "end": self.end,
+            "duration": self.duration(),
+        })
+    }
+
+    /// Every run is a trace of its own, with ids derived from the run so that exporting twice does not duplicate spans
+    fn to_span(&self) -> Value {
+        let id = format!("{}:{}", self.id, self.hash);
+        let mut attributes = vec![
+            string_attribute("nx.task.name", &self.task_name()),
+            string_attribute("nx.task.project", &self.project),
+            string_attribute("nx.task.target", &self.target),
+            string_attribute("nx.task.hash", &self.hash),
+            string_attribute("nx.task.status", &self.status),
+            string_attribute("nx.task.cache_status", self.cache_status()),
+            int_attribute("nx.task.exit_code", self.code as i64),
+            int_attribute("nx.task.duration_ms", self.duration()),
+        ];
+        if let Some(configuration) = &self.configuration {
+            attributes.push(string_attribute("nx.task.configuration", configuration));
+        }
+        let status = if self.code == 0 {
+            json!({ "code": 1 })
+        } else {
+            json!({ "code": 2, "message": format!("exited with code {}", self.code) })
+        };
+
+        json!({
+            "traceId": format!("{:032x}", xxh3_128(id.as_bytes())),
+            "spanId": format!("{:016x}", xxh3_64(id.as_bytes())),
+            "name": self.task_name(),
+            // SPAN_KIND_INTERNAL
+            "kind": 1,
+            "startTimeUnixNano": unix_nanos(self.start),
+            "endTimeUnixNano": unix_nanos(self.end),
+            "attributes": attributes,
+            "status": status,
+        })
+    }
+}
+
+pub fn to_jsonl(runs: &[ExportedTaskRun]) -> String {
+    runs.iter()
+        .map(|run| format!("{}\n", run.to_json()))
+        .collect()
+}
+
+pub fn to_otlp(runs: &[ExportedTaskRun], service_name: Option<&str>) -> String {
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [string_attribute(
+                    "service.name",
+                    service_name.unwrap_or(DEFAULT_SERVICE_NAME)
+                )],
+            },
+            "scopeSpans": [{
+                "scope": { "name": "nx" },
+                "spans": runs.iter().map(ExportedTaskRun::to_span).collect::<Vec<_>>(),
+            }],
+        }],
+    })
+    .to_string()
+}
+
+/// The traces url of an OTLP/HTTP endpoint, which is the endpoint itself when it already ends with `/v1/traces`
+pub fn traces_url(endpoint: &str) -> String {
+    let endpoint = endpoint.trim_end_matches('/');
+    if endpoint.ends_with(TRACES_PATH) {
+        endpoint.to_string()
+    } else {
+        format!("{}{}", endpoint, TRACES_PATH)
+    }
+}
+
+fn string_attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+/// 64 bit integers are strings in the JSON encoding of OTLP
+fn int_attribute(key: &str, value: i64) -> Value {
+    json!({ "key": key, "value": { "intValue": value.to_string() } })
+}
+
+fn unix_nanos(millis: i64) -> String {
+    millis.saturating_mul(1_000_000).to_string()
+}
+
+/// Sends exported spans to an OTLP/HTTP endpoint, and resolves to the number of spans that were sent
+pub struct PushTaskRuns {
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+    pub spans: u32,
+}
+
+impl Task for PushTaskRuns {
+    type Output = u32;
+    type JsValue = u32;
+
+    fn compute(&mut self) -> napi::Result<Self::Output> {
+        if self.spans == 0 {
+            return Ok(0);
+        }
+        let agent = ureq::AgentBuilder::new().timeout(PUSH_TIMEOUT).build();
+        with_retries(PUSH_RETRIES, || {
+            let mut request = agent
+                .post(&self.url)
+                .set("Content-Type", "application/json");
+            for (name, value) in &self.headers {
+                request = request.set(name, value);
+            }
+            request.send_string(&self.body)
+        })
+        .with_context(|| format!("Unable to push the task runs to {}", self.url))?;
+        Ok(self.spans)
+    }
+
+    fn resolve(&mut self, _: Env, spans: Self::Output) -> napi::Result<Self::JsValue> {
+        Ok(spans)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(configuration: Option<&str>, status: &str, code: i16) -> ExportedTaskRun {
+        ExportedTaskRun {
+            id: 1,
+            hash: "123".into(),
+            project: "proj".into(),
+            target: "build".into(),
+            configuration: configuration.map(String::from),
+            status: status.into(),
+            code,
+            start: 1_000,
+            end: 3_500,
+        }
+    }
+
+    #[test]
+    fn should_export_runs_as_jsonl() {
+        let jsonl = to_jsonl(&[
+            run(Some("production"), "remote-cache", 0),
+            run(None, "failure", 1),
+        ]);
+        let lines = jsonl
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["task"], "proj:build:production");
+        assert_eq!(lines[0]["cacheStatus"], "remote-hit");
+        assert_eq!(lines[0]["duration"], 2_500);
+        assert_eq!(lines[1]["task"], "proj:build");
+        assert_eq!(lines[1]["cacheStatus"], "miss");
+        assert_eq!(lines[1]["code"], 1);
+    }
+
+    #[test]
+    fn should_export_runs_as_otlp_spans() {
+        let request: Value = serde_json::from_str(&to_otlp(
+            &[run(None, "local-cache", 0), run(None, "failure", 2)],
+            None,
+        ))
+        .unwrap();
+        let resource_spans = &request["resourceSpans"][0];
+        assert_eq!(
+            resource_spans["resource"]["attributes"][0]["value"]["stringValue"],
+            "nx"
+        );
+        let span = &resource_spans["scopeSpans"][0]["spans"][0];
+        assert_eq!(span["name"], "proj:build");
+        assert_eq!(span["traceId"].as_str().unwrap().len(), 32);
+        assert_eq!(span["spanId"].as_str().unwrap().len(), 16);
+        assert_eq!(span["startTimeUnixNano"], "1000000000");
+        assert_eq!(span["status"]["code"], 1);
+        assert!(span["attributes"]
+            .as_array()
+            .unwrap()
+            .contains(&string_attribute("nx.task.cache_status", "local-hit")));
+        assert_eq!(
+            resource_spans["scopeSpans"][0]["spans"][1]["status"]["code"],
+            2
+        );
+
+        assert_eq!(
+            traces_url("http://localhost:4318/"),
+            "http://localhost:4318/v1/traces"
+        );
+        assert_eq!(
+            traces_url("http://localhost:4318/v1/traces"),
+            "http://localhost:4318/v1/traces"
+        );
+    }
+}
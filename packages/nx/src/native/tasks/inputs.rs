@@ -1,4 +1,6 @@
+use crate::native::glob::GlobOptions;
 use crate::native::project_graph::types::{Project, ProjectGraph};
+use crate::native::tasks::hashers::HashError;
 use crate::native::tasks::types::Task;
 use crate::native::types::{Input, NxJson};
 use std::collections::HashMap;
@@ -74,7 +76,7 @@ fn split_inputs_into_self_and_deps<'a>(
 ) -> anyhow::Result<SplitInputs<'a>> {
     let inputs = inputs.unwrap_or_else(|| {
         vec![
-            Input::FileSet("{projectRoot}/**/*"),
+            Input::FileSet("{projectRoot}/**/*", GlobOptions::default()),
             Input::Inputs {
                 input: "default",
                 dependencies: true,
@@ -101,7 +103,7 @@ fn split_inputs_into_self_and_deps<'a>(
                     ..
                 }
                 | Input::String(_)
-                | Input::FileSet(_)
+                | Input::FileSet(..)
                 | Input::Runtime(_)
                 | Input::Environment(_)
                 | Input::DepsOutputs { .. }
@@ -148,16 +150,16 @@ pub(super) fn expand_single_project_inputs<'a>(
                     expanded.extend(expand_named_input(s, named_inputs)?);
                 } else {
                     validate_file_set(s)?;
-                    expanded.push(Input::FileSet(s));
+                    expanded.push(Input::FileSet(s, GlobOptions::default()));
                 }
             }
             Input::Inputs {
                 input,
                 dependencies: false,
             } => expanded.extend(expand_named_input(input, named_inputs)?),
-            Input::FileSet(fileset) => {
+            Input::FileSet(fileset, options) => {
                 validate_file_set(fileset)?;
-                expanded.push(Input::FileSet(fileset));
+                expanded.push(Input::FileSet(fileset, *options));
             }
             Input::Runtime(runtime) => expanded.push(Input::Runtime(runtime)),
             Input::Environment(env) => expanded.push(Input::Environment(env)),
@@ -185,22 +187,13 @@ pub(super) fn expand_single_project_inputs<'a>(
     Ok(expanded)
 }
 
-fn validate_file_set(s: &str) -> anyhow::Result<()> {
+fn validate_file_set(s: &str) -> Result<(), HashError> {
     if !s.starts_with("{projectRoot}")
         && !s.starts_with("!{projectRoot}")
         && !s.starts_with("{workspaceRoot}")
         && !s.starts_with("!{workspaceRoot}")
     {
-        anyhow::bail!(
-            r#""{file_set}" is an invalid fileset.
-All filesets have to start with either {workspaceRoot} or {projectRoot}.
-For instance: "!{projectRoot}/**/*.spec.ts" or "{workspaceRoot}/package.json".
-If "{file_set}" is a named input, make sure it is defined in nx.json.
-"#,
-            file_set = s,
-            projectRoot = "{projectRoot}",
-            workspaceRoot = "{workspaceRoot}",
-        );
+        Err(HashError::MissingFileSetToken(s.to_string()))
     } else {
         Ok(())
     }
@@ -223,7 +216,10 @@ pub(super) fn get_named_inputs<'a>(
 ) -> HashMap<&'a str, Vec<Input<'a>>> {
     let mut collected_named_inputs: HashMap<&str, Vec<Input>> = HashMap::new();
 
-    collected_named_inputs.insert("default", vec![Input::FileSet("{projectRoot}/**/*")]);
+    collected_named_inputs.insert(
+        "default",
+        vec![Input::FileSet("{projectRoot}/**/*", GlobOptions::default())],
+    );
 
     let iterable_structs = [&nx_json.named_inputs, &project.named_inputs];
     for named_inputs in iterable_structs.into_iter().flatten() {
@@ -1,12 +1,27 @@
 mod dep_outputs;
+mod hash_diff;
 mod hash_planner;
 pub mod hashers;
 mod inputs;
+pub mod task_env;
 pub mod task_hasher;
 pub mod types;
 mod utils;
+pub mod visualization;
 
 #[cfg(not(target_arch = "wasm32"))]
 pub mod details;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod hash_cache;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod history_export;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod outputs_snapshots;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ports;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod scheduler;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod task_history;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod watch_runner;
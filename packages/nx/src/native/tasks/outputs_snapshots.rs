@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use napi::bindgen_prelude::*;
+use rusqlite::{params, OptionalExtension};
+use tracing::trace;
+
+use crate::native::cache::expand_outputs::get_files_for_outputs;
+use crate::native::db::connection::NxDbConnection;
+use crate::native::hasher::{hash_array, hash_file_path_streamed};
+use crate::native::utils::parallel::prelude::*;
+
+/// How the outputs of a task on disk differ from the outputs that were last written by a task.
+/// The paths are relative to the workspace root
+#[napi(object)]
+#[derive(Debug, Default, PartialEq)]
+pub struct OutputsDrift {
+    /// The hash of the last task that wrote the outputs
+    pub hash: String,
+    /// The files that were changed since the last run
+    pub modified: Vec<String>,
+    /// The files that were created since the last run
+    pub added: Vec<String>,
+    /// The files that were removed since the last run
+    pub removed: Vec<String>,
+}
+
+/// Records digests of the outputs of tasks after they run, to detect the outputs that were changed
+/// outside of Nx (e.g. manual edits of `dist`) before they are restored from the cache.
+///
+/// The snapshots are keyed on the outputs rather than on the tasks, because tasks can write the same
+/// outputs (e.g. the configurations of a build all write `dist/apps/app`)
+#[napi]
+pub struct NxOutputsSnapshots {
+    db: External<NxDbConnection>,
+    workspace_root: PathBuf,
+}
+
+#[napi]
+impl NxOutputsSnapshots {
+    #[napi(constructor)]
+    pub fn new(db: External<NxDbConnection>, workspace_root: String) -> anyhow::Result<Self> {
+        Ok(Self {
+            db,
+            workspace_root: PathBuf::from(workspace_root),
+        })
+    }
+
+    /// Replaces the snapshot of the outputs with the outputs on disk, after the task with the hash
+    /// `hash` ran or its outputs were restored
+    #[napi]
+    pub fn record_outputs(&self, hash: String, outputs: Vec<String>) -> anyhow::Result<()> {
+        let key = outputs_key(&outputs);
+        let files = digest_files(&self.workspace_root, outputs)?;
+        let digest = snapshot_digest(&files);
+        trace!(
+            "recording {} files of the outputs {} with the digest {}",
+            files.len(),
+            key,
+            digest
+        );
+        self.db.write(move |db| {
+            db.execute(
+                "INSERT OR REPLACE INTO task_outputs_snapshots (outputs, hash, digest) VALUES (?1, ?2, ?3)",
+                params![key, hash, digest],
+            )?;
+            db.execute(
+                "DELETE FROM task_outputs_files WHERE outputs = ?1",
+                params![key],
+            )?;
+            let mut stmt = db.prepare(
+                "INSERT INTO task_outputs_files (outputs, path, digest) VALUES (?1, ?2, ?3)",
+            )?;
+            for (path, digest) in files.iter() {
+                stmt.execute(params![key, path, digest])?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Compares the outputs on disk with their snapshot, whichever task wrote them last.
+    /// Returns nothing when the outputs have no snapshot or they are the ones of the snapshot
+    #[napi]
+    pub fn detect_drift(&self, outputs: Vec<String>) -> anyhow::Result<Option<OutputsDrift>> {
+        let key = outputs_key(&outputs);
+        let db = self.db.connection()?;
+        let Some((hash, digest)) = db
+            .query_row(
+                "SELECT hash, digest FROM task_outputs_snapshots WHERE outputs = ?1",
+                params![key],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+            )
+            .optional()?
+        else {
+            return Ok(None);
+        };
+
+        let files = digest_files(&self.workspace_root, outputs)?;
+        if snapshot_digest(&files) == digest {
+            return Ok(None);
+        }
+
+        let mut stmt =
+            db.prepare("SELECT path, digest FROM task_outputs_files WHERE outputs = ?1")?;
+        let mut recorded = stmt
+            .query_map(params![key], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<BTreeMap<String, String>>>()?;
+
+        let mut drift = OutputsDrift {
+            hash,
+            ..Default::default()
+        };
+        for (path, digest) in files {
+            match recorded.remove(&path) {
+                Some(recorded) if recorded == digest => {}
+                Some(_) => drift.modified.push(path),
+                None => drift.added.push(path),
+            }
+        }
+        drift.removed = recorded.into_keys().collect();
+        trace!("the outputs {} drifted: {:?}", key, drift);
+        Ok(Some(drift))
+    }
+}
+
+/// The key of the snapshot of the outputs, which does not depend on the order they are listed in
+fn outputs_key(outputs: &[String]) -> String {
+    let mut outputs = outputs.to_vec();
+    outputs.sort_unstable();
+    outputs.dedup();
+    outputs.join(",")
+}
+
+/// The digests of the files of the outputs by their paths relative to the workspace root
+fn digest_files(
+    workspace_root: &Path,
+    outputs: Vec<String>,
+) -> anyhow::Result<BTreeMap<String, String>> {
+    let files = get_files_for_outputs(workspace_root.to_string_lossy().into_owned(), outputs)?;
+    Ok(files
+        .into_par_iter()
+        .filter_map(|file| {
+            let digest = hash_file_path_streamed(workspace_root.join(&file))?;
+            Some((file, digest))
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .collect())
+}
+
+fn snapshot_digest(files: &BTreeMap<String, String>) -> String {
+    hash_array(
+        files
+            .iter()
+            .flat_map(|(path, digest)| [path.clone(), digest.clone()])
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    use crate::native::utils::Normalize;
+
+    use super::*;
+
+    #[test]
+    fn should_detect_outputs_changed_since_the_last_run() {
+        let temp = TempDir::new().unwrap();
+        let db = NxDbConnection::open(&temp.join("nx.db")).unwrap();
+        let workspace = TempDir::new().unwrap();
+        let snapshots =
+            NxOutputsSnapshots::new(External::new(db), workspace.to_normalized_string()).unwrap();
+        let outputs = || vec!["dist".to_string()];
+
+        workspace.child("dist/main.js").write_str("main").unwrap();
+        workspace
+            .child("dist/styles.css")
+            .write_str("body")
+            .unwrap();
+        assert_eq!(snapshots.detect_drift(outputs()).unwrap(), None);
+
+        snapshots.record_outputs("123".into(), outputs()).unwrap();
+        assert_eq!(snapshots.detect_drift(outputs()).unwrap(), None);
+
+        workspace.child("dist/main.js").write_str("edited").unwrap();
+        workspace.child("dist/extra.js").write_str("extra").unwrap();
+        std::fs::remove_file(workspace.join("dist/styles.css")).unwrap();
+        assert_eq!(
+            snapshots
+                .detect_drift(vec!["dist".into(), "dist".into()])
+                .unwrap(),
+            Some(OutputsDrift {
+                hash: "123".into(),
+                modified: vec!["dist/main.js".into()],
+                added: vec!["dist/extra.js".into()],
+                removed: vec!["dist/styles.css".into()],
+            })
+        );
+
+        snapshots.record_outputs("456".into(), outputs()).unwrap();
+        assert_eq!(snapshots.detect_drift(outputs()).unwrap(), None);
+    }
+}
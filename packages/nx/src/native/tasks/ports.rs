@@ -0,0 +1,373 @@
+use std::collections::{HashMap, HashSet};
+use std::net::{Ipv4Addr, Ipv6Addr, TcpListener};
+
+use parking_lot::Mutex;
+use tracing::debug;
+
+use crate::native::tasks::types::TaskGraph;
+
+const DEFAULT_RANGE_START: u32 = 4200;
+const DEFAULT_RANGE_END: u32 = 4999;
+
+#[napi(object)]
+pub struct PortAllocatorOptions {
+    /// The first port that is allocated, 4200 by default
+    pub range_start: Option<u32>,
+    /// The last port that is allocated, 4999 by default
+    pub range_end: Option<u32>,
+    /// The address that the ports have to be free on, any IPv4 and IPv6 address by default
+    pub host: Option<String>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PortReservation {
+    pub task_id: String,
+    pub port: u32,
+}
+
+#[napi(object)]
+#[derive(Debug, PartialEq)]
+pub struct PortConflict {
+    pub port: u32,
+    /// The tasks that listen on the port and can run at the same time, sorted
+    pub task_ids: Vec<String>,
+    /// Another process already listens on the port
+    pub in_use: bool,
+}
+
+/// Reserves the ports of the tasks that run at the same time, so that two tasks never listen on the same port.
+/// Ports are only allocated when no process listens on them, and are allocated in turn, so a port that was just
+/// released is not allocated again while the process that listened on it may still hold it
+#[napi]
+pub struct PortAllocator {
+    range_start: u16,
+    range_end: u16,
+    host: Option<String>,
+    state: Mutex<PortAllocatorState>,
+}
+
+#[derive(Default)]
+struct PortAllocatorState {
+    reserved: HashMap<u16, String>,
+    next: Option<u16>,
+}
+
+#[napi]
+impl PortAllocator {
+    #[napi(constructor)]
+    pub fn new(options: Option<PortAllocatorOptions>) -> anyhow::Result<Self> {
+        let range_start = options
+            .as_ref()
+            .and_then(|options| options.range_start)
+            .unwrap_or(DEFAULT_RANGE_START);
+        let range_end = options
+            .as_ref()
+            .and_then(|options| options.range_end)
+            .unwrap_or(DEFAULT_RANGE_END);
+        anyhow::ensure!(
+            0 < range_start && range_start <= range_end && range_end <= u16::MAX as u32,
+            "{}-{} is not a range of TCP ports",
+            range_start,
+            range_end
+        );
+
+        Ok(Self {
+            range_start: range_start as u16,
+            range_end: range_end as u16,
+            host: options.and_then(|options| options.host),
+            state: Default::default(),
+        })
+    }
+
+    /// Reserves a port for a task: its preferred port when it is free, or the next free port of the range.
+    /// A task that already has a port keeps it
+    #[napi]
+    pub fn reserve(&self, task_id: String, preferred: Option<u32>) -> anyhow::Result<u32> {
+        let mut state = self.state.lock();
+        if let Some((port, _)) = state.reserved.iter().find(|(_, owner)| **owner == task_id) {
+            return Ok(*port as u32);
+        }
+
+        let preferred = preferred
+            .and_then(|port| u16::try_from(port).ok())
+            .filter(|port| *port > 0);
+        if let Some(port) = preferred {
+            if !state.reserved.contains_key(&port) && self.is_free(port) {
+                state.reserved.insert(port, task_id);
+                return Ok(port as u32);
+            }
+            debug!("the port {} of {} is not free", port, task_id);
+        }
+
+        let start = state.next.unwrap_or(self.range_start);
+        let ports = (start..=self.range_end).chain(self.range_start..start);
+        for port in ports {
+            if !state.reserved.contains_key(&port) && self.is_free(port) {
+                debug!("reserved the port {} for {}", port, task_id);
+                state.reserved.insert(port, task_id);
+                state.next = Some(if port == self.range_end {
+                    self.range_start
+                } else {
+                    port + 1
+                });
+                return Ok(port as u32);
+            }
+        }
+        anyhow::bail!(
+            "there is no free port between {} and {} for {}",
+            self.range_start,
+            self.range_end,
+            task_id
+        )
+    }
+
+    /// Releases the port of a task, once it stopped listening on it
+    #[napi]
+    pub fn release(&self, task_id: String) {
+        self.state
+            .lock()
+            .reserved
+            .retain(|_, owner| *owner != task_id);
+    }
+
+    /// The reserved ports, sorted
+    #[napi]
+    pub fn reservations(&self) -> Vec<PortReservation> {
+        let mut reservations = self
+            .state
+            .lock()
+            .reserved
+            .iter()
+            .map(|(port, task_id)| PortReservation {
+                task_id: task_id.clone(),
+                port: *port as u32,
+            })
+            .collect::<Vec<_>>();
+        reservations.sort_by_key(|reservation| reservation.port);
+        reservations
+    }
+
+    fn is_free(&self, port: u16) -> bool {
+        port_is_free(self.host.as_deref(), port)
+    }
+}
+
+/// Whether no process listens on a port of a host, or of any IPv4 and IPv6 address without a host
+#[napi]
+pub fn is_port_free(port: u32, host: Option<String>) -> bool {
+    u16::try_from(port).is_ok_and(|port| port_is_free(host.as_deref(), port))
+}
+
+/// A port that no process listens on, picked by the OS
+#[napi]
+pub fn find_free_port(host: Option<String>) -> anyhow::Result<u32> {
+    let listener = match host {
+        Some(host) => TcpListener::bind((host.as_str(), 0))?,
+        None => TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0))?,
+    };
+    Ok(listener.local_addr()?.port() as u32)
+}
+
+/// Finds the tasks that listen on the same port and can run at the same time: tasks that do not depend on each other,
+/// and continuous tasks with the tasks that depend on them, since they keep running next to them.
+/// Ports that another process already listens on are conflicts as well, even with a single task
+#[napi]
+pub fn detect_port_conflicts(
+    task_graph: TaskGraph,
+    ports: HashMap<String, u32>,
+    continuous: Option<Vec<String>>,
+) -> Vec<PortConflict> {
+    let continuous = continuous
+        .unwrap_or_default()
+        .into_iter()
+        .collect::<HashSet<_>>();
+    // whether the task finished before the dependent task starts
+    let finishes_before = |task_id: &str, dependent: &str| {
+        !continuous.contains(task_id) && depends_on(&task_graph, dependent, task_id)
+    };
+
+    let mut tasks_by_port: HashMap<u32, Vec<&str>> = HashMap::new();
+    for (task_id, port) in &ports {
+        tasks_by_port
+            .entry(*port)
+            .or_default()
+            .push(task_id.as_str());
+    }
+
+    let mut conflicts = tasks_by_port
+        .into_iter()
+        .filter_map(|(port, tasks)| {
+            let mut task_ids = tasks
+                .iter()
+                .filter(|task_id| {
+                    tasks.iter().any(|other| {
+                        other != *task_id
+                            && !finishes_before(task_id, other)
+                            && !finishes_before(other, task_id)
+                    })
+                })
+                .map(|task_id| task_id.to_string())
+                .collect::<Vec<_>>();
+            let in_use = !is_port_free(port, None);
+            if in_use {
+                task_ids = tasks.iter().map(|task_id| task_id.to_string()).collect();
+            }
+            task_ids.sort();
+            (!task_ids.is_empty()).then_some(PortConflict {
+                port,
+                task_ids,
+                in_use,
+            })
+        })
+        .collect::<Vec<_>>();
+    conflicts.sort_by_key(|conflict| conflict.port);
+    conflicts
+}
+
+/// Whether a task depends on another task, directly or transitively
+fn depends_on(task_graph: &TaskGraph, task_id: &str, dependency: &str) -> bool {
+    let mut seen = HashSet::new();
+    let mut queue = vec![task_id];
+    while let Some(task_id) = queue.pop() {
+        for task_dependency in task_graph.dependencies.get(task_id).into_iter().flatten() {
+            if task_dependency == dependency {
+                return true;
+            }
+            if seen.insert(task_dependency.as_str()) {
+                queue.push(task_dependency);
+            }
+        }
+    }
+    false
+}
+
+/// Whether no process listens on a port. Servers commonly listen on every IPv4 address, every IPv6 address, or only on
+/// the loopback address of one of them, so without a host the port has to be free on all of them
+fn port_is_free(host: Option<&str>, port: u16) -> bool {
+    match host {
+        Some(host) => TcpListener::bind((host, port)).is_ok(),
+        None => {
+            TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)).is_ok()
+                && ipv6_port_is_free(Ipv6Addr::UNSPECIFIED, port)
+                && ipv6_port_is_free(Ipv6Addr::LOCALHOST, port)
+        }
+    }
+}
+
+/// Whether no process listens on an IPv6 port, which is free on machines without IPv6
+fn ipv6_port_is_free(address: Ipv6Addr, port: u16) -> bool {
+    match TcpListener::bind((address, port)) {
+        Ok(_) => true,
+        Err(e) => e.kind() == std::io::ErrorKind::AddrNotAvailable,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::native::tasks::types::Task;
+
+    fn allocator() -> PortAllocator {
+        let start = find_free_port(None).unwrap();
+        PortAllocator::new(Some(PortAllocatorOptions {
+            range_start: Some(start),
+            range_end: Some((start + 50).min(u16::MAX as u32)),
+            host: None,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn should_reserve_a_free_port_for_every_task() {
+        let ports = allocator();
+        let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+        let in_use = listener.local_addr().unwrap().port() as u32;
+
+        let app = ports.reserve("app:serve".into(), Some(in_use)).unwrap();
+        assert_ne!(app, in_use);
+        let api = ports.reserve("api:serve".into(), Some(app)).unwrap();
+        assert_ne!(api, app);
+        assert_eq!(ports.reserve("app:serve".into(), None).unwrap(), app);
+        assert_eq!(
+            ports
+                .reservations()
+                .into_iter()
+                .map(|reservation| reservation.task_id)
+                .collect::<HashSet<_>>(),
+            HashSet::from(["app:serve".to_string(), "api:serve".to_string()])
+        );
+
+        ports.release("app:serve".into());
+        // released ports are not allocated again right away
+        assert_ne!(ports.reserve("docs:serve".into(), None).unwrap(), app);
+        assert_eq!(ports.reserve("app:serve".into(), Some(app)).unwrap(), app);
+        assert!(PortAllocator::new(Some(PortAllocatorOptions {
+            range_start: Some(5000),
+            range_end: Some(4000),
+            host: None,
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn should_detect_tasks_that_listen_on_the_same_port_at_the_same_time() {
+        let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+        let in_use = listener.local_addr().unwrap().port() as u32;
+        let free = find_free_port(None).unwrap();
+        let task_graph = TaskGraph {
+            roots: vec![],
+            tasks: [
+                "app:serve",
+                "app-e2e:e2e",
+                "api:serve",
+                "lib:serve",
+                "docs:serve",
+            ]
+            .into_iter()
+            .map(|task_id| {
+                let task = Task {
+                    id: task_id.to_string(),
+                    ..Default::default()
+                };
+                (task_id.to_string(), task)
+            })
+            .collect(),
+            dependencies: HashMap::from([
+                ("app-e2e:e2e".into(), vec!["app:serve".into()]),
+                ("lib:serve".into(), vec!["api:serve".into()]),
+            ]),
+        };
+        let ports = HashMap::from([
+            ("app:serve".to_string(), free),
+            ("app-e2e:e2e".to_string(), free),
+            ("api:serve".to_string(), free + 1),
+            ("lib:serve".to_string(), free + 1),
+            ("docs:serve".to_string(), in_use),
+        ]);
+
+        let conflicts = detect_port_conflicts(task_graph, ports, Some(vec!["app:serve".into()]));
+        let conflict = |port| conflicts.iter().find(|conflict| conflict.port == port);
+
+        // the continuous task keeps running while the task that depends on it runs
+        assert_eq!(
+            conflict(free),
+            Some(&PortConflict {
+                port: free,
+                task_ids: vec!["app-e2e:e2e".into(), "app:serve".into()],
+                in_use: false,
+            })
+        );
+        assert_eq!(
+            conflict(in_use),
+            Some(&PortConflict {
+                port: in_use,
+                task_ids: vec!["docs:serve".into()],
+                in_use: true,
+            })
+        );
+        if is_port_free(free + 1, None) {
+            assert_eq!(conflict(free + 1), None);
+        }
+    }
+}
@@ -0,0 +1,719 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+use crossbeam_channel::unbounded;
+use tracing::{debug, trace};
+
+use crate::native::tasks::types::TaskGraph;
+
+#[napi(string_enum)]
+#[derive(Debug)]
+pub enum TaskRunStatus {
+    #[allow(non_camel_case_types)]
+    started,
+    #[allow(non_camel_case_types)]
+    success,
+    #[allow(non_camel_case_types)]
+    failure,
+    /// The task did not run, because one of its dependencies failed or the run was bailed
+    #[allow(non_camel_case_types)]
+    skipped,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct TaskLifecycleEvent {
+    pub task_id: String,
+    pub status: TaskRunStatus,
+    /// The exit code of the command, once the task has finished
+    pub code: Option<i32>,
+    /// How long the task ran in milliseconds, once the task has finished
+    pub duration: Option<f64>,
+}
+
+#[napi(object)]
+#[derive(Clone)]
+pub struct TaskCommand {
+    pub command: String,
+    pub cwd: Option<String>,
+    pub env: Option<HashMap<String, String>>,
+}
+
+/// How long running the tasks of a graph on `parallel` threads is estimated to take in milliseconds, when no task
+/// fails, with the estimated durations of the tasks. Used to print how long a run will take before it starts
+#[napi]
+pub fn estimate_task_graph_duration(
+    task_graph: TaskGraph,
+    estimated_durations: HashMap<String, f64>,
+    parallel: u32,
+) -> anyhow::Result<f64> {
+    let dependencies = task_dependencies(&task_graph);
+    validate_dependencies(&dependencies)?;
+    let durations = task_durations(&dependencies, &estimated_durations);
+    let priorities = critical_path_lengths(&dependencies, &durations);
+    Ok(simulate_run(
+        &dependencies,
+        &priorities,
+        &durations,
+        (parallel as usize).max(1),
+    ))
+}
+
+/// The priority of every task of a graph: the length of the longest chain of tasks that waits for it, including itself.
+/// The orchestrator of `nx run` starts the ready tasks on the critical path first with it
+#[napi]
+pub fn get_task_graph_priorities(
+    task_graph: TaskGraph,
+    estimated_durations: Option<HashMap<String, f64>>,
+) -> anyhow::Result<HashMap<String, f64>> {
+    let dependencies = task_dependencies(&task_graph);
+    validate_dependencies(&dependencies)?;
+    let durations = task_durations(&dependencies, &estimated_durations.unwrap_or_default());
+    Ok(critical_path_lengths(&dependencies, &durations))
+}
+
+/// Exit code and duration of a task that has run
+pub(crate) struct TaskRunResult {
+    pub code: i32,
+    pub duration: f64,
+}
+
+/// The shell command that runs the command of a task, in its directory and with its environment
+pub(crate) fn shell_command(task_command: &TaskCommand) -> Command {
+    #[cfg(windows)]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(&task_command.command);
+        command
+    };
+    #[cfg(not(windows))]
+    let mut command = {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(&task_command.command);
+        command
+    };
+
+    if let Some(cwd) = &task_command.cwd {
+        command.current_dir(cwd);
+    }
+    if let Some(env) = &task_command.env {
+        command.envs(env);
+    }
+    command.stdin(Stdio::null());
+    command
+}
+
+/// The dependencies of every task of the graph
+fn task_dependencies(task_graph: &TaskGraph) -> HashMap<String, Vec<String>> {
+    task_graph
+        .tasks
+        .keys()
+        .map(|task_id| {
+            let task_dependencies = task_graph
+                .dependencies
+                .get(task_id)
+                .cloned()
+                .unwrap_or_default();
+            (task_id.clone(), task_dependencies)
+        })
+        .collect()
+}
+
+/// Makes sure every dependency is a task of the graph and that the graph has no cycles
+fn validate_dependencies(dependencies: &HashMap<String, Vec<String>>) -> anyhow::Result<()> {
+    let mut remaining = HashMap::new();
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (task_id, task_dependencies) in dependencies {
+        for dependency in task_dependencies {
+            if !dependencies.contains_key(dependency) {
+                anyhow::bail!(
+                    "{} depends on {}, which is not in the task graph",
+                    task_id,
+                    dependency
+                );
+            }
+            dependents
+                .entry(dependency.as_str())
+                .or_default()
+                .push(task_id.as_str());
+        }
+        remaining.insert(task_id.as_str(), task_dependencies.len());
+    }
+
+    let mut ready = remaining
+        .iter()
+        .filter(|(_, count)| **count == 0)
+        .map(|(task_id, _)| *task_id)
+        .collect::<VecDeque<_>>();
+    let mut visited = 0;
+    while let Some(task_id) = ready.pop_front() {
+        visited += 1;
+        for dependent in dependents.get(task_id).into_iter().flatten() {
+            let count = remaining.get_mut(dependent).expect("dependents are tasks");
+            *count -= 1;
+            if *count == 0 {
+                ready.push_back(*dependent);
+            }
+        }
+    }
+
+    if visited != dependencies.len() {
+        let mut in_cycle = remaining
+            .into_iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(task_id, _)| task_id)
+            .collect::<Vec<_>>();
+        in_cycle.sort();
+        anyhow::bail!("the task graph has a cycle between {}", in_cycle.join(", "));
+    }
+    Ok(())
+}
+
+/// The estimated duration of every task. Tasks without an estimate have the average estimate, or 1 without estimates
+fn task_durations(
+    dependencies: &HashMap<String, Vec<String>>,
+    estimated_durations: &HashMap<String, f64>,
+) -> HashMap<String, f64> {
+    let estimates = dependencies
+        .keys()
+        .filter_map(|task_id| estimated_durations.get(task_id))
+        .collect::<Vec<_>>();
+    let default_duration = if estimates.is_empty() {
+        1.0
+    } else {
+        estimates.iter().copied().sum::<f64>() / estimates.len() as f64
+    };
+    dependencies
+        .keys()
+        .map(|task_id| {
+            let duration = estimated_durations
+                .get(task_id)
+                .copied()
+                .unwrap_or(default_duration);
+            (task_id.clone(), duration)
+        })
+        .collect()
+}
+
+/// The length of the longest chain of tasks that starts with each task and continues with the tasks that depend on it,
+/// weighted by their durations. Starting the tasks with the longest chains first shortens the runs of graphs whose
+/// branches are unbalanced
+fn critical_path_lengths(
+    dependencies: &HashMap<String, Vec<String>>,
+    durations: &HashMap<String, f64>,
+) -> HashMap<String, f64> {
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut remaining: HashMap<&str, usize> = dependencies
+        .keys()
+        .map(|task_id| (task_id.as_str(), 0))
+        .collect();
+    for (task_id, task_dependencies) in dependencies {
+        for dependency in task_dependencies {
+            if let Some(count) = remaining.get_mut(dependency.as_str()) {
+                *count += 1;
+                dependents
+                    .entry(dependency.as_str())
+                    .or_default()
+                    .push(task_id.as_str());
+            }
+        }
+    }
+
+    // the tasks that no task depends on come first, then the tasks whose dependents all have a length
+    let mut ready = remaining
+        .iter()
+        .filter(|(_, count)| **count == 0)
+        .map(|(task_id, _)| *task_id)
+        .collect::<Vec<_>>();
+    let mut lengths: HashMap<String, f64> = HashMap::with_capacity(dependencies.len());
+    while let Some(task_id) = ready.pop() {
+        let longest_dependent = dependents
+            .get(task_id)
+            .into_iter()
+            .flatten()
+            .map(|dependent| lengths[*dependent])
+            .fold(0.0, f64::max);
+        let duration = durations.get(task_id).copied().unwrap_or_default();
+        lengths.insert(task_id.to_string(), duration + longest_dependent);
+        for dependency in &dependencies[task_id] {
+            if let Some(count) = remaining.get_mut(dependency.as_str()) {
+                *count -= 1;
+                if *count == 0 {
+                    ready.push(dependency.as_str());
+                }
+            }
+        }
+    }
+    lengths
+}
+
+/// A task that can start, the tasks with the highest priority start first, then the longest tasks,
+/// then in the order of their ids
+#[derive(PartialEq)]
+struct ReadyTask<'a> {
+    priority: f64,
+    duration: f64,
+    task_id: &'a str,
+}
+
+impl<'a> ReadyTask<'a> {
+    fn new(
+        task_id: &'a str,
+        priorities: &HashMap<String, f64>,
+        durations: &HashMap<String, f64>,
+    ) -> Self {
+        Self {
+            priority: priorities.get(task_id).copied().unwrap_or_default(),
+            duration: durations.get(task_id).copied().unwrap_or_default(),
+            task_id,
+        }
+    }
+}
+
+impl Eq for ReadyTask<'_> {}
+
+impl PartialOrd for ReadyTask<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ReadyTask<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .total_cmp(&other.priority)
+            .then_with(|| self.duration.total_cmp(&other.duration))
+            .then_with(|| other.task_id.cmp(self.task_id))
+    }
+}
+
+/// A task of a simulated run, the task that finishes first comes first
+#[derive(PartialEq)]
+struct RunningTask<'a> {
+    end: f64,
+    task_id: &'a str,
+}
+
+impl Eq for RunningTask<'_> {}
+
+impl PartialOrd for RunningTask<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RunningTask<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .end
+            .total_cmp(&self.end)
+            .then_with(|| other.task_id.cmp(self.task_id))
+    }
+}
+
+/// Simulates the way `schedule` runs the tasks, with their estimated durations instead of their commands,
+/// and returns when the last task finishes
+fn simulate_run(
+    dependencies: &HashMap<String, Vec<String>>,
+    priorities: &HashMap<String, f64>,
+    durations: &HashMap<String, f64>,
+    parallel: usize,
+) -> f64 {
+    let mut remaining: HashMap<&str, usize> = HashMap::new();
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (task_id, task_dependencies) in dependencies {
+        remaining.insert(task_id.as_str(), task_dependencies.len());
+        for dependency in task_dependencies {
+            dependents
+                .entry(dependency.as_str())
+                .or_default()
+                .push(task_id.as_str());
+        }
+    }
+
+    let mut ready = remaining
+        .iter()
+        .filter(|(_, count)| **count == 0)
+        .map(|(task_id, _)| ReadyTask::new(task_id, priorities, durations))
+        .collect::<BinaryHeap<_>>();
+    let mut running = BinaryHeap::new();
+    let mut now = 0.0;
+    loop {
+        while running.len() < parallel {
+            let Some(ReadyTask {
+                task_id, duration, ..
+            }) = ready.pop()
+            else {
+                break;
+            };
+            running.push(RunningTask {
+                end: now + duration,
+                task_id,
+            });
+        }
+        let Some(RunningTask { end, task_id }) = running.pop() else {
+            break;
+        };
+        now = end;
+        for dependent in dependents.get(task_id).into_iter().flatten() {
+            if let Some(count) = remaining.get_mut(dependent) {
+                *count -= 1;
+                if *count == 0 {
+                    ready.push(ReadyTask::new(dependent, priorities, durations));
+                }
+            }
+        }
+    }
+    now
+}
+
+/// Runs the tasks on a work-stealing pool of `parallel` threads, starting each task once its dependencies succeeded.
+/// The scheduling itself happens on the calling thread, which only waits for tasks to finish.
+/// When more tasks are ready than threads are free, the tasks with the highest priority start first, then the longest
+/// tasks, so a long task does not start last and run alone
+pub(crate) fn schedule<R, E>(
+    dependencies: &HashMap<String, Vec<String>>,
+    priorities: &HashMap<String, f64>,
+    durations: &HashMap<String, f64>,
+    parallel: usize,
+    bail: bool,
+    run: R,
+    on_event: E,
+) -> anyhow::Result<HashMap<String, TaskRunStatus>>
+where
+    R: Fn(&str) -> TaskRunResult + Send + Sync + 'static,
+    E: Fn(TaskLifecycleEvent),
+{
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(parallel)
+        .thread_name(|i| format!("nx-task-{}", i))
+        .build()?;
+    let run = Arc::new(run);
+
+    let mut remaining: HashMap<&str, usize> = HashMap::new();
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (task_id, task_dependencies) in dependencies {
+        remaining.insert(task_id.as_str(), task_dependencies.len());
+        for dependency in task_dependencies {
+            dependents
+                .entry(dependency.as_str())
+                .or_default()
+                .push(task_id.as_str());
+        }
+    }
+
+    let mut ready = remaining
+        .iter()
+        .filter(|(_, count)| **count == 0)
+        .map(|(task_id, _)| ReadyTask::new(task_id, priorities, durations))
+        .collect::<BinaryHeap<_>>();
+
+    let (sender, receiver) = unbounded::<(String, TaskRunResult)>();
+    let mut statuses: HashMap<String, TaskRunStatus> = HashMap::new();
+    let mut running = 0;
+    let mut bailed = false;
+
+    loop {
+        if bailed {
+            ready.clear();
+        }
+        // tasks are only started when a thread is free, so the next ones are picked by their priority
+        while running < parallel {
+            let Some(ReadyTask { task_id, .. }) = ready.pop() else {
+                break;
+            };
+            trace!("starting {}", task_id);
+            on_event(TaskLifecycleEvent {
+                task_id: task_id.to_string(),
+                status: TaskRunStatus::started,
+                code: None,
+                duration: None,
+            });
+            running += 1;
+            let task_id = task_id.to_string();
+            let run = Arc::clone(&run);
+            let sender = sender.clone();
+            pool.spawn(move || {
+                let result = run(&task_id);
+                sender.send((task_id, result)).ok();
+            });
+        }
+
+        if running == 0 {
+            break;
+        }
+
+        let (task_id, result) = receiver.recv()?;
+        running -= 1;
+        let succeeded = result.code == 0;
+        trace!("{} finished with {}", task_id, result.code);
+        on_event(TaskLifecycleEvent {
+            task_id: task_id.clone(),
+            status: if succeeded {
+                TaskRunStatus::success
+            } else {
+                TaskRunStatus::failure
+            },
+            code: Some(result.code),
+            duration: Some(result.duration),
+        });
+
+        let task_dependents = dependents
+            .get(task_id.as_str())
+            .cloned()
+            .unwrap_or_default();
+        if succeeded {
+            statuses.insert(task_id, TaskRunStatus::success);
+            ready.extend(
+                task_dependents
+                    .into_iter()
+                    .filter(|dependent| {
+                        remaining.get_mut(dependent).is_some_and(|count| {
+                            *count -= 1;
+                            *count == 0
+                        })
+                    })
+                    .map(|dependent| ReadyTask::new(dependent, priorities, durations)),
+            );
+        } else {
+            statuses.insert(task_id, TaskRunStatus::failure);
+            bailed = bail;
+            // dependents of a failed task are never ready, so they are skipped at the end
+            for dependent in task_dependents {
+                remaining.remove(dependent);
+            }
+        }
+    }
+
+    // everything that did not run was skipped, because of a failed dependency or because the run was bailed
+    let mut skipped = dependencies
+        .keys()
+        .filter(|task_id| !statuses.contains_key(*task_id))
+        .cloned()
+        .collect::<Vec<_>>();
+    skipped.sort();
+    for task_id in skipped {
+        on_event(TaskLifecycleEvent {
+            task_id: task_id.clone(),
+            status: TaskRunStatus::skipped,
+            code: None,
+            duration: None,
+        });
+        statuses.insert(task_id, TaskRunStatus::skipped);
+    }
+
+    debug!("ran {} tasks", statuses.len());
+    Ok(statuses)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    use super::*;
+
+    fn graph(edges: &[(&str, &[&str])]) -> HashMap<String, Vec<String>> {
+        edges
+            .iter()
+            .map(|(task_id, dependencies)| {
+                (
+                    task_id.to_string(),
+                    dependencies.iter().map(|d| d.to_string()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    fn status_of(statuses: &HashMap<String, TaskRunStatus>, task_id: &str) -> String {
+        format!("{:?}", statuses[task_id])
+    }
+
+    #[test]
+    fn should_run_tasks_after_their_dependencies() {
+        let dependencies = graph(&[
+            ("app:build", &["lib:build", "util:build"]),
+            ("lib:build", &["util:build"]),
+            ("util:build", &[]),
+        ]);
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&events);
+
+        let statuses = schedule(
+            &dependencies,
+            &HashMap::new(),
+            &HashMap::new(),
+            4,
+            false,
+            |_| TaskRunResult {
+                code: 0,
+                duration: 0.0,
+            },
+            move |event| {
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push(format!("{}:{:?}", event.task_id, event.status))
+            },
+        )
+        .unwrap();
+
+        assert_eq!(status_of(&statuses, "app:build"), "success");
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                "util:build:started",
+                "util:build:success",
+                "lib:build:started",
+                "lib:build:success",
+                "app:build:started",
+                "app:build:success",
+            ]
+        );
+    }
+
+    #[test]
+    fn should_skip_dependents_of_failed_tasks() {
+        let dependencies = graph(&[("a", &["b"]), ("b", &[]), ("c", &["a"]), ("d", &[])]);
+
+        let statuses = schedule(
+            &dependencies,
+            &HashMap::new(),
+            &HashMap::new(),
+            2,
+            false,
+            |task_id| TaskRunResult {
+                code: if task_id == "b" { 1 } else { 0 },
+                duration: 0.0,
+            },
+            |_| {},
+        )
+        .unwrap();
+
+        assert_eq!(status_of(&statuses, "b"), "failure");
+        assert_eq!(status_of(&statuses, "a"), "skipped");
+        assert_eq!(status_of(&statuses, "c"), "skipped");
+        assert_eq!(status_of(&statuses, "d"), "success");
+    }
+
+    #[test]
+    fn should_respect_the_parallel_limit() {
+        let dependencies = (0..20)
+            .map(|i| (format!("task-{}", i), vec![]))
+            .collect::<HashMap<_, _>>();
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let (running_clone, max_clone) = (Arc::clone(&running), Arc::clone(&max_running));
+
+        schedule(
+            &dependencies,
+            &HashMap::new(),
+            &HashMap::new(),
+            3,
+            false,
+            move |_| {
+                let now = running_clone.fetch_add(1, Ordering::SeqCst) + 1;
+                max_clone.fetch_max(now, Ordering::SeqCst);
+                std::thread::sleep(std::time::Duration::from_millis(5));
+                running_clone.fetch_sub(1, Ordering::SeqCst);
+                TaskRunResult {
+                    code: 0,
+                    duration: 5.0,
+                }
+            },
+            |_| {},
+        )
+        .unwrap();
+
+        assert!(max_running.load(Ordering::SeqCst) <= 3);
+    }
+
+    #[test]
+    fn should_start_the_tasks_on_the_critical_path_first() {
+        let dependencies = graph(&[
+            ("app:e2e", &["app:build"]),
+            ("app:build", &["lib:build"]),
+            ("lib:build", &[]),
+            ("docs:build", &[]),
+            ("api:build", &[]),
+        ]);
+        let estimates = [
+            ("app:e2e", 5000.0),
+            ("app:build", 1000.0),
+            ("lib:build", 1000.0),
+            ("api:build", 3000.0),
+        ]
+        .into_iter()
+        .map(|(task_id, duration)| (task_id.to_string(), duration))
+        .collect();
+        let durations = task_durations(&dependencies, &estimates);
+        let priorities = critical_path_lengths(&dependencies, &durations);
+        assert_eq!(priorities["lib:build"], 7000.0);
+        assert_eq!(priorities["api:build"], 3000.0);
+        // tasks without an estimate weigh the average estimate
+        assert_eq!(priorities["docs:build"], 2500.0);
+
+        let started = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&started);
+        schedule(
+            &dependencies,
+            &priorities,
+            &durations,
+            1,
+            false,
+            |_| TaskRunResult {
+                code: 0,
+                duration: 0.0,
+            },
+            move |event| {
+                if matches!(event.status, TaskRunStatus::started) {
+                    recorded.lock().unwrap().push(event.task_id);
+                }
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            *started.lock().unwrap(),
+            [
+                "lib:build",
+                "app:build",
+                "app:e2e",
+                "api:build",
+                "docs:build"
+            ]
+        );
+    }
+
+    #[test]
+    fn should_estimate_the_duration_of_the_run() {
+        // the longest task starts first, so the short ones run next to it instead of before it
+        let dependencies = graph(&[("a", &[]), ("b", &[]), ("c", &[])]);
+        let durations = [("a", 1.0), ("b", 1.0), ("c", 4.0)]
+            .into_iter()
+            .map(|(task_id, duration)| (task_id.to_string(), duration))
+            .collect();
+        let priorities = critical_path_lengths(&dependencies, &durations);
+        assert_eq!(simulate_run(&dependencies, &priorities, &durations, 2), 4.0);
+        assert_eq!(simulate_run(&dependencies, &priorities, &durations, 1), 6.0);
+
+        let dependencies = graph(&[("app", &["lib"]), ("lib", &[]), ("docs", &[])]);
+        let durations = task_durations(
+            &dependencies,
+            &[("app".to_string(), 4.0), ("lib".to_string(), 2.0)].into(),
+        );
+        assert_eq!(durations["docs"], 3.0);
+        let priorities = critical_path_lengths(&dependencies, &durations);
+        assert_eq!(simulate_run(&dependencies, &priorities, &durations, 2), 6.0);
+    }
+
+    #[test]
+    fn should_reject_cycles() {
+        let dependencies = graph(&[("a", &["b"]), ("b", &["a"]), ("c", &[])]);
+        let error = validate_dependencies(&dependencies).unwrap_err();
+        assert_eq!(error.to_string(), "the task graph has a cycle between a, b");
+        assert!(validate_dependencies(&graph(&[("a", &["missing"])])).is_err());
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use tracing::trace;
+
+use crate::native::tasks::types::Task;
+
+/// The files that are loaded into the environment of Nx itself when it starts
+const ROOT_ENV_FILES: [&str; 3] = [".env", ".local.env", ".env.local"];
+
+/// A variable of a .env file, like dotenv parses them: `KEY=value`, `export KEY=value` and `KEY: value`, with single,
+/// double or backtick quoted values which can span lines, and comments
+static ENV_LINE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?m)^\s*(?:export\s+)?([\w.-]+)(?:\s*=\s*?|:\s+?)(\s*'(?:\\'|[^'])*'|\s*"(?:\\"|[^"])*"|\s*`(?:\\`|[^`])*`|[^#\r\n]+)?\s*(?:#.*)?$"#,
+    )
+    .expect("the pattern is valid")
+});
+
+/// A reference to a variable in a value, like dotenv-expand expands them: `$VAR`, `${VAR}` and `${VAR:-default}`,
+/// or an escaped `\$`
+static ENV_REFERENCE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\\\$|\$\{(\w+)(?::?-([^}]*))?\}|\$(\w+)").expect("the pattern is valid")
+});
+
+#[napi]
+/// Resolves the environment of every task from the environment of Nx, by task id.
+///
+/// The variables that Nx loaded from the `.env`, `.local.env` and `.env.local` files of the workspace root when it
+/// started are removed first, so that they are layered like the other files. Then, when `load_dot_env_files` is set,
+/// the .env files of the task are loaded. A variable is taken from the first of these that sets it:
+///
+/// 1. the environment of Nx
+/// 2. the files of the project of the task, then the files of the workspace root, each in this order:
+///    1. `.env.[target].[configuration].local`, `.env.[target].[configuration]`, `.env.[configuration].local`,
+///       `.env.[configuration]`, `.[target].[configuration].local.env`, `.[target].[configuration].env`,
+///       `.[configuration].local.env` and `.[configuration].env`, when the task has a configuration
+///    2. `.env.[target].local`, `.env.[target]`, `.[target].local.env` and `.[target].env`
+///    3. `.env.local`, `.local.env` and `.env` in the project, `.local.env`, `.env.local` and `.env` in the workspace root
+///
+/// References to variables in the values of the files are expanded with the variables that are already set,
+/// then with the variables of the same file. Every file is read once for all the tasks
+pub fn get_task_envs(
+    workspace_root: String,
+    tasks: Vec<Task>,
+    env: HashMap<String, String>,
+    load_dot_env_files: bool,
+) -> HashMap<String, HashMap<String, String>> {
+    let workspace_root = Path::new(&workspace_root);
+    let mut files = EnvFiles::default();
+
+    let mut base_env = env;
+    for file in ROOT_ENV_FILES {
+        let mut loaded = HashMap::new();
+        load_env_file(&mut loaded, files.get(&workspace_root.join(file)));
+        base_env.retain(|key, value| loaded.get(key) != Some(&*value));
+    }
+
+    tasks
+        .into_iter()
+        .map(|task| {
+            let mut env = base_env.clone();
+            if load_dot_env_files {
+                for file in env_files_for_task(&task) {
+                    load_env_file(&mut env, files.get(&workspace_root.join(file)));
+                }
+            }
+            (task.id, env)
+        })
+        .collect()
+}
+
+/// The .env files of a task from the workspace root, from the file that takes precedence
+fn env_files_for_task(task: &Task) -> Vec<String> {
+    let target = &task.target.target;
+    let configuration_files = |configuration: &str| {
+        vec![
+            format!(".env.{}.{}.local", target, configuration),
+            format!(".env.{}.{}", target, configuration),
+            format!(".env.{}.local", configuration),
+            format!(".env.{}", configuration),
+            format!(".{}.{}.local.env", target, configuration),
+            format!(".{}.{}.env", target, configuration),
+            format!(".{}.local.env", configuration),
+            format!(".{}.env", configuration),
+        ]
+    };
+    let target_files = vec![
+        format!(".env.{}.local", target),
+        format!(".env.{}", target),
+        format!(".{}.local.env", target),
+        format!(".{}.env", target),
+    ];
+    let configuration = task.target.configuration.as_deref();
+
+    let mut project_files = configuration.map(configuration_files).unwrap_or_default();
+    project_files.extend(target_files.iter().cloned());
+    project_files.extend([".env.local".into(), ".local.env".into(), ".env".into()]);
+
+    let mut root_files = configuration.map(configuration_files).unwrap_or_default();
+    root_files.extend(target_files);
+    root_files.extend([".local.env".into(), ".env.local".into(), ".env".into()]);
+
+    let project_files = task
+        .project_root
+        .as_deref()
+        .into_iter()
+        .flat_map(|project_root| {
+            project_files
+                .iter()
+                .map(move |file| format!("{}/{}", project_root, file))
+        });
+    project_files.chain(root_files).collect()
+}
+
+/// The parsed .env files, which are shared by the tasks
+#[derive(Default)]
+struct EnvFiles(HashMap<PathBuf, Vec<(String, String)>>);
+
+impl EnvFiles {
+    /// The variables of a file, a file that cannot be read has none
+    fn get(&mut self, path: &Path) -> &[(String, String)] {
+        self.0.entry(path.to_path_buf()).or_insert_with(|| {
+            std::fs::read_to_string(path)
+                .map(|contents| {
+                    trace!("loading {:?}", path);
+                    parse_env_file(&contents)
+                })
+                .unwrap_or_default()
+        })
+    }
+}
+
+/// The variables of a .env file, in the order of the file. A variable that is set twice has its last value
+fn parse_env_file(contents: &str) -> Vec<(String, String)> {
+    let contents = contents.replace("\r\n", "\n");
+    let mut variables: Vec<(String, String)> = vec![];
+    for captures in ENV_LINE.captures_iter(&contents) {
+        let key = captures[1].to_string();
+        let value = unquote(captures.get(2).map_or("", |value| value.as_str()).trim());
+        match variables.iter_mut().find(|(existing, _)| *existing == key) {
+            Some((_, existing)) => *existing = value,
+            None => variables.push((key, value)),
+        }
+    }
+    variables
+}
+
+/// Removes the quotes of a value. Double quoted values can contain escaped newlines
+fn unquote(value: &str) -> String {
+    let quote = value.chars().next();
+    let quoted = value.len() >= 2
+        && matches!(quote, Some('\'' | '"' | '`'))
+        && value.ends_with(quote.unwrap_or_default());
+    if !quoted {
+        return value.to_string();
+    }
+    let value = &value[1..value.len() - 1];
+    if quote == Some('"') {
+        value.replace("\\n", "\n").replace("\\r", "\r")
+    } else {
+        value.to_string()
+    }
+}
+
+/// Loads the variables of a file into an environment, without overriding the variables that are already set
+fn load_env_file(env: &mut HashMap<String, String>, variables: &[(String, String)]) {
+    let mut expanded: Vec<(String, String)> = Vec::with_capacity(variables.len());
+    for (key, value) in variables {
+        if env.contains_key(key) {
+            continue;
+        }
+        expanded.push((key.clone(), expand(value, env, &expanded)));
+    }
+    env.extend(expanded);
+}
+
+/// Expands the references to variables in a value, with the variables that are set in the environment first,
+/// then with the previous variables of the file. Variables that are not set are empty, unless they have a default
+fn expand(value: &str, env: &HashMap<String, String>, file: &[(String, String)]) -> String {
+    let lookup = |key: &str| {
+        env.get(key)
+            .or_else(|| {
+                file.iter()
+                    .find(|(variable, _)| variable == key)
+                    .map(|(_, value)| value)
+            })
+            .filter(|value| !value.is_empty())
+    };
+    ENV_REFERENCE
+        .replace_all(value, |captures: &Captures| {
+            if let Some(key) = captures.get(1).or_else(|| captures.get(3)) {
+                lookup(key.as_str())
+                    .map(String::as_str)
+                    .or_else(|| captures.get(2).map(|default| default.as_str()))
+                    .unwrap_or_default()
+                    .to_string()
+            } else {
+                "$".to_string()
+            }
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    use super::*;
+    use crate::native::tasks::types::TaskTarget;
+
+    #[test]
+    fn should_parse_and_expand_env_files() {
+        let variables = parse_env_file(
+            "# comment\r\nexport HOST=localhost\nPORT = 4200 # inline comment\nURL=\"http://${HOST}:$PORT\\n\"\n\
+             MULTILINE='first\nsecond'\nPRICE=\\$5\nMISSING=${UNSET:-default}$UNSET\nPORT=4300\n",
+        );
+        assert_eq!(
+            variables
+                .iter()
+                .map(|(key, _)| key.as_str())
+                .collect::<Vec<_>>(),
+            ["HOST", "PORT", "URL", "MULTILINE", "PRICE", "MISSING"]
+        );
+
+        let mut env = HashMap::from([("HOST".to_string(), "0.0.0.0".to_string())]);
+        load_env_file(&mut env, &variables);
+        assert_eq!(env["HOST"], "0.0.0.0");
+        assert_eq!(env["PORT"], "4300");
+        assert_eq!(env["URL"], "http://0.0.0.0:4300\n");
+        assert_eq!(env["MULTILINE"], "first\nsecond");
+        assert_eq!(env["PRICE"], "$5");
+        assert_eq!(env["MISSING"], "default");
+    }
+
+    #[test]
+    fn should_layer_the_env_files_of_the_tasks() {
+        let temp = TempDir::new().unwrap();
+        temp.child(".env")
+            .write_str("ROOT=root\nNAME=root\nLOADED=root")
+            .unwrap();
+        temp.child(".env.build")
+            .write_str("NAME=root-build")
+            .unwrap();
+        temp.child("apps/app/.env.production")
+            .write_str("NAME=app-production\nURL=${NAME}.example.com")
+            .unwrap();
+        temp.child("apps/app/.env.build.local")
+            .write_str("NAME=app-build-local")
+            .unwrap();
+        let task = |id: &str, configuration: Option<&str>| Task {
+            id: id.to_string(),
+            target: TaskTarget {
+                project: "app".into(),
+                target: "build".into(),
+                configuration: configuration.map(Into::into),
+            },
+            outputs: vec![],
+            project_root: Some("apps/app".into()),
+        };
+
+        let envs = get_task_envs(
+            temp.display().to_string(),
+            vec![
+                task("app:build", None),
+                task("app:build:production", Some("production")),
+            ],
+            HashMap::from([
+                // loaded by Nx when it started
+                ("LOADED".to_string(), "root".to_string()),
+                ("USER_SET".to_string(), "user".to_string()),
+            ]),
+            true,
+        );
+
+        let build = &envs["app:build"];
+        assert_eq!(build["NAME"], "app-build-local");
+        assert_eq!(build["ROOT"], "root");
+        assert_eq!(build["USER_SET"], "user");
+        assert_eq!(build.get("URL"), None);
+        let production = &envs["app:build:production"];
+        assert_eq!(production["NAME"], "app-production");
+        assert_eq!(production["URL"], "app-production.example.com");
+
+        let envs = get_task_envs(
+            temp.display().to_string(),
+            vec![task("app:build", None)],
+            HashMap::from([("LOADED".to_string(), "root".to_string())]),
+            false,
+        );
+        assert!(envs["app:build"].is_empty());
+    }
+}
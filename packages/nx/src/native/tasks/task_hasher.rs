@@ -1,20 +1,29 @@
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
+use crate::native::utils::parallel::prelude::*;
 use crate::native::{
-    hasher::hash,
+    glob::GlobOptions,
+    hasher::{hash, HashAlgorithm},
+    lock_file::LockFile,
+    machine_id::platform,
     project_graph::{types::ProjectGraph, utils::create_project_root_mappings},
     tasks::types::HashInstruction,
     types::NapiDashMap,
+    utils::{start_work, NxPath},
 };
 use crate::native::{
     project_graph::utils::ProjectRootMappings,
-    tasks::hashers::{hash_env, hash_runtime, hash_workspace_files},
+    tasks::hashers::{hash_env, hash_runtime, hash_workspace_files, runtime_cache},
 };
 use crate::native::{
     tasks::hashers::{
-        hash_all_externals, hash_external, hash_project_config, hash_project_files,
-        hash_task_output, hash_tsconfig_selectively,
+        collect_project_files, collect_workspace_files, get_file_hash, hash_all_externals,
+        hash_external, hash_project_config, hash_project_files, hash_task_output,
+        hash_tsconfig_selectively, project_file_set_globs, workspace_file_set_globs, HashError,
+        HasherErrors, MissingFileHasher, WorkspaceFilesCache,
     },
     types::FileData,
     workspace::types::ProjectFiles,
@@ -22,8 +31,7 @@ use crate::native::{
 use anyhow::anyhow;
 use dashmap::DashMap;
 use napi::bindgen_prelude::{Buffer, External};
-use rayon::prelude::*;
-use tracing::{debug, trace, trace_span};
+use tracing::{debug, trace, trace_span, warn};
 
 #[napi(object)]
 #[derive(Debug)]
@@ -35,8 +43,57 @@ pub struct HashDetails {
 #[napi(object)]
 pub struct HasherOptions {
     pub selectively_hash_ts_config: bool,
+    /// Hash file contents from disk when a file does not have a hash (e.g. untracked files)
+    pub hash_missing_files: Option<bool>,
+    /// Apply negated file set globs in order, so later globs override earlier ones (like .gitignore).
+    /// Defaults to excluding every file matched by a negated glob, regardless of its position
+    pub ordered_negated_globs: Option<bool>,
+    /// The maximum time in milliseconds a runtime input command can take before hashing fails.
+    /// Defaults to no timeout
+    pub runtime_timeout: Option<u32>,
+    /// Hash external dependencies along with their transitive dependencies from the workspace lock file
+    pub hash_externals_from_lock_file: Option<bool>,
+    /// Mixed into the hash of every task (e.g. the `hashSalt` of nx.json), so changing it intentionally
+    /// invalidates every cached result, locally and in remote caches
+    pub hash_salt: Option<String>,
+    /// Mix the OS and the CPU architecture into the hash of every task, so results are not shared between platforms
+    pub include_platform: Option<bool>,
+    /// The algorithm of the hash of every task, computed from the hashes of its inputs. Defaults to xxh3.
+    /// The inputs themselves (e.g. the files of the workspace) are still identified by their xxh3 hashes
+    pub hash_algorithm: Option<HashAlgorithm>,
 }
 
+#[napi(object)]
+pub struct HashInputDetails {
+    pub input: String,
+    pub value: String,
+    /// The globs after resolving `{workspaceRoot}` and `{projectRoot}`. Empty for inputs that are not file sets
+    pub globs: Vec<String>,
+    /// The matched files along with the hashes that went into the input hash
+    pub files: Vec<FileData>,
+}
+
+/// A cached hash that can be persisted between processes.
+/// The fingerprint identifies the files the hash was computed from
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PersistedHash {
+    pub cache_key: String,
+    pub hash: String,
+    pub fingerprint: String,
+}
+
+/// The version of the hashing algorithm, mixed into the hash of every task and recorded with every cache entry.
+/// It has to be bumped whenever a change makes the same inputs hash differently, so that the results cached
+/// with the previous algorithm (and the persisted hashes of file sets) are not reused
+#[napi]
+pub const HASH_VERSION: u32 = 2;
+
+const WORKSPACE_FILE_SET_PREFIX: &str = "workspace:";
+const MISSING_FILE_PREFIX: &str = "file:";
+/// Follows the prefix of the workspace file sets whose globs are matched case insensitively
+const CASE_INSENSITIVE_PREFIX: &str = "caseInsensitive:";
+
 #[napi]
 pub struct TaskHasher {
     workspace_root: String,
@@ -46,9 +103,11 @@ pub struct TaskHasher {
     ts_config: Vec<u8>,
     ts_config_paths: HashMap<String, Vec<String>>,
     options: Option<HasherOptions>,
-    workspace_files_cache: Arc<DashMap<String, String>>,
+    workspace_files_cache: Arc<WorkspaceFilesCache>,
     external_cache: Arc<DashMap<String, String>>,
     runtime_cache: Arc<DashMap<String, String>>,
+    missing_files_cache: Arc<DashMap<String, String>>,
+    lock_file: Option<LockFile>,
 }
 #[napi]
 impl TaskHasher {
@@ -62,7 +121,14 @@ impl TaskHasher {
         ts_config_paths: HashMap<String, Vec<String>>,
         options: Option<HasherOptions>,
     ) -> Self {
+        let lock_file = options
+            .as_ref()
+            .and_then(|o| o.hash_externals_from_lock_file)
+            .unwrap_or(false)
+            .then(|| read_lock_file(&workspace_root))
+            .flatten();
         Self {
+            lock_file,
             workspace_root,
             project_graph,
             project_file_map,
@@ -70,38 +136,227 @@ impl TaskHasher {
             ts_config: ts_config.to_vec(),
             ts_config_paths,
             options,
-            workspace_files_cache: Arc::new(DashMap::new()),
+            workspace_files_cache: Arc::new(WorkspaceFilesCache::default()),
             external_cache: Arc::new(DashMap::new()),
-            runtime_cache: Arc::new(DashMap::new()),
+            runtime_cache: runtime_cache(),
+            missing_files_cache: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Updates the project graph and the files used for hashing after the workspace has changed.
+    /// Only the cached hashes that include one of the changed files are invalidated, so the hasher can be reused between file changes
+    #[napi]
+    pub fn update_files(
+        &mut self,
+        project_graph: External<ProjectGraph>,
+        project_file_map: External<ProjectFiles>,
+        all_workspace_files: External<Vec<FileData>>,
+        ts_config: Buffer,
+        ts_config_paths: HashMap<String, Vec<String>>,
+        changed_files: Vec<String>,
+    ) {
+        trace!(
+            "updating task hasher with {} changed files",
+            changed_files.len()
+        );
+        self.project_graph = project_graph;
+        self.project_file_map = project_file_map;
+        self.all_workspace_files = all_workspace_files;
+        self.ts_config = ts_config.to_vec();
+        self.ts_config_paths = ts_config_paths;
+
+        // the cached hashes are keyed by the paths of the file map, which only have `/` separators
+        let changed_files = changed_files
+            .iter()
+            .map(|file| NxPath::new(file).into_string())
+            .collect::<Vec<_>>();
+        let invalidated = self.workspace_files_cache.invalidate(&changed_files);
+        trace!("invalidated {} workspace file sets", invalidated);
+        for changed_file in &changed_files {
+            self.missing_files_cache.remove(changed_file);
+        }
+
+        // the external nodes of the project graph are created from the lock file
+        if changed_files.iter().any(|file| is_lock_file(file)) {
+            trace!("lock file changed, rehashing externals");
+            if self.lock_file.is_some() {
+                self.lock_file = read_lock_file(&self.workspace_root);
+            }
+            self.external_cache.clear();
         }
     }
 
+    /// Exports the cached hashes of workspace file sets and files without hashes so they can be persisted
+    #[napi]
+    pub fn export_cache(&self) -> anyhow::Result<Vec<PersistedHash>> {
+        let missing_file_hasher = self.missing_file_hasher();
+        let mut persisted = self
+            .workspace_files_cache
+            .entries()
+            .into_iter()
+            .filter(|(_, _, hash)| !hash.is_empty())
+            .map(|(globs, options, hash)| {
+                Ok(PersistedHash {
+                    cache_key: format!(
+                        "{}{}{}",
+                        WORKSPACE_FILE_SET_PREFIX,
+                        if options.case_insensitive {
+                            CASE_INSENSITIVE_PREFIX
+                        } else {
+                            ""
+                        },
+                        serde_json::to_string(&globs)?
+                    ),
+                    fingerprint: self.file_set_fingerprint(
+                        &globs,
+                        options,
+                        missing_file_hasher.as_ref(),
+                    ),
+                    hash,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        persisted.extend(self.missing_files_cache.iter().filter_map(|entry| {
+            if entry.value().is_empty() {
+                return None;
+            }
+            Some(PersistedHash {
+                cache_key: format!("{}{}", MISSING_FILE_PREFIX, entry.key()),
+                hash: entry.value().clone(),
+                fingerprint: file_fingerprint(&self.workspace_root, entry.key())?,
+            })
+        }));
+
+        Ok(persisted)
+    }
+
+    /// Restores hashes exported by a previous process. Only hashes whose fingerprint still matches the
+    /// current files of the file set (or the file on disk) are restored. Returns the number of restored hashes
+    #[napi]
+    pub fn import_cache(&self, persisted: Vec<PersistedHash>) -> u32 {
+        let ordered_negated_globs = self
+            .options
+            .as_ref()
+            .and_then(|o| o.ordered_negated_globs)
+            .unwrap_or(false);
+        // the files without hashes are restored first, so the fingerprints of the file sets can use them
+        let (file_sets, missing_files): (Vec<_>, Vec<_>) = persisted
+            .into_iter()
+            .partition(|p| p.cache_key.starts_with(WORKSPACE_FILE_SET_PREFIX));
+
+        let missing_file_hasher = self.missing_file_hasher();
+        let mut restored = 0;
+        for PersistedHash {
+            cache_key,
+            hash,
+            fingerprint,
+        } in missing_files.into_iter().chain(file_sets)
+        {
+            let is_restored = if hash.is_empty() {
+                false
+            } else if let Some(globs) = cache_key.strip_prefix(WORKSPACE_FILE_SET_PREFIX) {
+                let (globs, options) = match globs.strip_prefix(CASE_INSENSITIVE_PREFIX) {
+                    Some(globs) => (
+                        globs,
+                        GlobOptions {
+                            case_insensitive: true,
+                        },
+                    ),
+                    None => (globs, GlobOptions::default()),
+                };
+                serde_json::from_str::<Vec<String>>(globs)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|globs| {
+                        if self.file_set_fingerprint(&globs, options, missing_file_hasher.as_ref())
+                            != fingerprint
+                        {
+                            return Err(anyhow!("the files of the file set changed"));
+                        }
+                        self.workspace_files_cache
+                            .restore(globs, options, hash, ordered_negated_globs)
+                            .map_err(anyhow::Error::from)
+                    })
+                    .is_ok()
+            } else if let Some(file) = cache_key.strip_prefix(MISSING_FILE_PREFIX) {
+                let is_valid =
+                    file_fingerprint(&self.workspace_root, file).as_ref() == Some(&fingerprint);
+                if is_valid {
+                    self.missing_files_cache.insert(file.to_string(), hash);
+                }
+                is_valid
+            } else {
+                false
+            };
+
+            if is_restored {
+                restored += 1;
+            } else {
+                trace!("discarding persisted hash {}", cache_key);
+            }
+        }
+        debug!("restored {} persisted hashes", restored);
+        restored
+    }
+
+    /// Identifies the files a file set can match, so its persisted hash is only reused when none of them changed
+    fn file_set_fingerprint(
+        &self,
+        globs: &[String],
+        options: GlobOptions,
+        missing_file_hasher: Option<&MissingFileHasher>,
+    ) -> String {
+        let ordered_negated_globs = self
+            .options
+            .as_ref()
+            .and_then(|o| o.ordered_negated_globs)
+            .unwrap_or(false);
+        let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+        hasher.update(&HASH_VERSION.to_le_bytes());
+        hasher.update(&[ordered_negated_globs as u8]);
+        hasher.update(
+            self.workspace_files_cache
+                .fingerprint(
+                    globs,
+                    options,
+                    &self.all_workspace_files,
+                    missing_file_hasher,
+                )
+                .as_bytes(),
+        );
+        hasher.digest().to_string()
+    }
+
     #[napi]
     pub fn hash_plans(
         &self,
         hash_plans: External<HashMap<String, Vec<HashInstruction>>>,
         js_env: HashMap<String, String>,
-    ) -> anyhow::Result<NapiDashMap<String, HashDetails>> {
+    ) -> napi::Result<NapiDashMap<String, HashDetails>, HasherErrors> {
+        let _span = trace_span!("hash_plans", tasks = hash_plans.len()).entered();
+        let _work = start_work("hashing tasks").map_err(HashError::Internal)?;
         debug!("hashing plans {:?}", hash_plans.as_ref());
         trace!("plan length: {}", hash_plans.len());
         trace!("all workspace files: {}", self.all_workspace_files.len());
         trace!("project_file_map: {}", self.project_file_map.len());
 
-        let ts_config_hash = hash(&self.ts_config);
-        let project_root_mappings = create_project_root_mappings(&self.project_graph.nodes);
-
-        let mut sorted_externals = self.project_graph.external_nodes.keys().collect::<Vec<_>>();
-        sorted_externals.par_sort();
-
-        let selectively_hash_tsconfig = self
-            .options
-            .as_ref()
-            .map(|o| o.selectively_hash_ts_config)
-            .unwrap_or(false);
+        let context = self.hash_context();
 
         let hash_time = std::time::Instant::now();
 
         let hashes: NapiDashMap<String, HashDetails> = NapiDashMap::new();
+        let hash_salt = self.options.as_ref().and_then(|o| o.hash_salt.as_deref());
+        let platform = self
+            .options
+            .as_ref()
+            .and_then(|o| o.include_platform)
+            .unwrap_or(false)
+            .then(platform);
+        let hash_algorithm = self
+            .options
+            .as_ref()
+            .and_then(|o| o.hash_algorithm)
+            .unwrap_or_default();
 
         hash_plans
             .iter()
@@ -112,17 +367,8 @@ impl TaskHasher {
             })
             .par_bridge()
             .try_for_each(|(task_id, instruction)| {
-                let hash_detail = self.hash_instruction(
-                    task_id,
-                    instruction,
-                    HashInstructionArgs {
-                        js_env: &js_env,
-                        ts_config_hash: &ts_config_hash,
-                        project_root_mappings: &project_root_mappings,
-                        sorted_externals: &sorted_externals,
-                        selectively_hash_tsconfig,
-                    },
-                )?;
+                let hash_detail =
+                    self.hash_instruction(task_id, instruction, context.args(&js_env))?;
 
                 let mut entry = hashes
                     .entry(task_id.to_string())
@@ -132,18 +378,25 @@ impl TaskHasher {
                     });
 
                 entry.details.insert(hash_detail.0, hash_detail.1);
-                Ok::<(), anyhow::Error>(())
+                Ok::<(), HashError>(())
             })?;
 
         hashes.iter_mut().for_each(|mut h| {
             let hash_details = h.value_mut();
             let mut keys = hash_details.details.keys().collect::<Vec<_>>();
             keys.par_sort();
-            let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+            let mut hasher = hash_algorithm.hasher();
+            hasher.update(&HASH_VERSION.to_le_bytes());
+            if let Some(hash_salt) = hash_salt {
+                hasher.update(hash_salt.as_bytes());
+            }
+            if let Some(platform) = &platform {
+                hasher.update(platform.as_bytes());
+            }
             for key in keys {
                 hasher.update(hash_details.details[key].as_bytes());
             }
-            hash_details.value = hasher.digest().to_string();
+            hash_details.value = hasher.finish();
         });
 
         trace!("hashing took {:?}", hash_time.elapsed());
@@ -151,6 +404,140 @@ impl TaskHasher {
         Ok(hashes)
     }
 
+    /// Returns the hashes of directories of the workspace, computed from the names and hashes of every file below them
+    /// (the hashes of the file sets matching every file of a directory). A directory keeps its hash until a file
+    /// below it changes, so caches can tell which parts of the workspace changed. Directories without files are left out
+    #[napi]
+    pub fn hash_directories(&self, directories: Vec<String>) -> HashMap<String, String> {
+        let missing_file_hasher = self.missing_file_hasher();
+        directories
+            .into_iter()
+            .filter_map(|directory| {
+                let hash = self.workspace_files_cache.hash_directory(
+                    directory.trim_end_matches('/'),
+                    &self.all_workspace_files,
+                    missing_file_hasher.as_ref(),
+                )?;
+                Some((directory, hash))
+            })
+            .collect()
+    }
+
+    /// Resolves the globs, matched files and file hashes behind every input in the hash plan of a task,
+    /// so tooling can show exactly why two hashes of the same task differ
+    #[napi]
+    pub fn get_hash_details(
+        &self,
+        task_id: String,
+        hash_plans: External<HashMap<String, Vec<HashInstruction>>>,
+        js_env: HashMap<String, String>,
+    ) -> napi::Result<Vec<HashInputDetails>, HasherErrors> {
+        let instructions = hash_plans.get(&task_id).ok_or_else(|| {
+            HashError::Internal(anyhow!("task {} not found in hash plans", task_id))
+        })?;
+        let context = self.hash_context();
+
+        instructions
+            .par_iter()
+            .map(|instruction| {
+                let (input, value) =
+                    self.hash_instruction(&task_id, instruction, context.args(&js_env))?;
+                let (globs, files) = self.collect_instruction_files(instruction, &context)?;
+                let files = files
+                    .into_iter()
+                    .map(|file| FileData {
+                        file: file.file.clone(),
+                        hash: get_file_hash(file, context.missing_file_hasher.as_ref())
+                            .into_owned(),
+                    })
+                    .collect();
+                Ok::<_, HashError>(HashInputDetails {
+                    input,
+                    value,
+                    globs,
+                    files,
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map_err(napi::Error::from)
+    }
+
+    fn hash_context(&self) -> HashContext<'_> {
+        let ts_config_hash = hash(&self.ts_config);
+        let project_root_mappings = create_project_root_mappings(&self.project_graph.nodes);
+
+        let mut sorted_externals = self.project_graph.external_nodes.keys().collect::<Vec<_>>();
+        sorted_externals.par_sort();
+
+        let selectively_hash_tsconfig = self
+            .options
+            .as_ref()
+            .map(|o| o.selectively_hash_ts_config)
+            .unwrap_or(false);
+
+        let ordered_negated_globs = self
+            .options
+            .as_ref()
+            .and_then(|o| o.ordered_negated_globs)
+            .unwrap_or(false);
+
+        HashContext {
+            ts_config_hash,
+            project_root_mappings,
+            sorted_externals,
+            selectively_hash_tsconfig,
+            ordered_negated_globs,
+            missing_file_hasher: self.missing_file_hasher(),
+        }
+    }
+
+    fn missing_file_hasher(&self) -> Option<MissingFileHasher> {
+        self.options
+            .as_ref()
+            .and_then(|o| o.hash_missing_files)
+            .unwrap_or(false)
+            .then(|| {
+                MissingFileHasher::new(&self.workspace_root, Arc::clone(&self.missing_files_cache))
+            })
+    }
+
+    /// Returns the resolved globs and matched files of file set instructions. Other instructions do not have any files
+    fn collect_instruction_files(
+        &self,
+        instruction: &HashInstruction,
+        context: &HashContext,
+    ) -> Result<(Vec<String>, Vec<&FileData>), HashError> {
+        match instruction {
+            HashInstruction::WorkspaceFileSet(workspace_file_set, options) => {
+                let globs = workspace_file_set_globs(workspace_file_set);
+                let files = collect_workspace_files(
+                    &globs,
+                    &self.all_workspace_files,
+                    context.ordered_negated_globs,
+                    *options,
+                )?;
+                Ok((globs, files))
+            }
+            HashInstruction::ProjectFileSet(project_name, file_sets, options) => {
+                let project = self
+                    .project_graph
+                    .nodes
+                    .get(project_name)
+                    .ok_or_else(|| HashError::ProjectNotFound(project_name.to_string()))?;
+                let globs = project_file_set_globs(&project.root, file_sets);
+                let files = collect_project_files(
+                    project_name,
+                    &globs,
+                    &self.project_file_map,
+                    context.ordered_negated_globs,
+                    *options,
+                )?;
+                Ok((globs, files))
+            }
+            _ => Ok((vec![], vec![])),
+        }
+    }
+
     fn hash_instruction(
         &self,
         task_id: &str,
@@ -161,16 +548,21 @@ impl TaskHasher {
             project_root_mappings,
             sorted_externals,
             selectively_hash_tsconfig,
+            missing_file_hasher,
+            ordered_negated_globs,
         }: HashInstructionArgs,
-    ) -> anyhow::Result<(String, String)> {
+    ) -> Result<(String, String), HashError> {
         let now = std::time::Instant::now();
         let span = trace_span!("hashing", task_id).entered();
         let hash = match instruction {
-            HashInstruction::WorkspaceFileSet(workspace_file_set) => {
+            HashInstruction::WorkspaceFileSet(workspace_file_set, options) => {
                 let hashed_workspace_files = hash_workspace_files(
                     workspace_file_set,
                     &self.all_workspace_files,
                     Arc::clone(&self.workspace_files_cache),
+                    missing_file_hasher,
+                    ordered_negated_globs,
+                    *options,
                 );
                 trace!(parent: &span, "hash_workspace_files: {:?}", now.elapsed());
                 hashed_workspace_files?
@@ -181,6 +573,10 @@ impl TaskHasher {
                     runtime,
                     js_env,
                     Arc::clone(&self.runtime_cache),
+                    self.options
+                        .as_ref()
+                        .and_then(|o| o.runtime_timeout)
+                        .map(|timeout| Duration::from_millis(timeout.into())),
                 )?;
                 trace!(parent: &span, "hash_runtime: {:?}", now.elapsed());
                 hashed_runtime
@@ -190,17 +586,20 @@ impl TaskHasher {
                 trace!(parent: &span, "hash_env: {:?}", now.elapsed());
                 hashed_env
             }
-            HashInstruction::ProjectFileSet(project_name, file_sets) => {
+            HashInstruction::ProjectFileSet(project_name, file_sets, options) => {
                 let project = self
                     .project_graph
                     .nodes
                     .get(project_name)
-                    .ok_or_else(|| anyhow!("project {} not found", project_name))?;
+                    .ok_or_else(|| HashError::ProjectNotFound(project_name.to_string()))?;
                 let hashed_project_files = hash_project_files(
                     project_name,
                     &project.root,
                     file_sets,
                     &self.project_file_map,
+                    missing_file_hasher,
+                    ordered_negated_globs,
+                    *options,
                 )?;
                 trace!(parent: &span, "hash_project_files: {:?}", now.elapsed());
                 hashed_project_files
@@ -247,6 +646,7 @@ impl TaskHasher {
                     external,
                     &self.project_graph.external_nodes,
                     Arc::clone(&self.external_cache),
+                    self.lock_file.as_ref(),
                 )?;
                 trace!(parent: &span, "hash_external: {:?}", now.elapsed());
                 hashed_external
@@ -256,6 +656,7 @@ impl TaskHasher {
                     sorted_externals,
                     &self.project_graph.external_nodes,
                     Arc::clone(&self.external_cache),
+                    self.lock_file.as_ref(),
                 )?;
                 trace!(parent: &span, "hash_all_externals: {:?}", now.elapsed());
                 hashed_all_externals
@@ -265,10 +666,313 @@ impl TaskHasher {
     }
 }
 
+fn read_lock_file(workspace_root: &str) -> Option<LockFile> {
+    LockFile::read(workspace_root)
+        .inspect_err(|e| warn!("unable to read the lock file: {}", e))
+        .ok()
+        .flatten()
+}
+
+/// Identifies the contents of a file on disk by its modification time and size
+fn file_fingerprint(workspace_root: &str, file: &str) -> Option<String> {
+    let metadata = std::fs::metadata(Path::new(workspace_root).join(file)).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?;
+    Some(format!("{}:{}", modified.as_nanos(), metadata.len()))
+}
+
+fn is_lock_file(file: &str) -> bool {
+    matches!(file, "package-lock.json" | "yarn.lock" | "pnpm-lock.yaml")
+}
+
+/// Everything computed once per call that is shared between the hashed instructions
+struct HashContext<'a> {
+    ts_config_hash: String,
+    project_root_mappings: ProjectRootMappings,
+    sorted_externals: Vec<&'a String>,
+    selectively_hash_tsconfig: bool,
+    ordered_negated_globs: bool,
+    missing_file_hasher: Option<MissingFileHasher>,
+}
+
+impl<'a> HashContext<'a> {
+    fn args<'b>(&'b self, js_env: &'b HashMap<String, String>) -> HashInstructionArgs<'b> {
+        HashInstructionArgs {
+            js_env,
+            ts_config_hash: &self.ts_config_hash,
+            project_root_mappings: &self.project_root_mappings,
+            sorted_externals: &self.sorted_externals,
+            selectively_hash_tsconfig: self.selectively_hash_tsconfig,
+            missing_file_hasher: self.missing_file_hasher.as_ref(),
+            ordered_negated_globs: self.ordered_negated_globs,
+        }
+    }
+}
+
 struct HashInstructionArgs<'a> {
     js_env: &'a HashMap<String, String>,
     ts_config_hash: &'a str,
     project_root_mappings: &'a ProjectRootMappings,
     sorted_externals: &'a [&'a String],
     selectively_hash_tsconfig: bool,
+    missing_file_hasher: Option<&'a MissingFileHasher>,
+    ordered_negated_globs: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::native::project_graph::types::{Project, Target};
+    use crate::native::tasks::hash_planner::HashPlanner;
+    use crate::native::tasks::hashers::file_set_cache_key;
+    use crate::native::tasks::types::{Task, TaskGraph, TaskTarget};
+    use crate::native::types::{FileSetInput, NxJson};
+    use napi::bindgen_prelude::Either7;
+
+    fn file(file: &str, hash: &str) -> FileData {
+        FileData {
+            file: file.into(),
+            hash: hash.into(),
+        }
+    }
+
+    fn project_graph() -> External<ProjectGraph> {
+        External::new(ProjectGraph {
+            nodes: HashMap::new(),
+            dependencies: HashMap::new(),
+            external_nodes: HashMap::new(),
+        })
+    }
+
+    fn hash_config_files(hasher: &TaskHasher) -> String {
+        let context = hasher.hash_context();
+        let js_env = HashMap::new();
+        let (_, hash) = hasher
+            .hash_instruction(
+                "app:build",
+                &HashInstruction::WorkspaceFileSet(
+                    vec!["{workspaceRoot}/config/**".into()],
+                    GlobOptions::default(),
+                ),
+                context.args(&js_env),
+            )
+            .unwrap();
+        hash
+    }
+
+    fn update_files(hasher: &mut TaskHasher, files: Vec<FileData>, changed_files: &[&str]) {
+        hasher.update_files(
+            project_graph(),
+            External::new(HashMap::new()),
+            External::new(files),
+            Buffer::from(b"{}".to_vec()),
+            HashMap::new(),
+            changed_files.iter().map(|f| f.to_string()).collect(),
+        );
+    }
+
+    fn task_hasher(files: Vec<FileData>) -> TaskHasher {
+        TaskHasher::new(
+            "/workspace".into(),
+            project_graph(),
+            External::new(HashMap::new()),
+            External::new(files),
+            Buffer::from(b"{}".to_vec()),
+            HashMap::new(),
+            None,
+        )
+    }
+
+    #[test]
+    fn should_rehash_the_file_sets_of_changed_files() {
+        let mut hasher = task_hasher(vec![file("config/a.json", "a"), file("config/b.json", "b")]);
+        let hash = hash_config_files(&hasher);
+
+        // the cached hash is kept while none of its files changed
+        update_files(
+            &mut hasher,
+            vec![file("config/a.json", "a"), file("config/b.json", "c")],
+            &["other.json"],
+        );
+        assert_eq!(hash_config_files(&hasher), hash);
+
+        update_files(
+            &mut hasher,
+            vec![file("config/a.json", "a"), file("config/b.json", "c")],
+            &["config/b.json"],
+        );
+        let updated_hash = hash_config_files(&hasher);
+        assert_ne!(updated_hash, hash);
+
+        update_files(
+            &mut hasher,
+            vec![file("config/a.json", "a"), file("config/b.json", "b")],
+            &["config/b.json"],
+        );
+        assert_eq!(hash_config_files(&hasher), hash);
+    }
+
+    #[test]
+    fn should_only_restore_the_file_sets_whose_files_did_not_change() {
+        let hasher = task_hasher(vec![file("config/a.json", "a"), file("other/b.json", "b")]);
+        hash_config_files(&hasher);
+        let persisted = hasher.export_cache().unwrap();
+        assert_eq!(persisted.len(), 1);
+
+        let unrelated_change =
+            task_hasher(vec![file("config/a.json", "a"), file("other/b.json", "c")]);
+        assert_eq!(unrelated_change.import_cache(persisted.clone()), 1);
+
+        let related_change =
+            task_hasher(vec![file("config/a.json", "c"), file("other/b.json", "b")]);
+        assert_eq!(related_change.import_cache(persisted), 0);
+    }
+
+    #[test]
+    fn should_not_restore_empty_hashes() {
+        let hasher = task_hasher(vec![file("config/a.json", "a")]);
+        hash_config_files(&hasher);
+        let mut persisted = hasher.export_cache().unwrap();
+        persisted[0].hash = String::new();
+
+        assert_eq!(
+            task_hasher(vec![file("config/a.json", "a")]).import_cache(persisted),
+            0
+        );
+    }
+
+    #[test]
+    fn should_match_the_file_sets_of_case_insensitive_inputs() {
+        let project_graph = || {
+            let file_set = |fileset: &str| {
+                Either7::C(FileSetInput {
+                    fileset: fileset.into(),
+                    case_insensitive: Some(true),
+                })
+            };
+            External::new(ProjectGraph {
+                nodes: HashMap::from([(
+                    "app".into(),
+                    Project {
+                        root: "apps/app".into(),
+                        targets: HashMap::from([(
+                            "build".into(),
+                            Target {
+                                inputs: Some(vec![
+                                    file_set("{workspaceRoot}/Dockerfile"),
+                                    file_set("{projectRoot}/Dockerfile"),
+                                ]),
+                                ..Default::default()
+                            },
+                        )]),
+                        ..Default::default()
+                    },
+                )]),
+                dependencies: HashMap::from([("app".into(), vec![])]),
+                external_nodes: HashMap::new(),
+            })
+        };
+        let task_graph = TaskGraph {
+            roots: vec!["app:build".into()],
+            tasks: HashMap::from([(
+                "app:build".into(),
+                Task {
+                    id: "app:build".into(),
+                    target: TaskTarget {
+                        project: "app".into(),
+                        target: "build".into(),
+                        configuration: None,
+                    },
+                    outputs: vec![],
+                    project_root: Some("apps/app".into()),
+                },
+            )]),
+            dependencies: HashMap::new(),
+        };
+        let plans = HashPlanner::new(NxJson { named_inputs: None }, project_graph())
+            .get_plans_internal(vec!["app:build"], task_graph)
+            .unwrap();
+        let case_insensitive = GlobOptions {
+            case_insensitive: true,
+        };
+        let workspace_file_set = HashInstruction::WorkspaceFileSet(
+            vec!["{workspaceRoot}/Dockerfile".into()],
+            case_insensitive,
+        );
+        let project_file_set = HashInstruction::ProjectFileSet(
+            "app".into(),
+            vec!["{projectRoot}/Dockerfile".into()],
+            case_insensitive,
+        );
+        assert!(plans["app:build"].contains(&workspace_file_set));
+        assert!(plans["app:build"].contains(&project_file_set));
+
+        let task_hasher = |dockerfile_hash: &str| {
+            TaskHasher::new(
+                "/workspace".into(),
+                project_graph(),
+                External::new(HashMap::from([(
+                    "app".into(),
+                    vec![file("apps/app/DOCKERFILE", "app")],
+                )])),
+                External::new(vec![
+                    file("apps/app/DOCKERFILE", "app"),
+                    file("dockerfile", dockerfile_hash),
+                ]),
+                Buffer::from(b"{}".to_vec()),
+                HashMap::new(),
+                None,
+            )
+        };
+        let hasher = task_hasher("a");
+        let context = hasher.hash_context();
+        let (_, files) = hasher
+            .collect_instruction_files(&workspace_file_set, &context)
+            .unwrap();
+        assert_eq!(files, vec![&file("dockerfile", "a")]);
+        let (_, files) = hasher
+            .collect_instruction_files(&project_file_set, &context)
+            .unwrap();
+        assert_eq!(files, vec![&file("apps/app/DOCKERFILE", "app")]);
+
+        let hash_file_set = |hasher: &TaskHasher, instruction: &HashInstruction| {
+            let context = hasher.hash_context();
+            let js_env = HashMap::new();
+            let (_, hash) = hasher
+                .hash_instruction("app:build", instruction, context.args(&js_env))
+                .unwrap();
+            hash
+        };
+        let case_sensitive_file_set = HashInstruction::WorkspaceFileSet(
+            vec!["{workspaceRoot}/Dockerfile".into()],
+            GlobOptions::default(),
+        );
+        assert_eq!(hash_file_set(&hasher, &case_sensitive_file_set), hash(b""));
+        let dockerfile_hash = hash_file_set(&hasher, &workspace_file_set);
+        assert_ne!(dockerfile_hash, hash(b""));
+        assert_ne!(
+            hash_file_set(&task_hasher("b"), &workspace_file_set),
+            dockerfile_hash
+        );
+
+        // the hashes of the same globs are persisted and restored with their options
+        let persisted = hasher.export_cache().unwrap();
+        assert_eq!(persisted.len(), 2);
+        assert_ne!(persisted[0].cache_key, persisted[1].cache_key);
+        let restored = task_hasher("a");
+        assert_eq!(restored.import_cache(persisted), 2);
+        assert_eq!(
+            restored
+                .workspace_files_cache
+                .get(&file_set_cache_key(
+                    &["Dockerfile".to_string()],
+                    case_insensitive
+                ))
+                .as_deref(),
+            Some(dockerfile_hash.as_str())
+        );
+    }
 }
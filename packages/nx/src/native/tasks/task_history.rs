@@ -1,10 +1,15 @@
 use std::rc::Rc;
 use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
 use itertools::Itertools;
 use napi::bindgen_prelude::*;
-use rusqlite::vtab::array;
-use rusqlite::{params, types::Value, Connection};
+use rusqlite::{params, types::Value};
 
+use crate::native::db::connection::NxDbConnection;
+use crate::native::tasks::history_export::{
+    to_jsonl, to_otlp, traces_url, ExportedTaskRun, PushTaskRuns, TaskRunsExportFormat,
+    TaskRunsExportOptions,
+};
 use crate::native::tasks::types::TaskTarget;
 
 #[napi(object)]
@@ -16,64 +21,78 @@ pub struct TaskRun {
     pub end: i64,
 }
 
+#[napi(object)]
+pub struct FlakinessWindow {
+    /// Runs that started before this, in milliseconds since the epoch, are not considered
+    pub since: i64,
+    /// Runs that started after this are not considered, defaults to now
+    pub until: Option<i64>,
+    /// Targets that flipped less often than this are not returned, defaults to 0
+    pub min_flip_rate: Option<f64>,
+}
+
+#[napi(object)]
+pub struct FlakyTarget {
+    pub project: String,
+    pub target: String,
+    pub configuration: Option<String>,
+    /// The runs of the target within the window
+    pub runs: u32,
+    /// The runs that succeeded right after a failed run with the same hash
+    pub flips: u32,
+    /// `flips` divided by `runs`
+    pub flip_rate: f64,
+    /// When the last flip started, in milliseconds since the epoch
+    pub last_flip: i64,
+}
+
+/// How long the last runs of a target took to execute, in milliseconds
+#[napi(object)]
+pub struct TaskDurationStats {
+    /// The runs that are considered, at most the last 20 runs that were not restored from the cache
+    pub runs: u32,
+    pub average: f64,
+    /// Used to estimate the next run, a run that was much slower or faster than usual weighs less than in `average`
+    pub median: f64,
+    pub max: f64,
+}
+
+/// The runs of each target that are considered to estimate its next run
+const DURATION_STATS_RUNS: u32 = 20;
+
 #[napi]
 pub struct NxTaskHistory {
-    db: External<Connection>,
+    db: External<NxDbConnection>,
 }
 
 #[napi]
 impl NxTaskHistory {
     #[napi(constructor)]
-    pub fn new(db: External<Connection>) -> anyhow::Result<Self> {
-        let s = Self { db };
-
-        s.setup()?;
-
-        Ok(s)
-    }
-
-    fn setup(&self) -> anyhow::Result<()> {
-        array::load_module(&self.db)?;
-        self.db
-            .execute_batch(
-                "
-            BEGIN;
-            CREATE TABLE IF NOT EXISTS task_history (
-                id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
-                hash TEXT NOT NULL,
-                status TEXT NOT NULL,
-                code INTEGER NOT NULL,
-                start TIMESTAMP NOT NULL,
-                end TIMESTAMP NOT NULL,
-                FOREIGN KEY (hash) REFERENCES task_details (hash)
-            );
-            CREATE INDEX IF NOT EXISTS hash_idx ON task_history (hash);
-            COMMIT;
-            ",
-            )
-            .map_err(anyhow::Error::from)
+    pub fn new(db: External<NxDbConnection>) -> anyhow::Result<Self> {
+        Ok(Self { db })
     }
 
     #[napi]
     pub fn record_task_runs(&self, task_runs: Vec<TaskRun>) -> anyhow::Result<()> {
-        for task_run in task_runs.iter() {
-            self.db
-                .execute(
-                    "
+        // the runs of concurrent commands are batched by the writer, and wait for each other instead of failing
+        self.db.write(move |db| {
+            let mut statement = db.prepare(
+                "
             INSERT INTO task_history
                 (hash, status, code, start, end)
                 VALUES (?1, ?2, ?3, ?4, ?5)",
-                    params![
-                        task_run.hash,
-                        task_run.status,
-                        task_run.code,
-                        task_run.start,
-                        task_run.end
-                    ],
-                )
-                .map_err(anyhow::Error::from)?;
-        }
-        Ok(())
+            )?;
+            for task_run in task_runs.iter() {
+                statement.execute(params![
+                    task_run.hash,
+                    task_run.status,
+                    task_run.code,
+                    task_run.start,
+                    task_run.end
+                ])?;
+            }
+            Ok(())
+        })
     }
 
     #[napi]
@@ -86,6 +105,7 @@ impl NxTaskHistory {
         );
 
         self.db
+            .connection()?
             .prepare(
                 "SELECT hash from task_history
                     WHERE hash IN rarray(?1)
@@ -98,6 +118,135 @@ impl NxTaskHistory {
             .collect()
     }
 
+    /// Finds the targets that passed after failing with the same inputs within a time window,
+    /// sorted from the flakiest
+    #[napi]
+    pub fn get_flaky_targets(&self, window: FlakinessWindow) -> anyhow::Result<Vec<FlakyTarget>> {
+        let until = window.until.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(i64::MAX, |now| now.as_millis() as i64)
+        });
+        let min_flip_rate = window.min_flip_rate.unwrap_or(0.0);
+
+        let flaky_targets = self
+            .db
+            .connection()?
+            .prepare(
+                "
+                SELECT project, target, configuration,
+                    COUNT(*) AS runs,
+                    SUM(flip) AS flips,
+                    MAX(CASE WHEN flip THEN start END) AS last_flip
+                    FROM (
+                        SELECT project, target, configuration, start,
+                            COALESCE(
+                                code = 0 AND LAG(code) OVER (
+                                    PARTITION BY task_history.hash ORDER BY start, id
+                                ) <> 0,
+                                0
+                            ) AS flip
+                            FROM task_history
+                                JOIN task_details ON task_history.hash = task_details.hash
+                            WHERE start >= ?1 AND start <= ?2
+                    )
+                    GROUP BY project, target, configuration
+                    HAVING flips > 0
+                    ORDER BY flips * 1.0 / runs DESC, flips DESC
+                ",
+            )?
+            .query_map(params![window.since, until], |row| {
+                let runs: u32 = row.get(3)?;
+                let flips: u32 = row.get(4)?;
+                Ok(FlakyTarget {
+                    project: row.get(0)?,
+                    target: row.get(1)?,
+                    configuration: row.get(2)?,
+                    runs,
+                    flips,
+                    flip_rate: flips as f64 / runs as f64,
+                    last_flip: row.get(5)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(flaky_targets
+            .into_iter()
+            .filter(|flaky_target| flaky_target.flip_rate >= min_flip_rate)
+            .collect())
+    }
+
+    /// Exports the recorded task runs, oldest first
+    #[napi]
+    pub fn export_task_runs(
+        &self,
+        format: TaskRunsExportFormat,
+        options: Option<TaskRunsExportOptions>,
+    ) -> anyhow::Result<String> {
+        let options = options.unwrap_or_default();
+        let runs = self.exported_task_runs(&options)?;
+        Ok(match format {
+            TaskRunsExportFormat::jsonl => to_jsonl(&runs),
+            TaskRunsExportFormat::otlp => to_otlp(&runs, options.service_name.as_deref()),
+        })
+    }
+
+    /// Pushes the recorded task runs as spans to an OTLP/HTTP endpoint, e.g. `http://localhost:4318`
+    #[napi(ts_return_type = "Promise<number>")]
+    pub fn push_task_runs(
+        &self,
+        endpoint: String,
+        options: Option<TaskRunsExportOptions>,
+        headers: Option<HashMap<String, String>>,
+    ) -> anyhow::Result<AsyncTask<PushTaskRuns>> {
+        let options = options.unwrap_or_default();
+        let runs = self.exported_task_runs(&options)?;
+        Ok(AsyncTask::new(PushTaskRuns {
+            url: traces_url(&endpoint),
+            headers: headers.unwrap_or_default(),
+            body: to_otlp(&runs, options.service_name.as_deref()),
+            spans: runs.len() as u32,
+        }))
+    }
+
+    fn exported_task_runs(
+        &self,
+        options: &TaskRunsExportOptions,
+    ) -> anyhow::Result<Vec<ExportedTaskRun>> {
+        self.db
+            .connection()?
+            .prepare(
+                "
+                SELECT id, task_history.hash, project, target, configuration, status, code, start, end
+                    FROM task_history
+                        JOIN task_details ON task_history.hash = task_details.hash
+                    WHERE start >= ?1 AND start <= ?2
+                    ORDER BY start, id
+                ",
+            )?
+            .query_map(
+                params![
+                    options.since.unwrap_or(i64::MIN),
+                    options.until.unwrap_or(i64::MAX)
+                ],
+                |row| {
+                    Ok(ExportedTaskRun {
+                        id: row.get(0)?,
+                        hash: row.get(1)?,
+                        project: row.get(2)?,
+                        target: row.get(3)?,
+                        configuration: row.get(4)?,
+                        status: row.get(5)?,
+                        code: row.get(6)?,
+                        start: row.get(7)?,
+                        end: row.get(8)?,
+                    })
+                },
+            )?
+            .map(|r| r.map_err(anyhow::Error::from))
+            .collect()
+    }
+
     #[napi]
     pub fn get_estimated_task_timings(&self, targets: Vec<TaskTarget>) -> anyhow::Result<HashMap<String, f64>> {
         let values = Rc::new(
@@ -114,6 +263,7 @@ impl NxTaskHistory {
 
         // for older query sql version, need to select:  (project || ':' || target || (CASE WHEN coalesce(configuration, '') <> '' THEN ':' || configuration ELSE '' END)) AS target_string,
         self.db
+            .connection()?
             .prepare(
                 "
                 SELECT
@@ -133,4 +283,73 @@ impl NxTaskHistory {
             .map(|r| r.map_err(anyhow::Error::from))
             .collect()
     }
+
+    /// The durations of the last executed runs of the targets, by `project:target[:configuration]`.
+    /// Runs restored from the cache take no time, so they are not considered. Targets that never ran are not returned
+    #[napi]
+    pub fn get_task_duration_stats(&self, targets: Vec<TaskTarget>) -> anyhow::Result<HashMap<String, TaskDurationStats>> {
+        let values = Rc::new(
+            targets
+                .iter()
+                .map(|t| Value::from(
+                    match &t.configuration {
+                        Some(configuration) => format!("{}:{}:{}", t.project, t.target, configuration),
+                        _ => format!("{}:{}", t.project, t.target)
+                    }
+                ))
+                .collect::<Vec<Value>>(),
+        );
+
+        let mut durations: HashMap<String, Vec<f64>> = HashMap::new();
+        for row in self
+            .db
+            .connection()?
+            .prepare(
+                "
+                SELECT target_string, duration
+                    FROM (
+                        SELECT
+                            CONCAT_WS(':', project, target, configuration) AS target_string,
+                            end - start AS duration,
+                            ROW_NUMBER() OVER (
+                                PARTITION BY project, target, configuration ORDER BY start DESC
+                            ) AS run
+                            FROM task_history
+                                JOIN task_details ON task_history.hash = task_details.hash
+                            WHERE status IN ('success', 'failure') AND end >= start
+                    )
+                    WHERE target_string in rarray(?1) AND run <= ?2
+                ",
+            )?
+            .query_map(params![values, DURATION_STATS_RUNS], |row| {
+                let target_string: String = row.get(0)?;
+                let duration: f64 = row.get(1)?;
+                Ok((target_string, duration))
+            })?
+        {
+            let (target_string, duration) = row?;
+            durations.entry(target_string).or_default().push(duration);
+        }
+
+        Ok(durations
+            .into_iter()
+            .map(|(target_string, durations)| (target_string, duration_stats(durations)))
+            .collect())
+    }
+}
+
+fn duration_stats(mut durations: Vec<f64>) -> TaskDurationStats {
+    durations.sort_by(f64::total_cmp);
+    let runs = durations.len();
+    let median = if runs.is_multiple_of(2) {
+        (durations[runs / 2 - 1] + durations[runs / 2]) / 2.0
+    } else {
+        durations[runs / 2]
+    };
+    TaskDurationStats {
+        runs: runs as u32,
+        average: durations.iter().sum::<f64>() / runs as f64,
+        median,
+        max: durations[runs - 1],
+    }
 }
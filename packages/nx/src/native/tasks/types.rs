@@ -6,6 +6,8 @@ use napi::{
     sys,
 };
 
+use crate::native::glob::GlobOptions;
+
 #[napi(object)]
 #[derive(Default, Clone)]
 pub struct Task {
@@ -32,10 +34,10 @@ pub struct TaskGraph {
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum HashInstruction {
-    WorkspaceFileSet(Vec<String>),
+    WorkspaceFileSet(Vec<String>, GlobOptions),
     Runtime(String),
     Environment(String),
-    ProjectFileSet(String, Vec<String>),
+    ProjectFileSet(String, Vec<String>, GlobOptions),
     ProjectConfiguration(String),
     TsConfiguration(String),
     TaskOutput(String, Vec<String>),
@@ -65,15 +67,23 @@ impl ToNapiValue for HashInstruction {
 
 impl fmt::Display for HashInstruction {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // the file sets that are matched case insensitively are told apart from the same case sensitive file sets
+        if let HashInstruction::WorkspaceFileSet(_, options)
+        | HashInstruction::ProjectFileSet(_, _, options) = self
+        {
+            if options.case_insensitive {
+                write!(f, "caseInsensitive:")?;
+            }
+        }
         write!(
             f,
             "{}",
             match self {
                 HashInstruction::AllExternalDependencies => "AllExternalDependencies".to_string(),
-                HashInstruction::ProjectFileSet(project_name, file_set) => {
+                HashInstruction::ProjectFileSet(project_name, file_set, _) => {
                     format!("{project_name}:{}", file_set.join(","))
                 }
-                HashInstruction::WorkspaceFileSet(file_set) =>
+                HashInstruction::WorkspaceFileSet(file_set, _) =>
                     format!("workspace:[{}]", file_set.join(",")),
                 HashInstruction::Runtime(runtime) => format!("runtime:{}", runtime),
                 HashInstruction::Environment(env) => format!("env:{}", env),
@@ -0,0 +1,352 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use anyhow::anyhow;
+
+use crate::native::tasks::types::TaskGraph;
+
+/// The result of a task in a run, or the estimated duration of a task that has not run
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct TaskGraphRunResult {
+    pub status: Option<String>,
+    /// In milliseconds since the unix epoch
+    pub start_time: Option<f64>,
+    pub end_time: Option<f64>,
+    /// In milliseconds, used when the start and end times are not known
+    pub duration: Option<f64>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskGraphNode {
+    pub id: String,
+    pub project: String,
+    pub target: String,
+    pub configuration: Option<String>,
+    pub status: Option<String>,
+    pub start_time: Option<f64>,
+    pub end_time: Option<f64>,
+    /// In milliseconds, 0 when it is not known
+    pub duration: f64,
+    /// How long after the start of the run the task can start at the earliest, when its dependencies finished
+    pub earliest_start: f64,
+    /// How long the task can be delayed without delaying the end of the run
+    pub slack: f64,
+    /// Whether the task is on the critical path
+    pub critical: bool,
+}
+
+/// The task `source` depends on the task `target`
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskGraphEdge {
+    pub source: String,
+    pub target: String,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskGraphVisualization {
+    /// Sorted by id
+    pub nodes: Vec<TaskGraphNode>,
+    pub edges: Vec<TaskGraphEdge>,
+    /// The longest chain of dependent tasks, in the order they run, which bounds the duration of the run
+    pub critical_path: Vec<String>,
+    pub critical_path_duration: f64,
+    /// The duration of all of the tasks, as if they ran one after the other
+    pub total_duration: f64,
+}
+
+/// Computes what the task graph view of `nx graph` renders: the tasks with their results, their dependencies,
+/// and the critical path of the run weighted by the durations of the tasks
+#[napi]
+pub fn visualize_task_graph(
+    task_graph: TaskGraph,
+    results: Option<HashMap<String, TaskGraphRunResult>>,
+) -> anyhow::Result<TaskGraphVisualization> {
+    let results = results.unwrap_or_default();
+    let tasks = task_graph.tasks.iter().collect::<BTreeMap<_, _>>();
+    let index = tasks
+        .keys()
+        .enumerate()
+        .map(|(i, id)| (id.as_str(), i))
+        .collect::<HashMap<_, _>>();
+
+    // dependencies on tasks that are not in the graph are not rendered
+    let dependencies = tasks
+        .keys()
+        .map(|id| {
+            task_graph
+                .dependencies
+                .get(*id)
+                .into_iter()
+                .flatten()
+                .filter_map(|dependency| index.get(dependency.as_str()).copied())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let durations = tasks
+        .keys()
+        .map(|id| results.get(*id).map_or(0.0, duration))
+        .collect::<Vec<_>>();
+
+    let ids = tasks.keys().collect::<Vec<_>>();
+
+    let schedule = Schedule::new(&dependencies, &durations).map_err(|task| {
+        anyhow!(
+            "The task graph has a circular dependency involving {}",
+            ids[task]
+        )
+    })?;
+    let critical_path = schedule.critical_path();
+
+    let mut critical = vec![false; tasks.len()];
+    for &task in &critical_path {
+        critical[task] = true;
+    }
+    let nodes = tasks
+        .iter()
+        .enumerate()
+        .map(|(i, (id, task))| {
+            let result = results.get(*id);
+            TaskGraphNode {
+                id: id.to_string(),
+                project: task.target.project.clone(),
+                target: task.target.target.clone(),
+                configuration: task.target.configuration.clone(),
+                status: result.and_then(|result| result.status.clone()),
+                start_time: result.and_then(|result| result.start_time),
+                end_time: result.and_then(|result| result.end_time),
+                duration: durations[i],
+                earliest_start: schedule.earliest_start(i),
+                slack: schedule.slack(i),
+                critical: critical[i],
+            }
+        })
+        .collect();
+    let edges = dependencies
+        .iter()
+        .enumerate()
+        .flat_map(|(i, task_dependencies)| {
+            task_dependencies
+                .iter()
+                .map(move |&dependency| (i, dependency))
+        })
+        .map(|(source, target)| TaskGraphEdge {
+            source: ids[source].to_string(),
+            target: ids[target].to_string(),
+        })
+        .collect();
+
+    Ok(TaskGraphVisualization {
+        nodes,
+        edges,
+        critical_path_duration: schedule.duration(),
+        critical_path: critical_path
+            .into_iter()
+            .map(|task| ids[task].to_string())
+            .collect(),
+        total_duration: durations.iter().sum(),
+    })
+}
+
+fn duration(result: &TaskGraphRunResult) -> f64 {
+    match (result.start_time, result.end_time) {
+        (Some(start), Some(end)) if end >= start => end - start,
+        _ => result.duration.unwrap_or(0.0).max(0.0),
+    }
+}
+
+/// When the tasks of a graph run at the earliest and at the latest without delaying the run, given unlimited parallelism
+struct Schedule<'a> {
+    durations: &'a [f64],
+    earliest_finish: Vec<f64>,
+    latest_finish: Vec<f64>,
+    /// The dependency of each task that finishes last, which the task waits for
+    last_dependency: Vec<Option<usize>>,
+}
+
+impl<'a> Schedule<'a> {
+    /// Fails with a task of a cycle when the graph is not acyclic
+    fn new(dependencies: &[Vec<usize>], durations: &'a [f64]) -> Result<Self, usize> {
+        let order = topological_order(dependencies)?;
+
+        let mut earliest_finish = vec![0.0; durations.len()];
+        let mut last_dependency = vec![None; durations.len()];
+        for &task in &order {
+            let mut start = 0.0;
+            for &dependency in &dependencies[task] {
+                if last_dependency[task].is_none() || earliest_finish[dependency] > start {
+                    start = earliest_finish[dependency];
+                    last_dependency[task] = Some(dependency);
+                }
+            }
+            earliest_finish[task] = start + durations[task];
+        }
+
+        let duration = earliest_finish.iter().copied().fold(0.0, f64::max);
+        let mut latest_finish = vec![duration; durations.len()];
+        for &task in order.iter().rev() {
+            let latest_start = latest_finish[task] - durations[task];
+            for &dependency in &dependencies[task] {
+                latest_finish[dependency] = latest_finish[dependency].min(latest_start);
+            }
+        }
+
+        Ok(Self {
+            durations,
+            earliest_finish,
+            latest_finish,
+            last_dependency,
+        })
+    }
+
+    fn duration(&self) -> f64 {
+        self.earliest_finish.iter().copied().fold(0.0, f64::max)
+    }
+
+    fn earliest_start(&self, task: usize) -> f64 {
+        self.earliest_finish[task] - self.durations[task]
+    }
+
+    fn slack(&self, task: usize) -> f64 {
+        (self.latest_finish[task] - self.earliest_finish[task]).max(0.0)
+    }
+
+    /// The chain of tasks that finishes last, from its first task
+    fn critical_path(&self) -> Vec<usize> {
+        let mut last = None;
+        for (task, &finish) in self.earliest_finish.iter().enumerate() {
+            if last.is_none_or(|last: usize| finish > self.earliest_finish[last]) {
+                last = Some(task);
+            }
+        }
+        let mut path = vec![];
+        while let Some(task) = last {
+            path.push(task);
+            last = self.last_dependency[task];
+        }
+        path.reverse();
+        path
+    }
+}
+
+/// Orders the tasks so every task comes after its dependencies. Fails with a task of a cycle
+fn topological_order(dependencies: &[Vec<usize>]) -> Result<Vec<usize>, usize> {
+    let mut dependents = vec![vec![]; dependencies.len()];
+    let mut remaining = vec![0; dependencies.len()];
+    for (task, task_dependencies) in dependencies.iter().enumerate() {
+        for &dependency in task_dependencies {
+            dependents[dependency].push(task);
+            remaining[task] += 1;
+        }
+    }
+
+    let mut ready = (0..dependencies.len())
+        .filter(|&task| remaining[task] == 0)
+        .collect::<VecDeque<_>>();
+    let mut order = Vec::with_capacity(dependencies.len());
+    while let Some(task) = ready.pop_front() {
+        order.push(task);
+        for &dependent in &dependents[task] {
+            remaining[dependent] -= 1;
+            if remaining[dependent] == 0 {
+                ready.push_back(dependent);
+            }
+        }
+    }
+
+    match (0..dependencies.len()).find(|&task| remaining[task] > 0) {
+        Some(task) => Err(task),
+        None => Ok(order),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::native::tasks::types::{Task, TaskTarget};
+
+    use super::*;
+
+    fn task_graph(dependencies: &[(&str, &[&str])]) -> TaskGraph {
+        TaskGraph {
+            roots: vec![],
+            tasks: dependencies
+                .iter()
+                .map(|(id, _)| {
+                    let (project, target) = id.split_once(':').unwrap();
+                    let task = Task {
+                        id: id.to_string(),
+                        target: TaskTarget {
+                            project: project.into(),
+                            target: target.into(),
+                            configuration: None,
+                        },
+                        ..Default::default()
+                    };
+                    (id.to_string(), task)
+                })
+                .collect(),
+            dependencies: dependencies
+                .iter()
+                .map(|(id, dependencies)| {
+                    (
+                        id.to_string(),
+                        dependencies.iter().map(|d| d.to_string()).collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn durations(durations: &[(&str, f64)]) -> HashMap<String, TaskGraphRunResult> {
+        durations
+            .iter()
+            .map(|(id, duration)| {
+                let result = TaskGraphRunResult {
+                    duration: Some(*duration),
+                    ..Default::default()
+                };
+                (id.to_string(), result)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_find_the_critical_path() {
+        let graph = task_graph(&[
+            ("app:build", &["lib:build", "ui:build"]),
+            ("lib:build", &["utils:build"]),
+            ("ui:build", &["utils:build"]),
+            ("utils:build", &[]),
+        ]);
+        let results = durations(&[
+            ("app:build", 30.0),
+            ("lib:build", 50.0),
+            ("ui:build", 20.0),
+            ("utils:build", 10.0),
+        ]);
+
+        let visualization = visualize_task_graph(graph, Some(results)).unwrap();
+        assert_eq!(
+            visualization.critical_path,
+            vec!["utils:build", "lib:build", "app:build"]
+        );
+        assert_eq!(visualization.critical_path_duration, 90.0);
+        assert_eq!(visualization.total_duration, 110.0);
+        let ui = &visualization.nodes[2];
+        assert_eq!(ui.id, "ui:build");
+        assert_eq!(
+            (ui.earliest_start, ui.slack, ui.critical),
+            (10.0, 30.0, false)
+        );
+        assert!(visualization.nodes[0].critical);
+        assert_eq!(visualization.edges.len(), 4);
+    }
+
+    #[test]
+    fn should_reject_circular_dependencies() {
+        let graph = task_graph(&[("app:build", &["lib:build"]), ("lib:build", &["app:build"])]);
+        assert!(visualize_task_graph(graph, None).is_err());
+    }
+}
@@ -0,0 +1,877 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{available_parallelism, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::{unbounded, Receiver, RecvTimeoutError, Sender};
+use napi::bindgen_prelude::External;
+use napi::threadsafe_function::{
+    ErrorStrategy::Fatal, ThreadsafeFunction, ThreadsafeFunctionCallMode::NonBlocking,
+};
+use napi::{Env, JsFunction};
+use parking_lot::Mutex;
+use tracing::{debug, trace};
+
+use crate::native::glob::{build_glob_matcher, NxGlobMatcher};
+use crate::native::project_graph::types::ProjectGraph;
+use crate::native::project_graph::utils::{
+    create_project_root_mappings, find_project_for_path, ProjectRootMappings,
+};
+use crate::native::pseudo_terminal::process_tree::kill_tree;
+use crate::native::tasks::hashers::{project_file_set_globs, workspace_file_set_globs};
+use crate::native::tasks::scheduler::{
+    schedule, shell_command, TaskCommand, TaskLifecycleEvent, TaskRunResult, TaskRunStatus,
+};
+use crate::native::tasks::types::{HashInstruction, TaskGraph};
+use crate::native::watch::WatchEvent;
+
+const DEFAULT_DEBOUNCE_MS: u32 = 300;
+/// The files that invalidate the tasks whose hash includes external dependencies
+const LOCK_FILES: [&str; 4] = [
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+];
+/// The files that invalidate the tasks whose hash includes the TypeScript configuration
+const ROOT_TS_CONFIGS: [&str; 2] = ["tsconfig.base.json", "tsconfig.json"];
+
+#[napi(object)]
+pub struct TaskWatchOptions {
+    /// How long to wait for more changes before the invalidated tasks run again, 300 milliseconds by default
+    pub debounce_ms: Option<u32>,
+    /// The maximum number of tasks that run at the same time, defaults to the number of cpus
+    pub parallel: Option<u32>,
+    /// Matches negated globs of the inputs in order, like the task hasher does with the same option
+    pub ordered_negated_globs: Option<bool>,
+}
+
+#[napi(string_enum)]
+#[derive(Debug, PartialEq)]
+pub enum TaskWatchEventKind {
+    /// A run of the invalidated tasks started
+    #[allow(non_camel_case_types)]
+    started,
+    /// A task of the run started, finished or was skipped
+    #[allow(non_camel_case_types)]
+    task,
+    /// Every task of the run has finished or was skipped
+    #[allow(non_camel_case_types)]
+    finished,
+    /// More files changed before the run finished, its tasks were killed and run again with the new changes
+    #[allow(non_camel_case_types)]
+    cancelled,
+}
+
+#[napi(object)]
+#[derive(Debug)]
+pub struct TaskWatchEvent {
+    pub kind: TaskWatchEventKind,
+    /// The number of the run, which increases with every run
+    pub run: u32,
+    /// The tasks of the run
+    pub tasks: Vec<String>,
+    /// The files whose changes invalidated the tasks of the run
+    pub changed_files: Vec<String>,
+    /// The lifecycle event of the task, for `task` events
+    pub task: Option<TaskLifecycleEvent>,
+    /// The final status of every task of the run, for `finished` and `cancelled` events.
+    /// Tasks that were killed or did not start because the run was cancelled are skipped
+    pub statuses: Option<HashMap<String, TaskRunStatus>>,
+}
+
+type EventCallback = ThreadsafeFunction<TaskWatchEvent, Fatal>;
+
+/// Runs the tasks of a task graph again when the files that they read change.
+/// The changes reported by the watcher are matched against the inputs of the hash plans of the tasks, so only the
+/// tasks whose inputs changed run again, with the tasks that depend on them. Changes are debounced, and a run that is
+/// still in progress when more files change is cancelled: its tasks are killed and run again with the new changes
+#[napi]
+pub struct TaskWatchRunner {
+    invalidation: TaskInvalidation,
+    sender: Sender<Message>,
+    callback: Arc<Mutex<Option<EventCallback>>>,
+    watch_loop: Mutex<Option<JoinHandle<()>>>,
+}
+
+#[napi]
+impl TaskWatchRunner {
+    #[napi(constructor)]
+    pub fn new(
+        project_graph: External<ProjectGraph>,
+        task_graph: TaskGraph,
+        hash_plans: External<HashMap<String, Vec<HashInstruction>>>,
+        commands: HashMap<String, TaskCommand>,
+        options: Option<TaskWatchOptions>,
+    ) -> anyhow::Result<Self> {
+        if let Some(task_id) = task_graph
+            .tasks
+            .keys()
+            .find(|task_id| !commands.contains_key(*task_id))
+        {
+            anyhow::bail!("there is no command for {}", task_id);
+        }
+
+        let invalidation = TaskInvalidation::new(
+            &project_graph,
+            &task_graph,
+            &hash_plans,
+            options
+                .as_ref()
+                .and_then(|options| options.ordered_negated_globs)
+                .unwrap_or(false),
+        )?;
+        let callback: Arc<Mutex<Option<EventCallback>>> = Default::default();
+        let watch_loop = WatchLoop {
+            dependencies: task_graph
+                .tasks
+                .keys()
+                .map(|task_id| {
+                    let dependencies = task_graph
+                        .dependencies
+                        .get(task_id)
+                        .cloned()
+                        .unwrap_or_default();
+                    (task_id.clone(), dependencies)
+                })
+                .collect(),
+            commands: Arc::new(commands),
+            debounce: Duration::from_millis(
+                options
+                    .as_ref()
+                    .and_then(|options| options.debounce_ms)
+                    .unwrap_or(DEFAULT_DEBOUNCE_MS)
+                    .into(),
+            ),
+            parallel: options
+                .as_ref()
+                .and_then(|options| options.parallel)
+                .map(|parallel| parallel as usize)
+                .unwrap_or_else(|| available_parallelism().map_or(2, |n| n.get()))
+                .max(1),
+            emit: {
+                let callback = Arc::clone(&callback);
+                Arc::new(move |event: TaskWatchEvent| {
+                    if let Some(callback) = callback.lock().as_ref() {
+                        callback.call(event, NonBlocking);
+                    }
+                })
+            },
+        };
+
+        let (sender, receiver) = unbounded();
+        let watch_loop = watch_loop.spawn(receiver, sender.clone());
+        Ok(Self {
+            invalidation,
+            sender,
+            callback,
+            watch_loop: Mutex::new(Some(watch_loop)),
+        })
+    }
+
+    /// Calls `callback` when a run starts, finishes or is cancelled, and when its tasks start and finish
+    #[napi]
+    pub fn on_event(
+        &self,
+        env: Env,
+        #[napi(ts_arg_type = "(event: TaskWatchEvent) => void")] callback: JsFunction,
+    ) -> napi::Result<()> {
+        let mut callback: EventCallback =
+            callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
+        // the watcher keeps the process alive while it watches, not the runs
+        callback.unref(&env)?;
+        *self.callback.lock() = Some(callback);
+        Ok(())
+    }
+
+    /// Invalidates the tasks whose inputs include one of the changed files, and the tasks that depend on them.
+    /// They run once no more files changed for the debounce duration. Returns the invalidated tasks
+    #[napi]
+    pub fn files_changed(&self, events: Vec<WatchEvent>) -> Vec<String> {
+        let files = events
+            .into_iter()
+            .flat_map(|event| [Some(event.path), event.from])
+            .flatten()
+            .map(|file| file.replace('\\', "/"))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let tasks = self.invalidation.invalidated_tasks(&files);
+        trace!("{} changed files invalidated {:?}", files.len(), tasks);
+
+        let mut invalidated = tasks.iter().cloned().collect::<Vec<_>>();
+        invalidated.sort();
+        if !tasks.is_empty() {
+            self.sender.send(Message::Changed { tasks, files }).ok();
+        }
+        invalidated
+    }
+
+    /// Runs tasks and the tasks that depend on them after the debounce duration, e.g. every task when watching starts
+    #[napi]
+    pub fn run_tasks(&self, task_ids: Vec<String>) -> anyhow::Result<()> {
+        if let Some(task_id) = task_ids
+            .iter()
+            .find(|task_id| !self.invalidation.dependents.contains_key(*task_id))
+        {
+            anyhow::bail!("{} is not in the task graph", task_id);
+        }
+        let tasks = self.invalidation.with_dependents(task_ids);
+        self.sender
+            .send(Message::Changed {
+                tasks,
+                files: vec![],
+            })
+            .ok();
+        Ok(())
+    }
+
+    /// Cancels the run that is in progress and stops running the tasks again
+    #[napi]
+    pub fn stop(&self) {
+        self.sender.send(Message::Stop).ok();
+        if let Some(watch_loop) = self.watch_loop.lock().take() {
+            watch_loop.join().ok();
+        }
+    }
+}
+
+impl Drop for TaskWatchRunner {
+    fn drop(&mut self) {
+        self.sender.send(Message::Stop).ok();
+    }
+}
+
+/// The files that invalidate a task, from an instruction of its hash plan
+enum InputFiles {
+    /// The files of the workspace that match globs
+    Workspace(NxGlobMatcher),
+    /// The files of a project that match globs
+    Project {
+        project: String,
+        globs: NxGlobMatcher,
+    },
+    /// The files with these paths
+    Paths(Vec<String>),
+}
+
+/// Finds the tasks that a change of files invalidates
+struct TaskInvalidation {
+    inputs: HashMap<String, Vec<InputFiles>>,
+    dependents: HashMap<String, Vec<String>>,
+    project_root_mappings: ProjectRootMappings,
+}
+
+impl TaskInvalidation {
+    fn new(
+        project_graph: &ProjectGraph,
+        task_graph: &TaskGraph,
+        hash_plans: &HashMap<String, Vec<HashInstruction>>,
+        ordered_negated_globs: bool,
+    ) -> anyhow::Result<Self> {
+        let mut dependents: HashMap<String, Vec<String>> = task_graph
+            .tasks
+            .keys()
+            .map(|task_id| (task_id.clone(), vec![]))
+            .collect();
+        for (task_id, dependencies) in &task_graph.dependencies {
+            for dependency in dependencies {
+                if let Some(dependency_dependents) = dependents.get_mut(dependency) {
+                    dependency_dependents.push(task_id.clone());
+                }
+            }
+        }
+
+        let mut inputs = HashMap::with_capacity(task_graph.tasks.len());
+        for task_id in task_graph.tasks.keys() {
+            let instructions = hash_plans
+                .get(task_id)
+                .ok_or_else(|| anyhow::anyhow!("there is no hash plan for {}", task_id))?;
+            let task_inputs = task_input_files(project_graph, instructions, ordered_negated_globs)?;
+            inputs.insert(task_id.clone(), task_inputs);
+        }
+
+        Ok(Self {
+            inputs,
+            dependents,
+            project_root_mappings: create_project_root_mappings(&project_graph.nodes),
+        })
+    }
+
+    /// The tasks whose inputs include one of the files, and the tasks that depend on them
+    fn invalidated_tasks(&self, files: &[String]) -> HashSet<String> {
+        let owners = files
+            .iter()
+            .map(|file| find_project_for_path(file, &self.project_root_mappings))
+            .collect::<Vec<_>>();
+        let changed = self
+            .inputs
+            .iter()
+            .filter(|(_, task_inputs)| {
+                files.iter().zip(&owners).any(|(file, owner)| {
+                    task_inputs.iter().any(|input| match input {
+                        InputFiles::Workspace(globs) => globs.is_match(file),
+                        InputFiles::Project { project, globs } => {
+                            *owner == Some(project.as_str()) && globs.is_match(file)
+                        }
+                        InputFiles::Paths(paths) => paths.contains(file),
+                    })
+                })
+            })
+            .map(|(task_id, _)| task_id.clone())
+            .collect::<Vec<_>>();
+        self.with_dependents(changed)
+    }
+
+    /// The tasks and every task that depends on them, directly or transitively
+    fn with_dependents(&self, task_ids: Vec<String>) -> HashSet<String> {
+        let mut tasks = HashSet::new();
+        let mut queue = task_ids;
+        while let Some(task_id) = queue.pop() {
+            if let Some(dependents) = self.dependents.get(&task_id) {
+                queue.extend(dependents.iter().cloned());
+            }
+            tasks.insert(task_id);
+        }
+        tasks
+    }
+}
+
+/// The files that invalidate a task with a hash plan. Runtime and environment inputs cannot be watched, and the
+/// outputs of dependencies change when the dependencies run again, which already runs the task again
+fn task_input_files(
+    project_graph: &ProjectGraph,
+    instructions: &[HashInstruction],
+    ordered_negated_globs: bool,
+) -> anyhow::Result<Vec<InputFiles>> {
+    let mut inputs = vec![];
+    let mut paths = BTreeSet::new();
+    for instruction in instructions {
+        match instruction {
+            HashInstruction::WorkspaceFileSet(file_sets, options) => {
+                let globs = workspace_file_set_globs(file_sets);
+                inputs.push(InputFiles::Workspace(build_glob_matcher(
+                    &globs,
+                    ordered_negated_globs,
+                    *options,
+                )?));
+            }
+            HashInstruction::ProjectFileSet(project_name, file_sets, options) => {
+                let Some(project) = project_graph.nodes.get(project_name) else {
+                    anyhow::bail!("project {} is not in the project graph", project_name);
+                };
+                let globs = project_file_set_globs(&project.root, file_sets);
+                inputs.push(InputFiles::Project {
+                    project: project_name.clone(),
+                    globs: build_glob_matcher(&globs, ordered_negated_globs, *options)?,
+                });
+            }
+            HashInstruction::ProjectConfiguration(project_name) => {
+                if let Some(project) = project_graph.nodes.get(project_name) {
+                    for file in ["project.json", "package.json"] {
+                        paths.insert(match project.root.as_str() {
+                            "" | "." => file.to_string(),
+                            root => format!("{}/{}", root.trim_end_matches('/'), file),
+                        });
+                    }
+                }
+            }
+            HashInstruction::TsConfiguration(_) => {
+                paths.extend(ROOT_TS_CONFIGS.map(String::from));
+            }
+            HashInstruction::External(_) | HashInstruction::AllExternalDependencies => {
+                paths.extend(LOCK_FILES.map(String::from));
+            }
+            HashInstruction::Runtime(_)
+            | HashInstruction::Environment(_)
+            | HashInstruction::TaskOutput(..) => {}
+        }
+    }
+    if !paths.is_empty() {
+        inputs.push(InputFiles::Paths(paths.into_iter().collect()));
+    }
+    Ok(inputs)
+}
+
+enum Message {
+    /// Files changed and invalidated tasks, or tasks were asked to run
+    Changed {
+        tasks: HashSet<String>,
+        files: Vec<String>,
+    },
+    /// The tasks of a run finished
+    Finished {
+        run: u32,
+    },
+    Stop,
+}
+
+/// What the tasks of a run share to be cancelled
+#[derive(Default)]
+struct RunState {
+    cancelled: AtomicBool,
+    /// The processes of the tasks that are running
+    processes: Mutex<HashMap<String, u32>>,
+    /// The tasks that were killed or did not start because the run was cancelled
+    interrupted: Mutex<HashSet<String>>,
+}
+
+struct ActiveRun {
+    id: u32,
+    tasks: Vec<String>,
+    files: Vec<String>,
+    state: Arc<RunState>,
+    thread: JoinHandle<HashMap<String, TaskRunStatus>>,
+}
+
+/// Collects the invalidated tasks until no more files change for the debounce duration, then runs them
+struct WatchLoop {
+    dependencies: HashMap<String, Vec<String>>,
+    commands: Arc<HashMap<String, TaskCommand>>,
+    debounce: Duration,
+    parallel: usize,
+    emit: Arc<dyn Fn(TaskWatchEvent) + Send + Sync>,
+}
+
+impl WatchLoop {
+    fn spawn(self, receiver: Receiver<Message>, sender: Sender<Message>) -> JoinHandle<()> {
+        std::thread::Builder::new()
+            .name("nx-task-watch".into())
+            .spawn(move || self.run(receiver, sender))
+            .expect("the watch thread can be spawned")
+    }
+
+    fn run(self, receiver: Receiver<Message>, sender: Sender<Message>) {
+        let mut pending_tasks: HashSet<String> = HashSet::new();
+        let mut pending_files: BTreeSet<String> = BTreeSet::new();
+        let mut deadline: Option<Instant> = None;
+        let mut active: Option<ActiveRun> = None;
+        let mut next_run = 1;
+
+        loop {
+            let message = match deadline {
+                Some(deadline) => receiver.recv_deadline(deadline),
+                None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match message {
+                Ok(Message::Changed { tasks, files }) => {
+                    pending_tasks.extend(tasks);
+                    pending_files.extend(files);
+                    // every change restarts the debounce, so a burst of changes runs the tasks once
+                    deadline = Some(Instant::now() + self.debounce);
+                }
+                Ok(Message::Finished { run }) => {
+                    // runs that were cancelled already reported their statuses
+                    if active.as_ref().is_some_and(|active| active.id == run) {
+                        let finished = active.take().expect("the run is active");
+                        let statuses = finished.thread.join().unwrap_or_default();
+                        debug!("run {} finished", finished.id);
+                        (self.emit)(TaskWatchEvent {
+                            kind: TaskWatchEventKind::finished,
+                            run: finished.id,
+                            tasks: finished.tasks,
+                            changed_files: finished.files,
+                            task: None,
+                            statuses: Some(statuses),
+                        });
+                    }
+                }
+                Ok(Message::Stop) | Err(RecvTimeoutError::Disconnected) => {
+                    if let Some(active) = active.take() {
+                        self.cancel(active);
+                    }
+                    break;
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    deadline = None;
+                    if let Some(superseded) = active.take() {
+                        let files = superseded.files.clone();
+                        let tasks = superseded.tasks.clone();
+                        let statuses = self.cancel(superseded);
+                        // the tasks of the superseded run that did not succeed still have to run
+                        pending_tasks.extend(tasks.into_iter().filter(|task_id| {
+                            !matches!(statuses.get(task_id), Some(TaskRunStatus::success))
+                        }));
+                        pending_files.extend(files);
+                    }
+                    active = Some(self.start(
+                        next_run,
+                        std::mem::take(&mut pending_tasks),
+                        std::mem::take(&mut pending_files),
+                        sender.clone(),
+                    ));
+                    next_run += 1;
+                }
+            }
+        }
+    }
+
+    /// Runs the tasks in dependency order on another thread. The dependencies that are not part of the run are
+    /// up to date, so the tasks do not wait for them
+    fn start(
+        &self,
+        id: u32,
+        tasks: HashSet<String>,
+        files: BTreeSet<String>,
+        sender: Sender<Message>,
+    ) -> ActiveRun {
+        let dependencies = tasks
+            .iter()
+            .map(|task_id| {
+                let task_dependencies = self.dependencies[task_id]
+                    .iter()
+                    .filter(|dependency| tasks.contains(*dependency))
+                    .cloned()
+                    .collect();
+                (task_id.clone(), task_dependencies)
+            })
+            .collect::<HashMap<_, Vec<_>>>();
+        let mut tasks = tasks.into_iter().collect::<Vec<_>>();
+        tasks.sort();
+        let files = files.into_iter().collect::<Vec<_>>();
+        debug!("run {} of {:?}", id, tasks);
+        (self.emit)(TaskWatchEvent {
+            kind: TaskWatchEventKind::started,
+            run: id,
+            tasks: tasks.clone(),
+            changed_files: files.clone(),
+            task: None,
+            statuses: None,
+        });
+
+        let state = Arc::new(RunState::default());
+        let parallel = self.parallel;
+        let run_task = {
+            let commands = Arc::clone(&self.commands);
+            let state = Arc::clone(&state);
+            move |task_id: &str| run_cancellable(task_id, &commands[task_id], &state)
+        };
+        let on_event = {
+            let emit = Arc::clone(&self.emit);
+            let state = Arc::clone(&state);
+            let tasks = tasks.clone();
+            move |event: TaskLifecycleEvent| {
+                // the tasks of a cancelled run fail because they were killed, which is not worth reporting
+                if !state.cancelled.load(Ordering::SeqCst) {
+                    emit(TaskWatchEvent {
+                        kind: TaskWatchEventKind::task,
+                        run: id,
+                        tasks: tasks.clone(),
+                        changed_files: vec![],
+                        task: Some(event),
+                        statuses: None,
+                    });
+                }
+            }
+        };
+        let thread = std::thread::spawn(move || {
+            let statuses = schedule(
+                &dependencies,
+                &HashMap::new(),
+                &HashMap::new(),
+                parallel,
+                false,
+                run_task,
+                on_event,
+            )
+            .unwrap_or_else(|e| {
+                debug!("could not run {}: {:?}", id, e);
+                HashMap::new()
+            });
+            sender.send(Message::Finished { run: id }).ok();
+            statuses
+        });
+
+        ActiveRun {
+            id,
+            tasks,
+            files,
+            state,
+            thread,
+        }
+    }
+
+    /// Kills the processes of the tasks of a run and waits for the run to stop
+    fn cancel(&self, run: ActiveRun) -> HashMap<String, TaskRunStatus> {
+        debug!("cancelling run {}", run.id);
+        run.state.cancelled.store(true, Ordering::SeqCst);
+        for pid in run.state.processes.lock().values() {
+            kill_tree(*pid, None).ok();
+        }
+
+        let mut statuses = run.thread.join().unwrap_or_default();
+        for task_id in run.state.interrupted.lock().iter() {
+            statuses.insert(task_id.clone(), TaskRunStatus::skipped);
+        }
+        (self.emit)(TaskWatchEvent {
+            kind: TaskWatchEventKind::cancelled,
+            run: run.id,
+            tasks: run.tasks,
+            changed_files: run.files,
+            task: None,
+            statuses: Some(statuses.clone()),
+        });
+        statuses
+    }
+}
+
+/// Runs the command of a task of a run, unless the run was cancelled. The process is killed when the run is cancelled
+fn run_cancellable(task_id: &str, task_command: &TaskCommand, state: &RunState) -> TaskRunResult {
+    let start = Instant::now();
+    let result = |code| TaskRunResult {
+        code,
+        duration: start.elapsed().as_secs_f64() * 1000.0,
+    };
+    if state.cancelled.load(Ordering::SeqCst) {
+        state.interrupted.lock().insert(task_id.to_string());
+        return result(1);
+    }
+
+    let mut child = match shell_command(task_command).spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            debug!("could not run {}: {:?}", task_id, e);
+            return result(1);
+        }
+    };
+    {
+        let mut processes = state.processes.lock();
+        processes.insert(task_id.to_string(), child.id());
+        // the processes of the run were killed while this one started
+        if state.cancelled.load(Ordering::SeqCst) {
+            kill_tree(child.id(), None).ok();
+        }
+    }
+    let status = child.wait();
+    state.processes.lock().remove(task_id);
+
+    if state.cancelled.load(Ordering::SeqCst) {
+        state.interrupted.lock().insert(task_id.to_string());
+    }
+    result(status.ok().and_then(|status| status.code()).unwrap_or(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::native::glob::GlobOptions;
+    use crate::native::project_graph::types::Project;
+    use crate::native::tasks::types::{Task, TaskTarget};
+
+    fn task(project: &str, target: &str) -> (String, Task) {
+        let id = format!("{}:{}", project, target);
+        let task = Task {
+            id: id.clone(),
+            target: TaskTarget {
+                project: project.to_string(),
+                target: target.to_string(),
+                configuration: None,
+            },
+            outputs: vec![],
+            project_root: None,
+        };
+        (id, task)
+    }
+
+    fn sorted(tasks: HashSet<String>) -> Vec<String> {
+        let mut tasks = tasks.into_iter().collect::<Vec<_>>();
+        tasks.sort();
+        tasks
+    }
+
+    #[test]
+    fn should_invalidate_the_tasks_whose_inputs_changed_and_their_dependents() {
+        let project_graph = ProjectGraph {
+            nodes: [
+                ("app", "apps/app"),
+                ("lib", "libs/lib"),
+                ("nested", "libs/lib/nested"),
+            ]
+            .into_iter()
+            .map(|(name, root)| {
+                let project = Project {
+                    root: root.to_string(),
+                    ..Default::default()
+                };
+                (name.to_string(), project)
+            })
+            .collect(),
+            dependencies: HashMap::new(),
+            external_nodes: HashMap::new(),
+        };
+        let task_graph = TaskGraph {
+            roots: vec!["lib:build".into(), "nested:test".into()],
+            tasks: [
+                task("app", "build"),
+                task("lib", "build"),
+                task("nested", "test"),
+            ]
+            .into_iter()
+            .collect(),
+            dependencies: HashMap::from([("app:build".into(), vec!["lib:build".into()])]),
+        };
+        let project_file_set = |project: &str| {
+            HashInstruction::ProjectFileSet(
+                project.to_string(),
+                vec![
+                    "{projectRoot}/**/*".into(),
+                    "!{projectRoot}/**/*.spec.ts".into(),
+                ],
+                GlobOptions::default(),
+            )
+        };
+        let hash_plans = HashMap::from([
+            ("app:build".to_string(), vec![project_file_set("app")]),
+            (
+                "lib:build".to_string(),
+                vec![
+                    project_file_set("lib"),
+                    HashInstruction::WorkspaceFileSet(
+                        vec!["{workspaceRoot}/nx.json".into()],
+                        GlobOptions::default(),
+                    ),
+                    HashInstruction::AllExternalDependencies,
+                ],
+            ),
+            (
+                "nested:test".to_string(),
+                vec![
+                    project_file_set("nested"),
+                    HashInstruction::ProjectConfiguration("nested".into()),
+                ],
+            ),
+        ]);
+        let invalidation =
+            TaskInvalidation::new(&project_graph, &task_graph, &hash_plans, false).unwrap();
+        let invalidated = |file: &str| sorted(invalidation.invalidated_tasks(&[file.to_string()]));
+
+        assert_eq!(
+            invalidated("libs/lib/src/index.ts"),
+            ["app:build", "lib:build"]
+        );
+        assert_eq!(invalidated("apps/app/src/main.ts"), ["app:build"]);
+        assert!(invalidated("libs/lib/src/index.spec.ts").is_empty());
+        // the files of a nested project are not files of the project around it
+        assert_eq!(invalidated("libs/lib/nested/src/index.ts"), ["nested:test"]);
+        assert_eq!(invalidated("libs/lib/nested/project.json"), ["nested:test"]);
+        assert_eq!(invalidated("nx.json"), ["app:build", "lib:build"]);
+        assert_eq!(invalidated("pnpm-lock.yaml"), ["app:build", "lib:build"]);
+        assert!(invalidated("README.md").is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn should_cancel_a_run_when_more_files_change() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let watch_loop = WatchLoop {
+            dependencies: HashMap::from([
+                ("lib:build".into(), vec![]),
+                ("lib:serve".into(), vec![]),
+            ]),
+            commands: Arc::new(HashMap::from([
+                (
+                    "lib:build".to_string(),
+                    TaskCommand {
+                        command: "true".into(),
+                        cwd: None,
+                        env: None,
+                    },
+                ),
+                (
+                    "lib:serve".to_string(),
+                    TaskCommand {
+                        command: "sleep 30".into(),
+                        cwd: None,
+                        env: None,
+                    },
+                ),
+            ])),
+            debounce: Duration::from_millis(10),
+            parallel: 2,
+            emit: {
+                let events = Arc::clone(&events);
+                Arc::new(move |event: TaskWatchEvent| events.lock().push(event))
+            },
+        };
+        let (sender, receiver) = unbounded();
+        let handle = watch_loop.spawn(receiver, sender.clone());
+        let wait_for = |predicate: &dyn Fn(&[TaskWatchEvent]) -> bool| {
+            let start = Instant::now();
+            while !predicate(&events.lock()) {
+                assert!(start.elapsed() < Duration::from_secs(10));
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        };
+        let changed = |task_id: &str, file: &str| Message::Changed {
+            tasks: HashSet::from([task_id.to_string()]),
+            files: vec![file.to_string()],
+        };
+
+        sender.send(changed("lib:serve", "libs/lib/a.ts")).unwrap();
+        wait_for(&|events| {
+            events.iter().any(|event| {
+                event.task.as_ref().is_some_and(|task| {
+                    task.task_id == "lib:serve" && matches!(task.status, TaskRunStatus::started)
+                })
+            })
+        });
+        sender.send(changed("lib:build", "libs/lib/b.ts")).unwrap();
+        wait_for(&|events| {
+            events
+                .iter()
+                .any(|event| event.run == 2 && event.kind == TaskWatchEventKind::started)
+        });
+        sender.send(Message::Stop).unwrap();
+        handle.join().unwrap();
+
+        let events = events.lock();
+        let runs = events
+            .iter()
+            .filter(|event| event.kind != TaskWatchEventKind::task)
+            .map(|event| {
+                (
+                    event.run,
+                    format!("{:?}", event.kind),
+                    event.tasks.clone(),
+                    event.changed_files.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let run = |run: u32, kind: &str, tasks: &[&str], files: &[&str]| {
+            (
+                run,
+                kind.to_string(),
+                tasks.iter().map(|t| t.to_string()).collect::<Vec<_>>(),
+                files.iter().map(|f| f.to_string()).collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(
+            runs,
+            [
+                run(1, "started", &["lib:serve"], &["libs/lib/a.ts"]),
+                run(1, "cancelled", &["lib:serve"], &["libs/lib/a.ts"]),
+                run(
+                    2,
+                    "started",
+                    &["lib:build", "lib:serve"],
+                    &["libs/lib/a.ts", "libs/lib/b.ts"]
+                ),
+                run(
+                    2,
+                    "cancelled",
+                    &["lib:build", "lib:serve"],
+                    &["libs/lib/a.ts", "libs/lib/b.ts"]
+                ),
+            ]
+        );
+        let cancelled = &events
+            .iter()
+            .find(|event| event.kind == TaskWatchEventKind::cancelled)
+            .unwrap();
+        assert_eq!(
+            format!("{:?}", cancelled.statuses.as_ref().unwrap()["lib:serve"]),
+            "skipped"
+        );
+    }
+}
@@ -83,6 +83,41 @@ describe('NxTaskHistory', () => {
     expect(r2).not.toContain('234');
   });
 
+  it('should query flaky targets within a window', () => {
+    const now = Date.now();
+    const run = (hash: string, code: number, start: number) => ({
+      hash,
+      code,
+      status: code === 0 ? 'success' : 'failure',
+      start,
+      end: start + 1000,
+    });
+    taskHistory.recordTaskRuns([
+      run('123', 1, now - 3000),
+      run('123', 0, now - 2000),
+      run('234', 1, now - 1000),
+      run('234', 1, now),
+    ]);
+
+    const r = taskHistory.getFlakyTargets({ since: now - 5000 });
+    expect(r).toEqual([
+      {
+        project: 'proj',
+        target: 'build',
+        configuration: 'production',
+        runs: 4,
+        flips: 1,
+        flipRate: 0.25,
+        lastFlip: now - 2000,
+      },
+    ]);
+
+    expect(taskHistory.getFlakyTargets({ since: now - 1500 })).toEqual([]);
+    expect(
+      taskHistory.getFlakyTargets({ since: now - 5000, minFlipRate: 0.5 })
+    ).toEqual([]);
+  });
+
   it('should get estimated task timings', () => {
     taskHistory.recordTaskRuns([
       {
@@ -1,6 +1,8 @@
 use napi::bindgen_prelude::Either7;
 use napi::Either;
 
+use crate::native::glob::GlobOptions;
+
 #[napi(object)]
 pub struct InputsInput {
     pub input: String,
@@ -11,6 +13,8 @@ pub struct InputsInput {
 #[napi(object)]
 pub struct FileSetInput {
     pub fileset: String,
+    /// Match the globs of the file set case insensitively (e.g. `Dockerfile` also matches `dockerfile`)
+    pub case_insensitive: Option<bool>,
 }
 
 #[napi(object)]
@@ -73,7 +77,12 @@ impl<'a> From<&'a JsInputs> for Input<'a> {
                     Input::String(string)
                 }
             }
-            Either7::C(file_set) => Input::FileSet(&file_set.fileset),
+            Either7::C(file_set) => Input::FileSet(
+                &file_set.fileset,
+                GlobOptions {
+                    case_insensitive: file_set.case_insensitive.unwrap_or(false),
+                },
+            ),
             Either7::D(runtime) => Input::Runtime(&runtime.runtime),
             Either7::E(environment) => Input::Environment(&environment.env),
             Either7::F(external_dependencies) => {
@@ -94,7 +103,7 @@ pub(crate) enum Input<'a> {
         dependencies: bool,
     },
     String(&'a str),
-    FileSet(&'a str),
+    FileSet(&'a str, GlobOptions),
     Runtime(&'a str),
     Environment(&'a str),
     ExternalDependency(&'a [String]),
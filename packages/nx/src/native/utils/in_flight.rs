@@ -0,0 +1,97 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::bail;
+use tracing::trace;
+
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// The work of the process that has to be completed before it shuts down
+static WORK: WorkTracker = WorkTracker::new();
+
+struct WorkTracker {
+    shutting_down: AtomicBool,
+    in_flight: AtomicUsize,
+}
+
+/// Work that the process waits for before it shuts down, it is done when this is dropped
+#[must_use]
+pub struct InFlightWork<'a>(&'a WorkTracker);
+
+impl Drop for InFlightWork<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl WorkTracker {
+    const fn new() -> Self {
+        Self {
+            shutting_down: AtomicBool::new(false),
+            in_flight: AtomicUsize::new(0),
+        }
+    }
+
+    fn start(&self, what: &str) -> anyhow::Result<InFlightWork<'_>> {
+        // the work is counted before checking for a shutdown, so a drain that started since then waits for it
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let work = InFlightWork(self);
+        if self.shutting_down.load(Ordering::SeqCst) {
+            bail!("Nx is shutting down, {} was not started", what);
+        }
+        Ok(work)
+    }
+
+    fn drain(&self, deadline: Instant) -> usize {
+        self.shutting_down.store(true, Ordering::SeqCst);
+        loop {
+            let in_flight = self.in_flight.load(Ordering::SeqCst);
+            if in_flight == 0 || Instant::now() >= deadline {
+                return in_flight;
+            }
+            std::thread::sleep(DRAIN_POLL_INTERVAL);
+        }
+    }
+}
+
+/// Starts work that has to be completed before the process shuts down (e.g. a write to the Nx database).
+/// No work is started once the process is shutting down
+pub fn start_work(what: &str) -> anyhow::Result<InFlightWork<'static>> {
+    WORK.start(what)
+}
+
+/// Stops new work from being started, and waits until the work in flight is done or the deadline has passed.
+/// Returns how much work is still in flight
+pub fn drain_work(deadline: Instant) -> usize {
+    let in_flight = WORK.drain(deadline);
+    trace!("{} units of work are still in flight", in_flight);
+    in_flight
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn should_wait_for_the_work_in_flight() {
+        let work = WorkTracker::new();
+        thread::scope(|scope| {
+            let started = work.start("hashing").unwrap();
+            scope.spawn(move || {
+                thread::sleep(Duration::from_millis(20));
+                drop(started);
+            });
+            assert_eq!(work.drain(Instant::now() + Duration::from_secs(5)), 0);
+        });
+        assert!(work.start("writing").is_err());
+    }
+
+    #[test]
+    fn should_stop_waiting_at_the_deadline() {
+        let work = WorkTracker::new();
+        let _started = work.start("hashing").unwrap();
+        assert_eq!(work.drain(Instant::now() + Duration::from_millis(20)), 1);
+    }
+}
@@ -1,14 +1,31 @@
 mod find_matching_projects;
 mod get_mod_time;
+mod in_flight;
 mod normalize_trait;
+mod nx_path;
 pub mod path;
 
 pub use find_matching_projects::*;
 pub use get_mod_time::*;
+pub use in_flight::*;
 pub use normalize_trait::Normalize;
+pub use nx_path::*;
 
 #[cfg_attr(not(target_arch = "wasm32"), path = "atomics/default.rs")]
 #[cfg_attr(target_arch = "wasm32", path = "atomics/wasm.rs")]
 pub mod atomics;
 
 pub use atomics::*;
+
+// the task scheduler, the watcher and the cache use rayon outside of wasm
+#[cfg(all(not(feature = "parallel"), not(target_arch = "wasm32")))]
+compile_error!("the `parallel` feature can only be disabled for wasm targets");
+
+#[cfg_attr(feature = "parallel", path = "parallel/rayon.rs")]
+#[cfg_attr(not(feature = "parallel"), path = "parallel/sequential.rs")]
+pub mod parallel;
+
+// the sequential implementation is also tested on the targets that use rayon
+#[cfg(all(test, feature = "parallel"))]
+#[path = "parallel/sequential.rs"]
+mod sequential;
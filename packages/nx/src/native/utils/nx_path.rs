@@ -0,0 +1,209 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Windows does not open paths of this length or longer, unless they are verbatim (`\\?\C:\...`)
+const MAX_PATH: usize = 260;
+
+/// A path with `/` separators on every platform, the way paths are keyed in the file map and matched by globs.
+/// On Windows, paths can come in with either separator (or a mix of both), with the verbatim prefix (`\\?\C:\`)
+/// or as UNC paths (`\\server\share`), and they are all normalized to the same path.
+/// `.` segments and repeated separators are removed, the casing of the path is preserved
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NxPath(String);
+
+impl NxPath {
+    pub fn new<S: AsRef<str>>(path: S) -> Self {
+        Self(normalize(path.as_ref(), cfg!(windows)))
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        Self::new(path.as_ref().to_string_lossy())
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl fmt::Display for NxPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for NxPath {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for NxPath {
+    fn as_ref(&self) -> &Path {
+        Path::new(&self.0)
+    }
+}
+
+/// The path to open a file with. On Windows, absolute paths that are too long to be opened as they are
+/// are turned into verbatim paths, every other path is returned as it is
+pub fn to_fs_path(path: &Path) -> Cow<'_, Path> {
+    if cfg!(windows) && path.as_os_str().len() >= MAX_PATH {
+        if let Some(verbatim) = verbatim(&path.to_string_lossy()) {
+            return Cow::Owned(PathBuf::from(verbatim));
+        }
+    }
+    Cow::Borrowed(path)
+}
+
+/// Replaces the `\` separators of Windows paths (and globs) with `/`. Other platforms only separate with `/`
+pub fn normalize_separators(path: &str) -> Cow<'_, str> {
+    if cfg!(windows) && path.contains('\\') {
+        Cow::Owned(path.replace('\\', "/"))
+    } else {
+        Cow::Borrowed(path)
+    }
+}
+
+fn normalize(path: &str, windows: bool) -> String {
+    let (prefix, rest) = if windows {
+        split_windows_prefix(path)
+    } else {
+        ("", path)
+    };
+    let is_separator = |c: char| c == '/' || (windows && c == '\\');
+
+    let mut normalized = String::with_capacity(path.len());
+    normalized.push_str(prefix);
+    if prefix.is_empty() && rest.starts_with(is_separator) {
+        normalized.push('/');
+    }
+    let segments = rest
+        .split(is_separator)
+        .filter(|segment| !segment.is_empty() && *segment != ".");
+    for (index, segment) in segments.enumerate() {
+        if index > 0 {
+            normalized.push('/');
+        }
+        normalized.push_str(segment);
+    }
+
+    // the root of a drive keeps its separator (`C:/`), since `C:` is the current directory of the drive
+    let is_drive = |path: &str| path.len() == 2 && path.ends_with(':');
+    if windows && is_drive(&normalized) && rest.len() > 2 {
+        normalized.push('/');
+    }
+    if normalized.is_empty() {
+        normalized.push('.');
+    }
+    normalized
+}
+
+/// Splits the verbatim and UNC prefixes from a Windows path. UNC paths keep a `//` prefix
+fn split_windows_prefix(path: &str) -> (&str, &str) {
+    let verbatim_unc = path
+        .strip_prefix(r"\\?\UNC\")
+        .or_else(|| path.strip_prefix("//?/UNC/"));
+    if let Some(rest) = verbatim_unc {
+        return ("//", rest);
+    }
+    let verbatim = path
+        .strip_prefix(r"\\?\")
+        .or_else(|| path.strip_prefix("//?/"));
+    if let Some(rest) = verbatim {
+        return ("", rest);
+    }
+    let unc = path.strip_prefix(r"\\").or_else(|| path.strip_prefix("//"));
+    match unc {
+        Some(rest) if !rest.is_empty() => ("//", rest),
+        _ => ("", path),
+    }
+}
+
+/// The verbatim form of an absolute Windows path, with `.` and `..` segments resolved,
+/// since verbatim paths are passed to the file system without being normalized
+fn verbatim(path: &str) -> Option<String> {
+    let normalized = normalize(path, true);
+    // the drive, or the server and the share of UNC paths, can not be left with `..`
+    let (prefix, rest, root_segments) = match normalized.strip_prefix("//") {
+        Some(rest) => (r"\\?\UNC\", rest, 2),
+        None => {
+            let bytes = normalized.as_bytes();
+            let is_absolute = bytes.len() >= 3
+                && bytes[0].is_ascii_alphabetic()
+                && bytes[1] == b':'
+                && bytes[2] == b'/';
+            if !is_absolute {
+                return None;
+            }
+            (r"\\?\", normalized.as_str(), 1)
+        }
+    };
+
+    let mut segments: Vec<&str> = vec![];
+    for segment in rest.split('/') {
+        match segment {
+            "" => {}
+            ".." if segments.len() > root_segments => {
+                segments.pop();
+            }
+            ".." => {}
+            segment => segments.push(segment),
+        }
+    }
+    Some(format!("{}{}", prefix, segments.join(r"\")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_normalize_windows_paths() {
+        for (path, normalized) in [
+            (r"apps\app/src\main.ts", "apps/app/src/main.ts"),
+            (r".\apps\\app\", "apps/app"),
+            (r"C:\Users\Nx\workspace", "C:/Users/Nx/workspace"),
+            (r"\\?\C:\Users\Nx\workspace", "C:/Users/Nx/workspace"),
+            (r"C:\", "C:/"),
+            (r"\\server\share\workspace", "//server/share/workspace"),
+            (
+                r"\\?\UNC\server\share\workspace",
+                "//server/share/workspace",
+            ),
+            (
+                r"\\?\UNC\Server\Share\Workspace",
+                "//Server/Share/Workspace",
+            ),
+            ("", "."),
+        ] {
+            assert_eq!(normalize(path, true), normalized, "{}", path);
+        }
+    }
+
+    #[test]
+    fn should_only_split_on_slashes_on_other_platforms() {
+        for (path, normalized) in [
+            ("apps//app/./src/main.ts", "apps/app/src/main.ts"),
+            ("/workspace/apps/", "/workspace/apps"),
+            (r"apps/with\backslash", r"apps/with\backslash"),
+            ("/", "/"),
+            ("./", "."),
+        ] {
+            assert_eq!(normalize(path, false), normalized, "{}", path);
+        }
+    }
+
+    #[test]
+    fn should_make_long_paths_verbatim() {
+        assert_eq!(
+            verbatim("C:/workspace/apps/../libs/lib").as_deref(),
+            Some(r"\\?\C:\workspace\libs\lib")
+        );
+        assert_eq!(
+            verbatim(r"\\server\share\..\..\workspace").as_deref(),
+            Some(r"\\?\UNC\server\share\workspace")
+        );
+        assert_eq!(verbatim("apps/app"), None);
+        assert_eq!(verbatim("/workspace/apps"), None);
+    }
+}
@@ -0,0 +1,7 @@
+//! Work is spread over the threads of the global rayon pool
+
+pub use rayon::join;
+
+pub mod prelude {
+    pub use rayon::prelude::*;
+}
@@ -0,0 +1,136 @@
+//! Runs the work of the rayon APIs that the hashers and globs use on the calling thread, in order,
+//! for targets without threads. The results are the same as with rayon, because every parallel
+//! result is either collected in order or sorted before it is used
+
+/// Runs `a` and then `b`, where rayon may run them at the same time
+pub fn join<A, B, RA, RB>(a: A, b: B) -> (RA, RB)
+where
+    A: FnOnce() -> RA,
+    B: FnOnce() -> RB,
+{
+    (a(), b())
+}
+
+pub mod prelude {
+    pub trait IntoParallelIterator: IntoIterator + Sized {
+        fn into_par_iter(self) -> Self::IntoIter {
+            self.into_iter()
+        }
+    }
+
+    impl<T: IntoIterator> IntoParallelIterator for T {}
+
+    pub trait IntoParallelRefIterator<'a> {
+        type Iter: Iterator;
+
+        fn par_iter(&'a self) -> Self::Iter;
+    }
+
+    impl<'a, T: 'a + ?Sized> IntoParallelRefIterator<'a> for T
+    where
+        &'a T: IntoIterator,
+    {
+        type Iter = <&'a T as IntoIterator>::IntoIter;
+
+        fn par_iter(&'a self) -> Self::Iter {
+            self.into_iter()
+        }
+    }
+
+    pub trait ParallelBridge: Iterator + Sized {
+        fn par_bridge(self) -> Self {
+            self
+        }
+    }
+
+    impl<I: Iterator> ParallelBridge for I {}
+
+    /// The adapters of rayon that have no equivalent with the same name on `Iterator`
+    pub trait ParallelIterator: Iterator + Sized {
+        fn flat_map_iter<U, F>(self, map: F) -> std::iter::FlatMap<Self, U, F>
+        where
+            U: IntoIterator,
+            F: FnMut(Self::Item) -> U,
+        {
+            self.flat_map(map)
+        }
+    }
+
+    impl<I: Iterator> ParallelIterator for I {}
+
+    pub trait ParallelSlice<T> {
+        fn par_chunks(&self, chunk_size: usize) -> std::slice::Chunks<'_, T>;
+    }
+
+    impl<T> ParallelSlice<T> for [T] {
+        fn par_chunks(&self, chunk_size: usize) -> std::slice::Chunks<'_, T> {
+            self.chunks(chunk_size)
+        }
+    }
+
+    pub trait ParallelSliceMut<T> {
+        fn par_sort(&mut self)
+        where
+            T: Ord;
+
+        fn par_sort_by<F>(&mut self, compare: F)
+        where
+            F: FnMut(&T, &T) -> std::cmp::Ordering;
+    }
+
+    impl<T> ParallelSliceMut<T> for [T] {
+        fn par_sort(&mut self)
+        where
+            T: Ord,
+        {
+            self.sort();
+        }
+
+        fn par_sort_by<F>(&mut self, compare: F)
+        where
+            F: FnMut(&T, &T) -> std::cmp::Ordering,
+        {
+            self.sort_by(compare);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::join;
+    use super::prelude::*;
+
+    #[test]
+    fn should_keep_the_results_of_rayon() {
+        let files = vec!["b.ts", "a.ts", "c.md"];
+        let mut matched: Vec<&&str> = files
+            .par_iter()
+            .filter(|file| file.ends_with(".ts"))
+            .collect();
+        matched.par_sort();
+        assert_eq!(matched, [&"a.ts", &"b.ts"]);
+
+        let mut lengths: Vec<(usize, &str)> = files
+            .clone()
+            .into_par_iter()
+            .map(|file| (file.len(), file))
+            .collect();
+        lengths.par_sort_by(|(_, a), (_, b)| b.cmp(a));
+        assert_eq!(lengths, [(4, "c.md"), (4, "b.ts"), (4, "a.ts")]);
+
+        let bridged: Result<(), String> = files.iter().par_bridge().try_for_each(|file| {
+            file.contains('.')
+                .then_some(())
+                .ok_or_else(|| file.to_string())
+        });
+        assert!(bridged.is_ok());
+
+        let chunked: Vec<usize> = files
+            .par_chunks(2)
+            .flat_map_iter(|chunk| chunk.iter().map(|file| file.len()))
+            .collect();
+        assert_eq!(chunked, [4, 4, 4]);
+
+        assert_eq!(join(|| files.len(), || files[0]), (3, "b.ts"));
+    }
+}
@@ -1,4 +1,4 @@
-use ignore::WalkBuilder;
+use ignore::{DirEntry, WalkBuilder};
 use std::fmt::Debug;
 use std::path::{Path, PathBuf};
 
@@ -8,11 +8,39 @@ use crate::native::logger::enable_logger;
 use crate::native::utils::{get_mod_time, Normalize};
 use walkdir::WalkDir;
 
+/// Directories that are never walked or watched, regardless of the ignore files
+pub(crate) const ALWAYS_IGNORED_GLOBS: [&str; 5] = [
+    "**/node_modules",
+    "**/.git",
+    "**/.nx/cache",
+    "**/.nx/workspace-data",
+    "**/.yarn/cache",
+];
+
+/// How the walkers handle symlinks
+#[napi(string_enum)]
+#[derive(Debug, Default)]
+pub enum SymlinkPolicy {
+    /// Symlinks are resolved, the files of symlinked directories are walked as if they were in the directory.
+    /// Symlinks that point to one of their parents are skipped, so cycles are only walked once
+    #[allow(non_camel_case_types)]
+    #[default]
+    follow,
+    /// Symlinks (including symlinked directories) are recorded as a single entry and are not resolved
+    #[allow(non_camel_case_types)]
+    record,
+    /// Symlinks are left out
+    #[allow(non_camel_case_types)]
+    skip,
+}
+
 #[derive(PartialEq, Debug, Ord, PartialOrd, Eq, Clone)]
 pub struct NxFile {
     pub full_path: String,
     pub normalized_path: String,
     pub mod_time: i64,
+    /// The target of the symlink, when symlinks are recorded instead of followed
+    pub link_target: Option<PathBuf>,
 }
 
 /// Walks the directory in a single thread and does not ignore any files
@@ -22,19 +50,14 @@ pub struct NxFile {
 pub fn nx_walker_sync<'a, P>(
     directory: P,
     ignores: Option<&[String]>,
+    symlinks: SymlinkPolicy,
 ) -> impl Iterator<Item = PathBuf>
 where
     P: AsRef<Path> + 'a,
 {
     let base_dir: PathBuf = directory.as_ref().into();
 
-    let mut base_ignores: Vec<String> = vec![
-        "**/node_modules".into(),
-        "**/.git".into(),
-        "**/.nx/cache".into(),
-        "**/.nx/workspace-data".into(),
-        "**/.yarn/cache".into(),
-    ];
+    let mut base_ignores: Vec<String> = ALWAYS_IGNORED_GLOBS.map(String::from).to_vec();
 
     if let Some(additional_ignores) = ignores {
         base_ignores.extend(additional_ignores.iter().map(|s| format!("**/{}", s)));
@@ -43,11 +66,14 @@ where
     let ignore_glob_set = build_glob_set(&base_ignores).expect("Should be valid globs");
 
     // Use WalkDir instead of ignore::WalkBuilder because it's faster
+    // WalkDir reports symlinks that point to one of their parents as errors, so cycles are not walked
     WalkDir::new(&base_dir)
+        .follow_links(matches!(symlinks, SymlinkPolicy::follow))
         .into_iter()
         .filter_entry(move |entry| {
             let path = entry.path().to_string_lossy();
             !ignore_glob_set.is_match(path.as_ref())
+                && !(matches!(symlinks, SymlinkPolicy::skip) && entry.path_is_symlink())
         })
         .filter_map(move |entry| {
             entry
@@ -58,12 +84,16 @@ where
 
 /// Walk the directory and ignore files from .gitignore and .nxignore
 #[cfg(target_arch = "wasm32")]
-pub fn nx_walker<P>(directory: P, use_ignores: bool) -> impl Iterator<Item = NxFile>
+pub fn nx_walker<P>(
+    directory: P,
+    use_ignores: bool,
+    symlinks: SymlinkPolicy,
+) -> impl Iterator<Item = NxFile>
 where
     P: AsRef<Path>,
 {
     let directory: PathBuf = directory.as_ref().into();
-    let walker = create_walker(&directory, use_ignores);
+    let walker = create_walker(&directory, use_ignores, symlinks);
 
     let entries = walker.build();
 
@@ -72,29 +102,17 @@ where
             return None;
         };
 
-        if dir_entry.file_type().is_some_and(|d| d.is_dir()) {
-            return None;
-        }
-
-        let Ok(file_path) = dir_entry.path().strip_prefix(&directory) else {
-            return None;
-        };
-
-        let Ok(metadata) = dir_entry.metadata() else {
-            return None;
-        };
-
-        Some(NxFile {
-            full_path: String::from(dir_entry.path().to_string_lossy()),
-            normalized_path: file_path.to_normalized_string(),
-            mod_time: get_mod_time(&metadata),
-        })
+        to_nx_file(&dir_entry, &directory, symlinks)
     })
 }
 
 /// Walk the directory and ignore files from .gitignore and .nxignore
 #[cfg(not(target_arch = "wasm32"))]
-pub fn nx_walker<P>(directory: P, use_ignores: bool) -> impl Iterator<Item = NxFile>
+pub fn nx_walker<P>(
+    directory: P,
+    use_ignores: bool,
+    symlinks: SymlinkPolicy,
+) -> impl Iterator<Item = NxFile>
 where
     P: AsRef<Path>,
 {
@@ -106,7 +124,7 @@ where
     enable_logger();
 
     let directory = directory.as_ref();
-    let mut walker = create_walker(directory, use_ignores);
+    let mut walker = create_walker(directory, use_ignores, symlinks);
 
     let cpus = available_parallelism().map_or(2, |n| n.get()) - 1;
 
@@ -120,29 +138,19 @@ where
         Box::new(move |entry| {
             use ignore::WalkState::*;
 
-            let Ok(dir_entry) = entry else {
-                return Continue;
+            let dir_entry = match entry {
+                Ok(dir_entry) => dir_entry,
+                Err(e) => {
+                    // symlink cycles are reported as errors when symlinks are followed
+                    trace!("skipping entry: {:?}", e);
+                    return Continue;
+                }
             };
 
-            if dir_entry.file_type().is_some_and(|d| d.is_dir()) {
-                return Continue;
+            if let Some(file) = to_nx_file(&dir_entry, directory, symlinks) {
+                tx.send(file).ok();
             }
 
-            let Ok(file_path) = dir_entry.path().strip_prefix(directory) else {
-                return Continue;
-            };
-
-            let Ok(metadata) = dir_entry.metadata() else {
-                return Continue;
-            };
-
-            tx.send(NxFile {
-                full_path: String::from(dir_entry.path().to_string_lossy()),
-                normalized_path: file_path.to_normalized_string(),
-                mod_time: get_mod_time(&metadata),
-            })
-            .ok();
-
             Continue
         })
     });
@@ -153,25 +161,50 @@ where
     receiver_thread.join().unwrap()
 }
 
-fn create_walker<P>(directory: P, use_ignores: bool) -> WalkBuilder
+fn to_nx_file(dir_entry: &DirEntry, directory: &Path, symlinks: SymlinkPolicy) -> Option<NxFile> {
+    if dir_entry.file_type().is_some_and(|d| d.is_dir()) {
+        return None;
+    }
+
+    let file_path = dir_entry.path().strip_prefix(directory).ok()?;
+
+    // symlinks are not followed when they are recorded, so the metadata is the metadata of the link
+    let metadata = dir_entry.metadata().ok()?;
+
+    let link_target = match symlinks {
+        SymlinkPolicy::record if dir_entry.path_is_symlink() => {
+            Some(std::fs::read_link(dir_entry.path()).ok()?)
+        }
+        _ => None,
+    };
+
+    Some(NxFile {
+        full_path: String::from(dir_entry.path().to_string_lossy()),
+        normalized_path: file_path.to_normalized_string(),
+        mod_time: get_mod_time(&metadata),
+        link_target,
+    })
+}
+
+pub(crate) fn create_walker<P>(
+    directory: P,
+    use_ignores: bool,
+    symlinks: SymlinkPolicy,
+) -> WalkBuilder
 where
     P: AsRef<Path>,
 {
     let directory: PathBuf = directory.as_ref().into();
 
-    let ignore_glob_set = build_glob_set(&[
-        "**/node_modules",
-        "**/.git",
-        "**/.nx/cache",
-        "**/.nx/workspace-data",
-        "**/.yarn/cache",
-    ])
-    .expect("These static ignores always build");
+    let ignore_glob_set =
+        build_glob_set(&ALWAYS_IGNORED_GLOBS).expect("These static ignores always build");
 
     let mut walker = WalkBuilder::new(&directory);
     walker.require_git(false);
     walker.hidden(false);
     walker.git_ignore(use_ignores);
+    // the walker reports symlinks that point to one of their parents as errors, so cycles are not walked
+    walker.follow_links(matches!(symlinks, SymlinkPolicy::follow));
     if use_ignores {
         walker.add_custom_ignore_filename(".nxignore");
     }
@@ -180,6 +213,7 @@ where
     walker.filter_entry(move |entry| {
         let path = entry.path().to_string_lossy();
         !ignore_glob_set.is_match(path.as_ref())
+            && !(matches!(symlinks, SymlinkPolicy::skip) && entry.path_is_symlink())
     });
     walker
 }
@@ -215,12 +249,12 @@ mod test {
     #[test]
     fn it_walks_a_directory() {
         // handle empty workspaces
-        let content = nx_walker("/does/not/exist", true).collect::<Vec<_>>();
+        let content = nx_walker("/does/not/exist", true, SymlinkPolicy::follow).collect::<Vec<_>>();
         assert!(content.is_empty());
 
         let temp_dir = setup_fs();
 
-        let mut content = nx_walker(&temp_dir, true).collect::<Vec<_>>();
+        let mut content = nx_walker(&temp_dir, true, SymlinkPolicy::follow).collect::<Vec<_>>();
         content.sort();
         let content = content
             .into_iter()
@@ -287,7 +321,7 @@ nested/child-two/
             )
             .unwrap();
 
-        let mut file_names = nx_walker(temp_dir, true)
+        let mut file_names = nx_walker(temp_dir, true, SymlinkPolicy::follow)
             .map(
                 |NxFile {
                      normalized_path: relative_path,
@@ -310,4 +344,58 @@ nested/child-two/
             )
         );
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn handles_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let temp_dir = setup_fs();
+        symlink(temp_dir.join("foo.txt"), temp_dir.join("linked.txt")).unwrap();
+        symlink(temp_dir.join("baz"), temp_dir.join("linked-dir")).unwrap();
+        // a cycle back to the workspace root
+        symlink(temp_dir.path(), temp_dir.join("baz/cycle")).unwrap();
+
+        let walk = |symlinks| {
+            let mut files = nx_walker(&temp_dir, true, symlinks)
+                .map(|file| (file.normalized_path, file.link_target))
+                .collect::<Vec<_>>();
+            files.sort();
+            files
+        };
+        let file = |path: &str| (path.to_string(), None);
+
+        assert_eq!(
+            walk(SymlinkPolicy::follow),
+            vec![
+                file("bar.txt"),
+                file("baz/qux.txt"),
+                file("foo.txt"),
+                file("linked-dir/qux.txt"),
+                file("linked.txt"),
+                file("test.txt"),
+            ]
+        );
+        assert_eq!(
+            walk(SymlinkPolicy::record),
+            vec![
+                file("bar.txt"),
+                ("baz/cycle".into(), Some(temp_dir.to_path_buf())),
+                file("baz/qux.txt"),
+                file("foo.txt"),
+                ("linked-dir".into(), Some(temp_dir.join("baz"))),
+                ("linked.txt".into(), Some(temp_dir.join("foo.txt"))),
+                file("test.txt"),
+            ]
+        );
+        assert_eq!(
+            walk(SymlinkPolicy::skip),
+            vec![
+                file("bar.txt"),
+                file("baz/qux.txt"),
+                file("foo.txt"),
+                file("test.txt"),
+            ]
+        );
+    }
 }
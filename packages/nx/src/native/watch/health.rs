@@ -0,0 +1,53 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+
+/// The counters of the watchers of this process, updated as they receive events
+pub(super) struct WatcherCounters {
+    pub watching: AtomicBool,
+    pub watched_roots: AtomicU32,
+    pub polled_roots: AtomicU32,
+    pub event_batches: AtomicU64,
+    pub events: AtomicU64,
+    pub dropped_events: AtomicU64,
+    pub errors: AtomicU64,
+}
+
+pub(super) static WATCHER_COUNTERS: WatcherCounters = WatcherCounters {
+    watching: AtomicBool::new(false),
+    watched_roots: AtomicU32::new(0),
+    polled_roots: AtomicU32::new(0),
+    event_batches: AtomicU64::new(0),
+    events: AtomicU64::new(0),
+    dropped_events: AtomicU64::new(0),
+    errors: AtomicU64::new(0),
+};
+
+/// How the file watcher has been doing since it started
+#[napi(object)]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WatcherHealth {
+    pub watching: bool,
+    /// The roots that are watched, including the origin
+    pub watched_roots: u32,
+    /// The roots that are scanned periodically, because they could not be fully watched
+    pub polled_roots: u32,
+    pub event_batches: i64,
+    pub events: i64,
+    /// Events that could not be read, or that were not sent to the callback of the watcher
+    pub dropped_events: i64,
+    /// Errors reported by the file system watcher
+    pub errors: i64,
+}
+
+pub fn watcher_health() -> WatcherHealth {
+    let counters = &WATCHER_COUNTERS;
+    let count = |counter: &AtomicU64| counter.load(Ordering::Relaxed) as i64;
+    WatcherHealth {
+        watching: counters.watching.load(Ordering::Relaxed),
+        watched_roots: counters.watched_roots.load(Ordering::Relaxed),
+        polled_roots: counters.polled_roots.load(Ordering::Relaxed),
+        event_batches: count(&counters.event_batches),
+        events: count(&counters.events),
+        dropped_events: count(&counters.dropped_events),
+        errors: count(&counters.errors),
+    }
+}
@@ -1,4 +1,9 @@
+mod health;
+mod poller;
 mod types;
 mod utils;
 mod watch_filterer;
 mod watcher;
+
+pub use health::{watcher_health, WatcherHealth};
+pub use types::WatchEvent;
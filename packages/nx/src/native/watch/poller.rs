@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, trace};
+use watchexec::error::{FsWatcherError, RuntimeError};
+use watchexec::Watchexec;
+use watchexec_events::filekind::{CreateKind, DataChange, FileEventKind, ModifyKind, RemoveKind};
+use watchexec_events::{Event, FileType, Priority, Source, Tag};
+
+use crate::native::walker::{nx_walker, SymlinkPolicy};
+
+/// How long to wait between two scans of the polled roots, unless another interval is given
+pub(super) const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+const MAX_USER_WATCHES_SYSCTL: &str = "sudo sysctl fs.inotify.max_user_watches=524288";
+
+/// Why some changes could be missed by the watcher
+#[napi(string_enum)]
+#[derive(Debug, PartialEq)]
+pub enum WatcherWarningKind {
+    /// The OS limit of the number of watched files (`fs.inotify.max_user_watches` on Linux) was reached
+    #[allow(non_camel_case_types)]
+    watchLimit,
+    /// The OS limit of the number of open file handles was reached
+    #[allow(non_camel_case_types)]
+    handleLimit,
+    /// A root could not be watched for another reason
+    #[allow(non_camel_case_types)]
+    unwatchable,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct WatcherWarning {
+    pub kind: WatcherWarningKind,
+    pub message: String,
+    /// The command that raises the limit that was reached, when it can be raised with sysctl
+    pub sysctl: Option<String>,
+    /// The roots whose changes are found by scanning them periodically instead
+    pub polled_roots: Vec<String>,
+}
+
+/// Scans the roots that could not be fully watched, and sends the changes it finds to the watcher
+/// as if they were file system events, so they go through the same filters and coalescing
+pub(super) struct Poller {
+    roots: Vec<String>,
+    use_ignore: bool,
+    interval: Duration,
+    polled_roots: Mutex<Vec<String>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl Poller {
+    pub fn new(roots: Vec<String>, use_ignore: bool, interval: Duration) -> Self {
+        Self {
+            roots,
+            use_ignore,
+            interval,
+            polled_roots: Mutex::new(vec![]),
+            stopped: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Starts polling the roots that may miss events because of `error`.
+    /// Returns the warning to report, unless `error` is not a failure to register watches or the roots are already polled
+    pub fn fall_back(
+        &self,
+        error: &RuntimeError,
+        watch_exec: &Arc<Watchexec>,
+    ) -> Option<WatcherWarning> {
+        let (kind, path, reason) = registration_failure(error)?;
+        // the limits are reached while adding watches, without the path of the root that was being added
+        let roots = match path.and_then(|path| self.root_of(path)) {
+            Some(root) => vec![root.to_string()],
+            None => self.roots.clone(),
+        };
+
+        let mut polled_roots = self.polled_roots.lock();
+        let new_roots: Vec<String> = roots
+            .into_iter()
+            .filter(|root| !polled_roots.contains(root))
+            .collect();
+        if new_roots.is_empty() {
+            return None;
+        }
+        for root in &new_roots {
+            debug!(?root, "falling back to polling");
+            self.poll(root.clone(), Arc::clone(watch_exec));
+        }
+        polled_roots.extend(new_roots);
+
+        Some(warning(kind, &reason, polled_roots.clone(), self.interval))
+    }
+
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    /// The most specific root containing `path`
+    fn root_of(&self, path: &Path) -> Option<&str> {
+        self.roots
+            .iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.len())
+            .map(String::as_str)
+    }
+
+    fn poll(&self, root: String, watch_exec: Arc<Watchexec>) {
+        let stopped = Arc::clone(&self.stopped);
+        let use_ignore = self.use_ignore;
+        let interval = self.interval;
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            let mut snapshot: Option<Snapshot> = None;
+            loop {
+                ticker.tick().await;
+                if stopped.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                let scanned_root = root.clone();
+                let Ok(current) =
+                    tokio::task::spawn_blocking(move || scan(&scanned_root, use_ignore)).await
+                else {
+                    break;
+                };
+                // the first scan is the state the next scans are compared with
+                let changes = snapshot
+                    .as_ref()
+                    .map(|previous| changes(previous, &current))
+                    .unwrap_or_default();
+                snapshot = Some(current);
+
+                trace!(?root, changes = changes.len(), "polled root");
+                for (path, kind) in changes {
+                    if watch_exec
+                        .send_event(file_event(path, kind), Priority::Normal)
+                        .await
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+            }
+            trace!(?root, "stopped polling");
+        });
+    }
+}
+
+/// The modification times of the files of a root, by their full paths
+type Snapshot = HashMap<PathBuf, i64>;
+
+fn scan(root: &str, use_ignore: bool) -> Snapshot {
+    nx_walker(root, use_ignore, SymlinkPolicy::follow)
+        .map(|file| (PathBuf::from(file.full_path), file.mod_time))
+        .collect()
+}
+
+/// The files that were created, modified or removed between two scans, sorted by path
+fn changes(previous: &Snapshot, current: &Snapshot) -> Vec<(PathBuf, FileEventKind)> {
+    let mut changes: Vec<(PathBuf, FileEventKind)> = current
+        .iter()
+        .filter_map(|(path, mod_time)| match previous.get(path) {
+            None => Some((path.clone(), FileEventKind::Create(CreateKind::File))),
+            Some(previous_mod_time) if previous_mod_time != mod_time => Some((
+                path.clone(),
+                FileEventKind::Modify(ModifyKind::Data(DataChange::Content)),
+            )),
+            Some(_) => None,
+        })
+        .chain(
+            previous
+                .keys()
+                .filter(|path| !current.contains_key(*path))
+                .map(|path| (path.clone(), FileEventKind::Remove(RemoveKind::File))),
+        )
+        .collect();
+    changes.sort_by(|(a, _), (b, _)| a.cmp(b));
+    changes
+}
+
+fn file_event(path: PathBuf, kind: FileEventKind) -> Event {
+    // removed files do not have a file type, like the events of the file system
+    let file_type = (!matches!(kind, FileEventKind::Remove(_))).then_some(FileType::File);
+    Event {
+        tags: vec![
+            Tag::Path { path, file_type },
+            Tag::FileEventKind(kind),
+            Tag::Source(Source::Filesystem),
+        ],
+        metadata: HashMap::new(),
+    }
+}
+
+/// The kind of a failure to register watches, with the path that could not be watched when it is known
+fn registration_failure(
+    error: &RuntimeError,
+) -> Option<(WatcherWarningKind, Option<&Path>, String)> {
+    let RuntimeError::FsWatcher { err, .. } = error else {
+        return None;
+    };
+    let (kind, path) = match err {
+        FsWatcherError::TooManyWatches { .. } => (WatcherWarningKind::watchLimit, None),
+        FsWatcherError::TooManyHandles { .. } => (WatcherWarningKind::handleLimit, None),
+        FsWatcherError::PathAdd { path, .. } => {
+            (WatcherWarningKind::unwatchable, Some(path.as_path()))
+        }
+        _ => return None,
+    };
+    Some((kind, path, err.to_string()))
+}
+
+fn warning(
+    kind: WatcherWarningKind,
+    reason: &str,
+    polled_roots: Vec<String>,
+    interval: Duration,
+) -> WatcherWarning {
+    let polling = format!(
+        "Changes to {} are found by scanning every {}ms instead, which is slower.",
+        polled_roots.join(", "),
+        interval.as_millis()
+    );
+    let (message, sysctl) = match kind {
+        WatcherWarningKind::watchLimit => (
+            format!(
+                "The limit of watched files was reached ({reason}). {polling}\nRaise the limit with `{MAX_USER_WATCHES_SYSCTL}`, and add `fs.inotify.max_user_watches=524288` to /etc/sysctl.conf to keep it after a restart."
+            ),
+            cfg!(target_os = "linux").then(|| MAX_USER_WATCHES_SYSCTL.to_string()),
+        ),
+        WatcherWarningKind::handleLimit => (
+            format!(
+                "The limit of open files was reached ({reason}). {polling}\nRaise the limit with `ulimit -n`."
+            ),
+            None,
+        ),
+        WatcherWarningKind::unwatchable => (format!("{reason}. {polling}"), None),
+    };
+    WatcherWarning {
+        kind,
+        message,
+        sysctl,
+        polled_roots,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_find_the_changes_between_scans() {
+        let previous: Snapshot = HashMap::from([
+            ("/ws/a.ts".into(), 1),
+            ("/ws/b.ts".into(), 1),
+            ("/ws/c.ts".into(), 1),
+        ]);
+        let current: Snapshot = HashMap::from([
+            ("/ws/a.ts".into(), 1),
+            ("/ws/b.ts".into(), 2),
+            ("/ws/d.ts".into(), 1),
+        ]);
+        assert_eq!(
+            changes(&previous, &current),
+            vec![
+                (
+                    "/ws/b.ts".into(),
+                    FileEventKind::Modify(ModifyKind::Data(DataChange::Content))
+                ),
+                ("/ws/c.ts".into(), FileEventKind::Remove(RemoveKind::File)),
+                ("/ws/d.ts".into(), FileEventKind::Create(CreateKind::File)),
+            ]
+        );
+        assert!(changes(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn should_explain_how_to_raise_the_watch_limit() {
+        let warning = warning(
+            WatcherWarningKind::watchLimit,
+            "OS limit on number of inotify watches reached",
+            vec!["/ws".into()],
+            DEFAULT_POLL_INTERVAL,
+        );
+        assert!(warning
+            .message
+            .contains("/ws are found by scanning every 2000ms"));
+        assert!(warning.message.contains(MAX_USER_WATCHES_SYSCTL));
+        assert_eq!(
+            warning.sysctl.is_some(),
+            cfg!(target_os = "linux"),
+            "{:?}",
+            warning
+        );
+    }
+}
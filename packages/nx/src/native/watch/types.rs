@@ -7,7 +7,7 @@ use watchexec_events::filekind::ModifyKind::Name;
 use watchexec_events::filekind::RenameMode;
 use watchexec_events::{Event, Tag};
 
-use crate::native::watch::utils::transform_event;
+use crate::native::watch::utils::{relative_to_origin, transform_event};
 
 #[napi(string_enum)]
 #[derive(Debug)]
@@ -18,6 +18,30 @@ pub enum EventType {
     update,
     #[allow(non_camel_case_types)]
     create,
+    #[allow(non_camel_case_types)]
+    moved,
+}
+
+/// How the events of a batch are coalesced into a single event per path
+#[napi(string_enum)]
+#[derive(Debug)]
+pub enum EventCoalescing {
+    /// Delete > Create > Modify, for consumers that process batches of changes
+    #[allow(non_camel_case_types)]
+    batched,
+    /// The latest event of a path wins, for consumers that need to follow every change
+    #[allow(non_camel_case_types)]
+    lastWriteWins,
+}
+
+#[napi(object)]
+pub struct WatchOptions {
+    /// How long to wait for more events before sending a batch of events, in milliseconds
+    pub debounce: Option<u32>,
+    pub coalescing: Option<EventCoalescing>,
+    /// How often the roots that cannot be watched (e.g. because the watch limit of the OS was reached)
+    /// are scanned for changes instead, in milliseconds. Defaults to 2000
+    pub poll_interval: Option<u32>,
 }
 
 #[derive(Debug, Clone)]
@@ -25,23 +49,32 @@ pub enum EventType {
 pub struct WatchEvent {
     pub path: String,
     pub r#type: EventType,
+    /// The previous path of moved files
+    pub from: Option<String>,
+    /// The new path of moved files, same as `path`
+    pub to: Option<String>,
 }
 
 impl From<&WatchEventInternal> for WatchEvent {
     fn from(value: &WatchEventInternal) -> Self {
-        let path = value
-            .path
-            .strip_prefix(&value.origin)
-            .unwrap_or(&value.path)
-            .display()
-            .to_string();
+        let relative_path = |path: &Path| {
+            let path = relative_to_origin(path, value.origin.as_ref())
+                .display()
+                .to_string();
 
-        #[cfg(windows)]
-        let path = path.replace('\\', "/");
+            #[cfg(windows)]
+            let path = path.replace('\\', "/");
 
+            path
+        };
+
+        let path = relative_path(&value.path);
+        let from = value.from.as_deref().map(relative_path);
         WatchEvent {
+            to: from.as_ref().map(|_| path.clone()),
             path,
             r#type: value.r#type,
+            from,
         }
     }
 }
@@ -51,11 +84,15 @@ pub(super) struct WatchEventInternal {
     pub path: PathBuf,
     pub r#type: EventType,
     pub origin: String,
+    pub from: Option<PathBuf>,
 }
 
+/// Transforms a watchexec event into watch events relative to the origin.
+/// The root is the watched root containing the event, which is either the origin or one of the additional roots
 pub fn transform_event_to_watch_events(
     value: &Event,
     origin: &str,
+    #[allow(unused_variables)] root: &str,
 ) -> anyhow::Result<Vec<WatchEventInternal>> {
     let transformed = transform_event(value);
     let value = transformed.as_ref().unwrap_or(value);
@@ -66,8 +103,6 @@ pub fn transform_event_to_watch_events(
         anyhow::bail!(error_msg)
     };
 
-    #[allow(unused_variables)]
-    // this is used in linux and windows blocks, and will show it as being unused in macos
     let Some(event_kind) = value.tags.iter().find_map(|t| match t {
         Tag::FileEventKind(event_kind) => Some(event_kind),
         _ => None,
@@ -77,12 +112,28 @@ pub fn transform_event_to_watch_events(
         anyhow::bail!(error_msg)
     };
 
+    // renames reported with both paths become a single moved event, so the moved file doesn't need to be hashed again
+    if let FileEventKind::Modify(Name(RenameMode::Both)) = event_kind {
+        let mut paths = value.paths();
+        if let (Some((from, _)), Some((to, _))) = (paths.next(), paths.next()) {
+            if to.is_file() {
+                return Ok(vec![WatchEventInternal {
+                    path: to.into(),
+                    r#type: EventType::moved,
+                    origin: origin.to_owned(),
+                    from: Some(from.into()),
+                }]);
+            }
+        }
+    }
+
     let path_ref = path.0;
     if path.1.is_none() && !path_ref.exists() {
         Ok(vec![WatchEventInternal {
             path: path_ref.into(),
             r#type: EventType::delete,
             origin: origin.to_owned(),
+            from: None,
         }])
     } else {
         #[cfg(target_os = "macos")]
@@ -113,6 +164,7 @@ pub fn transform_event_to_watch_events(
                 path: path_ref.into(),
                 r#type: event_type,
                 origin,
+                from: None,
             }])
         }
 
@@ -123,19 +175,19 @@ pub fn transform_event_to_watch_events(
 
         #[cfg(all(not(target_os = "macos"), not(target_os = "windows")))]
         {
-            use crate::native::walker::nx_walker_sync;
+            use crate::native::walker::{nx_walker_sync, SymlinkPolicy};
             use ignore::gitignore::GitignoreBuilder;
             use ignore::Match;
 
             if matches!(event_kind, FileEventKind::Create(CreateKind::Folder)) {
                 let mut result = vec![];
 
-                let mut gitignore_builder = GitignoreBuilder::new(origin);
-                let origin_path: &Path = origin.as_ref();
-                gitignore_builder.add(origin_path.join(".nxignore"));
+                let mut gitignore_builder = GitignoreBuilder::new(root);
+                let root_path: &Path = root.as_ref();
+                gitignore_builder.add(root_path.join(".nxignore"));
                 let ignore = gitignore_builder.build()?;
 
-                for path in nx_walker_sync(path_ref, None) {
+                for path in nx_walker_sync(path_ref, None, SymlinkPolicy::record) {
                     let path = path_ref.join(path);
                     let is_dir = path.is_dir();
                     if is_dir
@@ -151,6 +203,7 @@ pub fn transform_event_to_watch_events(
                         path,
                         r#type: EventType::create,
                         origin: origin.to_owned(),
+                        from: None,
                     });
                 }
 
@@ -181,5 +234,6 @@ fn create_watch_event_internal(
         path: path_ref.into(),
         r#type: event_kind,
         origin: origin.to_owned(),
+        from: None,
     }]
 }
@@ -1,54 +1,86 @@
 use ignore::WalkBuilder;
 use ignore_files::IgnoreFile;
-use std::path::Path;
+use std::path::{Component, Path};
 use std::{fs, path::PathBuf};
 use tracing::trace;
 use watchexec_events::{Event, Tag};
 
+use crate::native::glob::build_glob_set;
+use crate::native::walker::ALWAYS_IGNORED_GLOBS;
+
 pub(super) fn get_ignore_files<T: AsRef<str>>(
     use_ignore: bool,
     root: T,
 ) -> Option<Vec<IgnoreFile>> {
-    let root = root.as_ref();
     if use_ignore {
-        let mut walker = WalkBuilder::new(root);
-        walker.hidden(false);
-        walker.git_ignore(false);
-
-        let node_folder = PathBuf::from(root).join("node_modules");
-        walker.filter_entry(move |entry| !entry.path().starts_with(&node_folder));
-        Some(
-            walker
-                .build()
-                .flatten()
-                .filter(|result| result.path().ends_with(".gitignore"))
-                .map(|result| {
-                    let path: PathBuf = result.path().into();
-                    let parent: PathBuf = path.parent().unwrap_or(&path).into();
-                    IgnoreFile {
-                        path,
-                        applies_in: Some(parent),
-                        applies_to: None,
-                    }
-                })
-                .collect(),
-        )
+        Some(find_ignore_files(root.as_ref(), ".gitignore"))
     } else {
         None
     }
 }
 
-pub(super) fn get_nx_ignore<P: AsRef<Path>>(origin: P) -> Option<IgnoreFile> {
-    let nx_ignore_path = PathBuf::from(origin.as_ref()).join(".nxignore");
-    if nx_ignore_path.exists() {
-        Some(IgnoreFile {
-            path: nx_ignore_path,
-            applies_in: Some(origin.as_ref().into()),
-            applies_to: None,
+/// Returns the `.nxignore` files of the root and its nested directories
+pub(super) fn get_nx_ignore_files<P: AsRef<Path>>(root: P) -> Vec<IgnoreFile> {
+    find_ignore_files(root.as_ref(), ".nxignore")
+}
+
+/// Finds the ignore files with the given name in the root and its nested directories,
+/// each of them applies to the directory that contains it.
+/// Directories that are always ignored (like `node_modules` of nested packages) are not searched
+fn find_ignore_files<P: AsRef<Path>>(root: P, file_name: &str) -> Vec<IgnoreFile> {
+    let ignore_glob_set =
+        build_glob_set(&ALWAYS_IGNORED_GLOBS).expect("These static ignores always build");
+
+    let mut walker = WalkBuilder::new(root);
+    walker.hidden(false);
+    walker.git_ignore(false);
+    walker.filter_entry(move |entry| {
+        let path = entry.path().to_string_lossy();
+        !ignore_glob_set.is_match(path.as_ref())
+    });
+
+    walker
+        .build()
+        .flatten()
+        .filter(|result| {
+            result.file_name() == file_name && result.file_type().is_some_and(|t| t.is_file())
         })
-    } else {
-        None
+        .map(|result| {
+            let path: PathBuf = result.path().into();
+            let parent: PathBuf = path.parent().unwrap_or(&path).into();
+            IgnoreFile {
+                path,
+                applies_in: Some(parent),
+                applies_to: None,
+            }
+        })
+        .collect()
+}
+
+/// Returns the path relative to the origin.
+/// Paths outside of the origin (in additional roots) walk up from the origin with `..`
+pub(super) fn relative_to_origin(path: &Path, origin: &Path) -> PathBuf {
+    if let Ok(relative) = path.strip_prefix(origin) {
+        return relative.into();
+    }
+
+    let path_components = path.components().collect::<Vec<_>>();
+    let origin_components = origin.components().collect::<Vec<_>>();
+    let common = path_components
+        .iter()
+        .zip(&origin_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+    // paths on another drive can't be made relative
+    if common == 0 {
+        return path.into();
     }
+
+    origin_components[common..]
+        .iter()
+        .map(|_| Component::ParentDir)
+        .chain(path_components[common..].iter().copied())
+        .collect()
 }
 
 pub(super) fn transform_event(watch_event: &Event) -> Option<Event> {
@@ -79,3 +111,67 @@ pub(super) fn transform_event(watch_event: &Event) -> Option<Event> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn should_find_nested_ignore_files() {
+        let temp = TempDir::new().unwrap();
+        temp.child(".gitignore").write_str("dist").unwrap();
+        temp.child(".nxignore").write_str("tmp").unwrap();
+        temp.child("packages/a/.gitignore")
+            .write_str("build")
+            .unwrap();
+        temp.child("packages/a/.nxignore").write_str("out").unwrap();
+        temp.child("packages/a/node_modules/dep/.gitignore")
+            .write_str("*")
+            .unwrap();
+
+        let mut git_ignores = get_ignore_files(true, temp.to_string_lossy())
+            .unwrap()
+            .into_iter()
+            .map(|file| (file.path, file.applies_in.unwrap()))
+            .collect::<Vec<_>>();
+        git_ignores.sort();
+        assert_eq!(
+            git_ignores,
+            [
+                (temp.join(".gitignore"), temp.to_path_buf()),
+                (temp.join("packages/a/.gitignore"), temp.join("packages/a")),
+            ]
+        );
+
+        let mut nx_ignores = get_nx_ignore_files(&temp)
+            .into_iter()
+            .map(|file| file.path)
+            .collect::<Vec<_>>();
+        nx_ignores.sort();
+        assert_eq!(
+            nx_ignores,
+            [temp.join(".nxignore"), temp.join("packages/a/.nxignore")]
+        );
+        assert!(get_ignore_files(false, temp.to_string_lossy()).is_none());
+    }
+
+    #[test]
+    fn should_make_paths_relative_to_the_origin() {
+        let origin = Path::new("/workspace/repo/");
+        assert_eq!(
+            relative_to_origin(Path::new("/workspace/repo/libs/a.ts"), origin),
+            PathBuf::from("libs/a.ts")
+        );
+        assert_eq!(
+            relative_to_origin(Path::new("/workspace/shared-lib/src/index.ts"), origin),
+            PathBuf::from("../shared-lib/src/index.ts")
+        );
+        assert_eq!(
+            relative_to_origin(Path::new("/other/index.ts"), origin),
+            PathBuf::from("../../other/index.ts")
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use ignore::Match;
 use tracing::trace;
 use watchexec::error::RuntimeError;
@@ -8,7 +10,7 @@ use ignore_files::IgnoreFilter;
 use watchexec_events::{Event, FileType, Priority, Source, Tag};
 use watchexec_filterer_ignore::IgnoreFilterer;
 
-use crate::native::watch::utils::{get_ignore_files, get_nx_ignore, transform_event};
+use crate::native::watch::utils::{get_ignore_files, get_nx_ignore_files, transform_event};
 
 #[derive(Debug)]
 pub struct WatchFilterer {
@@ -113,18 +115,64 @@ impl Filterer for WatchFilterer {
     }
 }
 
+/// Filters events with the ignore files of the watched root that contains them
+#[derive(Debug)]
+pub struct MultiRootFilterer {
+    /// The filterers of the watched roots, the first one is the filterer of the origin
+    roots: Vec<(PathBuf, WatchFilterer)>,
+}
+
+impl Filterer for MultiRootFilterer {
+    fn check_event(&self, watch_event: &Event, priority: Priority) -> Result<bool, RuntimeError> {
+        let transformed = transform_event(watch_event);
+        let event = transformed.as_ref().unwrap_or(watch_event);
+
+        let filterer = event
+            .paths()
+            .next()
+            .and_then(|(path, _)| {
+                self.roots
+                    .iter()
+                    .filter(|(root, _)| path.starts_with(root))
+                    .max_by_key(|(root, _)| root.as_os_str().len())
+            })
+            .or(self.roots.first());
+
+        match filterer {
+            Some((_, filterer)) => filterer.check_event(watch_event, priority),
+            None => Ok(false),
+        }
+    }
+}
+
+pub(super) async fn create_multi_root_filter(
+    roots: &[String],
+    additional_globs: &[String],
+    use_ignore: bool,
+) -> anyhow::Result<MultiRootFilterer> {
+    let mut filterers = Vec::with_capacity(roots.len());
+    for root in roots {
+        filterers.push((
+            PathBuf::from(root),
+            create_filter(root, additional_globs, use_ignore).await?,
+        ));
+    }
+    Ok(MultiRootFilterer { roots: filterers })
+}
+
 pub(super) async fn create_filter(
     origin: &str,
     additional_globs: &[String],
     use_ignore: bool,
 ) -> anyhow::Result<WatchFilterer> {
     let ignore_files = get_ignore_files(use_ignore, origin);
-    let nx_ignore_file = get_nx_ignore(origin);
+    let nx_ignore_files = get_nx_ignore_files(origin);
 
     trace!(
         ?use_ignore,
         ?additional_globs,
         ?ignore_files,
+        ?nx_ignore_files,
         "Using these ignore files for the watcher"
     );
     let mut git_ignore = if let Some(ignore_files) = ignore_files {
@@ -145,14 +193,14 @@ pub(super) async fn create_filter(
         )
         .map_err(anyhow::Error::from)?;
 
-    let nx_ignore = if let Some(nx_ignore_file) = nx_ignore_file {
+    let nx_ignore = if nx_ignore_files.is_empty() {
+        None
+    } else {
         Some(
-            IgnoreFilter::new(origin, &[nx_ignore_file])
+            IgnoreFilter::new(origin, &nx_ignore_files)
                 .await
                 .map_err(anyhow::Error::from)?,
         )
-    } else {
-        None
     };
 
     Ok(WatchFilterer {
@@ -1,11 +1,17 @@
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
-use std::path::MAIN_SEPARATOR;
+use std::path::{Path, MAIN_SEPARATOR};
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
 
+use crate::native::watch::health::WATCHER_COUNTERS;
+use crate::native::watch::poller::{Poller, WatcherWarning, DEFAULT_POLL_INTERVAL};
 use crate::native::watch::types::{
-    transform_event_to_watch_events, EventType, WatchEvent, WatchEventInternal,
+    transform_event_to_watch_events, EventCoalescing, EventType, WatchEvent, WatchEventInternal,
+    WatchOptions,
 };
+use crate::native::watch::utils::transform_event;
 use crate::native::watch::watch_filterer;
 use napi::bindgen_prelude::*;
 use napi::threadsafe_function::{
@@ -13,9 +19,9 @@ use napi::threadsafe_function::{
 };
 use napi::{Env, JsFunction, JsObject};
 use rayon::prelude::*;
-use tracing::trace;
+use tracing::{debug, trace, warn};
 use tracing_subscriber::EnvFilter;
-use watchexec::Watchexec;
+use watchexec::{ErrorHook, Watchexec};
 use watchexec_events::{Event, Priority, Tag};
 use watchexec_signals::Signal;
 
@@ -25,6 +31,8 @@ pub struct Watcher {
     watch_exec: Arc<Watchexec>,
     additional_globs: Vec<String>,
     use_ignore: bool,
+    additional_roots: Vec<String>,
+    poller: Option<Arc<Poller>>,
 }
 
 #[napi]
@@ -34,11 +42,15 @@ impl Watcher {
     /// * .git/
     /// * node_modules/
     /// * .nx/
+    ///
+    /// Additional roots (absolute or relative to the origin) are watched as well,
+    /// events for files in them have paths relative to the origin (e.g. `../shared-lib/index.ts`)
     #[napi(constructor)]
     pub fn new(
         origin: String,
         additional_globs: Option<Vec<String>>,
         use_ignore: Option<bool>,
+        additional_roots: Option<Vec<String>>,
     ) -> Watcher {
         // always have these globs come before the additional globs
         let mut globs = vec![
@@ -53,24 +65,38 @@ impl Watcher {
             globs.extend(additional_globs);
         }
 
+        let origin = if cfg!(windows) {
+            origin.replace('/', "\\")
+        } else {
+            origin
+        };
+        let additional_roots = additional_roots
+            .unwrap_or_default()
+            .iter()
+            .map(|root| resolve_root(&origin, root))
+            .collect();
+
         Watcher {
-            origin: if cfg!(windows) {
-                origin.replace('/', "\\")
-            } else {
-                origin
-            },
+            origin,
             watch_exec: Arc::new(Watchexec::default()),
             additional_globs: globs,
             use_ignore: use_ignore.unwrap_or(true),
+            additional_roots,
+            poller: None,
         }
     }
 
+    /// Watches the roots for changes.
+    /// When some changes could be missed (e.g. because the watch limit of the OS was reached), the roots that cannot be
+    /// fully watched are scanned for changes periodically instead, and `on_warning` is called with the reason
     #[napi]
     pub fn watch(
         &mut self,
         env: Env,
         #[napi(ts_arg_type = "(err: string | null, events: WatchEvent[]) => void")]
         callback: JsFunction,
+        options: Option<WatchOptions>,
+        #[napi(ts_arg_type = "(warning: WatcherWarning) => void")] on_warning: Option<JsFunction>,
     ) -> Result<()> {
         _ = tracing_subscriber::fmt()
             .with_env_filter(EnvFilter::from_env("NX_NATIVE_LOGGING"))
@@ -95,19 +121,72 @@ impl Watcher {
 
         callback_tsfn.unref(&env)?;
 
+        let warning_tsfn: Option<ThreadsafeFunction<WatcherWarning>> = on_warning
+            .map(|on_warning| {
+                let mut tsfn = on_warning.create_threadsafe_function(
+                    0,
+                    |ctx: ThreadSafeCallContext<WatcherWarning>| Ok(vec![ctx.value]),
+                )?;
+                tsfn.unref(&env)?;
+                Ok::<_, napi::Error>(tsfn)
+            })
+            .transpose()?;
+
+        let coalescing = options
+            .as_ref()
+            .and_then(|options| options.coalescing)
+            .unwrap_or(EventCoalescing::batched);
+        if let Some(debounce) = options.as_ref().and_then(|options| options.debounce) {
+            self.watch_exec
+                .config
+                .throttle(Duration::from_millis(debounce.into()));
+        }
+
+        let poll_interval = options
+            .as_ref()
+            .and_then(|options| options.poll_interval)
+            .map_or(DEFAULT_POLL_INTERVAL, |interval| {
+                Duration::from_millis(interval.into())
+            });
+        let poller = Arc::new(Poller::new(self.roots(), self.use_ignore, poll_interval));
+        self.poller = Some(Arc::clone(&poller));
+        // the hooks are part of the config of watchexec, so they can't hold on to it
+        let watch_exec = Arc::downgrade(&self.watch_exec);
+        self.watch_exec.config.on_error(move |hook: ErrorHook| {
+            let Some(watch_exec) = watch_exec.upgrade() else {
+                return;
+            };
+            WATCHER_COUNTERS.errors.fetch_add(1, Ordering::Relaxed);
+            match poller.fall_back(&hook.error, &watch_exec) {
+                Some(warning) => {
+                    warn!("{}", warning.message);
+                    WATCHER_COUNTERS
+                        .polled_roots
+                        .store(warning.polled_roots.len() as u32, Ordering::Relaxed);
+                    if let Some(warning_tsfn) = &warning_tsfn {
+                        warning_tsfn.call(Ok(warning), ThreadsafeFunctionCallMode::NonBlocking);
+                    }
+                }
+                None => debug!(error = ?hook.error, "watcher error"),
+            }
+        });
+
         let origin = self.origin.clone();
+        let roots = self.roots();
         self.watch_exec.config.on_action(move |mut action| {
             let signals: Vec<Signal> = action.signals().collect();
-
-            if signals.contains(&Signal::Terminate) {
-                trace!("terminate - ending watch");
+            // the file events that were queued with the signal are still sent, so no change is lost when stopping
+            let quit = signals.contains(&Signal::Terminate) || signals.contains(&Signal::Interrupt);
+            if quit {
+                trace!(?signals, "ending watch");
                 action.quit();
-                return action;
             }
-
-            if signals.contains(&Signal::Interrupt) {
-                trace!("interrupt - ending watch");
-                action.quit();
+            let file_events = action
+                .events
+                .iter()
+                .filter(|ev| ev.signals().next().is_none())
+                .collect::<Vec<_>>();
+            if quit && file_events.is_empty() {
                 return action;
             }
 
@@ -117,53 +196,57 @@ impl Watcher {
             }
             trace!(?origin_path);
 
-            let events = action
-                .events
+            let events = file_events
                 .par_iter()
-                .filter_map(|ev| transform_event_to_watch_events(ev, &origin_path).ok())
+                .filter_map(|ev| {
+                    let root = find_root(&roots, ev).unwrap_or(&origin);
+                    let events = transform_event_to_watch_events(ev, &origin_path, root).ok();
+                    if events.is_none() {
+                        WATCHER_COUNTERS
+                            .dropped_events
+                            .fetch_add(1, Ordering::Relaxed);
+                    }
+                    events
+                })
                 .flatten()
                 .collect::<Vec<WatchEventInternal>>();
 
-            let mut group_events: HashMap<String, WatchEventInternal> = HashMap::new();
-            for g in events.into_iter() {
-                let path = g.path.display().to_string();
-
-                // Delete > Create > Modify
-                match group_events.entry(path) {
-                    // Delete should override anything
-                    Entry::Occupied(mut e) if matches!(g.r#type, EventType::delete) => {
-                        e.insert(g);
-                    }
-                    // Create should override update
-                    Entry::Occupied(mut e)
-                        if matches!(g.r#type, EventType::create)
-                            && matches!(e.get().r#type, EventType::update) =>
-                    {
-                        e.insert(g);
-                    }
-                    Entry::Occupied(_) => {}
-                    // If its empty, insert
-                    Entry::Vacant(e) => {
-                        e.insert(g);
-                    }
-                }
+            let group_events = coalesce_events(events, coalescing);
+            let sent_events = group_events.len() as u64;
+            WATCHER_COUNTERS
+                .event_batches
+                .fetch_add(1, Ordering::Relaxed);
+            WATCHER_COUNTERS
+                .events
+                .fetch_add(sent_events, Ordering::Relaxed);
+            let status =
+                callback_tsfn.call(Ok(group_events), ThreadsafeFunctionCallMode::NonBlocking);
+            if status != napi::Status::Ok {
+                warn!(?status, "could not send {} watch events", sent_events);
+                WATCHER_COUNTERS
+                    .dropped_events
+                    .fetch_add(sent_events, Ordering::Relaxed);
             }
-            callback_tsfn.call(Ok(group_events), ThreadsafeFunctionCallMode::NonBlocking);
 
             action
         });
 
-        let origin = self.origin.clone();
+        let roots = self.roots();
         let additional_globs = self.additional_globs.clone();
         let use_ignore = self.use_ignore;
         let watch_exec = self.watch_exec.clone();
         let start = async move {
-            trace!("configuring watch exec");
-            watch_exec.config.pathset([&origin.as_str()]);
+            trace!(?roots, "configuring watch exec");
+            watch_exec.config.pathset(roots.iter());
             watch_exec.config.filterer(
-                watch_filterer::create_filter(&origin, &additional_globs, use_ignore).await?,
+                watch_filterer::create_multi_root_filter(&roots, &additional_globs, use_ignore)
+                    .await?,
             );
             trace!("starting watch exec");
+            WATCHER_COUNTERS.watching.store(true, Ordering::Relaxed);
+            WATCHER_COUNTERS
+                .watched_roots
+                .store(roots.len() as u32, Ordering::Relaxed);
             watch_exec.main().await.map_err(anyhow::Error::from)?.ok();
             Ok(())
         };
@@ -176,6 +259,10 @@ impl Watcher {
     #[napi(ts_return_type = "Promise<void>")]
     pub fn stop(&mut self, env: Env) -> Result<JsObject> {
         trace!("stopping the watch process");
+        WATCHER_COUNTERS.watching.store(false, Ordering::Relaxed);
+        if let Some(poller) = &self.poller {
+            poller.stop();
+        }
         let watch_exec = self.watch_exec.clone();
         let send_terminate = async move {
             watch_exec
@@ -194,4 +281,162 @@ impl Watcher {
 
         env.spawn_future(send_terminate)
     }
+
+    /// The origin followed by the additional roots
+    fn roots(&self) -> Vec<String> {
+        std::iter::once(self.origin.clone())
+            .chain(self.additional_roots.iter().cloned())
+            .collect()
+    }
+}
+
+/// Coalesces the events of a batch into a single event per path
+fn coalesce_events(
+    events: Vec<WatchEventInternal>,
+    coalescing: EventCoalescing,
+) -> HashMap<String, WatchEventInternal> {
+    let mut group_events: HashMap<String, WatchEventInternal> = HashMap::new();
+    for g in events.into_iter() {
+        let path = g.path.display().to_string();
+
+        if matches!(coalescing, EventCoalescing::lastWriteWins) {
+            group_events.insert(path, g);
+            continue;
+        }
+
+        // Delete > Create > Modify
+        match group_events.entry(path) {
+            // Delete should override anything
+            Entry::Occupied(mut e) if matches!(g.r#type, EventType::delete) => {
+                e.insert(g);
+            }
+            // Create should override update
+            Entry::Occupied(mut e)
+                if matches!(g.r#type, EventType::create)
+                    && matches!(e.get().r#type, EventType::update) =>
+            {
+                e.insert(g);
+            }
+            // Moved should override create and update
+            Entry::Occupied(mut e)
+                if matches!(g.r#type, EventType::moved)
+                    && matches!(e.get().r#type, EventType::create | EventType::update) =>
+            {
+                e.insert(g);
+            }
+            Entry::Occupied(_) => {}
+            // If its empty, insert
+            Entry::Vacant(e) => {
+                e.insert(g);
+            }
+        }
+    }
+
+    // the previous paths of moved files are part of the moved events
+    let moved_from = group_events
+        .values()
+        .filter_map(|e| e.from.as_ref().map(|from| from.display().to_string()))
+        .collect::<Vec<_>>();
+    for from in moved_from {
+        if matches!(
+            group_events.get(&from).map(|e| e.r#type),
+            Some(EventType::delete)
+        ) {
+            group_events.remove(&from);
+        }
+    }
+
+    group_events
+}
+
+/// Resolves a root relative to the origin, following symlinks so that it matches the paths of the events
+fn resolve_root(origin: &str, root: &str) -> String {
+    let root = Path::new(origin).join(root);
+    dunce::canonicalize(&root)
+        .unwrap_or(root)
+        .display()
+        .to_string()
+}
+
+/// Finds the most specific root containing the event, so roots nested in other roots get their own events
+fn find_root<'a>(roots: &'a [String], event: &Event) -> Option<&'a str> {
+    let transformed = transform_event(event);
+    let (path, _) = transformed.as_ref().unwrap_or(event).paths().next()?;
+    roots
+        .iter()
+        .filter(|root| path.starts_with(root))
+        .max_by_key(|root| root.len())
+        .map(String::as_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn event(path: &str, r#type: EventType) -> WatchEventInternal {
+        WatchEventInternal {
+            path: PathBuf::from(path),
+            r#type,
+            origin: "/root/".into(),
+            from: None,
+        }
+    }
+
+    fn types(events: &HashMap<String, WatchEventInternal>) -> Vec<(&str, String)> {
+        let mut types = events
+            .iter()
+            .map(|(path, event)| (path.as_str(), format!("{:?}", event.r#type)))
+            .collect::<Vec<_>>();
+        types.sort_by_key(|(path, _)| *path);
+        types
+    }
+
+    #[test]
+    fn should_coalesce_batched_events_by_priority() {
+        let events = coalesce_events(
+            vec![
+                event("/root/a.ts", EventType::delete),
+                event("/root/a.ts", EventType::create),
+                event("/root/b.ts", EventType::update),
+                event("/root/b.ts", EventType::create),
+                event("/root/b.ts", EventType::update),
+            ],
+            EventCoalescing::batched,
+        );
+        assert_eq!(
+            types(&events),
+            [
+                ("/root/a.ts", "delete".to_string()),
+                ("/root/b.ts", "create".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn should_coalesce_events_with_last_write_wins() {
+        let moved = WatchEventInternal {
+            from: Some(PathBuf::from("/root/c.ts")),
+            ..event("/root/d.ts", EventType::moved)
+        };
+        let events = coalesce_events(
+            vec![
+                event("/root/a.ts", EventType::delete),
+                event("/root/a.ts", EventType::create),
+                event("/root/b.ts", EventType::create),
+                event("/root/b.ts", EventType::update),
+                event("/root/c.ts", EventType::delete),
+                moved,
+            ],
+            EventCoalescing::lastWriteWins,
+        );
+        assert_eq!(
+            types(&events),
+            [
+                ("/root/a.ts", "create".to_string()),
+                ("/root/b.ts", "update".to_string()),
+                ("/root/d.ts", "moved".to_string())
+            ]
+        );
+    }
 }
@@ -0,0 +1,228 @@
+use std::path::Path;
+
+use anyhow::Context;
+use regex::{Regex, RegexBuilder};
+use tracing::trace_span;
+
+use crate::native::glob::build_ordered_glob_set;
+use crate::native::utils::parallel::prelude::*;
+use crate::native::walker::{nx_walker, SymlinkPolicy};
+
+/// Files with a NUL byte in their first bytes are binary, like ripgrep detects them
+const BINARY_DETECTION_LENGTH: usize = 8 * 1024;
+
+#[napi(object)]
+#[derive(Default)]
+pub struct WorkspaceSearchOptions {
+    /// Matches the pattern regardless of case
+    pub ignore_case: Option<bool>,
+    /// The maximum number of matches, the first files in path order are returned
+    pub limit: Option<u32>,
+}
+
+/// A line of a file of the workspace that matches the searched pattern
+#[napi(object)]
+#[derive(Debug, PartialEq)]
+pub struct WorkspaceSearchMatch {
+    /// The path of the file from the workspace root
+    pub file: String,
+    /// The number of the line in the file, from 1
+    pub line: u32,
+    /// The column where the match starts in the line, in characters from 1
+    pub column: u32,
+    pub text: String,
+}
+
+#[napi]
+/// Searches the files of the workspace for the lines that match the regular expression `pattern`, like ripgrep does,
+/// so generators and migrations can find the usages of an import or an API without reading every file in JS.
+///
+/// Only the files that match `include_globs` (e.g. `libs/**`, `!libs/legacy/**`) are searched, every file when there
+/// are none. Later globs override earlier ones, as in a .gitignore. Files that are ignored by .gitignore and
+/// .nxignore, binary files and files that are not UTF-8 are skipped. `^` and `$` match at the start and the end of
+/// the lines, and a match that spans lines is reported on the line where it starts.
+///
+/// The files are read from the disk, so the changes of a generator that were not written yet are not searched.
+/// Matches are sorted by file, then by line
+pub fn search_workspace(
+    workspace_root: String,
+    pattern: String,
+    include_globs: Vec<String>,
+    options: Option<WorkspaceSearchOptions>,
+) -> anyhow::Result<Vec<WorkspaceSearchMatch>> {
+    let _span = trace_span!("search_workspace", pattern).entered();
+    let options = options.unwrap_or_default();
+    let regex = RegexBuilder::new(&pattern)
+        .multi_line(true)
+        .crlf(true)
+        .case_insensitive(options.ignore_case.unwrap_or_default())
+        .build()
+        .with_context(|| format!("{} is not a valid pattern", pattern))?;
+    let globs = build_ordered_glob_set(&include_globs)?;
+
+    let files = nx_walker(&workspace_root, true, SymlinkPolicy::default())
+        .filter(|file| globs.is_match(&file.normalized_path))
+        .collect::<Vec<_>>();
+
+    let mut files = files
+        .into_par_iter()
+        .map(|file| {
+            let matches = search_file(&regex, Path::new(&file.full_path));
+            (file.normalized_path, matches)
+        })
+        .filter(|(_, matches)| !matches.is_empty())
+        .collect::<Vec<_>>();
+    files.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+    let limit = options.limit.map_or(usize::MAX, |limit| limit as usize);
+    Ok(files
+        .into_iter()
+        .flat_map(|(file, matches)| {
+            matches
+                .into_iter()
+                .map(move |(line, column, text)| WorkspaceSearchMatch {
+                    file: file.clone(),
+                    line,
+                    column,
+                    text,
+                })
+        })
+        .take(limit)
+        .collect())
+}
+
+/// The line, the column and the text of the lines of a file that match. A line is reported once, at its first match,
+/// and a file that cannot be read, is binary or is not UTF-8 has none
+fn search_file(regex: &Regex, path: &Path) -> Vec<(u32, u32, String)> {
+    let Ok(contents) = std::fs::read(path) else {
+        return vec![];
+    };
+    let detected = &contents[..contents.len().min(BINARY_DETECTION_LENGTH)];
+    if detected.contains(&0) {
+        return vec![];
+    }
+    let Ok(contents) = String::from_utf8(contents) else {
+        return vec![];
+    };
+
+    let mut matches = vec![];
+    let mut line = 1;
+    let mut line_start = 0;
+    // the start of the line after the last reported line
+    let mut searched_until = 0;
+    for found in regex.find_iter(&contents) {
+        if found.start() < searched_until {
+            continue;
+        }
+        // an empty match after the last newline is not on a line
+        if found.start() == contents.len() && contents.ends_with('\n') {
+            break;
+        }
+        let before = &contents[line_start..found.start()];
+        if let Some(last_newline) = before.rfind('\n') {
+            line += before.matches('\n').count() as u32;
+            line_start += last_newline + 1;
+        }
+        let line_end = contents[found.start()..]
+            .find('\n')
+            .map_or(contents.len(), |end| found.start() + end);
+        let column = contents[line_start..found.start()].chars().count() as u32 + 1;
+        let text = contents[line_start..line_end].trim_end_matches('\r');
+        matches.push((line, column, text.to_string()));
+        searched_until = line_end + 1;
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    fn search(
+        temp: &TempDir,
+        pattern: &str,
+        include_globs: &[&str],
+        options: Option<WorkspaceSearchOptions>,
+    ) -> Vec<(String, u32, u32, String)> {
+        search_workspace(
+            temp.display().to_string(),
+            pattern.into(),
+            include_globs.iter().map(|glob| glob.to_string()).collect(),
+            options,
+        )
+        .unwrap()
+        .into_iter()
+        .map(|found| (found.file, found.line, found.column, found.text))
+        .collect()
+    }
+
+    #[test]
+    fn should_find_the_matching_lines_of_the_included_files() {
+        let temp = TempDir::new().unwrap();
+        temp.child(".gitignore").write_str("dist").unwrap();
+        temp.child("libs/a/src/index.ts")
+            .write_str("import { foo } from 'old-lib';\r\n\r\nconst é = foo(); foo();\r\n")
+            .unwrap();
+        temp.child("libs/a/src/index.spec.ts")
+            .write_str("import { foo } from 'old-lib';\n")
+            .unwrap();
+        temp.child("apps/b/main.ts")
+            .write_str("export * from 'other-lib';\nimport 'old-lib/styles';")
+            .unwrap();
+        temp.child("apps/b/image.ts")
+            .write_binary(b"old-lib\0")
+            .unwrap();
+        temp.child("dist/index.ts")
+            .write_str("import { foo } from 'old-lib';\n")
+            .unwrap();
+
+        assert_eq!(
+            search(&temp, "'old-lib", &["**/*.ts", "!**/*.spec.ts"], None),
+            [
+                (
+                    "apps/b/main.ts".to_string(),
+                    2,
+                    8,
+                    "import 'old-lib/styles';".to_string()
+                ),
+                (
+                    "libs/a/src/index.ts".to_string(),
+                    1,
+                    21,
+                    "import { foo } from 'old-lib';".to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            search(&temp, r"foo\(\);$", &["libs/**"], None),
+            [(
+                "libs/a/src/index.ts".to_string(),
+                3,
+                18,
+                "const é = foo(); foo();".to_string()
+            )]
+        );
+        assert_eq!(
+            search(
+                &temp,
+                "^IMPORT",
+                &[],
+                Some(WorkspaceSearchOptions {
+                    ignore_case: Some(true),
+                    limit: Some(2),
+                })
+            )
+            .into_iter()
+            .map(|(file, line, _, _)| (file, line))
+            .collect::<Vec<_>>(),
+            [
+                ("apps/b/main.ts".to_string(), 2),
+                ("libs/a/src/index.spec.ts".to_string(), 1)
+            ]
+        );
+        assert!(search_workspace(temp.display().to_string(), "(".into(), vec![], None).is_err());
+    }
+}
@@ -1,38 +1,64 @@
-use rayon::prelude::*;
-
-use crate::native::glob::build_glob_set;
+use crate::native::glob::{build_glob_set_with_options, GlobOptions, NxGlobSet};
 use crate::native::types::FileData;
+use crate::native::utils::parallel::prelude::*;
 
-/// Get workspace config files based on provided globs
-pub(super) fn glob_files(
-    files: &[FileData],
-    globs: Vec<String>,
-    exclude: Option<Vec<String>>,
-) -> napi::Result<impl ParallelIterator<Item = &FileData>> {
-    let globs = build_glob_set(&globs)?;
-
-    let exclude_glob_set = match exclude {
-        Some(exclude) => {
-            if exclude.is_empty() {
-                None
-            } else {
-                Some(build_glob_set(&exclude)?)
+/// Globs used to match workspace files, with an optional set of globs to exclude.
+/// Globs are case sensitive, unless `case_insensitive` is given or `NX_GLOB_CASE_INSENSITIVE=true` is set
+pub(super) struct FileGlobs {
+    globs: NxGlobSet,
+    exclude_glob_set: Option<NxGlobSet>,
+}
+
+impl FileGlobs {
+    pub fn new(
+        globs: Vec<String>,
+        exclude: Option<Vec<String>>,
+        case_insensitive: Option<bool>,
+    ) -> napi::Result<Self> {
+        let options = GlobOptions::with_case_insensitive(case_insensitive);
+        let globs = build_glob_set_with_options(&globs, options)?;
+
+        let exclude_glob_set = match exclude {
+            Some(exclude) => {
+                if exclude.is_empty() {
+                    None
+                } else {
+                    Some(build_glob_set_with_options(&exclude, options)?)
+                }
             }
-        }
-        None => None,
-    };
+            None => None,
+        };
 
-    Ok(files.par_iter().filter(move |file_data| {
-        let path = &file_data.file;
-        let is_match = globs.is_match(path);
+        Ok(Self {
+            globs,
+            exclude_glob_set,
+        })
+    }
+
+    pub fn is_match(&self, path: &str) -> bool {
+        let is_match = self.globs.is_match(path);
 
         if !is_match {
             return is_match;
         }
 
-        exclude_glob_set
+        self.exclude_glob_set
             .as_ref()
             .map(|exclude_glob_set| !exclude_glob_set.is_match(path))
             .unwrap_or(is_match)
-    }))
+    }
+}
+
+/// Get workspace config files based on provided globs
+pub(super) fn glob_files(
+    files: &[FileData],
+    globs: Vec<String>,
+    exclude: Option<Vec<String>>,
+    case_insensitive: Option<bool>,
+) -> napi::Result<impl ParallelIterator<Item = &FileData>> {
+    let file_globs = FileGlobs::new(globs, exclude, case_insensitive)?;
+
+    Ok(files
+        .par_iter()
+        .filter(move |file_data| file_globs.is_match(&file_data.file)))
 }
@@ -0,0 +1,155 @@
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context};
+use jsonschema::paths::{JSONPointer, PathChunk};
+use jsonschema::{Draft, JSONSchema};
+use once_cell::sync::Lazy;
+use serde_json::Value;
+
+use crate::native::json::{parse_json, JsonPosition};
+
+static NX_JSON_SCHEMA: Lazy<Result<JSONSchema, String>> =
+    Lazy::new(|| compile_schema(include_str!("../../../schemas/nx-schema.json")));
+static PROJECT_JSON_SCHEMA: Lazy<Result<JSONSchema, String>> =
+    Lazy::new(|| compile_schema(include_str!("../../../schemas/project-schema.json")));
+
+/// A problem of a configuration file
+#[napi(object)]
+#[derive(Debug, PartialEq)]
+pub struct ConfigDiagnostic {
+    pub file: String,
+    /// The JSON pointer of the invalid member, which is empty for the root and for syntax errors
+    pub pointer: String,
+    pub message: String,
+    /// Where the invalid member is in the file, or its closest parent
+    pub position: Option<JsonPosition>,
+}
+
+#[napi]
+/// Validates a configuration file (`nx.json` or a `project.json`) against the schema of the files of its name.
+/// Returns the syntax error of the file, or the members that do not match the schema, in the order of the file.
+/// The file is read as JSON with comments and trailing commas, like Nx reads it
+pub fn validate_config(path: String) -> anyhow::Result<Vec<ConfigDiagnostic>> {
+    let schema = match Path::new(&path).file_name().and_then(|name| name.to_str()) {
+        Some("nx.json") => &NX_JSON_SCHEMA,
+        Some("project.json") => &PROJECT_JSON_SCHEMA,
+        _ => bail!(
+            "{} is not a configuration file, only nx.json and project.json files are validated",
+            path
+        ),
+    };
+    let schema = schema
+        .as_ref()
+        .map_err(|e| anyhow!("The schema of {} is invalid: {}", path, e))?;
+    let contents =
+        std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path))?;
+    Ok(validate_contents(&path, &contents, schema))
+}
+
+fn compile_schema(schema: &str) -> Result<JSONSchema, String> {
+    let schema: Value = serde_json::from_str(schema).map_err(|e| e.to_string())?;
+    JSONSchema::options()
+        .with_draft(Draft::Draft7)
+        .compile(&schema)
+        .map_err(|e| e.to_string())
+}
+
+fn validate_contents(file: &str, contents: &str, schema: &JSONSchema) -> Vec<ConfigDiagnostic> {
+    let document = match parse_json(contents) {
+        Ok(document) => document,
+        Err(e) => {
+            return vec![ConfigDiagnostic {
+                file: file.to_string(),
+                pointer: String::new(),
+                message: e.message,
+                position: Some(e.position),
+            }]
+        }
+    };
+
+    let Err(errors) = schema.validate(&document.value) else {
+        return vec![];
+    };
+    let mut diagnostics = errors
+        .map(|error| {
+            let pointer = json_pointer(&error.instance_path);
+            ConfigDiagnostic {
+                file: file.to_string(),
+                position: document.position_of(&pointer),
+                message: error.to_string(),
+                pointer,
+            }
+        })
+        .collect::<Vec<_>>();
+    diagnostics.sort_by_key(|diagnostic| diagnostic.position.map(|position| position.offset));
+    diagnostics
+}
+
+/// The JSON pointer of a path of the instance, like the pointers of the parsed documents
+fn json_pointer(path: &JSONPointer) -> String {
+    path.iter()
+        .map(|chunk| match chunk {
+            PathChunk::Property(property) => {
+                format!("/{}", property.replace('~', "~0").replace('/', "~1"))
+            }
+            PathChunk::Index(index) => format!("/{}", index),
+            PathChunk::Keyword(keyword) => format!("/{}", keyword),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate_project(contents: &str) -> Vec<(String, Option<u32>)> {
+        let schema = PROJECT_JSON_SCHEMA.as_ref().unwrap();
+        validate_contents("libs/lib/project.json", contents, schema)
+            .into_iter()
+            .map(|diagnostic| {
+                let line = diagnostic.position.map(|position| position.line);
+                (diagnostic.pointer, line)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_report_the_members_that_do_not_match_the_schema() {
+        assert_eq!(
+            validate_project(
+                r#"{
+  // comments are allowed
+  "name": "lib",
+  "tags": "scope:shared",
+  "targets": {
+    "build": {
+      "executor": "@nx/js:tsc",
+      "dependsOn": 1,
+    },
+  },
+}"#
+            ),
+            vec![
+                ("/tags".to_string(), Some(4)),
+                ("/targets/build/dependsOn".to_string(), Some(8)),
+            ]
+        );
+        assert_eq!(
+            validate_project(r#"{ "name": "lib", "targets": {} }"#),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn should_report_syntax_errors() {
+        let schema = NX_JSON_SCHEMA.as_ref().unwrap();
+        let diagnostics = validate_contents("nx.json", "{\n  \"targetDefaults\": {\n}", schema);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].pointer, "");
+        assert_eq!(
+            diagnostics[0].position.map(|position| position.line),
+            Some(3)
+        );
+        assert!(validate_config("package.json".into()).is_err());
+    }
+}
@@ -1,22 +1,30 @@
 use std::collections::HashMap;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 
 use napi::bindgen_prelude::External;
-use rayon::prelude::*;
-use tracing::{trace, warn};
+use tracing::{trace, trace_span, warn};
 
 use crate::native::hasher::hash;
 use crate::native::logger::enable_logger;
 use crate::native::project_graph::utils::{find_project_for_path, ProjectRootMappings};
 use crate::native::types::FileData;
-use crate::native::utils::{path::get_child_files, Normalize, NxCondvar, NxMutex};
+use crate::native::utils::parallel::prelude::*;
+use crate::native::utils::{Normalize, NxCondvar, NxMutex};
+use crate::native::walker::SymlinkPolicy;
+use crate::native::workspace::file_index::{directory_range, ProjectRootsIndex};
 use crate::native::workspace::files_archive::{read_files_archive, write_files_archive};
 use crate::native::workspace::files_hashing::{full_files_hash, selective_files_hash};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::native::workspace::git_files;
+use crate::native::workspace::glob_stream::GlobStream;
+use crate::native::workspace::shared_file_map::{project_roots_hash, publish_shared_files};
 use crate::native::workspace::types::{
-    FileMap, NxWorkspaceFilesExternals, ProjectFiles, UpdatedWorkspaceFiles,
+    FileMap, FileMapDelta, NxWorkspaceFilesExternals, ProjectFiles, UpdatedWorkspaceFiles,
 };
+use crate::native::workspace::walk_checkpoint::WalkCheckpoint;
 use crate::native::workspace::{config_files, types::NxWorkspaceFiles, workspace_files};
 
 #[napi]
@@ -24,19 +32,51 @@ pub struct WorkspaceContext {
     pub workspace_root: String,
     workspace_root_path: PathBuf,
     files_worker: FilesWorker,
+    symlink_policy: SymlinkPolicy,
+    file_map_snapshot: NxMutex<Option<HashMap<String, String>>>,
+    /// The project roots of the last `getWorkspaceFiles` call
+    project_roots: NxMutex<ProjectRootsIndex>,
+    /// Incremented whenever the files change
+    files_generation: AtomicU32,
+    /// The generation and the project roots hash of the files that were last published
+    published_files: NxMutex<Option<(u32, String)>>,
 }
 
 type Files = Vec<(PathBuf, String)>;
 
-fn gather_and_hash_files(workspace_root: &Path, cache_dir: String) -> Vec<(PathBuf, String)> {
-    let archived_files = read_files_archive(&cache_dir);
+fn gather_and_hash_files(
+    workspace_root: &Path,
+    cache_dir: String,
+    symlinks: SymlinkPolicy,
+) -> Vec<(PathBuf, String)> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if git_files::is_git_file_discovery_enabled() {
+        match git_files::git_files_hash(workspace_root) {
+            Ok(files) => {
+                let mut files = files
+                    .into_iter()
+                    .map(|(path, hash)| (PathBuf::from(path), hash))
+                    .collect::<Vec<_>>();
+                files.par_sort();
+                return files;
+            }
+            Err(e) => warn!(
+                "could not get the workspace files from git, walking the workspace instead: {:?}",
+                e
+            ),
+        }
+    }
+
+    let checkpoint = WalkCheckpoint::open(workspace_root, &cache_dir);
+    let archived_files = checkpoint.restore(read_files_archive(&cache_dir));
 
     trace!("Gathering files in {}", workspace_root.display());
     let now = std::time::Instant::now();
+    let record_checkpoint = |files: &[_]| checkpoint.record(files);
     let file_hashes = if let Some(archived_files) = archived_files {
-        selective_files_hash(workspace_root, archived_files)
+        selective_files_hash(workspace_root, archived_files, symlinks, record_checkpoint)
     } else {
-        full_files_hash(workspace_root)
+        full_files_hash(workspace_root, symlinks, record_checkpoint)
     };
 
     let mut files = file_hashes
@@ -47,14 +87,86 @@ fn gather_and_hash_files(workspace_root: &Path, cache_dir: String) -> Vec<(PathB
     trace!("hashed and sorted files in {:?}", now.elapsed());
 
     write_files_archive(&cache_dir, file_hashes);
+    checkpoint.clear();
 
     files
 }
 
+/// Hashes the files that changed after the workspace files were gathered, the same way they were gathered
+fn hash_updated_files(
+    workspace_root_path: &Path,
+    updated_files: &[&str],
+    symlinks: SymlinkPolicy,
+) -> HashMap<String, String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if git_files::is_git_file_discovery_enabled() {
+        let existing_files = updated_files
+            .iter()
+            .copied()
+            .filter(|path| workspace_root_path.join(path).is_file())
+            .collect::<Vec<_>>();
+        match git_files::git_hash_objects(workspace_root_path, &existing_files) {
+            Ok(hashes) => {
+                return existing_files
+                    .into_iter()
+                    .map(String::from)
+                    .zip(hashes)
+                    .collect()
+            }
+            Err(e) => warn!("could not hash the updated files with git: {:?}", e),
+        }
+    }
+
+    updated_files
+        .par_iter()
+        .filter_map(|path| {
+            let full_path = workspace_root_path.join(path);
+            if !matches!(symlinks, SymlinkPolicy::follow) && full_path.is_symlink() {
+                let target = std::fs::read_link(&full_path).ok()?;
+                return matches!(symlinks, SymlinkPolicy::record).then(|| {
+                    (
+                        path.to_string(),
+                        hash(target.to_normalized_string().as_bytes()),
+                    )
+                });
+            }
+            let Ok(content) = std::fs::read(&full_path) else {
+                trace!("could not read file: {full_path:?}");
+                return None;
+            };
+            Some((path.to_string(), hash(&content)))
+        })
+        .collect()
+}
+
+fn to_file_data((path, hash): &(PathBuf, String)) -> FileData {
+    FileData {
+        file: path.to_normalized_string(),
+        hash: hash.clone(),
+    }
+}
+
+fn diff_file_map(mut previous: HashMap<String, String>, files: &[FileData]) -> FileMapDelta {
+    let mut delta = FileMapDelta::default();
+    for file in files {
+        match previous.remove(&file.file) {
+            None => delta.created.push(file.clone()),
+            Some(hash) if hash != file.hash => delta.updated.push(file.clone()),
+            Some(_) => {}
+        }
+    }
+    delta.deleted = previous
+        .into_iter()
+        .map(|(file, hash)| FileData { file, hash })
+        .collect();
+    delta.deleted.sort();
+    delta
+}
+
 struct FilesWorker(Option<Arc<(NxMutex<Files>, NxCondvar)>>);
 impl FilesWorker {
     #[cfg(not(target_arch = "wasm32"))]
-    fn gather_files(workspace_root: &Path, cache_dir: String) -> Self {
+    fn gather_files(workspace_root: &Path, cache_dir: String, symlinks: SymlinkPolicy) -> Self {
         if !workspace_root.exists() {
             warn!(
                 "workspace root does not exist: {}",
@@ -72,7 +184,7 @@ impl FilesWorker {
             trace!("Initially locking files");
             let mut workspace_files = lock.lock().expect("Should be the first time locking files");
 
-            let files = gather_and_hash_files(&workspace_root, cache_dir);
+            let files = gather_and_hash_files(&workspace_root, cache_dir, symlinks);
 
             *workspace_files = files;
             let files_len = workspace_files.len();
@@ -86,7 +198,7 @@ impl FilesWorker {
     }
 
     #[cfg(target_arch = "wasm32")]
-    fn gather_files(workspace_root: &Path, cache_dir: String) -> Self {
+    fn gather_files(workspace_root: &Path, cache_dir: String, symlinks: SymlinkPolicy) -> Self {
         if !workspace_root.exists() {
             warn!(
                 "workspace root does not exist: {}",
@@ -97,7 +209,7 @@ impl FilesWorker {
 
         let workspace_root = workspace_root.to_owned();
 
-        let files = gather_and_hash_files(&workspace_root, cache_dir);
+        let files = gather_and_hash_files(&workspace_root, cache_dir, symlinks);
 
         trace!("{} files retrieved", files.len());
 
@@ -107,37 +219,31 @@ impl FilesWorker {
     }
 
     fn get_files(&self) -> Vec<FileData> {
-        if let Some(files_sync) = &self.0 {
-            let (files_lock, cvar) = files_sync.deref();
-
-            trace!("waiting for files to be available");
-            let files = files_lock.lock().expect("Should be able to lock files");
-
-            #[cfg(target_arch = "wasm32")]
-            let files = cvar
-                .wait(files, |guard| guard.len() == 0)
-                .expect("Should be able to wait for files");
-
-            #[cfg(not(target_arch = "wasm32"))]
-            let files = cvar
-                .wait(files, |guard| guard.len() == 0)
-                .expect("Should be able to wait for files");
-
-            let file_data = files
-                .iter()
-                .map(|(path, hash)| FileData {
-                    file: path.to_normalized_string(),
-                    hash: hash.clone(),
-                })
-                .collect();
-
-            drop(files);
-
-            trace!("files are available");
-            file_data
-        } else {
-            vec![]
-        }
+        self.with_files(|files| files.iter().map(to_file_data).collect())
+            .unwrap_or_default()
+    }
+
+    /// Waits for the files to be available, and reads them while they are locked.
+    /// The files are sorted by path, so the files below a directory are next to each other
+    fn with_files<R>(&self, read: impl FnOnce(&Files) -> R) -> Option<R> {
+        let files_sync = self.0.as_ref()?;
+        let (files_lock, cvar) = files_sync.deref();
+
+        trace!("waiting for files to be available");
+        let files = files_lock.lock().expect("Should be able to lock files");
+
+        #[cfg(target_arch = "wasm32")]
+        let files = cvar
+            .wait(files, |guard| guard.len() == 0)
+            .expect("Should be able to wait for files");
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let files = cvar
+            .wait(files, |guard| guard.len() == 0)
+            .expect("Should be able to wait for files");
+
+        trace!("files are available");
+        Some(read(&files))
     }
 
     pub fn update_files(
@@ -145,6 +251,8 @@ impl FilesWorker {
         workspace_root_path: &Path,
         updated_files: Vec<&str>,
         deleted_files_and_directories: Vec<&str>,
+        moved_files: HashMap<String, String>,
+        symlinks: SymlinkPolicy,
     ) -> HashMap<String, String> {
         let Some(files_sync) = &self.0 else {
             trace!("there were no files because the workspace root did not exist");
@@ -157,6 +265,19 @@ impl FilesWorker {
             .expect("Should always be able to update files");
         let mut map: HashMap<PathBuf, String> = files.drain(..).collect();
 
+        // moved files keep their hash, files that were not known before are hashed like updated files
+        let mut moved_files_hashes: HashMap<String, String> = HashMap::new();
+        let mut updated_files: Vec<&str> = updated_files;
+        for (from, to) in moved_files.iter() {
+            match map.remove(&PathBuf::from(from)) {
+                Some(hash) => {
+                    map.insert(PathBuf::from(to), hash.clone());
+                    moved_files_hashes.insert(to.clone(), hash);
+                }
+                None => updated_files.push(to.as_str()),
+            }
+        }
+
         for deleted_path in deleted_files_and_directories {
             // If the path is a file, this removes it.
             let removal = map.remove(&PathBuf::from(deleted_path));
@@ -169,17 +290,8 @@ impl FilesWorker {
             };
         }
 
-        let updated_files_hashes: HashMap<String, String> = updated_files
-            .par_iter()
-            .filter_map(|path| {
-                let full_path = workspace_root_path.join(path);
-                let Ok(content) = std::fs::read(&full_path) else {
-                    trace!("could not read file: {full_path:?}");
-                    return None;
-                };
-                Some((path.to_string(), hash(&content)))
-            })
-            .collect();
+        let mut updated_files_hashes =
+            hash_updated_files(workspace_root_path, &updated_files, symlinks);
 
         for (file, hash) in &updated_files_hashes {
             map.entry(file.into())
@@ -190,24 +302,43 @@ impl FilesWorker {
         *files = map.into_iter().collect();
         files.par_sort();
 
+        for (file, hash) in moved_files_hashes {
+            updated_files_hashes.entry(file).or_insert(hash);
+        }
         updated_files_hashes
     }
 }
 
 #[napi]
 impl WorkspaceContext {
+    /// Symlinks are followed unless another `symlinkPolicy` is given.
+    /// When files are discovered with git, symlinks are always recorded, because git stores them as links
     #[napi(constructor)]
-    pub fn new(workspace_root: String, cache_dir: String) -> Self {
+    pub fn new(
+        workspace_root: String,
+        cache_dir: String,
+        symlink_policy: Option<SymlinkPolicy>,
+    ) -> Self {
         enable_logger();
 
         trace!(?workspace_root);
 
         let workspace_root_path = PathBuf::from(&workspace_root);
+        let symlink_policy = symlink_policy.unwrap_or_default();
 
         WorkspaceContext {
-            files_worker: FilesWorker::gather_files(&workspace_root_path, cache_dir.clone()),
+            files_worker: FilesWorker::gather_files(
+                &workspace_root_path,
+                cache_dir.clone(),
+                symlink_policy,
+            ),
             workspace_root,
             workspace_root_path,
+            symlink_policy,
+            file_map_snapshot: NxMutex::new(None),
+            project_roots: NxMutex::new(ProjectRootsIndex::default()),
+            files_generation: AtomicU32::new(0),
+            published_files: NxMutex::new(None),
         }
     }
 
@@ -216,29 +347,71 @@ impl WorkspaceContext {
         &self,
         project_root_map: HashMap<String, String>,
     ) -> anyhow::Result<NxWorkspaceFiles> {
+        *self.project_roots.lock()? = ProjectRootsIndex::new(project_root_map.keys());
         workspace_files::get_files(project_root_map, self.all_file_data())
             .map_err(anyhow::Error::from)
     }
 
+    /// Publishes the workspace files to `directory`, where clients read them with `readSharedWorkspaceFiles`
+    /// instead of receiving them over the daemon socket. The files are only published again once they changed,
+    /// or when they are published for other projects. Returns the generation of the published files
+    #[napi]
+    pub fn publish_workspace_files(
+        &self,
+        directory: String,
+        project_root_map: HashMap<String, String>,
+    ) -> anyhow::Result<u32> {
+        let mut published_files = self.published_files.lock()?;
+        // the files can only be newer than their generation, if they change while they are published
+        let generation = self.files_generation.load(Ordering::SeqCst);
+        let roots_hash = project_roots_hash(&project_root_map);
+        if published_files.as_ref() == Some(&(generation, roots_hash.clone())) {
+            return Ok(generation);
+        }
+
+        let files = self.get_workspace_files(project_root_map)?;
+        publish_shared_files(Path::new(&directory), generation, roots_hash.clone(), &files)?;
+        *published_files = Some((generation, roots_hash));
+        Ok(generation)
+    }
+
     #[napi]
     pub fn glob(
         &self,
         globs: Vec<String>,
         exclude: Option<Vec<String>>,
+        case_insensitive: Option<bool>,
     ) -> napi::Result<Vec<String>> {
+        let _span = trace_span!("glob", ?globs).entered();
         let file_data = self.all_file_data();
-        let globbed_files = config_files::glob_files(&file_data, globs, exclude)?;
+        let globbed_files =
+            config_files::glob_files(&file_data, globs, exclude, case_insensitive)?;
         Ok(globbed_files.map(|file| file.file.to_owned()).collect())
     }
 
+    /// Matches files in batches instead of returning every match at once.
+    /// Useful for large workspaces, where transferring all matches in a single array is expensive
+    #[napi]
+    pub fn glob_stream(
+        &self,
+        globs: Vec<String>,
+        exclude: Option<Vec<String>>,
+        batch_size: Option<u32>,
+        case_insensitive: Option<bool>,
+    ) -> napi::Result<GlobStream> {
+        let file_globs = config_files::FileGlobs::new(globs, exclude, case_insensitive)?;
+        Ok(GlobStream::new(self.all_file_data(), file_globs, batch_size))
+    }
+
     #[napi]
     pub fn hash_files_matching_glob(
         &self,
         globs: Vec<String>,
         exclude: Option<Vec<String>>,
+        case_insensitive: Option<bool>,
     ) -> napi::Result<String> {
         let files = &self.all_file_data();
-        let globbed_files = config_files::glob_files(files, globs, exclude)?;
+        let globbed_files = config_files::glob_files(files, globs, exclude, case_insensitive)?;
         Ok(hash(
             &globbed_files
                 .map(|file| file.hash.as_bytes())
@@ -252,9 +425,16 @@ impl WorkspaceContext {
         &self,
         updated_files: Vec<&str>,
         deleted_files: Vec<&str>,
+        moved_files: Option<HashMap<String, String>>,
     ) -> HashMap<String, String> {
-        self.files_worker
-            .update_files(&self.workspace_root_path, updated_files, deleted_files)
+        self.files_generation.fetch_add(1, Ordering::SeqCst);
+        self.files_worker.update_files(
+            &self.workspace_root_path,
+            updated_files,
+            deleted_files,
+            moved_files.unwrap_or_default(),
+            self.symlink_policy,
+        )
     }
 
     #[napi]
@@ -339,6 +519,29 @@ impl WorkspaceContext {
         }
     }
 
+    /// Returns the files that were created, updated or deleted since the previous call, and takes a new snapshot.
+    /// The first call returns every file of the workspace as created
+    #[napi]
+    pub fn get_file_map_delta(&self) -> anyhow::Result<FileMapDelta> {
+        let mut snapshot = self.file_map_snapshot.lock()?;
+        let files = self.all_file_data();
+        let previous = snapshot.take().unwrap_or_default();
+        let delta = diff_file_map(previous, &files);
+        trace!(
+            "file map delta: {} created, {} updated, {} deleted",
+            delta.created.len(),
+            delta.updated.len(),
+            delta.deleted.len()
+        );
+        *snapshot = Some(
+            files
+                .into_iter()
+                .map(|file| (file.file, file.hash))
+                .collect(),
+        );
+        Ok(delta)
+    }
+
     #[napi]
     pub fn all_file_data(&self) -> Vec<FileData> {
         self.files_worker.get_files()
@@ -346,6 +549,64 @@ impl WorkspaceContext {
 
     #[napi]
     pub fn get_files_in_directory(&self, directory: String) -> Vec<String> {
-        get_child_files(directory, self.files_worker.get_files())
+        self.files_worker
+            .with_files(|files| {
+                files[directory_range(files, Path::new(&directory))]
+                    .iter()
+                    .map(|(path, _)| path.to_normalized_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the files of a project without scanning every file of the workspace.
+    /// The files of the projects nested in the root are left out, like in the project file map,
+    /// once the project roots are known from `getWorkspaceFiles`
+    #[napi]
+    pub fn get_files_for_project(&self, root: String) -> anyhow::Result<Vec<FileData>> {
+        let project_roots = self.project_roots.lock()?;
+        Ok(self
+            .files_worker
+            .with_files(|files| {
+                project_roots
+                    .project_ranges(files, &root)
+                    .into_iter()
+                    .flat_map(move |range| files[range].iter().map(to_file_data))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_diff_file_maps() {
+        let file = |file: &str, hash: &str| FileData {
+            file: file.into(),
+            hash: hash.into(),
+        };
+        let previous = HashMap::from([
+            ("a.txt".to_string(), "a".to_string()),
+            ("b.txt".to_string(), "b".to_string()),
+            ("c.txt".to_string(), "c".to_string()),
+        ]);
+
+        let delta = diff_file_map(
+            previous,
+            &[
+                file("a.txt", "a"),
+                file("b.txt", "changed"),
+                file("d.txt", "d"),
+            ],
+        );
+
+        assert_eq!(delta.created, [file("d.txt", "d")]);
+        assert_eq!(delta.updated, [file("b.txt", "changed")]);
+        assert_eq!(delta.updated[0].hash, "changed");
+        assert_eq!(delta.deleted, [file("c.txt", "c")]);
+        assert_eq!(delta.deleted[0].hash, "c");
     }
 }
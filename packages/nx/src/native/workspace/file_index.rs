@@ -0,0 +1,146 @@
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// The project roots of the workspace, to find the files of a project in the sorted workspace files.
+/// Paths are sorted by their components, so the files below a directory are next to each other
+/// and are found with a binary search instead of a scan of every file of the workspace
+#[derive(Debug, Default)]
+pub(super) struct ProjectRootsIndex {
+    roots: Vec<PathBuf>,
+}
+
+impl ProjectRootsIndex {
+    pub fn new<S: AsRef<str>>(roots: impl IntoIterator<Item = S>) -> Self {
+        let mut roots = roots
+            .into_iter()
+            .map(|root| PathBuf::from(normalize_root(root.as_ref())))
+            .collect::<Vec<_>>();
+        roots.sort();
+        roots.dedup();
+        Self { roots }
+    }
+
+    /// The ranges of the sorted files that belong to a project root: the files below the root,
+    /// without the files of the projects nested in it
+    pub fn project_ranges<T>(&self, files: &[(PathBuf, T)], root: &str) -> Vec<Range<usize>> {
+        let root = Path::new(normalize_root(root));
+        let project_range = directory_range(files, root);
+
+        // the roots nested in the project come right after it
+        let nested_start = self.roots.partition_point(|other| other.as_path() <= root);
+        let nested_roots = self.roots[nested_start..]
+            .iter()
+            .take_while(|other| other.starts_with(root));
+
+        let mut ranges = vec![];
+        let mut start = project_range.start;
+        for nested_root in nested_roots {
+            // roots nested in an other nested root are empty, their files were already left out
+            let nested_range = directory_range(&files[start..project_range.end], nested_root);
+            if !nested_range.is_empty() {
+                ranges.push(start..start + nested_range.start);
+                start += nested_range.end;
+            }
+        }
+        ranges.push(start..project_range.end);
+        ranges.retain(|range| !range.is_empty());
+        ranges
+    }
+}
+
+/// The range of the sorted files that are below a directory
+pub(super) fn directory_range<T>(files: &[(PathBuf, T)], directory: &Path) -> Range<usize> {
+    let start = files.partition_point(|(file, _)| file.as_path() < directory);
+    let end = start + files[start..].partition_point(|(file, _)| file.starts_with(directory));
+    start..end
+}
+
+/// The root project is at `.`, which is the empty path below which every file is
+fn normalize_root(root: &str) -> &str {
+    match root.trim_end_matches('/') {
+        "." => "",
+        root => root,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(paths: &[&str]) -> Vec<(PathBuf, ())> {
+        let mut files = paths
+            .iter()
+            .map(|path| (PathBuf::from(path), ()))
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+
+    fn paths<T>(files: &[(PathBuf, T)], ranges: &[Range<usize>]) -> Vec<String> {
+        ranges
+            .iter()
+            .flat_map(|range| &files[range.clone()])
+            .map(|(file, _)| file.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn should_find_the_files_below_a_directory() {
+        let files = files(&[
+            "foo/bar",
+            "foo/baz",
+            "foo/child/bar",
+            "bar/baz",
+            "foo-other/not-child",
+        ]);
+        let range = directory_range(&files, Path::new("foo"));
+        assert_eq!(
+            paths(&files, &[range]),
+            ["foo/bar", "foo/baz", "foo/child/bar"]
+        );
+        assert!(directory_range(&files, Path::new("baz")).is_empty());
+    }
+
+    #[test]
+    fn should_leave_out_the_files_of_nested_projects() {
+        let files = files(&[
+            "README.md",
+            "apps/app/src/main.ts",
+            "apps/app/e2e/app.spec.ts",
+            "apps/app/e2e/fixtures/data.json",
+            "apps/app/e2e/fixtures/nested/project.json",
+            "apps/app/project.json",
+            "apps/app-e2e/project.json",
+            "libs/lib/index.ts",
+        ]);
+        let index = ProjectRootsIndex::new([
+            ".",
+            "apps/app",
+            "apps/app/e2e",
+            "apps/app/e2e/fixtures/nested",
+            "apps/app-e2e",
+            "libs/lib/",
+        ]);
+
+        assert_eq!(
+            paths(&files, &index.project_ranges(&files, "apps/app")),
+            ["apps/app/project.json", "apps/app/src/main.ts"]
+        );
+        assert_eq!(
+            paths(&files, &index.project_ranges(&files, "apps/app/e2e")),
+            [
+                "apps/app/e2e/app.spec.ts",
+                "apps/app/e2e/fixtures/data.json"
+            ]
+        );
+        assert_eq!(
+            paths(&files, &index.project_ranges(&files, ".")),
+            ["README.md"]
+        );
+        assert_eq!(
+            paths(&files, &index.project_ranges(&files, "libs/lib")),
+            ["libs/lib/index.ts"]
+        );
+        assert!(index.project_ranges(&files, "libs/missing").is_empty());
+    }
+}
@@ -2,24 +2,36 @@ use std::cmp;
 use std::path::Path;
 use std::thread::available_parallelism;
 
-use rayon::prelude::*;
 use tracing::trace;
 
-use crate::native::hasher::hash_file_path;
-use crate::native::walker::{nx_walker, NxFile};
+use crate::native::hasher::{hash, hash_file_path};
+use crate::native::utils::Normalize;
+use crate::native::utils::parallel::prelude::*;
+use crate::native::walker::{nx_walker, NxFile, SymlinkPolicy};
 use crate::native::workspace::files_archive::{NxFileHashed, NxFileHashes};
 
-pub fn full_files_hash(workspace_root: &Path) -> NxFileHashes {
-    let files = nx_walker(workspace_root, true).collect::<Vec<_>>();
+/// Number of files hashed between two checkpoints of the initial walk
+const CHECKPOINT_BATCH_SIZE: usize = 20_000;
+
+pub fn full_files_hash(
+    workspace_root: &Path,
+    symlinks: SymlinkPolicy,
+    checkpoint: impl FnMut(&[(String, NxFileHashed)]),
+) -> NxFileHashes {
+    let files = nx_walker(workspace_root, true, symlinks).collect::<Vec<_>>();
     trace!("Found {} files", files.len());
-    hash_files(files).into_iter().collect()
+    hash_files_in_batches(files, checkpoint)
+        .into_iter()
+        .collect()
 }
 
 pub fn selective_files_hash(
     workspace_root: &Path,
     mut archived_files: NxFileHashes,
+    symlinks: SymlinkPolicy,
+    checkpoint: impl FnMut(&[(String, NxFileHashed)]),
 ) -> NxFileHashes {
-    let files = nx_walker(workspace_root, true).collect::<Vec<_>>();
+    let files = nx_walker(workspace_root, true, symlinks).collect::<Vec<_>>();
     let mut archived = vec![];
     let mut not_archived = vec![];
     let now = std::time::Instant::now();
@@ -42,10 +54,29 @@ pub fn selective_files_hash(
 
     archived
         .into_iter()
-        .chain(hash_files(not_archived))
+        .chain(hash_files_in_batches(not_archived, checkpoint))
         .collect()
 }
 
+/// Hashes the files in batches, so the progress can be checkpointed after every batch
+fn hash_files_in_batches(
+    files: Vec<NxFile>,
+    mut checkpoint: impl FnMut(&[(String, NxFileHashed)]),
+) -> Vec<(String, NxFileHashed)> {
+    if files.len() <= CHECKPOINT_BATCH_SIZE {
+        return hash_files(files);
+    }
+
+    let mut hashed = Vec::with_capacity(files.len());
+    let mut files = files.into_iter().peekable();
+    while files.peek().is_some() {
+        let batch = hash_files(files.by_ref().take(CHECKPOINT_BATCH_SIZE).collect());
+        checkpoint(&batch);
+        hashed.extend(batch);
+    }
+    hashed
+}
+
 fn hash_files(files: Vec<NxFile>) -> Vec<(String, NxFileHashed)> {
     let num_parallelism = cmp::max(available_parallelism().map_or(2, |n| n.get()) / 3, 2);
     let chunks = files.len() / num_parallelism;
@@ -56,17 +87,21 @@ fn hash_files(files: Vec<NxFile>) -> Vec<(String, NxFileHashed)> {
         files
             .into_par_iter()
             .filter_map(|file| {
-                hash_file_path(&file.full_path)
+                hash_nx_file(&file)
                     .map(|hash| (file.normalized_path, NxFileHashed(hash, file.mod_time)))
             })
             .collect::<Vec<_>>()
     } else {
-        trace!("hashing workspace files in {} chunks of {}", num_parallelism, chunks);
+        trace!(
+            "hashing workspace files in {} chunks of {}",
+            num_parallelism,
+            chunks
+        );
         files
             .par_chunks(chunks)
             .flat_map_iter(|chunks| {
                 chunks.iter().filter_map(|file| {
-                    hash_file_path(&file.full_path).map(|hash| {
+                    hash_nx_file(file).map(|hash| {
                         (
                             file.normalized_path.clone(),
                             NxFileHashed(hash, file.mod_time),
@@ -81,12 +116,21 @@ fn hash_files(files: Vec<NxFile>) -> Vec<(String, NxFileHashed)> {
     files
 }
 
+/// Recorded symlinks are hashed by their target, the other files by their content
+fn hash_nx_file(file: &NxFile) -> Option<String> {
+    match &file.link_target {
+        Some(target) => Some(hash(target.to_normalized_string().as_bytes())),
+        None => hash_file_path(&file.full_path),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use assert_fs::prelude::*;
     use assert_fs::TempDir;
 
     use crate::native::utils::get_mod_time;
+    use crate::native::walker::SymlinkPolicy;
     use crate::native::workspace::files_archive::{NxFileHashed, NxFileHashes};
 
     fn setup_fs() -> TempDir {
@@ -145,7 +189,8 @@ mod tests {
         .into_iter()
         .collect::<NxFileHashes>();
 
-        let hashed_files = super::selective_files_hash(temp.path(), archived_files);
+        let hashed_files =
+            super::selective_files_hash(temp.path(), archived_files, SymlinkPolicy::follow, |_| {});
         let mut hashed_files = hashed_files
             .iter()
             .map(|(path, _)| path.as_str())
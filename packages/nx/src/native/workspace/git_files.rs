@@ -0,0 +1,324 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use tracing::{debug, trace};
+
+use crate::native::glob::build_glob_set;
+use crate::native::utils::parallel::{self, prelude::*};
+use crate::native::walker::ALWAYS_IGNORED_GLOBS;
+
+const GIT_FILE_DISCOVERY_ENV: &str = "NX_GIT_FILE_DISCOVERY";
+
+/// Whether the workspace files should be discovered and hashed with git instead of walking the workspace.
+/// File hashes are git blob hashes in this mode, so it changes the hashes of every task
+pub fn is_git_file_discovery_enabled() -> bool {
+    std::env::var(GIT_FILE_DISCOVERY_ENV).is_ok_and(|value| value == "true")
+}
+
+/// Lists the files of the workspace with their git blob hashes in one pass over the git index.
+/// Unmodified tracked files use the hashes from the index, so they are neither stat'd nor read.
+/// Only modified and untracked (but not ignored) files are hashed, with `git hash-object`.
+/// Files ignored by `.nxignore` files and the directories that are always ignored are left out
+pub fn git_files_hash(workspace_root: &Path) -> anyhow::Result<Vec<(String, String)>> {
+    let now = std::time::Instant::now();
+    let ((staged, deleted), changed) = parallel::join(
+        || {
+            parallel::join(
+                || git(workspace_root, &["ls-files", "-z", "--stage"], None),
+                || git(workspace_root, &["ls-files", "-z", "--deleted"], None),
+            )
+        },
+        || {
+            git(
+                workspace_root,
+                &[
+                    "ls-files",
+                    "-z",
+                    "--modified",
+                    "--others",
+                    "--exclude-standard",
+                ],
+                None,
+            )
+        },
+    );
+    let (staged, deleted, changed) = (staged?, deleted?, changed?);
+    trace!("listed git files in {:?}", now.elapsed());
+
+    let deleted = split_paths(&deleted).collect::<HashSet<_>>();
+    let mut files = HashMap::new();
+    let mut dirty = split_paths(&changed)
+        .filter(|path| !deleted.contains(path))
+        .collect::<HashSet<_>>();
+    for entry in parse_staged_files(&staged) {
+        // unmerged files have several stages, they are hashed like modified files
+        if entry.stage != "0" {
+            dirty.insert(entry.path);
+        } else if !deleted.contains(entry.path) && !dirty.contains(entry.path) {
+            files.insert(entry.path, entry.hash.to_string());
+        }
+    }
+
+    let dirty = dirty.into_iter().collect::<Vec<_>>();
+    trace!("hashing {} modified and untracked files", dirty.len());
+    files.extend(
+        dirty
+            .iter()
+            .copied()
+            .zip(git_hash_objects(workspace_root, &dirty)?),
+    );
+
+    let nx_ignore = build_nx_ignore(workspace_root, files.keys().copied())?;
+    let always_ignored = build_glob_set(
+        &ALWAYS_IGNORED_GLOBS
+            .iter()
+            .flat_map(|glob| [glob.to_string(), format!("{}/**", glob)])
+            .collect::<Vec<_>>(),
+    )?;
+
+    let files = files
+        .into_par_iter()
+        .filter(|(path, _)| {
+            !always_ignored.is_match(*path)
+                && !nx_ignore
+                    .iter()
+                    .any(|ignore| is_ignored(ignore, workspace_root, path))
+        })
+        .map(|(path, hash)| (path.to_string(), hash))
+        .collect::<Vec<_>>();
+
+    debug!(
+        "discovered {} files with git in {:?}",
+        files.len(),
+        now.elapsed()
+    );
+    Ok(files)
+}
+
+/// Hashes files (relative to the workspace root) the same way git hashes blobs
+pub fn git_hash_objects<S: AsRef<str>>(
+    workspace_root: &Path,
+    files: &[S],
+) -> anyhow::Result<Vec<String>> {
+    let (listed, unlisted): (Vec<_>, Vec<_>) = files
+        .iter()
+        .map(AsRef::as_ref)
+        .enumerate()
+        .partition(|(_, file)| is_stdin_path(file));
+    let mut hashes = vec![String::new(); files.len()];
+
+    if !listed.is_empty() {
+        let paths = listed
+            .iter()
+            .map(|(_, file)| format!("{}\n", file))
+            .collect::<String>();
+        let output = git(
+            workspace_root,
+            &["hash-object", "--stdin-paths"],
+            Some(paths.as_bytes()),
+        )?;
+        let listed_hashes = output.lines().collect::<Vec<_>>();
+        if listed_hashes.len() != listed.len() {
+            anyhow::bail!(
+                "git hash-object returned {} hashes for {} files",
+                listed_hashes.len(),
+                listed.len()
+            );
+        }
+        for ((index, _), hash) in listed.iter().zip(listed_hashes) {
+            hashes[*index] = hash.to_string();
+        }
+    }
+
+    // paths that `--stdin-paths` would read differently are passed as arguments instead
+    for (index, file) in unlisted {
+        trace!("hashing {:?} on its own", file);
+        let output = git(workspace_root, &["hash-object", "--", file], None)?;
+        hashes[index] = output.trim_end().to_string();
+    }
+    Ok(hashes)
+}
+
+/// Whether git reads the path back unchanged from a line of `--stdin-paths`, which ends the path at a newline,
+/// drops a trailing carriage return and unquotes a path that starts with a double quote
+fn is_stdin_path(file: &str) -> bool {
+    !file.contains('\n') && !file.ends_with('\r') && !file.starts_with('"')
+}
+
+struct StagedFile<'a> {
+    hash: &'a str,
+    stage: &'a str,
+    path: &'a str,
+}
+
+/// Parses the `<mode> <object> <stage>\t<path>` entries of `git ls-files -z --stage`.
+/// Submodules are left out, because they are directories of another repository
+fn parse_staged_files(output: &str) -> impl Iterator<Item = StagedFile<'_>> {
+    split_paths(output).filter_map(|entry| {
+        let (info, path) = entry.split_once('\t')?;
+        let mut info = info.split(' ');
+        let mode = info.next()?;
+        let hash = info.next()?;
+        let stage = info.next()?;
+        (mode != "160000").then_some(StagedFile { hash, stage, path })
+    })
+}
+
+fn split_paths(output: &str) -> impl Iterator<Item = &str> {
+    output.split('\0').filter(|path| !path.is_empty())
+}
+
+/// Builds the matchers of the `.nxignore` files among the workspace files
+fn build_nx_ignore<'a>(
+    workspace_root: &Path,
+    files: impl Iterator<Item = &'a str>,
+) -> anyhow::Result<Vec<Gitignore>> {
+    files
+        .filter(|file| file.rsplit('/').next() == Some(".nxignore"))
+        .map(|file| {
+            let path = workspace_root.join(file);
+            let mut builder = GitignoreBuilder::new(path.parent().unwrap_or(workspace_root));
+            if let Some(e) = builder.add(&path) {
+                return Err(anyhow::Error::from(e));
+            }
+            builder.build().map_err(anyhow::Error::from)
+        })
+        .collect()
+}
+
+fn is_ignored(ignore: &Gitignore, workspace_root: &Path, file: &str) -> bool {
+    let path = workspace_root.join(file);
+    path.starts_with(ignore.path()) && ignore.matched_path_or_any_parents(&path, false).is_ignore()
+}
+
+fn git(workspace_root: &Path, args: &[&str], stdin: Option<&[u8]>) -> anyhow::Result<String> {
+    let mut child = Command::new("git")
+        .args(args)
+        .current_dir(workspace_root)
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    if let (Some(input), Some(mut child_stdin)) = (stdin, child.stdin.take()) {
+        // write from another thread, so a full stdout pipe can't block the input
+        let input = input.to_vec();
+        std::thread::spawn(move || child_stdin.write_all(&input));
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8(output.stdout).map_err(anyhow::Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn should_parse_staged_files() {
+        let output = "100644 78981922613b2afb6025042ff6bd878ac1994e85 0\ta.txt\0\
+                      160000 f2ad6c76f0115a6ba5b00456a849810e7ec0af20 0\tsubmodule\0\
+                      100644 f2ad6c76f0115a6ba5b00456a849810e7ec0af20 2\tdir/with space.txt\0";
+        let entries = parse_staged_files(output)
+            .map(|entry| (entry.path, entry.hash, entry.stage))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            [
+                ("a.txt", "78981922613b2afb6025042ff6bd878ac1994e85", "0"),
+                (
+                    "dir/with space.txt",
+                    "f2ad6c76f0115a6ba5b00456a849810e7ec0af20",
+                    "2"
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn should_hash_tracked_modified_and_untracked_files() {
+        let temp = TempDir::new().unwrap();
+        let run = |args: &[&str]| git(temp.path(), args, None).unwrap();
+        run(&["init", "-q"]);
+        temp.child("a.txt").write_str("a").unwrap();
+        temp.child("b.txt").write_str("b").unwrap();
+        temp.child("deleted.txt").write_str("deleted").unwrap();
+        temp.child(".gitignore").write_str("dist").unwrap();
+        run(&["add", "."]);
+        run(&[
+            "-c",
+            "user.name=nx",
+            "-c",
+            "user.email=nx@example.com",
+            "commit",
+            "-q",
+            "-m",
+            "initial",
+        ]);
+
+        temp.child("b.txt").write_str("changed").unwrap();
+        std::fs::remove_file(temp.child("deleted.txt").path()).unwrap();
+        temp.child("untracked.txt").write_str("untracked").unwrap();
+        temp.child("dist/ignored.txt").write_str("ignored").unwrap();
+        temp.child(".nxignore").write_str("nx-ignored").unwrap();
+        temp.child("nx-ignored/file.txt")
+            .write_str("ignored")
+            .unwrap();
+
+        let mut files = git_files_hash(temp.path()).unwrap();
+        files.sort();
+        let hash = |path: &str| git_hash_objects(temp.path(), &[path]).unwrap().remove(0);
+        assert_eq!(
+            files,
+            [
+                (".gitignore".to_string(), hash(".gitignore")),
+                (".nxignore".to_string(), hash(".nxignore")),
+                ("a.txt".to_string(), hash("a.txt")),
+                ("b.txt".to_string(), hash("b.txt")),
+                ("untracked.txt".to_string(), hash("untracked.txt")),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_hash_files_whose_paths_do_not_fit_on_a_line() {
+        let temp = TempDir::new().unwrap();
+        git(temp.path(), &["init", "-q"], None).unwrap();
+        let files = ["a.txt", "with\nnewline.txt", "\"quoted\".txt", "b.txt"];
+        for file in files {
+            temp.child(file).write_str(file).unwrap();
+        }
+
+        let hashes = git_hash_objects(temp.path(), &files).unwrap();
+        let expected = files
+            .iter()
+            .map(|file| {
+                temp.child("expected.txt").write_str(file).unwrap();
+                git_hash_objects(temp.path(), &["expected.txt"])
+                    .unwrap()
+                    .remove(0)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(hashes, expected);
+        assert!(git_hash_objects::<&str>(temp.path(), &[])
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+use std::vec::IntoIter;
+
+use tracing::trace;
+
+use crate::native::types::FileData;
+use crate::native::workspace::config_files::FileGlobs;
+
+const DEFAULT_BATCH_SIZE: usize = 10_000;
+
+/// Lazily matches workspace files against globs, handing them to JS in batches
+/// so that large workspaces do not need to transfer every match at once
+#[napi]
+pub struct GlobStream {
+    files: IntoIter<FileData>,
+    file_globs: FileGlobs,
+    batch_size: usize,
+    done: bool,
+}
+
+#[napi]
+impl GlobStream {
+    pub(super) fn new(files: Vec<FileData>, file_globs: FileGlobs, batch_size: Option<u32>) -> Self {
+        Self {
+            files: files.into_iter(),
+            file_globs,
+            batch_size: batch_size
+                .map(|size| size as usize)
+                .filter(|size| *size > 0)
+                .unwrap_or(DEFAULT_BATCH_SIZE),
+            done: false,
+        }
+    }
+
+    /// Returns the next batch of matched files, or `null` when there are no more matches
+    #[napi]
+    pub fn next_batch(&mut self) -> Option<Vec<FileData>> {
+        if self.done {
+            return None;
+        }
+
+        let mut batch = Vec::with_capacity(self.batch_size);
+        for file in self.files.by_ref() {
+            if self.file_globs.is_match(&file.file) {
+                batch.push(file);
+                if batch.len() == self.batch_size {
+                    break;
+                }
+            }
+        }
+
+        if batch.len() < self.batch_size {
+            self.done = true;
+        }
+
+        trace!("glob stream batch of {} files", batch.len());
+        if batch.is_empty() {
+            None
+        } else {
+            Some(batch)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn files(count: usize) -> Vec<FileData> {
+        (0..count)
+            .map(|i| FileData {
+                file: format!("packages/file-{i}.{}", if i % 2 == 0 { "ts" } else { "js" }),
+                hash: i.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_stream_matching_files_in_batches() {
+        let file_globs = FileGlobs::new(vec!["**/*.ts".into()], None, None).unwrap();
+        let mut stream = GlobStream::new(files(10), file_globs, Some(2));
+
+        let mut batches = vec![];
+        while let Some(batch) = stream.next_batch() {
+            batches.push(batch.into_iter().map(|f| f.file).collect::<Vec<_>>());
+        }
+
+        assert_eq!(
+            batches,
+            vec![
+                vec!["packages/file-0.ts", "packages/file-2.ts"],
+                vec!["packages/file-4.ts", "packages/file-6.ts"],
+                vec!["packages/file-8.ts"],
+            ]
+        );
+        assert!(stream.next_batch().is_none());
+    }
+
+    #[test]
+    fn should_exclude_files_from_stream() {
+        let file_globs = FileGlobs::new(
+            vec!["**/*".into()],
+            Some(vec!["**/*.js".into(), "**/file-0.ts".into()]),
+            None,
+        )
+        .unwrap();
+        let mut stream = GlobStream::new(files(4), file_globs, None);
+
+        let batch = stream.next_batch().unwrap();
+        assert_eq!(
+            batch.into_iter().map(|f| f.file).collect::<Vec<_>>(),
+            vec!["packages/file-2.ts"]
+        );
+        assert!(stream.next_batch().is_none());
+    }
+}
@@ -3,14 +3,27 @@ use crate::native::workspace::types::NxWorkspaceFilesExternals;
 use napi::bindgen_prelude::External;
 use std::collections::HashMap;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod code_search;
 pub mod config_files;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod config_validation;
 pub mod context;
 mod errors;
+mod file_index;
 mod files_archive;
 mod files_hashing;
+#[cfg(not(target_arch = "wasm32"))]
+mod git_files;
+pub mod glob_stream;
+mod shared_file_map;
 pub mod types;
 pub mod workspace_files;
 
+#[cfg_attr(not(target_arch = "wasm32"), path = "walk_checkpoint/default.rs")]
+#[cfg_attr(target_arch = "wasm32", path = "walk_checkpoint/wasm.rs")]
+mod walk_checkpoint;
+
 #[napi]
 // should only be used in tests to transfer the file map from the JS world to the Rust world
 pub fn __test_only_transfer_file_map(
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, bail};
+use rkyv::{Archive, Serialize};
+use tracing::{debug, trace};
+
+use crate::native::hasher::hash;
+use crate::native::types::FileData;
+use crate::native::workspace::types::NxWorkspaceFiles;
+
+const SHARED_FILE_MAP: &str = "file-map.nxfm";
+const MAGIC: &[u8; 8] = b"NXFILES\0";
+/// Changed whenever the layout of the file map changes, file maps of other versions are not read
+const FORMAT_VERSION: u32 = 1;
+/// The magic, the format version, the generation, and 16 bytes of padding, which keeps the archive aligned in the memory map
+const HEADER_LEN: usize = 32;
+const GENERATION_OFFSET: usize = MAGIC.len() + 4;
+
+#[derive(Archive, Serialize)]
+#[archive(check_bytes)]
+struct SharedFiles {
+    /// The hash of the project root map the files were assigned to projects with
+    project_roots_hash: String,
+    project_file_map: Vec<(String, Vec<SharedFile>)>,
+    global_files: Vec<SharedFile>,
+}
+
+#[derive(Archive, Serialize)]
+#[archive(check_bytes)]
+struct SharedFile(String, String);
+
+/// Identifies a project root map, since the files are only shared with clients that have the same projects
+pub fn project_roots_hash(project_root_map: &HashMap<String, String>) -> String {
+    let mut roots = project_root_map.iter().collect::<Vec<_>>();
+    roots.sort();
+    let roots = roots
+        .into_iter()
+        .map(|(root, project)| format!("{}\0{}\0", root, project))
+        .collect::<String>();
+    hash(roots.as_bytes())
+}
+
+/// Writes the workspace files to a file that clients map in memory, instead of receiving them over the daemon socket.
+/// The generation identifies the state of the files, so that clients do not read files that were published before or after
+/// the generation they were told about
+pub fn publish_shared_files(
+    directory: &Path,
+    generation: u32,
+    project_roots_hash: String,
+    files: &NxWorkspaceFiles,
+) -> anyhow::Result<()> {
+    let now = std::time::Instant::now();
+    let shared = SharedFiles {
+        project_roots_hash,
+        project_file_map: files
+            .project_file_map
+            .iter()
+            .map(|(project, files)| (project.clone(), shared_files(files)))
+            .collect(),
+        global_files: shared_files(&files.global_files),
+    };
+    let archive = rkyv::to_bytes::<_, 4096>(&shared).map_err(|e| anyhow!("{}", e))?;
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN + archive.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&generation.to_le_bytes());
+    bytes.resize(HEADER_LEN, 0);
+    bytes.extend_from_slice(&archive);
+
+    // the file map is replaced at once, so the clients that mapped the previous one keep reading it as it was
+    fs::create_dir_all(directory)?;
+    let path = directory.join(SHARED_FILE_MAP);
+    let temp_path = path.with_extension(format!("nxfm.{}", std::process::id()));
+    fs::write(&temp_path, bytes)?;
+    fs::rename(&temp_path, &path)?;
+    trace!(
+        "published generation {} of the file map in {:?}",
+        generation,
+        now.elapsed()
+    );
+    Ok(())
+}
+
+/// Reads the workspace files published by the daemon with `WorkspaceContext.publishWorkspaceFiles`.
+/// There are no files when the published ones are of another generation, or were assigned to other projects
+#[napi]
+pub fn read_shared_workspace_files(
+    directory: String,
+    project_root_map: HashMap<String, String>,
+    generation: u32,
+) -> Option<NxWorkspaceFiles> {
+    let now = std::time::Instant::now();
+    let path = Path::new(&directory).join(SHARED_FILE_MAP);
+    match read_shared_files(&path, &project_roots_hash(&project_root_map), generation) {
+        Ok(files) => {
+            trace!("read the shared file map in {:?}", now.elapsed());
+            Some(files)
+        }
+        Err(e) => {
+            debug!("could not read the shared file map: {:?}", e);
+            None
+        }
+    }
+}
+
+fn read_shared_files(
+    path: &Path,
+    project_roots_hash: &str,
+    generation: u32,
+) -> anyhow::Result<NxWorkspaceFiles> {
+    // Safety: the file map is only replaced by renames, so the mapped file is never written to
+    #[cfg(not(target_arch = "wasm32"))]
+    let bytes = unsafe { memmap2::Mmap::map(&fs::File::open(path)?)? };
+    // there is no memory mapping in WASM, the file is read into memory that is aligned like the mapping
+    #[cfg(target_arch = "wasm32")]
+    let bytes = {
+        let mut aligned = rkyv::AlignedVec::new();
+        aligned.extend_from_slice(&fs::read(path)?);
+        aligned
+    };
+    if bytes.len() < HEADER_LEN || &bytes[..MAGIC.len()] != MAGIC {
+        bail!("{:?} is not a shared file map", path);
+    }
+    let version = u32::from_le_bytes(bytes[MAGIC.len()..GENERATION_OFFSET].try_into()?);
+    if version != FORMAT_VERSION {
+        bail!(
+            "the shared file map has the format {} instead of {}",
+            version,
+            FORMAT_VERSION
+        );
+    }
+    let published = u32::from_le_bytes(bytes[GENERATION_OFFSET..GENERATION_OFFSET + 4].try_into()?);
+    if published != generation {
+        bail!(
+            "the shared file map is of generation {} instead of {}",
+            published,
+            generation
+        );
+    }
+
+    let archived = rkyv::check_archived_root::<SharedFiles>(&bytes[HEADER_LEN..])
+        .map_err(|e| anyhow!("invalid shared file map: {}", e))?;
+    if archived.project_roots_hash.as_str() != project_roots_hash {
+        bail!("the shared file map was published for other projects");
+    }
+    // the files are read from the mapped archive directly, without deserializing it first
+    Ok(NxWorkspaceFiles {
+        project_file_map: archived
+            .project_file_map
+            .iter()
+            .map(|entry| (entry.0.to_string(), file_data(&entry.1)))
+            .collect(),
+        global_files: file_data(&archived.global_files),
+        external_references: None,
+    })
+}
+
+fn shared_files(files: &[FileData]) -> Vec<SharedFile> {
+    files
+        .iter()
+        .map(|file| SharedFile(file.file.clone(), file.hash.clone()))
+        .collect()
+}
+
+fn file_data(files: &[ArchivedSharedFile]) -> Vec<FileData> {
+    files
+        .iter()
+        .map(|file| FileData {
+            file: file.0.to_string(),
+            hash: file.1.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    fn file(file: &str) -> FileData {
+        FileData {
+            file: file.into(),
+            hash: format!("{}-hash", file),
+        }
+    }
+
+    #[test]
+    fn should_read_the_files_of_the_published_generation() {
+        let temp = TempDir::new().unwrap();
+        let directory = temp.display().to_string();
+        let project_root_map = HashMap::from([("apps/app".to_string(), "app".to_string())]);
+        assert!(
+            read_shared_workspace_files(directory.clone(), project_root_map.clone(), 1).is_none()
+        );
+
+        let files = NxWorkspaceFiles {
+            project_file_map: HashMap::from([("app".into(), vec![file("apps/app/main.ts")])]),
+            global_files: vec![file("package.json")],
+            external_references: None,
+        };
+        publish_shared_files(
+            temp.path(),
+            1,
+            project_roots_hash(&project_root_map),
+            &files,
+        )
+        .unwrap();
+
+        let shared =
+            read_shared_workspace_files(directory.clone(), project_root_map.clone(), 1).unwrap();
+        assert_eq!(shared.project_file_map, files.project_file_map);
+        assert_eq!(shared.global_files, files.global_files);
+
+        // clients that expect another generation, or have other projects, do not read the files
+        assert!(read_shared_workspace_files(directory.clone(), project_root_map, 2).is_none());
+        let other_projects = HashMap::from([("libs/lib".to_string(), "lib".to_string())]);
+        assert!(read_shared_workspace_files(directory, other_projects, 1).is_none());
+    }
+}
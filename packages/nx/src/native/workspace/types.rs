@@ -36,3 +36,13 @@ pub struct FileMap {
     pub project_file_map: ProjectFiles,
     pub non_project_files: Vec<FileData>,
 }
+
+/// The files that changed in the workspace since the previous `FileMapDelta` was taken.
+/// Deleted files have the last hash that was known for them
+#[napi(object)]
+#[derive(Debug, Default)]
+pub struct FileMapDelta {
+    pub created: Vec<FileData>,
+    pub updated: Vec<FileData>,
+    pub deleted: Vec<FileData>,
+}
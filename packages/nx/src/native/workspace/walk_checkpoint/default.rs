@@ -0,0 +1,143 @@
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+use tracing::{debug, trace};
+
+use crate::native::db::{create_connection, nx_db_path};
+use crate::native::workspace::files_archive::{NxFileHashed, NxFileHashes};
+
+/// Records the files hashed during the initial walk of the workspace in the Nx database,
+/// so a walk that gets interrupted can be resumed without hashing these files again.
+/// Failing to read or write the checkpoint only means that files are hashed again
+pub struct WalkCheckpoint {
+    db: Option<Connection>,
+}
+
+impl WalkCheckpoint {
+    pub fn open<P: AsRef<Path>>(workspace_root: &Path, cache_dir: P) -> Self {
+        match Self::connect(workspace_root, cache_dir.as_ref()) {
+            Ok(db) => Self { db: Some(db) },
+            Err(e) => {
+                debug!("could not open the walk checkpoint: {:?}", e);
+                Self { db: None }
+            }
+        }
+    }
+
+    fn connect(workspace_root: &Path, cache_dir: &Path) -> anyhow::Result<Connection> {
+        // the database is created and migrated by `connectToNxDb`
+        let db_path = nx_db_path(cache_dir, Some(workspace_root));
+        if !db_path.exists() {
+            anyhow::bail!("{:?} does not exist yet", db_path);
+        }
+        // the busy handler of the connection waits while other connections write to the database
+        create_connection(&db_path)
+    }
+
+    /// Adds the files hashed by an interrupted walk to the archived files.
+    /// Both are validated with the modification times of the files when they are used
+    pub fn restore(&self, archived_files: Option<NxFileHashes>) -> Option<NxFileHashes> {
+        let checkpointed = match self.read() {
+            Ok(checkpointed) if !checkpointed.is_empty() => checkpointed,
+            Ok(_) => return archived_files,
+            Err(e) => {
+                debug!("could not read the walk checkpoint: {:?}", e);
+                return archived_files;
+            }
+        };
+
+        debug!(
+            "resuming the workspace walk with {} hashed files",
+            checkpointed.len()
+        );
+        match archived_files {
+            Some(mut archived_files) => {
+                archived_files.extend(checkpointed);
+                Some(archived_files)
+            }
+            None => Some(checkpointed.into_iter().collect()),
+        }
+    }
+
+    fn read(&self) -> anyhow::Result<Vec<(String, NxFileHashed)>> {
+        let Some(db) = &self.db else {
+            return Ok(vec![]);
+        };
+        let mut stmt = db.prepare("SELECT path, hash, mod_time FROM workspace_walk_checkpoint")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get(0)?, NxFileHashed(row.get(1)?, row.get(2)?)))
+        })?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(anyhow::Error::from)
+    }
+
+    /// Records a batch of hashed files
+    pub fn record(&self, files: &[(String, NxFileHashed)]) {
+        let Some(db) = &self.db else {
+            return;
+        };
+        let result = db.unchecked_transaction().and_then(|transaction| {
+            {
+                let mut stmt = transaction.prepare_cached(
+                    "INSERT OR REPLACE INTO workspace_walk_checkpoint (path, hash, mod_time) VALUES (?1, ?2, ?3)",
+                )?;
+                for (path, NxFileHashed(hash, mod_time)) in files {
+                    stmt.execute(params![path, hash, mod_time])?;
+                }
+            }
+            transaction.commit()
+        });
+        match result {
+            Ok(_) => trace!("checkpointed {} hashed files", files.len()),
+            Err(e) => debug!("could not write the walk checkpoint: {:?}", e),
+        }
+    }
+
+    /// Removes the checkpoint after the walk has finished and the files archive has been written
+    pub fn clear(&self) {
+        let Some(db) = &self.db else {
+            return;
+        };
+        if let Err(e) = db.execute("DELETE FROM workspace_walk_checkpoint", []) {
+            debug!("could not clear the walk checkpoint: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::TempDir;
+
+    use super::*;
+    use crate::native::db::connection::NxDbConnection;
+
+    #[test]
+    fn should_restore_checkpointed_files() {
+        let temp = TempDir::new().unwrap();
+        assert!(WalkCheckpoint::open(temp.path(), &temp).db.is_none());
+
+        let _db = NxDbConnection::open(&nx_db_path(temp.path(), Some(temp.path()))).unwrap();
+        let checkpoint = WalkCheckpoint::open(temp.path(), &temp);
+        assert_eq!(checkpoint.restore(None), None);
+
+        checkpoint.record(&[
+            ("a.txt".into(), NxFileHashed("a".into(), 1)),
+            ("b.txt".into(), NxFileHashed("b".into(), 2)),
+        ]);
+
+        // a new connection resumes the walk of the interrupted one
+        let checkpoint = WalkCheckpoint::open(temp.path(), &temp);
+        let archived_files = [
+            ("b.txt".to_string(), NxFileHashed("old".into(), 1)),
+            ("c.txt".to_string(), NxFileHashed("c".into(), 3)),
+        ]
+        .into_iter()
+        .collect::<NxFileHashes>();
+        let restored = checkpoint.restore(Some(archived_files)).unwrap();
+        assert_eq!(restored.len(), 3);
+        assert_eq!(restored.get("b.txt"), Some(&NxFileHashed("b".into(), 2)));
+
+        checkpoint.clear();
+        assert_eq!(checkpoint.restore(None), None);
+    }
+}
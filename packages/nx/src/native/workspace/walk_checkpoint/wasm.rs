@@ -0,0 +1,20 @@
+use std::path::Path;
+
+use crate::native::workspace::files_archive::{NxFileHashed, NxFileHashes};
+
+/// The Nx database is not available in wasm, so the initial walk of the workspace is not checkpointed
+pub struct WalkCheckpoint;
+
+impl WalkCheckpoint {
+    pub fn open<P: AsRef<Path>>(_workspace_root: &Path, _cache_dir: P) -> Self {
+        Self
+    }
+
+    pub fn restore(&self, archived_files: Option<NxFileHashes>) -> Option<NxFileHashes> {
+        archived_files
+    }
+
+    pub fn record(&self, _files: &[(String, NxFileHashed)]) {}
+
+    pub fn clear(&self) {}
+}
@@ -2,10 +2,10 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 use napi::bindgen_prelude::External;
-use rayon::prelude::*;
 use tracing::trace;
 
 use crate::native::types::FileData;
+use crate::native::utils::parallel::prelude::*;
 use crate::native::workspace::types::{FileLocation, NxWorkspaceFiles, NxWorkspaceFilesExternals};
 
 pub(super) fn get_files(
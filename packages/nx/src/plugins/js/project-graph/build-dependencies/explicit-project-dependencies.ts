@@ -1,5 +1,5 @@
-import { join, relative } from 'path';
-import { DependencyType } from '../../../../config/project-graph';
+import { relative } from 'path';
+import { DependencyType, FileData } from '../../../../config/project-graph';
 import { ProjectConfiguration } from '../../../../config/workspace-json-project-json';
 import { CreateDependenciesContext } from '../../../../project-graph/plugins';
 import {
@@ -9,6 +9,11 @@ import {
 import { normalizePath } from '../../../../utils/path';
 import { workspaceRoot } from '../../../../utils/workspace-root';
 import { TargetProjectLocator } from './target-project-locator';
+import type { ImportScanner } from '../../../../native';
+
+// The scanner keeps the imports of the files it scanned, so that only the files
+// that changed are scanned again when the project graph is recomputed (e.g. by the daemon)
+let importScanner: ImportScanner | undefined;
 
 function isRoot(
   projects: Record<string, ProjectConfiguration>,
@@ -45,7 +50,7 @@ export function buildExplicitTypeScriptDependencies(
 ): RawProjectGraphDependency[] {
   const res: RawProjectGraphDependency[] = [];
 
-  const filesToProcess: Record<string, string[]> = {};
+  const filesToProcess: Record<string, FileData[]> = {};
 
   const moduleExtensions = [
     '.ts',
@@ -67,16 +72,19 @@ export function buildExplicitTypeScriptDependencies(
     ctx.fileMap.projectFileMap
   )) {
     filesToProcess[project] ??= [];
-    for (const { file } of fileData) {
-      if (moduleExtensions.some((ext) => file.endsWith(ext))) {
-        filesToProcess[project].push(join(workspaceRoot, file));
+    for (const data of fileData) {
+      if (moduleExtensions.some((ext) => data.file.endsWith(ext))) {
+        filesToProcess[project].push(data);
       }
     }
   }
 
-  const { findImports } = require('../../../../native');
+  if (!importScanner) {
+    const { ImportScanner } = require('../../../../native');
+    importScanner = new ImportScanner(workspaceRoot);
+  }
 
-  const imports = findImports(filesToProcess);
+  const imports = importScanner.scan(filesToProcess);
 
   for (const {
     sourceProject,
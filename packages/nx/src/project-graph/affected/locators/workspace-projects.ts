@@ -5,11 +5,26 @@ import {
   findProjectForPath,
 } from '../../utils/find-project-for-path';
 import { InputDefinition } from '../../../config/workspace-json-project-json';
+import { IS_WASM, owningProjects } from '../../../native';
 
 export const getTouchedProjects: TouchedProjectLocator = (
   touchedFiles,
   projectGraphNodes
 ): string[] => {
+  if (!IS_WASM) {
+    const projectRoots: Record<string, string> = {};
+    for (const [name, node] of Object.entries(projectGraphNodes)) {
+      projectRoots[name] = node.data.root;
+    }
+    const owners = owningProjects(
+      touchedFiles.map((f) => f.file),
+      projectRoots
+    );
+    return touchedFiles
+      .map((f) => owners[f.file])
+      .filter((project) => !!project);
+  }
+
   const projectRootMap = createProjectRootMappings(projectGraphNodes);
 
   return touchedFiles.reduce((affected, f) => {
@@ -11,6 +11,13 @@ import { LoadedNxPlugin, nxPluginCache } from '../internal-api';
 import { getPluginOsSocketPath } from '../../../daemon/socket-utils';
 import { consumeMessagesFromSocket } from '../../../utils/consume-messages-from-socket';
 import { signalToCode } from '../../../utils/exit-codes';
+import {
+  IS_WASM,
+  NxPluginWorkerPool,
+  PluginWorker,
+  PluginWorkerEvent,
+  PluginWorkerEventKind,
+} from '../../../native';
 
 import {
   consumeMessage,
@@ -20,7 +27,8 @@ import {
 
 const cleanupFunctions = new Set<() => void>();
 
-const pluginNames = new Map<ChildProcess, string>();
+// pid -> plugin name
+const pluginNames = new Map<number, string>();
 
 const PLUGIN_TIMEOUT_HINT_TEXT =
   'As a last resort, you can set NX_PLUGIN_NO_TIMEOUTS=true to bypass this timeout.';
@@ -38,6 +46,19 @@ interface PendingPromise {
   rejector: (err: any) => void;
 }
 
+/**
+ * The connection to the process of a plugin worker, which is replaced
+ * when the native worker pool restarts the worker.
+ */
+interface PluginWorkerConnection {
+  readonly pid: number;
+  readonly socket: Socket;
+  /** Resolves once the worker can receive requests */
+  ready: Promise<void>;
+  requestStarted?: (tx: string) => void;
+  requestFinished?: (tx: string) => void;
+}
+
 type NxPluginWorkerCache = Map<string, Promise<LoadedNxPlugin>>;
 
 const nxPluginWorkerCache: NxPluginWorkerCache = (global[
@@ -53,7 +74,17 @@ export async function loadRemoteNxPlugin(
     return [nxPluginWorkerCache.get(cacheKey), () => {}];
   }
 
+  const pool = getNativePluginWorkerPool();
+  if (pool) {
+    return loadPooledNxPlugin(pool, plugin, root, cacheKey);
+  }
+
   const { worker, socket } = await startPluginWorker();
+  const connection: PluginWorkerConnection = {
+    pid: worker.pid,
+    socket,
+    ready: Promise.resolve(),
+  };
 
   const pendingPromises = new Map<string, PendingPromise>();
 
@@ -89,14 +120,13 @@ export async function loadRemoteNxPlugin(
       'data',
       consumeMessagesFromSocket(
         createWorkerHandler(
-          worker,
+          connection,
           pendingPromises,
           (val) => {
             if (loadTimeout) clearTimeout(loadTimeout);
             res(val);
           },
-          rej,
-          socket
+          rej
         )
       )
     );
@@ -108,24 +138,180 @@ export async function loadRemoteNxPlugin(
   return [pluginPromise, cleanupFunction];
 }
 
+let nativePluginWorkerPool: NxPluginWorkerPool | null | undefined;
+
+// worker id -> handler of the events of the worker
+const pluginWorkerEventHandlers = new Map<
+  number,
+  (event: PluginWorkerEvent) => void
+>();
+
+function getNativePluginWorkerPool(): NxPluginWorkerPool | null {
+  if (nativePluginWorkerPool === undefined) {
+    nativePluginWorkerPool = IS_WASM ? null : createNativePluginWorkerPool();
+  }
+  return nativePluginWorkerPool;
+}
+
+function createNativePluginWorkerPool() {
+  const { args, env } = getPluginWorkerCommand();
+  const pool = new NxPluginWorkerPool({
+    program: process.execPath,
+    args,
+    env: Object.fromEntries(
+      Object.entries(env).filter(([, value]) => value !== undefined)
+    ),
+    socketPath: getPluginOsSocketPath(`${process.pid}-{id}`),
+    requestTimeoutMs: MAX_MESSAGE_WAIT,
+  });
+  pool.onEvent((event) => {
+    pluginWorkerEventHandlers.get(event.worker.id)?.(event);
+  });
+  return pool;
+}
+
+/**
+ * Loads a plugin in a worker of the native worker pool, which restarts the
+ * worker when it crashes or hangs. The plugin is loaded again in the new
+ * process of the worker before the next requests are sent to it.
+ */
+async function loadPooledNxPlugin(
+  pool: NxPluginWorkerPool,
+  plugin: PluginConfiguration,
+  root: string,
+  cacheKey: string
+): Promise<[Promise<LoadedNxPlugin>, () => void]> {
+  let worker: PluginWorker = pool.startWorker(cacheKey);
+  let socket = await connectToPluginWorker(worker.socketPath);
+
+  const pendingPromises = new Map<string, PendingPromise>();
+  const connection: PluginWorkerConnection = {
+    get pid() {
+      return worker.pid;
+    },
+    get socket() {
+      return socket;
+    },
+    ready: Promise.resolve(),
+    requestStarted: (tx) => pool.requestStarted(worker.id, tx),
+    requestFinished: (tx) => pool.requestFinished(worker.id, tx),
+  };
+
+  const load = (
+    onload: (plugin: LoadedNxPlugin) => void,
+    onloadError: (err?: unknown) => void
+  ) => {
+    socket.on(
+      'data',
+      consumeMessagesFromSocket(
+        createWorkerHandler(connection, pendingPromises, onload, onloadError)
+      )
+    );
+    sendMessageOverSocket(socket, {
+      type: 'load',
+      payload: { plugin, root },
+    });
+  };
+
+  let resolvePlugin: (plugin: LoadedNxPlugin) => void;
+  let rejectPlugin: (err?: unknown) => void;
+  const pluginPromise = new Promise<LoadedNxPlugin>((res, rej) => {
+    const loadTimeout = MAX_MESSAGE_WAIT
+      ? setTimeout(() => {
+          rej(
+            new Error(
+              `Loading "${plugin}" timed out after ${MINUTES} minutes. ${PLUGIN_TIMEOUT_HINT_TEXT}`
+            )
+          );
+        }, MAX_MESSAGE_WAIT)
+      : undefined;
+
+    resolvePlugin = (val) => {
+      if (loadTimeout) clearTimeout(loadTimeout);
+      res(val);
+    };
+    rejectPlugin = rej;
+  });
+
+  // the plugin is resolved by the first process that loads it,
+  // which is a restarted process when the first one crashed
+  const reload = async () => {
+    socket.destroy();
+    socket = await connectToPluginWorker(worker.socketPath);
+    await new Promise<void>((res, rej) =>
+      load(
+        (val) => {
+          resolvePlugin(val);
+          res();
+        },
+        (err) => {
+          rejectPlugin(err);
+          rej(err);
+        }
+      )
+    );
+  };
+
+  pluginWorkerEventHandlers.set(worker.id, (event) => {
+    const name = pluginNames.get(worker.pid) ?? worker.pid;
+    const error =
+      event.kind === PluginWorkerEventKind.hung
+        ? `Plugin worker ${name} did not respond within ${MINUTES} minutes and was restarted. ${PLUGIN_TIMEOUT_HINT_TEXT}`
+        : `Plugin worker ${name} exited unexpectedly with code ${event.exitCode}`;
+    const requests =
+      event.kind === PluginWorkerEventKind.exited
+        ? [...pendingPromises.keys()]
+        : event.requests;
+    for (const tx of requests) {
+      pendingPromises.get(tx)?.rejector(new Error(error));
+    }
+
+    if (event.kind === PluginWorkerEventKind.respawned) {
+      pluginNames.set(event.worker.pid, name);
+      worker = event.worker;
+      connection.ready = reload();
+      // the requests that wait for the worker fail when it cannot be reloaded
+      connection.ready.catch(() => {});
+    } else if (event.kind === PluginWorkerEventKind.exited) {
+      rejectPlugin(new Error(error));
+      pluginWorkerEventHandlers.delete(worker.id);
+      nxPluginWorkerCache.delete(cacheKey);
+    }
+  });
+
+  const cleanupFunction = () => {
+    pluginWorkerEventHandlers.delete(worker.id);
+    shutdownPluginWorker(socket);
+    socket.destroy();
+    pool.stopWorker(worker.id);
+    nxPluginWorkerCache.delete(cacheKey);
+  };
+
+  cleanupFunctions.add(cleanupFunction);
+
+  load(resolvePlugin, rejectPlugin);
+  nxPluginWorkerCache.set(cacheKey, pluginPromise);
+
+  return [pluginPromise, cleanupFunction];
+}
+
 function shutdownPluginWorker(socket: Socket) {
   sendMessageOverSocket(socket, { type: 'shutdown', payload: {} });
 }
 
 /**
  * Creates a message handler for the given worker.
- * @param worker Instance of plugin-worker
+ * @param connection Connection to the plugin-worker
  * @param pending Set of pending promises
  * @param onload Resolver for RemotePlugin promise
  * @param onloadError Rejecter for RemotePlugin promise
  * @returns Function to handle messages from the worker
  */
 function createWorkerHandler(
-  worker: ChildProcess,
+  connection: PluginWorkerConnection,
   pending: Map<string, PendingPromise>,
   onload: (plugin: LoadedNxPlugin) => void,
-  onloadError: (err?: unknown) => void,
-  socket: Socket
+  onloadError: (err?: unknown) => void
 ) {
   let pluginName: string;
 
@@ -137,12 +323,12 @@ function createWorkerHandler(
     if (!isPluginWorkerResult(message)) {
       return;
     }
-    return consumeMessage(socket, message, {
+    return consumeMessage(connection.socket, message, {
       'load-result': (result) => {
         if (result.success) {
           const { name, createNodesPattern, include, exclude } = result;
           pluginName = name;
-          pluginNames.set(worker, pluginName);
+          pluginNames.set(connection.pid, pluginName);
           onload({
             name,
             include,
@@ -152,12 +338,13 @@ function createWorkerHandler(
                   createNodesPattern,
                   (configFiles, ctx) => {
                     const tx =
-                      pluginName + worker.pid + ':createNodes:' + txId++;
+                      pluginName + connection.pid + ':createNodes:' + txId++;
                     return registerPendingPromise(
                       tx,
                       pending,
+                      connection,
                       () => {
-                        sendMessageOverSocket(socket, {
+                        sendMessageOverSocket(connection.socket, {
                           type: 'createNodes',
                           payload: { configFiles, context: ctx, tx },
                         });
@@ -173,12 +360,13 @@ function createWorkerHandler(
             createDependencies: result.hasCreateDependencies
               ? (ctx) => {
                   const tx =
-                    pluginName + worker.pid + ':createDependencies:' + txId++;
+                    pluginName + connection.pid + ':createDependencies:' + txId++;
                   return registerPendingPromise(
                     tx,
                     pending,
+                    connection,
                     () => {
-                      sendMessageOverSocket(socket, {
+                      sendMessageOverSocket(connection.socket, {
                         type: 'createDependencies',
                         payload: { context: ctx, tx },
                       });
@@ -193,12 +381,13 @@ function createWorkerHandler(
             processProjectGraph: result.hasProcessProjectGraph
               ? (graph, ctx) => {
                   const tx =
-                    pluginName + worker.pid + ':processProjectGraph:' + txId++;
+                    pluginName + connection.pid + ':processProjectGraph:' + txId++;
                   return registerPendingPromise(
                     tx,
                     pending,
+                    connection,
                     () => {
-                      sendMessageOverSocket(socket, {
+                      sendMessageOverSocket(connection.socket, {
                         type: 'processProjectGraph',
                         payload: { graph, ctx, tx },
                       });
@@ -213,12 +402,13 @@ function createWorkerHandler(
             createMetadata: result.hasCreateMetadata
               ? (graph, ctx) => {
                   const tx =
-                    pluginName + worker.pid + ':createMetadata:' + txId++;
+                    pluginName + connection.pid + ':createMetadata:' + txId++;
                   return registerPendingPromise(
                     tx,
                     pending,
+                    connection,
                     () => {
-                      sendMessageOverSocket(socket, {
+                      sendMessageOverSocket(connection.socket, {
                         type: 'createMetadata',
                         payload: { graph, context: ctx, tx },
                       });
@@ -280,7 +470,7 @@ function createWorkerExitHandler(
       pendingPromise.rejector(
         new Error(
           `Plugin worker ${
-            pluginNames.get(worker) ?? worker.pid
+            pluginNames.get(worker.pid) ?? worker.pid
           } exited unexpectedly with code ${worker.exitCode}`
         )
       );
@@ -307,6 +497,7 @@ process.on('SIGTERM', exitHandler);
 function registerPendingPromise(
   tx: string,
   pending: Map<string, PendingPromise>,
+  connection: PluginWorkerConnection,
   callback: () => void,
   context: {
     plugin: string;
@@ -329,9 +520,13 @@ function registerPendingPromise(
         }, MAX_MESSAGE_WAIT)
       : undefined;
 
-    callback();
+    connection.ready.then(() => {
+      connection.requestStarted?.(tx);
+      callback();
+    }, rej);
   }).finally(() => {
     pending.delete(tx);
+    connection.requestFinished?.(tx);
     if (timeout) clearTimeout(timeout);
   });
 
@@ -344,8 +539,7 @@ function registerPendingPromise(
   return promise;
 }
 
-global.nxPluginWorkerCount ??= 0;
-async function startPluginWorker() {
+function getPluginWorkerCommand() {
   // this should only really be true when running unit tests within
   // the Nx repo. We still need to start the worker in this case,
   // but its typescript.
@@ -365,41 +559,44 @@ async function startPluginWorker() {
       : {}),
   };
 
+  const args = [
+    ...(isWorkerTypescript ? ['--require', 'ts-node/register'] : []),
+    workerPath,
+  ];
+
+  return { args, env };
+}
+
+global.nxPluginWorkerCount ??= 0;
+async function startPluginWorker() {
+  const { args, env } = getPluginWorkerCommand();
+
   const ipcPath = getPluginOsSocketPath(
     [process.pid, global.nxPluginWorkerCount++].join('-')
   );
 
-  const worker = spawn(
-    process.execPath,
-    [
-      ...(isWorkerTypescript ? ['--require', 'ts-node/register'] : []),
-      workerPath,
-      ipcPath,
-    ],
-    {
-      stdio: 'inherit',
-      env,
-      detached: true,
-      shell: false,
-      windowsHide: true,
-    }
-  );
+  const worker = spawn(process.execPath, [...args, ipcPath], {
+    stdio: 'inherit',
+    env,
+    detached: true,
+    shell: false,
+    windowsHide: true,
+  });
   worker.unref();
 
+  const socket = await connectToPluginWorker(ipcPath);
+  return { worker, socket };
+}
+
+function connectToPluginWorker(ipcPath: string) {
   let attempts = 0;
-  return new Promise<{
-    worker: ChildProcess;
-    socket: Socket;
-  }>((resolve, reject) => {
+  return new Promise<Socket>((resolve, reject) => {
     const id = setInterval(async () => {
       const socket = await isServerAvailable(ipcPath);
       if (socket) {
         socket.unref();
         clearInterval(id);
-        resolve({
-          worker,
-          socket,
-        });
+        resolve(socket);
       } else if (attempts > 10000) {
         // daemon fails to start, the process probably exited
         // we print the logs and exit the client
@@ -6,7 +6,7 @@ import {
   TargetConfiguration,
   TargetMetadata,
 } from '../../config/workspace-json-project-json';
-import { NX_PREFIX } from '../../utils/logger';
+import { logger, NX_PREFIX } from '../../utils/logger';
 import { readJsonFile } from '../../utils/fileutils';
 import { workspaceRoot } from '../../utils/workspace-root';
 
@@ -30,6 +30,7 @@ import {
 } from '../error-types';
 import { CreateNodesResult } from '../plugins';
 import { isGlobPattern } from '../../utils/globs';
+import { IS_WASM, materializeProjectTargets } from '../../native';
 
 export type SourceInformation = [file: string | null, plugin: string];
 export type ConfigurationSourceMaps = Record<
@@ -618,7 +619,13 @@ function validateAndNormalizeProjectRootMap(
       }
     }
 
-    normalizeTargets(project, sourceMaps, nxJsonConfiguration);
+    if (IS_WASM) {
+      normalizeTargets(project, sourceMaps, nxJsonConfiguration);
+    }
+  }
+
+  if (!IS_WASM) {
+    materializeTargets(projectRootMap, sourceMaps, nxJsonConfiguration);
   }
 
   if (conflicts.size > 0) {
@@ -630,6 +637,39 @@ function validateAndNormalizeProjectRootMap(
   return projectRootMap;
 }
 
+/**
+ * Normalizes the targets of all of the projects and merges the target defaults
+ * into them natively, like {@link normalizeTargets} does for each project.
+ */
+function materializeTargets(
+  projectRootMap: Record<string, ProjectConfiguration>,
+  sourceMaps: ConfigurationSourceMaps,
+  nxJsonConfiguration: NxJsonConfiguration<'*' | string[]>
+) {
+  const { projects, targetDefaultProperties, conflicts } =
+    materializeProjectTargets(
+      JSON.stringify(projectRootMap),
+      nxJsonConfiguration.targetDefaults &&
+        JSON.stringify(nxJsonConfiguration.targetDefaults),
+      sourceMaps
+    );
+
+  const materializedProjects: Record<string, ProjectConfiguration> =
+    JSON.parse(projects);
+  for (const root in materializedProjects) {
+    projectRootMap[root] = materializedProjects[root];
+  }
+  for (const root in targetDefaultProperties) {
+    sourceMaps[root] ??= {};
+    for (const key of targetDefaultProperties[root]) {
+      sourceMaps[root][key] = ['nx.json', 'nx/target-defaults'];
+    }
+  }
+  for (const conflict of conflicts) {
+    logger.verbose(conflict.message);
+  }
+}
+
 function normalizeTargets(
   project: ProjectConfiguration,
   sourceMaps: ConfigurationSourceMaps,
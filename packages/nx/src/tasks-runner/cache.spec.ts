@@ -0,0 +1,167 @@
+import { existsSync, mkdirSync } from 'fs';
+import { join } from 'path';
+import { TempFs } from '../internal-testing-utils/temp-fs';
+import { Task } from '../config/task-graph';
+import { DefaultTasksRunnerOptions } from './default-tasks-runner';
+import { Cache } from './cache';
+
+function createMockTask(hash: string, outputs: string[]): Task {
+  return {
+    id: 'web:build',
+    target: {
+      project: 'web',
+      target: 'build',
+    },
+    outputs,
+    overrides: {},
+    hash,
+    parallelism: true,
+  };
+}
+
+describe('Cache', () => {
+  let fs: TempFs;
+  let cacheFs: TempFs;
+  let cache: Cache;
+  let originalCompression: string;
+
+  beforeEach(async () => {
+    fs = new TempFs('cache');
+    cacheFs = new TempFs('cache-directory', false);
+    originalCompression = process.env.NX_CACHE_COMPRESSION;
+    delete process.env.NX_CACHE_COMPRESSION;
+
+    cache = new Cache({} as DefaultTasksRunnerOptions);
+    cache.root = fs.tempDir;
+    cache.cachePath = cacheFs.tempDir;
+    cache.terminalOutputsDir = join(cacheFs.tempDir, 'terminalOutputs');
+    mkdirSync(cache.terminalOutputsDir);
+
+    await fs.createFiles({
+      'dist/apps/web/main.js': 'main',
+      'dist/apps/web/main.js.map': 'map',
+      'dist/apps/web/assets/logo.svg': 'logo',
+      'dist/apps/web/cache/contents': 'contents',
+      'dist/libs/ui/index.js': 'ui',
+    });
+  });
+
+  afterEach(() => {
+    if (originalCompression === undefined) {
+      delete process.env.NX_CACHE_COMPRESSION;
+    } else {
+      process.env.NX_CACHE_COMPRESSION = originalCompression;
+    }
+    cacheFs.cleanup();
+    fs.cleanup();
+  });
+
+  async function cacheOutputs(task: Task) {
+    await cache.put(task, 'terminal output', task.outputs, 0);
+    const cachedResult = await cache.get(task);
+    expect(cachedResult).not.toBeNull();
+    return cachedResult;
+  }
+
+  function exists(path: string) {
+    return existsSync(join(fs.tempDir, path));
+  }
+
+  it('should cache and restore the files matched by glob outputs', async () => {
+    const task = createMockTask('glob', [
+      'dist/apps/web/**/*.js',
+      'dist/apps/web/assets',
+    ]);
+    const cachedResult = await cacheOutputs(task);
+    expect(
+      existsSync(join(cachedResult.outputsPath, 'dist/apps/web/main.js.map'))
+    ).toEqual(false);
+    expect(existsSync(join(cachedResult.outputsPath, 'dist/libs'))).toEqual(
+      false
+    );
+    fs.removeFileSync('dist/apps/web/main.js');
+    fs.removeFileSync('dist/apps/web/assets/logo.svg');
+
+    await cache.copyFilesFromCache(task.hash, cachedResult, task.outputs);
+
+    expect(await fs.readFile('dist/apps/web/main.js')).toEqual('main');
+    expect(await fs.readFile('dist/apps/web/assets/logo.svg')).toEqual(
+      'logo'
+    );
+  });
+
+  it('should not cache the files excluded by negated outputs', async () => {
+    const task = createMockTask('negated', [
+      'dist/apps/web',
+      '!dist/apps/web/cache',
+    ]);
+    const cachedResult = await cacheOutputs(task);
+    expect(
+      existsSync(join(cachedResult.outputsPath, 'dist/apps/web/main.js.map'))
+    ).toEqual(true);
+    expect(
+      existsSync(join(cachedResult.outputsPath, 'dist/apps/web/cache'))
+    ).toEqual(false);
+    fs.removeFileSync('dist/apps/web/main.js');
+    fs.removeFileSync('dist/apps/web/cache/contents');
+
+    await cache.copyFilesFromCache(task.hash, cachedResult, task.outputs);
+
+    expect(await fs.readFile('dist/apps/web/main.js')).toEqual('main');
+    expect(exists('dist/apps/web/cache/contents')).toEqual(false);
+  });
+
+  it('should restore the outputs over the existing files', async () => {
+    const task = createMockTask('existing', ['dist/libs/ui']);
+    const cachedResult = await cacheOutputs(task);
+    fs.writeFile('dist/libs/ui/index.js', 'changed');
+    fs.writeFile('dist/libs/ui/stale.js', 'stale');
+
+    await cache.copyFilesFromCache(task.hash, cachedResult, task.outputs);
+
+    expect(await fs.readFile('dist/libs/ui/index.js')).toEqual('ui');
+    expect(exists('dist/libs/ui/stale.js')).toEqual(false);
+  });
+
+  describe('with NX_CACHE_COMPRESSION=zstd', () => {
+    beforeEach(() => {
+      process.env.NX_CACHE_COMPRESSION = 'zstd';
+    });
+
+    it('should pack the outputs into outputs.tar.zst', async () => {
+      const task = createMockTask('compressed', [
+        'dist/apps/web/**/*.js',
+        'dist/apps/web/assets',
+      ]);
+      const cachedResult = await cacheOutputs(task);
+      expect(cachedResult.outputsPath).toEqual(
+        join(cacheFs.tempDir, 'compressed', 'outputs.tar.zst')
+      );
+      expect(
+        existsSync(join(cacheFs.tempDir, 'compressed', 'outputs'))
+      ).toEqual(false);
+      fs.removeFileSync('dist/apps/web/main.js');
+      fs.removeFileSync('dist/apps/web/main.js.map');
+
+      await cache.copyFilesFromCache(task.hash, cachedResult, task.outputs);
+
+      expect(await fs.readFile('dist/apps/web/main.js')).toEqual('main');
+      expect(await fs.readFile('dist/apps/web/assets/logo.svg')).toEqual(
+        'logo'
+      );
+      expect(exists('dist/apps/web/main.js.map')).toEqual(false);
+    });
+
+    it('should restore the archived outputs over the existing files', async () => {
+      const task = createMockTask('compressed-existing', ['dist/libs/ui']);
+      const cachedResult = await cacheOutputs(task);
+      fs.writeFile('dist/libs/ui/index.js', 'changed');
+      fs.writeFile('dist/libs/ui/stale.js', 'stale');
+
+      await cache.copyFilesFromCache(task.hash, cachedResult, task.outputs);
+
+      expect(await fs.readFile('dist/libs/ui/index.js')).toEqual('ui');
+      expect(exists('dist/libs/ui/stale.js')).toEqual(false);
+    });
+  });
+});
@@ -11,12 +11,25 @@ import { spawn } from 'child_process';
 import { cacheDir } from '../utils/cache-directory';
 import { Task } from '../config/task-graph';
 import { machineId } from 'node-machine-id';
-import { NxCache, CachedResult as NativeCacheResult } from '../native';
+import {
+  BlobStorageCacheClient,
+  BlobStorageCacheOptions,
+  CompressionOptions,
+  configureCompression,
+  IS_WASM,
+  NxCache,
+  CachedResult as NativeCacheResult,
+  RemoteCacheClient,
+  RemoteExecutionCacheClient,
+  RemoteExecutionCacheOptions,
+} from '../native';
 import { getDbConnection } from '../utils/db-connection';
 import { isNxCloudUsed } from '../utils/nx-cloud-utils';
 import { readNxJson } from '../config/nx-json';
 import { verifyOrUpdateNxCloudClient } from '../nx-cloud/update-manager';
 import { getCloudOptions } from '../nx-cloud/utilities/get-cloud-options';
+import { output } from '../utils/output';
+import { getCacheGcOptions } from '../utils/cache-gc';
 
 export type CachedResult = {
   terminalOutput: string;
@@ -26,7 +39,16 @@ export type CachedResult = {
 };
 export type TaskWithCachedResult = { task: Task; cachedResult: CachedResult };
 
+/**
+ * The outputs of a task are packed into this zstd-compressed tarball
+ * instead of the `outputs` directory when `NX_CACHE_COMPRESSION=zstd`
+ */
+const ARCHIVED_OUTPUTS = 'outputs.tar.zst';
+
 export function getCache(options: DefaultTasksRunnerOptions) {
+  if (options.compression && !IS_WASM) {
+    configureCompression(options.compression as CompressionOptions);
+  }
   return process.env.NX_DISABLE_DB !== 'true' &&
     process.env.NX_DB_CACHE === 'true'
     ? new DbCache({
@@ -34,6 +56,8 @@ export function getCache(options: DefaultTasksRunnerOptions) {
         nxCloudRemoteCache: isNxCloudUsed(readNxJson())
           ? options.remoteCache
           : null,
+        remoteExecutionCache: options.remoteExecutionCache,
+        blobStorageCache: options.blobStorageCache,
       })
     : new Cache(options);
 }
@@ -47,7 +71,13 @@ export class DbCache {
     this.remoteCache = await this.getRemoteCache();
   }
 
-  constructor(private readonly options: { nxCloudRemoteCache: RemoteCache }) {}
+  constructor(
+    private readonly options: {
+      nxCloudRemoteCache: RemoteCache;
+      remoteExecutionCache?: RemoteExecutionCacheOptions;
+      blobStorageCache?: BlobStorageCacheOptions;
+    }
+  ) {}
 
   async get(task: Task): Promise<CachedResult | null> {
     const res = this.cache.get(task.hash);
@@ -58,6 +88,7 @@ export class DbCache {
         remote: false,
       };
     }
+    this.warnAboutCorruptedEntries();
     await this.setup();
     if (this.remoteCache) {
       // didn't find it locally but we have a remote cache
@@ -93,20 +124,46 @@ export class DbCache {
     code: number
   ) {
     return tryAndRetry(async () => {
-      this.cache.put(task.hash, terminalOutput, outputs, code);
+      // the remote cache gets the terminal output without the secrets that were printed
+      const storedTerminalOutput = this.cache.put(
+        task.hash,
+        terminalOutput,
+        outputs,
+        code,
+        task.id
+      );
 
       await this.setup();
       if (this.remoteCache) {
         await this.remoteCache.store(
           task.hash,
           this.cache.cacheDirectory,
-          terminalOutput,
+          storedTerminalOutput,
           code
         );
       }
     });
   }
 
+  /**
+   * Whether each task will be replayed from the local or the remote cache, by task id,
+   * with a single lookup in each of them. The tasks have to be hashed already
+   */
+  async checkCacheHits(tasks: Task[]): Promise<Record<string, boolean>> {
+    const hashes = [...new Set(tasks.map((task) => task.hash))];
+    const hits = this.cache.checkCacheHits(hashes);
+    const misses = hashes.filter((hash) => !hits[hash]);
+    if (misses.length > 0) {
+      await this.setup();
+      if (this.remoteCache?.checkCacheHits) {
+        Object.assign(hits, await this.remoteCache.checkCacheHits(misses));
+      }
+    }
+    return Object.fromEntries(
+      tasks.map((task) => [task.id, hits[task.hash] ?? false])
+    );
+  }
+
   copyFilesFromCache(_: string, cachedResult: CachedResult, outputs: string[]) {
     return tryAndRetry(async () =>
       this.cache.copyFilesFromCache(cachedResult, outputs)
@@ -114,13 +171,25 @@ export class DbCache {
   }
 
   removeOldCacheRecords() {
-    return this.cache.removeOldCacheRecords();
+    return this.cache.collectGarbage(getCacheGcOptions());
   }
 
   temporaryOutputPath(task: Task) {
     return this.cache.getTaskOutputsPath(task.hash);
   }
 
+  private warnAboutCorruptedEntries() {
+    for (const entry of this.cache.takeCorruptedEntries()) {
+      output.warn({
+        title: `The cache entry of ${entry.hash} is corrupted and will not be restored`,
+        bodyLines: [
+          `Expected the digest ${entry.expectedDigest} but found ${entry.actualDigest}.`,
+          `The entry was moved to ${entry.quarantinePath} and the task will be run again.`,
+        ],
+      });
+    }
+  }
+
   private async getRemoteCache(): Promise<RemoteCacheV2 | null> {
     if (this.remoteCachePromise) {
       return this.remoteCachePromise;
@@ -131,6 +200,36 @@ export class DbCache {
   }
 
   private async _getRemoteCache(): Promise<RemoteCacheV2 | null> {
+    const selfHostedServer = process.env.NX_SELF_HOSTED_REMOTE_CACHE_SERVER;
+    if (selfHostedServer && !IS_WASM) {
+      return toRemoteCache(
+        new RemoteCacheClient({
+          url: selfHostedServer,
+          accessToken: process.env.NX_SELF_HOSTED_REMOTE_CACHE_ACCESS_TOKEN,
+          chunking: process.env.NX_SELF_HOSTED_REMOTE_CACHE_CHUNKING === 'true',
+          fileTransfers:
+            process.env.NX_SELF_HOSTED_REMOTE_CACHE_FILE_TRANSFERS === 'true',
+        })
+      );
+    }
+    if (this.options.remoteExecutionCache && !IS_WASM) {
+      return toRemoteCache(
+        new RemoteExecutionCacheClient(this.options.remoteExecutionCache)
+      );
+    }
+    const blobStorageCache = process.env.NX_BLOB_STORAGE_CACHE
+      ? {
+          ...this.options.blobStorageCache,
+          url: process.env.NX_BLOB_STORAGE_CACHE,
+          endpoint:
+            process.env.NX_BLOB_STORAGE_CACHE_ENDPOINT ??
+            this.options.blobStorageCache?.endpoint,
+        }
+      : this.options.blobStorageCache;
+    if (blobStorageCache && !IS_WASM) {
+      return toRemoteCache(new BlobStorageCacheClient(blobStorageCache));
+    }
+
     const nxJson = readNxJson();
     if (isNxCloudUsed(nxJson)) {
       const options = getCloudOptions();
@@ -236,17 +335,11 @@ export class Cache {
         terminalOutput ?? 'no terminal output'
       );
 
-      await mkdir(join(td, 'outputs'));
-      const expandedOutputs = await this.expandOutputsInWorkspace(outputs);
-
-      await Promise.all(
-        expandedOutputs.map(async (f) => {
-          const src = join(this.root, f);
-          if (await pathExists(src)) {
-            const cached = join(td, 'outputs', f);
-            await this.copy(src, cached);
-          }
-        })
+      const compress = shouldCompressOutputs();
+      this.packOutputs(
+        outputs,
+        join(td, compress ? ARCHIVED_OUTPUTS : 'outputs'),
+        compress
       );
       // we need this file to account for partial writes to the cache folder.
       // creating this file is atomic, whereas creating a folder is not.
@@ -273,19 +366,14 @@ export class Cache {
     outputs: string[]
   ) {
     return tryAndRetry(async () => {
-      const expandedOutputs = await this.expandOutputsInCache(
-        outputs,
-        cachedResult
-      );
-      await Promise.all(
-        expandedOutputs.map(async (f) => {
-          const cached = join(cachedResult.outputsPath, f);
-          if (await pathExists(cached)) {
-            const src = join(this.root, f);
-            await this.remove(src);
-            await this.copy(cached, src);
-          }
-        })
+      const { unpackOutputs } = require('../native');
+      performance.mark('unpackOutputs:start');
+      unpackOutputs(cachedResult.outputsPath, this.root, outputs);
+      performance.mark('unpackOutputs:end');
+      performance.measure(
+        'unpackOutputs',
+        'unpackOutputs:start',
+        'unpackOutputs:end'
       );
     });
   }
@@ -294,49 +382,20 @@ export class Cache {
     return join(this.terminalOutputsDir, task.hash);
   }
 
-  private async expandOutputsInWorkspace(outputs: string[]) {
-    return this._expandOutputs(outputs, workspaceRoot);
-  }
-
-  private async expandOutputsInCache(
+  private packOutputs(
     outputs: string[],
-    cachedResult: CachedResult
+    destination: string,
+    compress: boolean
   ) {
-    return this._expandOutputs(outputs, cachedResult.outputsPath);
-  }
-
-  private async _expandOutputs(
-    outputs: string[],
-    cwd: string
-  ): Promise<string[]> {
-    const { expandOutputs } = require('../native');
-    performance.mark('expandOutputs:start');
-    const results = expandOutputs(cwd, outputs);
-    performance.mark('expandOutputs:end');
+    const { packOutputs } = require('../native');
+    performance.mark('packOutputs:start');
+    packOutputs(this.root, outputs, destination, compress);
+    performance.mark('packOutputs:end');
     performance.measure(
-      'expandOutputs',
-      'expandOutputs:start',
-      'expandOutputs:end'
+      'packOutputs',
+      'packOutputs:start',
+      'packOutputs:end'
     );
-
-    return results;
-  }
-
-  private async copy(src: string, destination: string): Promise<void> {
-    const { copy } = require('../native');
-    // 'cp -a /path/dir/ dest/' operates differently to 'cp -a /path/dir dest/'
-    // --> which means actual build works but subsequent populate from cache (using cp -a) does not
-    // --> the fix is to remove trailing slashes to ensure consistent & expected behaviour
-    src = src.replace(/[\/\\]$/, '');
-
-    return new Promise((res, rej) => {
-      try {
-        copy(src, destination);
-        res();
-      } catch (e) {
-        rej(e);
-      }
-    });
   }
 
   private async remove(path: string): Promise<void> {
@@ -365,9 +424,12 @@ export class Cache {
         code = Number(await readFile(join(td, 'code'), 'utf-8'));
       } catch {}
 
+      const archivedOutputs = join(td, ARCHIVED_OUTPUTS);
       return {
         terminalOutput,
-        outputsPath: join(td, 'outputs'),
+        outputsPath: (await pathExists(archivedOutputs))
+          ? archivedOutputs
+          : join(td, 'outputs'),
         code,
       };
     } else {
@@ -412,6 +474,61 @@ export class Cache {
   }
 }
 
+/**
+ * Wraps a native remote cache client, failed requests are reported as warnings instead of failing the task
+ */
+function toRemoteCache(
+  client:
+    | RemoteCacheClient
+    | RemoteExecutionCacheClient
+    | BlobStorageCacheClient
+): RemoteCacheV2 {
+  return {
+    retrieve: async (hash, cacheDirectory) => {
+      try {
+        return await client.retrieve(hash, cacheDirectory);
+      } catch (e) {
+        output.warn({
+          title: `Unable to retrieve ${hash} from the remote cache`,
+          bodyLines: [e.message],
+        });
+        return null;
+      }
+    },
+    store: async (hash, cacheDirectory, terminalOutput, code) => {
+      try {
+        return await client.store(
+          hash,
+          cacheDirectory,
+          terminalOutput ?? '',
+          code
+        );
+      } catch (e) {
+        output.warn({
+          title: `Unable to store ${hash} in the remote cache`,
+          bodyLines: [e.message],
+        });
+        return false;
+      }
+    },
+    checkCacheHits: async (hashes) => {
+      try {
+        return await client.checkCacheHits(hashes);
+      } catch (e) {
+        output.warn({
+          title: `Unable to check the remote cache for the tasks that will be replayed`,
+          bodyLines: [e.message],
+        });
+        return {};
+      }
+    },
+  };
+}
+
+function shouldCompressOutputs() {
+  return process.env.NX_CACHE_COMPRESSION === 'zstd' && !IS_WASM;
+}
+
 function tryAndRetry<T>(fn: () => Promise<T>): Promise<T> {
   let attempts = 0;
   // Generate a random number between 2 and 4 to raise to the power of attempts
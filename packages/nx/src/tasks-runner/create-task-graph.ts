@@ -320,6 +320,7 @@ export class ProcessTasks {
       ),
       cache: project.data.targets[target].cache,
       parallelism: project.data.targets[target].parallelism ?? true,
+      resourceLimits: project.data.targets[target].resourceLimits,
     };
   }
 
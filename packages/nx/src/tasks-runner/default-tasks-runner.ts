@@ -10,7 +10,11 @@ import { DaemonClient } from '../daemon/client/client';
 import { cacheDir } from '../utils/cache-directory';
 import { readFile, writeFile, mkdir } from 'fs/promises';
 import { join } from 'path';
-import { CachedResult } from '../native';
+import {
+  BlobStorageCacheOptions,
+  CachedResult,
+  RemoteExecutionCacheOptions,
+} from '../native';
 
 export interface RemoteCache {
   retrieve: (hash: string, cacheDirectory: string) => Promise<boolean>;
@@ -63,6 +67,10 @@ export abstract class RemoteCacheV2 {
     terminalOutput: string,
     code: number
   ): Promise<boolean>;
+  /**
+   * Whether the remote cache has the artifact of each hash, without downloading them
+   */
+  checkCacheHits?(hashes: string[]): Promise<Record<string, boolean>>;
 }
 
 export interface DefaultTasksRunnerOptions {
@@ -72,10 +80,53 @@ export interface DefaultTasksRunnerOptions {
   runtimeCacheInputs?: string[];
   cacheDirectory?: string;
   remoteCache?: RemoteCache;
+  /**
+   * A Bazel Remote Execution API compatible server (e.g. BuildBarn or Buildfarm) used as remote cache
+   */
+  remoteExecutionCache?: RemoteExecutionCacheOptions;
+  /**
+   * An S3, GCS or Azure Blob Storage bucket used as remote cache
+   */
+  blobStorageCache?: BlobStorageCacheOptions;
   lifeCycle: LifeCycle;
   captureStderr?: boolean;
   skipNxCache?: boolean;
   batch?: boolean;
+  /**
+   * The algorithm of the hashes of the tasks, defaults to xxh3.
+   * Use blake3 or sha256 with remote caches that address artifacts by a cryptographic digest
+   */
+  hashAlgorithm?: 'xxh3' | 'blake3' | 'sha256';
+  /**
+   * Mixes the OS and the CPU architecture into the hash of every task, so results are not shared between platforms
+   */
+  includePlatform?: boolean;
+  /**
+   * Hashes the external dependencies along with their transitive dependencies from the lock file of the workspace
+   */
+  hashExternalsFromLockFile?: boolean;
+  /**
+   * The maximum time in milliseconds a runtime input command can take before hashing fails, defaults to no timeout
+   */
+  runtimeTimeout?: number;
+  /**
+   * Applies the negated globs of the file sets in order, so later globs override earlier ones (like .gitignore)
+   * instead of excluding every file matched by a negated glob
+   */
+  orderedNegatedGlobs?: boolean;
+  /**
+   * Hashes the files without a hash (e.g. untracked files) from their content on the disk
+   */
+  hashMissingFiles?: boolean;
+  /**
+   * The compression of the cached artifacts. Its levels are chosen from the measured throughput by default:
+   * fast levels for the local cache, higher levels for remote caches
+   */
+  compression?: {
+    algorithm?: 'zstd' | 'brotli';
+    localLevel?: number;
+    remoteLevel?: number;
+  };
 }
 
 export const defaultTasksRunner: TasksRunner<
@@ -0,0 +1,39 @@
+import { TaskGraph } from '../config/task-graph';
+import { estimateTaskGraphDuration, IS_WASM } from '../native';
+import { getTaskHistory } from '../utils/task-history';
+
+/**
+ * Estimates how long running the tasks of the graph takes in milliseconds,
+ * with the median duration of the last runs of every task in the task history.
+ * Tasks that are restored from the cache take less, so runs that hit the cache
+ * are faster than the estimate.
+ *
+ * Returns null when none of the tasks ran before or the Nx database is disabled.
+ */
+export function estimateRunDuration(
+  taskGraph: TaskGraph,
+  parallel: number
+): number | null {
+  if (IS_WASM || process.env.NX_DISABLE_DB === 'true') {
+    return null;
+  }
+  try {
+    const tasks = Object.values(taskGraph.tasks);
+    const stats = getTaskHistory().getTaskDurationStats(
+      tasks.map((task) => task.target)
+    );
+    const estimatedDurations: Record<string, number> = {};
+    for (const task of tasks) {
+      if (stats[task.id]) {
+        estimatedDurations[task.id] = stats[task.id].median;
+      }
+    }
+    if (Object.keys(estimatedDurations).length === 0) {
+      return null;
+    }
+    return estimateTaskGraphDuration(taskGraph, estimatedDurations, parallel);
+  } catch {
+    // the estimate is informative, a run does not fail without it
+    return null;
+  }
+}
@@ -21,6 +21,13 @@ import {
   PseudoTerminal,
 } from './pseudo-terminal';
 import { signalToCode } from '../utils/exit-codes';
+import {
+  AnsiMode,
+  ExitDetails,
+  IS_WASM,
+  killTree,
+  TaskMetrics,
+} from '../native';
 
 const forkScript = join(__dirname, './fork.js');
 
@@ -170,7 +177,11 @@ export class ForkedProcessTaskRunner {
       env: NodeJS.ProcessEnv;
       disablePseudoTerminal: boolean;
     }
-  ): Promise<{ code: number; terminalOutput: string }> {
+  ): Promise<{
+    code: number;
+    terminalOutput: string;
+    metrics?: TaskMetrics;
+  }> {
     const shouldPrefix =
       streamOutput && process.env.NX_PREFIX_OUTPUT === 'true';
 
@@ -211,7 +222,11 @@ export class ForkedProcessTaskRunner {
       taskGraph: TaskGraph;
       env: NodeJS.ProcessEnv;
     }
-  ): Promise<{ code: number; terminalOutput: string }> {
+  ): Promise<{
+    code: number;
+    terminalOutput: string;
+    metrics?: TaskMetrics;
+  }> {
     const args = getPrintableCommandArgsForTask(task);
     if (streamOutput) {
       output.logCommand(args.join(' '));
@@ -223,6 +238,7 @@ export class ForkedProcessTaskRunner {
       execArgv: process.execArgv,
       jsEnv: env,
       quiet: !streamOutput,
+      resourceLimits: task.resourceLimits,
     });
 
     p.send({
@@ -234,12 +250,23 @@ export class ForkedProcessTaskRunner {
     this.processes.add(p);
 
     let terminalOutput = '';
+    // cursor movements would corrupt replays of the cached output, colors are kept
     p.onOutput((msg) => {
       terminalOutput += msg;
-    });
+    }, AnsiMode.normalize);
 
     return new Promise((res) => {
-      p.onExit((code) => {
+      p.onExit((code, details) => {
+        if (details.memoryLimitExceeded) {
+          output.error({
+            title: `${task.id} exceeded its memory limit of ${task.resourceLimits?.memory} MiB`,
+            bodyLines: [
+              'Increase "resourceLimits.memory" in the configuration of the target, or reduce the memory usage of the task.',
+            ],
+          });
+        } else if (details.signal) {
+          printSignalExit(task, details);
+        }
         // If the exit code is greater than 128, it's a special exit code for a signal
         if (code >= 128) {
           process.exit(code);
@@ -248,6 +275,7 @@ export class ForkedProcessTaskRunner {
         res({
           code,
           terminalOutput,
+          metrics: details.metrics,
         });
       });
     });
@@ -454,6 +482,22 @@ export class ForkedProcessTaskRunner {
     writeFileSync(outputPath, content);
   }
 
+  /** Kills the workers started by the task along with the task process */
+  private killProcessTree(
+    p: ChildProcess | PseudoTtyProcess,
+    signal?: NodeJS.Signals
+  ) {
+    if ('connected' in p && !IS_WASM) {
+      try {
+        killTree(p.pid, signal);
+        return;
+      } catch {
+        // fall back to killing the process itself
+      }
+    }
+    p.kill(signal);
+  }
+
   private setupProcessEventListeners() {
     if (this.pseudoTerminal) {
       this.pseudoTerminal.onMessageFromChildren((message: Serializable) => {
@@ -479,14 +523,14 @@ export class ForkedProcessTaskRunner {
     process.on('exit', () => {
       this.processes.forEach((p) => {
         if ('connected' in p ? p.connected : p.isAlive) {
-          p.kill();
+          this.killProcessTree(p);
         }
       });
     });
     process.on('SIGINT', () => {
       this.processes.forEach((p) => {
         if ('connected' in p ? p.connected : p.isAlive) {
-          p.kill('SIGTERM');
+          this.killProcessTree(p, 'SIGTERM');
         }
       });
       // we exit here because we don't need to write anything to cache.
@@ -495,7 +539,7 @@ export class ForkedProcessTaskRunner {
     process.on('SIGTERM', () => {
       this.processes.forEach((p) => {
         if ('connected' in p ? p.connected : p.isAlive) {
-          p.kill('SIGTERM');
+          this.killProcessTree(p, 'SIGTERM');
         }
       });
       // no exit here because we expect child processes to terminate which
@@ -504,7 +548,7 @@ export class ForkedProcessTaskRunner {
     process.on('SIGHUP', () => {
       this.processes.forEach((p) => {
         if ('connected' in p ? p.connected : p.isAlive) {
-          p.kill('SIGTERM');
+          this.killProcessTree(p, 'SIGTERM');
         }
       });
       // no exit here because we expect child processes to terminate which
@@ -552,3 +596,28 @@ function logClearLineToPrefixTransformer(prefix) {
     },
   });
 }
+
+function printSignalExit(task: Task, details: ExitDetails) {
+  const bodyLines: string[] = [];
+  if (details.oomKilled) {
+    bodyLines.push(
+      'The process ran out of memory and was killed by the operating system.',
+      'Reduce the memory usage of the task, or run fewer tasks in parallel with --parallel.'
+    );
+  } else if (details.signal === 'SIGKILL') {
+    bodyLines.push(
+      'SIGKILL is usually sent when the machine is out of memory or the task was killed externally.'
+    );
+  }
+  if (details.coreDumpSignal) {
+    bodyLines.push(
+      'The process crashed, a core dump may have been written depending on the ulimit -c setting.'
+    );
+  }
+  output.error({
+    title: `${task.id} was killed by ${details.signal}${
+      details.oomKilled ? ' (out of memory)' : ''
+    }`,
+    bodyLines,
+  });
+}
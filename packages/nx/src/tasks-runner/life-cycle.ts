@@ -1,11 +1,16 @@
 import { TaskStatus } from './tasks-runner';
 import { Task } from '../config/task-graph';
+import { TaskMetrics } from '../native';
 
 export interface TaskResult {
   task: Task;
   status: TaskStatus;
   code: number;
   terminalOutput?: string;
+  /**
+   * The CPU time, peak memory and I/O of the task, when it ran in a pseudo terminal
+   */
+  metrics?: TaskMetrics;
 }
 
 export interface TaskMetadata {
@@ -19,6 +24,12 @@ export interface LifeCycle {
 
   scheduleTask?(task: Task): void | Promise<void>;
 
+  /**
+   * Called before the tasks run with whether each task that was hashed in advance
+   * will be replayed from the cache, by task id
+   */
+  expectCacheHits?(cacheHits: Record<string, boolean>): void | Promise<void>;
+
   /**
    * @deprecated use startTasks
    *
@@ -74,6 +85,14 @@ export class CompositeLifeCycle implements LifeCycle {
     }
   }
 
+  async expectCacheHits(cacheHits: Record<string, boolean>): Promise<void> {
+    for (let l of this.lifeCycles) {
+      if (l.expectCacheHits) {
+        await l.expectCacheHits(cacheHits);
+      }
+    }
+  }
+
   startTask(task: Task): void {
     for (let l of this.lifeCycles) {
       if (l.startTask) {
@@ -4,6 +4,7 @@ import { getPrintableCommandArgsForTask } from '../utils';
 import type { LifeCycle } from '../life-cycle';
 import { Task } from '../../config/task-graph';
 import { formatFlags, formatTargetsAndProjects } from './formatting-utils';
+import { prettyTime } from './pretty-time';
 
 /**
  * The following life cycle's outputs are static, meaning no previous content
@@ -25,7 +26,11 @@ export class StaticRunManyTerminalOutputLifeCycle implements LifeCycle {
       targets?: string[];
       configuration?: string;
     },
-    private readonly taskOverrides: any
+    private readonly taskOverrides: any,
+    /**
+     * How long the run is estimated to take in milliseconds, from the previous runs of the tasks
+     */
+    private readonly estimatedDuration: number | null = null
   ) {}
 
   startCommand(): void {
@@ -50,6 +55,18 @@ export class StaticRunManyTerminalOutputLifeCycle implements LifeCycle {
         .map(([flag, value]) => formatFlags('', flag, value))
         .forEach((arg) => bodyLines.push(arg));
     }
+    // runs that take less than a second are not worth an estimate
+    if (this.estimatedDuration !== null && this.estimatedDuration >= 1000) {
+      bodyLines.push('');
+      bodyLines.push(
+        output.dim(
+          `Estimated time without the cache: ${prettyTime(
+            Math.round(this.estimatedDuration) * 1e6,
+            's'
+          )}`
+        )
+      );
+    }
 
     const title = `Running ${formatTargetsAndProjects(
       this.projectNames,
@@ -66,6 +83,15 @@ export class StaticRunManyTerminalOutputLifeCycle implements LifeCycle {
     output.addVerticalSeparatorWithoutNewLines('cyan');
   }
 
+  expectCacheHits(cacheHits: Record<string, boolean>): void {
+    const hits = Object.values(cacheHits).filter((hit) => hit).length;
+    if (hits > 0) {
+      output.logSingleLine(
+        `Nx will read the output from the cache instead of running the command for ${hits} out of ${this.tasks.length} tasks.`
+      );
+    }
+  }
+
   endCommand(): void {
     output.addNewline();
 
@@ -39,6 +39,7 @@ export class TaskHistoryLifeCycle implements LifeCycle {
   async endCommand() {
     const entries = Array.from(this.taskRuns);
     await this.taskHistory.recordTaskRuns(entries.map(([_, v]) => v));
+    await this.pushTaskRuns(entries.map(([_, v]) => v));
     const flakyTasks = await this.taskHistory.getFlakyTasks(
       entries.map(([hash]) => hash)
     );
@@ -63,4 +64,43 @@ export class TaskHistoryLifeCycle implements LifeCycle {
       });
     }
   }
+
+  /**
+   * Pushes the runs of this command as spans to the OTLP endpoint set by `NX_TASK_HISTORY_OTLP_ENDPOINT`
+   */
+  private async pushTaskRuns(taskRuns: TaskRun[]) {
+    const endpoint = process.env.NX_TASK_HISTORY_OTLP_ENDPOINT;
+    if (!endpoint || taskRuns.length === 0) {
+      return;
+    }
+    try {
+      await this.taskHistory.pushTaskRuns(
+        endpoint,
+        { since: Math.min(...taskRuns.map((taskRun) => taskRun.start)) },
+        parseOtlpHeaders(process.env.NX_TASK_HISTORY_OTLP_HEADERS)
+      );
+    } catch (e) {
+      output.warn({
+        title: `Unable to push the task runs to ${endpoint}`,
+        bodyLines: [e.message ?? e.toString()],
+      });
+    }
+  }
+}
+
+/**
+ * Parses headers in the format of `OTEL_EXPORTER_OTLP_HEADERS`, e.g. `api-key=secret,team=ci`
+ */
+function parseOtlpHeaders(
+  headers: string | undefined
+): Record<string, string> {
+  const parsed: Record<string, string> = {};
+  for (const header of headers?.split(',') ?? []) {
+    const separator = header.indexOf('=');
+    if (separator > 0) {
+      parsed[decodeURIComponent(header.slice(0, separator).trim())] =
+        decodeURIComponent(header.slice(separator + 1).trim());
+    }
+  }
+  return parsed;
 }
@@ -1,9 +1,9 @@
-import { LifeCycle, TaskMetadata } from '../life-cycle';
-import { TaskStatus } from '../tasks-runner';
+import { LifeCycle, TaskMetadata, TaskResult } from '../life-cycle';
 
 import { performance } from 'perf_hooks';
 import { join } from 'path';
 import { Task } from '../../config/task-graph';
+import { startProfiling, stopProfiling } from '../../native';
 import { writeJsonFile } from '../../utils/fileutils';
 
 export class TaskProfilingLifeCycle implements LifeCycle {
@@ -19,6 +19,7 @@ export class TaskProfilingLifeCycle implements LifeCycle {
 
   constructor(_profileFile: string) {
     this.profileFile = join(process.cwd(), _profileFile);
+    startProfiling();
   }
 
   startTasks(tasks: Task[], { groupId }: TaskMetadata): void {
@@ -32,10 +33,7 @@ export class TaskProfilingLifeCycle implements LifeCycle {
     }
   }
 
-  endTasks(
-    taskResults: Array<{ task: Task; status: TaskStatus; code: number }>,
-    metadata: TaskMetadata
-  ): void {
+  endTasks(taskResults: TaskResult[], metadata: TaskMetadata): void {
     for (let tr of taskResults) {
       if (tr.task.startTime) {
         this.timings[tr.task.id].perfStart = tr.task.startTime;
@@ -50,15 +48,17 @@ export class TaskProfilingLifeCycle implements LifeCycle {
   }
 
   endCommand(): void {
+    // spans of the native module, e.g. hashing and restoring outputs from the cache
+    this.profile.push(...stopProfiling());
     writeJsonFile(this.profileFile, this.profile);
     console.log(`Performance Profile: ${this.profileFile}`);
   }
 
   private recordTaskCompletions(
-    tasks: Array<{ task: Task; status: TaskStatus }>,
+    tasks: TaskResult[],
     { groupId }: TaskMetadata
   ) {
-    for (const { task, status } of tasks) {
+    for (const { task, status, metrics } of tasks) {
       const { perfStart, perfEnd } = this.timings[task.id];
       this.profile.push({
         name: task.id,
@@ -71,6 +71,7 @@ export class TaskProfilingLifeCycle implements LifeCycle {
         args: {
           target: task.target,
           status,
+          ...(metrics ? { metrics } : {}),
         },
       });
     }
@@ -1,4 +1,11 @@
-import { ChildProcess, RustPseudoTerminal, IS_WASM } from '../native';
+import {
+  AnsiMode,
+  ChildProcess,
+  ExitDetails,
+  RustPseudoTerminal,
+  IS_WASM,
+  ResourceLimits,
+} from '../native';
 import { PseudoIPCServer } from './pseudo-ipc';
 import { getForkedProcessOsSocketPath } from '../daemon/socket-utils';
 import { Serializable } from 'child_process';
@@ -73,11 +80,13 @@ export class PseudoTerminal {
       execArgv,
       jsEnv,
       quiet,
+      resourceLimits,
     }: {
       cwd?: string;
       execArgv?: string[];
       jsEnv?: Record<string, string>;
       quiet?: boolean;
+      resourceLimits?: ResourceLimits;
     }
   ) {
     if (!this.initialized) {
@@ -91,7 +100,8 @@ export class PseudoTerminal {
         cwd,
         jsEnv,
         execArgv,
-        quiet
+        quiet,
+        resourceLimits
       ),
       id,
       this.pseudoIPC
@@ -102,6 +112,13 @@ export class PseudoTerminal {
     return cp;
   }
 
+  /**
+   * Replays the output of a forked task that is still buffered, from `fromOffset` onwards
+   */
+  getTaskOutput(taskId: string, fromOffset?: number) {
+    return this.rustPseudoTerminal.getTaskOutput(taskId, fromOffset);
+  }
+
   sendMessageToChildren(message: Serializable) {
     this.pseudoIPC.sendMessageToChildren(message);
   }
@@ -141,26 +158,26 @@ export class PseudoTtyProcess {
   exitCallbacks = [];
 
   constructor(private childProcess: ChildProcess) {
-    childProcess.onExit((message) => {
+    childProcess.onExit((message, details) => {
       this.isAlive = false;
 
       const exitCode = messageToCode(message);
 
-      this.exitCallbacks.forEach((cb) => cb(exitCode));
+      this.exitCallbacks.forEach((cb) => cb(exitCode, details));
     });
   }
 
-  onExit(callback: (code: number) => void): void {
+  onExit(callback: (code: number, details: ExitDetails) => void): void {
     this.exitCallbacks.push(callback);
   }
 
-  onOutput(callback: (message: string) => void): void {
-    this.childProcess.onOutput(callback);
+  onOutput(callback: (message: string) => void, ansi?: AnsiMode): void {
+    this.childProcess.onOutput(callback, ansi);
   }
 
-  kill(): void {
+  kill(signal?: NodeJS.Signals): void {
     try {
-      this.childProcess.kill();
+      this.childProcess.kill(signal);
     } catch {
       // when the child process completes before we explicitly call kill, this will throw
       // do nothing
@@ -12,6 +12,7 @@ import { Task, TaskGraph } from '../config/task-graph';
 import { TargetDependencyConfig } from '../config/workspace-json-project-json';
 import { daemonClient } from '../daemon/client/client';
 import { createTaskHasher } from '../hasher/create-task-hasher';
+import { InProcessTaskHasher } from '../hasher/task-hasher';
 import { hashTasksThatDoNotDependOnOutputsOfOtherTasks } from '../hasher/hash-task';
 import { IS_WASM } from '../native';
 import { createProjectGraphAsync } from '../project-graph/project-graph';
@@ -32,6 +33,7 @@ import {
 } from '../utils/sync-generators';
 import { workspaceRoot } from '../utils/workspace-root';
 import { createTaskGraph } from './create-task-graph';
+import { estimateRunDuration } from './estimate-run-duration';
 import { CompositeLifeCycle, LifeCycle } from './life-cycle';
 import { createRunManyDynamicOutputRenderer } from './life-cycles/dynamic-run-many-terminal-output-life-cycle';
 import { createRunOneDynamicOutputRenderer } from './life-cycles/dynamic-run-one-terminal-output-life-cycle';
@@ -54,6 +56,7 @@ import chalk = require('chalk');
 async function getTerminalOutputLifeCycle(
   initiatingProject: string,
   projectNames: string[],
+  taskGraph: TaskGraph,
   tasks: Task[],
   nxArgs: NxArgs,
   nxJson: NxJsonConfiguration,
@@ -102,7 +105,8 @@ async function getTerminalOutputLifeCycle(
           projectNames,
           tasks,
           nxArgs,
-          overridesWithoutHidden
+          overridesWithoutHidden,
+          estimateRunDuration(taskGraph, Number(runnerOptions.parallel ?? 3))
         ),
         renderIsDone: Promise.resolve(),
       };
@@ -189,6 +193,7 @@ export async function runCommand(
       const { lifeCycle, renderIsDone } = await getTerminalOutputLifeCycle(
         initiatingProject,
         projectNames,
+        taskGraph,
         tasks,
         nxArgs,
         nxJson,
@@ -648,6 +653,10 @@ export async function invokeTasksRunner({
     // simply await the promise
     anyFailures = await anyFailuresInPromise(promiseOrObservable as any);
   }
+  // the daemon persists the hashes of its own hasher
+  if (hasher instanceof InProcessTaskHasher) {
+    hasher.persistCache();
+  }
   return anyFailures ? 1 : 0;
 }
 
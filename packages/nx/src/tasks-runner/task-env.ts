@@ -3,6 +3,7 @@ import { config as loadDotEnvFile } from 'dotenv';
 import { expand } from 'dotenv-expand';
 import { workspaceRoot } from '../utils/workspace-root';
 import { join } from 'node:path';
+import { getTaskEnvs, IS_WASM } from '../native';
 
 export function getEnvVariablesForBatchProcess(
   skipNxCache: boolean,
@@ -29,6 +30,33 @@ export function getTaskSpecificEnv(task: Task) {
       taskEnv;
 }
 
+/**
+ * Resolves the task specific env of every task at once, reading each dot env file a single time.
+ * See `getTaskEnvs` for the order in which the dot env files take precedence
+ */
+export function getTaskSpecificEnvs(
+  tasks: Task[]
+): Record<string, NodeJS.ProcessEnv> {
+  if (IS_WASM) {
+    return Object.fromEntries(
+      tasks.map((task) => [task.id, getTaskSpecificEnv(task)])
+    );
+  }
+
+  const env: Record<string, string> = {};
+  for (const [key, value] of Object.entries(process.env)) {
+    if (value !== undefined) {
+      env[key] = value;
+    }
+  }
+  return getTaskEnvs(
+    workspaceRoot,
+    tasks,
+    env,
+    process.env.NX_LOAD_DOT_ENV_FILES === 'true'
+  );
+}
+
 export function getEnvVariablesForTask(
   task: Task,
   taskSpecificEnv: NodeJS.ProcessEnv,
@@ -5,7 +5,7 @@ import { writeFileSync } from 'fs';
 import { TaskHasher } from '../hasher/task-hasher';
 import runCommandsImpl from '../executors/run-commands/run-commands.impl';
 import { ForkedProcessTaskRunner } from './forked-process-task-runner';
-import { getCache } from './cache';
+import { DbCache, getCache } from './cache';
 import { DefaultTasksRunnerOptions } from './default-tasks-runner';
 import { TaskStatus } from './tasks-runner';
 import {
@@ -19,6 +19,13 @@ import {
 } from './utils';
 import { Batch, TasksSchedule } from './tasks-schedule';
 import { TaskMetadata } from './life-cycle';
+import {
+  IS_WASM,
+  NxOutputsSnapshots,
+  OutputsDrift,
+  TaskMetrics,
+  verifyOutputs,
+} from '../native';
 import { ProjectGraph } from '../config/project-graph';
 import { Task, TaskGraph } from '../config/task-graph';
 import { DaemonClient } from '../daemon/client/client';
@@ -26,11 +33,14 @@ import { hashTask } from '../hasher/hash-task';
 import {
   getEnvVariablesForBatchProcess,
   getEnvVariablesForTask,
-  getTaskSpecificEnv,
+  getTaskSpecificEnvs,
 } from './task-env';
 import { workspaceRoot } from '../utils/workspace-root';
 import { output } from '../utils/output';
 import { combineOptionsForExecutor } from '../utils/params';
+import { getDbConnection } from '../utils/db-connection';
+
+const MAX_REPORTED_OUTPUT_CHANGES = 10;
 
 export class TaskOrchestrator {
   private cache = getCache(this.options);
@@ -48,6 +58,9 @@ export class TaskOrchestrator {
     this.options.captureStderr
   );
   private reverseTaskDeps = calculateReverseDeps(this.taskGraph);
+  private taskSpecificEnvs = getTaskSpecificEnvs(
+    Object.values(this.taskGraph.tasks)
+  );
 
   private processedTasks = new Map<string, Promise<NodeJS.ProcessEnv>>();
   private processedBatches = new Map<Batch, Promise<void>>();
@@ -61,6 +74,18 @@ export class TaskOrchestrator {
 
   private bailed = false;
 
+  // digesting the outputs after every task is not free, so the changed
+  // outputs are only detected when it is enabled
+  private outputsSnapshots =
+    process.env.NX_KEEP_CHANGED_OUTPUTS === 'true' &&
+    !IS_WASM &&
+    process.env.NX_DISABLE_DB !== 'true'
+      ? new NxOutputsSnapshots(getDbConnection(), workspaceRoot)
+      : null;
+  // the tasks whose changed outputs were kept, which are not snapshotted so
+  // the changes are reported until they are removed
+  private tasksWithDriftedOutputs = new Set<string>();
+
   // endregion internal state
 
   constructor(
@@ -81,6 +106,8 @@ export class TaskOrchestrator {
       this.tasksSchedule.init(),
     ]);
 
+    await this.checkCacheHits();
+
     // initial scheduling
     await this.scheduleNextTasks();
 
@@ -108,6 +135,28 @@ export class TaskOrchestrator {
     return this.completedTasks;
   }
 
+  /**
+   * Looks up the tasks that are hashed in advance in the cache, so the life
+   * cycles can report how many tasks will be replayed before any of them runs
+   */
+  private async checkCacheHits() {
+    if (
+      this.options.skipNxCache ||
+      !(this.cache instanceof DbCache) ||
+      !this.options.lifeCycle.expectCacheHits
+    ) {
+      return;
+    }
+    const tasks = Object.values(this.taskGraph.tasks).filter(
+      (task) => task.hash && isCacheableTask(task, this.options)
+    );
+    if (tasks.length > 0) {
+      await this.options.lifeCycle.expectCacheHits(
+        await this.cache.checkCacheHits(tasks)
+      );
+    }
+  }
+
   private async executeNextBatchOfTasksUsingTaskSchedule() {
     // completed all the tasks
     if (!this.tasksSchedule.hasTasks() || this.bailed) {
@@ -151,7 +200,7 @@ export class TaskOrchestrator {
     taskId: string
   ): Promise<NodeJS.ProcessEnv> {
     const task = this.taskGraph.tasks[taskId];
-    const taskSpecificEnv = getTaskSpecificEnv(task);
+    const taskSpecificEnv = this.taskSpecificEnvs[task.id];
 
     if (!task.hash) {
       await hashTask(
@@ -229,7 +278,12 @@ export class TaskOrchestrator {
     const outputs = task.outputs;
     const shouldCopyOutputsFromCache =
       !!outputs.length &&
-      (await this.shouldCopyOutputsFromCache(outputs, task.hash));
+      (await this.shouldCopyOutputsFromCache(
+        outputs,
+        task.hash,
+        cachedResult.outputsPath
+      )) &&
+      !this.keepDriftedOutputs(task);
     if (shouldCopyOutputsFromCache) {
       await this.cache.copyFilesFromCache(task.hash, cachedResult, outputs);
     }
@@ -385,6 +439,7 @@ export class TaskOrchestrator {
       task: Task;
       status: TaskStatus;
       terminalOutput?: string;
+      metrics?: TaskMetrics;
     }[] = doNotSkipCache ? await this.applyCachedResults([task]) : [];
 
     // the task wasn't cached
@@ -472,17 +527,19 @@ export class TaskOrchestrator {
         });
       } else {
         // cache prep
-        const { code, terminalOutput } = await this.runTaskInForkedProcess(
-          task,
-          env,
-          pipeOutput,
-          temporaryOutputPath,
-          streamOutput
-        );
+        const { code, terminalOutput, metrics } =
+          await this.runTaskInForkedProcess(
+            task,
+            env,
+            pipeOutput,
+            temporaryOutputPath,
+            streamOutput
+          );
         results.push({
           task,
           status: code === 0 ? 'success' : 'failure',
           terminalOutput,
+          metrics,
         });
       }
     }
@@ -495,14 +552,18 @@ export class TaskOrchestrator {
     pipeOutput: boolean,
     temporaryOutputPath: string,
     streamOutput: boolean
-  ) {
+  ): Promise<{
+    code: number;
+    terminalOutput?: string;
+    metrics?: TaskMetrics;
+  }> {
     try {
       const usePtyFork = process.env.NX_NATIVE_COMMAND_RUNNER !== 'false';
 
       // Disable the pseudo terminal if this is a run-many
       const disablePseudoTerminal = !this.initiatingProject;
       // execution
-      const { code, terminalOutput } = usePtyFork
+      const result = usePtyFork
         ? await this.forkedProcessTaskRunner.forkProcess(task, {
             temporaryOutputPath,
             streamOutput,
@@ -519,10 +580,7 @@ export class TaskOrchestrator {
             env,
           });
 
-      return {
-        code,
-        terminalOutput,
-      };
+      return result;
     } catch (e) {
       return {
         code: 1,
@@ -543,12 +601,14 @@ export class TaskOrchestrator {
       task: Task;
       status: TaskStatus;
       terminalOutput?: string;
+      metrics?: TaskMetrics;
     }[],
     doNotSkipCache: boolean,
     { groupId }: { groupId: number }
   ) {
     for (const task of tasks) {
       await this.recordOutputsHash(task);
+      this.recordOutputsSnapshot(task);
     }
 
     if (doNotSkipCache) {
@@ -701,19 +761,104 @@ export class TaskOrchestrator {
     this.groups[id] = false;
   }
 
-  private async shouldCopyOutputsFromCache(outputs: string[], hash: string) {
+  private async shouldCopyOutputsFromCache(
+    outputs: string[],
+    hash: string,
+    cachedOutputsPath: string
+  ) {
     if (this.daemon?.enabled()) {
-      return !(await this.daemon.outputsHashesMatch(outputs, hash));
+      return (
+        !(await this.daemon.outputsHashesMatch(outputs, hash)) ||
+        ((await this.daemon.outputsChangedWhileRecording(outputs)) &&
+          this.outputsWereClobbered(outputs, cachedOutputsPath))
+      );
     } else {
       return true;
     }
   }
 
+  /**
+   * The daemon ignores the changes of the outputs right after their hash was
+   * recorded, which are usually the writes of the task itself. When there were
+   * such changes, the outputs are compared with the cache in case they were
+   * made by something else, e.g. by a tool that rewrote them in place
+   */
+  private outputsWereClobbered(outputs: string[], cachedOutputsPath: string) {
+    if (IS_WASM) {
+      return false;
+    }
+    try {
+      const { missing, mismatched } = verifyOutputs(
+        workspaceRoot,
+        cachedOutputsPath,
+        outputs
+      );
+      return missing.length > 0 || mismatched.length > 0;
+    } catch {
+      return true;
+    }
+  }
+
   private async recordOutputsHash(task: Task) {
     if (this.daemon?.enabled()) {
       return this.daemon.recordOutputsHash(task.outputs, task.hash);
     }
   }
 
+  private recordOutputsSnapshot(task: Task) {
+    if (
+      !this.outputsSnapshots ||
+      !task.hash ||
+      !task.outputs.length ||
+      this.tasksWithDriftedOutputs.has(task.id)
+    ) {
+      return;
+    }
+    try {
+      this.outputsSnapshots.recordOutputs(task.hash, task.outputs);
+    } catch {
+      // the snapshots are only used to warn about changed outputs
+    }
+  }
+
+  /**
+   * Restoring the outputs of a task from the cache would overwrite the changes
+   * that were made to them since a task last wrote them (e.g. manual edits of
+   * dist), so the changes are kept and reported instead when
+   * NX_KEEP_CHANGED_OUTPUTS is enabled
+   */
+  private keepDriftedOutputs(task: Task) {
+    if (!this.outputsSnapshots) {
+      return false;
+    }
+    let drift: OutputsDrift | null;
+    try {
+      drift = this.outputsSnapshots.detectDrift(task.outputs);
+    } catch {
+      return false;
+    }
+    if (!drift) {
+      return false;
+    }
+    this.tasksWithDriftedOutputs.add(task.id);
+    const changes = [
+      ...drift.modified.map((file) => `- modified: ${file}`),
+      ...drift.added.map((file) => `- added: ${file}`),
+      ...drift.removed.map((file) => `- removed: ${file}`),
+    ];
+    output.warn({
+      title: `The outputs of ${task.id} were changed since a task last wrote them, so they were not restored from the cache`,
+      bodyLines: [
+        ...changes.slice(0, MAX_REPORTED_OUTPUT_CHANGES),
+        ...(changes.length > MAX_REPORTED_OUTPUT_CHANGES
+          ? [`...and ${changes.length - MAX_REPORTED_OUTPUT_CHANGES} more`]
+          : []),
+        '',
+        'Remove the changes, or run the task with --skip-nx-cache to overwrite them.',
+      ],
+    });
+    return true;
+  }
+
   // endregion utils
 }
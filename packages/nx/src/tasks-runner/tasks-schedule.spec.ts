@@ -479,6 +479,36 @@ describe('TasksSchedule', () => {
           expect(taskSchedule.nextTask()).toEqual(app4Test); // app4 should run because it has the longest runtime
           expect(taskSchedule.nextTask()).toEqual(app1Test); // app1 should run last because it has the shortest runtime
         });
+
+        describe('with critical path scheduling', () => {
+          let originalScheduling;
+          beforeEach(() => {
+            originalScheduling = process.env['NX_CRITICAL_PATH_SCHEDULING'];
+            process.env['NX_CRITICAL_PATH_SCHEDULING'] = 'true';
+          });
+
+          afterEach(() => {
+            process.env['NX_CRITICAL_PATH_SCHEDULING'] = originalScheduling;
+          });
+
+          it('should schedule the longest tasks first', async () => {
+            taskHistory.getEstimatedTaskTimings.mockReturnValue({
+              'app1:test': 200,
+              'app2:test': 300,
+              'app3:test': 400,
+              'app4:test': 500,
+              'lib1:test': 100,
+            });
+            await taskSchedule.init();
+
+            await taskSchedule.scheduleNextTasks();
+            expect(taskSchedule.nextTask()).toEqual(app4Test);
+            expect(taskSchedule.nextTask()).toEqual(app3Test);
+            expect(taskSchedule.nextTask()).toEqual(app2Test);
+            expect(taskSchedule.nextTask()).toEqual(app1Test);
+            expect(taskSchedule.nextTask()).toEqual(lib1Test); // lib1 runs last, no task of the graph waits for it
+          });
+        });
       });
     });
 
@@ -10,6 +10,7 @@ import { ProjectGraph } from '../config/project-graph';
 import { findAllProjectNodeDependencies } from '../utils/project-graph-utils';
 import { reverse } from '../project-graph/operators';
 import { TaskHistory, getTaskHistory } from '../utils/task-history';
+import { getTaskGraphPriorities, IS_WASM } from '../native';
 
 export interface Batch {
   executorName: string;
@@ -29,6 +30,7 @@ export class TasksSchedule {
   private completedTasks = new Set<string>();
   private scheduleRequestsExecutionChain = Promise.resolve();
   private estimatedTaskTimings: Record<string, number> = {};
+  private taskPriorities: Record<string, number> | null = null;
 
   constructor(
     private readonly projectGraph: ProjectGraph,
@@ -43,6 +45,13 @@ export class TasksSchedule {
           Object.values(this.taskGraph.tasks).map((t) => t.target)
         );
     }
+    if (process.env.NX_CRITICAL_PATH_SCHEDULING === 'true' && !IS_WASM) {
+      // the chains are weighted by the durations of the tasks in the task history
+      this.taskPriorities = getTaskGraphPriorities(
+        this.taskGraph,
+        this.estimatedTaskTimings
+      );
+    }
   }
 
   public async scheduleNextTasks() {
@@ -112,7 +121,17 @@ export class TasksSchedule {
       .concat(taskId)
       // NOTE: sort task by most dependent on first
       .sort((taskId1, taskId2) => {
-        // First compare the length of task dependencies.
+        // When enabled, the tasks with the longest chain of tasks waiting for them come first.
+        if (this.taskPriorities) {
+          const priorityDifference =
+            this.taskPriorities[taskId2] - this.taskPriorities[taskId1];
+
+          if (priorityDifference !== 0) {
+            return priorityDifference;
+          }
+        }
+
+        // Then compare the length of task dependencies.
         const taskDifference =
           this.reverseTaskDeps[taskId2].length -
           this.reverseTaskDeps[taskId1].length;
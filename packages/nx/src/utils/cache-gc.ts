@@ -0,0 +1,14 @@
+import { NxJsonConfiguration, readNxJson } from '../config/nx-json';
+import type { CacheGcOptions } from '../native';
+
+/**
+ * The limits of the local cache, from the environment or from `nx.json`
+ */
+export function getCacheGcOptions(
+  nxJson: NxJsonConfiguration = readNxJson()
+): CacheGcOptions {
+  return {
+    maxAge: process.env.NX_CACHE_MAX_AGE ?? nxJson.maxCacheAge,
+    maxSize: process.env.NX_CACHE_MAX_SIZE ?? nxJson.maxCacheSize,
+  };
+}
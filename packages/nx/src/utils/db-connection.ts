@@ -1,10 +1,19 @@
-import { connectToNxDb, ExternalObject } from '../native';
+import { connectToNxDb, ExternalObject, shutdownGracefully } from '../native';
 import { workspaceDataDirectory } from './cache-directory';
+import { workspaceRoot } from './workspace-root';
 import { version as NX_VERSION } from '../../package.json';
 
 let dbConnection: ExternalObject<any>;
 
 export function getDbConnection(directory = workspaceDataDirectory) {
-  dbConnection ??= connectToNxDb(directory, NX_VERSION);
+  dbConnection ??= connectToNxDb(directory, NX_VERSION, workspaceRoot);
   return dbConnection;
 }
+
+/**
+ * Waits for the native work in flight (hashing and writes to the database) before the process exits,
+ * and checkpoints the database when this process is connected to it
+ */
+export function shutdownNativeWork(timeoutMs?: number) {
+  return shutdownGracefully(dbConnection, timeoutMs);
+}
@@ -1,6 +1,7 @@
 import { minimatch } from 'minimatch';
 import type { ProjectGraphProjectNode } from '../config/project-graph';
 import { isGlobPattern } from './globs';
+import { FilteredProject, filterProjects, IS_WASM } from '../native';
 
 const validPatternTypes = [
   'name', // Pattern is based on the project's name
@@ -132,6 +133,32 @@ export function findMatchingProjects(
   return Array.from(matchedProjects);
 }
 
+/**
+ * Find matching project names given a list of project filter expressions,
+ * e.g. `tag:api && !tag:experimental`. The expressions are evaluated natively,
+ * so large workspaces are filtered without building predicates in JS.
+ *
+ * @param patterns A list of project filter expressions to match against.
+ * @param projects A map of {@link ProjectGraphProjectNode} by project name.
+ * @returns
+ */
+export function findProjectsMatchingExpressions(
+  patterns: string[] = [],
+  projects: Record<string, ProjectGraphProjectNode>
+): string[] {
+  if (IS_WASM) {
+    return findMatchingProjects(patterns, projects);
+  }
+  if (!patterns.length || patterns.filter((p) => p.length).length === 0) {
+    return []; // Short circuit if called with no patterns
+  }
+  const filteredProjects: Record<string, FilteredProject> = {};
+  for (const [name, node] of Object.entries(projects)) {
+    filteredProjects[name] = { root: node.data.root, tags: node.data.tags };
+  }
+  return filterProjects(patterns, filteredProjects);
+}
+
 function addMatchingProjectsByDirectory(
   projectNames: string[],
   projects: Record<string, ProjectGraphProjectNode>,
@@ -1,6 +1,14 @@
 import { daemonClient } from '../daemon/client/client';
 import { isOnDaemon } from '../daemon/is-on-daemon';
-import { NxTaskHistory, TaskRun, TaskTarget } from '../native';
+import {
+  FlakinessWindow,
+  NxTaskHistory,
+  TaskDurationStats,
+  TaskRun,
+  TaskRunsExportFormat,
+  TaskRunsExportOptions,
+  TaskTarget,
+} from '../native';
 import { getDbConnection } from './db-connection';
 
 export class TaskHistory {
@@ -20,6 +28,17 @@ export class TaskHistory {
     return await daemonClient.getEstimatedTaskTimings(targets);
   }
 
+  /**
+   * This function returns the durations of the last runs per target that were not restored from the cache
+   * @param targets
+   * @returns a map where key is task id (project:target:configuration), value is the statistics of the durations
+   */
+  getTaskDurationStats(
+    targets: TaskTarget[]
+  ): Record<string, TaskDurationStats> {
+    return this.taskHistory.getTaskDurationStats(targets);
+  }
+
   async getFlakyTasks(hashes: string[]) {
     if (isOnDaemon() || !daemonClient.enabled()) {
       return this.taskHistory.getFlakyTasks(hashes);
@@ -27,6 +46,40 @@ export class TaskHistory {
     return await daemonClient.getFlakyTasks(hashes);
   }
 
+  /**
+   * This function returns the targets that passed after failing with the same inputs within a time window
+   * @param window
+   * @returns the flaky targets, sorted from the flakiest
+   */
+  async getFlakyTargets(window: FlakinessWindow) {
+    if (isOnDaemon() || !daemonClient.enabled()) {
+      return this.taskHistory.getFlakyTargets(window);
+    }
+    return await daemonClient.getFlakyTargets(window);
+  }
+
+  /**
+   * This function exports the recorded task runs as JSONL or as OTLP spans
+   */
+  exportTaskRuns(
+    format: TaskRunsExportFormat,
+    options?: TaskRunsExportOptions
+  ): string {
+    return this.taskHistory.exportTaskRuns(format, options);
+  }
+
+  /**
+   * This function pushes the recorded task runs as spans to an OTLP/HTTP endpoint
+   * @returns the number of spans that were pushed
+   */
+  pushTaskRuns(
+    endpoint: string,
+    options?: TaskRunsExportOptions,
+    headers?: Record<string, string>
+  ): Promise<number> {
+    return this.taskHistory.pushTaskRuns(endpoint, options, headers);
+  }
+
   async recordTaskRuns(taskRuns: TaskRun[]) {
     if (isOnDaemon() || !daemonClient.enabled()) {
       return this.taskHistory.recordTaskRuns(taskRuns);
@@ -1,4 +1,8 @@
-import type { NxWorkspaceFilesExternals, WorkspaceContext } from '../native';
+import type {
+  FileData,
+  NxWorkspaceFilesExternals,
+  WorkspaceContext,
+} from '../native';
 import { performance } from 'perf_hooks';
 import { workspaceDataDirectoryForWorkspace } from './cache-directory';
 import { isOnDaemon } from '../daemon/is-on-daemon';
@@ -33,6 +37,18 @@ export async function getNxWorkspaceFilesFromContext(
   return daemonClient.getWorkspaceFiles(projectRootMap);
 }
 
+/**
+ * Publishes the workspace files of the daemon to `directory`, where its clients read them without receiving them over the socket
+ */
+export function publishWorkspaceFilesInContext(
+  workspaceRoot: string,
+  directory: string,
+  projectRootMap: Record<string, string>
+) {
+  ensureContextAvailable(workspaceRoot);
+  return workspaceContext.publishWorkspaceFiles(directory, projectRootMap);
+}
+
 /**
  * Sync method to get files matching globs from workspace context.
  * NOTE: This method will create the workspace context if it doesn't exist.
@@ -62,6 +78,25 @@ export async function globWithWorkspaceContext(
   }
 }
 
+/**
+ * Async iterator over files matching globs from the workspace context.
+ * Matches are pulled from the native layer in batches, which avoids
+ * transferring every match at once in large workspaces.
+ */
+export async function* globStreamWithWorkspaceContext(
+  workspaceRoot: string,
+  globs: string[],
+  exclude?: string[],
+  batchSize?: number
+): AsyncGenerator<FileData[]> {
+  ensureContextAvailable(workspaceRoot);
+  const stream = workspaceContext.globStream(globs, exclude, batchSize);
+  let batch: FileData[] | null;
+  while ((batch = stream.nextBatch())) {
+    yield batch;
+  }
+}
+
 export async function hashWithWorkspaceContext(
   workspaceRoot: string,
   globs: string[],
@@ -106,10 +141,15 @@ export async function updateContextWithChangedFiles(
 export function updateFilesInContext(
   workspaceRoot: string,
   updatedFiles: string[],
-  deletedFiles: string[]
+  deletedFiles: string[],
+  movedFiles?: Record<string, string>
 ) {
   ensureContextAvailable(workspaceRoot);
-  return workspaceContext?.incrementalUpdate(updatedFiles, deletedFiles);
+  return workspaceContext?.incrementalUpdate(
+    updatedFiles,
+    deletedFiles,
+    movedFiles
+  );
 }
 
 export async function getAllFileDataInContext(workspaceRoot: string) {